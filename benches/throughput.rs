@@ -0,0 +1,70 @@
+//!
+//! Benchmarks of a single block, bulk ctr, cbc and gcm and the key schedule on
+//! every backend the cpu supports, measured with aes::benchmark. Run them with
+//! cargo bench, optionally followed by -- and a part of the names to run, for
+//! example cargo bench -- gcm.
+//!
+
+use std::time::Duration;
+
+use aes::benchmark::{measure_throughput, Throughput};
+use aes::gcm::Gcm;
+use aes::{cbc, ctr, Aes128, Aes256, KeyScheduleMode, RoundBackend};
+
+///
+/// The time each benchmark is repeated for.
+///
+const MINIMUM_TIME: Duration = Duration::from_millis(500);
+
+///
+/// The length of the data of the bulk benchmarks, 64 KiB.
+///
+const BULK_BYTES: usize = 64 * 1024;
+
+fn main() {
+    let filter = std::env::args().skip(1).find(|argument| !argument.starts_with('-'));
+    let run = |name: &str, measure: &mut dyn FnMut() -> Throughput| {
+        if filter.as_ref().is_none_or(|filter| name.contains(filter.as_str())) {
+            println!("{:<40} {}", name, measure());
+        }
+    };
+    let data = vec![0xa5u8; BULK_BYTES];
+    for backend in backends() {
+        let cipher = Aes128::new(&[7; 16]).with_backend(backend);
+        let mut block = [0; 16];
+        run(&format!("block/encrypt/{:?}", backend), &mut || measure_throughput(16, MINIMUM_TIME, || cipher.encrypt_block_in_place(&mut block)));
+        run(&format!("block/decrypt/{:?}", backend), &mut || measure_throughput(16, MINIMUM_TIME, || cipher.decrypt_block_in_place(&mut block)));
+        run(&format!("ctr/{:?}", backend), &mut || measure_throughput(BULK_BYTES, MINIMUM_TIME, || ctr::encrypt(&cipher, &[0; 16], &data)));
+        run(&format!("cbc/encrypt/{:?}", backend), &mut || measure_throughput(BULK_BYTES, MINIMUM_TIME, || cbc::encrypt(&cipher, &[0; 16], &data)));
+        let ciphertext = cbc::encrypt(&cipher, &[0; 16], &data);
+        run(&format!("cbc/decrypt/{:?}", backend), &mut || measure_throughput(BULK_BYTES, MINIMUM_TIME, || cbc::decrypt(&cipher, &[0; 16], &ciphertext)));
+    }
+    let gcm = Gcm::new(&[7; 16], 16).unwrap();
+    run("gcm/encrypt", &mut || measure_throughput(BULK_BYTES, MINIMUM_TIME, || gcm.encrypt(&[0; 12], &[], &data)));
+    let sealed = gcm.encrypt(&[0; 12], &[], &data).unwrap();
+    run("gcm/decrypt", &mut || measure_throughput(BULK_BYTES, MINIMUM_TIME, || gcm.decrypt(&[0; 12], &[], &sealed)));
+    for mode in [KeyScheduleMode::Precomputed, KeyScheduleMode::OnTheFly] {
+        run(&format!("key_schedule/128/{:?}", mode), &mut || measure_throughput(16, MINIMUM_TIME, || Aes128::with_mode(&[7; 16], mode)));
+        run(&format!("key_schedule/256/{:?}", mode), &mut || measure_throughput(32, MINIMUM_TIME, || Aes256::with_mode(&[7; 32], mode)));
+    }
+}
+
+///
+/// The backends supported by the cpu.
+///
+fn backends() -> Vec<RoundBackend> {
+    let backends = [
+        RoundBackend::Bytewise,
+        RoundBackend::TTable,
+        RoundBackend::Bitsliced,
+        #[cfg(feature = "portable-simd")]
+        RoundBackend::PortableSimd,
+        #[cfg(target_arch = "aarch64")]
+        RoundBackend::Armv8,
+        #[cfg(target_arch = "x86_64")]
+        RoundBackend::Vaes,
+        #[cfg(all(target_arch = "riscv64", target_feature = "zkne", target_feature = "zknd"))]
+        RoundBackend::Zkn
+    ];
+    backends.into_iter().filter(RoundBackend::is_supported).collect()
+}
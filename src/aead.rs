@@ -0,0 +1,490 @@
+use std::fmt;
+use std::vec::Vec;
+
+use crate::secret::Redacted;
+use crate::zeroize::Zeroize;
+use crate::{hex, AesError, DecryptedState, EncryptedState};
+
+///
+/// Aead is an authenticated encryption mode with associated data. It is 
+/// implemented by all the AEAD modes of the crate, so they can be used through 
+/// the same interface.
+/// 
+/// The associated data is given as a single slice to encrypt and decrypt, or in
+/// pieces with update_aad on the AadBuilder returned by with_nonce. The tag can 
+/// be kept with the ciphertext or handled separately with the detached variants.
+/// The modes can be used as dyn Aead, except for with_nonce.
+/// 
+pub trait Aead {
+
+    ///
+    /// Encrypts and authenticates the plaintext.
+    /// 
+    /// nonce: The nonce, with a length supported by the mode.
+    /// aad: The associated data, authenticated but not encrypted.
+    /// plaintext: The data to encrypt.
+    /// 
+    /// result: The ciphertext with the tag or the error from the mode.
+    /// 
+    fn encrypt(&self, nonce: &[u8], aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, AesError>;
+
+    ///
+    /// Decrypts the ciphertext and verifies the tag.
+    /// 
+    /// nonce: The nonce used when encrypting.
+    /// aad: The associated data used when encrypting.
+    /// ciphertext: The ciphertext with the tag.
+    /// 
+    /// result: The plaintext, AesError::AuthenticationFailed if the tag does not 
+    ///         match or another error from the mode.
+    /// 
+    fn decrypt(&self, nonce: &[u8], aad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, AesError>;
+
+    ///
+    /// The length of the tag in bytes.
+    /// 
+    /// result: The tag length used by the mode.
+    /// 
+    fn tag_length(&self) -> usize;
+
+    ///
+    /// Encrypts and authenticates the plaintext into an output buffer. The output
+    /// is cleared first, so a buffer can be reused for many messages. The modes 
+    /// that can encrypt without allocating, such as Gcm, use the capacity of the 
+    /// output, the default falls back to encrypt.
+    /// 
+    /// nonce: The nonce, with a length supported by the mode.
+    /// aad: The associated data, authenticated but not encrypted.
+    /// plaintext: The data to encrypt.
+    /// output: The buffer replaced by the ciphertext with the tag. Left empty on 
+    ///         an error.
+    /// 
+    /// result: Ok or the error from the mode.
+    /// 
+    fn encrypt_into(&self, nonce: &[u8], aad: &[u8], plaintext: &[u8], output: &mut Vec<u8>) -> Result<(), AesError> {
+        output.clear();
+        output.extend_from_slice(&self.encrypt(nonce, aad, plaintext)?);
+        Ok(())
+    }
+
+    ///
+    /// Decrypts the ciphertext into an output buffer and verifies the tag. The 
+    /// output is cleared first, so a buffer can be reused for many messages.
+    /// 
+    /// nonce: The nonce used when encrypting.
+    /// aad: The associated data used when encrypting.
+    /// ciphertext: The ciphertext with the tag.
+    /// output: The buffer replaced by the plaintext. Left empty on an error, so 
+    ///         unauthenticated plaintext is never given out.
+    /// 
+    /// result: Ok, AesError::AuthenticationFailed if the tag does not match or 
+    ///         another error from the mode.
+    /// 
+    fn decrypt_into(&self, nonce: &[u8], aad: &[u8], ciphertext: &[u8], output: &mut Vec<u8>) -> Result<(), AesError> {
+        output.clear();
+        output.extend_from_slice(&self.decrypt(nonce, aad, ciphertext)?);
+        Ok(())
+    }
+
+    ///
+    /// Encrypts and authenticates the plaintext, returning the tag separately.
+    /// 
+    /// nonce: The nonce, with a length supported by the mode.
+    /// aad: The associated data, authenticated but not encrypted.
+    /// plaintext: The data to encrypt.
+    /// 
+    /// result: The ciphertext and the tag or the error from the mode.
+    /// 
+    fn encrypt_detached(&self, nonce: &[u8], aad: &[u8], plaintext: &[u8]) -> Result<(Vec<u8>, Vec<u8>), AesError> {
+        let mut ciphertext = self.encrypt(nonce, aad, plaintext)?;
+        let tag = ciphertext.split_off(ciphertext.len() - self.tag_length());
+        Ok((ciphertext, tag))
+    }
+
+    ///
+    /// Decrypts the ciphertext and verifies the tag given separately.
+    /// 
+    /// nonce: The nonce used when encrypting.
+    /// aad: The associated data used when encrypting.
+    /// ciphertext: The ciphertext without the tag.
+    /// tag: The tag of the ciphertext.
+    /// 
+    /// result: The plaintext, AesError::InvalidTagLength if the tag has the wrong 
+    ///         length, AesError::AuthenticationFailed if the tag does not match or 
+    ///         another error from the mode.
+    /// 
+    fn decrypt_detached(&self, nonce: &[u8], aad: &[u8], ciphertext: &[u8], tag: &[u8]) -> Result<Vec<u8>, AesError> {
+        if tag.len() != self.tag_length() {
+            return Err(AesError::InvalidTagLength);
+        }
+        let mut combined = Vec::with_capacity(ciphertext.len() + tag.len());
+        combined.extend_from_slice(ciphertext);
+        combined.extend_from_slice(tag);
+        self.decrypt(nonce, aad, &combined)
+    }
+
+    ///
+    /// Starts a message where the associated data is given in pieces.
+    /// 
+    /// nonce: The nonce of the message.
+    /// 
+    /// result: An AadBuilder without any associated data.
+    /// 
+    fn with_nonce(&self, nonce: &[u8]) -> AadBuilder<'_, Self> where Self: Sized {
+        AadBuilder { cipher: self, nonce: nonce.to_vec(), aad: Vec::new() }
+    }
+
+}
+
+///
+/// AadBuilder collects the associated data of a message in pieces. The pieces
+/// are authenticated as if they were given as one slice. They are buffered and 
+/// passed to the mode when the message is encrypted or decrypted, so the 
+/// associated data is held in memory until then.
+/// 
+pub struct AadBuilder<'a, A: Aead> {
+    cipher: &'a A,
+    nonce: Vec<u8>,
+    aad: Vec<u8>
+}
+
+impl<A: Aead> AadBuilder<'_, A> {
+
+    ///
+    /// Adds the next piece of the associated data to the buffer.
+    /// 
+    /// aad: The piece of the associated data.
+    /// 
+    /// result: The builder, so calls can be chained.
+    /// 
+    pub fn update_aad(&mut self, aad: &[u8]) -> &mut Self {
+        self.aad.extend_from_slice(aad);
+        self
+    }
+
+    ///
+    /// Encrypts and authenticates the plaintext with the associated data collected.
+    /// 
+    /// plaintext: The data to encrypt.
+    /// 
+    /// result: The ciphertext with the tag or the error from the mode.
+    /// 
+    pub fn encrypt(self, plaintext: &[u8]) -> Result<Vec<u8>, AesError> {
+        self.cipher.encrypt(&self.nonce, &self.aad, plaintext)
+    }
+
+    ///
+    /// Decrypts the ciphertext and verifies the tag with the associated data collected.
+    /// 
+    /// ciphertext: The ciphertext with the tag.
+    /// 
+    /// result: The plaintext or the error from the mode.
+    /// 
+    pub fn decrypt(self, ciphertext: &[u8]) -> Result<Vec<u8>, AesError> {
+        self.cipher.decrypt(&self.nonce, &self.aad, ciphertext)
+    }
+
+    ///
+    /// Encrypts the plaintext with the associated data collected, returning the tag separately.
+    /// 
+    /// plaintext: The data to encrypt.
+    /// 
+    /// result: The ciphertext and the tag or the error from the mode.
+    /// 
+    pub fn encrypt_detached(self, plaintext: &[u8]) -> Result<(Vec<u8>, Vec<u8>), AesError> {
+        self.cipher.encrypt_detached(&self.nonce, &self.aad, plaintext)
+    }
+
+    ///
+    /// Decrypts the ciphertext and verifies the separate tag with the associated data collected.
+    /// 
+    /// ciphertext: The ciphertext without the tag.
+    /// tag: The tag of the ciphertext.
+    /// 
+    /// result: The plaintext or the error from the mode.
+    /// 
+    pub fn decrypt_detached(self, ciphertext: &[u8], tag: &[u8]) -> Result<Vec<u8>, AesError> {
+        self.cipher.decrypt_detached(&self.nonce, &self.aad, ciphertext, tag)
+    }
+
+}
+
+///
+/// AeadMessage is a message of an AEAD with its nonce and associated data. The
+/// message is generic over the state like AESBlock. An OpenMessage holds the 
+/// plaintext and can only be sealed, a SealedMessage holds the ciphertext with 
+/// the tag and can only be opened, which gives the plaintext only if the tag
+/// matches. Unauthenticated plaintext can therefore never be handled by mistake.
+/// 
+pub struct AeadMessage<State = DecryptedState> {
+    nonce: Vec<u8>,
+    aad: Vec<u8>,
+    data: Vec<u8>,
+    state: std::marker::PhantomData<State>
+}
+
+///
+/// OpenMessage is a message holding the plaintext.
+/// 
+pub type OpenMessage = AeadMessage<DecryptedState>;
+
+///
+/// SealedMessage is a message holding the ciphertext with the tag.
+/// 
+pub type SealedMessage = AeadMessage<EncryptedState>;
+
+impl AeadMessage<DecryptedState> {
+
+    ///
+    /// Creates a new OpenMessage.
+    /// 
+    /// nonce: The nonce to seal the message with, with a length supported by the mode.
+    /// aad: The associated data, authenticated but not encrypted.
+    /// plaintext: The data to encrypt.
+    /// 
+    /// result: An OpenMessage with the plaintext.
+    /// 
+    pub fn new(nonce: &[u8], aad: &[u8], plaintext: Vec<u8>) -> OpenMessage {
+        AeadMessage { nonce: nonce.to_vec(), aad: aad.to_vec(), data: plaintext, state: std::marker::PhantomData::<DecryptedState> }
+    }
+
+    ///
+    /// Encrypts and authenticates the plaintext of the message.
+    /// 
+    /// cipher: The AEAD used to seal the message.
+    /// 
+    /// result: The SealedMessage with the same nonce and associated data or the
+    ///         error from the mode.
+    /// 
+    pub fn seal<A: Aead + ?Sized>(mut self, cipher: &A) -> Result<SealedMessage, AesError> {
+        let ciphertext = cipher.encrypt(&self.nonce, &self.aad, &self.data)?;
+        Ok(AeadMessage { nonce: std::mem::take(&mut self.nonce), aad: std::mem::take(&mut self.aad), data: ciphertext, state: std::marker::PhantomData::<EncryptedState> })
+    }
+
+}
+
+impl AeadMessage<EncryptedState> {
+
+    ///
+    /// Creates a new SealedMessage, for example from a received message.
+    /// 
+    /// nonce: The nonce used when sealing.
+    /// aad: The associated data used when sealing.
+    /// ciphertext: The ciphertext with the tag.
+    /// 
+    /// result: A SealedMessage with the ciphertext.
+    /// 
+    pub fn new(nonce: &[u8], aad: &[u8], ciphertext: Vec<u8>) -> SealedMessage {
+        AeadMessage { nonce: nonce.to_vec(), aad: aad.to_vec(), data: ciphertext, state: std::marker::PhantomData::<EncryptedState> }
+    }
+
+    ///
+    /// Decrypts the ciphertext of the message and verifies the tag.
+    /// 
+    /// cipher: The AEAD used to seal the message.
+    /// 
+    /// result: The OpenMessage with the same nonce and associated data, 
+    ///         AesError::AuthenticationFailed if the tag does not match or another
+    ///         error from the mode.
+    /// 
+    pub fn open<A: Aead + ?Sized>(mut self, cipher: &A) -> Result<OpenMessage, AesError> {
+        let plaintext = cipher.decrypt(&self.nonce, &self.aad, &self.data)?;
+        Ok(AeadMessage { nonce: std::mem::take(&mut self.nonce), aad: std::mem::take(&mut self.aad), data: plaintext, state: std::marker::PhantomData::<DecryptedState> })
+    }
+
+}
+
+impl<State> AeadMessage<State> {
+
+    ///
+    /// Gets the nonce of the message.
+    /// 
+    /// result: The nonce.
+    /// 
+    pub fn nonce(&self) -> &[u8] {
+        &self.nonce
+    }
+
+    ///
+    /// Gets the associated data of the message.
+    /// 
+    /// result: The associated data.
+    /// 
+    pub fn aad(&self) -> &[u8] {
+        &self.aad
+    }
+
+    ///
+    /// Gets the data of the message, the plaintext of an OpenMessage or the 
+    /// ciphertext with the tag of a SealedMessage.
+    /// 
+    /// result: The data.
+    /// 
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    ///
+    /// Consumes the message and returns the data.
+    /// 
+    /// result: The plaintext of an OpenMessage or the ciphertext with the tag of a
+    ///         SealedMessage.
+    /// 
+    pub fn into_inner(mut self) -> Vec<u8> {
+        std::mem::take(&mut self.data)
+    }
+
+}
+
+impl fmt::Debug for AeadMessage<DecryptedState> {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AeadMessage<DecryptedState>")
+            .field("nonce", &hex::encode(&self.nonce))
+            .field("aad", &hex::encode(&self.aad))
+            .field("data", &Redacted(self.data.len()))
+            .finish()
+    }
+
+}
+
+impl fmt::Debug for AeadMessage<EncryptedState> {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AeadMessage<EncryptedState>")
+            .field("nonce", &hex::encode(&self.nonce))
+            .field("aad", &hex::encode(&self.aad))
+            .field("data", &hex::encode(&self.data))
+            .finish()
+    }
+
+}
+
+impl<State> Drop for AeadMessage<State> {
+
+    fn drop(&mut self) {
+        self.data.zeroize();
+    }
+
+}
+
+#[cfg(all(test, feature = "encrypt", feature = "decrypt"))]
+mod tests {
+
+    use super::*;
+    use crate::ccm::Ccm;
+    use crate::eax::Eax;
+    use crate::gcm::Gcm;
+    use crate::gcm_siv::GcmSiv;
+    use crate::ocb::Ocb;
+    use crate::siv::Siv;
+
+    fn roundtrip<A: Aead>(cipher: &A, nonce: &[u8]) {
+        let expected = cipher.encrypt(nonce, b"header in pieces", b"payload").unwrap();
+        let mut builder = cipher.with_nonce(nonce);
+        builder.update_aad(b"header").update_aad(b" in ").update_aad(b"pieces");
+        assert_eq!(expected, builder.encrypt(b"payload").unwrap());
+        let mut builder = cipher.with_nonce(nonce);
+        builder.update_aad(b"header in").update_aad(b" pieces");
+        assert_eq!(b"payload".to_vec(), builder.decrypt(&expected).unwrap());
+        let mut builder = cipher.with_nonce(nonce);
+        builder.update_aad(b"header");
+        assert_eq!(Some(AesError::AuthenticationFailed), builder.decrypt(&expected).err());
+    }
+
+    fn detached<A: Aead>(cipher: &A, nonce: &[u8], tag_first: bool) {
+        let combined = cipher.encrypt(nonce, b"header", b"payload").unwrap();
+        let (ciphertext, tag) = cipher.encrypt_detached(nonce, b"header", b"payload").unwrap();
+        assert_eq!(cipher.tag_length(), tag.len());
+        let expected = if tag_first { [tag.clone(), ciphertext.clone()] } else { [ciphertext.clone(), tag.clone()] };
+        assert_eq!(combined, expected.concat());
+        assert_eq!(b"payload".to_vec(), cipher.decrypt_detached(nonce, b"header", &ciphertext, &tag).unwrap());
+        let mut modified = tag.clone();
+        modified[0] ^= 1;
+        assert_eq!(Some(AesError::AuthenticationFailed), cipher.decrypt_detached(nonce, b"header", &ciphertext, &modified).err());
+        assert_eq!(Some(AesError::InvalidTagLength), cipher.decrypt_detached(nonce, b"header", &ciphertext, &tag[1..]).err());
+        let mut builder = cipher.with_nonce(nonce);
+        builder.update_aad(b"head").update_aad(b"er");
+        let (ciphertext, tag) = builder.encrypt_detached(b"payload").unwrap();
+        let mut builder = cipher.with_nonce(nonce);
+        builder.update_aad(b"header");
+        assert_eq!(b"payload".to_vec(), builder.decrypt_detached(&ciphertext, &tag).unwrap());
+    }
+
+    fn into<A: Aead>(cipher: &A, nonce: &[u8]) {
+        let expected = cipher.encrypt(nonce, b"header", b"payload").unwrap();
+        let mut output = b"previous message".to_vec();
+        cipher.encrypt_into(nonce, b"header", b"payload", &mut output).unwrap();
+        assert_eq!(expected, output);
+        cipher.decrypt_into(nonce, b"header", &expected, &mut output).unwrap();
+        assert_eq!(b"payload".to_vec(), output);
+        assert_eq!(Some(AesError::AuthenticationFailed), cipher.decrypt_into(nonce, b"headex", &expected, &mut output).err());
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn test_into() {
+        into(&Ccm::<16>::new(&[1; 16], 8, 3).unwrap(), &[2; 12]);
+        into(&Gcm::<16>::new(&[1; 16], 12).unwrap(), &[2; 12]);
+        into(&GcmSiv::<16>::new(&[1; 16]), &[2; 12]);
+        into(&Siv::<16>::new(&[1; 16], &[3; 16]), &[2; 12]);
+        into(&Eax::<16>::new(&[1; 16]), &[2; 12]);
+        into(&Ocb::<16>::new(&[1; 16], 12).unwrap(), &[2; 12]);
+    }
+
+    #[test]
+    fn test_detached() {
+        detached(&Ccm::<16>::new(&[1; 16], 8, 3).unwrap(), &[2; 12], false);
+        detached(&Gcm::<16>::new(&[1; 16], 12).unwrap(), &[2; 12], false);
+        detached(&GcmSiv::<16>::new(&[1; 16]), &[2; 12], false);
+        detached(&Siv::<16>::new(&[1; 16], &[3; 16]), &[2; 12], true);
+        detached(&Eax::<16>::new(&[1; 16]), &[2; 12], false);
+        detached(&Ocb::<16>::new(&[1; 16], 12).unwrap(), &[2; 12], false);
+    }
+
+    #[test]
+    fn test_incremental_aad() {
+        roundtrip(&Ccm::<16>::new(&[1; 16], 16, 3).unwrap(), &[2; 12]);
+        roundtrip(&Gcm::<16>::new(&[1; 16], 16).unwrap(), &[2; 12]);
+        roundtrip(&GcmSiv::<16>::new(&[1; 16]), &[2; 12]);
+        roundtrip(&Siv::<16>::new(&[1; 16], &[3; 16]), &[2; 12]);
+        roundtrip(&Eax::<16>::new(&[1; 16]), &[2; 12]);
+        roundtrip(&Ocb::<16>::new(&[1; 16], 16).unwrap(), &[2; 12]);
+    }
+
+    #[test]
+    fn test_dyn() {
+        let ciphers: Vec<Box<dyn Aead>> = vec![Box::new(Gcm::<16>::new(&[1; 16], 16).unwrap()), Box::new(Eax::<16>::new(&[1; 16])), Box::new(Siv::<16>::new(&[1; 16], &[3; 16]))];
+        for cipher in &ciphers {
+            let ciphertext = cipher.encrypt(&[2; 12], b"header", b"payload").unwrap();
+            assert_eq!(7 + cipher.tag_length(), ciphertext.len());
+            assert_eq!(b"payload".to_vec(), cipher.decrypt(&[2; 12], b"header", &ciphertext).unwrap());
+            let sealed = OpenMessage::new(&[2; 12], b"header", b"payload".to_vec()).seal(cipher.as_ref()).unwrap();
+            assert_eq!(b"payload".to_vec(), sealed.open(cipher.as_ref()).unwrap().into_inner());
+        }
+    }
+
+    #[test]
+    fn test_message() {
+        let cipher = Gcm::<16>::new(&[1; 16], 16).unwrap();
+        let sealed = OpenMessage::new(&[2; 12], b"header", b"payload".to_vec()).seal(&cipher).unwrap();
+        assert_eq!(cipher.encrypt(&[2; 12], b"header", b"payload").unwrap(), sealed.as_bytes());
+        assert_eq!(&[2; 12], sealed.nonce());
+        assert_eq!(b"header", sealed.aad());
+        let received = SealedMessage::new(&[2; 12], b"header", sealed.into_inner());
+        let opened = received.open(&cipher).unwrap();
+        assert_eq!(b"payload".to_vec(), opened.into_inner());
+        let mut ciphertext = cipher.encrypt(&[2; 12], b"header", b"payload").unwrap();
+        ciphertext[0] ^= 1;
+        assert_eq!(Some(AesError::AuthenticationFailed), SealedMessage::new(&[2; 12], b"header", ciphertext).open(&cipher).err());
+    }
+
+    #[test]
+    fn test_message_debug() {
+        let open = OpenMessage::new(&[2; 2], b"A", b"payload".to_vec());
+        assert_eq!("AeadMessage<DecryptedState> { nonce: \"0202\", aad: \"41\", data: [REDACTED; 7] }", format!("{:?}", open));
+        let sealed = SealedMessage::new(&[2; 2], b"A", vec![0xab; 2]);
+        assert_eq!("AeadMessage<EncryptedState> { nonce: \"0202\", aad: \"41\", data: \"abab\" }", format!("{:?}", sealed));
+    }
+
+}
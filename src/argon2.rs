@@ -0,0 +1,424 @@
+//!
+//! Argon2id, the memory hard password hashing function of RFC 9106, version 
+//! 0x13. Argon2id is the recommended key derivation function for passwords. The
+//! first half of the first pass uses data independent addressing like Argon2i 
+//! and the rest data dependent addressing like Argon2d.
+//! 
+//! The memory, the iterations and the parallelism are tunable with 
+//! Argon2Params, which can be encoded into the header of encrypted output so 
+//! the key can be derived again after the defaults change. The lanes are 
+//! computed one after the other on the calling thread.
+//! 
+//! Only available with the argon2 feature.
+//! 
+
+use std::vec::Vec;
+
+use crate::blake2b::{blake2b, Blake2b};
+use crate::zeroize::{Zeroize, Zeroizing};
+use crate::AesError;
+
+///
+/// The version of Argon2, 0x13.
+/// 
+const VERSION: u32 = 0x13;
+
+///
+/// The type of Argon2id.
+/// 
+const ARGON2ID: u32 = 2;
+
+///
+/// The number of slices of a lane per pass.
+/// 
+const SYNC_POINTS: usize = 4;
+
+///
+/// The largest memory in KiB Argon2id may use, 4 GiB. Parameters needing more are
+/// rejected, so a forged header cannot make the derivation allocate without bound.
+/// 
+pub const MAX_MEMORY_KIB: u32 = 1 << 22;
+
+///
+/// The largest number of passes over the memory.
+/// 
+pub const MAX_ITERATIONS: u32 = 1 << 10;
+
+///
+/// A 1024 byte block of the memory as 128 words.
+/// 
+type Block = [u64; 128];
+
+///
+/// The cost parameters of Argon2id.
+/// 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Argon2Params {
+    ///
+    /// The memory in KiB, at least 8 times the parallelism and at most MAX_MEMORY_KIB.
+    /// 
+    pub memory_kib: u32,
+    ///
+    /// The number of passes over the memory, 1 to MAX_ITERATIONS.
+    /// 
+    pub iterations: u32,
+    ///
+    /// The number of lanes, 1 to 2^24 - 1.
+    /// 
+    pub parallelism: u32
+}
+
+impl Argon2Params {
+
+    ///
+    /// The length of the encoded parameters in bytes.
+    /// 
+    pub const ENCODED_LENGTH: usize = 12;
+
+    ///
+    /// Creates new parameters.
+    /// 
+    /// memory_kib: The memory in KiB.
+    /// iterations: The number of passes.
+    /// parallelism: The number of lanes.
+    /// 
+    /// result: The parameters or AesError::InvalidKdfParameters if they are not valid.
+    /// 
+    pub fn new(memory_kib: u32, iterations: u32, parallelism: u32) -> Result<Self, AesError> {
+        let params = Self { memory_kib, iterations, parallelism };
+        params.validate()?;
+        Ok(params)
+    }
+
+    ///
+    /// Encodes the parameters as memory, iterations and parallelism, each a 4 byte
+    /// big endian number.
+    /// 
+    /// result: The 12 byte encoding.
+    /// 
+    pub fn encode(&self) -> [u8; 12] {
+        let mut encoded = [0; 12];
+        encoded[..4].copy_from_slice(&self.memory_kib.to_be_bytes());
+        encoded[4..8].copy_from_slice(&self.iterations.to_be_bytes());
+        encoded[8..].copy_from_slice(&self.parallelism.to_be_bytes());
+        encoded
+    }
+
+    ///
+    /// Decodes parameters encoded by encode.
+    /// 
+    /// encoded: The 12 byte encoding.
+    /// 
+    /// result: The parameters or AesError::InvalidKdfParameters if they are not valid.
+    /// 
+    pub fn decode(encoded: &[u8; 12]) -> Result<Self, AesError> {
+        let word = |index: usize| u32::from_be_bytes(encoded[index..index + 4].try_into().unwrap());
+        Self::new(word(0), word(4), word(8))
+    }
+
+    ///
+    /// Checks the limits of RFC 9106 and that the memory and the iterations are at
+    /// most MAX_MEMORY_KIB and MAX_ITERATIONS.
+    /// 
+    fn validate(&self) -> Result<(), AesError> {
+        if self.iterations == 0 || self.parallelism == 0 || self.parallelism >= 1 << 24 || (self.memory_kib as u64) < 8 * self.parallelism as u64 {
+            return Err(AesError::InvalidKdfParameters);
+        }
+        if self.memory_kib > MAX_MEMORY_KIB || self.iterations > MAX_ITERATIONS {
+            return Err(AesError::InvalidKdfParameters);
+        }
+        Ok(())
+    }
+
+}
+
+impl Default for Argon2Params {
+
+    ///
+    /// The second recommended option of RFC 9106, 64 MiB of memory, 3 passes and 4 lanes.
+    /// 
+    fn default() -> Self {
+        Self { memory_kib: 64 * 1024, iterations: 3, parallelism: 4 }
+    }
+
+}
+
+///
+/// Derives a key from the password with Argon2id.
+/// 
+/// password: The password.
+/// salt: The salt, at least 8 bytes and unique for every password.
+/// params: The cost parameters.
+/// output_length: The length of the key, at least 4 bytes.
+/// 
+/// result: The key or AesError::InvalidKdfParameters if the parameters, the salt
+///         or the output length are not valid.
+/// 
+pub fn argon2id(password: &[u8], salt: &[u8], params: &Argon2Params, output_length: usize) -> Result<Vec<u8>, AesError> {
+    argon2id_with_secret(password, salt, &[], &[], params, output_length)
+}
+
+///
+/// Derives a key from the password with Argon2id, a secret key and associated data.
+/// 
+/// password: The password.
+/// salt: The salt, at least 8 bytes and unique for every password.
+/// secret: The optional secret key, empty for none.
+/// associated_data: The optional associated data, empty for none.
+/// params: The cost parameters.
+/// output_length: The length of the key, at least 4 bytes.
+/// 
+/// result: The key or AesError::InvalidKdfParameters if the parameters, the salt
+///         or the output length are not valid or the memory could not be 
+///         allocated.
+/// 
+pub fn argon2id_with_secret(password: &[u8], salt: &[u8], secret: &[u8], associated_data: &[u8], params: &Argon2Params, output_length: usize) -> Result<Vec<u8>, AesError> {
+    params.validate()?;
+    if salt.len() < 8 || output_length < 4 || u32::try_from(output_length).is_err() {
+        return Err(AesError::InvalidKdfParameters);
+    }
+    let lanes = params.parallelism as usize;
+    let lane_length = params.memory_kib as usize / (SYNC_POINTS * lanes) * SYNC_POINTS;
+    let segment_length = lane_length / SYNC_POINTS;
+    let mut hasher = Blake2b::new(64);
+    for value in [params.parallelism, output_length as u32, params.memory_kib, params.iterations, VERSION, ARGON2ID] {
+        hasher.update(&value.to_le_bytes());
+    }
+    for input in [password, salt, secret, associated_data] {
+        hasher.update(&(input.len() as u32).to_le_bytes());
+        hasher.update(input);
+    }
+    let initial = Zeroizing(hasher.finalize());
+    let mut memory = Vec::new();
+    memory.try_reserve_exact(lanes * lane_length).map_err(|_| AesError::InvalidKdfParameters)?;
+    memory.resize(lanes * lane_length, [0u64; 128]);
+    for lane in 0..lanes {
+        for column in 0..2 {
+            let input = [initial.as_slice(), &(column as u32).to_le_bytes(), &(lane as u32).to_le_bytes()].concat();
+            memory[lane * lane_length + column] = block_from_bytes(&variable_hash(1024, &input));
+        }
+    }
+    let instance = Instance { memory_blocks: (lanes * lane_length) as u64, iterations: params.iterations as u64, lanes, lane_length, segment_length };
+    for pass in 0..params.iterations as usize {
+        for slice in 0..SYNC_POINTS {
+            for lane in 0..lanes {
+                instance.fill_segment(&mut memory, pass, lane, slice);
+            }
+        }
+    }
+    let mut last = memory[lane_length - 1];
+    for lane in 1..lanes {
+        xor_block(&mut last, &memory[lane * lane_length + lane_length - 1]);
+    }
+    memory.zeroize();
+    let output = variable_hash(output_length, &Zeroizing(block_to_bytes(&last)));
+    last.zeroize();
+    Ok(output)
+}
+
+///
+/// The dimensions of the memory of one Argon2id run.
+/// 
+struct Instance {
+    memory_blocks: u64,
+    iterations: u64,
+    lanes: usize,
+    lane_length: usize,
+    segment_length: usize
+}
+
+impl Instance {
+
+    ///
+    /// Fills one segment of a lane.
+    /// 
+    fn fill_segment(&self, memory: &mut [Block], pass: usize, lane: usize, slice: usize) {
+        let data_independent = pass == 0 && slice < SYNC_POINTS / 2;
+        let mut input = [0u64; 128];
+        let mut addresses = [0u64; 128];
+        if data_independent {
+            input[..6].copy_from_slice(&[pass as u64, lane as u64, slice as u64, self.memory_blocks, self.iterations, ARGON2ID as u64]);
+        }
+        let start = if pass == 0 && slice == 0 { 2 } else { 0 };
+        if data_independent && start == 2 {
+            next_addresses(&mut input, &mut addresses);
+        }
+        for index in start..self.segment_length {
+            let column = slice * self.segment_length + index;
+            let current = lane * self.lane_length + column;
+            let previous = if column == 0 { current + self.lane_length - 1 } else { current - 1 };
+            let pseudo_random = if data_independent {
+                if index % 128 == 0 {
+                    next_addresses(&mut input, &mut addresses);
+                }
+                addresses[index % 128]
+            } else {
+                memory[previous][0]
+            };
+            let reference_lane = if pass == 0 && slice == 0 { lane } else { (pseudo_random >> 32) as usize % self.lanes };
+            let reference = reference_lane * self.lane_length + self.reference_column(pass, slice, index, pseudo_random as u32, reference_lane == lane);
+            let mut block = memory[previous];
+            compress_into(&mut block, &memory[reference]);
+            if pass == 0 {
+                memory[current] = block;
+            } else {
+                xor_block(&mut memory[current], &block);
+            }
+        }
+    }
+
+    ///
+    /// Maps the pseudo random value to a column of the reference lane, as 3.4.2 
+    /// of RFC 9106.
+    /// 
+    fn reference_column(&self, pass: usize, slice: usize, index: usize, pseudo_random: u32, same_lane: bool) -> usize {
+        let finished = if pass == 0 { slice * self.segment_length } else { self.lane_length - self.segment_length };
+        let area = if same_lane {
+            finished + index - 1
+        } else if index == 0 {
+            finished - 1
+        } else {
+            finished
+        };
+        let x = (pseudo_random as u64 * pseudo_random as u64) >> 32;
+        let relative = area - 1 - ((area as u64 * x) >> 32) as usize;
+        let start = if pass == 0 || slice == SYNC_POINTS - 1 { 0 } else { (slice + 1) * self.segment_length };
+        (start + relative) % self.lane_length
+    }
+
+}
+
+///
+/// Increments the counter of the input block and creates the next block of 
+/// addresses, G(0, G(0, input)).
+/// 
+fn next_addresses(input: &mut Block, addresses: &mut Block) {
+    input[6] += 1;
+    *addresses = [0; 128];
+    compress_into(addresses, input);
+    let first = *addresses;
+    *addresses = [0; 128];
+    compress_into(addresses, &first);
+}
+
+///
+/// The compression function G of Argon2. The block becomes G(block, other).
+/// 
+fn compress_into(block: &mut Block, other: &Block) {
+    xor_block(block, other);
+    let r = *block;
+    for row in 0..8 {
+        let indexes: [usize; 16] = std::array::from_fn(|index| row * 16 + index);
+        permute(block, &indexes);
+    }
+    for column in 0..8 {
+        let indexes: [usize; 16] = std::array::from_fn(|index| (index / 2) * 16 + column * 2 + index % 2);
+        permute(block, &indexes);
+    }
+    xor_block(block, &r);
+}
+
+///
+/// The permutation P of Argon2 on 16 words of the block.
+/// 
+fn permute(block: &mut Block, indexes: &[usize; 16]) {
+    let mut v: [u64; 16] = std::array::from_fn(|index| block[indexes[index]]);
+    for (a, b, c, d) in [(0, 4, 8, 12), (1, 5, 9, 13), (2, 6, 10, 14), (3, 7, 11, 15), (0, 5, 10, 15), (1, 6, 11, 12), (2, 7, 8, 13), (3, 4, 9, 14)] {
+        mix(&mut v, a, b, c, d);
+    }
+    for (index, value) in indexes.iter().zip(v) {
+        block[*index] = value;
+    }
+}
+
+///
+/// The function GB of Argon2, the BLAKE2b mixing with multiplications added.
+/// 
+fn mix(v: &mut [u64; 16], a: usize, b: usize, c: usize, d: usize) {
+    let multiply = |x: u64, y: u64| 2u64.wrapping_mul(x & 0xffffffff).wrapping_mul(y & 0xffffffff);
+    v[a] = v[a].wrapping_add(v[b]).wrapping_add(multiply(v[a], v[b]));
+    v[d] = (v[d] ^ v[a]).rotate_right(32);
+    v[c] = v[c].wrapping_add(v[d]).wrapping_add(multiply(v[c], v[d]));
+    v[b] = (v[b] ^ v[c]).rotate_right(24);
+    v[a] = v[a].wrapping_add(v[b]).wrapping_add(multiply(v[a], v[b]));
+    v[d] = (v[d] ^ v[a]).rotate_right(16);
+    v[c] = v[c].wrapping_add(v[d]).wrapping_add(multiply(v[c], v[d]));
+    v[b] = (v[b] ^ v[c]).rotate_right(63);
+}
+
+///
+/// The variable length hash H' of Argon2, built from BLAKE2b.
+/// 
+fn variable_hash(output_length: usize, input: &[u8]) -> Vec<u8> {
+    let prefixed = [&(output_length as u32).to_le_bytes(), input].concat();
+    if output_length <= 64 {
+        return blake2b(output_length, &prefixed);
+    }
+    let blocks = output_length.div_ceil(32) - 2;
+    let mut output = Vec::with_capacity(output_length);
+    let mut value = blake2b(64, &prefixed);
+    for _ in 1..blocks {
+        output.extend_from_slice(&value[..32]);
+        value = blake2b(64, &value);
+    }
+    output.extend_from_slice(&value[..32]);
+    output.extend_from_slice(&blake2b(output_length - 32 * blocks, &value));
+    output
+}
+
+///
+/// Xors the other block into the block.
+/// 
+fn xor_block(block: &mut Block, other: &Block) {
+    block.iter_mut().zip(other).for_each(|(a, b)| *a ^= b);
+}
+
+///
+/// Reads a block from 1024 little endian bytes.
+/// 
+fn block_from_bytes(bytes: &[u8]) -> Block {
+    std::array::from_fn(|index| u64::from_le_bytes(bytes[index * 8..index * 8 + 8].try_into().unwrap()))
+}
+
+///
+/// Writes a block as 1024 little endian bytes.
+/// 
+fn block_to_bytes(block: &Block) -> Vec<u8> {
+    block.iter().flat_map(|word| word.to_le_bytes()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::test_util::hex;
+
+    #[test]
+    fn test_rfc9106_vector() {
+        let params = Argon2Params::new(32, 3, 4).unwrap();
+        let key = argon2id_with_secret(&[1; 32], &[2; 16], &[3; 8], &[4; 12], &params, 32).unwrap();
+        assert_eq!(hex("0d640df58d78766c08c037a34a8b53c9d01ef0452d75b65eb52520e96b01e659"), key);
+    }
+
+    #[test]
+    fn test_params() {
+        let params = Argon2Params::new(1024, 2, 1).unwrap();
+        assert_eq!(Ok(params), Argon2Params::decode(&params.encode()));
+        assert_eq!(Some(AesError::InvalidKdfParameters), Argon2Params::new(7, 1, 1).err());
+        assert_eq!(Some(AesError::InvalidKdfParameters), Argon2Params::new(64, 0, 1).err());
+        assert_eq!(Some(AesError::InvalidKdfParameters), Argon2Params::new(64, 1, 0).err());
+        assert_eq!(Some(AesError::InvalidKdfParameters), argon2id(b"password", b"short", &params, 32).err());
+        assert_eq!(Some(AesError::InvalidKdfParameters), argon2id(b"password", b"somesalt", &params, 3).err());
+    }
+
+    #[test]
+    fn test_limits() {
+        assert!(Argon2Params::new(MAX_MEMORY_KIB, MAX_ITERATIONS, 1).is_ok());
+        assert_eq!(Some(AesError::InvalidKdfParameters), Argon2Params::new(MAX_MEMORY_KIB + 1, 1, 1).err());
+        assert_eq!(Some(AesError::InvalidKdfParameters), Argon2Params::new(u32::MAX, 1, 1).err());
+        assert_eq!(Some(AesError::InvalidKdfParameters), Argon2Params::new(64, MAX_ITERATIONS + 1, 1).err());
+        let forged = Argon2Params { memory_kib: u32::MAX, iterations: 1, parallelism: 1 };
+        assert_eq!(Some(AesError::InvalidKdfParameters), argon2id(b"password", b"somesalt", &forged, 32).err());
+    }
+
+}
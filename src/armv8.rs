@@ -0,0 +1,172 @@
+//!
+//! ARMv8 Cryptography Extensions implementation of the rounds, for aarch64 cpus
+//! such as Apple Silicon and AWS Graviton. AESE does AddRoundKey, SubBytes and
+//! ShiftRows of a round in one instruction and AESMC does MixColumns, AESD and
+//! AESIMC are their inverses. The instructions take constant time.
+//!
+//! The extensions are optional in ARMv8, so the support is detected when the
+//! backend is chosen. The functions must only be called when is_supported is true.
+//!
+
+use std::arch::aarch64::{vdupq_n_u8, veorq_u8, vld1q_u8, vst1q_u8};
+#[cfg(feature = "decrypt")]
+use std::arch::aarch64::{vaesdq_u8, vaesimcq_u8};
+#[cfg(feature = "encrypt")]
+use std::arch::aarch64::{vaeseq_u8, vaesmcq_u8};
+use std::ops::Deref;
+
+use crate::zeroize::Zeroizing;
+#[cfg(feature = "decrypt")]
+use crate::InverseRoundKeys;
+
+///
+/// Whether the cpu supports the AES instructions.
+///
+/// result: true if the backend can be used.
+///
+pub(crate) fn is_supported() -> bool {
+    std::arch::is_aarch64_feature_detected!("aes")
+}
+
+///
+/// Encrypts the blocks in place with the roundkeys produced by an iterator.
+///
+/// blocks: The blocks to encrypt, replaced by the encrypted blocks.
+/// rounds: The number of rounds.
+/// roundkeys: An iterator producing rounds + 1 roundkeys of 16 bytes.
+///
+#[cfg(feature = "encrypt")]
+pub(crate) fn encrypt_rounds<K: Deref<Target = [u8; 16]>>(blocks: &mut [[u8; 16]], rounds: usize, roundkeys: impl Iterator<Item = K>) {
+    debug_assert!(is_supported());
+    let roundkeys = copy_roundkeys(roundkeys);
+    // The backend is only chosen when the cpu supports the instructions.
+    unsafe { encrypt(blocks, rounds, &roundkeys) }
+}
+
+///
+/// Decrypts the blocks in place with the roundkeys produced by an iterator in
+/// reverse order. AESIMC is applied to the roundkeys of the middle rounds, so the
+/// equivalent inverse cipher can be used.
+///
+/// blocks: The blocks to decrypt, replaced by the decrypted blocks.
+/// rounds: The number of rounds.
+/// roundkeys: An iterator producing rounds + 1 roundkeys of 16 bytes, starting
+///            with the last roundkey.
+///
+#[cfg(feature = "decrypt")]
+pub(crate) fn decrypt_rounds<K: Deref<Target = [u8; 16]>>(blocks: &mut [[u8; 16]], rounds: usize, roundkeys: impl Iterator<Item = K>) {
+    debug_assert!(is_supported());
+    let roundkeys = copy_roundkeys(roundkeys);
+    // The backend is only chosen when the cpu supports the instructions.
+    unsafe { decrypt(blocks, rounds, &roundkeys, true) }
+}
+
+///
+/// Decrypts the blocks in place using the equivalent inverse cipher.
+///
+/// blocks: The blocks to decrypt, replaced by the decrypted blocks.
+/// roundkeys: The inverse roundkeys, containing 11, 13 or 15 roundkeys.
+///
+#[cfg(feature = "decrypt")]
+pub(crate) fn decrypt_equivalent_rounds(blocks: &mut [[u8; 16]], roundkeys: &InverseRoundKeys) {
+    debug_assert!(is_supported());
+    let rounds = roundkeys.rounds();
+    let roundkeys = copy_roundkeys(roundkeys.as_slice().iter().rev());
+    // The backend is only chosen when the cpu supports the instructions.
+    unsafe { decrypt(blocks, rounds, &roundkeys, false) }
+}
+
+///
+/// Copies the roundkeys into an array, in the order they are used.
+///
+fn copy_roundkeys<K: Deref<Target = [u8; 16]>>(roundkeys: impl Iterator<Item = K>) -> Zeroizing<[[u8; 16]; 15]> {
+    let mut copied = Zeroizing([[0; 16]; 15]);
+    for (slot, roundkey) in copied.iter_mut().zip(roundkeys) {
+        *slot = *roundkey;
+    }
+    copied
+}
+
+///
+/// Encrypts the blocks with AESE and AESMC. AESE adds the roundkey before the
+/// substitution, so the last roundkey is added separately.
+///
+#[cfg(feature = "encrypt")]
+#[target_feature(enable = "neon,aes")]
+unsafe fn encrypt(blocks: &mut [[u8; 16]], rounds: usize, roundkeys: &[[u8; 16]; 15]) {
+    let mut keys = [vdupq_n_u8(0); 15];
+    for (key, roundkey) in keys.iter_mut().zip(roundkeys) {
+        *key = vld1q_u8(roundkey.as_ptr());
+    }
+    for block in blocks.iter_mut() {
+        let mut state = vld1q_u8(block.as_ptr());
+        for key in &keys[..rounds - 1] {
+            state = vaesmcq_u8(vaeseq_u8(state, *key));
+        }
+        state = veorq_u8(vaeseq_u8(state, keys[rounds - 1]), keys[rounds]);
+        vst1q_u8(block.as_mut_ptr(), state);
+    }
+}
+
+///
+/// Decrypts the blocks with AESD and AESIMC using the equivalent inverse cipher.
+/// Without mixed roundkeys, AESIMC is applied to the roundkeys of the middle rounds
+/// first.
+///
+#[cfg(feature = "decrypt")]
+#[target_feature(enable = "neon,aes")]
+unsafe fn decrypt(blocks: &mut [[u8; 16]], rounds: usize, roundkeys: &[[u8; 16]; 15], mix_roundkeys: bool) {
+    let mut keys = [vdupq_n_u8(0); 15];
+    for (key, roundkey) in keys.iter_mut().zip(roundkeys) {
+        *key = vld1q_u8(roundkey.as_ptr());
+    }
+    if mix_roundkeys {
+        for key in &mut keys[1..rounds] {
+            *key = vaesimcq_u8(*key);
+        }
+    }
+    for block in blocks.iter_mut() {
+        let mut state = vld1q_u8(block.as_ptr());
+        for key in &keys[..rounds - 1] {
+            state = vaesimcq_u8(vaesdq_u8(state, *key));
+        }
+        state = veorq_u8(vaesdq_u8(state, keys[rounds - 1]), keys[rounds]);
+        vst1q_u8(block.as_mut_ptr(), state);
+    }
+}
+
+#[cfg(all(test, feature = "encrypt", feature = "decrypt"))]
+mod tests {
+
+    use super::*;
+    use crate::test_util::hex;
+    use crate::key_schedule::{ReverseRoundKeyGenerator, RoundKeyGenerator};
+    use crate::RoundKeys;
+
+    #[test]
+    fn test_fips197_vectors() {
+        if !is_supported() {
+            return;
+        }
+        let plaintext: [u8; 16] = hex("00112233445566778899aabbccddeeff").try_into().unwrap();
+        let vectors = [
+            ("000102030405060708090a0b0c0d0e0f", "69c4e0d86a7b0430d8cdb78070b4c55a"),
+            ("000102030405060708090a0b0c0d0e0f1011121314151617", "dda97ca4864cdfe06eaf70a0ec0d7191"),
+            ("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f", "8ea2b7ca516745bfeafc49904b496089")
+        ];
+        for (key, expected) in vectors {
+            let key = hex(key);
+            let roundkeys = RoundKeys::new(RoundKeyGenerator::new(&key).map(|roundkey| *roundkey).collect()).unwrap();
+            let mut blocks = [plaintext; 3];
+            encrypt_rounds(&mut blocks, roundkeys.rounds(), roundkeys.as_slice().iter());
+            assert_eq!([<[u8; 16]>::try_from(hex(expected)).unwrap(); 3], blocks);
+            let mut decrypted = blocks;
+            decrypt_equivalent_rounds(&mut decrypted, &InverseRoundKeys::new(&roundkeys));
+            assert_eq!([plaintext; 3], decrypted);
+            let mut decrypted = blocks;
+            decrypt_rounds(&mut decrypted, roundkeys.rounds(), ReverseRoundKeyGenerator::new(&ReverseRoundKeyGenerator::last_words(&key)));
+            assert_eq!([plaintext; 3], decrypted);
+        }
+    }
+
+}
@@ -0,0 +1,375 @@
+use std::vec::Vec;
+
+use crate::key_wrap::{unwrap_with_padding_using, wrap_with_padding_using};
+use crate::random::fill_random;
+use crate::zeroize::Zeroizing;
+use crate::{cbc, cmac, ctr, gcm, Aes, AesError, SecretKey};
+
+///
+/// KeyBackend performs block operations and key wrapping with keys it holds, 
+/// referenced by a handle. It lets the key material stay in a hardware security
+/// module or a PKCS#11 token, where only the handle is known to the application.
+/// SoftwareBackend is the default backend, holding the keys in memory.
+/// 
+/// Every block operation may fail, for example when the token is removed, so 
+/// all methods return a Result. CTR, CBC, GCM and CMAC are provided on top of
+/// the block operations, so the modes can be used with keys that never leave
+/// the backend.
+/// 
+pub trait KeyBackend {
+
+    ///
+    /// The handle of a key held by the backend.
+    /// 
+    type Handle;
+
+    ///
+    /// Encrypts a single block.
+    /// 
+    /// handle: The key.
+    /// block: The block to encrypt.
+    /// 
+    /// result: The encrypted block or an error from the backend.
+    /// 
+    fn encrypt_block(&self, handle: &Self::Handle, block: &[u8; 16]) -> Result<[u8; 16], AesError>;
+
+    ///
+    /// Decrypts a single block.
+    /// 
+    /// handle: The key.
+    /// block: The block to decrypt.
+    /// 
+    /// result: The decrypted block or an error from the backend.
+    /// 
+    fn decrypt_block(&self, handle: &Self::Handle, block: &[u8; 16]) -> Result<[u8; 16], AesError>;
+
+    ///
+    /// Wraps a key held by the backend, so it can be exported.
+    /// 
+    /// kek: The key encryption key.
+    /// key: The key to wrap.
+    /// 
+    /// result: The wrapped key or an error from the backend.
+    /// 
+    fn wrap(&self, kek: &Self::Handle, key: &Self::Handle) -> Result<Vec<u8>, AesError>;
+
+    ///
+    /// Unwraps a wrapped key into the backend.
+    /// 
+    /// kek: The key encryption key.
+    /// wrapped: The wrapped key.
+    /// 
+    /// result: The handle of the unwrapped key, AesError::AuthenticationFailed if
+    ///         the integrity check fails or an error from the backend.
+    /// 
+    fn unwrap(&mut self, kek: &Self::Handle, wrapped: &[u8]) -> Result<Self::Handle, AesError>;
+
+    ///
+    /// Xors the data with the CTR keystream of a key, as ctr::apply_keystream.
+    /// 
+    /// handle: The key.
+    /// counter: The initial 16 byte counter block.
+    /// data: The data to encrypt or decrypt. May be of any length.
+    /// 
+    /// result: The data xored with the keystream or an error from the backend.
+    /// 
+    fn apply_keystream(&self, handle: &Self::Handle, counter: &[u8; 16], data: &[u8]) -> Result<Vec<u8>, AesError> {
+        ctr::apply_keystream_using(|block| self.encrypt_block(handle, block), counter, data)
+    }
+
+    ///
+    /// Pads the plaintext with PKCS#7 and encrypts it in CBC mode, as cbc::encrypt.
+    /// 
+    /// handle: The key.
+    /// iv: The 16 byte initialization vector.
+    /// plaintext: The data to encrypt. May be of any length.
+    /// 
+    /// result: The encrypted data or an error from the backend.
+    /// 
+    fn encrypt_cbc(&self, handle: &Self::Handle, iv: &[u8; 16], plaintext: &[u8]) -> Result<Vec<u8>, AesError> {
+        cbc::encrypt_using(|block| self.encrypt_block(handle, block), iv, plaintext)
+    }
+
+    ///
+    /// Decrypts the ciphertext in CBC mode and removes the PKCS#7 padding, as 
+    /// cbc::decrypt.
+    /// 
+    /// handle: The key.
+    /// iv: The 16 byte initialization vector used when encrypting.
+    /// ciphertext: The data to decrypt. Must be a non empty multiple of 16 bytes.
+    /// 
+    /// result: The decrypted data, AesError::InvalidDataLength if the ciphertext 
+    ///         has an invalid length, AesError::InvalidPadding if the padding is 
+    ///         not valid or an error from the backend.
+    /// 
+    fn decrypt_cbc(&self, handle: &Self::Handle, iv: &[u8; 16], ciphertext: &[u8]) -> Result<Vec<u8>, AesError> {
+        cbc::decrypt_using(|block| self.decrypt_block(handle, block), iv, ciphertext)
+    }
+
+    ///
+    /// Encrypts and authenticates the plaintext with GCM, as Gcm::encrypt.
+    /// 
+    /// handle: The key.
+    /// tag_length: The length of the tag in bytes, between 12 and 16.
+    /// nonce: The nonce, preferably 12 bytes. It must not be empty.
+    /// aad: The associated data, authenticated but not encrypted.
+    /// plaintext: The data to encrypt.
+    /// 
+    /// result: The ciphertext followed by the tag, the error of Gcm::new or 
+    ///         Gcm::encrypt or an error from the backend.
+    /// 
+    fn encrypt_gcm(&self, handle: &Self::Handle, tag_length: usize, nonce: &[u8], aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, AesError> {
+        gcm::encrypt_using(|block| self.encrypt_block(handle, block), tag_length, nonce, aad, plaintext)
+    }
+
+    ///
+    /// Decrypts the ciphertext with GCM and verifies the tag, as Gcm::decrypt.
+    /// 
+    /// handle: The key.
+    /// tag_length: The length of the tag in bytes, between 12 and 16.
+    /// nonce: The nonce used when encrypting.
+    /// aad: The associated data used when encrypting.
+    /// ciphertext: The ciphertext followed by the tag.
+    /// 
+    /// result: The plaintext, AesError::AuthenticationFailed if the tag does not 
+    ///         match, the error of Gcm::new or Gcm::decrypt or an error from the 
+    ///         backend.
+    /// 
+    fn decrypt_gcm(&self, handle: &Self::Handle, tag_length: usize, nonce: &[u8], aad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, AesError> {
+        gcm::decrypt_using(|block| self.encrypt_block(handle, block), tag_length, nonce, aad, ciphertext)
+    }
+
+    ///
+    /// Calculates the CMAC of the data, as Cmac::mac.
+    /// 
+    /// handle: The key of the mac.
+    /// data: The data to authenticate. May be of any length.
+    /// 
+    /// result: The 16 byte mac or an error from the backend.
+    /// 
+    fn cmac(&self, handle: &Self::Handle, data: &[u8]) -> Result<[u8; 16], AesError> {
+        cmac::mac_using(|block| self.encrypt_block(handle, block), data)
+    }
+
+}
+
+///
+/// KeyHandle references a key of a SoftwareBackend.
+/// 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyHandle(usize);
+
+///
+/// The cipher of a key of a SoftwareBackend.
+/// 
+enum SoftwareCipher {
+    Aes128(Aes<16>),
+    Aes192(Aes<24>),
+    Aes256(Aes<32>)
+}
+
+///
+/// SoftwareBackend holds the keys in memory and uses the ciphers of this crate. 
+/// Keys are wrapped with RFC 5649 key wrap with padding. The key bytes are wiped
+/// when a key is destroyed or the backend is dropped.
+/// 
+#[derive(Default)]
+pub struct SoftwareBackend {
+    keys: Vec<Option<(Zeroizing<Vec<u8>>, SoftwareCipher)>>
+}
+
+impl SoftwareBackend {
+
+    ///
+    /// Creates a new SoftwareBackend without keys.
+    /// 
+    /// result: A new SoftwareBackend.
+    /// 
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///
+    /// Imports a key into the backend.
+    /// 
+    /// key: The 16, 24 or 32 byte key.
+    /// 
+    /// result: The handle of the key or AesError::InvalidKeyLength if the key has
+    ///         an invalid length.
+    /// 
+    pub fn import(&mut self, key: &[u8]) -> Result<KeyHandle, AesError> {
+        let cipher = match key.len() {
+            16 => SoftwareCipher::Aes128(Aes::new(key.try_into().unwrap())),
+            24 => SoftwareCipher::Aes192(Aes::new(key.try_into().unwrap())),
+            32 => SoftwareCipher::Aes256(Aes::new(key.try_into().unwrap())),
+            length => return Err(AesError::InvalidKeyLength { length, min: 16, max: 32, multiple: 8 })
+        };
+        self.keys.push(Some((Zeroizing(key.to_vec()), cipher)));
+        Ok(KeyHandle(self.keys.len() - 1))
+    }
+
+    ///
+    /// Imports a SecretKey into the backend.
+    /// 
+    /// key: The 16, 24 or 32 byte key.
+    /// 
+    /// result: The handle of the key or AesError::InvalidKeyLength if the key has
+    ///         an invalid length.
+    /// 
+    pub fn import_secret<const KEY_BYTES: usize>(&mut self, key: &SecretKey<KEY_BYTES>) -> Result<KeyHandle, AesError> {
+        self.import(key.expose_secret())
+    }
+
+    ///
+    /// Generates a new random key in the backend.
+    /// 
+    /// length: The length of the key, 16, 24 or 32 bytes.
+    /// 
+    /// result: The handle of the key, AesError::InvalidKeyLength if the length is
+    ///         invalid or AesError::RandomUnavailable if no key could be generated.
+    /// 
+    pub fn generate(&mut self, length: usize) -> Result<KeyHandle, AesError> {
+        if ![16, 24, 32].contains(&length) {
+            return Err(AesError::InvalidKeyLength { length, min: 16, max: 32, multiple: 8 });
+        }
+        let mut key = Zeroizing(vec![0; length]);
+        fill_random(&mut key)?;
+        self.import(&key)
+    }
+
+    ///
+    /// Destroys a key. The handle is not reused.
+    /// 
+    /// handle: The key.
+    /// 
+    /// result: Ok or AesError::KeyNotFound if there is no key with the handle.
+    /// 
+    pub fn destroy(&mut self, handle: &KeyHandle) -> Result<(), AesError> {
+        self.keys.get_mut(handle.0).and_then(Option::take).map(|_| ()).ok_or(AesError::KeyNotFound)
+    }
+
+    ///
+    /// Finds the key of a handle.
+    /// 
+    fn key(&self, handle: &KeyHandle) -> Result<&(Zeroizing<Vec<u8>>, SoftwareCipher), AesError> {
+        self.keys.get(handle.0).and_then(Option::as_ref).ok_or(AesError::KeyNotFound)
+    }
+
+}
+
+impl KeyBackend for SoftwareBackend {
+
+    type Handle = KeyHandle;
+
+    fn encrypt_block(&self, handle: &KeyHandle, block: &[u8; 16]) -> Result<[u8; 16], AesError> {
+        let encrypted = match &self.key(handle)?.1 {
+            SoftwareCipher::Aes128(cipher) => cipher.encrypt_bytes(block),
+            SoftwareCipher::Aes192(cipher) => cipher.encrypt_bytes(block),
+            SoftwareCipher::Aes256(cipher) => cipher.encrypt_bytes(block)
+        };
+        Ok(encrypted.try_into().unwrap())
+    }
+
+    fn decrypt_block(&self, handle: &KeyHandle, block: &[u8; 16]) -> Result<[u8; 16], AesError> {
+        let decrypted = match &self.key(handle)?.1 {
+            SoftwareCipher::Aes128(cipher) => cipher.decrypt_bytes(block),
+            SoftwareCipher::Aes192(cipher) => cipher.decrypt_bytes(block),
+            SoftwareCipher::Aes256(cipher) => cipher.decrypt_bytes(block)
+        };
+        Ok(decrypted.try_into().unwrap())
+    }
+
+    fn wrap(&self, kek: &KeyHandle, key: &KeyHandle) -> Result<Vec<u8>, AesError> {
+        let key_data = &self.key(key)?.0;
+        wrap_with_padding_using(|block| self.encrypt_block(kek, block), key_data)
+    }
+
+    fn unwrap(&mut self, kek: &KeyHandle, wrapped: &[u8]) -> Result<KeyHandle, AesError> {
+        let key_data = Zeroizing(unwrap_with_padding_using(|block| self.decrypt_block(kek, block), wrapped)?);
+        self.import(&key_data)
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::key_wrap::KeyWrap;
+    use crate::test_util::hex;
+
+    #[test]
+    fn test_block_operations() {
+        let mut backend = SoftwareBackend::new();
+        let handle = backend.import(&hex("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f")).unwrap();
+        let plaintext = hex("00112233445566778899aabbccddeeff").try_into().unwrap();
+        let ciphertext = backend.encrypt_block(&handle, &plaintext).unwrap();
+        assert_eq!(hex("8ea2b7ca516745bfeafc49904b496089"), ciphertext);
+        assert_eq!(plaintext, backend.decrypt_block(&handle, &ciphertext).unwrap());
+    }
+
+    #[test]
+    fn test_wrap_unwrap() {
+        let mut backend = SoftwareBackend::new();
+        let kek = backend.import(&[7; 24]).unwrap();
+        let key = backend.generate(32).unwrap();
+        let wrapped = backend.wrap(&kek, &key).unwrap();
+        assert_eq!(backend.key(&key).unwrap().0.to_vec(), KeyWrap::<24>::new(&[7; 24]).unwrap_with_padding(&wrapped).unwrap());
+        let unwrapped = backend.unwrap(&kek, &wrapped).unwrap();
+        assert_ne!(key, unwrapped);
+        let block = [1; 16];
+        assert_eq!(backend.encrypt_block(&key, &block), backend.encrypt_block(&unwrapped, &block));
+        let mut modified = wrapped.clone();
+        modified[0] ^= 1;
+        assert_eq!(Err(AesError::AuthenticationFailed), backend.unwrap(&kek, &modified));
+    }
+
+    #[test]
+    fn test_destroy() {
+        let mut backend = SoftwareBackend::new();
+        let handle = backend.import(&[1; 16]).unwrap();
+        assert_eq!(Err(AesError::InvalidKeyLength { length: 20, min: 16, max: 32, multiple: 8 }), backend.import(&[1; 20]));
+        backend.destroy(&handle).unwrap();
+        assert_eq!(Err(AesError::KeyNotFound), backend.destroy(&handle));
+        assert_eq!(Err(AesError::KeyNotFound), backend.encrypt_block(&handle, &[0; 16]));
+        assert_eq!(KeyHandle(1), backend.import(&[1; 16]).unwrap());
+    }
+
+    #[test]
+    fn test_modes() {
+        let key = hex("000102030405060708090a0b0c0d0e0f1011121314151617");
+        let mut backend = SoftwareBackend::new();
+        let handle = backend.import(&key).unwrap();
+        let cipher = Aes::<24>::new(&key.clone().try_into().unwrap());
+        let (iv, nonce, aad) = ([9; 16], [4; 12], b"header");
+        for length in [0, 1, 15, 16, 17, 33, 64] {
+            let data: Vec<u8> = (0..length as u8).collect();
+            assert_eq!(ctr::apply_keystream(&cipher, &iv, &data), backend.apply_keystream(&handle, &iv, &data).unwrap());
+            let encrypted = backend.encrypt_cbc(&handle, &iv, &data).unwrap();
+            assert_eq!(cbc::encrypt(&cipher, &iv, &data), encrypted);
+            assert_eq!(data, backend.decrypt_cbc(&handle, &iv, &encrypted).unwrap());
+            let gcm = gcm::Gcm::<24>::new(&key.clone().try_into().unwrap(), 12).unwrap();
+            let sealed = backend.encrypt_gcm(&handle, 12, &nonce, aad, &data).unwrap();
+            assert_eq!(gcm.encrypt(&nonce, aad, &data).unwrap(), sealed);
+            assert_eq!(data, backend.decrypt_gcm(&handle, 12, &nonce, aad, &sealed).unwrap());
+            assert_eq!(gcm.encrypt(&[4; 8], aad, &data), backend.encrypt_gcm(&handle, 12, &[4; 8], aad, &data));
+            let cmac = cmac::Cmac::<24>::new(&key.clone().try_into().unwrap());
+            assert_eq!(cmac.mac(&data), backend.cmac(&handle, &data).unwrap());
+        }
+        let mut sealed = backend.encrypt_gcm(&handle, 16, &nonce, aad, b"data").unwrap();
+        sealed[0] ^= 1;
+        assert_eq!(Err(AesError::AuthenticationFailed), backend.decrypt_gcm(&handle, 16, &nonce, aad, &sealed));
+        assert_eq!(Err(AesError::TagTooShort), backend.encrypt_gcm(&handle, 8, &nonce, aad, b"data"));
+        assert_eq!(Err(AesError::InvalidDataLength { length: 15, min: 16, multiple: 16 }), backend.decrypt_cbc(&handle, &iv, &[0; 15]));
+        backend.destroy(&handle).unwrap();
+        assert_eq!(Err(AesError::KeyNotFound), backend.cmac(&handle, b"data"));
+    }
+
+    #[test]
+    fn test_import_secret() {
+        let mut backend = SoftwareBackend::new();
+        let handle = backend.import_secret(&SecretKey::new([3; 32])).unwrap();
+        assert_eq!(Aes::new(&[3; 32]).encrypt_bytes(&[5; 16]), backend.encrypt_block(&handle, &[5; 16]).unwrap());
+    }
+
+}
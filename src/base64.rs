@@ -0,0 +1,98 @@
+//!
+//! Base64 encoding and decoding with the standard alphabet and padding, as 
+//! specified in RFC 4648 section 4. Used so that ciphertexts can be stored
+//! in JSON or other text formats.
+//! 
+
+use std::string::String;
+use std::vec::Vec;
+
+///
+/// The standard base64 alphabet.
+/// 
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+///
+/// Encodes the bytes as padded base64.
+/// 
+/// data: The bytes to encode.
+/// 
+/// result: A string with 4 characters for each started 3 bytes.
+/// 
+pub(crate) fn encode(data: &[u8]) -> String {
+    let mut result = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let value = (chunk[0] as u32) << 16 | (*chunk.get(1).unwrap_or(&0) as u32) << 8 | *chunk.get(2).unwrap_or(&0) as u32;
+        for idx in 0..4 {
+            if idx <= chunk.len() {
+                result.push(ALPHABET[(value >> (18 - 6 * idx) & 63) as usize] as char);
+            } else {
+                result.push('=');
+            }
+        }
+    }
+    result
+}
+
+///
+/// Decodes padded base64. The unused bits of the last character must be zero, so
+/// that each byte string has exactly one encoding.
+/// 
+/// data: The base64 characters.
+/// 
+/// result: The decoded bytes or None if the data is not valid base64.
+/// 
+pub(crate) fn decode(data: &[u8]) -> Option<Vec<u8>> {
+    if !data.len().is_multiple_of(4) {
+        return None;
+    }
+    let mut result = Vec::with_capacity(data.len() / 4 * 3);
+    for (index, chunk) in data.chunks(4).enumerate() {
+        let padding = chunk.iter().rev().take_while(|character| **character == b'=').count();
+        if padding > 2 || (padding > 0 && index != data.len() / 4 - 1) {
+            return None;
+        }
+        let mut value = 0u32;
+        for character in &chunk[..4 - padding] {
+            value = value << 6 | ALPHABET.iter().position(|digit| digit == character)? as u32;
+        }
+        value <<= 6 * padding;
+        if value & (0xffffff >> (8 * (3 - padding))) != 0 {
+            return None;
+        }
+        result.extend_from_slice(&value.to_be_bytes()[1..4 - padding]);
+    }
+    Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_rfc4648_vectors() {
+        for (data, encoded) in [("", ""), ("f", "Zg=="), ("fo", "Zm8="), ("foo", "Zm9v"), ("foob", "Zm9vYg=="), ("fooba", "Zm9vYmE="), ("foobar", "Zm9vYmFy")] {
+            assert_eq!(encoded, encode(data.as_bytes()));
+            assert_eq!(Some(data.as_bytes().to_vec()), decode(encoded.as_bytes()));
+        }
+    }
+
+    #[test]
+    fn test_binary() {
+        let data: Vec<u8> = (0..=255).collect();
+        assert_eq!(Some(data.clone()), decode(encode(&data).as_bytes()));
+        assert_eq!("+/8=", encode(&[0xfb, 0xff]));
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert_eq!(None, decode(b"Zm9"));
+        assert_eq!(None, decode(b"Zm9*"));
+        assert_eq!(None, decode(b"Z==="));
+        assert_eq!(None, decode(b"Zg==Zm9v"));
+        assert_eq!(None, decode(b"Zh=="));
+        assert_eq!(None, decode(b"Zm9="));
+    }
+
+}
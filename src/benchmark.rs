@@ -0,0 +1,139 @@
+//!
+//! Throughput measurement, so the speed of the backends and the modes can be
+//! compared and regressions found. The benchmarks in benches/ are built on it,
+//! and programs using the crate can call it to report the speed on the machine
+//! they run on, for example to choose a RoundBackend.
+//!
+//! An operation is repeated in batches of doubling size until a minimum time has
+//! passed, so the clock is read rarely even when a single run takes nanoseconds.
+//!
+
+use std::fmt;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+///
+/// The result of a measurement, the number of bytes processed in the measured time.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Throughput {
+    bytes: u64,
+    iterations: u64,
+    elapsed: Duration
+}
+
+impl Throughput {
+
+    ///
+    /// The number of bytes processed in all the runs.
+    ///
+    /// result: The bytes processed by one run multiplied by the number of runs.
+    ///
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+
+    ///
+    /// The number of times the operation was run, not counting the warm up.
+    ///
+    /// result: The number of runs.
+    ///
+    pub fn iterations(&self) -> u64 {
+        self.iterations
+    }
+
+    ///
+    /// The time of all the runs.
+    ///
+    /// result: The measured time, at least the minimum time of the measurement.
+    ///
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    ///
+    /// The average time of a single run.
+    ///
+    /// result: The measured time divided by the number of runs.
+    ///
+    pub fn time_per_iteration(&self) -> Duration {
+        self.elapsed.div_f64(self.iterations as f64)
+    }
+
+    ///
+    /// The number of bytes processed per second.
+    ///
+    /// result: The throughput in bytes per second.
+    ///
+    pub fn bytes_per_second(&self) -> f64 {
+        self.bytes as f64 / self.elapsed.as_secs_f64()
+    }
+
+}
+
+impl fmt::Display for Throughput {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.1} MiB/s ({:?} per iteration)", self.bytes_per_second() / (1024.0 * 1024.0), self.time_per_iteration())
+    }
+
+}
+
+///
+/// Measures the throughput of an operation, for example
+/// measure_throughput(data.len(), Duration::from_secs(1), || ctr::encrypt(&cipher, &counter, &data)).
+/// The operation is run once to warm up the caches before the measurement. The
+/// result of the operation is passed to std::hint::black_box, so the compiler
+/// does not remove the work.
+///
+/// bytes_per_iteration: The number of bytes processed by one run of the operation.
+/// minimum_time: The operation is repeated until at least this time has passed.
+/// operation: The operation to measure.
+///
+/// result: The bytes processed, the number of runs and the time they took.
+///
+pub fn measure_throughput<T>(bytes_per_iteration: usize, minimum_time: Duration, mut operation: impl FnMut() -> T) -> Throughput {
+    black_box(operation());
+    let start = Instant::now();
+    let mut iterations: u64 = 0;
+    let mut batch: u64 = 1;
+    loop {
+        for _ in 0..batch {
+            black_box(operation());
+        }
+        iterations += batch;
+        let elapsed = start.elapsed();
+        if elapsed >= minimum_time {
+            return Throughput {
+                bytes: iterations * bytes_per_iteration as u64,
+                iterations,
+                elapsed
+            };
+        }
+        batch *= 2;
+    }
+}
+
+#[cfg(all(test, feature = "encrypt", feature = "decrypt"))]
+mod tests {
+
+    use super::*;
+    use crate::Aes128;
+
+    #[test]
+    fn test_measure_throughput() {
+        let cipher = Aes128::new(&[0; 16]);
+        let mut block = [0; 16];
+        let mut runs = 0;
+        let throughput = measure_throughput(16, Duration::from_millis(20), || {
+            runs += 1;
+            cipher.encrypt_block_in_place(&mut block);
+        });
+        assert_eq!(runs, throughput.iterations() + 1);
+        assert_eq!(16 * throughput.iterations(), throughput.bytes());
+        assert!(throughput.elapsed() >= Duration::from_millis(20));
+        assert!(throughput.bytes_per_second() > 0.0);
+        assert!(throughput.to_string().contains("MiB/s"));
+    }
+
+}
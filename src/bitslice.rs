@@ -0,0 +1,413 @@
+//!
+//! Bitsliced implementation of the rounds. Four blocks are processed in parallel
+//! in eight 64 bit words, where word i holds bit i of all the 64 bytes. Bit
+//! 16 * block + position of a word is the bit of the byte at the position of the
+//! block, so every block uses a 16 bit lane of the words.
+//!
+//! SubBytes computes the inverse in GF(2^8) as x^254 with logical operations on
+//! the words, followed by the affine transformation, so there are no table
+//! lookups and no branches depending on the data. The rounds take the same time
+//! for every key and block, which makes them safe against cache timing attacks.
+//! Fewer than four blocks leave the unused lanes empty.
+//!
+//! The rounds are generic over the Word of the state, so the same rounds run on
+//! vectors of words holding more blocks.
+//!
+
+use std::ops::{BitAnd, BitOr, BitXor, BitXorAssign, Deref, Not, Shl, Shr};
+
+use crate::zeroize::{Zeroize, Zeroizing};
+#[cfg(feature = "decrypt")]
+use crate::InverseRoundKeys;
+
+///
+/// The bit of row 0 in every column of every lane.
+///
+const ROW: u64 = 0x1111_1111_1111_1111;
+
+///
+/// Word is the type of the eight words of the state, a u64 holding four blocks
+/// or a vector of u64 holding four blocks in each element.
+///
+pub(crate) trait Word: Copy + Zeroize + BitAnd<Output = Self> + BitOr<Output = Self> + BitXor<Output = Self> + BitXorAssign + Not<Output = Self> + Shl<u64, Output = Self> + Shr<u64, Output = Self> {
+
+    ///
+    /// The number of blocks processed in parallel.
+    ///
+    const BLOCKS: usize;
+
+    ///
+    /// Creates a word with the bits in every element.
+    ///
+    fn splat(bits: u64) -> Self;
+
+    ///
+    /// Bitslices up to BLOCKS blocks into eight words.
+    ///
+    fn pack(blocks: &[[u8; 16]]) -> [Self; 8];
+
+    ///
+    /// Converts the eight words back into the blocks.
+    ///
+    fn unpack(state: &[Self; 8], blocks: &mut [[u8; 16]]);
+
+}
+
+impl Word for u64 {
+
+    const BLOCKS: usize = 4;
+
+    fn splat(bits: u64) -> Self {
+        bits
+    }
+
+    fn pack(blocks: &[[u8; 16]]) -> [Self; 8] {
+        pack(blocks)
+    }
+
+    fn unpack(state: &[Self; 8], blocks: &mut [[u8; 16]]) {
+        unpack(state, blocks)
+    }
+
+}
+
+///
+/// Encrypts the blocks in place with the roundkeys produced by an iterator.
+///
+/// blocks: The blocks to encrypt, replaced by the encrypted blocks.
+/// rounds: The number of rounds.
+/// roundkeys: An iterator producing rounds + 1 roundkeys of 16 bytes.
+///
+#[cfg(feature = "encrypt")]
+pub(crate) fn encrypt_rounds<W: Word, K: Deref<Target = [u8; 16]>>(blocks: &mut [[u8; 16]], rounds: usize, roundkeys: impl Iterator<Item = K>) {
+    let roundkeys = pack_roundkeys::<W, _>(roundkeys);
+    for_each_batch(blocks, |state| {
+        add_roundkey(state, &roundkeys[0]);
+        for roundkey in &roundkeys[1..rounds] {
+            sub_bytes(state);
+            shift_rows(state);
+            mix_columns(state);
+            add_roundkey(state, roundkey);
+        }
+        sub_bytes(state);
+        shift_rows(state);
+        add_roundkey(state, &roundkeys[rounds]);
+    });
+}
+
+///
+/// Decrypts the blocks in place with the roundkeys produced by an iterator in
+/// reverse order, using the inverse cipher.
+///
+/// blocks: The blocks to decrypt, replaced by the decrypted blocks.
+/// rounds: The number of rounds.
+/// roundkeys: An iterator producing rounds + 1 roundkeys of 16 bytes, starting
+///            with the last roundkey.
+///
+#[cfg(feature = "decrypt")]
+pub(crate) fn decrypt_rounds<W: Word, K: Deref<Target = [u8; 16]>>(blocks: &mut [[u8; 16]], rounds: usize, roundkeys: impl Iterator<Item = K>) {
+    let roundkeys = pack_roundkeys::<W, _>(roundkeys);
+    for_each_batch(blocks, |state| {
+        add_roundkey(state, &roundkeys[0]);
+        for roundkey in &roundkeys[1..rounds] {
+            inverse_sub_bytes(state);
+            inverse_shift_rows(state);
+            add_roundkey(state, roundkey);
+            inverse_mix_columns(state);
+        }
+        inverse_sub_bytes(state);
+        inverse_shift_rows(state);
+        add_roundkey(state, &roundkeys[rounds]);
+    });
+}
+
+///
+/// Decrypts the blocks in place using the equivalent inverse cipher.
+///
+/// blocks: The blocks to decrypt, replaced by the decrypted blocks.
+/// roundkeys: The inverse roundkeys, containing 11, 13 or 15 roundkeys.
+///
+#[cfg(feature = "decrypt")]
+pub(crate) fn decrypt_equivalent_rounds<W: Word>(blocks: &mut [[u8; 16]], roundkeys: &InverseRoundKeys) {
+    let rounds = roundkeys.rounds();
+    let roundkeys = pack_roundkeys::<W, _>(roundkeys.as_slice().iter().rev());
+    for_each_batch(blocks, |state| {
+        add_roundkey(state, &roundkeys[0]);
+        for roundkey in &roundkeys[1..rounds] {
+            inverse_sub_bytes(state);
+            inverse_shift_rows(state);
+            inverse_mix_columns(state);
+            add_roundkey(state, roundkey);
+        }
+        inverse_sub_bytes(state);
+        inverse_shift_rows(state);
+        add_roundkey(state, &roundkeys[rounds]);
+    });
+}
+
+///
+/// Runs the rounds on the blocks, up to Word::BLOCKS blocks at a time.
+///
+fn for_each_batch<W: Word>(blocks: &mut [[u8; 16]], mut rounds: impl FnMut(&mut [W; 8])) {
+    for batch in blocks.chunks_mut(W::BLOCKS) {
+        let mut state = W::pack(batch);
+        rounds(&mut state);
+        W::unpack(&state, batch);
+        state.zeroize();
+    }
+}
+
+///
+/// Bitslices the roundkeys, copying each roundkey into all the lanes.
+///
+fn pack_roundkeys<W: Word, K: Deref<Target = [u8; 16]>>(roundkeys: impl Iterator<Item = K>) -> Zeroizing<[[W; 8]; 15]> {
+    let mut packed = Zeroizing([[W::splat(0); 8]; 15]);
+    for (slot, roundkey) in packed.iter_mut().zip(roundkeys) {
+        *slot = pack(&[*roundkey; 4]).map(W::splat);
+    }
+    packed
+}
+
+///
+/// Bitslices up to four blocks into eight words.
+///
+fn pack(blocks: &[[u8; 16]]) -> [u64; 8] {
+    let mut state = [0; 8];
+    for (index, block) in blocks.iter().enumerate() {
+        for (position, value) in block.iter().enumerate() {
+            for (bit, word) in state.iter_mut().enumerate() {
+                *word |= (((value >> bit) & 1) as u64) << (index * 16 + position);
+            }
+        }
+    }
+    state
+}
+
+///
+/// Converts the eight words back into the blocks.
+///
+fn unpack(state: &[u64; 8], blocks: &mut [[u8; 16]]) {
+    for (index, block) in blocks.iter_mut().enumerate() {
+        for (position, value) in block.iter_mut().enumerate() {
+            *value = state.iter().enumerate().fold(0, |byte, (bit, word)| byte | ((((word >> (index * 16 + position)) & 1) as u8) << bit));
+        }
+    }
+}
+
+///
+/// Xors the bitsliced roundkey into the state.
+///
+fn add_roundkey<W: Word>(state: &mut [W; 8], roundkey: &[W; 8]) {
+    state.iter_mut().zip(roundkey).for_each(|(word, key)| *word ^= *key);
+}
+
+///
+/// Substitutes every byte with the s-box, the inverse followed by the affine
+/// transformation with the constant 0x63.
+///
+#[cfg(feature = "encrypt")]
+fn sub_bytes<W: Word>(state: &mut [W; 8]) {
+    let mut inverse = invert(state);
+    *state = std::array::from_fn(|bit| {
+        let word = inverse[bit] ^ inverse[(bit + 4) % 8] ^ inverse[(bit + 5) % 8] ^ inverse[(bit + 6) % 8] ^ inverse[(bit + 7) % 8];
+        word ^ W::splat(0u64.wrapping_sub((0x63 >> bit) & 1))
+    });
+    inverse.zeroize();
+}
+
+///
+/// Substitutes every byte with the inverse s-box, the inverse affine
+/// transformation followed by the inverse.
+///
+#[cfg(feature = "decrypt")]
+fn inverse_sub_bytes<W: Word>(state: &mut [W; 8]) {
+    let mut affine: [W; 8] = std::array::from_fn(|bit| {
+        let word = state[(bit + 2) % 8] ^ state[(bit + 5) % 8] ^ state[(bit + 7) % 8];
+        word ^ W::splat(0u64.wrapping_sub((0x05 >> bit) & 1))
+    });
+    *state = invert(&affine);
+    affine.zeroize();
+}
+
+///
+/// Calculates the inverse of every byte in GF(2^8) as x^254, where the inverse
+/// of 0 is 0.
+///
+fn invert<W: Word>(x: &[W; 8]) -> [W; 8] {
+    let x3 = multiply(&square(x), x);
+    let x7 = multiply(&square(&x3), x);
+    let x15 = multiply(&square(&x7), x);
+    let x127 = multiply(&square(&square(&square(&x15))), &x7);
+    square(&x127)
+}
+
+///
+/// Multiplies the bytes of two states in GF(2^8).
+///
+fn multiply<W: Word>(a: &[W; 8], b: &[W; 8]) -> [W; 8] {
+    let mut product = [W::splat(0); 15];
+    for (i, a) in a.iter().enumerate() {
+        for (j, b) in b.iter().enumerate() {
+            product[i + j] ^= *a & *b;
+        }
+    }
+    reduce(product)
+}
+
+///
+/// Squares the bytes of the state in GF(2^8), which only moves the bits.
+///
+fn square<W: Word>(a: &[W; 8]) -> [W; 8] {
+    let mut product = [W::splat(0); 15];
+    for (i, a) in a.iter().enumerate() {
+        product[2 * i] = *a;
+    }
+    reduce(product)
+}
+
+///
+/// Reduces a product of degree 14 with the polynomial x^8 + x^4 + x^3 + x + 1.
+///
+fn reduce<W: Word>(mut product: [W; 15]) -> [W; 8] {
+    for degree in (8..15).rev() {
+        let high = product[degree];
+        product[degree - 4] ^= high;
+        product[degree - 5] ^= high;
+        product[degree - 7] ^= high;
+        product[degree - 8] ^= high;
+    }
+    let reduced = std::array::from_fn(|bit| product[bit]);
+    product.zeroize();
+    reduced
+}
+
+///
+/// Shifts row r of every block r columns to the left.
+///
+#[cfg(feature = "encrypt")]
+fn shift_rows<W: Word>(state: &mut [W; 8]) {
+    for word in state.iter_mut() {
+        *word = (*word & W::splat(ROW)) | (1..4).fold(W::splat(0), |shifted, row| shifted | rotate_lanes(*word & W::splat(ROW << row), 4 * row));
+    }
+}
+
+///
+/// Shifts row r of every block r columns to the right.
+///
+#[cfg(feature = "decrypt")]
+fn inverse_shift_rows<W: Word>(state: &mut [W; 8]) {
+    for word in state.iter_mut() {
+        *word = (*word & W::splat(ROW)) | (1..4).fold(W::splat(0), |shifted, row| shifted | rotate_lanes(*word & W::splat(ROW << row), 16 - 4 * row));
+    }
+}
+
+///
+/// Rotates each 16 bit lane of the word to the right.
+///
+fn rotate_lanes<W: Word>(word: W, bits: u64) -> W {
+    let low = W::splat(0x0001_0001_0001_0001 * (0xffff >> bits));
+    ((word >> bits) & low) | ((word << (16 - bits)) & !low)
+}
+
+///
+/// Moves row r + rows of every column to row r.
+///
+fn rotate_rows<W: Word>(word: W, rows: u64) -> W {
+    let low = W::splat(ROW * (0xf >> rows));
+    ((word >> rows) & low) | ((word << (4 - rows)) & !low)
+}
+
+///
+/// Multiplies every byte by x in GF(2^8).
+///
+fn xtime<W: Word>(state: &[W; 8]) -> [W; 8] {
+    [state[7], state[0] ^ state[7], state[1], state[2] ^ state[7], state[3] ^ state[7], state[4], state[5], state[6]]
+}
+
+///
+/// Mixes the columns, 2 * a0 + 3 * a1 + a2 + a3 for every row.
+///
+fn mix_columns<W: Word>(state: &mut [W; 8]) {
+    let next = state.map(|word| rotate_rows(word, 1));
+    let sum = std::array::from_fn(|bit| state[bit] ^ next[bit]);
+    let doubled = xtime(&sum);
+    for (bit, word) in state.iter_mut().enumerate() {
+        *word = doubled[bit] ^ next[bit] ^ rotate_rows(*word, 2) ^ rotate_rows(*word, 3);
+    }
+}
+
+///
+/// Inverses the column mixing. The inverse matrix is the matrix of MixColumns
+/// times a matrix adding 4 * (a0 + a2) to every row.
+///
+#[cfg(feature = "decrypt")]
+fn inverse_mix_columns<W: Word>(state: &mut [W; 8]) {
+    let sum = state.map(|word| word ^ rotate_rows(word, 2));
+    let quadrupled = xtime(&xtime(&sum));
+    state.iter_mut().zip(quadrupled).for_each(|(word, value)| *word ^= value);
+    mix_columns(state);
+}
+
+#[cfg(all(test, feature = "encrypt", feature = "decrypt"))]
+mod tests {
+
+    use super::*;
+    use crate::test_util::hex;
+    use crate::{AESBlock, DecryptedState, EncryptedState, RoundKeys};
+    use crate::key_schedule::{ReverseRoundKeyGenerator, RoundKeyGenerator};
+
+    #[test]
+    fn test_sub_bytes() {
+        for start in (0..256).step_by(64) {
+            let blocks: Vec<[u8; 16]> = (0..4).map(|block| std::array::from_fn(|position| (start + block * 16 + position) as u8)).collect();
+            let mut state = pack(&blocks);
+            sub_bytes(&mut state);
+            let mut substituted = [[0; 16]; 4];
+            unpack(&state, &mut substituted);
+            assert_eq!(blocks.iter().map(|block| block.map(|value| AESBlock::<DecryptedState>::S_BOX[value as usize])).collect::<Vec<_>>(), substituted);
+            inverse_sub_bytes(&mut state);
+            unpack(&state, &mut substituted);
+            assert_eq!(blocks, substituted);
+        }
+        let mut state = pack(&[[0x63; 16]]);
+        inverse_sub_bytes(&mut state);
+        assert_eq!([0; 8], state.map(|word| word & 0xffff));
+        assert_eq!(0x52, AESBlock::<EncryptedState>::INVERSE_S_BOX[0]);
+    }
+
+    #[test]
+    fn test_mix_columns() {
+        let mut state = pack(&[hex("db135345f20a225c01010101c6c6c6c6").try_into().unwrap()]);
+        mix_columns(&mut state);
+        let mut mixed = [[0; 16]];
+        unpack(&state, &mut mixed);
+        assert_eq!(hex("8e4da1bc9fdc589d01010101c6c6c6c6"), mixed[0]);
+        inverse_mix_columns(&mut state);
+        unpack(&state, &mut mixed);
+        assert_eq!(hex("db135345f20a225c01010101c6c6c6c6"), mixed[0]);
+    }
+
+    #[test]
+    fn test_fips197_vectors() {
+        let plaintext: [u8; 16] = hex("00112233445566778899aabbccddeeff").try_into().unwrap();
+        let vectors = [
+            ("000102030405060708090a0b0c0d0e0f", "69c4e0d86a7b0430d8cdb78070b4c55a"),
+            ("000102030405060708090a0b0c0d0e0f1011121314151617", "dda97ca4864cdfe06eaf70a0ec0d7191"),
+            ("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f", "8ea2b7ca516745bfeafc49904b496089")
+        ];
+        for (key, expected) in vectors {
+            let key = hex(key);
+            let roundkeys = RoundKeys::new(RoundKeyGenerator::new(&key).map(|roundkey| *roundkey).collect()).unwrap();
+            let mut blocks = [plaintext; 5];
+            encrypt_rounds::<u64, _>(&mut blocks, roundkeys.rounds(), roundkeys.as_slice().iter());
+            assert_eq!([<[u8; 16]>::try_from(hex(expected)).unwrap(); 5], blocks);
+            let mut decrypted = blocks;
+            decrypt_equivalent_rounds::<u64>(&mut decrypted, &InverseRoundKeys::new(&roundkeys));
+            assert_eq!([plaintext; 5], decrypted);
+            let mut decrypted = blocks;
+            decrypt_rounds::<u64, _>(&mut decrypted, roundkeys.rounds(), ReverseRoundKeyGenerator::new(&ReverseRoundKeyGenerator::last_words(&key)));
+            assert_eq!([plaintext; 5], decrypted);
+        }
+    }
+
+}
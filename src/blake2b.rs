@@ -0,0 +1,184 @@
+//!
+//! BLAKE2b as specified in RFC 7693, without a key. Only used internally by 
+//! Argon2id.
+//! 
+
+use crate::zeroize::Zeroize;
+
+///
+/// The initialization vector, the same as the one of SHA-512.
+/// 
+const IV: [u64; 8] = [
+    0x6a09e667f3bcc908, 0xbb67ae8584caa73b, 0x3c6ef372fe94f82b, 0xa54ff53a5f1d36f1,
+    0x510e527fade682d1, 0x9b05688c2b3e6c1f, 0x1f83d9abfb41bd6b, 0x5be0cd19137e2179
+];
+
+///
+/// The message word permutations of the rounds.
+/// 
+const SIGMA: [[usize; 16]; 10] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+    [11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+    [7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+    [9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+    [2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+    [12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+    [13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+    [6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+    [10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0]
+];
+
+///
+/// Blake2b is an incremental BLAKE2b with an output length of 1 to 64 bytes.
+/// 
+#[derive(Clone)]
+pub(crate) struct Blake2b {
+    hash: [u64; 8],
+    buffer: [u8; 128],
+    buffered: usize,
+    length: u128,
+    output_length: usize
+}
+
+impl Blake2b {
+
+    ///
+    /// Creates a new Blake2b.
+    /// 
+    /// output_length: The length of the digest, 1 to 64 bytes.
+    /// 
+    /// result: A Blake2b without any data.
+    /// 
+    pub(crate) fn new(output_length: usize) -> Self {
+        let mut hash = IV;
+        hash[0] ^= 0x01010000 ^ output_length as u64;
+        Self { hash, buffer: [0; 128], buffered: 0, length: 0, output_length }
+    }
+
+    ///
+    /// Adds the data to the hash. A full buffer is only compressed when more data
+    /// follows, since the last block is compressed with the final flag.
+    /// 
+    /// data: The next chunk of the data, of any length.
+    /// 
+    pub(crate) fn update(&mut self, mut data: &[u8]) {
+        while !data.is_empty() {
+            if self.buffered == 128 {
+                self.length += 128;
+                compress(&mut self.hash, &self.buffer, self.length, false);
+                self.buffered = 0;
+            }
+            let count = data.len().min(128 - self.buffered);
+            self.buffer[self.buffered..self.buffered + count].copy_from_slice(&data[..count]);
+            self.buffered += count;
+            data = &data[count..];
+        }
+    }
+
+    ///
+    /// Compresses the last block and calculates the digest.
+    /// 
+    /// result: The digest of output_length bytes.
+    /// 
+    pub(crate) fn finalize(mut self) -> Vec<u8> {
+        self.length += self.buffered as u128;
+        self.buffer[self.buffered..].fill(0);
+        compress(&mut self.hash, &self.buffer, self.length, true);
+        self.hash.iter().flat_map(|word| word.to_le_bytes()).take(self.output_length).collect()
+    }
+
+}
+
+///
+/// Calculates the BLAKE2b digest of the data.
+/// 
+/// output_length: The length of the digest, 1 to 64 bytes.
+/// data: The data to hash.
+/// 
+/// result: The digest.
+/// 
+pub(crate) fn blake2b(output_length: usize, data: &[u8]) -> Vec<u8> {
+    let mut hasher = Blake2b::new(output_length);
+    hasher.update(data);
+    hasher.finalize()
+}
+
+///
+/// Compresses a 128 byte block into the hash.
+/// 
+fn compress(hash: &mut [u64; 8], block: &[u8; 128], length: u128, last: bool) {
+    let mut m = [0u64; 16];
+    for (word, bytes) in m.iter_mut().zip(block.chunks(8)) {
+        *word = u64::from_le_bytes(bytes.try_into().unwrap());
+    }
+    let mut v = [0u64; 16];
+    v[..8].copy_from_slice(hash);
+    v[8..].copy_from_slice(&IV);
+    v[12] ^= length as u64;
+    v[13] ^= (length >> 64) as u64;
+    if last {
+        v[14] = !v[14];
+    }
+    for sigma in SIGMA.iter().cycle().take(12) {
+        mix(&mut v, 0, 4, 8, 12, m[sigma[0]], m[sigma[1]]);
+        mix(&mut v, 1, 5, 9, 13, m[sigma[2]], m[sigma[3]]);
+        mix(&mut v, 2, 6, 10, 14, m[sigma[4]], m[sigma[5]]);
+        mix(&mut v, 3, 7, 11, 15, m[sigma[6]], m[sigma[7]]);
+        mix(&mut v, 0, 5, 10, 15, m[sigma[8]], m[sigma[9]]);
+        mix(&mut v, 1, 6, 11, 12, m[sigma[10]], m[sigma[11]]);
+        mix(&mut v, 2, 7, 8, 13, m[sigma[12]], m[sigma[13]]);
+        mix(&mut v, 3, 4, 9, 14, m[sigma[14]], m[sigma[15]]);
+    }
+    for index in 0..8 {
+        hash[index] ^= v[index] ^ v[index + 8];
+    }
+}
+
+///
+/// The mixing function G of BLAKE2b.
+/// 
+fn mix(v: &mut [u64; 16], a: usize, b: usize, c: usize, d: usize, x: u64, y: u64) {
+    v[a] = v[a].wrapping_add(v[b]).wrapping_add(x);
+    v[d] = (v[d] ^ v[a]).rotate_right(32);
+    v[c] = v[c].wrapping_add(v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(24);
+    v[a] = v[a].wrapping_add(v[b]).wrapping_add(y);
+    v[d] = (v[d] ^ v[a]).rotate_right(16);
+    v[c] = v[c].wrapping_add(v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(63);
+}
+
+impl Drop for Blake2b {
+
+    fn drop(&mut self) {
+        self.hash.zeroize();
+        self.buffer.zeroize();
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::test_util::hex;
+
+    #[test]
+    fn test_blake2b() {
+        let expected = "ba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d17d87c5392aab792dc252d5de4533cc9518d38aa8dbf1925ab92386edd4009923";
+        assert_eq!(hex(expected), blake2b(64, b"abc"));
+        assert_eq!(hex("0e5751c026e543b2e8ab2eb06099daa1d1e5df47778f7787faab45cdf12fe3a8"), blake2b(32, &[]));
+    }
+
+    #[test]
+    fn test_blake2b_incremental() {
+        let data: Vec<u8> = (0..512).map(|index| index as u8).collect();
+        let mut hasher = Blake2b::new(20);
+        hasher.update(&data[..128]);
+        hasher.update(&data[128..300]);
+        hasher.update(&data[300..]);
+        assert_eq!(hex("e1695d971d2357c55fe6824cf175e915700ea2f2"), hasher.finalize());
+    }
+
+}
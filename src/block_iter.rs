@@ -0,0 +1,123 @@
+use crate::{AESBlock, Aes, AesError, DecryptedState, EncryptedState};
+
+///
+/// BlockIterator adds lazy block encryption to iterators over blocks, for example
+/// plaintext.chunks(16).encrypt_with(&cipher). Each block is encrypted on its own
+/// when the adapter is advanced, so large inputs are never collected in memory.
+/// 
+/// The blocks are encrypted like ecb, so equal blocks give equal ciphertext. A
+/// block that is not 16 bytes gives AesError::InvalidBlockLength.
+/// 
+pub trait BlockIterator: Iterator + Sized {
+
+    ///
+    /// Encrypts each block of the iterator when it is yielded.
+    /// 
+    /// cipher: The cipher used to encrypt the blocks.
+    /// 
+    /// result: An iterator yielding the encrypted blocks.
+    /// 
+    #[cfg(feature = "encrypt")]
+    fn encrypt_with<const KEY_BYTES: usize>(self, cipher: &Aes<KEY_BYTES>) -> EncryptBlocks<'_, Self, KEY_BYTES> {
+        EncryptBlocks { blocks: self, cipher }
+    }
+
+    ///
+    /// Decrypts each block of the iterator when it is yielded.
+    /// 
+    /// cipher: The cipher used to decrypt the blocks.
+    /// 
+    /// result: An iterator yielding the decrypted blocks.
+    /// 
+    #[cfg(feature = "decrypt")]
+    fn decrypt_with<const KEY_BYTES: usize>(self, cipher: &Aes<KEY_BYTES>) -> DecryptBlocks<'_, Self, KEY_BYTES> {
+        DecryptBlocks { blocks: self, cipher }
+    }
+
+}
+
+impl<I: Iterator<Item: AsRef<[u8]>>> BlockIterator for I {}
+
+///
+/// EncryptBlocks is the iterator returned by BlockIterator::encrypt_with.
+/// 
+#[cfg(feature = "encrypt")]
+pub struct EncryptBlocks<'a, I, const KEY_BYTES: usize> {
+    blocks: I,
+    cipher: &'a Aes<KEY_BYTES>
+}
+
+#[cfg(feature = "encrypt")]
+impl<I: Iterator<Item: AsRef<[u8]>>, const KEY_BYTES: usize> Iterator for EncryptBlocks<'_, I, KEY_BYTES> {
+
+    type Item = Result<AESBlock<EncryptedState>, AesError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let block = self.blocks.next()?;
+        Some(AESBlock::<DecryptedState>::try_new(block.as_ref()).map(|block| self.cipher.encrypt_block(&block)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.blocks.size_hint()
+    }
+
+}
+
+///
+/// DecryptBlocks is the iterator returned by BlockIterator::decrypt_with.
+/// 
+#[cfg(feature = "decrypt")]
+pub struct DecryptBlocks<'a, I, const KEY_BYTES: usize> {
+    blocks: I,
+    cipher: &'a Aes<KEY_BYTES>
+}
+
+#[cfg(feature = "decrypt")]
+impl<I: Iterator<Item: AsRef<[u8]>>, const KEY_BYTES: usize> Iterator for DecryptBlocks<'_, I, KEY_BYTES> {
+
+    type Item = Result<AESBlock<DecryptedState>, AesError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let block = self.blocks.next()?;
+        Some(AESBlock::<EncryptedState>::try_new(block.as_ref()).map(|block| self.cipher.decrypt_block(&block)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.blocks.size_hint()
+    }
+
+}
+
+#[cfg(all(test, feature = "encrypt", feature = "decrypt"))]
+mod tests {
+
+    use super::*;
+    use crate::ecb;
+    use std::vec::Vec;
+
+    #[test]
+    fn test_encrypt_with() {
+        let cipher = Aes::<16>::new(&[1; 16]);
+        let plaintext: Vec<u8> = (0..64).collect();
+        let encrypted: Vec<u8> = plaintext.chunks(16).encrypt_with(&cipher).flat_map(|block| block.unwrap().into_inner()).collect();
+        assert_eq!(ecb::encrypt(&cipher, &plaintext).unwrap(), encrypted);
+        let decrypted: Vec<u8> = encrypted.chunks(16).decrypt_with(&cipher).flat_map(|block| block.unwrap().into_inner()).collect();
+        assert_eq!(plaintext, decrypted);
+    }
+
+    #[test]
+    fn test_lazy() {
+        let cipher = Aes::<16>::new(&[1; 16]);
+        let mut blocks = std::iter::repeat([7u8; 16]).encrypt_with(&cipher);
+        let first = blocks.next().unwrap().unwrap();
+        assert_eq!(first.as_bytes(), blocks.next().unwrap().unwrap().as_bytes());
+    }
+
+    #[test]
+    fn test_partial_block() {
+        let cipher = Aes::<16>::new(&[1; 16]);
+        let results: Vec<_> = [0u8; 20].chunks(16).encrypt_with(&cipher).map(|block| block.err()).collect();
+        assert_eq!(vec![None, Some(AesError::InvalidBlockLength { length: 4, min: 16, max: 16, multiple: 16 })], results);
+    }
+
+}
@@ -0,0 +1,167 @@
+use std::vec::Vec;
+
+use crate::{Aes, AesError};
+
+///
+/// BlockMode is a mode of operation that processes the data one 16 byte block at
+/// a time with a cipher. It is implemented by the ecb, cbc and ctr modes, and can 
+/// be implemented by downstream crates for other chaining modes.
+/// 
+/// A mode is created with init, fed all full blocks with process_block and ended 
+/// with finalize, which gets the remaining bytes shorter than a block.
+/// 
+pub trait BlockMode<'a, const KEY_BYTES: usize>: Sized {
+
+    ///
+    /// Creates the mode.
+    /// 
+    /// cipher: The cipher used for each block.
+    /// iv: The 16 byte initialization vector or initial counter block. Ignored by 
+    ///     modes without an iv.
+    /// 
+    /// result: The initialized mode.
+    /// 
+    fn init(cipher: &'a Aes<KEY_BYTES>, iv: &[u8; 16]) -> Self;
+
+    ///
+    /// Processes the next full block.
+    /// 
+    /// block: The next 16 bytes of the data.
+    /// 
+    /// result: The 16 processed bytes.
+    /// 
+    fn process_block(&mut self, block: &[u8; 16]) -> [u8; 16];
+
+    ///
+    /// Processes the bytes remaining after the last full block.
+    /// 
+    /// remaining: Between 0 and 15 bytes.
+    /// 
+    /// result: The processed bytes or AesError::InvalidDataLength if the mode only
+    ///         handles full blocks and remaining is not empty.
+    /// 
+    fn finalize(self, remaining: &[u8]) -> Result<Vec<u8>, AesError>;
+
+    ///
+    /// Processes all the data with process_block and finalize.
+    /// 
+    /// data: The data to process.
+    /// 
+    /// result: The processed data or the error from finalize.
+    /// 
+    fn process(self, data: &[u8]) -> Result<Vec<u8>, AesError> {
+        let mut result = Vec::new();
+        self.process_into(data, &mut result)?;
+        Ok(result)
+    }
+
+    ///
+    /// Processes all the data with process_block and finalize into an output 
+    /// buffer. The output is cleared first, so a buffer can be reused for many 
+    /// messages without allocating once its capacity is large enough.
+    /// 
+    /// data: The data to process.
+    /// output: The buffer replaced by the processed data. Left empty on an error.
+    /// 
+    /// result: Ok or the error from finalize. AesError::InvalidDataLength has the
+    ///         length of all the data.
+    /// 
+    fn process_into(mut self, data: &[u8], output: &mut Vec<u8>) -> Result<(), AesError> {
+        output.clear();
+        output.reserve(data.len());
+        let mut blocks = data.chunks_exact(16);
+        for block in &mut blocks {
+            output.extend(self.process_block(block.try_into().unwrap()));
+        }
+        let last = self.finalize(blocks.remainder()).map_err(|error| match error {
+            AesError::InvalidDataLength { min, multiple, .. } => AesError::InvalidDataLength { length: data.len(), min, multiple },
+            error => error
+        }).inspect_err(|_| output.clear())?;
+        output.extend(last);
+        Ok(())
+    }
+
+}
+
+///
+/// Finalize for modes that only handle full blocks.
+/// 
+pub(crate) fn finalize_full_blocks(remaining: &[u8]) -> Result<Vec<u8>, AesError> {
+    if !remaining.is_empty() {
+        return Err(AesError::InvalidDataLength { length: remaining.len(), min: 0, multiple: 16 });
+    }
+    Ok(Vec::new())
+}
+
+///
+/// Takes the start of an output slice for a result of a known length.
+/// 
+/// output: The output given by the caller.
+/// length: The length of the result.
+/// 
+/// result: The first length bytes of the output or AesError::OutputTooShort if 
+///         the output is shorter.
+/// 
+pub(crate) fn output_prefix(output: &mut [u8], length: usize) -> Result<&mut [u8], AesError> {
+    let given = output.len();
+    output.get_mut(..length).ok_or(AesError::OutputTooShort { needed: length, given })
+}
+
+#[cfg(all(test, feature = "encrypt", feature = "decrypt"))]
+mod tests {
+
+    use super::*;
+    use crate::{ecb, Aes128};
+
+    struct Ofb<'a> {
+        cipher: ecb::Encryptor<'a, 16>,
+        register: [u8; 16]
+    }
+
+    impl<'a> BlockMode<'a, 16> for Ofb<'a> {
+
+        fn init(cipher: &'a Aes128, iv: &[u8; 16]) -> Self {
+            Self { cipher: ecb::Encryptor::init(cipher, iv), register: *iv }
+        }
+
+        fn process_block(&mut self, block: &[u8; 16]) -> [u8; 16] {
+            self.register = self.cipher.process_block(&self.register);
+            std::array::from_fn(|index| block[index] ^ self.register[index])
+        }
+
+        fn finalize(self, remaining: &[u8]) -> Result<Vec<u8>, AesError> {
+            finalize_full_blocks(remaining)
+        }
+
+    }
+
+    #[test]
+    fn test_custom_mode() {
+        let cipher = Aes128::new(&[0x2b, 0x7e, 0x15, 0x16, 0x28, 0xae, 0xd2, 0xa6, 0xab, 0xf7, 0x15, 0x88, 0x09, 0xcf, 0x4f, 0x3c]);
+        let iv = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15];
+        let plaintext = [0x6b, 0xc1, 0xbe, 0xe2, 0x2e, 0x40, 0x9f, 0x96, 0xe9, 0x3d, 0x7e, 0x11, 0x73, 0x93, 0x17, 0x2a];
+        let expected = vec![0x3b, 0x3f, 0xd9, 0x2e, 0xb7, 0x2d, 0xad, 0x20, 0x33, 0x34, 0x49, 0xf8, 0xe8, 0x3c, 0xfb, 0x4a];
+        assert_eq!(expected, Ofb::init(&cipher, &iv).process(&plaintext).unwrap());
+        assert_eq!(Some(AesError::InvalidDataLength { length: 17, min: 0, multiple: 16 }), Ofb::init(&cipher, &iv).process(&[0; 17]).err());
+    }
+
+    #[test]
+    fn test_process_into() {
+        let cipher = Aes128::new(&[0; 16]);
+        let iv = [7; 16];
+        let mut output = vec![0xff; 100];
+        Ofb::init(&cipher, &iv).process_into(&[1; 32], &mut output).unwrap();
+        assert_eq!(Ofb::init(&cipher, &iv).process(&[1; 32]).unwrap(), output);
+        assert!(output.capacity() >= 100);
+        assert_eq!(Some(AesError::InvalidDataLength { length: 33, min: 0, multiple: 16 }), Ofb::init(&cipher, &iv).process_into(&[1; 33], &mut output).err());
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn test_output_prefix() {
+        let mut output = [0; 20];
+        assert_eq!(16, output_prefix(&mut output, 16).unwrap().len());
+        assert_eq!(Some(AesError::OutputTooShort { needed: 21, given: 20 }), output_prefix(&mut output, 21).err());
+    }
+
+}
@@ -0,0 +1,342 @@
+//!
+//! Builder for a configured cipher. The mode of operation, the padding, the tag
+//! length and the counter layout are chosen once, and the built Cipher encrypts
+//! and decrypts messages of any length with them:
+//! 
+//! Aes::builder().key(&key).mode(Mode::Cbc).padding(Padding::Pkcs7).build()?
+//! 
+//! Options that do not apply to the mode, for example a padding for CTR or a tag
+//! length for CBC, are rejected by build instead of being ignored.
+//! 
+
+use std::vec::Vec;
+
+use crate::ctr::CounterLayout;
+use crate::gcm::Gcm;
+use crate::padding::{Pkcs7, ZeroPadding};
+use crate::{cbc, cfb, ctr, ecb, Aes, AesError, SecretKey};
+
+///
+/// Mode is the mode of operation of a Cipher.
+/// 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Mode {
+    ///
+    /// Electronic codebook. Every block is encrypted on its own, so equal blocks
+    /// give equal ciphertext. No iv is used.
+    /// 
+    Ecb,
+    ///
+    /// Cipher block chaining with a 16 byte iv.
+    /// 
+    #[default]
+    Cbc,
+    ///
+    /// Counter mode with a 16 byte initial counter block.
+    /// 
+    Ctr,
+    ///
+    /// Full block cipher feedback with a 16 byte iv.
+    /// 
+    Cfb,
+    ///
+    /// Galois/Counter Mode, authenticated encryption with a nonce, preferably 12 bytes.
+    /// 
+    Gcm
+}
+
+///
+/// Padding is the padding scheme of a Cipher in ECB or CBC mode.
+/// 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Padding {
+    ///
+    /// No padding. The data must be a multiple of 16 bytes.
+    /// 
+    None,
+    ///
+    /// PKCS#7 padding.
+    /// 
+    #[default]
+    Pkcs7,
+    ///
+    /// Zero padding. Trailing zeros of the data are removed when decrypting.
+    /// 
+    Zero
+}
+
+///
+/// CipherBuilder collects the configuration of a Cipher. It is created with
+/// Aes::builder.
+/// 
+pub struct CipherBuilder<const KEY_BYTES: usize = 16> {
+    key: Option<SecretKey<KEY_BYTES>>,
+    mode: Mode,
+    padding: Option<Padding>,
+    tag_length: Option<usize>,
+    counter_layout: Option<CounterLayout>
+}
+
+impl<const KEY_BYTES: usize> CipherBuilder<KEY_BYTES> {
+
+    ///
+    /// Creates a new CipherBuilder without a key, using CBC with PKCS#7 padding.
+    /// 
+    pub(crate) fn new() -> Self {
+        Self { key: None, mode: Mode::default(), padding: None, tag_length: None, counter_layout: None }
+    }
+
+    ///
+    /// Sets the key. The key is copied and wiped when the builder is dropped.
+    /// 
+    /// key: A key of 16, 24 or 32 bytes.
+    /// 
+    pub fn key(mut self, key: &[u8; KEY_BYTES]) -> Self {
+        self.key = Some(SecretKey::new(*key));
+        self
+    }
+
+    ///
+    /// Sets the mode of operation. Defaults to Mode::Cbc.
+    /// 
+    pub fn mode(mut self, mode: Mode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    ///
+    /// Sets the padding for ECB and CBC. Defaults to Padding::Pkcs7.
+    /// 
+    pub fn padding(mut self, padding: Padding) -> Self {
+        self.padding = Some(padding);
+        self
+    }
+
+    ///
+    /// Sets the tag length for GCM, between 12 and 16 bytes. Defaults to 16.
+    /// 
+    pub fn tag_length(mut self, tag_length: usize) -> Self {
+        self.tag_length = Some(tag_length);
+        self
+    }
+
+    ///
+    /// Sets the counter layout for CTR. Defaults to CounterLayout::Counter128.
+    /// 
+    pub fn counter_layout(mut self, counter_layout: CounterLayout) -> Self {
+        self.counter_layout = Some(counter_layout);
+        self
+    }
+
+    ///
+    /// Creates the cipher.
+    /// 
+    /// result: The configured Cipher, AesError::InvalidConfiguration if the key is
+    ///         missing or an option does not apply to the mode, or the error from
+    ///         Gcm::new if the tag length is not valid.
+    /// 
+    pub fn build(self) -> Result<Cipher<KEY_BYTES>, AesError> {
+        let key = self.key.as_ref().ok_or(AesError::InvalidConfiguration)?.expose_secret();
+        let uses_padding = matches!(self.mode, Mode::Ecb | Mode::Cbc);
+        if (self.padding.is_some() && !uses_padding) || (self.tag_length.is_some() && self.mode != Mode::Gcm) || (self.counter_layout.is_some() && self.mode != Mode::Ctr) {
+            return Err(AesError::InvalidConfiguration);
+        }
+        let engine = match self.mode {
+            Mode::Gcm => Engine::Gcm(Gcm::new(key, self.tag_length.unwrap_or(16))?),
+            _ => Engine::Block(Aes::new(key))
+        };
+        Ok(Cipher {
+            engine,
+            mode: self.mode,
+            padding: self.padding.unwrap_or_default(),
+            counter_layout: self.counter_layout.unwrap_or_default()
+        })
+    }
+
+}
+
+///
+/// The cipher used by the mode of a Cipher.
+/// 
+enum Engine<const KEY_BYTES: usize> {
+    Block(Aes<KEY_BYTES>),
+    Gcm(Gcm<KEY_BYTES>)
+}
+
+///
+/// Cipher is a cipher configured with CipherBuilder. The iv given when encrypting
+/// depends on the mode: empty for ECB, 16 bytes for CBC, CTR and CFB, and the
+/// nonce for GCM. The same iv must be given when decrypting.
+/// 
+pub struct Cipher<const KEY_BYTES: usize = 16> {
+    engine: Engine<KEY_BYTES>,
+    mode: Mode,
+    padding: Padding,
+    counter_layout: CounterLayout
+}
+
+impl<const KEY_BYTES: usize> Cipher<KEY_BYTES> {
+
+    ///
+    /// Returns the mode of operation of the cipher.
+    /// 
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    ///
+    /// Encrypts the plaintext.
+    /// 
+    /// iv: The iv, counter block or nonce of the mode.
+    /// plaintext: The data to encrypt.
+    /// 
+    /// result: The ciphertext, AesError::InvalidNonceLength if the iv has the wrong
+    ///         length, AesError::InvalidDataLength if the data must be full blocks
+    ///         or the error from the mode.
+    /// 
+    pub fn encrypt(&self, iv: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, AesError> {
+        match &self.engine {
+            Engine::Gcm(gcm) => gcm.encrypt(iv, &[], plaintext),
+            Engine::Block(cipher) => match self.mode {
+                Mode::Ecb => {
+                    Self::validate_iv(iv, 0)?;
+                    match self.padding {
+                        Padding::None => ecb::encrypt(cipher, plaintext),
+                        Padding::Pkcs7 => Ok(ecb::encrypt_with_padding(cipher, plaintext, &Pkcs7)),
+                        Padding::Zero => Ok(ecb::encrypt_with_padding(cipher, plaintext, &ZeroPadding))
+                    }
+                },
+                Mode::Cbc => {
+                    let iv = Self::validate_iv(iv, 16)?.try_into().unwrap();
+                    match self.padding {
+                        Padding::None => cbc::encrypt_unpadded(cipher, &iv, plaintext),
+                        Padding::Pkcs7 => Ok(cbc::encrypt_with_padding(cipher, &iv, plaintext, &Pkcs7)),
+                        Padding::Zero => Ok(cbc::encrypt_with_padding(cipher, &iv, plaintext, &ZeroPadding))
+                    }
+                },
+                Mode::Ctr => ctr::apply_keystream_with_layout(cipher, Self::validate_iv(iv, 16)?.try_into().unwrap(), self.counter_layout, plaintext),
+                _ => Ok(cfb::encrypt128(cipher, Self::validate_iv(iv, 16)?.try_into().unwrap(), plaintext))
+            }
+        }
+    }
+
+    ///
+    /// Decrypts the ciphertext.
+    /// 
+    /// iv: The iv, counter block or nonce used when encrypting.
+    /// ciphertext: The data to decrypt.
+    /// 
+    /// result: The plaintext, AesError::InvalidNonceLength if the iv has the wrong
+    ///         length, AesError::InvalidPadding if the padding is not valid,
+    ///         AesError::AuthenticationFailed if the GCM tag does not match or the
+    ///         error from the mode.
+    /// 
+    pub fn decrypt(&self, iv: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, AesError> {
+        match &self.engine {
+            Engine::Gcm(gcm) => gcm.decrypt(iv, &[], ciphertext),
+            Engine::Block(cipher) => match self.mode {
+                Mode::Ecb => {
+                    Self::validate_iv(iv, 0)?;
+                    match self.padding {
+                        Padding::None => ecb::decrypt(cipher, ciphertext),
+                        Padding::Pkcs7 => ecb::decrypt_with_padding(cipher, ciphertext, &Pkcs7),
+                        Padding::Zero => ecb::decrypt_with_padding(cipher, ciphertext, &ZeroPadding)
+                    }
+                },
+                Mode::Cbc => {
+                    let iv = Self::validate_iv(iv, 16)?.try_into().unwrap();
+                    match self.padding {
+                        Padding::None => cbc::decrypt_unpadded(cipher, &iv, ciphertext),
+                        Padding::Pkcs7 => cbc::decrypt_with_padding(cipher, &iv, ciphertext, &Pkcs7),
+                        Padding::Zero => cbc::decrypt_with_padding(cipher, &iv, ciphertext, &ZeroPadding)
+                    }
+                },
+                Mode::Ctr => ctr::apply_keystream_with_layout(cipher, Self::validate_iv(iv, 16)?.try_into().unwrap(), self.counter_layout, ciphertext),
+                _ => Ok(cfb::decrypt128(cipher, Self::validate_iv(iv, 16)?.try_into().unwrap(), ciphertext))
+            }
+        }
+    }
+
+    ///
+    /// Validates the length of the iv.
+    /// 
+    fn validate_iv(iv: &[u8], length: usize) -> Result<&[u8], AesError> {
+        if iv.len() != length {
+            return Err(AesError::InvalidNonceLength { length: iv.len(), min: length, max: length });
+        }
+        Ok(iv)
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::test_util::hex;
+
+    const KEY: &str = "2b7e151628aed2a6abf7158809cf4f3c";
+    const IV: &str = "000102030405060708090a0b0c0d0e0f";
+    const PLAINTEXT: &str = "6bc1bee22e409f96e93d7e117393172a";
+
+    fn key() -> [u8; 16] {
+        hex(KEY).try_into().unwrap()
+    }
+
+    #[test]
+    fn test_sp800_38a_vectors() {
+        let cases = [
+            (Mode::Ecb, "", "3ad77bb40d7a3660a89ecaf32466ef97"),
+            (Mode::Cbc, IV, "7649abac8119b246cee98e9b12e9197d"),
+            (Mode::Cfb, IV, "3b3fd92eb72dad20333449f8e83cfb4a"),
+            (Mode::Ctr, "f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff", "874d6191b620e3261bef6864990db6ce")
+        ];
+        for (mode, iv, ciphertext) in cases {
+            let builder = Aes::builder().key(&key()).mode(mode);
+            let cipher = if matches!(mode, Mode::Ecb | Mode::Cbc) { builder.padding(Padding::None) } else { builder }.build().unwrap();
+            assert_eq!(mode, cipher.mode());
+            assert_eq!(hex(ciphertext), cipher.encrypt(&hex(iv), &hex(PLAINTEXT)).unwrap());
+            assert_eq!(hex(PLAINTEXT), cipher.decrypt(&hex(iv), &hex(ciphertext)).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_padding() {
+        let cipher = Aes::builder().key(&key()).build().unwrap();
+        assert_eq!(Mode::Cbc, cipher.mode());
+        let expected = cbc::encrypt_with_padding(&Aes::new(&key()), &[1; 16], b"attack at dawn", &Pkcs7);
+        assert_eq!(expected, cipher.encrypt(&[1; 16], b"attack at dawn").unwrap());
+        assert_eq!(b"attack at dawn".to_vec(), cipher.decrypt(&[1; 16], &expected).unwrap());
+        let cipher = Aes::builder().key(&key()).mode(Mode::Ecb).padding(Padding::Zero).build().unwrap();
+        assert_eq!(16, cipher.encrypt(&[], b"data").unwrap().len());
+        let cipher = Aes::builder().key(&key()).mode(Mode::Ecb).padding(Padding::None).build().unwrap();
+        assert_eq!(Some(AesError::InvalidDataLength { length: 4, min: 0, multiple: 16 }), cipher.encrypt(&[], b"data").err());
+    }
+
+    #[test]
+    fn test_gcm() {
+        let cipher = Aes::builder().key(&[3; 32]).mode(Mode::Gcm).tag_length(12).build().unwrap();
+        let ciphertext = cipher.encrypt(&[7; 12], b"data").unwrap();
+        assert_eq!(Gcm::new(&[3; 32], 12).unwrap().encrypt(&[7; 12], &[], b"data").unwrap(), ciphertext);
+        assert_eq!(b"data".to_vec(), cipher.decrypt(&[7; 12], &ciphertext).unwrap());
+        assert_eq!(Some(AesError::TagTooShort), Aes::builder().key(&[3; 32]).mode(Mode::Gcm).tag_length(8).build().err());
+    }
+
+    #[test]
+    fn test_counter_layout() {
+        let cipher = Aes::builder().key(&key()).mode(Mode::Ctr).counter_layout(CounterLayout::Nonce96Counter32).build().unwrap();
+        let counter = [0xff; 16];
+        assert_eq!(Some(AesError::CounterOverflow), cipher.encrypt(&counter, &[0; 32]).err());
+    }
+
+    #[test]
+    fn test_invalid_configuration() {
+        assert_eq!(Some(AesError::InvalidConfiguration), CipherBuilder::<16>::new().build().err());
+        assert_eq!(Some(AesError::InvalidConfiguration), Aes::builder().key(&key()).mode(Mode::Ctr).padding(Padding::Pkcs7).build().err());
+        assert_eq!(Some(AesError::InvalidConfiguration), Aes::builder().key(&key()).tag_length(16).build().err());
+        assert_eq!(Some(AesError::InvalidConfiguration), Aes::builder().key(&key()).counter_layout(CounterLayout::Counter128).build().err());
+        let cipher = Aes::builder().key(&key()).build().unwrap();
+        assert_eq!(Some(AesError::InvalidNonceLength { length: 12, min: 16, max: 16 }), cipher.encrypt(&[0; 12], b"data").err());
+    }
+
+}
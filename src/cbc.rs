@@ -0,0 +1,917 @@
+//!
+//! Cipher block chaining (CBC) mode. Each plaintext block is xored with the
+//! previous ciphertext block before it is encrypted. The first block is xored
+//! with the initialization vector.
+//! 
+//! The iv must be unpredictable and never reused with the same key. The mode
+//! gives confidentiality only, the ciphertext is not authenticated. The
+//! GuardedEncryptor consumes its iv on use, so the same iv cannot be used twice
+//! by mistake. The StreamEncryptor and StreamDecryptor accept the data in chunks
+//! of any size and buffer partial blocks until finalize.
+//! 
+//! encrypt and decrypt use PKCS#7 padding, other schemes can be used with 
+//! encrypt_with_padding and decrypt_with_padding.
+//! 
+//! Ciphertext stealing (CTS) encrypts data of any length of at least 16 bytes
+//! without padding, so the ciphertext has the same length as the plaintext. The
+//! three variants from the addendum to NIST SP 800-38A differ only in the order
+//! of the last two ciphertext blocks.
+//! 
+
+use std::vec::Vec;
+
+use crate::block_mode::{finalize_full_blocks, output_prefix};
+use crate::padding::{self, Padding, Pkcs7};
+use crate::{Aes, AesError, BlockMode};
+#[cfg(feature = "encrypt")]
+use crate::{FreshNonceState, UsedNonceState};
+
+///
+/// The ciphertext stealing variant, which decides the order of the last two 
+/// ciphertext blocks.
+/// 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CtsVariant {
+    ///
+    /// The partial block comes before the last full block.
+    /// 
+    Cs1,
+    ///
+    /// The last two blocks are swapped only when the last block is partial, so
+    /// aligned data gives the same ciphertext as plain CBC.
+    /// 
+    Cs2,
+    ///
+    /// The last two blocks are always swapped. Used by Kerberos (RFC 3962).
+    /// 
+    Cs3
+}
+
+///
+/// Pads the plaintext with PKCS#7 and encrypts it.
+/// 
+/// cipher: The cipher used to encrypt each block.
+/// iv: The 16 byte initialization vector.
+/// plaintext: The data to encrypt. May be of any length.
+/// 
+/// result: The encrypted data. Always 1 to 16 bytes longer than the plaintext.
+/// 
+#[cfg(feature = "encrypt")]
+pub fn encrypt<const KEY_BYTES: usize>(cipher: &Aes<KEY_BYTES>, iv: &[u8; 16], plaintext: &[u8]) -> Vec<u8> {
+    encrypt_with_padding(cipher, iv, plaintext, &Pkcs7)
+}
+
+///
+/// Decrypts the ciphertext and removes the PKCS#7 padding.
+/// 
+/// cipher: The cipher used to decrypt each block.
+/// iv: The 16 byte initialization vector used when encrypting.
+/// ciphertext: The data to decrypt. Must be a non empty multiple of 16 bytes.
+/// 
+/// result: The decrypted data, AesError::InvalidDataLength if the ciphertext is
+///         empty or not a multiple of 16 bytes or AesError::InvalidPadding if
+///         the padding is not valid.
+/// 
+#[cfg(feature = "decrypt")]
+pub fn decrypt<const KEY_BYTES: usize>(cipher: &Aes<KEY_BYTES>, iv: &[u8; 16], ciphertext: &[u8]) -> Result<Vec<u8>, AesError> {
+    if ciphertext.is_empty() || !ciphertext.len().is_multiple_of(16) {
+        return Err(AesError::InvalidDataLength { length: ciphertext.len(), min: 16, multiple: 16 });
+    }
+    decrypt_with_padding(cipher, iv, ciphertext, &Pkcs7)
+}
+
+///
+/// Pads the plaintext with the padding scheme and encrypts it.
+/// 
+/// cipher: The cipher used to encrypt each block.
+/// iv: The 16 byte initialization vector.
+/// plaintext: The data to encrypt. May be of any length.
+/// padding: The padding scheme.
+/// 
+/// result: The encrypted data.
+/// 
+#[cfg(feature = "encrypt")]
+pub fn encrypt_with_padding<const KEY_BYTES: usize, P: Padding>(cipher: &Aes<KEY_BYTES>, iv: &[u8; 16], plaintext: &[u8], padding: &P) -> Vec<u8> {
+    let mut encrypted = Vec::new();
+    encrypt_padded_into(cipher, iv, plaintext, padding, &mut encrypted);
+    encrypted
+}
+
+///
+/// Decrypts the ciphertext and removes the padding of the padding scheme.
+/// 
+/// cipher: The cipher used to decrypt each block.
+/// iv: The 16 byte initialization vector used when encrypting.
+/// ciphertext: The data to decrypt. Must be a multiple of 16 bytes.
+/// padding: The padding scheme used when encrypting.
+/// 
+/// result: The decrypted data, AesError::InvalidDataLength if the ciphertext is
+///         not a multiple of 16 bytes or AesError::InvalidPadding if the padding
+///         is not valid.
+/// 
+#[cfg(feature = "decrypt")]
+pub fn decrypt_with_padding<const KEY_BYTES: usize, P: Padding>(cipher: &Aes<KEY_BYTES>, iv: &[u8; 16], ciphertext: &[u8], padding: &P) -> Result<Vec<u8>, AesError> {
+    let mut decrypted = Vec::new();
+    decrypt_padded_into(cipher, iv, ciphertext, padding, &mut decrypted)?;
+    Ok(decrypted)
+}
+
+///
+/// Encrypts the data without padding.
+/// 
+/// cipher: The cipher used to encrypt each block.
+/// iv: The 16 byte initialization vector.
+/// data: The data to encrypt. Must be a multiple of 16 bytes.
+/// 
+/// result: The encrypted data or AesError::InvalidDataLength if the data is not
+///         a multiple of 16 bytes.
+/// 
+#[cfg(feature = "encrypt")]
+pub fn encrypt_unpadded<const KEY_BYTES: usize>(cipher: &Aes<KEY_BYTES>, iv: &[u8; 16], data: &[u8]) -> Result<Vec<u8>, AesError> {
+    Encryptor::init(cipher, iv).process(data)
+}
+
+///
+/// Decrypts the data without removing any padding.
+/// 
+/// cipher: The cipher used to decrypt each block.
+/// iv: The 16 byte initialization vector used when encrypting.
+/// data: The data to decrypt. Must be a multiple of 16 bytes.
+/// 
+/// result: The decrypted data or AesError::InvalidDataLength if the data is not
+///         a multiple of 16 bytes.
+/// 
+#[cfg(feature = "decrypt")]
+pub fn decrypt_unpadded<const KEY_BYTES: usize>(cipher: &Aes<KEY_BYTES>, iv: &[u8; 16], data: &[u8]) -> Result<Vec<u8>, AesError> {
+    Decryptor::init(cipher, iv).process(data)
+}
+
+///
+/// Pads the plaintext with PKCS#7 and encrypts it into an output buffer. The 
+/// output is cleared first, so its capacity is reused and no memory is allocated
+/// once it is large enough.
+/// 
+/// cipher: The cipher used to encrypt each block.
+/// iv: The 16 byte initialization vector.
+/// plaintext: The data to encrypt. May be of any length.
+/// output: The buffer replaced by the encrypted data.
+/// 
+#[cfg(feature = "encrypt")]
+pub fn encrypt_into<const KEY_BYTES: usize>(cipher: &Aes<KEY_BYTES>, iv: &[u8; 16], plaintext: &[u8], output: &mut Vec<u8>) {
+    encrypt_padded_into(cipher, iv, plaintext, &Pkcs7, output);
+}
+
+///
+/// Decrypts the ciphertext into an output buffer and removes the PKCS#7 padding.
+/// The output is cleared first, so its capacity is reused and no memory is 
+/// allocated once it is large enough.
+/// 
+/// cipher: The cipher used to decrypt each block.
+/// iv: The 16 byte initialization vector used when encrypting.
+/// ciphertext: The data to decrypt. Must be a non empty multiple of 16 bytes.
+/// output: The buffer replaced by the decrypted data. Left empty on an error.
+/// 
+/// result: Ok, AesError::InvalidDataLength if the ciphertext is empty or not a 
+///         multiple of 16 bytes or AesError::InvalidPadding if the padding is 
+///         not valid.
+/// 
+#[cfg(feature = "decrypt")]
+pub fn decrypt_into<const KEY_BYTES: usize>(cipher: &Aes<KEY_BYTES>, iv: &[u8; 16], ciphertext: &[u8], output: &mut Vec<u8>) -> Result<(), AesError> {
+    if ciphertext.is_empty() || !ciphertext.len().is_multiple_of(16) {
+        output.clear();
+        return Err(AesError::InvalidDataLength { length: ciphertext.len(), min: 16, multiple: 16 });
+    }
+    decrypt_padded_into(cipher, iv, ciphertext, &Pkcs7, output)
+}
+
+///
+/// Pads the plaintext with PKCS#7 and encrypts it into the start of an output 
+/// slice.
+/// 
+/// cipher: The cipher used to encrypt each block.
+/// iv: The 16 byte initialization vector.
+/// plaintext: The data to encrypt. May be of any length.
+/// output: The slice the encrypted data is written to. Must have room for the 
+///         padded plaintext, the length rounded up to the next multiple of 16.
+/// 
+/// result: The number of bytes written or AesError::OutputTooShort if the output
+///         is shorter than the padded plaintext.
+/// 
+#[cfg(feature = "encrypt")]
+pub fn encrypt_into_slice<const KEY_BYTES: usize>(cipher: &Aes<KEY_BYTES>, iv: &[u8; 16], plaintext: &[u8], output: &mut [u8]) -> Result<usize, AesError> {
+    let length = plaintext.len() / 16 * 16 + 16;
+    let output = output_prefix(output, length)?;
+    output[..plaintext.len()].copy_from_slice(plaintext);
+    output[plaintext.len()..].fill((length - plaintext.len()) as u8);
+    encrypt_chained(cipher, iv, output);
+    Ok(length)
+}
+
+///
+/// Decrypts the ciphertext into the start of an output slice and removes the 
+/// PKCS#7 padding. The padding is written to the output before it is removed, so
+/// the output must be as long as the ciphertext.
+/// 
+/// cipher: The cipher used to decrypt each block.
+/// iv: The 16 byte initialization vector used when encrypting.
+/// ciphertext: The data to decrypt. Must be a non empty multiple of 16 bytes.
+/// output: The slice the decrypted data is written to. Zeroed on a padding error.
+/// 
+/// result: The length of the decrypted data without the padding, 
+///         AesError::InvalidDataLength if the ciphertext is empty or not a 
+///         multiple of 16 bytes, AesError::OutputTooShort if the output is 
+///         shorter than the ciphertext or AesError::InvalidPadding if the 
+///         padding is not valid.
+/// 
+#[cfg(feature = "decrypt")]
+pub fn decrypt_into_slice<const KEY_BYTES: usize>(cipher: &Aes<KEY_BYTES>, iv: &[u8; 16], ciphertext: &[u8], output: &mut [u8]) -> Result<usize, AesError> {
+    if ciphertext.is_empty() || !ciphertext.len().is_multiple_of(16) {
+        return Err(AesError::InvalidDataLength { length: ciphertext.len(), min: 16, multiple: 16 });
+    }
+    let output = output_prefix(output, ciphertext.len())?;
+    output.copy_from_slice(ciphertext);
+    decrypt_chained(cipher, iv, ciphertext, output);
+    padding::unpad(output).map(<[u8]>::len).inspect_err(|_| output.fill(0))
+}
+
+///
+/// GuardedEncryptor is a cbc encryptor that can only encrypt once per iv. 
+/// Encrypting consumes the encryptor and returns it in the UsedNonceState, which
+/// must get a new iv with with_fresh_nonce before it can encrypt again.
+/// 
+#[cfg(feature = "encrypt")]
+pub struct GuardedEncryptor<'a, const KEY_BYTES: usize, State = FreshNonceState> {
+    cipher: &'a Aes<KEY_BYTES>,
+    iv: [u8; 16],
+    state: std::marker::PhantomData<State>
+}
+
+#[cfg(feature = "encrypt")]
+impl<'a, const KEY_BYTES: usize> GuardedEncryptor<'a, KEY_BYTES, FreshNonceState> {
+
+    ///
+    /// Creates a new GuardedEncryptor.
+    /// 
+    /// cipher: The cipher used to encrypt each block.
+    /// iv: The 16 byte initialization vector for the next encryption.
+    /// 
+    /// result: A GuardedEncryptor ready to encrypt.
+    /// 
+    pub fn new(cipher: &'a Aes<KEY_BYTES>, iv: &[u8; 16]) -> Self {
+        GuardedEncryptor { cipher, iv: *iv, state: std::marker::PhantomData::<FreshNonceState> }
+    }
+
+    ///
+    /// Pads the plaintext with PKCS#7 and encrypts it with the iv.
+    /// 
+    /// plaintext: The data to encrypt. May be of any length.
+    /// 
+    /// result: The encrypted data and the encryptor with the iv marked as used.
+    /// 
+    pub fn encrypt(self, plaintext: &[u8]) -> (Vec<u8>, GuardedEncryptor<'a, KEY_BYTES, UsedNonceState>) {
+        let encrypted = encrypt(self.cipher, &self.iv, plaintext);
+        (encrypted, GuardedEncryptor { cipher: self.cipher, iv: self.iv, state: std::marker::PhantomData::<UsedNonceState> })
+    }
+
+}
+
+#[cfg(feature = "encrypt")]
+impl<'a, const KEY_BYTES: usize> GuardedEncryptor<'a, KEY_BYTES, UsedNonceState> {
+
+    ///
+    /// Replaces the used iv with a new one.
+    /// 
+    /// iv: The 16 byte initialization vector for the next encryption.
+    /// 
+    /// result: The encryptor ready to encrypt or AesError::NonceReused if the iv is
+    ///         the same as the one already used.
+    /// 
+    pub fn with_fresh_nonce(self, iv: &[u8; 16]) -> Result<GuardedEncryptor<'a, KEY_BYTES, FreshNonceState>, AesError> {
+        if *iv == self.iv {
+            return Err(AesError::NonceReused);
+        }
+        Ok(GuardedEncryptor::new(self.cipher, iv))
+    }
+
+}
+
+///
+/// Encryptor is the cbc encryption as a BlockMode. Each output block is chained 
+/// into the next input block.
+/// 
+#[cfg(feature = "encrypt")]
+pub struct Encryptor<'a, const KEY_BYTES: usize> {
+    cipher: &'a Aes<KEY_BYTES>,
+    previous: [u8; 16]
+}
+
+#[cfg(feature = "encrypt")]
+impl<'a, const KEY_BYTES: usize> BlockMode<'a, KEY_BYTES> for Encryptor<'a, KEY_BYTES> {
+
+    fn init(cipher: &'a Aes<KEY_BYTES>, iv: &[u8; 16]) -> Self {
+        Self { cipher, previous: *iv }
+    }
+
+    fn process_block(&mut self, block: &[u8; 16]) -> [u8; 16] {
+        self.previous = self.cipher.encrypt_bytes(&xor(block, &self.previous)).try_into().unwrap();
+        self.previous
+    }
+
+    fn finalize(self, remaining: &[u8]) -> Result<Vec<u8>, AesError> {
+        finalize_full_blocks(remaining)
+    }
+
+}
+
+///
+/// Decryptor is the cbc decryption as a BlockMode. Each input block is chained 
+/// into the next output block.
+/// 
+#[cfg(feature = "decrypt")]
+pub struct Decryptor<'a, const KEY_BYTES: usize> {
+    cipher: &'a Aes<KEY_BYTES>,
+    previous: [u8; 16]
+}
+
+#[cfg(feature = "decrypt")]
+impl<'a, const KEY_BYTES: usize> BlockMode<'a, KEY_BYTES> for Decryptor<'a, KEY_BYTES> {
+
+    fn init(cipher: &'a Aes<KEY_BYTES>, iv: &[u8; 16]) -> Self {
+        Self { cipher, previous: *iv }
+    }
+
+    fn process_block(&mut self, block: &[u8; 16]) -> [u8; 16] {
+        let decrypted = xor(&self.cipher.decrypt_bytes(block), &self.previous).try_into().unwrap();
+        self.previous = *block;
+        decrypted
+    }
+
+    fn finalize(self, remaining: &[u8]) -> Result<Vec<u8>, AesError> {
+        finalize_full_blocks(remaining)
+    }
+
+}
+
+///
+/// StreamEncryptor is an incremental cbc encryptor with PKCS#7 padding. Data is
+/// given in chunks with update, and the padded last block is returned by finalize.
+/// 
+#[cfg(feature = "encrypt")]
+pub struct StreamEncryptor<'a, const KEY_BYTES: usize> {
+    encryptor: Encryptor<'a, KEY_BYTES>,
+    buffer: Vec<u8>
+}
+
+#[cfg(feature = "encrypt")]
+impl<'a, const KEY_BYTES: usize> StreamEncryptor<'a, KEY_BYTES> {
+
+    ///
+    /// Creates a new StreamEncryptor.
+    /// 
+    /// cipher: The cipher used to encrypt each block.
+    /// iv: The 16 byte initialization vector.
+    /// 
+    /// result: A StreamEncryptor without any data.
+    /// 
+    pub fn new(cipher: &'a Aes<KEY_BYTES>, iv: &[u8; 16]) -> Self {
+        Self { encryptor: Encryptor::init(cipher, iv), buffer: Vec::with_capacity(16) }
+    }
+
+    ///
+    /// Encrypts all full blocks available and buffers the rest.
+    /// 
+    /// data: The next chunk of the plaintext. May be of any length.
+    /// 
+    /// result: The encrypted blocks, a multiple of 16 bytes.
+    /// 
+    pub fn update(&mut self, data: &[u8]) -> Vec<u8> {
+        self.buffer.extend_from_slice(data);
+        let available = self.buffer.len() - self.buffer.len() % 16;
+        process_buffered(&mut self.encryptor, &mut self.buffer, available)
+    }
+
+    ///
+    /// Pads the buffered data and encrypts it.
+    /// 
+    /// result: The last encrypted block.
+    /// 
+    pub fn finalize(mut self) -> Vec<u8> {
+        let mut padded = padding::pad(&self.buffer);
+        let length = padded.len();
+        process_buffered(&mut self.encryptor, &mut padded, length)
+    }
+
+}
+
+///
+/// StreamDecryptor is an incremental cbc decryptor that removes the PKCS#7 padding.
+/// The last full block is kept back by update, since it holds the padding.
+/// 
+#[cfg(feature = "decrypt")]
+pub struct StreamDecryptor<'a, const KEY_BYTES: usize> {
+    decryptor: Decryptor<'a, KEY_BYTES>,
+    buffer: Vec<u8>,
+    length: usize
+}
+
+#[cfg(feature = "decrypt")]
+impl<'a, const KEY_BYTES: usize> StreamDecryptor<'a, KEY_BYTES> {
+
+    ///
+    /// Creates a new StreamDecryptor.
+    /// 
+    /// cipher: The cipher used to decrypt each block.
+    /// iv: The 16 byte initialization vector used when encrypting.
+    /// 
+    /// result: A StreamDecryptor without any data.
+    /// 
+    pub fn new(cipher: &'a Aes<KEY_BYTES>, iv: &[u8; 16]) -> Self {
+        Self { decryptor: Decryptor::init(cipher, iv), buffer: Vec::with_capacity(32), length: 0 }
+    }
+
+    ///
+    /// Decrypts all full blocks available except the last one and buffers the rest.
+    /// 
+    /// data: The next chunk of the ciphertext. May be of any length.
+    /// 
+    /// result: The decrypted blocks, a multiple of 16 bytes.
+    /// 
+    pub fn update(&mut self, data: &[u8]) -> Vec<u8> {
+        self.buffer.extend_from_slice(data);
+        self.length += data.len();
+        let available = self.buffer.len().saturating_sub(1) / 16 * 16;
+        process_buffered(&mut self.decryptor, &mut self.buffer, available)
+    }
+
+    ///
+    /// Decrypts the last block and removes the padding.
+    /// 
+    /// result: The rest of the decrypted data, AesError::InvalidDataLength if the 
+    ///         ciphertext was empty or not a multiple of 16 bytes or 
+    ///         AesError::InvalidPadding if the padding is not valid.
+    /// 
+    pub fn finalize(mut self) -> Result<Vec<u8>, AesError> {
+        if self.buffer.len() != 16 {
+            return Err(AesError::InvalidDataLength { length: self.length, min: 16, multiple: 16 });
+        }
+        let mut decrypted = process_buffered(&mut self.decryptor, &mut self.buffer, 16);
+        let length = padding::unpad(&decrypted)?.len();
+        decrypted.truncate(length);
+        Ok(decrypted)
+    }
+
+}
+
+///
+/// Processes the first length bytes of the buffer, which must be a multiple of 16,
+/// and removes them from the buffer.
+/// 
+fn process_buffered<'a, const KEY_BYTES: usize>(mode: &mut impl BlockMode<'a, KEY_BYTES>, buffer: &mut Vec<u8>, length: usize) -> Vec<u8> {
+    let result = buffer[..length].chunks_exact(16).flat_map(|block| mode.process_block(block.try_into().unwrap())).collect();
+    buffer.drain(..length);
+    result
+}
+
+///
+/// Encrypts the data with ciphertext stealing.
+/// 
+/// cipher: The cipher used to encrypt each block.
+/// iv: The 16 byte initialization vector.
+/// data: The data to encrypt. Must be at least 16 bytes.
+/// variant: The order of the last two ciphertext blocks.
+/// 
+/// result: The encrypted data, with the same length as the data, or 
+///         AesError::InvalidDataLength if the data is shorter than 16 bytes.
+/// 
+#[cfg(feature = "encrypt")]
+pub fn encrypt_cts<const KEY_BYTES: usize>(cipher: &Aes<KEY_BYTES>, iv: &[u8; 16], data: &[u8], variant: CtsVariant) -> Result<Vec<u8>, AesError> {
+    if data.len() < 16 {
+        return Err(AesError::InvalidDataLength { length: data.len(), min: 16, multiple: 1 });
+    }
+    let (blocks, last_length) = cts_layout(data.len());
+    let mut padded = data.to_vec();
+    padded.resize(blocks * 16, 0);
+    let mut encrypted = encrypt_blocks(cipher, iv, &padded);
+    if blocks > 1 {
+        let last = encrypted.split_off((blocks - 1) * 16);
+        let mut stolen = encrypted.split_off((blocks - 2) * 16);
+        stolen.truncate(last_length);
+        if is_swapped(variant, last_length) {
+            encrypted.extend(last);
+            encrypted.extend(stolen);
+        } else {
+            encrypted.extend(stolen);
+            encrypted.extend(last);
+        }
+    }
+    Ok(encrypted)
+}
+
+///
+/// Decrypts data encrypted with ciphertext stealing.
+/// 
+/// cipher: The cipher used to decrypt each block.
+/// iv: The 16 byte initialization vector used when encrypting.
+/// data: The data to decrypt. Must be at least 16 bytes.
+/// variant: The order of the last two ciphertext blocks used when encrypting.
+/// 
+/// result: The decrypted data, with the same length as the data, or 
+///         AesError::InvalidDataLength if the data is shorter than 16 bytes.
+/// 
+#[cfg(feature = "decrypt")]
+pub fn decrypt_cts<const KEY_BYTES: usize>(cipher: &Aes<KEY_BYTES>, iv: &[u8; 16], data: &[u8], variant: CtsVariant) -> Result<Vec<u8>, AesError> {
+    if data.len() < 16 {
+        return Err(AesError::InvalidDataLength { length: data.len(), min: 16, multiple: 1 });
+    }
+    let (blocks, last_length) = cts_layout(data.len());
+    if blocks == 1 {
+        return decrypt_unpadded(cipher, iv, data);
+    }
+    let (chained, tail) = data.split_at((blocks - 2) * 16);
+    let (stolen, last) = if is_swapped(variant, last_length) {
+        let (last, stolen) = tail.split_at(16);
+        (stolen, last)
+    } else {
+        tail.split_at(last_length)
+    };
+    let previous: &[u8] = if chained.is_empty() { iv } else { &chained[chained.len() - 16..] };
+    let intermediate = cipher.decrypt_bytes(last);
+    let mut second_last = stolen.to_vec();
+    second_last.extend_from_slice(&intermediate[last_length..]);
+    let mut decrypted = decrypt_unpadded(cipher, iv, chained)?;
+    decrypted.extend(xor(&cipher.decrypt_bytes(&second_last), previous));
+    decrypted.extend(xor(&intermediate[..last_length], stolen));
+    Ok(decrypted)
+}
+
+///
+/// Finds the number of blocks and the length of the last, possibly partial, block.
+/// 
+fn cts_layout(length: usize) -> (usize, usize) {
+    let blocks = length.div_ceil(16);
+    (blocks, length - (blocks - 1) * 16)
+}
+
+///
+/// Whether the last two ciphertext blocks are swapped for the variant.
+/// 
+fn is_swapped(variant: CtsVariant, last_length: usize) -> bool {
+    match variant {
+        CtsVariant::Cs1 => false,
+        CtsVariant::Cs2 => last_length < 16,
+        CtsVariant::Cs3 => true
+    }
+}
+
+///
+/// Chains and encrypts the blocks. The data is expected to be a multiple of 16 bytes.
+/// 
+#[cfg(feature = "encrypt")]
+fn encrypt_blocks<const KEY_BYTES: usize>(cipher: &Aes<KEY_BYTES>, iv: &[u8; 16], data: &[u8]) -> Vec<u8> {
+    let mut encrypted = data.to_vec();
+    encrypt_chained(cipher, iv, &mut encrypted);
+    encrypted
+}
+
+///
+/// Chains and encrypts the blocks in place. The data is expected to be a multiple
+/// of 16 bytes.
+/// 
+#[cfg(feature = "encrypt")]
+fn encrypt_chained<const KEY_BYTES: usize>(cipher: &Aes<KEY_BYTES>, iv: &[u8; 16], data: &mut [u8]) {
+    let mut previous = *iv;
+    for block in data.as_chunks_mut::<16>().0 {
+        block.iter_mut().zip(previous).for_each(|(byte, chained)| *byte ^= chained);
+        cipher.encrypt_block_in_place(block);
+        previous = *block;
+    }
+}
+
+///
+/// Decrypts a copy of the ciphertext in place. The blocks do not depend on each
+/// other when decrypting, so they are decrypted in one call to Aes::decrypt_blocks
+/// and then xored with the previous ciphertext blocks.
+/// 
+/// ciphertext: The ciphertext, a multiple of 16 bytes.
+/// data: The copy of the ciphertext, replaced by the decrypted data.
+/// 
+#[cfg(feature = "decrypt")]
+fn decrypt_chained<const KEY_BYTES: usize>(cipher: &Aes<KEY_BYTES>, iv: &[u8; 16], ciphertext: &[u8], data: &mut [u8]) {
+    cipher.decrypt_blocks(data).unwrap();
+    let previous = std::iter::once(iv.as_slice()).chain(ciphertext.chunks_exact(16));
+    for (block, previous) in data.chunks_exact_mut(16).zip(previous) {
+        block.iter_mut().zip(previous).for_each(|(byte, chained)| *byte ^= chained);
+    }
+}
+
+///
+/// Pads the plaintext with the padding scheme and encrypts it into the output, 
+/// which is cleared first.
+/// 
+#[cfg(feature = "encrypt")]
+fn encrypt_padded_into<const KEY_BYTES: usize, P: Padding>(cipher: &Aes<KEY_BYTES>, iv: &[u8; 16], plaintext: &[u8], padding: &P, output: &mut Vec<u8>) {
+    let full = plaintext.len() / 16 * 16;
+    output.clear();
+    output.extend_from_slice(&plaintext[..full]);
+    output.extend_from_slice(&padding.pad_block(&plaintext[full..]));
+    encrypt_chained(cipher, iv, output);
+}
+
+///
+/// Decrypts the ciphertext into the output, which is cleared first, and removes 
+/// the padding of the padding scheme. The output is left empty on an error.
+/// 
+#[cfg(feature = "decrypt")]
+fn decrypt_padded_into<const KEY_BYTES: usize, P: Padding>(cipher: &Aes<KEY_BYTES>, iv: &[u8; 16], ciphertext: &[u8], padding: &P, output: &mut Vec<u8>) -> Result<(), AesError> {
+    output.clear();
+    if !ciphertext.len().is_multiple_of(16) {
+        return Err(AesError::InvalidDataLength { length: ciphertext.len(), min: 0, multiple: 16 });
+    }
+    output.extend_from_slice(ciphertext);
+    decrypt_chained(cipher, iv, ciphertext, output);
+    let length = padding::unpad_with(padding, output).map(<[u8]>::len).inspect_err(|_| output.clear())?;
+    output.truncate(length);
+    Ok(())
+}
+
+///
+/// Pads the plaintext with PKCS#7 and encrypts it over any block encryption, such
+/// as a key held by a KeyBackend.
+/// 
+/// encrypt: Encrypts a 16 byte block with the key.
+/// iv: The 16 byte initialization vector.
+/// plaintext: The data to encrypt. May be of any length.
+/// 
+/// result: The encrypted data or the error of encrypt.
+/// 
+#[cfg(all(feature = "encrypt", feature = "decrypt"))]
+pub(crate) fn encrypt_using<F: Fn(&[u8; 16]) -> Result<[u8; 16], AesError>>(encrypt: F, iv: &[u8; 16], plaintext: &[u8]) -> Result<Vec<u8>, AesError> {
+    let mut encrypted = padding::pad_with(&Pkcs7, plaintext);
+    let mut previous = *iv;
+    for block in encrypted.as_chunks_mut::<16>().0 {
+        block.iter_mut().zip(previous).for_each(|(byte, chained)| *byte ^= chained);
+        *block = encrypt(block)?;
+        previous = *block;
+    }
+    Ok(encrypted)
+}
+
+///
+/// Decrypts the ciphertext over any block decryption and removes the PKCS#7 
+/// padding.
+/// 
+/// decrypt: Decrypts a 16 byte block with the key.
+/// iv: The 16 byte initialization vector used when encrypting.
+/// ciphertext: The data to decrypt. Must be a non empty multiple of 16 bytes.
+/// 
+/// result: The decrypted data, AesError::InvalidDataLength if the ciphertext is
+///         empty or not a multiple of 16 bytes, AesError::InvalidPadding if the 
+///         padding is not valid or the error of decrypt.
+/// 
+#[cfg(all(feature = "encrypt", feature = "decrypt"))]
+pub(crate) fn decrypt_using<F: Fn(&[u8; 16]) -> Result<[u8; 16], AesError>>(decrypt: F, iv: &[u8; 16], ciphertext: &[u8]) -> Result<Vec<u8>, AesError> {
+    if ciphertext.is_empty() || !ciphertext.len().is_multiple_of(16) {
+        return Err(AesError::InvalidDataLength { length: ciphertext.len(), min: 16, multiple: 16 });
+    }
+    let mut decrypted = Vec::with_capacity(ciphertext.len());
+    let mut previous = iv;
+    for block in ciphertext.as_chunks::<16>().0 {
+        decrypted.extend(decrypt(block)?.iter().zip(previous).map(|(byte, chained)| byte ^ chained));
+        previous = block;
+    }
+    let length = padding::unpad_with(&Pkcs7, &decrypted)?.len();
+    decrypted.truncate(length);
+    Ok(decrypted)
+}
+
+///
+/// Xors two blocks of equal length.
+/// 
+fn xor(block: &[u8], other: &[u8]) -> Vec<u8> {
+    block.iter().zip(other).map(|(a, b)| a ^ b).collect()
+}
+
+#[cfg(all(test, feature = "encrypt", feature = "decrypt"))]
+mod tests {
+
+    use super::*;
+    use crate::test_util::hex;
+    use crate::{Aes128, Aes192, Aes256};
+
+    const IV: [u8; 16] = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15];
+    const PLAINTEXT: &str = "6bc1bee22e409f96e93d7e117393172a ae2d8a571e03ac9c9eb76fac45af8e51
+                             30c81c46a35ce411e5fbc1191a0a52ef f69f2445df4f9b17ad2b417be66c3710";
+
+    #[test]
+    fn test_cbc_aes128() {
+        let cipher = Aes128::new(&hex("2b7e151628aed2a6abf7158809cf4f3c").try_into().unwrap());
+        let expected = hex("7649abac8119b246cee98e9b12e9197d 5086cb9b507219ee95db113a917678b2
+                            73bed6b8e3c1743b7116e69e22229516 3ff1caa1681fac09120eca307586e1a7");
+        assert_eq!(expected, encrypt_unpadded(&cipher, &IV, &hex(PLAINTEXT)).unwrap());
+        assert_eq!(hex(PLAINTEXT), decrypt_unpadded(&cipher, &IV, &expected).unwrap());
+    }
+
+    #[test]
+    fn test_cbc_aes192() {
+        let cipher = Aes192::new(&hex("8e73b0f7da0e6452c810f32b809079e562f8ead2522c6b7b").try_into().unwrap());
+        let expected = hex("4f021db243bc633d7178183a9fa071e8 b4d9ada9ad7dedf4e5e738763f69145a
+                            571b242012fb7ae07fa9baac3df102e0 08b0e27988598881d920a9e64f5615cd");
+        assert_eq!(expected, encrypt_unpadded(&cipher, &IV, &hex(PLAINTEXT)).unwrap());
+        assert_eq!(hex(PLAINTEXT), decrypt_unpadded(&cipher, &IV, &expected).unwrap());
+    }
+
+    #[test]
+    fn test_cbc_aes256() {
+        let cipher = Aes256::new(&hex("603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4").try_into().unwrap());
+        let expected = hex("f58c4c04d6e5f1ba779eabfb5f7bfbd6 9cfc4e967edb808d679f777bc6702c7d
+                            39f23369a9d9bacfa530e26304231461 b2eb05e2c39be9fcda6c19078c6a9d1b");
+        assert_eq!(expected, encrypt_unpadded(&cipher, &IV, &hex(PLAINTEXT)).unwrap());
+        assert_eq!(hex(PLAINTEXT), decrypt_unpadded(&cipher, &IV, &expected).unwrap());
+    }
+
+    #[test]
+    fn test_cbc_padded() {
+        let cipher = Aes128::new(&hex("2b7e151628aed2a6abf7158809cf4f3c").try_into().unwrap());
+        let expected = hex("6ae270ce3f64eb80454caccab7752082 1ec52ef79cf5a60b6b54e361ab971978");
+        assert_eq!(expected, encrypt(&cipher, &IV, b"Hello CBC world!!"));
+        assert_eq!(b"Hello CBC world!!".to_vec(), decrypt(&cipher, &IV, &expected).unwrap());
+        for length in 0..=33 {
+            let plaintext = vec![0xa5; length];
+            let encrypted = encrypt(&cipher, &IV, &plaintext);
+            assert_eq!((length / 16 + 1) * 16, encrypted.len());
+            assert_eq!(plaintext, decrypt(&cipher, &IV, &encrypted).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_cbc_into() {
+        let cipher = Aes128::new(&hex("2b7e151628aed2a6abf7158809cf4f3c").try_into().unwrap());
+        let mut output = Vec::new();
+        let mut slice = [0; 64];
+        for length in [33, 16, 0, 5] {
+            let plaintext = vec![0xa5; length];
+            let expected = encrypt(&cipher, &IV, &plaintext);
+            encrypt_into(&cipher, &IV, &plaintext, &mut output);
+            assert_eq!(expected, output);
+            assert_eq!(Ok(expected.len()), encrypt_into_slice(&cipher, &IV, &plaintext, &mut slice));
+            assert_eq!(expected, slice[..expected.len()]);
+            decrypt_into(&cipher, &IV, &expected, &mut output).unwrap();
+            assert_eq!(plaintext, output);
+            assert_eq!(Ok(length), decrypt_into_slice(&cipher, &IV, &expected, &mut slice));
+            assert_eq!(plaintext, slice[..length]);
+        }
+        assert_eq!(Some(AesError::OutputTooShort { needed: 32, given: 31 }), encrypt_into_slice(&cipher, &IV, &[0; 16], &mut [0; 31]).err());
+        assert_eq!(Some(AesError::OutputTooShort { needed: 32, given: 31 }), decrypt_into_slice(&cipher, &IV, &[0; 32], &mut [0; 31]).err());
+        assert_eq!(Some(AesError::InvalidDataLength { length: 0, min: 16, multiple: 16 }), decrypt_into(&cipher, &IV, &[], &mut output).err());
+        assert_eq!(Some(AesError::InvalidDataLength { length: 17, min: 16, multiple: 16 }), decrypt_into_slice(&cipher, &IV, &[0; 17], &mut slice).err());
+        let encrypted = encrypt_unpadded(&cipher, &IV, &[0; 16]).unwrap();
+        assert_eq!(Some(AesError::InvalidPadding), decrypt_into(&cipher, &IV, &encrypted, &mut output).err());
+        assert!(output.is_empty());
+        assert_eq!(Some(AesError::InvalidPadding), decrypt_into_slice(&cipher, &IV, &encrypted, &mut slice).err());
+        assert_eq!([0; 16], slice[..16]);
+    }
+
+    #[test]
+    fn test_cbc_invalid_length() {
+        let cipher = Aes128::new(&[0; 16]);
+        assert_eq!(Some(AesError::InvalidDataLength { length: 0, min: 16, multiple: 16 }), decrypt(&cipher, &IV, &[]).err());
+        assert_eq!(Some(AesError::InvalidDataLength { length: 17, min: 16, multiple: 16 }), decrypt(&cipher, &IV, &[0; 17]).err());
+        assert_eq!(Some(AesError::InvalidDataLength { length: 15, min: 0, multiple: 16 }), encrypt_unpadded(&cipher, &IV, &[0; 15]).err());
+        assert_eq!(Some(AesError::InvalidDataLength { length: 31, min: 0, multiple: 16 }), decrypt_unpadded(&cipher, &IV, &[0; 31]).err());
+    }
+
+    #[test]
+    fn test_cbc_invalid_padding() {
+        let cipher = Aes128::new(&[0; 16]);
+        let mut block = [16; 16];
+        block[0] = 15;
+        let encrypted = encrypt_unpadded(&cipher, &IV, &block).unwrap();
+        assert_eq!(Some(AesError::InvalidPadding), decrypt(&cipher, &IV, &encrypted).err());
+        let encrypted = encrypt_unpadded(&cipher, &IV, &[0; 16]).unwrap();
+        assert_eq!(Some(AesError::InvalidPadding), decrypt(&cipher, &IV, &encrypted).err());
+    }
+
+    #[test]
+    fn test_cts_rfc3962() {
+        let cipher = Aes128::new(b"chicken teriyaki");
+        let plaintext = b"I would like the General Gau's Chicken, please, and wonton soup.";
+        let vectors = [
+            (17, "c6353568f2bf8cb4d8a580362da7ff7f 97"),
+            (31, "fc00783e0efdb2c1d445d4c8eff7ed22 97687268d6ecccc0c07b25e25ecfe5"),
+            (32, "39312523a78662d5be7fcbcc98ebf5a8 97687268d6ecccc0c07b25e25ecfe584"),
+            (47, "97687268d6ecccc0c07b25e25ecfe584 b3fffd940c16a18c1b5549d2f838029e 39312523a78662d5be7fcbcc98ebf5"),
+            (48, "97687268d6ecccc0c07b25e25ecfe584 9dad8bbb96c4cdc03bc103e1a194bbd8 39312523a78662d5be7fcbcc98ebf5a8"),
+            (64, "97687268d6ecccc0c07b25e25ecfe584 39312523a78662d5be7fcbcc98ebf5a8
+                  4807efe836ee89a526730dbc2f7bc840 9dad8bbb96c4cdc03bc103e1a194bbd8")
+        ];
+        for (length, expected) in vectors {
+            assert_eq!(hex(expected), encrypt_cts(&cipher, &[0; 16], &plaintext[..length], CtsVariant::Cs3).unwrap());
+            assert_eq!(plaintext[..length].to_vec(), decrypt_cts(&cipher, &[0; 16], &hex(expected), CtsVariant::Cs3).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_cts_cs1_cs2() {
+        let cipher = Aes128::new(&hex("2b7e151628aed2a6abf7158809cf4f3c").try_into().unwrap());
+        let plaintext: Vec<u8> = (100..164).collect();
+        let vectors = [
+            (CtsVariant::Cs1, 16, "a5973ea5578c2079fc413fd45aa126ef"),
+            (CtsVariant::Cs1, 17, "a5d9a4a9b92b4165fc542b709f09dbc3 87"),
+            (CtsVariant::Cs1, 31, "a5973ea5578c2079fc413fd45aa12607 4094307ebc879442be404bb20f8085"),
+            (CtsVariant::Cs1, 32, "a5973ea5578c2079fc413fd45aa126ef 1aded5372ea038eb6c9d7fcfbb0d1116"),
+            (CtsVariant::Cs1, 47, "a5973ea5578c2079fc413fd45aa126ef 1aded5372ea038eb6c9d7fcfbb0d1107 d05e8783a6c93e09cc8b4a122ea7e1"),
+            (CtsVariant::Cs2, 16, "a5973ea5578c2079fc413fd45aa126ef"),
+            (CtsVariant::Cs2, 17, "d9a4a9b92b4165fc542b709f09dbc387 a5"),
+            (CtsVariant::Cs2, 32, "a5973ea5578c2079fc413fd45aa126ef 1aded5372ea038eb6c9d7fcfbb0d1116"),
+            (CtsVariant::Cs2, 47, "a5973ea5578c2079fc413fd45aa126ef 07d05e8783a6c93e09cc8b4a122ea7e1 1aded5372ea038eb6c9d7fcfbb0d11"),
+            (CtsVariant::Cs2, 48, "a5973ea5578c2079fc413fd45aa126ef 1aded5372ea038eb6c9d7fcfbb0d1116 809ddf1c4fbfbaa3e1bb96c36e07c660")
+        ];
+        for (variant, length, expected) in vectors {
+            assert_eq!(hex(expected), encrypt_cts(&cipher, &IV, &plaintext[..length], variant).unwrap());
+            assert_eq!(plaintext[..length].to_vec(), decrypt_cts(&cipher, &IV, &hex(expected), variant).unwrap());
+        }
+        assert_eq!(encrypt_unpadded(&cipher, &IV, &plaintext).unwrap(), encrypt_cts(&cipher, &IV, &plaintext, CtsVariant::Cs2).unwrap());
+    }
+
+    #[test]
+    fn test_cts_invalid_length() {
+        let cipher = Aes128::new(&[0; 16]);
+        assert_eq!(Some(AesError::InvalidDataLength { length: 15, min: 16, multiple: 1 }), encrypt_cts(&cipher, &IV, &[0; 15], CtsVariant::Cs1).err());
+        assert_eq!(Some(AesError::InvalidDataLength { length: 0, min: 16, multiple: 1 }), decrypt_cts(&cipher, &IV, &[], CtsVariant::Cs3).err());
+    }
+
+    #[test]
+    fn test_guarded_encryptor() {
+        let cipher = Aes128::new(&hex("2b7e151628aed2a6abf7158809cf4f3c").try_into().unwrap());
+        let (first, used) = GuardedEncryptor::new(&cipher, &IV).encrypt(b"Hello CBC world!!");
+        assert_eq!(encrypt(&cipher, &IV, b"Hello CBC world!!"), first);
+        assert_eq!(Some(AesError::NonceReused), used.with_fresh_nonce(&IV).err());
+        let (_, used) = GuardedEncryptor::new(&cipher, &IV).encrypt(b"Hello CBC world!!");
+        let (second, _) = used.with_fresh_nonce(&[1; 16]).unwrap().encrypt(b"Hello CBC world!!");
+        assert_eq!(encrypt(&cipher, &[1; 16], b"Hello CBC world!!"), second);
+    }
+
+    #[test]
+    fn test_stream() {
+        let cipher = Aes128::new(&hex("2b7e151628aed2a6abf7158809cf4f3c").try_into().unwrap());
+        let plaintext: Vec<u8> = (0..100).collect();
+        let expected = encrypt(&cipher, &IV, &plaintext);
+        for chunk_size in [1, 7, 16, 33, 100] {
+            let mut encryptor = StreamEncryptor::new(&cipher, &IV);
+            let mut encrypted: Vec<u8> = plaintext.chunks(chunk_size).flat_map(|chunk| encryptor.update(chunk)).collect();
+            encrypted.extend(encryptor.finalize());
+            assert_eq!(expected, encrypted);
+            let mut decryptor = StreamDecryptor::new(&cipher, &IV);
+            let mut decrypted: Vec<u8> = expected.chunks(chunk_size).flat_map(|chunk| decryptor.update(chunk)).collect();
+            decrypted.extend(decryptor.finalize().unwrap());
+            assert_eq!(plaintext, decrypted);
+        }
+    }
+
+    #[test]
+    fn test_stream_empty_and_invalid() {
+        let cipher = Aes128::new(&[0; 16]);
+        let encrypted = StreamEncryptor::new(&cipher, &IV).finalize();
+        assert_eq!(encrypt(&cipher, &IV, &[]), encrypted);
+        let mut decryptor = StreamDecryptor::new(&cipher, &IV);
+        assert!(decryptor.update(&encrypted).is_empty());
+        assert_eq!(Vec::<u8>::new(), decryptor.finalize().unwrap());
+        assert_eq!(Some(AesError::InvalidDataLength { length: 0, min: 16, multiple: 16 }), StreamDecryptor::new(&cipher, &IV).finalize().err());
+        let mut decryptor = StreamDecryptor::new(&cipher, &IV);
+        decryptor.update(&[0; 20]);
+        assert_eq!(Some(AesError::InvalidDataLength { length: 20, min: 16, multiple: 16 }), decryptor.finalize().err());
+    }
+
+    ///
+    /// A padding with 0x80 followed by zeros, as in ISO/IEC 7816-4.
+    /// 
+    struct Iso7816;
+
+    impl Padding for Iso7816 {
+
+        fn pad_block(&self, last: &[u8]) -> Vec<u8> {
+            let mut block = last.to_vec();
+            block.push(0x80);
+            block.resize(16, 0);
+            block
+        }
+
+        fn unpad_block(&self, last: &[u8; 16]) -> Result<usize, AesError> {
+            last.iter().rposition(|&byte| byte == 0x80).ok_or(AesError::InvalidPadding)
+        }
+
+    }
+
+    #[test]
+    fn test_cbc_with_padding() {
+        let cipher = Aes128::new(&hex("2b7e151628aed2a6abf7158809cf4f3c").try_into().unwrap());
+        assert_eq!(encrypt(&cipher, &IV, b"some data"), encrypt_with_padding(&cipher, &IV, b"some data", &Pkcs7));
+        let encrypted = encrypt_with_padding(&cipher, &IV, b"some data", &Iso7816);
+        let mut expected = b"some data\x80".to_vec();
+        expected.resize(16, 0);
+        assert_eq!(expected, decrypt_unpadded(&cipher, &IV, &encrypted).unwrap());
+        assert_eq!(b"some data".to_vec(), decrypt_with_padding(&cipher, &IV, &encrypted, &Iso7816).unwrap());
+        assert_eq!(Some(AesError::InvalidPadding), decrypt_with_padding(&cipher, &IV, &encrypted, &Pkcs7).err());
+    }
+
+}
@@ -0,0 +1,221 @@
+//!
+//! The classic CBC-MAC, as in ISO/IEC 9797-1 algorithm 1 with zero padding. The
+//! mac is the last block of the cbc encryption of the message with a zero iv.
+//! 
+//! The plain CBC-MAC is only secure when all messages authenticated with a key 
+//! have the same length, otherwise a mac can be extended to a longer message. A
+//! CbcMac is therefore created either with a fixed message length, which every 
+//! message must have, or with length prefixing, where the length of the message 
+//! is the first block. The incremental mac needs the length declared when it is 
+//! started in both cases. Cmac should be preferred for new designs.
+//! 
+
+use crate::mac::BlockBuffer;
+use crate::{Aes, AesError, Mac};
+
+///
+/// How CbcMac protects against messages of different lengths.
+/// 
+enum LengthPolicy {
+    Fixed(usize),
+    Prefixed
+}
+
+///
+/// CbcMac is a CBC-MAC with either a fixed message length or length prefixing.
+/// 
+pub struct CbcMac<const KEY_BYTES: usize = 16> {
+    cipher: Aes<KEY_BYTES>,
+    policy: LengthPolicy
+}
+
+impl<const KEY_BYTES: usize> CbcMac<KEY_BYTES> {
+
+    ///
+    /// Creates a new CbcMac for messages of exactly one length.
+    /// 
+    /// key: The key of the mac. It must not be used for any other length.
+    /// length: The length of all messages, a non empty multiple of 16 bytes.
+    /// 
+    /// result: A new CbcMac or AesError::InvalidDataLength if the length is zero 
+    ///         or not a multiple of 16 bytes.
+    /// 
+    pub fn fixed_length(key: &[u8; KEY_BYTES], length: usize) -> Result<Self, AesError> {
+        if length == 0 || !length.is_multiple_of(16) {
+            return Err(AesError::InvalidDataLength { length, min: 16, multiple: 16 });
+        }
+        Ok(Self { cipher: Aes::new(key), policy: LengthPolicy::Fixed(length) })
+    }
+
+    ///
+    /// Creates a new CbcMac that prefixes every message with its length in bytes as
+    /// a 16 byte big endian block and pads the message with zeros.
+    /// 
+    /// key: The key of the mac.
+    /// 
+    /// result: A new CbcMac for messages of any length.
+    /// 
+    pub fn length_prefixed(key: &[u8; KEY_BYTES]) -> Self {
+        Self { cipher: Aes::new(key), policy: LengthPolicy::Prefixed }
+    }
+
+    ///
+    /// Calculates the mac of the data.
+    /// 
+    /// data: The data to authenticate.
+    /// 
+    /// result: The 16 byte mac, AesError::InvalidDataLength if the CbcMac has a 
+    ///         fixed length and the data is shorter or AesError::DataTooLong if 
+    ///         the data is longer.
+    /// 
+    pub fn mac(&self, data: &[u8]) -> Result<[u8; 16], AesError> {
+        let mut state = self.start(data.len())?;
+        state.update(data);
+        state.finalize()
+    }
+
+    ///
+    /// Starts an incremental mac of a message with a declared length.
+    /// 
+    /// length: The length of the message in bytes.
+    /// 
+    /// result: A CbcMacState without any data, AesError::InvalidDataLength if the 
+    ///         CbcMac has a fixed length and the length is shorter or 
+    ///         AesError::DataTooLong if the length is longer.
+    /// 
+    pub fn start(&self, length: usize) -> Result<CbcMacState<'_, KEY_BYTES>, AesError> {
+        let mut state = CbcMacState { cipher: &self.cipher, state: [0; 16], buffer: BlockBuffer::new(), declared: length, received: 0 };
+        match self.policy {
+            LengthPolicy::Fixed(fixed) if fixed != length => return Err(length_error(length, fixed)),
+            LengthPolicy::Fixed(_) => (),
+            LengthPolicy::Prefixed => state.add(&(length as u128).to_be_bytes())
+        }
+        Ok(state)
+    }
+
+}
+
+///
+/// CbcMacState is an incremental CBC-MAC of a message with a declared length.
+/// 
+pub struct CbcMacState<'a, const KEY_BYTES: usize> {
+    cipher: &'a Aes<KEY_BYTES>,
+    state: [u8; 16],
+    buffer: BlockBuffer,
+    declared: usize,
+    received: usize
+}
+
+impl<const KEY_BYTES: usize> CbcMacState<'_, KEY_BYTES> {
+
+    ///
+    /// Adds the blocks to the chaining state.
+    /// 
+    fn add(&mut self, data: &[u8]) {
+        let (cipher, state) = (self.cipher, &mut self.state);
+        self.buffer.update(data, |block| chain_block(cipher, state, block));
+    }
+
+}
+
+impl<const KEY_BYTES: usize> Mac for CbcMacState<'_, KEY_BYTES> {
+
+    type Output = Result<[u8; 16], AesError>;
+
+    fn update(&mut self, data: &[u8]) {
+        self.received = self.received.saturating_add(data.len());
+        self.add(data);
+    }
+
+    ///
+    /// result: The 16 byte mac, AesError::InvalidDataLength if the data added is 
+    ///         shorter than the declared length or AesError::DataTooLong if it is
+    ///         longer.
+    /// 
+    fn finalize(mut self) -> Self::Output {
+        if self.received != self.declared {
+            return Err(length_error(self.received, self.declared));
+        }
+        let mut last = [0; 16];
+        last[..self.buffer.last().len()].copy_from_slice(self.buffer.last());
+        chain_block(self.cipher, &mut self.state, &last);
+        Ok(self.state)
+    }
+
+}
+
+///
+/// Xors the block into the chaining state and encrypts it, one step of CBC-MAC.
+/// 
+/// cipher: The cipher of the mac.
+/// state: The chaining state, the previous encrypted block.
+/// block: The next block.
+/// 
+pub(crate) fn chain_block<const KEY_BYTES: usize>(cipher: &Aes<KEY_BYTES>, state: &mut [u8; 16], block: &[u8; 16]) {
+    state.iter_mut().zip(block).for_each(|(a, b)| *a ^= b);
+    *state = cipher.encrypt_bytes(state).try_into().unwrap();
+}
+
+///
+/// The error for a message of the wrong length, InvalidDataLength with the 
+/// expected length as the minimum if it is shorter and DataTooLong if it is longer.
+/// 
+fn length_error(length: usize, expected: usize) -> AesError {
+    if length < expected {
+        AesError::InvalidDataLength { length, min: expected, multiple: 1 }
+    } else {
+        AesError::DataTooLong
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::test_util::hex;
+
+    const KEY: &str = "2b7e151628aed2a6abf7158809cf4f3c";
+    const MESSAGE: &str = "6bc1bee22e409f96e93d7e117393172a ae2d8a571e03ac9c9eb76fac45af8e51
+                           30c81c46a35ce411e5fbc1191a0a52ef f69f2445df4f9b17ad2b417be66c3710";
+
+    #[test]
+    fn test_fixed_length() {
+        let mac = CbcMac::<16>::fixed_length(&hex(KEY).try_into().unwrap(), 16).unwrap();
+        assert_eq!(hex("3ad77bb40d7a3660a89ecaf32466ef97"), mac.mac(&hex(MESSAGE)[..16]).unwrap());
+        assert_eq!(Some(AesError::DataTooLong), mac.mac(&hex(MESSAGE)).err());
+        let mac = CbcMac::<16>::fixed_length(&hex(KEY).try_into().unwrap(), 64).unwrap();
+        assert_eq!(hex("a7356e1207bb406639e5e5ceb9a9ed93"), mac.mac(&hex(MESSAGE)).unwrap());
+        assert_eq!(Some(AesError::InvalidDataLength { length: 0, min: 16, multiple: 16 }), CbcMac::<16>::fixed_length(&[0; 16], 0).err());
+        assert_eq!(Some(AesError::InvalidDataLength { length: 20, min: 16, multiple: 16 }), CbcMac::<16>::fixed_length(&[0; 16], 20).err());
+    }
+
+    #[test]
+    fn test_length_prefixed() {
+        let mac = CbcMac::<16>::length_prefixed(&hex(KEY).try_into().unwrap());
+        assert_eq!(hex("ca648ffb2b9bb55a9c2abffd5ed0f128"), mac.mac(&hex(MESSAGE)[..40]).unwrap());
+        assert_eq!(hex("7df76b0c1ab899b33e42f047b91b546f"), mac.mac(&[]).unwrap());
+        assert_ne!(mac.mac(&[1]).unwrap(), mac.mac(&[1, 0]).unwrap());
+    }
+
+    #[test]
+    fn test_incremental() {
+        let message = hex(MESSAGE);
+        let mac = CbcMac::<16>::length_prefixed(&hex(KEY).try_into().unwrap());
+        let mut state = mac.start(40).unwrap();
+        state.update(&message[..7]);
+        state.update(&message[7..40]);
+        assert_eq!(hex("ca648ffb2b9bb55a9c2abffd5ed0f128"), state.finalize().unwrap());
+        let mut state = mac.start(40).unwrap();
+        state.update(&message[..41]);
+        assert_eq!(Some(AesError::DataTooLong), state.finalize().err());
+        let mut state = mac.start(40).unwrap();
+        state.update(&message[..39]);
+        assert_eq!(Some(AesError::InvalidDataLength { length: 39, min: 40, multiple: 1 }), state.finalize().err());
+        let mac = CbcMac::<16>::fixed_length(&hex(KEY).try_into().unwrap(), 64).unwrap();
+        let mut state = mac.start(64).unwrap();
+        message.chunks(5).for_each(|chunk| state.update(chunk));
+        assert_eq!(hex("a7356e1207bb406639e5e5ceb9a9ed93"), state.finalize().unwrap());
+        assert_eq!(Some(AesError::InvalidDataLength { length: 48, min: 64, multiple: 1 }), mac.start(48).err());
+    }
+
+}
@@ -0,0 +1,281 @@
+//!
+//! Counter with CBC-MAC (CCM) mode, as specified in RFC 3610 and NIST SP 800-38C.
+//! CCM is an authenticated encryption mode. A CBC-MAC over the nonce, the 
+//! associated data and the plaintext is the tag, and the plaintext and the tag 
+//! are encrypted in counter mode.
+//! 
+//! The length field size L decides the maximum message length, 2^(8L) - 1 bytes,
+//! and the nonce length, 15 - L bytes. A nonce must never be used twice with the
+//! same key. Tags shorter than 8 bytes are refused.
+//! 
+
+use std::vec::Vec;
+
+use crate::ctr::Ctr;
+use crate::{cbc, verify_tag, Aead, Aes, AesError, BlockMode, SecretKey};
+
+///
+/// The shortest tag length in bytes accepted by Ccm.
+/// 
+pub const MIN_TAG_LENGTH: usize = 8;
+
+///
+/// Ccm is a CCM cipher with a fixed tag length and length field size.
+/// 
+pub struct Ccm<const KEY_BYTES: usize = 16> {
+    cipher: Aes<KEY_BYTES>,
+    tag_length: usize,
+    length_size: usize
+}
+
+impl<const KEY_BYTES: usize> Ccm<KEY_BYTES> {
+
+    ///
+    /// Creates a new Ccm cipher.
+    /// 
+    /// key: The key used to encrypt the data.
+    /// tag_length: The length of the tag in bytes. Must be 8, 10, 12, 14 or 16.
+    /// length_size: The size of the length field in bytes. Must be between 2 and 8.
+    /// 
+    /// result: A new Ccm cipher, AesError::TagTooShort if the tag length is less 
+    ///         than MIN_TAG_LENGTH, AesError::InvalidTagLength if the tag length is
+    ///         not valid or AesError::InvalidLengthFieldSize if the length field 
+    ///         size is not valid.
+    /// 
+    pub fn new(key: &[u8; KEY_BYTES], tag_length: usize, length_size: usize) -> Result<Self, AesError> {
+        if tag_length < MIN_TAG_LENGTH {
+            return Err(AesError::TagTooShort);
+        }
+        if tag_length > 16 || !tag_length.is_multiple_of(2) {
+            return Err(AesError::InvalidTagLength);
+        }
+        if !(2..=8).contains(&length_size) {
+            return Err(AesError::InvalidLengthFieldSize);
+        }
+        Ok(Self { cipher: Aes::new(key), tag_length, length_size })
+    }
+
+    ///
+    /// Creates a new Ccm cipher from a SecretKey.
+    /// 
+    /// key: The key used to encrypt the data.
+    /// tag_length: The length of the tag in bytes. Must be 8, 10, 12, 14 or 16.
+    /// length_size: The size of the length field in bytes. Must be between 2 and 8.
+    /// 
+    /// result: A new Ccm cipher or the error of new.
+    /// 
+    pub fn from_secret(key: &SecretKey<KEY_BYTES>, tag_length: usize, length_size: usize) -> Result<Self, AesError> {
+        Self::new(key.expose_secret(), tag_length, length_size)
+    }
+
+    ///
+    /// The length of the nonce required by the length field size.
+    /// 
+    /// result: 15 - length_size.
+    /// 
+    pub fn nonce_length(&self) -> usize {
+        15 - self.length_size
+    }
+
+    ///
+    /// Encrypts and authenticates the plaintext.
+    /// 
+    /// nonce: The nonce. Must be nonce_length bytes.
+    /// aad: The associated data, authenticated but not encrypted.
+    /// plaintext: The data to encrypt.
+    /// 
+    /// result: The ciphertext followed by the tag, AesError::InvalidNonceLength if 
+    ///         the nonce has the wrong length or AesError::DataTooLong if the 
+    ///         plaintext does not fit in the length field.
+    /// 
+    pub fn encrypt(&self, nonce: &[u8], aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, AesError> {
+        self.validate(nonce, plaintext.len())?;
+        let tag = self.mac(nonce, aad, plaintext);
+        let mut ctr = Ctr::init(&self.cipher, &self.counter_block(nonce));
+        let encrypted_tag = ctr.apply_keystream(&tag);
+        let mut encrypted = ctr.apply_keystream(plaintext);
+        encrypted.extend_from_slice(&encrypted_tag[..self.tag_length]);
+        Ok(encrypted)
+    }
+
+    ///
+    /// Decrypts the ciphertext and verifies the tag.
+    /// 
+    /// nonce: The nonce used when encrypting.
+    /// aad: The associated data used when encrypting.
+    /// ciphertext: The ciphertext followed by the tag.
+    /// 
+    /// result: The plaintext, AesError::InvalidNonceLength if the nonce has the 
+    ///         wrong length or AesError::AuthenticationFailed if the tag does not
+    ///         match.
+    /// 
+    pub fn decrypt(&self, nonce: &[u8], aad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, AesError> {
+        if ciphertext.len() < self.tag_length {
+            return Err(AesError::AuthenticationFailed);
+        }
+        let (ciphertext, tag) = ciphertext.split_at(ciphertext.len() - self.tag_length);
+        self.validate(nonce, ciphertext.len())?;
+        let mut ctr = Ctr::init(&self.cipher, &self.counter_block(nonce));
+        let keystream = ctr.apply_keystream(&[0; 16]);
+        let plaintext = ctr.apply_keystream(ciphertext);
+        let computed: Vec<u8> = self.mac(nonce, aad, &plaintext).iter().zip(keystream).map(|(a, b)| a ^ b).take(self.tag_length).collect();
+        verify_tag(tag, &computed)?;
+        Ok(plaintext)
+    }
+
+    ///
+    /// Validates the nonce length and that the data length fits in the length field.
+    /// 
+    fn validate(&self, nonce: &[u8], length: usize) -> Result<(), AesError> {
+        if nonce.len() != self.nonce_length() {
+            return Err(AesError::InvalidNonceLength { length: nonce.len(), min: self.nonce_length(), max: self.nonce_length() });
+        }
+        if self.length_size < 8 && (length as u64) >> (self.length_size * 8) != 0 {
+            return Err(AesError::DataTooLong);
+        }
+        Ok(())
+    }
+
+    ///
+    /// Calculates the CBC-MAC of the B0 block, the encoded associated data and the
+    /// plaintext, each padded with zeros to a multiple of 16 bytes.
+    /// 
+    fn mac(&self, nonce: &[u8], aad: &[u8], plaintext: &[u8]) -> Vec<u8> {
+        let flags = (u8::from(!aad.is_empty()) << 6) | (((self.tag_length - 2) / 2) << 3) as u8 | (self.length_size - 1) as u8;
+        let mut blocks = vec![flags];
+        blocks.extend_from_slice(nonce);
+        blocks.extend_from_slice(&(plaintext.len() as u64).to_be_bytes()[8 - self.length_size..]);
+        if !aad.is_empty() {
+            blocks.extend(encode_aad_length(aad.len()));
+            blocks.extend_from_slice(aad);
+            pad_zeros(&mut blocks);
+        }
+        blocks.extend_from_slice(plaintext);
+        pad_zeros(&mut blocks);
+        let encrypted = cbc::encrypt_unpadded(&self.cipher, &[0; 16], &blocks).unwrap();
+        encrypted[encrypted.len() - 16..].to_vec()
+    }
+
+    ///
+    /// Creates the counter block A0 from the flags and the nonce. The counter is the
+    /// last length_size bytes.
+    /// 
+    fn counter_block(&self, nonce: &[u8]) -> [u8; 16] {
+        let mut block = [0; 16];
+        block[0] = (self.length_size - 1) as u8;
+        block[1..1 + nonce.len()].copy_from_slice(nonce);
+        block
+    }
+
+}
+
+///
+/// Encodes the length of the associated data with 2, 6 or 10 bytes.
+/// 
+fn encode_aad_length(length: usize) -> Vec<u8> {
+    if length < 0xff00 {
+        (length as u16).to_be_bytes().to_vec()
+    } else if length <= u32::MAX as usize {
+        [0xff, 0xfe].into_iter().chain((length as u32).to_be_bytes()).collect()
+    } else {
+        [0xff, 0xff].into_iter().chain((length as u64).to_be_bytes()).collect()
+    }
+}
+
+///
+/// Pads the data with zeros to a multiple of 16 bytes.
+/// 
+fn pad_zeros(data: &mut Vec<u8>) {
+    data.resize(data.len().div_ceil(16) * 16, 0);
+}
+
+impl<const KEY_BYTES: usize> Aead for Ccm<KEY_BYTES> {
+
+    fn encrypt(&self, nonce: &[u8], aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, AesError> {
+        Ccm::encrypt(self, nonce, aad, plaintext)
+    }
+
+    fn decrypt(&self, nonce: &[u8], aad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, AesError> {
+        Ccm::decrypt(self, nonce, aad, ciphertext)
+    }
+
+    fn tag_length(&self) -> usize {
+        self.tag_length
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::test_util::hex;
+
+    fn rfc3610_packet(nonce: &str, aad_length: u8, total_length: u8, tag_length: usize, expected: &str) {
+        let ccm = Ccm::<16>::new(&hex("c0c1c2c3c4c5c6c7c8c9cacbcccdcecf").try_into().unwrap(), tag_length, 2).unwrap();
+        let packet: Vec<u8> = (0..total_length).collect();
+        let (aad, plaintext) = packet.split_at(aad_length as usize);
+        assert_eq!(hex(expected), ccm.encrypt(&hex(nonce), aad, plaintext).unwrap());
+        assert_eq!(plaintext.to_vec(), ccm.decrypt(&hex(nonce), aad, &hex(expected)).unwrap());
+    }
+
+    #[test]
+    fn test_rfc3610_vectors() {
+        rfc3610_packet("00000003020100a0a1a2a3a4a5", 8, 31, 8, "588c979a61c663d2f066d0c2c0f989806d5f6b61dac38417e8d12cfdf926e0");
+        rfc3610_packet("00000004030201a0a1a2a3a4a5", 8, 32, 8, "72c91a36e135f8cf291ca894085c87e3cc15c439c9e43a3ba091d56e10400916");
+        rfc3610_packet("00000005040302a0a1a2a3a4a5", 8, 33, 8, "51b1e5f44a197d1da46b0f8e2d282ae871e838bb64da8596574adaa76fbd9fb0c5");
+        rfc3610_packet("00000006050403a0a1a2a3a4a5", 12, 31, 8, "a28c6865939a9a79faaa5c4c2a9d4a91cdac8c96c861b9c9e61ef1");
+        rfc3610_packet("00000009080706a0a1a2a3a4a5", 8, 31, 10, "0135d1b2c95f41d5d1d4fec185d166b8094e999dfed96c048c56602c97acbb7490");
+    }
+
+    #[test]
+    fn test_sp800_38c_example_3() {
+        let ccm = Ccm::<16>::new(&hex("404142434445464748494a4b4c4d4e4f").try_into().unwrap(), 8, 3).unwrap();
+        let nonce = hex("101112131415161718191a1b");
+        let aad = hex("000102030405060708090a0b0c0d0e0f10111213");
+        let plaintext = hex("202122232425262728292a2b2c2d2e2f3031323334353637");
+        let expected = hex("e3b201a9f5b71a7a9b1ceaeccd97e70b6176aad9a4428aa5484392fbc1b09951");
+        assert_eq!(expected, ccm.encrypt(&nonce, &aad, &plaintext).unwrap());
+        assert_eq!(plaintext, ccm.decrypt(&nonce, &aad, &expected).unwrap());
+    }
+
+    #[test]
+    fn test_ccm_long_aad_and_empty() {
+        let key = hex("404142434445464748494a4b4c4d4e4f").try_into().unwrap();
+        let aad: Vec<u8> = (0..70000).map(|index| index as u8).collect();
+        let plaintext: Vec<u8> = (0..32).collect();
+        let ccm = Ccm::<16>::new(&key, 8, 8).unwrap();
+        let expected = hex("5142217be071b53e79388e8f1c31d3f48c161fad7b4ad3f349401b24d26c8e09ab7875eaf2933fb6");
+        assert_eq!(expected, ccm.encrypt(&hex("10111213141516"), &aad, &plaintext).unwrap());
+        assert_eq!(plaintext, ccm.decrypt(&hex("10111213141516"), &aad, &expected).unwrap());
+        let ccm = Ccm::<16>::new(&key, 16, 3).unwrap();
+        let expected = hex("538f76630f36a98a2f502d9b23d86343");
+        assert_eq!(expected, ccm.encrypt(&hex("101112131415161718191a1b"), &[], &[]).unwrap());
+        assert_eq!(Vec::<u8>::new(), ccm.decrypt(&hex("101112131415161718191a1b"), &[], &expected).unwrap());
+    }
+
+    #[test]
+    fn test_ccm_authentication_failed() {
+        let ccm = Ccm::<16>::new(&[0; 16], 8, 2).unwrap();
+        let nonce = [1; 13];
+        let mut encrypted = ccm.encrypt(&nonce, b"header", b"payload").unwrap();
+        assert_eq!(Some(AesError::AuthenticationFailed), ccm.decrypt(&nonce, b"headex", &encrypted).err());
+        encrypted[0] ^= 1;
+        assert_eq!(Some(AesError::AuthenticationFailed), ccm.decrypt(&nonce, b"header", &encrypted).err());
+        assert_eq!(Some(AesError::AuthenticationFailed), ccm.decrypt(&nonce, b"header", &[0; 7]).err());
+    }
+
+    #[test]
+    fn test_ccm_invalid_parameters() {
+        assert_eq!(Some(AesError::TagTooShort), Ccm::<16>::new(&[0; 16], 4, 2).err());
+        assert_eq!(Some(AesError::TagTooShort), Ccm::<16>::new(&[0; 16], 6, 2).err());
+        assert_eq!(Some(AesError::InvalidTagLength), Ccm::<16>::new(&[0; 16], 9, 2).err());
+        assert_eq!(Some(AesError::InvalidTagLength), Ccm::<16>::new(&[0; 16], 18, 2).err());
+        assert_eq!(Some(AesError::InvalidLengthFieldSize), Ccm::<16>::new(&[0; 16], 8, 1).err());
+        let ccm = Ccm::<16>::new(&[0; 16], 8, 2).unwrap();
+        assert_eq!(13, ccm.nonce_length());
+        assert_eq!(Some(AesError::InvalidNonceLength { length: 12, min: 13, max: 13 }), ccm.encrypt(&[0; 12], &[], &[]).err());
+        assert_eq!(Some(AesError::DataTooLong), ccm.encrypt(&[0; 13], &[], &[0; 65536]).err());
+    }
+
+}
@@ -0,0 +1,152 @@
+//!
+//! Cipher feedback (CFB) mode with 8 bit and 128 bit segments. The cipher
+//! encrypts a shift register to produce a keystream segment, which is xored
+//! with the data. The ciphertext segment is then shifted into the register.
+//!
+//! CFB128 processes a full block per cipher call, while CFB8 makes a cipher
+//! call for every byte. The data may be of any length for both segment sizes,
+//! a partial last segment in CFB128 uses only the start of its keystream.
+//!
+
+use std::vec::Vec;
+
+use crate::Aes;
+
+///
+/// Encrypts the data with 8 bit segments.
+/// 
+/// cipher: The cipher used to generate the keystream.
+/// iv: The 16 byte initialization vector.
+/// data: The data to encrypt. May be of any length.
+/// 
+/// result: The encrypted data, with the same length as the data.
+/// 
+pub fn encrypt8<const KEY_BYTES: usize>(cipher: &Aes<KEY_BYTES>, iv: &[u8; 16], data: &[u8]) -> Vec<u8> {
+    crypt(cipher, iv, data, 1, false)
+}
+
+///
+/// Decrypts the data with 8 bit segments.
+/// 
+/// cipher: The cipher used to generate the keystream.
+/// iv: The 16 byte initialization vector used when encrypting.
+/// data: The data to decrypt. May be of any length.
+/// 
+/// result: The decrypted data, with the same length as the data.
+/// 
+pub fn decrypt8<const KEY_BYTES: usize>(cipher: &Aes<KEY_BYTES>, iv: &[u8; 16], data: &[u8]) -> Vec<u8> {
+    crypt(cipher, iv, data, 1, true)
+}
+
+///
+/// Encrypts the data with 128 bit segments.
+/// 
+/// cipher: The cipher used to generate the keystream.
+/// iv: The 16 byte initialization vector.
+/// data: The data to encrypt. May be of any length.
+/// 
+/// result: The encrypted data, with the same length as the data.
+/// 
+pub fn encrypt128<const KEY_BYTES: usize>(cipher: &Aes<KEY_BYTES>, iv: &[u8; 16], data: &[u8]) -> Vec<u8> {
+    crypt(cipher, iv, data, 16, false)
+}
+
+///
+/// Decrypts the data with 128 bit segments.
+/// 
+/// cipher: The cipher used to generate the keystream.
+/// iv: The 16 byte initialization vector used when encrypting.
+/// data: The data to decrypt. May be of any length.
+/// 
+/// result: The decrypted data, with the same length as the data.
+/// 
+pub fn decrypt128<const KEY_BYTES: usize>(cipher: &Aes<KEY_BYTES>, iv: &[u8; 16], data: &[u8]) -> Vec<u8> {
+    crypt(cipher, iv, data, 16, true)
+}
+
+///
+/// Processes the data segment by segment. The register is shifted left by the
+/// segment size and the ciphertext segment is appended. The ciphertext is the
+/// output when encrypting and the input when decrypting.
+/// 
+/// segment_bytes: The segment size in bytes, between 1 and 16.
+/// decrypt: Whether the data is ciphertext.
+/// 
+fn crypt<const KEY_BYTES: usize>(cipher: &Aes<KEY_BYTES>, iv: &[u8; 16], data: &[u8], segment_bytes: usize, decrypt: bool) -> Vec<u8> {
+    let mut register = iv.to_vec();
+    let mut result = Vec::with_capacity(data.len());
+    for segment in data.chunks(segment_bytes) {
+        let keystream = cipher.encrypt_bytes(&register);
+        let output: Vec<u8> = segment.iter().zip(&keystream).map(|(a, b)| a ^ b).collect();
+        let ciphertext = if decrypt { segment } else { &output };
+        register.drain(..segment_bytes);
+        register.extend_from_slice(ciphertext);
+        result.extend(output);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::test_util::hex;
+    use crate::{Aes128, Aes192, Aes256};
+
+    const IV: [u8; 16] = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15];
+    const PLAINTEXT: &str = "6bc1bee22e409f96e93d7e117393172a ae2d8a571e03ac9c9eb76fac45af8e51
+                             30c81c46a35ce411e5fbc1191a0a52ef f69f2445df4f9b17ad2b417be66c3710";
+
+    #[test]
+    fn test_cfb128_aes128() {
+        let cipher = Aes128::new(&hex("2b7e151628aed2a6abf7158809cf4f3c").try_into().unwrap());
+        let expected = hex("3b3fd92eb72dad20333449f8e83cfb4a c8a64537a0b3a93fcde3cdad9f1ce58b
+                            26751f67a3cbb140b1808cf187a4f4df c04b05357c5d1c0eeac4c66f9ff7f2e6");
+        assert_eq!(expected, encrypt128(&cipher, &IV, &hex(PLAINTEXT)));
+        assert_eq!(hex(PLAINTEXT), decrypt128(&cipher, &IV, &expected));
+    }
+
+    #[test]
+    fn test_cfb128_aes192() {
+        let cipher = Aes192::new(&hex("8e73b0f7da0e6452c810f32b809079e562f8ead2522c6b7b").try_into().unwrap());
+        let expected = hex("cdc80d6fddf18cab34c25909c99a4174 67ce7f7f81173621961a2b70171d3d7a
+                            2e1e8a1dd59b88b1c8e60fed1efac4c9 c05f9f9ca9834fa042ae8fba584b09ff");
+        assert_eq!(expected, encrypt128(&cipher, &IV, &hex(PLAINTEXT)));
+        assert_eq!(hex(PLAINTEXT), decrypt128(&cipher, &IV, &expected));
+    }
+
+    #[test]
+    fn test_cfb128_aes256() {
+        let cipher = Aes256::new(&hex("603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4").try_into().unwrap());
+        let expected = hex("dc7e84bfda79164b7ecd8486985d3860 39ffed143b28b1c832113c6331e5407b
+                            df10132415e54b92a13ed0a8267ae2f9 75a385741ab9cef82031623d55b1e471");
+        assert_eq!(expected, encrypt128(&cipher, &IV, &hex(PLAINTEXT)));
+        assert_eq!(hex(PLAINTEXT), decrypt128(&cipher, &IV, &expected));
+    }
+
+    fn cfb8_known_answer<const KEY_BYTES: usize>(key: &str, expected: &str) {
+        let cipher = Aes::<KEY_BYTES>::new(&hex(key).try_into().unwrap());
+        let plaintext = &hex(PLAINTEXT)[..18];
+        assert_eq!(hex(expected), encrypt8(&cipher, &IV, plaintext));
+        assert_eq!(plaintext.to_vec(), decrypt8(&cipher, &IV, &hex(expected)));
+    }
+
+    #[test]
+    fn test_cfb8() {
+        cfb8_known_answer::<16>("2b7e151628aed2a6abf7158809cf4f3c", "3b79424c9c0dd436bace9e0ed4586a4f32b9");
+        cfb8_known_answer::<24>("8e73b0f7da0e6452c810f32b809079e562f8ead2522c6b7b", "cda2521ef0a905ca44cd057cbf0d47a0678a");
+        cfb8_known_answer::<32>("603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4", "dc1f1a8520a64db55fcc8ac554844e889700");
+    }
+
+    #[test]
+    fn test_cfb128_partial_segment() {
+        let cipher = Aes128::new(&hex("2b7e151628aed2a6abf7158809cf4f3c").try_into().unwrap());
+        let plaintext = hex(PLAINTEXT);
+        let full = encrypt128(&cipher, &IV, &plaintext);
+        for length in [0, 1, 15, 17, 40, 63] {
+            assert_eq!(full[..length].to_vec(), encrypt128(&cipher, &IV, &plaintext[..length]));
+            assert_eq!(plaintext[..length].to_vec(), decrypt128(&cipher, &IV, &full[..length]));
+        }
+    }
+
+}
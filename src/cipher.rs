@@ -0,0 +1,744 @@
+use std::fmt;
+use std::vec::Vec;
+
+#[cfg(all(feature = "encrypt", feature = "decrypt"))]
+use crate::builder::CipherBuilder;
+#[cfg(feature = "decrypt")]
+use crate::key_schedule::ReverseRoundKeyGenerator;
+use crate::key_schedule::RoundKeyGenerator;
+use crate::secret::Redacted;
+use crate::zeroize::{Zeroize, Zeroizing};
+use crate::{bitslice, cbc, ttable};
+#[cfg(target_arch = "aarch64")]
+use crate::armv8;
+#[cfg(feature = "portable-simd")]
+use crate::simd;
+#[cfg(target_arch = "x86_64")]
+use crate::vaes;
+#[cfg(all(target_arch = "riscv64", target_feature = "zkne", target_feature = "zknd"))]
+use crate::zkn;
+#[cfg(feature = "decrypt")]
+use crate::InverseRoundKeys;
+use crate::{AESBlock, AesError, DecryptedState, EncryptedState, KeySchedule, RoundKeys};
+
+///
+/// KeyScheduleMode decides how the Aes struct handles the roundkeys.
+/// 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyScheduleMode {
+    ///
+    /// All roundkeys are generated once when the cipher is created. This is the 
+    /// fastest mode when encrypting many blocks.
+    /// 
+    Precomputed,
+    ///
+    /// Only the key and the last words of the key expansion are stored, and each 
+    /// roundkey is generated during the rounds of the encryption or decryption. This 
+    /// uses less memory at the cost of generating the roundkeys for every block.
+    /// 
+    OnTheFly
+}
+
+///
+/// RoundBackend decides how the Aes struct computes the rounds of the cipher.
+/// 
+/// The default backend is the fastest constant time backend supported by the cpu,
+/// the AES instructions if there are any and the bitsliced backend otherwise. With
+/// the portable-simd feature the bitsliced rounds run on SIMD vectors instead.
+/// 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoundBackend {
+    ///
+    /// The rounds are computed on four blocks at a time, with the bits of the 
+    /// blocks spread over eight words and the s-box computed with logical 
+    /// operations. There are no table lookups or branches depending on the data,
+    /// so this backend is safe against cache timing attacks. It is the default on 
+    /// cpus without AES instructions.
+    /// 
+    Bitsliced,
+    ///
+    /// The rounds are computed byte by byte as in FIPS-197. This is the slowest
+    /// backend, and the lookups in the s-boxes depend on the data.
+    /// 
+    Bytewise,
+    ///
+    /// The rounds are computed with four tables of 256 words per direction, 
+    /// combining the steps of a round into 16 lookups. This is several times faster
+    /// than the bytewise backend, but the lookups leak the data through the cache
+    /// timing to an attacker sharing the cpu.
+    /// 
+    TTable,
+    ///
+    /// The rounds are computed with the AES instructions of the ARMv8 Cryptography
+    /// Extensions. Only available on aarch64 cpus supporting the extensions.
+    /// 
+    #[cfg(target_arch = "aarch64")]
+    Armv8,
+    ///
+    /// The rounds are computed with the VAES instructions on four blocks at a time
+    /// in 512 bit registers, and with AES-NI for the remaining blocks. This is the
+    /// fastest backend for bulk encryption with encrypt_blocks, ctr and xts. Only
+    /// available on x86_64 cpus supporting VAES and AVX-512.
+    /// 
+    #[cfg(target_arch = "x86_64")]
+    Vaes,
+    ///
+    /// The bitsliced rounds on sixteen blocks at a time, with the words of the 
+    /// state in std::simd vectors. This is faster than the bitsliced backend for
+    /// bulk encryption on targets with SIMD instructions but no AES instructions,
+    /// and is constant time in the same way. Only available with the portable-simd
+    /// feature.
+    /// 
+    #[cfg(feature = "portable-simd")]
+    PortableSimd,
+    ///
+    /// The rounds are computed with the AES instructions of the RISC-V scalar 
+    /// cryptography extensions Zkne and Zknd. Only available on riscv64 when the 
+    /// target enables the extensions at compile time.
+    /// 
+    #[cfg(all(target_arch = "riscv64", target_feature = "zkne", target_feature = "zknd"))]
+    Zkn
+}
+
+impl RoundBackend {
+
+    ///
+    /// Finds the fastest constant time backend supported by the cpu.
+    /// 
+    /// result: The backend with AES instructions if the cpu supports them,
+    ///         RoundBackend::PortableSimd with the portable-simd feature or
+    ///         RoundBackend::Bitsliced.
+    /// 
+    pub fn detect() -> RoundBackend {
+        #[cfg(all(target_arch = "riscv64", target_feature = "zkne", target_feature = "zknd"))]
+        if RoundBackend::Zkn.is_supported() {
+            return RoundBackend::Zkn;
+        }
+        #[cfg(target_arch = "aarch64")]
+        if RoundBackend::Armv8.is_supported() {
+            return RoundBackend::Armv8;
+        }
+        #[cfg(target_arch = "x86_64")]
+        if RoundBackend::Vaes.is_supported() {
+            return RoundBackend::Vaes;
+        }
+        #[cfg(feature = "portable-simd")]
+        return RoundBackend::PortableSimd;
+        #[cfg(not(feature = "portable-simd"))]
+        RoundBackend::Bitsliced
+    }
+
+    ///
+    /// Whether the backend can be used on this cpu. The software backends are
+    /// supported everywhere, and RoundBackend::Zkn wherever it is compiled.
+    /// 
+    /// result: true if the backend is supported.
+    /// 
+    pub fn is_supported(&self) -> bool {
+        match self {
+            #[cfg(target_arch = "aarch64")]
+            RoundBackend::Armv8 => armv8::is_supported(),
+            #[cfg(target_arch = "x86_64")]
+            RoundBackend::Vaes => vaes::is_supported(),
+            _ => true
+        }
+    }
+
+}
+
+impl Default for RoundBackend {
+
+    fn default() -> RoundBackend {
+        RoundBackend::detect()
+    }
+
+}
+
+///
+/// The roundkeys as stored by the Aes struct.
+/// 
+enum Schedule {
+    Precomputed {
+        roundkeys: RoundKeys,
+        #[cfg(feature = "decrypt")]
+        inverse_roundkeys: InverseRoundKeys
+    },
+    OnTheFly {
+        key: Vec<u8>,
+        #[cfg(feature = "decrypt")]
+        last_words: Vec<[u8; 4]>
+    }
+}
+
+impl Drop for Schedule {
+
+    fn drop(&mut self) {
+        if let Schedule::OnTheFly { key, #[cfg(feature = "decrypt")] last_words } = self {
+            key.zeroize();
+            #[cfg(feature = "decrypt")]
+            last_words.zeroize();
+        }
+    }
+
+}
+
+///
+/// Aes is a cipher context created from a 16, 24 or 32 byte key. The key expansion
+/// is done once when the struct is created, so the struct can be reused to encrypt
+/// and decrypt any number of blocks.
+/// 
+/// The struct is generic over the key length in bytes, so using any other key 
+/// length fails to compile.
+/// 
+pub struct Aes<const KEY_BYTES: usize = 16> {
+    schedule: Schedule,
+    backend: RoundBackend
+}
+
+///
+/// Aes context using a 16 byte key.
+/// 
+pub type Aes128 = Aes<16>;
+///
+/// Aes context using a 24 byte key.
+/// 
+pub type Aes192 = Aes<24>;
+///
+/// Aes context using a 32 byte key.
+/// 
+pub type Aes256 = Aes<32>;
+
+impl<const KEY_BYTES: usize> Aes<KEY_BYTES> {
+
+    ///
+    /// Creates a new Aes struct with precomputed roundkeys.
+    /// 
+    /// key: A key of 16, 24 or 32 bytes.
+    /// 
+    /// result: A Aes struct using the key.
+    /// 
+    pub fn new(key: &[u8; KEY_BYTES]) -> Aes<KEY_BYTES> {
+        Aes::with_mode(key, KeyScheduleMode::Precomputed)
+    }
+
+    ///
+    /// Creates a builder for a cipher with a mode of operation and padding, for
+    /// example Aes::builder().key(&key).mode(Mode::Cbc).build().
+    /// 
+    /// result: A CipherBuilder without a key.
+    /// 
+    #[cfg(all(feature = "encrypt", feature = "decrypt"))]
+    pub fn builder() -> CipherBuilder<KEY_BYTES> {
+        CipherBuilder::new()
+    }
+
+    ///
+    /// Creates a new Aes struct with the specified key schedule mode.
+    /// 
+    /// key: A key of 16, 24 or 32 bytes.
+    /// mode: Decides if the roundkeys are precomputed or generated on the fly.
+    /// 
+    /// result: A Aes struct using the key.
+    /// 
+    pub fn with_mode(key: &[u8; KEY_BYTES], mode: KeyScheduleMode) -> Aes<KEY_BYTES> {
+        let schedule = match mode {
+            KeyScheduleMode::Precomputed => {
+                let roundkeys = KeySchedule::new(key).roundkeys().clone();
+                Schedule::Precomputed {
+                    #[cfg(feature = "decrypt")]
+                    inverse_roundkeys: InverseRoundKeys::new(&roundkeys),
+                    roundkeys
+                }
+            },
+            KeyScheduleMode::OnTheFly => Schedule::OnTheFly {
+                key: key.to_vec(),
+                #[cfg(feature = "decrypt")]
+                last_words: ReverseRoundKeyGenerator::last_words(key)
+            }
+        };
+        Aes {
+            schedule,
+            backend: RoundBackend::default()
+        }
+    }
+
+    ///
+    /// Creates a new Aes struct from previously exported roundkeys, for example
+    /// roundkeys created with RoundKeys::from_bytes.
+    /// 
+    /// roundkeys: The roundkeys for a key of KEY_BYTES bytes.
+    /// 
+    /// result: A Aes struct with precomputed roundkeys or AesError::InvalidRoundKeys
+    ///         if the number of roundkeys does not match the key length.
+    /// 
+    pub fn from_roundkeys(roundkeys: RoundKeys) -> Result<Aes<KEY_BYTES>, AesError> {
+        if roundkeys.rounds() != KeySchedule::<KEY_BYTES>::ROUNDS {
+            return Err(AesError::InvalidRoundKeys);
+        }
+        Ok(Aes {
+            schedule: Schedule::Precomputed {
+                #[cfg(feature = "decrypt")]
+                inverse_roundkeys: InverseRoundKeys::new(&roundkeys),
+                roundkeys
+            },
+            backend: RoundBackend::default()
+        })
+    }
+
+    ///
+    /// Changes the backend computing the rounds, for example 
+    /// Aes::new(&key).with_backend(RoundBackend::TTable).
+    /// 
+    /// backend: The backend used for all blocks encrypted or decrypted. A backend
+    ///          not supported by the cpu is replaced by RoundBackend::detect().
+    /// 
+    /// result: The Aes struct using the backend.
+    /// 
+    pub fn with_backend(mut self, backend: RoundBackend) -> Aes<KEY_BYTES> {
+        self.backend = if backend.is_supported() { backend } else { RoundBackend::detect() };
+        self
+    }
+
+    ///
+    /// Returns the roundkeys used by the cipher, so that they can be exported with
+    /// RoundKeys::to_bytes. With the on the fly mode the roundkeys are generated.
+    /// 
+    /// result: The roundkeys of the cipher.
+    /// 
+    pub fn roundkeys(&self) -> RoundKeys {
+        match &self.schedule {
+            Schedule::Precomputed { roundkeys, .. } => roundkeys.clone(),
+            Schedule::OnTheFly { key, .. } => RoundKeys::from_valid(RoundKeyGenerator::new(key).map(|roundkey| *roundkey).collect())
+        }
+    }
+
+    ///
+    /// Returns the key schedule mode of the cipher.
+    /// 
+    pub fn mode(&self) -> KeyScheduleMode {
+        match self.schedule {
+            Schedule::Precomputed { .. } => KeyScheduleMode::Precomputed,
+            Schedule::OnTheFly { .. } => KeyScheduleMode::OnTheFly
+        }
+    }
+
+    ///
+    /// Returns the backend computing the rounds of the cipher.
+    /// 
+    pub fn backend(&self) -> RoundBackend {
+        self.backend
+    }
+
+    ///
+    /// The heap buffers holding the key or the roundkeys, so they can be locked in
+    /// memory.
+    /// 
+    #[cfg(feature = "mlock")]
+    pub(crate) fn secret_buffers(&self) -> Vec<&[u8]> {
+        #[cfg_attr(not(feature = "decrypt"), allow(unused_mut))]
+        let mut buffers = match &self.schedule {
+            Schedule::Precomputed { roundkeys, .. } => vec![roundkeys.as_slice().as_flattened()],
+            Schedule::OnTheFly { key, .. } => vec![key.as_slice()]
+        };
+        #[cfg(feature = "decrypt")]
+        match &self.schedule {
+            Schedule::Precomputed { inverse_roundkeys, .. } => buffers.push(inverse_roundkeys.as_slice().as_flattened()),
+            Schedule::OnTheFly { last_words, .. } => buffers.push(last_words.as_flattened())
+        }
+        buffers
+    }
+
+    ///
+    /// Encrypts a single 16 byte block.
+    /// 
+    /// block: The block to encrypt.
+    /// 
+    /// result: A AESBlock with the 16 bytes encrypted.
+    /// 
+    #[cfg(feature = "encrypt")]
+    pub fn encrypt_block(&self, block: &AESBlock<DecryptedState>) -> AESBlock<EncryptedState> {
+        let mut grid = *block.as_bytes();
+        self.encrypt_block_in_place(&mut grid);
+        AESBlock::from_grid(grid)
+    }
+
+    ///
+    /// Encrypts a single 16 byte block in place, without allocating or copying 
+    /// the data.
+    /// 
+    /// block: The 16 bytes to encrypt, replaced by the encrypted bytes.
+    /// 
+    #[cfg(feature = "encrypt")]
+    pub fn encrypt_block_in_place(&self, block: &mut [u8; 16]) {
+        self.encrypt_in_place(std::slice::from_mut(block));
+    }
+
+    ///
+    /// Encrypts the blocks in place with the backend of the cipher. The 
+    /// bitsliced, T-table and VAES backends encrypt four blocks at a time, the portable
+    /// SIMD backend sixteen and the other backends one.
+    /// 
+    #[cfg(feature = "encrypt")]
+    fn encrypt_in_place(&self, blocks: &mut [[u8; 16]]) {
+        let rounds = AESBlock::<DecryptedState>::from_grid([0; 16]);
+        match (&self.schedule, self.backend) {
+            (Schedule::Precomputed { roundkeys, .. }, RoundBackend::Bitsliced) => bitslice::encrypt_rounds::<u64, _>(blocks, roundkeys.rounds(), roundkeys.as_slice().iter()),
+            (Schedule::OnTheFly { key, .. }, RoundBackend::Bitsliced) => bitslice::encrypt_rounds::<u64, _>(blocks, KeySchedule::<KEY_BYTES>::ROUNDS, RoundKeyGenerator::new(key)),
+            #[cfg(feature = "portable-simd")]
+            (Schedule::Precomputed { roundkeys, .. }, RoundBackend::PortableSimd) => bitslice::encrypt_rounds::<simd::Words, _>(blocks, roundkeys.rounds(), roundkeys.as_slice().iter()),
+            #[cfg(feature = "portable-simd")]
+            (Schedule::OnTheFly { key, .. }, RoundBackend::PortableSimd) => bitslice::encrypt_rounds::<simd::Words, _>(blocks, KeySchedule::<KEY_BYTES>::ROUNDS, RoundKeyGenerator::new(key)),
+            #[cfg(target_arch = "aarch64")]
+            (Schedule::Precomputed { roundkeys, .. }, RoundBackend::Armv8) => armv8::encrypt_rounds(blocks, roundkeys.rounds(), roundkeys.as_slice().iter()),
+            #[cfg(target_arch = "aarch64")]
+            (Schedule::OnTheFly { key, .. }, RoundBackend::Armv8) => armv8::encrypt_rounds(blocks, KeySchedule::<KEY_BYTES>::ROUNDS, RoundKeyGenerator::new(key)),
+            #[cfg(target_arch = "x86_64")]
+            (Schedule::Precomputed { roundkeys, .. }, RoundBackend::Vaes) => vaes::encrypt_rounds(blocks, roundkeys.rounds(), roundkeys.as_slice().iter()),
+            #[cfg(target_arch = "x86_64")]
+            (Schedule::OnTheFly { key, .. }, RoundBackend::Vaes) => vaes::encrypt_rounds(blocks, KeySchedule::<KEY_BYTES>::ROUNDS, RoundKeyGenerator::new(key)),
+            #[cfg(all(target_arch = "riscv64", target_feature = "zkne", target_feature = "zknd"))]
+            (Schedule::Precomputed { roundkeys, .. }, RoundBackend::Zkn) => zkn::encrypt_rounds(blocks, roundkeys.rounds(), roundkeys.as_slice().iter()),
+            #[cfg(all(target_arch = "riscv64", target_feature = "zkne", target_feature = "zknd"))]
+            (Schedule::OnTheFly { key, .. }, RoundBackend::Zkn) => zkn::encrypt_rounds(blocks, KeySchedule::<KEY_BYTES>::ROUNDS, RoundKeyGenerator::new(key)),
+            (Schedule::Precomputed { roundkeys, .. }, RoundBackend::TTable) => ttable::encrypt_rounds(blocks, roundkeys.rounds(), roundkeys.as_slice().iter()),
+            (Schedule::OnTheFly { key, .. }, RoundBackend::TTable) => ttable::encrypt_rounds(blocks, KeySchedule::<KEY_BYTES>::ROUNDS, RoundKeyGenerator::new(key)),
+            (schedule, _) => for block in blocks {
+                match schedule {
+                    Schedule::Precomputed { roundkeys, .. } => rounds.encrypt_rounds(block, roundkeys.rounds(), roundkeys.as_slice().iter()),
+                    Schedule::OnTheFly { key, .. } => rounds.encrypt_rounds(block, KeySchedule::<KEY_BYTES>::ROUNDS, RoundKeyGenerator::new(key))
+                }
+            }
+        }
+    }
+
+    ///
+    /// Encrypts a buffer of whole blocks in place in one call. Each block is 
+    /// encrypted on its own like ecb.
+    /// 
+    /// data: The blocks to encrypt, replaced by the encrypted blocks. Must be a 
+    ///       multiple of 16 bytes.
+    /// 
+    /// result: Ok or AesError::InvalidDataLength if the data is not a multiple of
+    ///         16 bytes. The data is not changed if the length is invalid.
+    /// 
+    #[cfg(feature = "encrypt")]
+    pub fn encrypt_blocks(&self, data: &mut [u8]) -> Result<(), AesError> {
+        let (blocks, rest) = data.as_chunks_mut::<16>();
+        if !rest.is_empty() {
+            return Err(AesError::InvalidDataLength { length: data.len(), min: 0, multiple: 16 });
+        }
+        self.encrypt_in_place(blocks);
+        Ok(())
+    }
+
+    ///
+    /// Encrypts 16 bytes. Used by the modes of operation, which always split the 
+    /// data into blocks of 16 bytes before encrypting.
+    /// 
+    /// data: A slice of exactly 16 bytes.
+    /// 
+    /// result: A vector of 16 bytes encrypted.
+    /// 
+    #[cfg(feature = "encrypt")]
+    pub(crate) fn encrypt_bytes(&self, data: &[u8]) -> Vec<u8> {
+        let mut block = Zeroizing(<[u8; 16]>::try_from(data).unwrap());
+        self.encrypt_block_in_place(&mut block);
+        block.to_vec()
+    }
+
+    ///
+    /// Decrypts a buffer of whole blocks in place in one call.
+    /// 
+    /// data: The blocks to decrypt, replaced by the decrypted blocks. Must be a 
+    ///       multiple of 16 bytes.
+    /// 
+    /// result: Ok or AesError::InvalidDataLength if the data is not a multiple of
+    ///         16 bytes. The data is not changed if the length is invalid.
+    /// 
+    #[cfg(feature = "decrypt")]
+    pub fn decrypt_blocks(&self, data: &mut [u8]) -> Result<(), AesError> {
+        let (blocks, rest) = data.as_chunks_mut::<16>();
+        if !rest.is_empty() {
+            return Err(AesError::InvalidDataLength { length: data.len(), min: 0, multiple: 16 });
+        }
+        self.decrypt_in_place(blocks);
+        Ok(())
+    }
+
+    ///
+    /// Decrypts 16 bytes. Used by the modes of operation, which always split the 
+    /// data into blocks of 16 bytes before decrypting.
+    /// 
+    /// data: A slice of exactly 16 bytes.
+    /// 
+    /// result: A vector of 16 bytes decrypted.
+    /// 
+    #[cfg(feature = "decrypt")]
+    pub(crate) fn decrypt_bytes(&self, data: &[u8]) -> Vec<u8> {
+        let mut block = Zeroizing(<[u8; 16]>::try_from(data).unwrap());
+        self.decrypt_block_in_place(&mut block);
+        block.to_vec()
+    }
+
+    ///
+    /// Decrypts a single 16 byte block. With precomputed roundkeys the equivalent 
+    /// inverse cipher is used.
+    /// 
+    /// block: The block to decrypt.
+    /// 
+    /// result: A AESBlock with the 16 bytes decrypted.
+    /// 
+    #[cfg(feature = "decrypt")]
+    pub fn decrypt_block(&self, block: &AESBlock<EncryptedState>) -> AESBlock<DecryptedState> {
+        let mut grid = *block.as_bytes();
+        self.decrypt_block_in_place(&mut grid);
+        AESBlock::from_grid(grid)
+    }
+
+    ///
+    /// Decrypts a single 16 byte block in place, without allocating or copying 
+    /// the data. With precomputed roundkeys the equivalent inverse cipher is used.
+    /// 
+    /// block: The 16 bytes to decrypt, replaced by the decrypted bytes.
+    /// 
+    #[cfg(feature = "decrypt")]
+    pub fn decrypt_block_in_place(&self, block: &mut [u8; 16]) {
+        self.decrypt_in_place(std::slice::from_mut(block));
+    }
+
+    ///
+    /// Decrypts the blocks in place with the backend of the cipher. The 
+    /// bitsliced, T-table and VAES backends decrypt four blocks at a time, the portable
+    /// SIMD backend sixteen and the other backends one.
+    /// 
+    #[cfg(feature = "decrypt")]
+    fn decrypt_in_place(&self, blocks: &mut [[u8; 16]]) {
+        let rounds = AESBlock::<EncryptedState>::from_grid([0; 16]);
+        match (&self.schedule, self.backend) {
+            (Schedule::Precomputed { inverse_roundkeys, .. }, RoundBackend::Bitsliced) => bitslice::decrypt_equivalent_rounds::<u64>(blocks, inverse_roundkeys),
+            (Schedule::OnTheFly { last_words, .. }, RoundBackend::Bitsliced) => bitslice::decrypt_rounds::<u64, _>(blocks, KeySchedule::<KEY_BYTES>::ROUNDS, ReverseRoundKeyGenerator::new(last_words)),
+            #[cfg(feature = "portable-simd")]
+            (Schedule::Precomputed { inverse_roundkeys, .. }, RoundBackend::PortableSimd) => bitslice::decrypt_equivalent_rounds::<simd::Words>(blocks, inverse_roundkeys),
+            #[cfg(feature = "portable-simd")]
+            (Schedule::OnTheFly { last_words, .. }, RoundBackend::PortableSimd) => bitslice::decrypt_rounds::<simd::Words, _>(blocks, KeySchedule::<KEY_BYTES>::ROUNDS, ReverseRoundKeyGenerator::new(last_words)),
+            #[cfg(target_arch = "aarch64")]
+            (Schedule::Precomputed { inverse_roundkeys, .. }, RoundBackend::Armv8) => armv8::decrypt_equivalent_rounds(blocks, inverse_roundkeys),
+            #[cfg(target_arch = "aarch64")]
+            (Schedule::OnTheFly { last_words, .. }, RoundBackend::Armv8) => armv8::decrypt_rounds(blocks, KeySchedule::<KEY_BYTES>::ROUNDS, ReverseRoundKeyGenerator::new(last_words)),
+            #[cfg(target_arch = "x86_64")]
+            (Schedule::Precomputed { inverse_roundkeys, .. }, RoundBackend::Vaes) => vaes::decrypt_equivalent_rounds(blocks, inverse_roundkeys),
+            #[cfg(target_arch = "x86_64")]
+            (Schedule::OnTheFly { last_words, .. }, RoundBackend::Vaes) => vaes::decrypt_rounds(blocks, KeySchedule::<KEY_BYTES>::ROUNDS, ReverseRoundKeyGenerator::new(last_words)),
+            #[cfg(all(target_arch = "riscv64", target_feature = "zkne", target_feature = "zknd"))]
+            (Schedule::Precomputed { inverse_roundkeys, .. }, RoundBackend::Zkn) => zkn::decrypt_equivalent_rounds(blocks, inverse_roundkeys),
+            #[cfg(all(target_arch = "riscv64", target_feature = "zkne", target_feature = "zknd"))]
+            (Schedule::OnTheFly { last_words, .. }, RoundBackend::Zkn) => zkn::decrypt_rounds(blocks, KeySchedule::<KEY_BYTES>::ROUNDS, ReverseRoundKeyGenerator::new(last_words)),
+            (Schedule::Precomputed { inverse_roundkeys, .. }, RoundBackend::TTable) => ttable::decrypt_equivalent_rounds(blocks, inverse_roundkeys),
+            (Schedule::OnTheFly { last_words, .. }, RoundBackend::TTable) => ttable::decrypt_rounds(blocks, KeySchedule::<KEY_BYTES>::ROUNDS, ReverseRoundKeyGenerator::new(last_words)),
+            (schedule, _) => for block in blocks {
+                match schedule {
+                    Schedule::Precomputed { inverse_roundkeys, .. } => rounds.decrypt_equivalent_rounds(block, inverse_roundkeys),
+                    Schedule::OnTheFly { last_words, .. } => rounds.decrypt_rounds(block, KeySchedule::<KEY_BYTES>::ROUNDS, ReverseRoundKeyGenerator::new(last_words))
+                }
+            }
+        }
+    }
+
+}
+
+///
+/// Encrypts data of any length with a key in one call. The data is split into
+/// blocks, padded with PKCS#7 and chained with CBC, so this is the same as 
+/// cbc::encrypt with Aes::new(key). A new random iv must be used for every 
+/// message, and the ciphertext is not authenticated.
+/// 
+/// key: A key of 16, 24 or 32 bytes.
+/// iv: The 16 byte initialization vector.
+/// plaintext: The data to encrypt. May be of any length.
+/// 
+/// result: The encrypted data. Always 1 to 16 bytes longer than the plaintext.
+/// 
+#[cfg(feature = "encrypt")]
+pub fn encrypt_bytes<const KEY_BYTES: usize>(key: &[u8; KEY_BYTES], iv: &[u8; 16], plaintext: &[u8]) -> Vec<u8> {
+    cbc::encrypt(&Aes::new(key), iv, plaintext)
+}
+
+///
+/// Decrypts data encrypted with encrypt_bytes and removes the padding.
+/// 
+/// key: The key used when encrypting.
+/// iv: The 16 byte initialization vector used when encrypting.
+/// ciphertext: The data to decrypt. Must be a non empty multiple of 16 bytes.
+/// 
+/// result: The decrypted data, AesError::InvalidDataLength if the ciphertext is
+///         empty or not a multiple of 16 bytes or AesError::InvalidPadding if
+///         the padding is not valid.
+/// 
+#[cfg(feature = "decrypt")]
+pub fn decrypt_bytes<const KEY_BYTES: usize>(key: &[u8; KEY_BYTES], iv: &[u8; 16], ciphertext: &[u8]) -> Result<Vec<u8>, AesError> {
+    cbc::decrypt(&Aes::new(key), iv, ciphertext)
+}
+
+impl<const KEY_BYTES: usize> fmt::Debug for Aes<KEY_BYTES> {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct(&format!("Aes<{}>", KEY_BYTES))
+            .field("mode", &self.mode())
+            .field("key", &Redacted(KEY_BYTES))
+            .finish()
+    }
+
+}
+
+#[cfg(all(test, feature = "encrypt", feature = "decrypt"))]
+mod tests {
+
+    use super::*;
+    use crate::ecb;
+    use crate::test_util::hex;
+
+    fn known_answer<const KEY_BYTES: usize>(key: &[u8; KEY_BYTES], plaintext: &[u8], ciphertext: &[u8]) {
+        let block = AESBlock::<DecryptedState>::try_new(plaintext).unwrap();
+        for mode in [KeyScheduleMode::Precomputed, KeyScheduleMode::OnTheFly] {
+            let aes = Aes::with_mode(key, mode);
+            assert_eq!(mode, aes.mode());
+            let encrypted = aes.encrypt_block(&block);
+            assert_eq!(ciphertext, encrypted.grid);
+            assert_eq!(plaintext, aes.decrypt_block(&encrypted).grid);
+        }
+    }
+
+    #[test]
+    fn test_encrypt_block_128() {
+        known_answer(&[0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f],
+            &[0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff],
+            &[0x69, 0xc4, 0xe0, 0xd8, 0x6a, 0x7b, 0x04, 0x30, 0xd8, 0xcd, 0xb7, 0x80, 0x70, 0xb4, 0xc5, 0x5a]);
+    }
+
+    #[test]
+    fn test_encrypt_block_192() {
+        known_answer(&[0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f,
+              0x10, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17],
+            &[0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff],
+            &[0xdd, 0xa9, 0x7c, 0xa4, 0x86, 0x4c, 0xdf, 0xe0, 0x6e, 0xaf, 0x70, 0xa0, 0xec, 0x0d, 0x71, 0x91]);
+    }
+
+    #[test]
+    fn test_encrypt_block_256() {
+        known_answer(&[0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f,
+              0x10, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17, 0x18, 0x19, 0x1a, 0x1b, 0x1c, 0x1d, 0x1e, 0x1f],
+            &[0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff],
+            &[0x8e, 0xa2, 0xb7, 0xca, 0x51, 0x67, 0x45, 0xbf, 0xea, 0xfc, 0x49, 0x90, 0x4b, 0x49, 0x60, 0x89]);
+    }
+
+    #[test]
+    fn test_export_import_roundkeys() {
+        let key: [u8; 24] = [9; 24];
+        let exported = Aes192::with_mode(&key, KeyScheduleMode::OnTheFly).roundkeys().to_bytes();
+        assert_eq!(Aes192::new(&key).roundkeys().to_bytes(), exported);
+        let imported = Aes192::from_roundkeys(RoundKeys::from_bytes(&exported).unwrap()).unwrap();
+        let block = AESBlock::<DecryptedState>::try_new(&[1; 16]).unwrap();
+        assert_eq!(Aes192::new(&key).encrypt_block(&block).grid, imported.encrypt_block(&block).grid);
+        assert_eq!(Some(AesError::InvalidRoundKeys), Aes128::from_roundkeys(RoundKeys::from_bytes(&exported).unwrap()).err());
+    }
+
+    #[test]
+    fn test_one_shot() {
+        let key = [0x2b, 0x7e, 0x15, 0x16, 0x28, 0xae, 0xd2, 0xa6, 0xab, 0xf7, 0x15, 0x88, 0x09, 0xcf, 0x4f, 0x3c];
+        let iv = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15];
+        let plaintext = [0x6b, 0xc1, 0xbe, 0xe2, 0x2e, 0x40, 0x9f, 0x96, 0xe9, 0x3d, 0x7e, 0x11, 0x73, 0x93, 0x17, 0x2a];
+        let ciphertext = encrypt_bytes(&key, &iv, &plaintext);
+        assert_eq!(32, ciphertext.len());
+        assert_eq!([0x76, 0x49, 0xab, 0xac, 0x81, 0x19, 0xb2, 0x46, 0xce, 0xe9, 0x8e, 0x9b, 0x12, 0xe9, 0x19, 0x7d], ciphertext[..16]);
+        assert_eq!(plaintext.to_vec(), decrypt_bytes(&key, &iv, &ciphertext).unwrap());
+        for length in [0, 1, 15, 17, 100] {
+            let data = vec![9; length];
+            assert_eq!(data, decrypt_bytes(&[5; 32], &iv, &encrypt_bytes(&[5; 32], &iv, &data)).unwrap());
+        }
+        assert_eq!(Some(AesError::InvalidDataLength { length: 20, min: 16, multiple: 16 }), decrypt_bytes(&key, &iv, &ciphertext[..20]).err());
+    }
+
+    #[test]
+    fn test_debug() {
+        assert_eq!("Aes<24> { mode: Precomputed, key: [REDACTED; 24] }", format!("{:?}", Aes192::new(&[0x41; 24])));
+        assert_eq!("Aes<16> { mode: OnTheFly, key: [REDACTED; 16] }", format!("{:?}", Aes128::with_mode(&[0x41; 16], KeyScheduleMode::OnTheFly)));
+    }
+
+    #[test]
+    fn test_in_place() {
+        let plaintext: [u8; 16] = core::array::from_fn(|idx| (idx * 0x11) as u8);
+        let expected = hex("8ea2b7ca516745bfeafc49904b496089");
+        for mode in [KeyScheduleMode::Precomputed, KeyScheduleMode::OnTheFly] {
+            let aes = Aes256::with_mode(&core::array::from_fn(|idx| idx as u8), mode);
+            let mut block = plaintext;
+            aes.encrypt_block_in_place(&mut block);
+            assert_eq!(expected, block);
+            aes.decrypt_block_in_place(&mut block);
+            assert_eq!(plaintext, block);
+        }
+    }
+
+    #[test]
+    fn test_blocks() {
+        let aes = Aes128::new(&[3; 16]);
+        let plaintext: Vec<u8> = (0..64).collect();
+        let mut data = plaintext.clone();
+        aes.encrypt_blocks(&mut data).unwrap();
+        assert_eq!(ecb::encrypt(&aes, &plaintext).unwrap(), data);
+        aes.decrypt_blocks(&mut data).unwrap();
+        assert_eq!(plaintext, data);
+        let mut data = [1; 20];
+        assert_eq!(Err(AesError::InvalidDataLength { length: 20, min: 0, multiple: 16 }), aes.encrypt_blocks(&mut data));
+        assert_eq!(Err(AesError::InvalidDataLength { length: 20, min: 0, multiple: 16 }), aes.decrypt_blocks(&mut data));
+        assert_eq!([1; 20], data);
+        assert_eq!(Ok(()), aes.encrypt_blocks(&mut []));
+    }
+
+    #[test]
+    fn test_reuse_context() {
+        let aes = Aes256::new(&[7; 32]);
+        for value in 0..64u8 {
+            let block = AESBlock::<DecryptedState>::try_new(&[value; 16]).unwrap();
+            let encrypted = aes.encrypt_block(&block);
+            assert_eq!(block.grid, aes.decrypt_block(&encrypted).grid);
+        }
+    }
+
+    fn compare_backends<const KEY_BYTES: usize>(key: &[u8; KEY_BYTES]) {
+        let plaintext: Vec<u8> = (0..=255).collect();
+        for mode in [KeyScheduleMode::Precomputed, KeyScheduleMode::OnTheFly] {
+            let bytewise = Aes::with_mode(key, mode).with_backend(RoundBackend::Bytewise);
+            #[cfg(feature = "portable-simd")]
+            let backends = [RoundBackend::TTable, RoundBackend::Bitsliced, RoundBackend::PortableSimd, RoundBackend::detect()];
+            #[cfg(not(feature = "portable-simd"))]
+            let backends = [RoundBackend::TTable, RoundBackend::Bitsliced, RoundBackend::detect()];
+            for backend in backends {
+                let aes = Aes::with_mode(key, mode).with_backend(backend);
+                assert_eq!(backend, aes.backend());
+                let mut data = plaintext.clone();
+                aes.encrypt_blocks(&mut data).unwrap();
+                assert_eq!(ecb::encrypt(&bytewise, &plaintext).unwrap(), data);
+                aes.decrypt_blocks(&mut data).unwrap();
+                assert_eq!(plaintext, data);
+                let mut block = [5; 16];
+                aes.encrypt_block_in_place(&mut block);
+                assert_eq!(ecb::encrypt(&bytewise, &[5; 16]).unwrap(), block);
+            }
+        }
+    }
+
+    #[test]
+    fn test_backends() {
+        assert_eq!(RoundBackend::detect(), Aes128::new(&[1; 16]).backend());
+        assert!(RoundBackend::detect().is_supported());
+        compare_backends(&[1; 16]);
+        compare_backends(&[2; 24]);
+        compare_backends(&[3; 32]);
+        let block = AESBlock::<DecryptedState>::try_new(&hex("00112233445566778899aabbccddeeff")).unwrap();
+        for backend in [RoundBackend::Bytewise, RoundBackend::TTable, RoundBackend::Bitsliced, RoundBackend::detect()] {
+            let aes = Aes128::new(&hex("000102030405060708090a0b0c0d0e0f").try_into().unwrap()).with_backend(backend);
+            assert_eq!(hex("69c4e0d86a7b0430d8cdb78070b4c55a"), aes.encrypt_block(&block).into_inner());
+        }
+    }
+
+}
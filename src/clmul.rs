@@ -0,0 +1,110 @@
+//!
+//! Carry-less multiplication of the GHASH field elements with PCLMULQDQ on x86_64
+//! and PMULL on aarch64. The instructions multiply two 64 bit polynomials in
+//! constant time, so a field multiplication is four instructions followed by the
+//! reduction, instead of a loop over the 128 bits.
+//!
+//! The instructions are optional, so the support is detected when multiplying.
+//! The functions must only be called when is_supported is true.
+//!
+
+#[cfg(target_arch = "aarch64")]
+use std::arch::aarch64::vmull_p64;
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::{_mm_clmulepi64_si128, _mm_cvtsi64_si128, _mm_storeu_si128};
+
+///
+/// Whether the cpu supports the carry-less multiplication.
+///
+/// result: true if multiply can be used.
+///
+pub(crate) fn is_supported() -> bool {
+    #[cfg(target_arch = "x86_64")]
+    return std::arch::is_x86_feature_detected!("pclmulqdq");
+    #[cfg(target_arch = "aarch64")]
+    return std::arch::is_aarch64_feature_detected!("aes");
+}
+
+///
+/// Multiplies x and y in GF(2^128) with reflected bits, as the multiplication of
+/// GHASH. The 256 bit product is computed from four 64 bit products, shifted one
+/// bit to the left for the reflected bits and reduced.
+///
+/// x: The first factor, the bytes of the field element read as big endian.
+/// y: The second factor, the bytes of the field element read as big endian.
+///
+/// result: The product in the same form as the factors.
+///
+#[cfg_attr(target_arch = "x86_64", target_feature(enable = "pclmulqdq"))]
+#[cfg_attr(target_arch = "aarch64", target_feature(enable = "neon,aes"))]
+pub(crate) unsafe fn multiply(x: u128, y: u128) -> u128 {
+    let (x_high, x_low) = ((x >> 64) as u64, x as u64);
+    let (y_high, y_low) = ((y >> 64) as u64, y as u64);
+    let middle = multiply_words(x_high, y_low) ^ multiply_words(x_low, y_high);
+    let high = multiply_words(x_high, y_high) ^ (middle >> 64);
+    let low = multiply_words(x_low, y_low) ^ (middle << 64);
+    reduce((high << 1) | (low >> 127), low << 1)
+}
+
+///
+/// Multiplies two 64 bit polynomials without carries.
+///
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "pclmulqdq")]
+unsafe fn multiply_words(a: u64, b: u64) -> u128 {
+    let product = _mm_clmulepi64_si128(_mm_cvtsi64_si128(a as i64), _mm_cvtsi64_si128(b as i64), 0x00);
+    let mut bytes = [0u8; 16];
+    _mm_storeu_si128(bytes.as_mut_ptr().cast(), product);
+    u128::from_le_bytes(bytes)
+}
+
+///
+/// Multiplies two 64 bit polynomials without carries.
+///
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "neon,aes")]
+unsafe fn multiply_words(a: u64, b: u64) -> u128 {
+    vmull_p64(a, b)
+}
+
+///
+/// Reduces a 256 bit product with reflected bits by x^128 + x^7 + x^2 + x + 1.
+/// The high half holds the terms below x^128, and the low half the terms from
+/// x^128 up, which are multiplied by x^7 + x^2 + x + 1 with right shifts. The bits
+/// shifted out are multiplied once more.
+///
+/// high: The terms of the product below x^128.
+/// low: The terms of the product from x^128.
+///
+/// result: The reduced product.
+///
+fn reduce(high: u128, low: u128) -> u128 {
+    let overflow = (low << 127) ^ (low << 126) ^ (low << 121);
+    let folded = low ^ overflow;
+    high ^ folded ^ (folded >> 1) ^ (folded >> 2) ^ (folded >> 7)
+}
+
+#[cfg(all(test, feature = "encrypt", feature = "decrypt"))]
+mod tests {
+
+    use super::*;
+    use crate::test_util::hex;
+
+    #[test]
+    fn test_multiply() {
+        if !is_supported() {
+            return;
+        }
+        let one = 1 << 127;
+        let value = u128::from_be_bytes(hex("66e94bd4ef8a2c3b884cfa59ca342b2e").try_into().unwrap());
+        let key = u128::from_be_bytes(hex("b83b533708bf535d0aa6e52980d53b78").try_into().unwrap());
+        // The cpu was checked to support the instructions.
+        unsafe {
+            assert_eq!(value, multiply(value, one));
+            assert_eq!(multiply(value, key), multiply(key, value));
+            assert_eq!(0, multiply(value, 0));
+            assert_eq!(u128::from_be_bytes(hex("ef59fe80701f896bbb9cab5b0908053e").try_into().unwrap()), multiply(value, key));
+        }
+    }
+
+}
@@ -0,0 +1,224 @@
+//!
+//! Cipher-based message authentication code (CMAC), as specified in NIST SP 800-38B
+//! and RFC 4493. CMAC is also known as OMAC1. It is a CBC-MAC where the last block
+//! is xored with one of two subkeys derived from the key, which makes it secure
+//! for messages of any length. The mac can be calculated at once or 
+//! incrementally with a CmacState.
+//! 
+
+use crate::cbc_mac::chain_block;
+use crate::mac::BlockBuffer;
+use crate::zeroize::Zeroize;
+use crate::{Aes, Mac, SecretKey};
+#[cfg(all(feature = "encrypt", feature = "decrypt"))]
+use crate::AesError;
+
+///
+/// Cmac is a CMAC with subkeys derived from the cipher.
+/// 
+pub struct Cmac<const KEY_BYTES: usize = 16> {
+    cipher: Aes<KEY_BYTES>,
+    complete_subkey: [u8; 16],
+    partial_subkey: [u8; 16]
+}
+
+impl<const KEY_BYTES: usize> Cmac<KEY_BYTES> {
+
+    ///
+    /// Creates a new Cmac.
+    /// 
+    /// key: The key of the mac.
+    /// 
+    /// result: A new Cmac with the subkeys derived.
+    /// 
+    pub fn new(key: &[u8; KEY_BYTES]) -> Self {
+        let cipher = Aes::new(key);
+        let complete_subkey = double(&cipher.encrypt_bytes(&[0; 16]).try_into().unwrap());
+        let partial_subkey = double(&complete_subkey);
+        Self { cipher, complete_subkey, partial_subkey }
+    }
+
+    ///
+    /// Calculates the mac of the data.
+    /// 
+    /// data: The data to authenticate. May be of any length.
+    /// 
+    /// result: The 16 byte mac.
+    /// 
+    pub fn mac(&self, data: &[u8]) -> [u8; 16] {
+        let mut state = self.start();
+        state.update(data);
+        state.finalize()
+    }
+
+    ///
+    /// Starts an incremental mac.
+    /// 
+    /// result: A CmacState without any data.
+    /// 
+    pub fn start(&self) -> CmacState<'_, KEY_BYTES> {
+        CmacState { cmac: self, state: [0; 16], buffer: BlockBuffer::new() }
+    }
+
+}
+
+impl<const KEY_BYTES: usize> From<&SecretKey<KEY_BYTES>> for Cmac<KEY_BYTES> {
+
+    fn from(key: &SecretKey<KEY_BYTES>) -> Self {
+        Cmac::new(key.expose_secret())
+    }
+
+}
+
+///
+/// CmacState is an incremental CMAC.
+/// 
+pub struct CmacState<'a, const KEY_BYTES: usize> {
+    cmac: &'a Cmac<KEY_BYTES>,
+    state: [u8; 16],
+    buffer: BlockBuffer
+}
+
+impl<const KEY_BYTES: usize> Mac for CmacState<'_, KEY_BYTES> {
+
+    type Output = [u8; 16];
+
+    fn update(&mut self, data: &[u8]) {
+        let (cipher, state) = (&self.cmac.cipher, &mut self.state);
+        self.buffer.update(data, |block| chain_block(cipher, state, block));
+    }
+
+    fn finalize(mut self) -> Self::Output {
+        let last = finalize_block(self.buffer.last(), &self.cmac.complete_subkey, &self.cmac.partial_subkey);
+        chain_block(&self.cmac.cipher, &mut self.state, &last);
+        self.state
+    }
+
+}
+
+///
+/// Calculates the CMAC of the data over any block encryption, such as a key held
+/// by a KeyBackend.
+/// 
+/// encrypt: Encrypts a 16 byte block with the key of the mac.
+/// data: The data to authenticate. May be of any length.
+/// 
+/// result: The 16 byte mac or the error of encrypt.
+/// 
+#[cfg(all(feature = "encrypt", feature = "decrypt"))]
+pub(crate) fn mac_using<F: Fn(&[u8; 16]) -> Result<[u8; 16], AesError>>(encrypt: F, data: &[u8]) -> Result<[u8; 16], AesError> {
+    let mut complete_subkey = double(&encrypt(&[0; 16])?);
+    let mut partial_subkey = double(&complete_subkey);
+    let full = data.len().saturating_sub(1) / 16 * 16;
+    let mut state = [0; 16];
+    for block in data[..full].as_chunks::<16>().0.iter().chain([&finalize_block(&data[full..], &complete_subkey, &partial_subkey)]) {
+        state.iter_mut().zip(block).for_each(|(a, b)| *a ^= b);
+        state = encrypt(&state)?;
+    }
+    complete_subkey.zeroize();
+    partial_subkey.zeroize();
+    Ok(state)
+}
+
+///
+/// Creates the last block of CMAC and XCBC. A full block is xored with the first
+/// key, a partial block is padded with 0x80 and zeros and xored with the second.
+/// 
+/// last: The last 0 to 16 bytes of the data.
+/// complete_key: The key for a full last block.
+/// partial_key: The key for a partial last block.
+/// 
+/// result: The last block.
+/// 
+pub(crate) fn finalize_block(last: &[u8], complete_key: &[u8; 16], partial_key: &[u8; 16]) -> [u8; 16] {
+    let mut block = [0; 16];
+    block[..last.len()].copy_from_slice(last);
+    let key = if last.len() == 16 {
+        complete_key
+    } else {
+        block[last.len()] = 0x80;
+        partial_key
+    };
+    block.iter_mut().zip(key).for_each(|(a, b)| *a ^= b);
+    block
+}
+
+///
+/// Multiplies the block by x in GF(2^128) with the polynomial x^128 + x^7 + x^2 + x + 1,
+/// where the block is a big endian number.
+/// 
+pub(crate) fn double(block: &[u8; 16]) -> [u8; 16] {
+    let value = u128::from_be_bytes(*block);
+    ((value << 1) ^ ((value >> 127) * 0x87)).to_be_bytes()
+}
+
+impl<const KEY_BYTES: usize> Drop for Cmac<KEY_BYTES> {
+
+    fn drop(&mut self) {
+        self.complete_subkey.zeroize();
+        self.partial_subkey.zeroize();
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::test_util::hex;
+    use crate::AesError;
+
+    const MESSAGE: &str = "6bc1bee22e409f96e93d7e117393172a ae2d8a571e03ac9c9eb76fac45af8e51
+                           30c81c46a35ce411e5fbc1191a0a52ef f69f2445df4f9b17ad2b417be66c3710";
+
+    #[test]
+    fn test_rfc4493_vectors() {
+        let cmac = Cmac::<16>::new(&hex("2b7e151628aed2a6abf7158809cf4f3c").try_into().unwrap());
+        let message = hex(MESSAGE);
+        assert_eq!(hex("bb1d6929e95937287fa37d129b756746"), cmac.mac(&[]));
+        assert_eq!(hex("070a16b46b4d4144f79bdd9dd04a287c"), cmac.mac(&message[..16]));
+        assert_eq!(hex("dfa66747de9ae63030ca32611497c827"), cmac.mac(&message[..40]));
+        assert_eq!(hex("51f0bebf7e3b9d92fc49741779363cfe"), cmac.mac(&message));
+    }
+
+    #[test]
+    fn test_sp800_38b_aes256() {
+        let cmac = Cmac::<32>::new(&hex("603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4").try_into().unwrap());
+        assert_eq!(hex("028962f61b7bf89efc6b551f4667d983"), cmac.mac(&[]));
+        assert_eq!(hex("e1992190549f6ed5696a2c056c315410"), cmac.mac(&hex(MESSAGE)));
+    }
+
+    #[test]
+    fn test_incremental() {
+        let cmac = Cmac::<16>::new(&hex("2b7e151628aed2a6abf7158809cf4f3c").try_into().unwrap());
+        let message = hex(MESSAGE);
+        for split in [0, 1, 15, 16, 17, 32, 40] {
+            let mut state = cmac.start();
+            state.update(&message[..split]);
+            state.update(&message[split..40]);
+            assert_eq!(hex("dfa66747de9ae63030ca32611497c827"), state.finalize());
+        }
+        let mut state = cmac.start();
+        message.chunks(16).for_each(|chunk| state.update(chunk));
+        assert_eq!(hex("51f0bebf7e3b9d92fc49741779363cfe"), state.finalize());
+    }
+
+    #[test]
+    fn test_verify() {
+        let cmac = Cmac::<16>::new(&hex("2b7e151628aed2a6abf7158809cf4f3c").try_into().unwrap());
+        let mut state = cmac.start();
+        state.update(&hex(MESSAGE));
+        assert_eq!(Ok(()), state.verify(&hex("51f0bebf7e3b9d92fc49741779363cfe")));
+        let mut state = cmac.start();
+        state.update(&hex(MESSAGE));
+        assert_eq!(Err(AesError::AuthenticationFailed), state.verify(&hex("51f0bebf7e3b9d92fc49741779363cff")));
+    }
+
+    #[test]
+    fn test_subkeys() {
+        let cmac = Cmac::<16>::new(&hex("2b7e151628aed2a6abf7158809cf4f3c").try_into().unwrap());
+        assert_eq!(hex("fbeed618357133667c85e08f7236a8de"), cmac.complete_subkey);
+        assert_eq!(hex("f7ddac306ae266ccf90bc11ee46d513b"), cmac.partial_subkey);
+    }
+
+}
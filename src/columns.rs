@@ -0,0 +1,227 @@
+//!
+//! The state of a block as four column words. Each column of the grid is a big
+//! endian u32 with the first row in the highest byte, so MixColumns of a column is
+//! a few rotations and xors of one word, and ShiftRows takes each row of a column
+//! from another column with a mask. The bytewise and T-table rounds use the same
+//! layout, so the roundkeys and the conversions are shared between them.
+//!
+
+///
+/// The state of a block, the four columns of the grid as big endian words.
+///
+pub(crate) type Columns = [u32; 4];
+
+///
+/// The bits of each row in a column word.
+///
+const ROW_MASKS: [u32; 4] = [0xff00_0000, 0x00ff_0000, 0x0000_ff00, 0x0000_00ff];
+
+///
+/// Converts 16 bytes in column-major order into four big endian column words.
+///
+/// bytes: The 16 bytes of the grid.
+///
+/// result: The state of the grid.
+///
+pub(crate) fn from_bytes(bytes: &[u8; 16]) -> Columns {
+    std::array::from_fn(|column| u32::from_be_bytes(bytes[column * 4..column * 4 + 4].try_into().unwrap()))
+}
+
+///
+/// Writes the column words back into 16 bytes in column-major order.
+///
+/// state: The state to write.
+/// bytes: The 16 bytes of the grid, replaced by the state.
+///
+pub(crate) fn to_bytes(state: &Columns, bytes: &mut [u8; 16]) {
+    for (column, word) in bytes.chunks_exact_mut(4).zip(state) {
+        column.copy_from_slice(&word.to_be_bytes());
+    }
+}
+
+///
+/// Xors the roundkey into the state.
+///
+/// state: The state the roundkey is added to in place.
+/// roundkey: The roundkey as column words.
+///
+pub(crate) fn add_roundkey(state: &mut Columns, roundkey: &Columns) {
+    state.iter_mut().zip(roundkey).for_each(|(word, key)| *word ^= key);
+}
+
+///
+/// Xors a roundkey of 16 bytes in column-major order into the state.
+///
+/// state: The state the roundkey is added to in place.
+/// roundkey: The 16 bytes of the roundkey.
+///
+pub(crate) fn add_roundkey_bytes(state: &mut Columns, roundkey: &[u8; 16]) {
+    for (word, key) in state.iter_mut().zip(roundkey.as_chunks::<4>().0) {
+        *word ^= u32::from_be_bytes(*key);
+    }
+}
+
+///
+/// Substitutes each byte of the state with the corresponding byte in the s-box.
+///
+/// state: The state substituted in place.
+/// s_box: The s-box or the inverse s-box.
+///
+pub(crate) fn sub_bytes(state: &mut Columns, s_box: &[u8; 256]) {
+    for word in state.iter_mut() {
+        *word = u32::from_be_bytes(word.to_be_bytes().map(|value| s_box[value as usize]));
+    }
+}
+
+///
+/// Shifts the rows of the state to the left. Row r of a column is taken from the
+/// column r * shift to the right, so a shift of 1 is ShiftRows and a shift of 3 is
+/// InvShiftRows.
+///
+/// state: The state shifted in place.
+/// shift: The column offset of the second row, 1 for encryption and 3 for decryption.
+///
+pub(crate) fn shift_rows(state: &mut Columns, shift: usize) {
+    let shifted: Columns = std::array::from_fn(|column| {
+        (0..4).fold(0, |word, row| word | (state[(column + row * shift) % 4] & ROW_MASKS[row]))
+    });
+    *state = shifted;
+}
+
+///
+/// Applies MixColumns to every column of the state.
+///
+/// state: The state mixed in place.
+///
+#[cfg(feature = "encrypt")]
+pub(crate) fn mix_columns(state: &mut Columns) {
+    *state = state.map(mix_column);
+}
+
+///
+/// Applies InvMixColumns to every column of the state.
+///
+/// state: The state mixed in place.
+///
+#[cfg(feature = "decrypt")]
+pub(crate) fn inverse_mix_columns(state: &mut Columns) {
+    *state = state.map(inverse_mix_column);
+}
+
+///
+/// Applies MixColumns to a column word. The first row of the result is
+/// 2 * a0 + 3 * a1 + a2 + a3, which is 2 * (a0 + a1) + a1 + a2 + a3, and the other
+/// rows are the same with the column rotated.
+///
+/// word: The column as a big endian word.
+///
+/// result: The mixed column.
+///
+#[cfg(feature = "encrypt")]
+pub(crate) fn mix_column(word: u32) -> u32 {
+    let rotated = word.rotate_left(8);
+    double(word ^ rotated) ^ rotated ^ word.rotate_left(16) ^ word.rotate_left(24)
+}
+
+///
+/// Applies InvMixColumns to a column word. The inverse matrix is the MixColumns
+/// matrix multiplied by a matrix adding 4 * (a0 + a2) to the even rows and
+/// 4 * (a1 + a3) to the odd rows, so the column is prepared with that and mixed.
+///
+/// word: The column as a big endian word.
+///
+/// result: The inverse mixed column.
+///
+#[cfg(feature = "decrypt")]
+pub(crate) fn inverse_mix_column(word: u32) -> u32 {
+    let prepared = word ^ double(double(word ^ word.rotate_left(16)));
+    let rotated = prepared.rotate_left(8);
+    double(prepared ^ rotated) ^ rotated ^ prepared.rotate_left(16) ^ prepared.rotate_left(24)
+}
+
+///
+/// Multiplies each of the four bytes in the word by 2 in GF(2^8), reducing the bytes
+/// that overflow with the polynomial x^8 + x^4 + x^3 + x + 1.
+///
+fn double(word: u32) -> u32 {
+    ((word & 0x7f7f_7f7f) << 1) ^ (((word >> 7) & 0x0101_0101) * 0x1b)
+}
+
+#[cfg(all(test, feature = "encrypt", feature = "decrypt"))]
+mod tests {
+
+    use super::*;
+    use crate::{AESBlock, DecryptedState, EncryptedState};
+
+    #[test]
+    fn test_conversions() {
+        let grid = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15];
+        let state = from_bytes(&grid);
+        assert_eq!([0x00010203, 0x04050607, 0x08090a0b, 0x0c0d0e0f], state);
+        let mut bytes = [0; 16];
+        to_bytes(&state, &mut bytes);
+        assert_eq!(grid, bytes);
+    }
+
+    #[test]
+    fn test_add_roundkey() {
+        let mut state = from_bytes(&[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]);
+        add_roundkey(&mut state, &from_bytes(&[0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4]));
+        assert_eq!(from_bytes(&[0, 3, 6, 11, 8, 4, 5, 2, 15, 0, 1, 6, 3, 15, 13, 11]), state);
+        add_roundkey_bytes(&mut state, &[0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4]);
+        assert_eq!(from_bytes(&[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]), state);
+    }
+
+    #[test]
+    fn test_shift_rows() {
+        for (row, expected) in [[1, 2, 3, 4], [2, 3, 4, 1], [3, 4, 1, 2], [4, 1, 2, 3]].into_iter().enumerate() {
+            let shift = 8 * (3 - row);
+            let mut state = [1 << shift, 2 << shift, 3 << shift, 4 << shift];
+            shift_rows(&mut state, 1);
+            assert_eq!(expected.map(|value| value << shift), state);
+            shift_rows(&mut state, 3);
+            assert_eq!([1 << shift, 2 << shift, 3 << shift, 4 << shift], state);
+        }
+        let mut state = from_bytes(&[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]);
+        shift_rows(&mut state, 1);
+        assert_eq!(from_bytes(&[0, 5, 10, 15, 4, 9, 14, 3, 8, 13, 2, 7, 12, 1, 6, 11]), state);
+        shift_rows(&mut state, 3);
+        assert_eq!(from_bytes(&[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]), state);
+    }
+
+    #[test]
+    fn test_sub_bytes() {
+        let mut state = from_bytes(&[219, 242, 1, 198, 19, 10, 1, 198, 83, 34, 1, 198, 69, 92, 1, 198]);
+        sub_bytes(&mut state, &AESBlock::<DecryptedState>::S_BOX);
+        assert_eq!(from_bytes(&[185, 137, 124, 180, 125, 103, 124, 180, 237, 147, 124, 180, 110, 74, 124, 180]), state);
+        sub_bytes(&mut state, &AESBlock::<EncryptedState>::INVERSE_S_BOX);
+        assert_eq!(from_bytes(&[219, 242, 1, 198, 19, 10, 1, 198, 83, 34, 1, 198, 69, 92, 1, 198]), state);
+    }
+
+    #[test]
+    fn test_mix_column() {
+        for (column, mixed) in [(0x01010101, 0x01010101), (0xdb135345, 0x8e4da1bc), (0xf20a225c, 0x9fdc589d), (0xc6c6c6c6, 0xc6c6c6c6)] {
+            assert_eq!(mixed, mix_column(column));
+            assert_eq!(column, inverse_mix_column(mixed));
+        }
+        let block = AESBlock::<DecryptedState>::new([0; 16]);
+        for word in (0..1000u32).map(|value| value.wrapping_mul(0x9e3779b9)) {
+            let bytes = word.to_be_bytes();
+            let expected = [[2, 3, 1, 1], [1, 2, 3, 1], [1, 1, 2, 3], [3, 1, 1, 2]].map(|factors| {
+                (0..4).fold(0, |value, row| value ^ block.multiply(factors[row], bytes[row]))
+            });
+            assert_eq!(u32::from_be_bytes(expected), mix_column(word));
+            assert_eq!(word, inverse_mix_column(mix_column(word)));
+        }
+    }
+
+    #[test]
+    fn test_mix_columns() {
+        let mut state = from_bytes(&[219, 19, 83, 69, 242, 10, 34, 92, 1, 1, 1, 1, 198, 198, 198, 198]);
+        mix_columns(&mut state);
+        assert_eq!(from_bytes(&[142, 77, 161, 188, 159, 220, 88, 157, 1, 1, 1, 1, 198, 198, 198, 198]), state);
+        inverse_mix_columns(&mut state);
+        assert_eq!(from_bytes(&[219, 19, 83, 69, 242, 10, 34, 92, 1, 1, 1, 1, 198, 198, 198, 198]), state);
+    }
+
+}
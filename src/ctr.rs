@@ -0,0 +1,575 @@
+//!
+//! Counter (CTR) mode. The cipher encrypts successive counter blocks to produce a
+//! keystream, which is xored with the data. Encryption and decryption are the
+//! same operation, and the data may be of any length.
+//! 
+//! The counter block is the nonce followed by a big endian counter, which is
+//! incremented for every block. The split between the nonce and the counter is
+//! given by a CounterLayout. A counter block must never be used twice with the
+//! same key, or the keystream is reused, so the layout aware functions fail when
+//! the counter overflows into a value already used.
+//! 
+
+use std::vec::Vec;
+
+#[cfg(feature = "parallel")]
+use crate::parallel;
+use crate::block_mode::output_prefix;
+use crate::zeroize::Zeroize;
+use crate::{Aes, AesError, BlockMode};
+
+///
+/// The number of keystream blocks generated at a time, so the counter blocks are
+/// encrypted together with Aes::encrypt_blocks.
+/// 
+const KEYSTREAM_BLOCKS: usize = 16;
+
+///
+/// CounterLayout is the split of the 16 byte counter block into nonce and counter.
+/// Only the counter part is incremented, the nonce is never changed.
+/// 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CounterLayout {
+    ///
+    /// A 12 byte nonce followed by a 32 bit counter, as used by GCM.
+    /// 
+    Nonce96Counter32,
+    ///
+    /// An 8 byte nonce followed by a 64 bit counter.
+    /// 
+    Nonce64Counter64,
+    ///
+    /// The whole block is a 128 bit counter.
+    /// 
+    #[default]
+    Counter128
+}
+
+impl CounterLayout {
+
+    ///
+    /// The number of bytes of the counter, at the end of the counter block.
+    /// 
+    /// result: 4, 8 or 16.
+    /// 
+    pub fn counter_bytes(&self) -> usize {
+        match self {
+            CounterLayout::Nonce96Counter32 => 4,
+            CounterLayout::Nonce64Counter64 => 8,
+            CounterLayout::Counter128 => 16
+        }
+    }
+
+    ///
+    /// The number of bytes of the nonce, at the start of the counter block.
+    /// 
+    /// result: 12, 8 or 0.
+    /// 
+    pub fn nonce_bytes(&self) -> usize {
+        16 - self.counter_bytes()
+    }
+
+    ///
+    /// Creates a counter block from a nonce and an initial counter.
+    /// 
+    /// nonce: The nonce. Must be nonce_bytes long.
+    /// counter: The initial counter. Must fit in counter_bytes.
+    /// 
+    /// result: The counter block, AesError::InvalidNonceLength if the nonce has the 
+    ///         wrong length or AesError::CounterOverflow if the counter is too large.
+    /// 
+    pub fn counter_block(&self, nonce: &[u8], counter: u128) -> Result<[u8; 16], AesError> {
+        if nonce.len() != self.nonce_bytes() {
+            return Err(AesError::InvalidNonceLength { length: nonce.len(), min: self.nonce_bytes(), max: self.nonce_bytes() });
+        }
+        if self.counter_bytes() < 16 && counter >> (self.counter_bytes() * 8) != 0 {
+            return Err(AesError::CounterOverflow);
+        }
+        let mut block = counter.to_be_bytes();
+        block[..nonce.len()].copy_from_slice(nonce);
+        Ok(block)
+    }
+
+}
+
+///
+/// Encrypts the data. Same as apply_keystream.
+/// 
+/// cipher: The cipher used to generate the keystream.
+/// counter: The initial 16 byte counter block.
+/// data: The data to encrypt. May be of any length.
+/// 
+/// result: The encrypted data, with the same length as the data.
+/// 
+pub fn encrypt<const KEY_BYTES: usize>(cipher: &Aes<KEY_BYTES>, counter: &[u8; 16], data: &[u8]) -> Vec<u8> {
+    apply_keystream(cipher, counter, data)
+}
+
+///
+/// Decrypts the data. Same as apply_keystream.
+/// 
+/// cipher: The cipher used to generate the keystream.
+/// counter: The initial 16 byte counter block used when encrypting.
+/// data: The data to decrypt. May be of any length.
+/// 
+/// result: The decrypted data, with the same length as the data.
+/// 
+pub fn decrypt<const KEY_BYTES: usize>(cipher: &Aes<KEY_BYTES>, counter: &[u8; 16], data: &[u8]) -> Vec<u8> {
+    apply_keystream(cipher, counter, data)
+}
+
+///
+/// Xors the data with the keystream starting at the counter block. The first
+/// byte of the data is xored with the first byte of the keystream. A partial
+/// last block uses only the start of its keystream block.
+/// 
+/// cipher: The cipher used to generate the keystream.
+/// counter: The initial 16 byte counter block.
+/// data: The data to xor with the keystream.
+/// 
+/// result: The data xored with the keystream.
+/// 
+pub fn apply_keystream<const KEY_BYTES: usize>(cipher: &Aes<KEY_BYTES>, counter: &[u8; 16], data: &[u8]) -> Vec<u8> {
+    Ctr::init(cipher, counter).apply_keystream(data)
+}
+
+///
+/// Xors the data with the keystream starting at the counter block into an output 
+/// buffer. The output is cleared first, so its capacity is reused and no memory 
+/// is allocated once it is large enough.
+/// 
+/// cipher: The cipher used to generate the keystream.
+/// counter: The initial 16 byte counter block.
+/// data: The data to xor with the keystream.
+/// output: The buffer replaced by the data xored with the keystream.
+/// 
+pub fn apply_keystream_into<const KEY_BYTES: usize>(cipher: &Aes<KEY_BYTES>, counter: &[u8; 16], data: &[u8], output: &mut Vec<u8>) {
+    output.clear();
+    output.extend_from_slice(data);
+    Ctr::init(cipher, counter).apply_keystream_in_place(output);
+}
+
+///
+/// Xors the data with the keystream starting at the counter block into the start
+/// of an output slice.
+/// 
+/// cipher: The cipher used to generate the keystream.
+/// counter: The initial 16 byte counter block.
+/// data: The data to xor with the keystream.
+/// output: The slice the result is written to. Must be at least as long as the data.
+/// 
+/// result: The number of bytes written or AesError::OutputTooShort if the output
+///         is shorter than the data.
+/// 
+pub fn apply_keystream_into_slice<const KEY_BYTES: usize>(cipher: &Aes<KEY_BYTES>, counter: &[u8; 16], data: &[u8], output: &mut [u8]) -> Result<usize, AesError> {
+    let output = output_prefix(output, data.len())?;
+    output.copy_from_slice(data);
+    Ctr::init(cipher, counter).apply_keystream_in_place(output);
+    Ok(data.len())
+}
+
+///
+/// Xors the data with the keystream starting at the counter block, with the chunks
+/// of the data processed in parallel. The counter block of each chunk is computed
+/// from its offset, so the result is the same as with apply_keystream.
+/// 
+/// cipher: The cipher used to generate the keystream.
+/// counter: The initial 16 byte counter block.
+/// data: The data to xor with the keystream.
+/// chunk_size: The bytes processed by a thread at a time, rounded down to a 
+///             multiple of 16, for example parallel::DEFAULT_CHUNK_SIZE.
+/// 
+/// result: The data xored with the keystream.
+/// 
+#[cfg(feature = "parallel")]
+pub fn apply_keystream_parallel<const KEY_BYTES: usize>(cipher: &Aes<KEY_BYTES>, counter: &[u8; 16], data: &[u8], chunk_size: usize) -> Vec<u8> {
+    let mut result = data.to_vec();
+    let chunk_size = (chunk_size / 16).max(1) * 16;
+    parallel::for_each_chunk(&mut result, chunk_size, 16, |index, chunk| {
+        let first_block = (index * chunk_size / 16) as u128;
+        let counter = u128::from_be_bytes(*counter).wrapping_add(first_block).to_be_bytes();
+        Ctr::init(cipher, &counter).apply_keystream_in_place(chunk);
+    });
+    result
+}
+
+///
+/// Xors the data with the keystream starting at the counter block, incrementing
+/// only the counter part of the layout.
+/// 
+/// cipher: The cipher used to generate the keystream.
+/// counter: The initial 16 byte counter block.
+/// layout: The split of the counter block into nonce and counter.
+/// data: The data to xor with the keystream.
+/// 
+/// result: The data xored with the keystream or AesError::CounterOverflow if the
+///         data needs more blocks than the counter has left.
+/// 
+pub fn apply_keystream_with_layout<const KEY_BYTES: usize>(cipher: &Aes<KEY_BYTES>, counter: &[u8; 16], layout: CounterLayout, data: &[u8]) -> Result<Vec<u8>, AesError> {
+    Ctr::with_layout(cipher, counter, layout).process(data)
+}
+
+///
+/// Xors the data with the keystream starting at the counter block over any block
+/// encryption, such as a key held by a KeyBackend. The whole block is a 128 bit 
+/// counter, as with apply_keystream.
+/// 
+/// encrypt: Encrypts a 16 byte counter block with the key.
+/// counter: The initial 16 byte counter block.
+/// data: The data to xor with the keystream.
+/// 
+/// result: The data xored with the keystream or the error of encrypt.
+/// 
+#[cfg(all(feature = "encrypt", feature = "decrypt"))]
+pub(crate) fn apply_keystream_using<F: Fn(&[u8; 16]) -> Result<[u8; 16], AesError>>(encrypt: F, counter: &[u8; 16], data: &[u8]) -> Result<Vec<u8>, AesError> {
+    let mut result = data.to_vec();
+    let mut counter = *counter;
+    for chunk in result.chunks_mut(16) {
+        let mut keystream = encrypt(&counter)?;
+        chunk.iter_mut().zip(keystream).for_each(|(byte, key)| *byte ^= key);
+        keystream.zeroize();
+        increment(&mut counter, CounterLayout::Counter128);
+    }
+    Ok(result)
+}
+
+///
+/// Ctr is the counter mode as a BlockMode. The same mode is used for encryption 
+/// and decryption, and finalize accepts a partial last block. Finalize fails 
+/// with AesError::CounterOverflow if the counter has overflowed and a counter 
+/// block was reused.
+/// 
+/// Ctr also works on a byte level with apply_keystream, and seek moves to any 
+/// byte offset of the keystream without generating the keystream before it.
+/// 
+/// The keystream of up to 16 blocks is generated at a time, so the backends
+/// encrypting several blocks at once are used for long data.
+/// 
+pub struct Ctr<'a, const KEY_BYTES: usize> {
+    cipher: &'a Aes<KEY_BYTES>,
+    initial: [u8; 16],
+    counter: [u8; 16],
+    layout: CounterLayout,
+    keystream: [u8; 16 * KEYSTREAM_BLOCKS],
+    used: usize,
+    available: usize,
+    exhausted: bool,
+    overflowed: bool
+}
+
+impl<'a, const KEY_BYTES: usize> Ctr<'a, KEY_BYTES> {
+
+    ///
+    /// Creates a Ctr with a counter layout. BlockMode::init uses CounterLayout::Counter128.
+    /// 
+    /// cipher: The cipher used to generate the keystream.
+    /// counter: The initial 16 byte counter block.
+    /// layout: The split of the counter block into nonce and counter.
+    /// 
+    /// result: The Ctr mode.
+    /// 
+    pub fn with_layout(cipher: &'a Aes<KEY_BYTES>, counter: &[u8; 16], layout: CounterLayout) -> Self {
+        Self { cipher, initial: *counter, counter: *counter, layout, keystream: [0; 16 * KEYSTREAM_BLOCKS], used: 0, available: 0, exhausted: false, overflowed: false }
+    }
+
+    ///
+    /// Xors the data with the keystream from the current position and moves the 
+    /// position forward by the length of the data.
+    /// 
+    /// data: The data to xor with the keystream. May be of any length.
+    /// 
+    /// result: The data xored with the keystream.
+    /// 
+    pub fn apply_keystream(&mut self, data: &[u8]) -> Vec<u8> {
+        let mut result = data.to_vec();
+        self.apply_keystream_in_place(&mut result);
+        result
+    }
+
+    ///
+    /// Xors the data in place with the keystream from the current position and 
+    /// moves the position forward by the length of the data.
+    /// 
+    /// data: The data to xor with the keystream, replaced by the result.
+    /// 
+    pub fn apply_keystream_in_place(&mut self, data: &mut [u8]) {
+        let mut remaining = data;
+        while !remaining.is_empty() {
+            if self.used == self.available {
+                self.next_keystream(remaining.len().div_ceil(16));
+            }
+            let length = remaining.len().min(self.available - self.used);
+            let (current, rest) = remaining.split_at_mut(length);
+            current.iter_mut().zip(&self.keystream[self.used..]).for_each(|(byte, key)| *byte ^= key);
+            self.used += length;
+            remaining = rest;
+        }
+    }
+
+    ///
+    /// Moves the position to a byte offset from the initial counter block.
+    /// 
+    /// byte_offset: The offset in bytes from the start of the keystream.
+    /// 
+    /// result: Ok or AesError::CounterOverflow if the offset is beyond the 
+    ///         keystream the counter can produce.
+    /// 
+    pub fn seek(&mut self, byte_offset: u64) -> Result<(), AesError> {
+        let nonce_bytes = self.layout.nonce_bytes();
+        let mut initial = [0; 16];
+        initial[nonce_bytes..].copy_from_slice(&self.initial[nonce_bytes..]);
+        let target = u128::from_be_bytes(initial).checked_add((byte_offset / 16) as u128).ok_or(AesError::CounterOverflow)?;
+        let mut counter = target.to_be_bytes();
+        if counter[..nonce_bytes].iter().any(|&byte| byte != 0) {
+            return Err(AesError::CounterOverflow);
+        }
+        counter[..nonce_bytes].copy_from_slice(&self.initial[..nonce_bytes]);
+        self.counter = counter;
+        self.exhausted = false;
+        self.used = 0;
+        self.available = 0;
+        if !byte_offset.is_multiple_of(16) {
+            self.next_keystream(1);
+            self.used = (byte_offset % 16) as usize;
+        }
+        Ok(())
+    }
+
+    ///
+    /// Encrypts the next counter blocks into the keystream and increments the counter
+    /// for each of them. Only the blocks needed are generated, so the counter does
+    /// not overflow on blocks that are never used.
+    /// 
+    /// blocks: The number of blocks needed, at most KEYSTREAM_BLOCKS are generated.
+    /// 
+    fn next_keystream(&mut self, blocks: usize) {
+        let blocks = blocks.min(KEYSTREAM_BLOCKS);
+        for block in self.keystream.chunks_exact_mut(16).take(blocks) {
+            self.overflowed |= self.exhausted;
+            block.copy_from_slice(&self.counter);
+            self.exhausted = increment(&mut self.counter, self.layout);
+        }
+        self.cipher.encrypt_blocks(&mut self.keystream[..blocks * 16]).unwrap();
+        self.used = 0;
+        self.available = blocks * 16;
+    }
+
+}
+
+impl<'a, const KEY_BYTES: usize> BlockMode<'a, KEY_BYTES> for Ctr<'a, KEY_BYTES> {
+
+    fn init(cipher: &'a Aes<KEY_BYTES>, counter: &[u8; 16]) -> Self {
+        Self::with_layout(cipher, counter, CounterLayout::Counter128)
+    }
+
+    fn process_block(&mut self, block: &[u8; 16]) -> [u8; 16] {
+        self.apply_keystream(block).try_into().unwrap()
+    }
+
+    fn finalize(mut self, remaining: &[u8]) -> Result<Vec<u8>, AesError> {
+        let result = self.apply_keystream(remaining);
+        if self.overflowed {
+            return Err(AesError::CounterOverflow);
+        }
+        Ok(result)
+    }
+
+}
+
+impl<const KEY_BYTES: usize> Drop for Ctr<'_, KEY_BYTES> {
+
+    fn drop(&mut self) {
+        self.keystream.zeroize();
+    }
+
+}
+
+///
+/// Increments the counter part of the counter block as a big endian integer. 
+/// Wraps around to zero after the maximum value.
+/// 
+/// result: Whether the counter wrapped around.
+/// 
+fn increment(counter: &mut [u8; 16], layout: CounterLayout) -> bool {
+    for byte in counter[layout.nonce_bytes()..].iter_mut().rev() {
+        *byte = byte.wrapping_add(1);
+        if *byte != 0 {
+            return false;
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::test_util::hex;
+    use crate::{Aes128, Aes192, Aes256};
+
+    const COUNTER: [u8; 16] = [0xf0, 0xf1, 0xf2, 0xf3, 0xf4, 0xf5, 0xf6, 0xf7, 0xf8, 0xf9, 0xfa, 0xfb, 0xfc, 0xfd, 0xfe, 0xff];
+    const PLAINTEXT: &str = "6bc1bee22e409f96e93d7e117393172a ae2d8a571e03ac9c9eb76fac45af8e51
+                             30c81c46a35ce411e5fbc1191a0a52ef f69f2445df4f9b17ad2b417be66c3710";
+
+    #[test]
+    fn test_ctr_aes128() {
+        let cipher = Aes128::new(&hex("2b7e151628aed2a6abf7158809cf4f3c").try_into().unwrap());
+        let expected = hex("874d6191b620e3261bef6864990db6ce 9806f66b7970fdff8617187bb9fffdff
+                            5ae4df3edbd5d35e5b4f09020db03eab 1e031dda2fbe03d1792170a0f3009cee");
+        assert_eq!(expected, encrypt(&cipher, &COUNTER, &hex(PLAINTEXT)));
+        assert_eq!(hex(PLAINTEXT), decrypt(&cipher, &COUNTER, &expected));
+    }
+
+    #[test]
+    fn test_ctr_aes192() {
+        let cipher = Aes192::new(&hex("8e73b0f7da0e6452c810f32b809079e562f8ead2522c6b7b").try_into().unwrap());
+        let expected = hex("1abc932417521ca24f2b0459fe7e6e0b 090339ec0aa6faefd5ccc2c6f4ce8e94
+                            1e36b26bd1ebc670d1bd1d665620abf7 4f78a7f6d29809585a97daec58c6b050");
+        assert_eq!(expected, encrypt(&cipher, &COUNTER, &hex(PLAINTEXT)));
+        assert_eq!(hex(PLAINTEXT), decrypt(&cipher, &COUNTER, &expected));
+    }
+
+    #[test]
+    fn test_ctr_aes256() {
+        let cipher = Aes256::new(&hex("603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4").try_into().unwrap());
+        let expected = hex("601ec313775789a5b7a7f504bbf3d228 f443e3ca4d62b59aca84e990cacaf5c5
+                            2b0930daa23de94ce87017ba2d84988d dfc9c58db67aada613c2dd08457941a6");
+        assert_eq!(expected, encrypt(&cipher, &COUNTER, &hex(PLAINTEXT)));
+        assert_eq!(hex(PLAINTEXT), decrypt(&cipher, &COUNTER, &expected));
+    }
+
+    #[test]
+    fn test_ctr_partial_block() {
+        let cipher = Aes128::new(&hex("2b7e151628aed2a6abf7158809cf4f3c").try_into().unwrap());
+        let plaintext = hex(PLAINTEXT);
+        let full = encrypt(&cipher, &COUNTER, &plaintext);
+        for length in 0..plaintext.len() {
+            assert_eq!(full[..length].to_vec(), encrypt(&cipher, &COUNTER, &plaintext[..length]));
+        }
+    }
+
+    #[test]
+    fn test_ctr_counter_wraps() {
+        let cipher = Aes128::new(&[0; 16]);
+        let expected = hex("3f5b8cc9ea855a0afa7347d23e8d664e 66e94bd4");
+        assert_eq!(expected, apply_keystream(&cipher, &[0xff; 16], &[0; 20]));
+    }
+
+    #[test]
+    fn test_ctr_into() {
+        let cipher = Aes128::new(&hex("2b7e151628aed2a6abf7158809cf4f3c").try_into().unwrap());
+        let plaintext = hex(PLAINTEXT);
+        let expected = encrypt(&cipher, &COUNTER, &plaintext);
+        let mut output = Vec::new();
+        for length in [64, 40, 0, 17] {
+            apply_keystream_into(&cipher, &COUNTER, &plaintext[..length], &mut output);
+            assert_eq!(expected[..length], output);
+        }
+        let mut slice = [0; 70];
+        assert_eq!(Ok(17), apply_keystream_into_slice(&cipher, &COUNTER, &plaintext[..17], &mut slice));
+        assert_eq!(expected[..17], slice[..17]);
+        assert_eq!(Ok(64), apply_keystream_into_slice(&cipher, &COUNTER, &expected, &mut slice));
+        assert_eq!(plaintext, slice[..64]);
+        assert_eq!(Some(AesError::OutputTooShort { needed: 64, given: 63 }), apply_keystream_into_slice(&cipher, &COUNTER, &plaintext, &mut [0; 63]).err());
+    }
+
+    #[test]
+    fn test_ctr_keystream_blocks() {
+        let cipher = Aes128::new(&hex("2b7e151628aed2a6abf7158809cf4f3c").try_into().unwrap());
+        let mut counter = COUNTER;
+        let mut expected = Vec::new();
+        for _ in 0..KEYSTREAM_BLOCKS * 3 {
+            expected.extend(cipher.encrypt_bytes(&counter));
+            increment(&mut counter, CounterLayout::Counter128);
+        }
+        assert_eq!(expected, apply_keystream(&cipher, &COUNTER, &vec![0; expected.len()]));
+        let mut ctr = Ctr::init(&cipher, &COUNTER);
+        let mut chunked = Vec::new();
+        for length in [1, 15, 17, 255, 300].into_iter().cycle().take(10) {
+            let start = chunked.len();
+            chunked.extend(ctr.apply_keystream(&vec![0; length.min(expected.len() - start)]));
+        }
+        assert_eq!(expected, chunked);
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_ctr_parallel() {
+        let cipher = Aes128::new(&hex("2b7e151628aed2a6abf7158809cf4f3c").try_into().unwrap());
+        let data: Vec<u8> = (0..5000).map(|value| value as u8).collect();
+        let expected = apply_keystream(&cipher, &COUNTER, &data);
+        for chunk_size in [1, 16, 100, 1024, parallel::DEFAULT_CHUNK_SIZE] {
+            assert_eq!(expected, apply_keystream_parallel(&cipher, &COUNTER, &data, chunk_size));
+        }
+        assert_eq!(apply_keystream(&cipher, &[0xff; 16], &[0; 40]), apply_keystream_parallel(&cipher, &[0xff; 16], &[0; 40], 16));
+    }
+
+    #[test]
+    fn test_ctr_layouts() {
+        let cipher = Aes128::new(&hex("2b7e151628aed2a6abf7158809cf4f3c").try_into().unwrap());
+        let plaintext = hex(PLAINTEXT);
+        let expected = encrypt(&cipher, &COUNTER, &plaintext);
+        for layout in [CounterLayout::Nonce96Counter32, CounterLayout::Nonce64Counter64, CounterLayout::Counter128] {
+            assert_eq!(expected, apply_keystream_with_layout(&cipher, &COUNTER, layout, &plaintext).unwrap());
+        }
+        let mut counter = [0xff; 16];
+        counter[..12].copy_from_slice(&[1; 12]);
+        let mut wrapped = [1; 16];
+        wrapped[12..].copy_from_slice(&[0; 4]);
+        let expected: Vec<u8> = encrypt(&cipher, &counter, &[0; 16]).into_iter().chain(encrypt(&cipher, &wrapped, &[0; 16])).collect();
+        let mut ctr = Ctr::with_layout(&cipher, &counter, CounterLayout::Nonce96Counter32);
+        assert_eq!(expected[..16], ctr.process_block(&[0; 16]));
+        assert_eq!(expected[16..], ctr.process_block(&[0; 16]));
+    }
+
+    #[test]
+    fn test_ctr_overflow() {
+        let cipher = Aes128::new(&[0; 16]);
+        let counter = CounterLayout::Nonce96Counter32.counter_block(&[7; 12], 0xffffffff).unwrap();
+        assert!(apply_keystream_with_layout(&cipher, &counter, CounterLayout::Nonce96Counter32, &[0; 16]).is_ok());
+        assert_eq!(Some(AesError::CounterOverflow), apply_keystream_with_layout(&cipher, &counter, CounterLayout::Nonce96Counter32, &[0; 17]).err());
+        let counter = CounterLayout::Nonce64Counter64.counter_block(&[7; 8], u64::MAX as u128 - 1).unwrap();
+        assert!(apply_keystream_with_layout(&cipher, &counter, CounterLayout::Nonce64Counter64, &[0; 32]).is_ok());
+        assert_eq!(Some(AesError::CounterOverflow), apply_keystream_with_layout(&cipher, &counter, CounterLayout::Nonce64Counter64, &[0; 48]).err());
+        assert_eq!(Some(AesError::CounterOverflow), apply_keystream_with_layout(&cipher, &[0xff; 16], CounterLayout::Counter128, &[0; 20]).err());
+    }
+
+    #[test]
+    fn test_counter_block() {
+        let layout = CounterLayout::Nonce96Counter32;
+        assert_eq!(hex("0102030405060708090a0b0c00000002"), layout.counter_block(&hex("0102030405060708090a0b0c"), 2).unwrap());
+        assert_eq!(Some(AesError::InvalidNonceLength { length: 8, min: 12, max: 12 }), layout.counter_block(&[0; 8], 0).err());
+        assert_eq!(Some(AesError::CounterOverflow), layout.counter_block(&[0; 12], 1 << 32).err());
+        assert_eq!([0xff; 16], CounterLayout::Counter128.counter_block(&[], u128::MAX).unwrap());
+        assert_eq!(CounterLayout::Counter128, CounterLayout::default());
+    }
+
+    #[test]
+    fn test_ctr_seek() {
+        let cipher = Aes128::new(&hex("2b7e151628aed2a6abf7158809cf4f3c").try_into().unwrap());
+        let plaintext = hex(PLAINTEXT);
+        let encrypted = encrypt(&cipher, &COUNTER, &plaintext);
+        let mut ctr = Ctr::init(&cipher, &COUNTER);
+        for (start, end) in [(0, 64), (5, 21), (16, 32), (33, 64), (63, 64), (17, 18)] {
+            ctr.seek(start as u64).unwrap();
+            assert_eq!(plaintext[start..end].to_vec(), ctr.apply_keystream(&encrypted[start..end]));
+        }
+        ctr.seek(3).unwrap();
+        let mut decrypted = ctr.apply_keystream(&encrypted[3..10]);
+        decrypted.extend(ctr.apply_keystream(&encrypted[10..40]));
+        assert_eq!(plaintext[3..40].to_vec(), decrypted);
+    }
+
+    #[test]
+    fn test_ctr_seek_overflow() {
+        let cipher = Aes128::new(&[0; 16]);
+        let counter = CounterLayout::Nonce96Counter32.counter_block(&[7; 12], 0xfffffffe).unwrap();
+        let mut ctr = Ctr::with_layout(&cipher, &counter, CounterLayout::Nonce96Counter32);
+        assert_eq!(Ok(()), ctr.seek(31));
+        assert_eq!(Err(AesError::CounterOverflow), ctr.seek(32));
+        let mut ctr = Ctr::init(&cipher, &[0xff; 16]);
+        assert_eq!(Ok(()), ctr.seek(15));
+        assert_eq!(Err(AesError::CounterOverflow), ctr.seek(16));
+    }
+
+}
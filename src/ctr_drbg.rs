@@ -0,0 +1,282 @@
+//!
+//! CTR_DRBG, the deterministic random bit generator built on the block cipher as
+//! specified in NIST SP 800-90A, with the derivation function. The state is a 
+//! key and a 128 bit counter V, output is the encryption of successive counter 
+//! values and the state is updated after every request.
+//! 
+//! The generator is seeded from entropy input, either given by the caller or read
+//! from the operating system. With prediction resistance enabled it is reseeded
+//! from the operating system before every request.
+//! 
+
+use std::vec::Vec;
+
+use crate::random::fill_random;
+use crate::zeroize::{Zeroize, Zeroizing};
+use crate::{Aes, AesError};
+
+///
+/// The number of requests allowed before the generator must be reseeded.
+/// 
+pub const RESEED_INTERVAL: u64 = 1 << 48;
+
+///
+/// The maximum number of bytes returned by a single request.
+/// 
+pub const MAX_REQUEST_LENGTH: usize = 1 << 16;
+
+///
+/// CtrDrbg is a CTR_DRBG with AES-128, AES-192 or AES-256, with a security 
+/// strength of KEY_BYTES bytes.
+/// 
+pub struct CtrDrbg<const KEY_BYTES: usize = 32> {
+    cipher: Aes<KEY_BYTES>,
+    counter: u128,
+    reseed_counter: u64,
+    prediction_resistance: bool
+}
+
+impl<const KEY_BYTES: usize> CtrDrbg<KEY_BYTES> {
+
+    ///
+    /// Instantiates the generator from the entropy input, the nonce and the 
+    /// personalization string.
+    /// 
+    /// entropy: The entropy input, at least KEY_BYTES bytes.
+    /// nonce: The nonce, at least KEY_BYTES / 2 bytes that are never repeated.
+    /// personalization: The personalization string, may be empty.
+    /// 
+    /// result: The generator or AesError::InsufficientEntropy if the entropy input 
+    ///         is too short.
+    /// 
+    pub fn instantiate(entropy: &[u8], nonce: &[u8], personalization: &[u8]) -> Result<Self, AesError> {
+        if entropy.len() < KEY_BYTES {
+            return Err(AesError::InsufficientEntropy);
+        }
+        let mut drbg = Self { cipher: Aes::new(&[0; KEY_BYTES]), counter: 0, reseed_counter: 1, prediction_resistance: false };
+        drbg.update(&derive::<KEY_BYTES>(&[entropy, nonce, personalization]));
+        Ok(drbg)
+    }
+
+    ///
+    /// Instantiates the generator from entropy input and a nonce read from the 
+    /// operating system.
+    /// 
+    /// personalization: The personalization string, may be empty.
+    /// 
+    /// result: The generator or AesError::RandomUnavailable if the operating 
+    ///         system could not provide the entropy.
+    /// 
+    pub fn from_os(personalization: &[u8]) -> Result<Self, AesError> {
+        let mut entropy = Zeroizing(vec![0; KEY_BYTES + KEY_BYTES / 2]);
+        fill_random(&mut entropy)?;
+        let (entropy, nonce) = entropy.split_at(KEY_BYTES);
+        Self::instantiate(entropy, nonce, personalization)
+    }
+
+    ///
+    /// Enables or disables prediction resistance. When enabled, every request
+    /// first reseeds the generator from the operating system.
+    /// 
+    /// enabled: If prediction resistance is enabled.
+    /// 
+    pub fn set_prediction_resistance(&mut self, enabled: bool) {
+        self.prediction_resistance = enabled;
+    }
+
+    ///
+    /// Reseeds the generator with new entropy input.
+    /// 
+    /// entropy: The entropy input, at least KEY_BYTES bytes.
+    /// additional: The additional input, may be empty.
+    /// 
+    /// result: Ok or AesError::InsufficientEntropy if the entropy input is too 
+    ///         short.
+    /// 
+    pub fn reseed(&mut self, entropy: &[u8], additional: &[u8]) -> Result<(), AesError> {
+        if entropy.len() < KEY_BYTES {
+            return Err(AesError::InsufficientEntropy);
+        }
+        self.update(&derive::<KEY_BYTES>(&[entropy, additional]));
+        self.reseed_counter = 1;
+        Ok(())
+    }
+
+    ///
+    /// Fills the output with random bytes.
+    /// 
+    /// output: The buffer to fill, at most MAX_REQUEST_LENGTH bytes.
+    /// additional: The additional input, may be empty.
+    /// 
+    /// result: Ok, AesError::DataTooLong if the output is too long, 
+    ///         AesError::ReseedRequired if the reseed interval has passed or 
+    ///         AesError::RandomUnavailable if prediction resistance is enabled and
+    ///         the operating system could not provide the entropy.
+    /// 
+    pub fn generate(&mut self, output: &mut [u8], additional: &[u8]) -> Result<(), AesError> {
+        if output.len() > MAX_REQUEST_LENGTH {
+            return Err(AesError::DataTooLong);
+        }
+        if self.prediction_resistance {
+            let mut entropy = [0; KEY_BYTES];
+            fill_random(&mut entropy)?;
+            self.reseed(&entropy, additional)?;
+            return self.generate_block(output, &[]);
+        }
+        if self.reseed_counter > RESEED_INTERVAL {
+            return Err(AesError::ReseedRequired);
+        }
+        self.generate_block(output, additional)
+    }
+
+    ///
+    /// The generate function of the specification, after the reseed checks.
+    /// 
+    fn generate_block(&mut self, output: &mut [u8], additional: &[u8]) -> Result<(), AesError> {
+        let additional = if additional.is_empty() {
+            vec![0; KEY_BYTES + 16]
+        } else {
+            let additional = derive::<KEY_BYTES>(&[additional]);
+            self.update(&additional);
+            additional
+        };
+        for chunk in output.chunks_mut(16) {
+            let block = self.next_block();
+            chunk.copy_from_slice(&block[..chunk.len()]);
+        }
+        self.update(&additional);
+        self.reseed_counter += 1;
+        Ok(())
+    }
+
+    ///
+    /// Increments the counter and encrypts it.
+    /// 
+    fn next_block(&mut self) -> Vec<u8> {
+        self.counter = self.counter.wrapping_add(1);
+        self.cipher.encrypt_bytes(&self.counter.to_be_bytes())
+    }
+
+    ///
+    /// The update function, replacing the key and the counter with the next 
+    /// KEY_BYTES + 16 bytes of output xored with the provided data.
+    /// 
+    fn update(&mut self, provided: &[u8]) {
+        let mut temp = Vec::with_capacity(KEY_BYTES + 32);
+        while temp.len() < KEY_BYTES + 16 {
+            temp.extend(self.next_block());
+        }
+        temp.iter_mut().zip(provided).for_each(|(a, b)| *a ^= b);
+        self.cipher = Aes::new(temp[..KEY_BYTES].try_into().unwrap());
+        self.counter = u128::from_be_bytes(temp[KEY_BYTES..KEY_BYTES + 16].try_into().unwrap());
+        temp.zeroize();
+    }
+
+}
+
+///
+/// The block cipher derivation function, compressing the concatenated inputs to
+/// KEY_BYTES + 16 bytes of seed material.
+/// 
+fn derive<const KEY_BYTES: usize>(inputs: &[&[u8]]) -> Vec<u8> {
+    let seed_length = KEY_BYTES + 16;
+    let input_length: usize = inputs.iter().map(|input| input.len()).sum();
+    let mut data = Vec::with_capacity(input_length + 25);
+    data.extend_from_slice(&(input_length as u32).to_be_bytes());
+    data.extend_from_slice(&(seed_length as u32).to_be_bytes());
+    inputs.iter().for_each(|input| data.extend_from_slice(input));
+    data.push(0x80);
+    data.resize(data.len().next_multiple_of(16), 0);
+    let key: Vec<u8> = (0..KEY_BYTES as u8).collect();
+    let cipher = Aes::<KEY_BYTES>::new(key.as_slice().try_into().unwrap());
+    let mut temp = Vec::with_capacity(seed_length + 16);
+    for index in 0..seed_length.div_ceil(16) as u32 {
+        let mut chain = [0; 16];
+        chain[..4].copy_from_slice(&index.to_be_bytes());
+        chain = cipher.encrypt_bytes(&chain).try_into().unwrap();
+        for block in data.chunks(16) {
+            chain.iter_mut().zip(block).for_each(|(a, b)| *a ^= b);
+            chain = cipher.encrypt_bytes(&chain).try_into().unwrap();
+        }
+        temp.extend_from_slice(&chain);
+    }
+    let cipher = Aes::<KEY_BYTES>::new(temp[..KEY_BYTES].try_into().unwrap());
+    let mut block = temp[KEY_BYTES..KEY_BYTES + 16].to_vec();
+    let mut seed = Vec::with_capacity(seed_length + 16);
+    while seed.len() < seed_length {
+        block = cipher.encrypt_bytes(&block);
+        seed.extend_from_slice(&block);
+    }
+    seed.truncate(seed_length);
+    seed
+}
+
+impl<const KEY_BYTES: usize> Drop for CtrDrbg<KEY_BYTES> {
+
+    fn drop(&mut self) {
+        self.counter.zeroize();
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::test_util::hex;
+
+    #[test]
+    fn test_cavs_aes128_use_df() {
+        let mut drbg = CtrDrbg::<16>::instantiate(&hex("890eb067acf7382eff80b0c73bc872c6"), &hex("aad471ef3ef1d203"), &[]).unwrap();
+        let mut output = [0; 64];
+        drbg.generate(&mut output, &[]).unwrap();
+        drbg.generate(&mut output, &[]).unwrap();
+        assert_eq!(hex("a5514ed7095f64f3d0d3a5760394ab42062f373a25072a6ea6bcfd8489e94af6
+                        cf18659fea22ed1ca0a9e33f718b115ee536b12809c31b72b08ddd8be1910fa3"), output);
+    }
+
+    #[test]
+    fn test_aes256_additional_input_and_reseed() {
+        let entropy: Vec<u8> = (0..80).collect();
+        let mut drbg = CtrDrbg::<32>::instantiate(&entropy[..32], &entropy[32..48], b"personal").unwrap();
+        let mut output = [0; 32];
+        drbg.generate(&mut output, b"first").unwrap();
+        assert_eq!(hex("41de2c06a144b51be8ce6dc9c5ffbd7102ae8d632e1d0fee574eb4ff0cdacd86"), output);
+        drbg.reseed(&entropy[48..], b"again").unwrap();
+        let mut output = [0; 40];
+        drbg.generate(&mut output, &[]).unwrap();
+        assert_eq!(hex("d3c9d682d9084d8322b27dac2279e8c83a26ee3d617f9013212a94b0a6ebe1afa7ab7c4b976702c5"), output);
+    }
+
+    #[test]
+    fn test_prediction_resistance() {
+        let mut drbg = CtrDrbg::<32>::instantiate(&[1; 32], &[2; 16], &[]).unwrap();
+        let mut twin = CtrDrbg::<32>::instantiate(&[1; 32], &[2; 16], &[]).unwrap();
+        drbg.set_prediction_resistance(true);
+        let (mut first, mut second) = ([0; 32], [0; 32]);
+        drbg.generate(&mut first, &[]).unwrap();
+        twin.generate(&mut second, &[]).unwrap();
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn test_from_os() {
+        let (mut first, mut second) = ([0; 32], [0; 32]);
+        CtrDrbg::<16>::from_os(&[]).unwrap().generate(&mut first, &[]).unwrap();
+        CtrDrbg::<16>::from_os(&[]).unwrap().generate(&mut second, &[]).unwrap();
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn test_limits() {
+        assert_eq!(Some(AesError::InsufficientEntropy), CtrDrbg::<32>::instantiate(&[0; 31], &[0; 16], &[]).err());
+        let mut drbg = CtrDrbg::<16>::instantiate(&[0; 16], &[0; 8], &[]).unwrap();
+        assert_eq!(Err(AesError::InsufficientEntropy), drbg.reseed(&[0; 15], &[]));
+        assert_eq!(Err(AesError::DataTooLong), drbg.generate(&mut vec![0; MAX_REQUEST_LENGTH + 1], &[]));
+        drbg.reseed_counter = RESEED_INTERVAL + 1;
+        assert_eq!(Err(AesError::ReseedRequired), drbg.generate(&mut [0; 16], &[]));
+        drbg.reseed(&[0; 16], &[]).unwrap();
+        assert_eq!(Ok(()), drbg.generate(&mut [0; 16], &[]));
+    }
+
+}
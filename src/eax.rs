@@ -0,0 +1,155 @@
+//!
+//! EAX mode, an authenticated encryption mode built from counter mode and OMAC 
+//! (CMAC), as specified by Bellare, Rogaway and Wagner. The nonce, the header and
+//! the ciphertext are each authenticated with OMAC tweaked by a different prefix 
+//! block, and the tag is the xor of the three macs.
+//! 
+//! The nonce may be of any length, but must never be used twice with the same key.
+//! 
+
+use std::vec::Vec;
+
+use crate::cmac::Cmac;
+use crate::{ctr, verify_tag, Aead, Aes, AesError, SecretKey};
+
+///
+/// Eax is an EAX cipher with 16 byte tags.
+/// 
+pub struct Eax<const KEY_BYTES: usize = 16> {
+    cipher: Aes<KEY_BYTES>,
+    mac: Cmac<KEY_BYTES>
+}
+
+impl<const KEY_BYTES: usize> Eax<KEY_BYTES> {
+
+    ///
+    /// Creates a new Eax cipher.
+    /// 
+    /// key: The key used for both counter mode and OMAC.
+    /// 
+    /// result: A new Eax cipher.
+    /// 
+    pub fn new(key: &[u8; KEY_BYTES]) -> Self {
+        Self { cipher: Aes::new(key), mac: Cmac::new(key) }
+    }
+
+    ///
+    /// Encrypts and authenticates the plaintext.
+    /// 
+    /// nonce: The nonce, of any length.
+    /// header: The associated data, authenticated but not encrypted.
+    /// plaintext: The data to encrypt.
+    /// 
+    /// result: The ciphertext followed by the 16 byte tag.
+    /// 
+    pub fn encrypt(&self, nonce: &[u8], header: &[u8], plaintext: &[u8]) -> Vec<u8> {
+        let nonce_mac = self.omac(0, nonce);
+        let mut encrypted = ctr::apply_keystream(&self.cipher, &nonce_mac, plaintext);
+        let tag = self.tag(&nonce_mac, header, &encrypted);
+        encrypted.extend_from_slice(&tag);
+        encrypted
+    }
+
+    ///
+    /// Decrypts the ciphertext and verifies the tag.
+    /// 
+    /// nonce: The nonce used when encrypting.
+    /// header: The associated data used when encrypting.
+    /// ciphertext: The ciphertext followed by the 16 byte tag.
+    /// 
+    /// result: The plaintext or AesError::AuthenticationFailed if the tag does not 
+    ///         match.
+    /// 
+    pub fn decrypt(&self, nonce: &[u8], header: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, AesError> {
+        if ciphertext.len() < 16 {
+            return Err(AesError::AuthenticationFailed);
+        }
+        let (ciphertext, tag) = ciphertext.split_at(ciphertext.len() - 16);
+        let nonce_mac = self.omac(0, nonce);
+        verify_tag(tag, &self.tag(&nonce_mac, header, ciphertext))?;
+        Ok(ctr::apply_keystream(&self.cipher, &nonce_mac, ciphertext))
+    }
+
+    ///
+    /// Calculates the tag from the nonce mac, the header and the ciphertext.
+    /// 
+    fn tag(&self, nonce_mac: &[u8; 16], header: &[u8], ciphertext: &[u8]) -> [u8; 16] {
+        let header_mac = self.omac(1, header);
+        let ciphertext_mac = self.omac(2, ciphertext);
+        std::array::from_fn(|index| nonce_mac[index] ^ header_mac[index] ^ ciphertext_mac[index])
+    }
+
+    ///
+    /// Calculates OMAC of the data prefixed by a block with the tweak in the last byte.
+    /// 
+    fn omac(&self, tweak: u8, data: &[u8]) -> [u8; 16] {
+        let mut prefixed = vec![0; 16];
+        prefixed[15] = tweak;
+        prefixed.extend_from_slice(data);
+        self.mac.mac(&prefixed)
+    }
+
+}
+
+impl<const KEY_BYTES: usize> From<&SecretKey<KEY_BYTES>> for Eax<KEY_BYTES> {
+
+    fn from(key: &SecretKey<KEY_BYTES>) -> Self {
+        Eax::new(key.expose_secret())
+    }
+
+}
+
+impl<const KEY_BYTES: usize> Aead for Eax<KEY_BYTES> {
+
+    fn encrypt(&self, nonce: &[u8], aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, AesError> {
+        Ok(Eax::encrypt(self, nonce, aad, plaintext))
+    }
+
+    fn decrypt(&self, nonce: &[u8], aad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, AesError> {
+        Eax::decrypt(self, nonce, aad, ciphertext)
+    }
+
+    fn tag_length(&self) -> usize {
+        16
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::test_util::hex;
+
+    fn known_answer(key: &str, nonce: &str, header: &str, plaintext: &str, expected: &str) {
+        let eax = Eax::<16>::new(&hex(key).try_into().unwrap());
+        assert_eq!(hex(expected), eax.encrypt(&hex(nonce), &hex(header), &hex(plaintext)));
+        assert_eq!(hex(plaintext), eax.decrypt(&hex(nonce), &hex(header), &hex(expected)).unwrap());
+    }
+
+    #[test]
+    fn test_eax_vectors() {
+        known_answer("233952dee4d5ed5f9b9c6d6ff80ff478", "62ec67f9c3a4a407fcb2a8c49031a8b3", "6bfb914fd07eae6b", "",
+            "e037830e8389f27b025a2d6527e79d01");
+        known_answer("91945d3f4dcbee0bf45ef52255f095a4", "becaf043b0a23d843194ba972c66debd", "fa3bfd4806eb53fa", "f7fb",
+            "19dd5c4c9331049d0bdab0277408f67967e5");
+        known_answer("01f74ad64077f2e704c0f60ada3dd523", "70c3db4f0d26368400a10ed05d2bff5e", "234a3463c1264ac6", "1a47cb4933",
+            "d851d5bae03a59f238a23e39199dc9266626c40f80");
+        known_answer("d07cf6cbb7f313bdde66b727afd3c5e8", "8408dfff3c1a2b1292dc199e46b7d617", "33cce2eabff5a79d", "481c9e39b1",
+            "632a9d131ad4c168a4225d8e1ff755939974a7bede");
+        known_answer("8395fcf1e95bebd697bd010bc766aac3", "22e7add93cfc6393c57ec0b3c17d6b44", "126735fcc320d25a",
+            "ca40d7446e545ffaed3bd12a740a659ffbbb3ceab7", "cb8920f87a6c75cff39627b56e3ed197c552d295a7cfc46afc253b4652b1af3795b124ab6e");
+    }
+
+    #[test]
+    fn test_eax_authentication_failed() {
+        let eax = Eax::<16>::new(&[0; 16]);
+        let mut encrypted = eax.encrypt(b"nonce", b"header", b"payload");
+        assert_eq!(Some(AesError::AuthenticationFailed), eax.decrypt(b"nonce", b"headex", &encrypted).err());
+        assert_eq!(Some(AesError::AuthenticationFailed), eax.decrypt(b"nonc", b"header", &encrypted).err());
+        encrypted[0] ^= 1;
+        assert_eq!(Some(AesError::AuthenticationFailed), eax.decrypt(b"nonce", b"header", &encrypted).err());
+        assert_eq!(Some(AesError::AuthenticationFailed), eax.decrypt(b"nonce", b"header", &[0; 15]).err());
+    }
+
+}
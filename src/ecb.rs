@@ -0,0 +1,398 @@
+//!
+//! Electronic codebook (ECB) mode. Each 16 byte block is encrypted independently
+//! with the same key. The padded functions add and remove PKCS#7 padding, so 
+//! data of any length can be encrypted, and the with_padding functions take any
+//! other padding scheme.
+//! 
+//! WARNING: ECB is not secure for structured data. Identical plaintext blocks give
+//! identical ciphertext blocks, so patterns in the plaintext are visible in the
+//! ciphertext. It is meant as a building block for other modes and for testing
+//! against known answer vectors.
+//! 
+
+use std::vec::Vec;
+
+use crate::block_mode::{finalize_full_blocks, output_prefix};
+use crate::padding::{self, Padding, Pkcs7};
+#[cfg(feature = "parallel")]
+use crate::parallel;
+use crate::{Aes, AesError, BlockMode};
+
+///
+/// Encrypts the data block by block. The blocks are encrypted in one call to 
+/// Aes::encrypt_blocks, so the backends handling several blocks at once are used.
+/// 
+/// cipher: The cipher used to encrypt each block.
+/// data: The data to encrypt. Must be a multiple of 16 bytes.
+/// 
+/// result: The encrypted data or AesError::InvalidDataLength if the data is not
+///         a multiple of 16 bytes.
+/// 
+#[cfg(feature = "encrypt")]
+pub fn encrypt<const KEY_BYTES: usize>(cipher: &Aes<KEY_BYTES>, data: &[u8]) -> Result<Vec<u8>, AesError> {
+    let mut result = Vec::new();
+    encrypt_into(cipher, data, &mut result)?;
+    Ok(result)
+}
+
+///
+/// Decrypts the data block by block. The blocks are decrypted in one call to 
+/// Aes::decrypt_blocks, so the backends handling several blocks at once are used.
+/// 
+/// cipher: The cipher used to decrypt each block.
+/// data: The data to decrypt. Must be a multiple of 16 bytes.
+/// 
+/// result: The decrypted data or AesError::InvalidDataLength if the data is not
+///         a multiple of 16 bytes.
+/// 
+#[cfg(feature = "decrypt")]
+pub fn decrypt<const KEY_BYTES: usize>(cipher: &Aes<KEY_BYTES>, data: &[u8]) -> Result<Vec<u8>, AesError> {
+    let mut result = Vec::new();
+    decrypt_into(cipher, data, &mut result)?;
+    Ok(result)
+}
+
+///
+/// Encrypts the data block by block into an output buffer. The output is cleared
+/// first, so its capacity is reused and no memory is allocated once it is large 
+/// enough.
+/// 
+/// cipher: The cipher used to encrypt each block.
+/// data: The data to encrypt. Must be a multiple of 16 bytes.
+/// output: The buffer replaced by the encrypted data. Left empty on an error.
+/// 
+/// result: Ok or AesError::InvalidDataLength if the data is not a multiple of 
+///         16 bytes.
+/// 
+#[cfg(feature = "encrypt")]
+pub fn encrypt_into<const KEY_BYTES: usize>(cipher: &Aes<KEY_BYTES>, data: &[u8], output: &mut Vec<u8>) -> Result<(), AesError> {
+    output.clear();
+    if !data.len().is_multiple_of(16) {
+        return Err(AesError::InvalidDataLength { length: data.len(), min: 0, multiple: 16 });
+    }
+    output.extend_from_slice(data);
+    cipher.encrypt_blocks(output)
+}
+
+///
+/// Decrypts the data block by block into an output buffer. The output is cleared
+/// first, so its capacity is reused and no memory is allocated once it is large 
+/// enough.
+/// 
+/// cipher: The cipher used to decrypt each block.
+/// data: The data to decrypt. Must be a multiple of 16 bytes.
+/// output: The buffer replaced by the decrypted data. Left empty on an error.
+/// 
+/// result: Ok or AesError::InvalidDataLength if the data is not a multiple of 
+///         16 bytes.
+/// 
+#[cfg(feature = "decrypt")]
+pub fn decrypt_into<const KEY_BYTES: usize>(cipher: &Aes<KEY_BYTES>, data: &[u8], output: &mut Vec<u8>) -> Result<(), AesError> {
+    output.clear();
+    if !data.len().is_multiple_of(16) {
+        return Err(AesError::InvalidDataLength { length: data.len(), min: 0, multiple: 16 });
+    }
+    output.extend_from_slice(data);
+    cipher.decrypt_blocks(output)
+}
+
+///
+/// Encrypts the data block by block into the start of an output slice.
+/// 
+/// cipher: The cipher used to encrypt each block.
+/// data: The data to encrypt. Must be a multiple of 16 bytes.
+/// output: The slice the encrypted data is written to. Must be at least as long
+///         as the data.
+/// 
+/// result: The number of bytes written, AesError::InvalidDataLength if the data
+///         is not a multiple of 16 bytes or AesError::OutputTooShort if the 
+///         output is shorter than the data.
+/// 
+#[cfg(feature = "encrypt")]
+pub fn encrypt_into_slice<const KEY_BYTES: usize>(cipher: &Aes<KEY_BYTES>, data: &[u8], output: &mut [u8]) -> Result<usize, AesError> {
+    if !data.len().is_multiple_of(16) {
+        return Err(AesError::InvalidDataLength { length: data.len(), min: 0, multiple: 16 });
+    }
+    let output = output_prefix(output, data.len())?;
+    output.copy_from_slice(data);
+    cipher.encrypt_blocks(output)?;
+    Ok(data.len())
+}
+
+///
+/// Decrypts the data block by block into the start of an output slice.
+/// 
+/// cipher: The cipher used to decrypt each block.
+/// data: The data to decrypt. Must be a multiple of 16 bytes.
+/// output: The slice the decrypted data is written to. Must be at least as long
+///         as the data.
+/// 
+/// result: The number of bytes written, AesError::InvalidDataLength if the data
+///         is not a multiple of 16 bytes or AesError::OutputTooShort if the 
+///         output is shorter than the data.
+/// 
+#[cfg(feature = "decrypt")]
+pub fn decrypt_into_slice<const KEY_BYTES: usize>(cipher: &Aes<KEY_BYTES>, data: &[u8], output: &mut [u8]) -> Result<usize, AesError> {
+    if !data.len().is_multiple_of(16) {
+        return Err(AesError::InvalidDataLength { length: data.len(), min: 0, multiple: 16 });
+    }
+    let output = output_prefix(output, data.len())?;
+    output.copy_from_slice(data);
+    cipher.decrypt_blocks(output)?;
+    Ok(data.len())
+}
+
+///
+/// Encrypts the data block by block, with the chunks of the data encrypted in
+/// parallel.
+/// 
+/// cipher: The cipher used to encrypt each block.
+/// data: The data to encrypt. Must be a multiple of 16 bytes.
+/// chunk_size: The bytes processed by a thread at a time, rounded down to a 
+///             multiple of 16, for example parallel::DEFAULT_CHUNK_SIZE.
+/// 
+/// result: The encrypted data or AesError::InvalidDataLength if the data is not
+///         a multiple of 16 bytes.
+/// 
+#[cfg(all(feature = "parallel", feature = "encrypt"))]
+pub fn encrypt_parallel<const KEY_BYTES: usize>(cipher: &Aes<KEY_BYTES>, data: &[u8], chunk_size: usize) -> Result<Vec<u8>, AesError> {
+    if !data.len().is_multiple_of(16) {
+        return Err(AesError::InvalidDataLength { length: data.len(), min: 0, multiple: 16 });
+    }
+    let mut result = data.to_vec();
+    parallel::for_each_chunk(&mut result, chunk_size, 16, |_, chunk| cipher.encrypt_blocks(chunk).unwrap());
+    Ok(result)
+}
+
+///
+/// Decrypts the data block by block, with the chunks of the data decrypted in
+/// parallel.
+/// 
+/// cipher: The cipher used to decrypt each block.
+/// data: The data to decrypt. Must be a multiple of 16 bytes.
+/// chunk_size: The bytes processed by a thread at a time, rounded down to a 
+///             multiple of 16, for example parallel::DEFAULT_CHUNK_SIZE.
+/// 
+/// result: The decrypted data or AesError::InvalidDataLength if the data is not
+///         a multiple of 16 bytes.
+/// 
+#[cfg(all(feature = "parallel", feature = "decrypt"))]
+pub fn decrypt_parallel<const KEY_BYTES: usize>(cipher: &Aes<KEY_BYTES>, data: &[u8], chunk_size: usize) -> Result<Vec<u8>, AesError> {
+    if !data.len().is_multiple_of(16) {
+        return Err(AesError::InvalidDataLength { length: data.len(), min: 0, multiple: 16 });
+    }
+    let mut result = data.to_vec();
+    parallel::for_each_chunk(&mut result, chunk_size, 16, |_, chunk| cipher.decrypt_blocks(chunk).unwrap());
+    Ok(result)
+}
+
+///
+/// Pads the data with PKCS#7 and encrypts it block by block.
+/// 
+/// cipher: The cipher used to encrypt each block.
+/// data: The data to encrypt. May be of any length.
+/// 
+/// result: The encrypted data. Always 1 to 16 bytes longer than the data.
+/// 
+#[cfg(feature = "encrypt")]
+pub fn encrypt_padded<const KEY_BYTES: usize>(cipher: &Aes<KEY_BYTES>, data: &[u8]) -> Vec<u8> {
+    encrypt_with_padding(cipher, data, &Pkcs7)
+}
+
+///
+/// Decrypts the data block by block and removes the PKCS#7 padding.
+/// 
+/// cipher: The cipher used to decrypt each block.
+/// data: The data to decrypt. Must be a non empty multiple of 16 bytes.
+/// 
+/// result: The decrypted data, AesError::InvalidDataLength if the data is empty
+///         or not a multiple of 16 bytes or AesError::InvalidPadding if the 
+///         padding is not valid.
+/// 
+#[cfg(feature = "decrypt")]
+pub fn decrypt_padded<const KEY_BYTES: usize>(cipher: &Aes<KEY_BYTES>, data: &[u8]) -> Result<Vec<u8>, AesError> {
+    if data.is_empty() {
+        return Err(AesError::InvalidDataLength { length: data.len(), min: 16, multiple: 16 });
+    }
+    decrypt_with_padding(cipher, data, &Pkcs7)
+}
+
+///
+/// Pads the data with the padding scheme and encrypts it block by block.
+/// 
+/// cipher: The cipher used to encrypt each block.
+/// data: The data to encrypt. May be of any length.
+/// padding: The padding scheme.
+/// 
+/// result: The encrypted data.
+/// 
+#[cfg(feature = "encrypt")]
+pub fn encrypt_with_padding<const KEY_BYTES: usize, P: Padding>(cipher: &Aes<KEY_BYTES>, data: &[u8], padding: &P) -> Vec<u8> {
+    encrypt(cipher, &padding::pad_with(padding, data)).unwrap()
+}
+
+///
+/// Decrypts the data block by block and removes the padding of the padding scheme.
+/// 
+/// cipher: The cipher used to decrypt each block.
+/// data: The data to decrypt. Must be a multiple of 16 bytes.
+/// padding: The padding scheme used when encrypting.
+/// 
+/// result: The decrypted data, AesError::InvalidDataLength if the data is not a
+///         multiple of 16 bytes or AesError::InvalidPadding if the padding is not
+///         valid.
+/// 
+#[cfg(feature = "decrypt")]
+pub fn decrypt_with_padding<const KEY_BYTES: usize, P: Padding>(cipher: &Aes<KEY_BYTES>, data: &[u8], padding: &P) -> Result<Vec<u8>, AesError> {
+    let mut decrypted = decrypt(cipher, data)?;
+    let length = padding::unpad_with(padding, &decrypted)?.len();
+    decrypted.truncate(length);
+    Ok(decrypted)
+}
+
+///
+/// Encryptor is the ecb encryption as a BlockMode. The iv is ignored.
+/// 
+#[cfg(feature = "encrypt")]
+pub struct Encryptor<'a, const KEY_BYTES: usize> {
+    cipher: &'a Aes<KEY_BYTES>
+}
+
+#[cfg(feature = "encrypt")]
+impl<'a, const KEY_BYTES: usize> BlockMode<'a, KEY_BYTES> for Encryptor<'a, KEY_BYTES> {
+
+    fn init(cipher: &'a Aes<KEY_BYTES>, _iv: &[u8; 16]) -> Self {
+        Self { cipher }
+    }
+
+    fn process_block(&mut self, block: &[u8; 16]) -> [u8; 16] {
+        self.cipher.encrypt_bytes(block).try_into().unwrap()
+    }
+
+    fn finalize(self, remaining: &[u8]) -> Result<Vec<u8>, AesError> {
+        finalize_full_blocks(remaining)
+    }
+
+}
+
+///
+/// Decryptor is the ecb decryption as a BlockMode. The iv is ignored.
+/// 
+#[cfg(feature = "decrypt")]
+pub struct Decryptor<'a, const KEY_BYTES: usize> {
+    cipher: &'a Aes<KEY_BYTES>
+}
+
+#[cfg(feature = "decrypt")]
+impl<'a, const KEY_BYTES: usize> BlockMode<'a, KEY_BYTES> for Decryptor<'a, KEY_BYTES> {
+
+    fn init(cipher: &'a Aes<KEY_BYTES>, _iv: &[u8; 16]) -> Self {
+        Self { cipher }
+    }
+
+    fn process_block(&mut self, block: &[u8; 16]) -> [u8; 16] {
+        self.cipher.decrypt_bytes(block).try_into().unwrap()
+    }
+
+    fn finalize(self, remaining: &[u8]) -> Result<Vec<u8>, AesError> {
+        finalize_full_blocks(remaining)
+    }
+
+}
+
+#[cfg(all(test, feature = "encrypt", feature = "decrypt"))]
+mod tests {
+
+    use super::*;
+    use crate::test_util::hex;
+    use crate::{Aes128, Aes192, Aes256};
+
+    const PLAINTEXT: &str = "6bc1bee22e409f96e93d7e117393172a ae2d8a571e03ac9c9eb76fac45af8e51
+                             30c81c46a35ce411e5fbc1191a0a52ef f69f2445df4f9b17ad2b417be66c3710";
+
+    #[test]
+    fn test_ecb_aes128() {
+        let cipher = Aes128::new(&hex("2b7e151628aed2a6abf7158809cf4f3c").try_into().unwrap());
+        let expected = hex("3ad77bb40d7a3660a89ecaf32466ef97 f5d3d58503b9699de785895a96fdbaaf
+                            43b1cd7f598ece23881b00e3ed030688 7b0c785e27e8ad3f8223207104725dd4");
+        assert_eq!(expected, encrypt(&cipher, &hex(PLAINTEXT)).unwrap());
+        assert_eq!(hex(PLAINTEXT), decrypt(&cipher, &expected).unwrap());
+    }
+
+    #[test]
+    fn test_ecb_into() {
+        let cipher = Aes128::new(&hex("2b7e151628aed2a6abf7158809cf4f3c").try_into().unwrap());
+        let expected = encrypt(&cipher, &hex(PLAINTEXT)).unwrap();
+        let mut output = Vec::with_capacity(64);
+        encrypt_into(&cipher, &hex(PLAINTEXT), &mut output).unwrap();
+        assert_eq!(expected, output);
+        let buffer = output.as_ptr();
+        decrypt_into(&cipher, &expected, &mut output).unwrap();
+        assert_eq!(hex(PLAINTEXT), output);
+        assert_eq!(buffer, output.as_ptr());
+        assert_eq!(Some(AesError::InvalidDataLength { length: 17, min: 0, multiple: 16 }), decrypt_into(&cipher, &[0; 17], &mut output).err());
+        assert!(output.is_empty());
+        let mut slice = [0; 80];
+        assert_eq!(Ok(64), encrypt_into_slice(&cipher, &hex(PLAINTEXT), &mut slice));
+        assert_eq!(expected, slice[..64]);
+        assert_eq!(Ok(64), decrypt_into_slice(&cipher, &expected, &mut slice));
+        assert_eq!(hex(PLAINTEXT), slice[..64]);
+        assert_eq!(Some(AesError::OutputTooShort { needed: 64, given: 48 }), encrypt_into_slice(&cipher, &expected, &mut [0; 48]).err());
+        assert_eq!(Some(AesError::InvalidDataLength { length: 17, min: 0, multiple: 16 }), decrypt_into_slice(&cipher, &[0; 17], &mut slice).err());
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_ecb_parallel() {
+        let cipher = Aes128::new(&hex("2b7e151628aed2a6abf7158809cf4f3c").try_into().unwrap());
+        let data: Vec<u8> = (0..4096).map(|value| value as u8).collect();
+        let expected = encrypt(&cipher, &data).unwrap();
+        for chunk_size in [16, 100, 1024, parallel::DEFAULT_CHUNK_SIZE] {
+            assert_eq!(expected, encrypt_parallel(&cipher, &data, chunk_size).unwrap());
+            assert_eq!(data, decrypt_parallel(&cipher, &expected, chunk_size).unwrap());
+        }
+        assert_eq!(Some(AesError::InvalidDataLength { length: 17, min: 0, multiple: 16 }), encrypt_parallel(&cipher, &[0; 17], 16).err());
+        assert_eq!(Some(AesError::InvalidDataLength { length: 17, min: 0, multiple: 16 }), decrypt_parallel(&cipher, &[0; 17], 16).err());
+    }
+
+    #[test]
+    fn test_ecb_aes192() {
+        let cipher = Aes192::new(&hex("8e73b0f7da0e6452c810f32b809079e562f8ead2522c6b7b").try_into().unwrap());
+        let expected = hex("bd334f1d6e45f25ff712a214571fa5cc 974104846d0ad3ad7734ecb3ecee4eef
+                            ef7afd2270e2e60adce0ba2face6444e 9a4b41ba738d6c72fb16691603c18e0e");
+        assert_eq!(expected, encrypt(&cipher, &hex(PLAINTEXT)).unwrap());
+        assert_eq!(hex(PLAINTEXT), decrypt(&cipher, &expected).unwrap());
+    }
+
+    #[test]
+    fn test_ecb_aes256() {
+        let cipher = Aes256::new(&hex("603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4").try_into().unwrap());
+        let expected = hex("f3eed1bdb5d2a03c064b5a7e3db181f8 591ccb10d410ed26dc5ba74a31362870
+                            b6ed21b99ca6f4f9f153e7b1beafed1d 23304b7a39f9f3ff067d8d8f9e24ecc7");
+        assert_eq!(expected, encrypt(&cipher, &hex(PLAINTEXT)).unwrap());
+        assert_eq!(hex(PLAINTEXT), decrypt(&cipher, &expected).unwrap());
+    }
+
+    #[test]
+    fn test_ecb_invalid_length() {
+        let cipher = Aes128::new(&[0; 16]);
+        assert_eq!(Some(AesError::InvalidDataLength { length: 15, min: 0, multiple: 16 }), encrypt(&cipher, &[0; 15]).err());
+        assert_eq!(Some(AesError::InvalidDataLength { length: 33, min: 0, multiple: 16 }), decrypt(&cipher, &[0; 33]).err());
+        assert_eq!(Vec::<u8>::new(), encrypt(&cipher, &[]).unwrap());
+    }
+
+    #[test]
+    fn test_ecb_padded() {
+        let cipher = Aes128::new(&hex("2b7e151628aed2a6abf7158809cf4f3c").try_into().unwrap());
+        let encrypted = encrypt_padded(&cipher, &hex(PLAINTEXT));
+        assert_eq!(80, encrypted.len());
+        assert_eq!(encrypt(&cipher, &hex(PLAINTEXT)).unwrap(), encrypted[..64]);
+        assert_eq!(hex(PLAINTEXT), decrypt_padded(&cipher, &encrypted).unwrap());
+        assert_eq!(b"short".to_vec(), decrypt_padded(&cipher, &encrypt_padded(&cipher, b"short")).unwrap());
+        assert_eq!(Some(AesError::InvalidDataLength { length: 0, min: 16, multiple: 16 }), decrypt_padded(&cipher, &[]).err());
+        assert_eq!(Some(AesError::InvalidPadding), decrypt_padded(&cipher, &encrypted[..64]).err());
+        assert_eq!(encrypted, encrypt_with_padding(&cipher, &hex(PLAINTEXT), &Pkcs7));
+        assert_eq!(hex(PLAINTEXT), decrypt_with_padding(&cipher, &encrypted, &Pkcs7).unwrap());
+    }
+
+}
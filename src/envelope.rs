@@ -0,0 +1,434 @@
+//!
+//! Envelope encryption with a key encryption key (KEK) and data keys (DEK). Every
+//! message is encrypted with AES-256-GCM under a new random data key, and the 
+//! data key is wrapped under the long-term key encryption key with RFC 3394 key
+//! wrap. The key encryption key never touches the data, so it can be kept in a
+//! key management system while the wrapped data keys are stored with the data.
+//! 
+//! EnvelopeData holds the wrapped data key, the nonce and the ciphertext, and is
+//! serialized as:
+//! 
+//! version (1 byte) || wrapped key length (1 byte) || wrapped key || nonce (12 bytes) || ciphertext || tag (16 bytes)
+//! 
+//! The version and the associated data are authenticated by GCM. The wrapped key
+//! is protected by the integrity check of key wrap, so it can be rewrapped under 
+//! a new key encryption key without touching the ciphertext. rewrap_envelope does
+//! this for serialized envelopes when rotating the key encryption key, and 
+//! reencrypt_raw encrypts data that was encrypted directly under the old key into
+//! a new envelope.
+//! 
+
+#[cfg(feature = "base64")]
+use std::string::String;
+use std::vec::Vec;
+
+#[cfg(feature = "base64")]
+use crate::base64;
+use crate::gcm::Gcm;
+use crate::key_wrap::KeyWrap;
+use crate::zeroize::Zeroizing;
+use crate::{AesError, Nonce, SecretKey};
+
+///
+/// The version of the serialized format written by this crate.
+/// 
+pub const VERSION: u8 = 1;
+
+///
+/// The length of the data key in bytes.
+/// 
+const DATA_KEY_LENGTH: usize = 32;
+
+///
+/// The length of the GCM nonce in bytes.
+/// 
+const NONCE_LENGTH: usize = 12;
+
+///
+/// The length of the GCM tag in bytes.
+/// 
+const TAG_LENGTH: usize = 16;
+
+///
+/// EnvelopeData is a message encrypted with a data key, together with the data 
+/// key wrapped under the key encryption key.
+/// 
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(into = "SerializedEnvelope", try_from = "SerializedEnvelope"))]
+pub struct EnvelopeData {
+    ///
+    /// The data key wrapped with RFC 3394 key wrap.
+    /// 
+    pub wrapped_key: Vec<u8>,
+    ///
+    /// The GCM nonce.
+    /// 
+    pub nonce: [u8; 12],
+    ///
+    /// The ciphertext followed by the GCM tag.
+    /// 
+    pub ciphertext: Vec<u8>
+}
+
+///
+/// The serde representation of EnvelopeData, with the version first so that the 
+/// format can change without breaking stored envelopes.
+/// 
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SerializedEnvelope {
+    version: u8,
+    wrapped_key: Vec<u8>,
+    nonce: [u8; 12],
+    ciphertext: Vec<u8>
+}
+
+#[cfg(feature = "serde")]
+impl From<EnvelopeData> for SerializedEnvelope {
+
+    fn from(envelope: EnvelopeData) -> Self {
+        SerializedEnvelope { version: VERSION, wrapped_key: envelope.wrapped_key, nonce: envelope.nonce, ciphertext: envelope.ciphertext }
+    }
+
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<SerializedEnvelope> for EnvelopeData {
+
+    type Error = AesError;
+
+    fn try_from(envelope: SerializedEnvelope) -> Result<Self, AesError> {
+        if envelope.version != VERSION {
+            return Err(AesError::UnsupportedVersion);
+        }
+        if envelope.ciphertext.len() < TAG_LENGTH {
+            return Err(AesError::InvalidDataLength { length: envelope.ciphertext.len(), min: TAG_LENGTH, multiple: 1 });
+        }
+        Ok(EnvelopeData { wrapped_key: envelope.wrapped_key, nonce: envelope.nonce, ciphertext: envelope.ciphertext })
+    }
+
+}
+
+impl EnvelopeData {
+
+    ///
+    /// Serializes the envelope.
+    /// 
+    /// result: The serialized envelope.
+    /// 
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(2 + self.wrapped_key.len() + NONCE_LENGTH + self.ciphertext.len());
+        bytes.push(VERSION);
+        bytes.push(self.wrapped_key.len() as u8);
+        bytes.extend_from_slice(&self.wrapped_key);
+        bytes.extend_from_slice(&self.nonce);
+        bytes.extend_from_slice(&self.ciphertext);
+        bytes
+    }
+
+    ///
+    /// Parses an envelope serialized by to_bytes.
+    /// 
+    /// bytes: The serialized envelope.
+    /// 
+    /// result: The envelope, AesError::UnsupportedVersion if the version is 
+    ///         unknown or AesError::InvalidDataLength if the data is too short.
+    /// 
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, AesError> {
+        if bytes.len() < 2 {
+            return Err(AesError::InvalidDataLength { length: bytes.len(), min: 2, multiple: 1 });
+        }
+        if bytes[0] != VERSION {
+            return Err(AesError::UnsupportedVersion);
+        }
+        let wrapped_length = bytes[1] as usize;
+        if bytes.len() < 2 + wrapped_length + NONCE_LENGTH + TAG_LENGTH {
+            return Err(AesError::InvalidDataLength { length: bytes.len(), min: 2 + wrapped_length + NONCE_LENGTH + TAG_LENGTH, multiple: 1 });
+        }
+        let (wrapped_key, rest) = bytes[2..].split_at(wrapped_length);
+        let (nonce, ciphertext) = rest.split_at(NONCE_LENGTH);
+        Ok(Self { wrapped_key: wrapped_key.to_vec(), nonce: nonce.try_into().unwrap(), ciphertext: ciphertext.to_vec() })
+    }
+
+    ///
+    /// Serializes the envelope as base64, so that it can be stored in JSON.
+    /// 
+    /// result: The serialized envelope as padded base64.
+    /// 
+    #[cfg(feature = "base64")]
+    pub fn to_base64(&self) -> String {
+        base64::encode(&self.to_bytes())
+    }
+
+    ///
+    /// Parses an envelope serialized by to_base64.
+    /// 
+    /// data: The serialized envelope as padded base64.
+    /// 
+    /// result: The envelope, AesError::InvalidBase64 if the data is not base64 or
+    ///         the errors of from_bytes.
+    /// 
+    #[cfg(feature = "base64")]
+    pub fn from_base64(data: &str) -> Result<Self, AesError> {
+        Self::from_bytes(&base64::decode(data.as_bytes()).ok_or(AesError::InvalidBase64)?)
+    }
+
+}
+
+///
+/// Envelope encrypts messages under new data keys wrapped with a key encryption
+/// key of KEY_BYTES bytes.
+/// 
+pub struct Envelope<const KEY_BYTES: usize = 32> {
+    kek: KeyWrap<KEY_BYTES>
+}
+
+impl<const KEY_BYTES: usize> Envelope<KEY_BYTES> {
+
+    ///
+    /// Creates a new Envelope.
+    /// 
+    /// kek: The key encryption key.
+    /// 
+    /// result: A new Envelope.
+    /// 
+    pub fn new(kek: &[u8; KEY_BYTES]) -> Self {
+        Self { kek: KeyWrap::new(kek) }
+    }
+
+    ///
+    /// Encrypts the plaintext under a new random data key and wraps the data key.
+    /// 
+    /// aad: The associated data, authenticated but not stored in the envelope.
+    /// plaintext: The data to encrypt.
+    /// 
+    /// result: The envelope, AesError::RandomUnavailable if no data key or nonce
+    ///         could be generated or AesError::DataTooLong if the plaintext is too
+    ///         long for GCM.
+    /// 
+    pub fn seal(&self, aad: &[u8], plaintext: &[u8]) -> Result<EnvelopeData, AesError> {
+        let data_key = SecretKey::<DATA_KEY_LENGTH>::generate()?;
+        let nonce = Nonce::<NONCE_LENGTH>::generate()?;
+        let ciphertext = data_cipher(&data_key)?.encrypt(nonce.as_ref(), &authenticated_data(aad), plaintext)?;
+        let wrapped_key = self.kek.wrap(data_key.expose_secret())?;
+        Ok(EnvelopeData { wrapped_key, nonce: *nonce.as_bytes(), ciphertext })
+    }
+
+    ///
+    /// Unwraps the data key and decrypts the envelope.
+    /// 
+    /// aad: The associated data used when sealing.
+    /// envelope: The envelope.
+    /// 
+    /// result: The plaintext or AesError::AuthenticationFailed if the key 
+    ///         encryption key is wrong or the envelope or the associated data was
+    ///         modified.
+    /// 
+    pub fn open(&self, aad: &[u8], envelope: &EnvelopeData) -> Result<Vec<u8>, AesError> {
+        let data_key = self.unwrap_key(&envelope.wrapped_key)?;
+        data_cipher(&data_key)?.decrypt(&envelope.nonce, &authenticated_data(aad), &envelope.ciphertext)
+    }
+
+    ///
+    /// Rewraps the data key of the envelope under another key encryption key. The
+    /// ciphertext is not decrypted or changed.
+    /// 
+    /// new_kek: The envelope with the new key encryption key.
+    /// envelope: The envelope sealed with this key encryption key.
+    /// 
+    /// result: The envelope with the rewrapped data key or 
+    ///         AesError::AuthenticationFailed if the data key can not be unwrapped.
+    /// 
+    pub fn rewrap<const NEW_KEY_BYTES: usize>(&self, new_kek: &Envelope<NEW_KEY_BYTES>, envelope: &EnvelopeData) -> Result<EnvelopeData, AesError> {
+        let data_key = self.unwrap_key(&envelope.wrapped_key)?;
+        let wrapped_key = new_kek.kek.wrap(data_key.expose_secret())?;
+        Ok(EnvelopeData { wrapped_key, ..envelope.clone() })
+    }
+
+    ///
+    /// Unwraps the data key of an envelope.
+    /// 
+    fn unwrap_key(&self, wrapped_key: &[u8]) -> Result<SecretKey<DATA_KEY_LENGTH>, AesError> {
+        let key = Zeroizing(self.kek.unwrap(wrapped_key)?);
+        SecretKey::from_slice(&key).map_err(|_| AesError::AuthenticationFailed)
+    }
+
+}
+
+///
+/// Rewraps the data key of a serialized envelope under a new key encryption key,
+/// for bulk key rotation. The ciphertext is not touched.
+/// 
+/// old_key: The key encryption key the envelope is wrapped under.
+/// new_key: The new key encryption key.
+/// envelope: The serialized envelope.
+/// 
+/// result: The serialized envelope under the new key, an error of 
+///         EnvelopeData::from_bytes if the envelope is malformed or 
+///         AesError::AuthenticationFailed if the data key can not be unwrapped 
+///         with the old key.
+/// 
+pub fn rewrap_envelope<const OLD_KEY_BYTES: usize, const NEW_KEY_BYTES: usize>(old_key: &[u8; OLD_KEY_BYTES], new_key: &[u8; NEW_KEY_BYTES], envelope: &[u8]) -> Result<Vec<u8>, AesError> {
+    let envelope = EnvelopeData::from_bytes(envelope)?;
+    Ok(Envelope::new(old_key).rewrap(&Envelope::new(new_key), &envelope)?.to_bytes())
+}
+
+///
+/// Reencrypts data encrypted directly under a key into a new envelope, for 
+/// moving data into envelopes. The data is the nonce, the ciphertext and the tag
+/// of AES-GCM under the old key.
+/// 
+/// old_key: The key the data is encrypted under.
+/// new_key: The new key encryption key.
+/// aad: The associated data of the AES-GCM encryption, also authenticated by the
+///      new envelope.
+/// ciphertext: The AES-GCM encrypted data.
+/// 
+/// result: The serialized envelope under the new key or 
+///         AesError::AuthenticationFailed if the data can not be decrypted with 
+///         the old key.
+/// 
+pub fn reencrypt_raw<const OLD_KEY_BYTES: usize, const NEW_KEY_BYTES: usize>(old_key: &[u8; OLD_KEY_BYTES], new_key: &[u8; NEW_KEY_BYTES], aad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, AesError> {
+    if ciphertext.len() < NONCE_LENGTH + TAG_LENGTH {
+        return Err(AesError::AuthenticationFailed);
+    }
+    let (nonce, ciphertext) = ciphertext.split_at(NONCE_LENGTH);
+    let plaintext = Zeroizing(Gcm::new(old_key, TAG_LENGTH)?.decrypt(nonce, aad, ciphertext)?);
+    Ok(Envelope::new(new_key).seal(aad, &plaintext)?.to_bytes())
+}
+
+///
+/// Creates the GCM cipher of the data key.
+/// 
+fn data_cipher(data_key: &SecretKey<DATA_KEY_LENGTH>) -> Result<Gcm<DATA_KEY_LENGTH>, AesError> {
+    Gcm::new(data_key.expose_secret(), TAG_LENGTH)
+}
+
+///
+/// The data authenticated by GCM, the version followed by the associated data.
+/// 
+fn authenticated_data(aad: &[u8]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(1 + aad.len());
+    data.push(VERSION);
+    data.extend_from_slice(aad);
+    data
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let envelope = Envelope::<32>::new(&[1; 32]);
+        for plaintext in [&b""[..], b"attack at dawn", &[9; 100]] {
+            let sealed = envelope.seal(b"header", plaintext).unwrap();
+            assert_eq!(40, sealed.wrapped_key.len());
+            assert_eq!(plaintext.len() + TAG_LENGTH, sealed.ciphertext.len());
+            assert_eq!(plaintext, envelope.open(b"header", &sealed).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_serialization() {
+        let envelope = Envelope::<16>::new(&[1; 16]);
+        let sealed = envelope.seal(&[], b"data").unwrap();
+        let bytes = sealed.to_bytes();
+        assert_eq!([VERSION, 40], bytes[..2]);
+        assert_eq!(2 + 40 + 12 + 4 + 16, bytes.len());
+        assert_eq!(sealed, EnvelopeData::from_bytes(&bytes).unwrap());
+        assert_eq!(Err(AesError::InvalidDataLength { length: 69, min: 70, multiple: 1 }), EnvelopeData::from_bytes(&bytes[..69]));
+        let mut bytes = bytes;
+        bytes[0] = 2;
+        assert_eq!(Err(AesError::UnsupportedVersion), EnvelopeData::from_bytes(&bytes));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let envelope = Envelope::<32>::new(&[1; 32]);
+        let sealed = envelope.seal(b"header", b"data").unwrap();
+        let json = serde_json::to_string(&sealed).unwrap();
+        assert!(json.starts_with("{\"version\":1,\"wrapped_key\":["));
+        let parsed: EnvelopeData = serde_json::from_str(&json).unwrap();
+        assert_eq!(sealed, parsed);
+        assert_eq!(b"data".to_vec(), envelope.open(b"header", &parsed).unwrap());
+        let unknown = json.replacen("\"version\":1", "\"version\":2", 1);
+        assert!(serde_json::from_str::<EnvelopeData>(&unknown).unwrap_err().to_string().starts_with(&AesError::UnsupportedVersion.to_string()));
+        let json = "{\"version\":1,\"wrapped_key\":[],\"nonce\":[0,0,0,0,0,0,0,0,0,0,0,0],\"ciphertext\":[1,2]}";
+        assert!(serde_json::from_str::<EnvelopeData>(json).is_err());
+    }
+
+    #[cfg(feature = "base64")]
+    #[test]
+    fn test_base64() {
+        let envelope = Envelope::<32>::new(&[1; 32]);
+        let sealed = envelope.seal(b"header", b"data").unwrap();
+        let encoded = sealed.to_base64();
+        assert!(encoded.starts_with("AS"));
+        assert_eq!(sealed, EnvelopeData::from_base64(&encoded).unwrap());
+        assert_eq!(Err(AesError::InvalidBase64), EnvelopeData::from_base64(&encoded[1..]));
+        assert_eq!(Err(AesError::InvalidDataLength { length: 2, min: 70, multiple: 1 }), EnvelopeData::from_base64("ASg="));
+    }
+
+    #[test]
+    fn test_new_data_key_per_message() {
+        let envelope = Envelope::<32>::new(&[1; 32]);
+        let first = envelope.seal(&[], b"data").unwrap();
+        let second = envelope.seal(&[], b"data").unwrap();
+        assert_ne!(first.wrapped_key, second.wrapped_key);
+        assert_ne!(first.ciphertext, second.ciphertext);
+    }
+
+    #[test]
+    fn test_rewrap() {
+        let (old, new) = (Envelope::<32>::new(&[1; 32]), Envelope::<16>::new(&[2; 16]));
+        let sealed = old.seal(b"header", b"data").unwrap();
+        let rewrapped = old.rewrap(&new, &sealed).unwrap();
+        assert_eq!(sealed.ciphertext, rewrapped.ciphertext);
+        assert_ne!(sealed.wrapped_key, rewrapped.wrapped_key);
+        assert_eq!(b"data".to_vec(), new.open(b"header", &rewrapped).unwrap());
+        assert_eq!(Err(AesError::AuthenticationFailed), old.open(b"header", &rewrapped));
+        assert_eq!(Err(AesError::AuthenticationFailed), new.rewrap(&old, &sealed));
+    }
+
+    #[test]
+    fn test_rewrap_envelope() {
+        let sealed = Envelope::<32>::new(&[1; 32]).seal(b"header", b"data").unwrap();
+        let rotated = rewrap_envelope(&[1; 32], &[3; 32], &sealed.to_bytes()).unwrap();
+        let rotated = EnvelopeData::from_bytes(&rotated).unwrap();
+        assert_eq!(sealed.ciphertext, rotated.ciphertext);
+        assert_eq!(b"data".to_vec(), Envelope::<32>::new(&[3; 32]).open(b"header", &rotated).unwrap());
+        assert_eq!(Err(AesError::AuthenticationFailed), rewrap_envelope(&[2; 32], &[3; 32], &sealed.to_bytes()));
+        assert_eq!(Err(AesError::UnsupportedVersion), rewrap_envelope(&[1; 32], &[3; 32], &[0; 64]));
+    }
+
+    #[test]
+    fn test_reencrypt_raw() {
+        let nonce = [7; 12];
+        let mut direct = nonce.to_vec();
+        direct.extend(Gcm::<32>::new(&[1; 32], 16).unwrap().encrypt(&nonce, b"header", b"legacy data").unwrap());
+        let rotated = reencrypt_raw(&[1; 32], &[3; 32], b"header", &direct).unwrap();
+        let rotated = EnvelopeData::from_bytes(&rotated).unwrap();
+        assert_eq!(b"legacy data".to_vec(), Envelope::<32>::new(&[3; 32]).open(b"header", &rotated).unwrap());
+        assert_eq!(Err(AesError::AuthenticationFailed), reencrypt_raw(&[1; 32], &[3; 32], &[], &direct));
+        assert_eq!(Err(AesError::AuthenticationFailed), reencrypt_raw(&[2; 32], &[3; 32], b"header", &direct));
+        assert_eq!(Err(AesError::AuthenticationFailed), reencrypt_raw(&[1; 32], &[3; 32], b"header", &direct[..20]));
+    }
+
+    #[test]
+    fn test_authentication() {
+        let envelope = Envelope::<32>::new(&[1; 32]);
+        let sealed = envelope.seal(b"header", b"data").unwrap();
+        assert_eq!(Err(AesError::AuthenticationFailed), Envelope::<32>::new(&[2; 32]).open(b"header", &sealed));
+        assert_eq!(Err(AesError::AuthenticationFailed), envelope.open(b"other", &sealed));
+        let mut modified = sealed.clone();
+        modified.ciphertext[0] ^= 1;
+        assert_eq!(Err(AesError::AuthenticationFailed), envelope.open(b"header", &modified));
+        let mut modified = sealed.clone();
+        modified.wrapped_key.truncate(24);
+        assert_eq!(Err(AesError::AuthenticationFailed), envelope.open(b"header", &modified));
+    }
+
+}
@@ -0,0 +1,249 @@
+use std::fmt;
+use std::io;
+
+///
+/// AesError is the error type returned by the crate when the input to any of the
+/// public functions is invalid or when authentication of the data fails.
+/// 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AesError {
+    ///
+    /// The key does not have a length accepted by the cipher, usually 16, 24 or 32
+    /// bytes. The length is the length of the key that was given, the accepted 
+    /// lengths are the multiples of multiple from min to max.
+    /// 
+    InvalidKeyLength { length: usize, min: usize, max: usize, multiple: usize },
+    ///
+    /// The block is not exactly 16 bytes, or not the block length of the Rijndael
+    /// roundkeys. The length is the length of the block that was given, the 
+    /// accepted lengths are the multiples of multiple from min to max.
+    /// 
+    InvalidBlockLength { length: usize, min: usize, max: usize, multiple: usize },
+    ///
+    /// The data does not have a length accepted by the function, for example it is
+    /// not a multiple of 16 bytes or too short. The length is the length of the 
+    /// data that was given, the accepted lengths are the multiples of multiple of
+    /// at least min bytes.
+    /// 
+    InvalidDataLength { length: usize, min: usize, multiple: usize },
+    ///
+    /// The roundkeys are not 11, 13 or 15 roundkeys of exactly 16 bytes.
+    /// 
+    InvalidRoundKeys,
+    ///
+    /// The padding of the decrypted data is not valid.
+    /// 
+    InvalidPadding,
+    ///
+    /// The nonce does not have the length required by the mode. The length is the
+    /// length of the nonce that was given, the accepted lengths are min to max 
+    /// bytes. Max is usize::MAX if there is no upper limit.
+    /// 
+    InvalidNonceLength { length: usize, min: usize, max: usize },
+    ///
+    /// The counter overflowed, so a counter block would be reused.
+    /// 
+    CounterOverflow,
+    ///
+    /// The nonce or iv is the same as the one already used.
+    /// 
+    NonceReused,
+    ///
+    /// The tag length is not supported by the mode.
+    /// 
+    InvalidTagLength,
+    ///
+    /// The tag length is shorter than the minimum the mode allows.
+    /// 
+    TagTooShort,
+    ///
+    /// The length field size of the CCM mode is not between 2 and 8 bytes.
+    /// 
+    InvalidLengthFieldSize,
+    ///
+    /// The data is too long for the mode.
+    /// 
+    DataTooLong,
+    ///
+    /// The output buffer is too short for the result. Needed is the number of 
+    /// bytes the output needs and given the length of the output.
+    /// 
+    OutputTooShort { needed: usize, given: usize },
+    ///
+    /// The parameters of the key derivation function are not valid.
+    /// 
+    InvalidKdfParameters,
+    ///
+    /// The operating system could not provide random bytes.
+    /// 
+    RandomUnavailable,
+    ///
+    /// The format version of the data is not supported.
+    /// 
+    UnsupportedVersion,
+    ///
+    /// The entropy input is shorter than the security strength.
+    /// 
+    InsufficientEntropy,
+    ///
+    /// The random bit generator must be reseeded before it is used again.
+    /// 
+    ReseedRequired,
+    ///
+    /// A key with the same name already exists.
+    /// 
+    DuplicateKey,
+    ///
+    /// No key with the name or id exists.
+    /// 
+    KeyNotFound,
+    ///
+    /// Reading or writing a file failed. The kind is the kind of the io error.
+    /// 
+    Io { kind: io::ErrorKind },
+    ///
+    /// The number of key shares is not valid.
+    /// 
+    InvalidShareCount,
+    ///
+    /// The capacity or false positive rate of the nonce tracker is not valid.
+    /// 
+    InvalidTrackerParameters,
+    ///
+    /// The memory could not be locked.
+    /// 
+    MemoryLockFailed,
+    ///
+    /// The key block is malformed or a header field is not valid.
+    /// 
+    InvalidKeyBlock,
+    ///
+    /// The string contains a character that is not a hex digit or an odd number
+    /// of digits.
+    /// 
+    InvalidHex,
+    ///
+    /// The string is not padded base64 with the standard alphabet.
+    /// 
+    InvalidBase64,
+    ///
+    /// The cipher configuration is missing the key or has an option that does not
+    /// apply to the mode.
+    /// 
+    InvalidConfiguration,
+    ///
+    /// The authentication tag of the data did not match.
+    /// 
+    AuthenticationFailed
+}
+
+impl fmt::Display for AesError {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AesError::InvalidKeyLength { length, min, max, multiple } => write!(f, "Key must be {} bytes, not {} bytes", Lengths(*min, *max, *multiple), length),
+            AesError::InvalidBlockLength { length, min, max, multiple } => write!(f, "Block must be {} bytes, not {} bytes", Lengths(*min, *max, *multiple), length),
+            AesError::InvalidDataLength { length, min, multiple: 1 } => write!(f, "Data must be at least {} bytes, not {} bytes", min, length),
+            AesError::InvalidDataLength { length, min: 0, multiple } => write!(f, "Data must be a multiple of {} bytes, not {} bytes", multiple, length),
+            AesError::InvalidDataLength { length, min, multiple } => write!(f, "Data must be a multiple of {} bytes of at least {} bytes, not {} bytes", multiple, min, length),
+            AesError::InvalidRoundKeys => write!(f, "Roundkeys must be 11, 13 or 15 roundkeys of 16 bytes"),
+            AesError::InvalidPadding => write!(f, "Padding of the data is invalid"),
+            AesError::InvalidNonceLength { length, min, max } => write!(f, "Nonce must be {} bytes, not {} bytes", Lengths(*min, *max, 1), length),
+            AesError::CounterOverflow => write!(f, "Counter overflowed"),
+            AesError::NonceReused => write!(f, "Nonce was already used"),
+            AesError::InvalidTagLength => write!(f, "Tag has an invalid length"),
+            AesError::TagTooShort => write!(f, "Tag is shorter than the allowed minimum"),
+            AesError::InvalidLengthFieldSize => write!(f, "Length field must be 2 to 8 bytes"),
+            AesError::DataTooLong => write!(f, "Data is too long for the mode"),
+            AesError::OutputTooShort { needed, given } => write!(f, "Output must be at least {} bytes, not {} bytes", needed, given),
+            AesError::InvalidKdfParameters => write!(f, "Key derivation parameters are invalid"),
+            AesError::RandomUnavailable => write!(f, "Random bytes are not available"),
+            AesError::UnsupportedVersion => write!(f, "Format version is not supported"),
+            AesError::InsufficientEntropy => write!(f, "Entropy input is too short"),
+            AesError::ReseedRequired => write!(f, "Generator must be reseeded"),
+            AesError::DuplicateKey => write!(f, "Key already exists"),
+            AesError::KeyNotFound => write!(f, "Key was not found"),
+            AesError::Io { kind } => write!(f, "Reading or writing the file failed: {}", kind),
+            AesError::InvalidShareCount => write!(f, "Number of key shares is not valid"),
+            AesError::InvalidTrackerParameters => write!(f, "Nonce tracker parameters are not valid"),
+            AesError::MemoryLockFailed => write!(f, "Memory could not be locked"),
+            AesError::InvalidKeyBlock => write!(f, "Key block is not valid"),
+            AesError::InvalidHex => write!(f, "Hex string is not valid"),
+            AesError::InvalidBase64 => write!(f, "Base64 string is not valid"),
+            AesError::InvalidConfiguration => write!(f, "Cipher configuration is not valid"),
+            AesError::AuthenticationFailed => write!(f, "Authentication of the data failed")
+        }
+    }
+
+}
+
+impl std::error::Error for AesError {}
+
+impl From<io::Error> for AesError {
+
+    fn from(error: io::Error) -> Self {
+        AesError::Io { kind: error.kind() }
+    }
+
+}
+
+///
+/// Lengths formats the accepted lengths of an error, the multiples of the third
+/// value from the first to the second. A few lengths are listed, more are given
+/// as a range.
+/// 
+struct Lengths(usize, usize, usize);
+
+impl fmt::Display for Lengths {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Lengths(min, max, multiple) = *self;
+        if max == usize::MAX {
+            return write!(f, "at least {}", min);
+        }
+        let count = (max - min) / multiple + 1;
+        match count {
+            1 => write!(f, "{}", min),
+            2..=4 => {
+                for index in 0..count - 1 {
+                    let separator = if index + 2 == count { " or " } else { ", " };
+                    write!(f, "{}{}", min + index * multiple, separator)?;
+                }
+                write!(f, "{}", max)
+            }
+            _ if multiple == 1 => write!(f, "{} to {}", min, max),
+            _ => write!(f, "a multiple of {} from {} to {}", multiple, min, max)
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        assert_eq!("Block must be 16 bytes, not 15 bytes", AesError::InvalidBlockLength { length: 15, min: 16, max: 16, multiple: 16 }.to_string());
+        assert_eq!("Block must be 16, 24 or 32 bytes, not 20 bytes", AesError::InvalidBlockLength { length: 20, min: 16, max: 32, multiple: 8 }.to_string());
+        assert_eq!("Key must be 1 to 255 bytes, not 0 bytes", AesError::InvalidKeyLength { length: 0, min: 1, max: 255, multiple: 1 }.to_string());
+        assert_eq!("Key must be a multiple of 8 from 16 to 64 bytes, not 20 bytes", AesError::InvalidKeyLength { length: 20, min: 16, max: 64, multiple: 8 }.to_string());
+        assert_eq!("Nonce must be at least 1 bytes, not 0 bytes", AesError::InvalidNonceLength { length: 0, min: 1, max: usize::MAX }.to_string());
+        assert_eq!("Nonce must be 7 to 13 bytes, not 14 bytes", AesError::InvalidNonceLength { length: 14, min: 7, max: 13 }.to_string());
+        assert_eq!("Nonce must be 24 bytes, not 12 bytes", AesError::InvalidNonceLength { length: 12, min: 24, max: 24 }.to_string());
+        assert_eq!("Data must be a multiple of 16 bytes, not 17 bytes", AesError::InvalidDataLength { length: 17, min: 0, multiple: 16 }.to_string());
+        assert_eq!("Data must be a multiple of 16 bytes of at least 32 bytes, not 16 bytes", AesError::InvalidDataLength { length: 16, min: 32, multiple: 16 }.to_string());
+        assert_eq!("Data must be at least 28 bytes, not 20 bytes", AesError::InvalidDataLength { length: 20, min: 28, multiple: 1 }.to_string());
+        assert_eq!("Output must be at least 32 bytes, not 31 bytes", AesError::OutputTooShort { needed: 32, given: 31 }.to_string());
+        assert_eq!("Authentication of the data failed", AesError::AuthenticationFailed.to_string());
+        assert_eq!("Reading or writing the file failed: entity not found", AesError::from(io::Error::from(io::ErrorKind::NotFound)).to_string());
+    }
+
+    #[test]
+    fn test_error_trait() {
+        let error: Box<dyn std::error::Error> = Box::new(AesError::InvalidKeyLength { length: 20, min: 16, max: 32, multiple: 8 });
+        assert_eq!("Key must be 16, 24 or 32 bytes, not 20 bytes", error.to_string());
+    }
+
+}
@@ -0,0 +1,119 @@
+//!
+//! CBC with encrypted salt-sector initialization vectors (ESSIV), compatible with
+//! the aes-cbc-essiv:sha256 format of dm-crypt. The iv of every sector is the 
+//! sector number encrypted with a key derived by hashing the data key with SHA-256,
+//! so the ivs are unpredictable without knowing the key.
+//! 
+//! The sector number is encoded as a 64 bit little endian integer padded with 
+//! zeros to 16 bytes, as dm-crypt does for the plain64 iv.
+//! 
+
+use std::vec::Vec;
+
+use crate::sha256::sha256;
+use crate::zeroize::Zeroizing;
+use crate::{cbc, Aes, Aes256, AesError};
+
+///
+/// EssivCbc is a cbc cipher for sectors with ESSIV ivs.
+/// 
+pub struct EssivCbc<const KEY_BYTES: usize = 16> {
+    cipher: Aes<KEY_BYTES>,
+    essiv: Aes256
+}
+
+impl<const KEY_BYTES: usize> EssivCbc<KEY_BYTES> {
+
+    ///
+    /// Creates a new EssivCbc cipher. The key of the iv cipher is the SHA-256 digest
+    /// of the key.
+    /// 
+    /// key: The key used to encrypt the data.
+    /// 
+    /// result: A new EssivCbc cipher.
+    /// 
+    pub fn new(key: &[u8; KEY_BYTES]) -> Self {
+        Self {
+            cipher: Aes::new(key),
+            essiv: Aes256::new(&Zeroizing(sha256(key)))
+        }
+    }
+
+    ///
+    /// Derives the iv of a sector.
+    /// 
+    /// sector_number: The number of the sector.
+    /// 
+    /// result: The 16 byte iv.
+    /// 
+    pub fn iv(&self, sector_number: u64) -> [u8; 16] {
+        let mut sector = [0; 16];
+        sector[..8].copy_from_slice(&sector_number.to_le_bytes());
+        self.essiv.encrypt_bytes(&sector).try_into().unwrap()
+    }
+
+    ///
+    /// Encrypts a sector with cbc without padding.
+    /// 
+    /// sector_number: The number of the sector, used to derive the iv.
+    /// data: The data to encrypt. Must be a multiple of 16 bytes.
+    /// 
+    /// result: The encrypted data or AesError::InvalidDataLength if the data is not
+    ///         a multiple of 16 bytes.
+    /// 
+    pub fn encrypt_sector(&self, sector_number: u64, data: &[u8]) -> Result<Vec<u8>, AesError> {
+        cbc::encrypt_unpadded(&self.cipher, &self.iv(sector_number), data)
+    }
+
+    ///
+    /// Decrypts a sector with cbc without padding.
+    /// 
+    /// sector_number: The number of the sector used when encrypting.
+    /// data: The data to decrypt. Must be a multiple of 16 bytes.
+    /// 
+    /// result: The decrypted data or AesError::InvalidDataLength if the data is not
+    ///         a multiple of 16 bytes.
+    /// 
+    pub fn decrypt_sector(&self, sector_number: u64, data: &[u8]) -> Result<Vec<u8>, AesError> {
+        cbc::decrypt_unpadded(&self.cipher, &self.iv(sector_number), data)
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::test_util::hex;
+
+    #[test]
+    fn test_essiv_aes128() {
+        let essiv = EssivCbc::<16>::new(&hex("2b7e151628aed2a6abf7158809cf4f3c").try_into().unwrap());
+        assert_eq!(hex("3b68b16a5bf4e958866f9c86fbd1d23f"), essiv.iv(0));
+        let expected = hex("59fb8870f80940313d2ae05322d20824 53b89513fbc8d76cdfdd6c7dcea06363");
+        assert_eq!(expected, essiv.encrypt_sector(0, &[0; 32]).unwrap());
+        assert_eq!(vec![0; 32], essiv.decrypt_sector(0, &expected).unwrap());
+        let plaintext: Vec<u8> = (0..48).collect();
+        let expected = hex("d47a080816750d35a3e592bed2874980 55329a47b39dae7164d80f4a4bed52aa c83e0887685989c409f1c178dfc4d85e");
+        assert_eq!(hex("3ae2baa332a2fe39010b5a14a6896374"), essiv.iv(1234567));
+        assert_eq!(expected, essiv.encrypt_sector(1234567, &plaintext).unwrap());
+        assert_eq!(plaintext, essiv.decrypt_sector(1234567, &expected).unwrap());
+    }
+
+    #[test]
+    fn test_essiv_aes256() {
+        let essiv = EssivCbc::<32>::new(&hex("603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4").try_into().unwrap());
+        let plaintext: Vec<u8> = (0..32).collect();
+        let expected = hex("0c64987677476a38d28b9ad9341c2307 5da3fb281d53fcae7e8a5b8d6d5e608c");
+        assert_eq!(hex("0e4734ea231ee8e43dd5c7d7ca1dc1a1"), essiv.iv(42));
+        assert_eq!(expected, essiv.encrypt_sector(42, &plaintext).unwrap());
+        assert_eq!(plaintext, essiv.decrypt_sector(42, &expected).unwrap());
+    }
+
+    #[test]
+    fn test_essiv_invalid_length() {
+        let essiv = EssivCbc::<16>::new(&[0; 16]);
+        assert_eq!(Some(AesError::InvalidDataLength { length: 17, min: 0, multiple: 16 }), essiv.encrypt_sector(0, &[0; 17]).err());
+    }
+
+}
@@ -0,0 +1,247 @@
+//!
+//! Encrypt-then-mac, the generic composition of a cipher mode and a mac that is
+//! secure when the keys are independent. The plaintext is encrypted with cbc 
+//! or ctr, and the mac is calculated over the associated data, the iv and the 
+//! ciphertext, so nothing is decrypted before the mac is verified.
+//! 
+//! The output is framed as iv || ciphertext || tag. The mac input is the length
+//! of the associated data as an 8 byte big endian number, the associated data, 
+//! the iv and the ciphertext. The length prefix keeps the boundary between the 
+//! associated data and the iv unambiguous.
+//! 
+//! The two keys must be independent. from_master_key derives both from one key
+//! with HKDF.
+//! 
+
+use std::vec::Vec;
+
+use crate::cmac::Cmac;
+use crate::hmac::Hmac;
+use crate::zeroize::Zeroizing;
+use crate::{cbc, ctr, hkdf, verify_tag, Aes, AesError, Mac};
+
+///
+/// The HKDF info label of the encryption key derived from a master key.
+/// 
+const ENCRYPTION_LABEL: &[u8] = b"aes etm encryption key";
+
+///
+/// The HKDF info label of the mac key derived from a master key.
+/// 
+const MAC_LABEL: &[u8] = b"aes etm mac key";
+
+///
+/// The cipher mode used by Etm.
+/// 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EtmMode {
+    ///
+    /// Cbc with PKCS#7 padding and a random iv.
+    /// 
+    Cbc,
+    ///
+    /// Ctr with the iv as the initial 128 bit counter block.
+    /// 
+    Ctr
+}
+
+///
+/// The mac used by Etm.
+/// 
+enum Authenticator<const KEY_BYTES: usize> {
+    Cmac(Cmac<KEY_BYTES>),
+    Hmac(Hmac)
+}
+
+///
+/// Etm is an encrypt-then-mac composition of a cipher mode and CMAC or HMAC-SHA256.
+/// 
+pub struct Etm<const KEY_BYTES: usize = 16> {
+    cipher: Aes<KEY_BYTES>,
+    mode: EtmMode,
+    authenticator: Authenticator<KEY_BYTES>
+}
+
+impl<const KEY_BYTES: usize> Etm<KEY_BYTES> {
+
+    ///
+    /// Creates a new Etm with CMAC and a 16 byte tag.
+    /// 
+    /// encryption_key: The key of the cipher.
+    /// mac_key: The key of the mac. Must be independent of the encryption key.
+    /// mode: The cipher mode.
+    /// 
+    /// result: A new Etm.
+    /// 
+    pub fn with_cmac(encryption_key: &[u8; KEY_BYTES], mac_key: &[u8; KEY_BYTES], mode: EtmMode) -> Self {
+        Self { cipher: Aes::new(encryption_key), mode, authenticator: Authenticator::Cmac(Cmac::new(mac_key)) }
+    }
+
+    ///
+    /// Creates a new Etm with HMAC-SHA256 and a 32 byte tag.
+    /// 
+    /// encryption_key: The key of the cipher.
+    /// mac_key: The key of the mac, of any length. Must be independent of the 
+    ///          encryption key.
+    /// mode: The cipher mode.
+    /// 
+    /// result: A new Etm.
+    /// 
+    pub fn with_hmac(encryption_key: &[u8; KEY_BYTES], mac_key: &[u8], mode: EtmMode) -> Self {
+        Self { cipher: Aes::new(encryption_key), mode, authenticator: Authenticator::Hmac(Hmac::new(mac_key)) }
+    }
+
+    ///
+    /// Creates a new Etm with HMAC-SHA256 from a single master key. The encryption
+    /// key and the mac key are derived with HKDF and distinct info labels.
+    /// 
+    /// master_key: The master key, at least as long as the encryption key.
+    /// mode: The cipher mode.
+    /// 
+    /// result: A new Etm.
+    /// 
+    pub fn from_master_key(master_key: &[u8], mode: EtmMode) -> Self {
+        let pseudorandom_key = Zeroizing(hkdf::extract(&[], master_key));
+        let encryption_key = Zeroizing(hkdf::expand(pseudorandom_key.as_slice(), ENCRYPTION_LABEL, KEY_BYTES).unwrap());
+        let mac_key = Zeroizing(hkdf::expand(pseudorandom_key.as_slice(), MAC_LABEL, 32).unwrap());
+        Self::with_hmac(encryption_key.as_slice().try_into().unwrap(), &mac_key, mode)
+    }
+
+    ///
+    /// The length of the tag in bytes.
+    /// 
+    /// result: 16 for CMAC or 32 for HMAC-SHA256.
+    /// 
+    pub fn tag_length(&self) -> usize {
+        match self.authenticator {
+            Authenticator::Cmac(_) => 16,
+            Authenticator::Hmac(_) => 32
+        }
+    }
+
+    ///
+    /// Encrypts the plaintext and authenticates the result.
+    /// 
+    /// iv: The 16 byte iv, random and never reused with the same key.
+    /// aad: The associated data, authenticated but not encrypted.
+    /// plaintext: The data to encrypt.
+    /// 
+    /// result: The iv, the ciphertext and the tag.
+    /// 
+    pub fn encrypt(&self, iv: &[u8; 16], aad: &[u8], plaintext: &[u8]) -> Vec<u8> {
+        let ciphertext = match self.mode {
+            EtmMode::Cbc => cbc::encrypt(&self.cipher, iv, plaintext),
+            EtmMode::Ctr => ctr::encrypt(&self.cipher, iv, plaintext)
+        };
+        let mut framed = Vec::with_capacity(16 + ciphertext.len() + self.tag_length());
+        framed.extend_from_slice(iv);
+        framed.extend_from_slice(&ciphertext);
+        let tag = self.tag(aad, &framed);
+        framed.extend_from_slice(&tag);
+        framed
+    }
+
+    ///
+    /// Verifies the tag and decrypts the ciphertext.
+    /// 
+    /// aad: The associated data used when encrypting.
+    /// framed: The iv, the ciphertext and the tag.
+    /// 
+    /// result: The plaintext, AesError::AuthenticationFailed if the tag does not 
+    ///         match or the framed data is too short, or the error of the cipher 
+    ///         mode for authenticated but malformed ciphertext.
+    /// 
+    pub fn decrypt(&self, aad: &[u8], framed: &[u8]) -> Result<Vec<u8>, AesError> {
+        if framed.len() < 16 + self.tag_length() {
+            return Err(AesError::AuthenticationFailed);
+        }
+        let (authenticated, tag) = framed.split_at(framed.len() - self.tag_length());
+        verify_tag(tag, &self.tag(aad, authenticated))?;
+        let (iv, ciphertext) = authenticated.split_at(16);
+        let iv = iv.try_into().unwrap();
+        match self.mode {
+            EtmMode::Cbc => cbc::decrypt(&self.cipher, iv, ciphertext),
+            EtmMode::Ctr => Ok(ctr::decrypt(&self.cipher, iv, ciphertext))
+        }
+    }
+
+    ///
+    /// Calculates the tag over the length of the associated data, the associated
+    /// data, the iv and the ciphertext.
+    /// 
+    fn tag(&self, aad: &[u8], authenticated: &[u8]) -> Vec<u8> {
+        let length = (aad.len() as u64).to_be_bytes();
+        match &self.authenticator {
+            Authenticator::Cmac(cmac) => transcript(cmac.start(), &length, aad, authenticated).to_vec(),
+            Authenticator::Hmac(hmac) => transcript(hmac.start(), &length, aad, authenticated).to_vec()
+        }
+    }
+
+}
+
+///
+/// Adds the parts of the transcript to the mac in order.
+/// 
+fn transcript<M: Mac>(mut mac: M, length: &[u8], aad: &[u8], authenticated: &[u8]) -> M::Output {
+    mac.update(length);
+    mac.update(aad);
+    mac.update(authenticated);
+    mac.finalize()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    const IV: [u8; 16] = [9; 16];
+
+    fn roundtrip(etm: &Etm<16>, expected_length: usize) {
+        let framed = etm.encrypt(&IV, b"header", b"some plaintext");
+        assert_eq!(expected_length, framed.len());
+        assert_eq!(IV, framed[..16]);
+        assert_eq!(b"some plaintext".to_vec(), etm.decrypt(b"header", &framed).unwrap());
+        assert_eq!(Some(AesError::AuthenticationFailed), etm.decrypt(b"headex", &framed).err());
+        for index in [0, 16, framed.len() - 1] {
+            let mut modified = framed.clone();
+            modified[index] ^= 1;
+            assert_eq!(Some(AesError::AuthenticationFailed), etm.decrypt(b"header", &modified).err());
+        }
+        assert_eq!(Some(AesError::AuthenticationFailed), etm.decrypt(b"header", &framed[..20]).err());
+    }
+
+    #[test]
+    fn test_cbc_cmac() {
+        roundtrip(&Etm::<16>::with_cmac(&[1; 16], &[2; 16], EtmMode::Cbc), 16 + 16 + 16);
+    }
+
+    #[test]
+    fn test_ctr_hmac() {
+        let etm = Etm::<16>::with_hmac(&[1; 16], b"mac key", EtmMode::Ctr);
+        roundtrip(&etm, 16 + 14 + 32);
+        let framed = etm.encrypt(&IV, &[], b"some plaintext");
+        let mut transcript = vec![0; 8];
+        transcript.extend_from_slice(&framed[..30]);
+        assert_eq!(Hmac::new(b"mac key").mac(&transcript), framed[30..]);
+    }
+
+    #[test]
+    fn test_from_master_key() {
+        let etm = Etm::<32>::from_master_key(&[5; 32], EtmMode::Cbc);
+        let pseudorandom_key = hkdf::extract(&[], &[5; 32]);
+        let encryption_key: [u8; 32] = hkdf::expand(pseudorandom_key.as_slice(), ENCRYPTION_LABEL, 32).unwrap().try_into().unwrap();
+        let mac_key = hkdf::expand(pseudorandom_key.as_slice(), MAC_LABEL, 32).unwrap();
+        assert_ne!(encryption_key.to_vec(), mac_key);
+        let framed = etm.encrypt(&IV, b"header", b"plaintext");
+        let expected = Etm::<32>::with_hmac(&encryption_key, &mac_key, EtmMode::Cbc);
+        assert_eq!(b"plaintext".to_vec(), expected.decrypt(b"header", &framed).unwrap());
+    }
+
+    #[test]
+    fn test_aad_boundary() {
+        let etm = Etm::<16>::with_cmac(&[1; 16], &[2; 16], EtmMode::Ctr);
+        let framed = etm.encrypt(&IV, b"ab", b"c");
+        assert_eq!(Some(AesError::AuthenticationFailed), etm.decrypt(b"a", &framed).err());
+    }
+
+}
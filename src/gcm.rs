@@ -0,0 +1,518 @@
+//!
+//! Galois/Counter Mode (GCM), as specified in NIST SP 800-38D. GCM is an 
+//! authenticated encryption mode. The plaintext is encrypted in counter mode 
+//! with a 32 bit counter, and the tag is GHASH over the associated data and the
+//! ciphertext, encrypted with the first counter block.
+//! 
+//! A 12 byte nonce is used directly as the initial counter block, other lengths
+//! are hashed with GHASH first. The tag may be truncated to 12 bytes, but not 
+//! further. A nonce must never be used twice with the same key.
+//! 
+
+use std::vec::Vec;
+
+use crate::block_mode::output_prefix;
+use crate::ghash::Ghash;
+use crate::{verify_tag, Aead, Aes, AesError, SecretKey};
+
+///
+/// The shortest tag length in bytes accepted by Gcm.
+/// 
+pub const MIN_TAG_LENGTH: usize = 12;
+
+///
+/// The maximum length of the plaintext, 2^36 - 32 bytes.
+/// 
+const MAX_LENGTH: u64 = (1 << 36) - 32;
+
+///
+/// Gcm is a GCM cipher with a fixed tag length.
+/// 
+pub struct Gcm<const KEY_BYTES: usize = 16> {
+    cipher: Aes<KEY_BYTES>,
+    hash: Ghash,
+    tag_length: usize
+}
+
+impl<const KEY_BYTES: usize> Gcm<KEY_BYTES> {
+
+    ///
+    /// Creates a new Gcm cipher.
+    /// 
+    /// key: The key used to encrypt the data.
+    /// tag_length: The length of the tag in bytes, between 12 and 16.
+    /// 
+    /// result: A new Gcm cipher, AesError::TagTooShort if the tag length is less 
+    ///         than MIN_TAG_LENGTH or AesError::InvalidTagLength if it is more 
+    ///         than 16.
+    /// 
+    pub fn new(key: &[u8; KEY_BYTES], tag_length: usize) -> Result<Self, AesError> {
+        check_tag_length(tag_length)?;
+        let cipher = Aes::new(key);
+        let hash = Ghash::from_cipher(&cipher);
+        Ok(Self { cipher, hash, tag_length })
+    }
+
+    ///
+    /// Creates a new Gcm cipher from a SecretKey.
+    /// 
+    /// key: The key used to encrypt the data.
+    /// tag_length: The length of the tag in bytes, between 12 and 16.
+    /// 
+    /// result: A new Gcm cipher or the error of new.
+    /// 
+    pub fn from_secret(key: &SecretKey<KEY_BYTES>, tag_length: usize) -> Result<Self, AesError> {
+        Self::new(key.expose_secret(), tag_length)
+    }
+
+    ///
+    /// Encrypts and authenticates the plaintext.
+    /// 
+    /// nonce: The nonce, preferably 12 bytes. It must not be empty.
+    /// aad: The associated data, authenticated but not encrypted.
+    /// plaintext: The data to encrypt.
+    /// 
+    /// result: The ciphertext followed by the tag, AesError::InvalidNonceLength if
+    ///         the nonce is empty or AesError::DataTooLong if the plaintext is 
+    ///         longer than 2^36 - 32 bytes.
+    /// 
+    pub fn encrypt(&self, nonce: &[u8], aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, AesError> {
+        let mut encrypted = Vec::new();
+        self.encrypt_into(nonce, aad, plaintext, &mut encrypted)?;
+        Ok(encrypted)
+    }
+
+    ///
+    /// Decrypts the ciphertext and verifies the tag.
+    /// 
+    /// nonce: The nonce used when encrypting.
+    /// aad: The associated data used when encrypting.
+    /// ciphertext: The ciphertext followed by the tag.
+    /// 
+    /// result: The plaintext, AesError::AuthenticationFailed if the tag does not 
+    ///         match or the error from the parameter validation.
+    /// 
+    pub fn decrypt(&self, nonce: &[u8], aad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, AesError> {
+        let mut decrypted = Vec::new();
+        self.decrypt_into(nonce, aad, ciphertext, &mut decrypted)?;
+        Ok(decrypted)
+    }
+
+    ///
+    /// Encrypts and authenticates the plaintext into an output buffer. The output
+    /// is cleared first, so its capacity is reused and no memory is allocated for
+    /// the ciphertext once it is large enough.
+    /// 
+    /// nonce: The nonce, preferably 12 bytes. It must not be empty.
+    /// aad: The associated data, authenticated but not encrypted.
+    /// plaintext: The data to encrypt.
+    /// output: The buffer replaced by the ciphertext followed by the tag. Left 
+    ///         empty on an error.
+    /// 
+    /// result: Ok, AesError::InvalidNonceLength if the nonce is empty or 
+    ///         AesError::DataTooLong if the plaintext is longer than 2^36 - 32 
+    ///         bytes.
+    /// 
+    pub fn encrypt_into(&self, nonce: &[u8], aad: &[u8], plaintext: &[u8], output: &mut Vec<u8>) -> Result<(), AesError> {
+        output.clear();
+        let initial = self.initial_counter(nonce, plaintext.len())?;
+        output.reserve(plaintext.len() + self.tag_length);
+        output.extend_from_slice(plaintext);
+        self.apply_keystream(&initial, output);
+        let tag = self.tag(&initial, aad, output);
+        output.extend_from_slice(&tag[..self.tag_length]);
+        Ok(())
+    }
+
+    ///
+    /// Decrypts the ciphertext into an output buffer and verifies the tag. The tag 
+    /// is verified before anything is decrypted, and the output is cleared first,
+    /// so its capacity is reused.
+    /// 
+    /// nonce: The nonce used when encrypting.
+    /// aad: The associated data used when encrypting.
+    /// ciphertext: The ciphertext followed by the tag.
+    /// output: The buffer replaced by the plaintext. Left empty on an error.
+    /// 
+    /// result: Ok, AesError::AuthenticationFailed if the tag does not match or the
+    ///         error from the parameter validation.
+    /// 
+    pub fn decrypt_into(&self, nonce: &[u8], aad: &[u8], ciphertext: &[u8], output: &mut Vec<u8>) -> Result<(), AesError> {
+        output.clear();
+        let (initial, ciphertext) = self.verify(nonce, aad, ciphertext)?;
+        output.extend_from_slice(ciphertext);
+        self.apply_keystream(&initial, output);
+        Ok(())
+    }
+
+    ///
+    /// Encrypts and authenticates the plaintext into the start of an output slice.
+    /// 
+    /// nonce: The nonce, preferably 12 bytes. It must not be empty.
+    /// aad: The associated data, authenticated but not encrypted.
+    /// plaintext: The data to encrypt.
+    /// output: The slice the ciphertext followed by the tag is written to. Must 
+    ///         be at least the length of the plaintext plus the tag length.
+    /// 
+    /// result: The number of bytes written, AesError::OutputTooShort if the output
+    ///         is too short or the error from the parameter validation.
+    /// 
+    pub fn encrypt_into_slice(&self, nonce: &[u8], aad: &[u8], plaintext: &[u8], output: &mut [u8]) -> Result<usize, AesError> {
+        let initial = self.initial_counter(nonce, plaintext.len())?;
+        let output = output_prefix(output, plaintext.len() + self.tag_length)?;
+        let (encrypted, tag) = output.split_at_mut(plaintext.len());
+        encrypted.copy_from_slice(plaintext);
+        self.apply_keystream(&initial, encrypted);
+        tag.copy_from_slice(&self.tag(&initial, aad, encrypted)[..self.tag_length]);
+        Ok(plaintext.len() + self.tag_length)
+    }
+
+    ///
+    /// Decrypts the ciphertext into the start of an output slice and verifies the 
+    /// tag. Nothing is written to the output unless the tag matches.
+    /// 
+    /// nonce: The nonce used when encrypting.
+    /// aad: The associated data used when encrypting.
+    /// ciphertext: The ciphertext followed by the tag.
+    /// output: The slice the plaintext is written to. Must be at least the length
+    ///         of the ciphertext without the tag.
+    /// 
+    /// result: The number of bytes written, AesError::AuthenticationFailed if the 
+    ///         tag does not match, AesError::OutputTooShort if the output is too 
+    ///         short or the error from the parameter validation.
+    /// 
+    pub fn decrypt_into_slice(&self, nonce: &[u8], aad: &[u8], ciphertext: &[u8], output: &mut [u8]) -> Result<usize, AesError> {
+        let (initial, ciphertext) = self.verify(nonce, aad, ciphertext)?;
+        let output = output_prefix(output, ciphertext.len())?;
+        output.copy_from_slice(ciphertext);
+        self.apply_keystream(&initial, output);
+        Ok(ciphertext.len())
+    }
+
+    ///
+    /// Starts the authentication of a message for GMAC.
+    /// 
+    /// nonce: The nonce of the message.
+    /// 
+    /// result: A Ghash with the hash key and the encrypted initial counter block,
+    ///         or AesError::InvalidNonceLength if the nonce is empty.
+    /// 
+    pub(crate) fn start_authentication(&self, nonce: &[u8]) -> Result<(Ghash, [u8; 16]), AesError> {
+        let initial = self.initial_counter(nonce, 0)?;
+        Ok((self.hash.clone(), self.cipher.encrypt_bytes(&initial).try_into().unwrap()))
+    }
+
+    ///
+    /// Creates the initial counter block J0 from the nonce.
+    /// 
+    /// nonce: The nonce.
+    /// length: The length of the plaintext or ciphertext.
+    /// 
+    /// result: J0 or the error if the nonce or the length is invalid.
+    /// 
+    fn initial_counter(&self, nonce: &[u8], length: usize) -> Result<[u8; 16], AesError> {
+        initial_counter(&self.hash, nonce, length)
+    }
+
+    ///
+    /// Splits the tag from the ciphertext and verifies it.
+    /// 
+    /// nonce: The nonce used when encrypting.
+    /// aad: The associated data used when encrypting.
+    /// ciphertext: The ciphertext followed by the tag.
+    /// 
+    /// result: J0 and the ciphertext without the tag, AesError::AuthenticationFailed
+    ///         if the tag does not match or the error from the parameter validation.
+    /// 
+    fn verify<'a>(&self, nonce: &[u8], aad: &[u8], ciphertext: &'a [u8]) -> Result<([u8; 16], &'a [u8]), AesError> {
+        if ciphertext.len() < self.tag_length {
+            return Err(AesError::AuthenticationFailed);
+        }
+        let (ciphertext, tag) = ciphertext.split_at(ciphertext.len() - self.tag_length);
+        let initial = self.initial_counter(nonce, ciphertext.len())?;
+        verify_tag(tag, &self.tag(&initial, aad, ciphertext)[..self.tag_length])?;
+        Ok((initial, ciphertext))
+    }
+
+    ///
+    /// Applies the keystream starting at the counter block after J0 in place.
+    /// 
+    /// initial: The initial counter block J0.
+    /// data: The data to encrypt or decrypt, replaced by the data xored with the 
+    ///       keystream.
+    /// 
+    fn apply_keystream(&self, initial: &[u8; 16], data: &mut [u8]) {
+        let mut counter = *initial;
+        for chunk in data.chunks_mut(16) {
+            increment32(&mut counter);
+            let mut keystream = counter;
+            self.cipher.encrypt_block_in_place(&mut keystream);
+            chunk.iter_mut().zip(keystream).for_each(|(byte, key)| *byte ^= key);
+        }
+    }
+
+    ///
+    /// Calculates the full 16 byte tag.
+    /// 
+    /// initial: The initial counter block J0.
+    /// aad: The associated data.
+    /// ciphertext: The ciphertext.
+    /// 
+    /// result: GHASH of the associated data and the ciphertext encrypted with J0.
+    /// 
+    fn tag(&self, initial: &[u8; 16], aad: &[u8], ciphertext: &[u8]) -> [u8; 16] {
+        compute_tag(&self.hash, &self.cipher.encrypt_bytes(initial).try_into().unwrap(), aad, ciphertext)
+    }
+
+}
+
+impl<const KEY_BYTES: usize> Aead for Gcm<KEY_BYTES> {
+
+    fn encrypt(&self, nonce: &[u8], aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, AesError> {
+        Gcm::encrypt(self, nonce, aad, plaintext)
+    }
+
+    fn decrypt(&self, nonce: &[u8], aad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, AesError> {
+        Gcm::decrypt(self, nonce, aad, ciphertext)
+    }
+
+    fn encrypt_into(&self, nonce: &[u8], aad: &[u8], plaintext: &[u8], output: &mut Vec<u8>) -> Result<(), AesError> {
+        Gcm::encrypt_into(self, nonce, aad, plaintext, output)
+    }
+
+    fn decrypt_into(&self, nonce: &[u8], aad: &[u8], ciphertext: &[u8], output: &mut Vec<u8>) -> Result<(), AesError> {
+        Gcm::decrypt_into(self, nonce, aad, ciphertext, output)
+    }
+
+    fn tag_length(&self) -> usize {
+        self.tag_length
+    }
+
+}
+
+///
+/// Encrypts and authenticates the plaintext over any block encryption, such as a
+/// key held by a KeyBackend.
+/// 
+/// encrypt: Encrypts a 16 byte block with the key.
+/// tag_length: The length of the tag in bytes, between 12 and 16.
+/// nonce: The nonce, preferably 12 bytes. It must not be empty.
+/// aad: The associated data, authenticated but not encrypted.
+/// plaintext: The data to encrypt.
+/// 
+/// result: The ciphertext followed by the tag, the error from the parameter
+///         validation or the error of encrypt.
+/// 
+#[cfg(all(feature = "encrypt", feature = "decrypt"))]
+pub(crate) fn encrypt_using<F: Fn(&[u8; 16]) -> Result<[u8; 16], AesError>>(encrypt: F, tag_length: usize, nonce: &[u8], aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, AesError> {
+    check_tag_length(tag_length)?;
+    let hash = Ghash::new(&encrypt(&[0; 16])?);
+    let initial = initial_counter(&hash, nonce, plaintext.len())?;
+    let mut encrypted = plaintext.to_vec();
+    apply_keystream_using(&encrypt, &initial, &mut encrypted)?;
+    let tag = compute_tag(&hash, &encrypt(&initial)?, aad, &encrypted);
+    encrypted.extend_from_slice(&tag[..tag_length]);
+    Ok(encrypted)
+}
+
+///
+/// Decrypts the ciphertext over any block encryption and verifies the tag before
+/// anything is decrypted.
+/// 
+/// encrypt: Encrypts a 16 byte block with the key.
+/// tag_length: The length of the tag in bytes, between 12 and 16.
+/// nonce: The nonce used when encrypting.
+/// aad: The associated data used when encrypting.
+/// ciphertext: The ciphertext followed by the tag.
+/// 
+/// result: The plaintext, AesError::AuthenticationFailed if the tag does not 
+///         match, the error from the parameter validation or the error of encrypt.
+/// 
+#[cfg(all(feature = "encrypt", feature = "decrypt"))]
+pub(crate) fn decrypt_using<F: Fn(&[u8; 16]) -> Result<[u8; 16], AesError>>(encrypt: F, tag_length: usize, nonce: &[u8], aad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, AesError> {
+    check_tag_length(tag_length)?;
+    if ciphertext.len() < tag_length {
+        return Err(AesError::AuthenticationFailed);
+    }
+    let (ciphertext, tag) = ciphertext.split_at(ciphertext.len() - tag_length);
+    let hash = Ghash::new(&encrypt(&[0; 16])?);
+    let initial = initial_counter(&hash, nonce, ciphertext.len())?;
+    verify_tag(tag, &compute_tag(&hash, &encrypt(&initial)?, aad, ciphertext)[..tag_length])?;
+    let mut decrypted = ciphertext.to_vec();
+    apply_keystream_using(&encrypt, &initial, &mut decrypted)?;
+    Ok(decrypted)
+}
+
+///
+/// Applies the keystream starting at the counter block after J0 in place over
+/// any block encryption.
+/// 
+#[cfg(all(feature = "encrypt", feature = "decrypt"))]
+fn apply_keystream_using<F: Fn(&[u8; 16]) -> Result<[u8; 16], AesError>>(encrypt: &F, initial: &[u8; 16], data: &mut [u8]) -> Result<(), AesError> {
+    let mut counter = *initial;
+    for chunk in data.chunks_mut(16) {
+        increment32(&mut counter);
+        chunk.iter_mut().zip(encrypt(&counter)?).for_each(|(byte, key)| *byte ^= key);
+    }
+    Ok(())
+}
+
+///
+/// Checks the tag length.
+/// 
+/// result: Ok, AesError::TagTooShort if the tag length is less than MIN_TAG_LENGTH
+///         or AesError::InvalidTagLength if it is more than 16.
+/// 
+fn check_tag_length(tag_length: usize) -> Result<(), AesError> {
+    if tag_length < MIN_TAG_LENGTH {
+        return Err(AesError::TagTooShort);
+    }
+    if tag_length > 16 {
+        return Err(AesError::InvalidTagLength);
+    }
+    Ok(())
+}
+
+///
+/// Creates the initial counter block J0 from the nonce.
+/// 
+/// hash: The Ghash with the hash key.
+/// nonce: The nonce.
+/// length: The length of the plaintext or ciphertext.
+/// 
+/// result: J0 or the error if the nonce or the length is invalid.
+/// 
+fn initial_counter(hash: &Ghash, nonce: &[u8], length: usize) -> Result<[u8; 16], AesError> {
+    if nonce.is_empty() {
+        return Err(AesError::InvalidNonceLength { length: 0, min: 1, max: usize::MAX });
+    }
+    if length as u64 > MAX_LENGTH {
+        return Err(AesError::DataTooLong);
+    }
+    if nonce.len() == 12 {
+        let mut initial = [0; 16];
+        initial[..12].copy_from_slice(nonce);
+        initial[15] = 1;
+        return Ok(initial);
+    }
+    let mut hash = hash.clone();
+    hash.update_padded(nonce);
+    hash.update_lengths(0, nonce.len() as u64);
+    Ok(hash.finalize())
+}
+
+///
+/// Calculates the full 16 byte tag.
+/// 
+/// hash: The Ghash with the hash key.
+/// mask: The initial counter block J0 encrypted.
+/// aad: The associated data.
+/// ciphertext: The ciphertext.
+/// 
+/// result: GHASH of the associated data and the ciphertext xored with the mask.
+/// 
+fn compute_tag(hash: &Ghash, mask: &[u8; 16], aad: &[u8], ciphertext: &[u8]) -> [u8; 16] {
+    let mut hash = hash.clone();
+    hash.update_padded(aad);
+    hash.update_padded(ciphertext);
+    hash.update_lengths(aad.len() as u64, ciphertext.len() as u64);
+    let mut tag = hash.finalize();
+    tag.iter_mut().zip(mask).for_each(|(a, b)| *a ^= b);
+    tag
+}
+
+///
+/// Increments the last 32 bits of the counter block, wrapping around.
+/// 
+/// counter: The counter block.
+/// 
+fn increment32(counter: &mut [u8; 16]) {
+    let value = u32::from_be_bytes(counter[12..].try_into().unwrap()).wrapping_add(1);
+    counter[12..].copy_from_slice(&value.to_be_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::test_util::hex;
+
+    const KEY: &str = "feffe9928665731c6d6a8f9467308308";
+    const AAD: &str = "feedfacedeadbeeffeedfacedeadbeefabaddad2";
+    const PLAINTEXT: &str = "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a721c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b39";
+
+    fn gcm_vector<const KEY_BYTES: usize>(key: &str, tag_length: usize, nonce: &str, expected: &str) {
+        let gcm = Gcm::<KEY_BYTES>::new(&hex(key).try_into().unwrap(), tag_length).unwrap();
+        assert_eq!(hex(expected), gcm.encrypt(&hex(nonce), &hex(AAD), &hex(PLAINTEXT)).unwrap());
+        assert_eq!(hex(PLAINTEXT), gcm.decrypt(&hex(nonce), &hex(AAD), &hex(expected)).unwrap());
+        let mut output = Vec::new();
+        gcm.encrypt_into(&hex(nonce), &hex(AAD), &hex(PLAINTEXT), &mut output).unwrap();
+        assert_eq!(hex(expected), output);
+        gcm.decrypt_into(&hex(nonce), &hex(AAD), &hex(expected), &mut output).unwrap();
+        assert_eq!(hex(PLAINTEXT), output);
+        let mut slice = [0; 100];
+        assert_eq!(Ok(hex(expected).len()), gcm.encrypt_into_slice(&hex(nonce), &hex(AAD), &hex(PLAINTEXT), &mut slice));
+        assert_eq!(hex(expected), slice[..hex(expected).len()]);
+        assert_eq!(Ok(hex(PLAINTEXT).len()), gcm.decrypt_into_slice(&hex(nonce), &hex(AAD), &hex(expected), &mut slice));
+        assert_eq!(hex(PLAINTEXT), slice[..hex(PLAINTEXT).len()]);
+    }
+
+    #[test]
+    fn test_gcm_test_case_4() {
+        gcm_vector::<16>(KEY, 16, "cafebabefacedbaddecaf888", "42831ec2217774244b7221b784d0d49ce3aa212f2c02a4e035c17e2329aca12e21d514b25466931c7d8f6a5aac84aa051ba30b396a0aac973d58e0915bc94fbc3221a5db94fae95ae7121a47");
+    }
+
+    #[test]
+    fn test_gcm_test_case_5_truncated() {
+        gcm_vector::<16>(KEY, 12, "cafebabefacedbad", "61353b4c2806934a777ff51fa22a4755699b2a714fcdc6f83766e5f97b6c742373806900e49f24b22b097544d4896b424989b5e1ebac0f07c23f45983612d2e79e3b0785561be14a");
+    }
+
+    #[test]
+    fn test_gcm_test_case_6() {
+        let nonce = "9313225df88406e555909c5aff5269aa6a7a9538534f7da1e4c303d2a318a728c3c0c95156809539fcf0e2429a6b525416aedbf5a0de6a57a637b39b";
+        gcm_vector::<16>(KEY, 16, nonce, "8ce24998625615b603a033aca13fb894be9112a5c3a211a8ba262a3cca7e2ca701e4a9a4fba43c90ccdcb281d48c7c6fd62875d2aca417034c34aee5619cc5aefffe0bfa462af43c1699d050");
+    }
+
+    #[test]
+    fn test_gcm_test_case_16() {
+        gcm_vector::<32>(&[KEY, KEY].concat(), 16, "cafebabefacedbaddecaf888", "522dc1f099567d07f47f37a32a84427d643a8cdcbfe5c0c97598a2bd2555d1aa8cb08e48590dbb3da7b08b1056828838c5f61e6393ba7a0abcc9f66276fc6ece0f4e1768cddf8853bb2d551b");
+    }
+
+    #[test]
+    fn test_gcm_authentication_failed() {
+        let gcm = Gcm::<16>::new(&[0; 16], 16).unwrap();
+        let mut encrypted = gcm.encrypt(&[1; 12], b"header", b"payload").unwrap();
+        assert_eq!(Some(AesError::AuthenticationFailed), gcm.decrypt(&[1; 12], b"headex", &encrypted).err());
+        encrypted[0] ^= 1;
+        assert_eq!(Some(AesError::AuthenticationFailed), gcm.decrypt(&[1; 12], b"header", &encrypted).err());
+        assert_eq!(Some(AesError::AuthenticationFailed), gcm.decrypt(&[1; 12], b"header", &[0; 15]).err());
+    }
+
+    #[test]
+    fn test_gcm_into_errors() {
+        let gcm = Gcm::<16>::new(&[0; 16], 16).unwrap();
+        let mut encrypted = gcm.encrypt(&[1; 12], b"header", b"payload").unwrap();
+        let mut output = vec![0xff; 40];
+        assert_eq!(Some(AesError::InvalidNonceLength { length: 0, min: 1, max: usize::MAX }), gcm.encrypt_into(&[], &[], b"payload", &mut output).err());
+        assert!(output.is_empty());
+        assert_eq!(Some(AesError::OutputTooShort { needed: 23, given: 22 }), gcm.encrypt_into_slice(&[1; 12], b"header", b"payload", &mut [0; 22]).err());
+        assert_eq!(Some(AesError::OutputTooShort { needed: 7, given: 6 }), gcm.decrypt_into_slice(&[1; 12], b"header", &encrypted, &mut [0; 6]).err());
+        encrypted[0] ^= 1;
+        output.extend_from_slice(b"previous");
+        assert_eq!(Some(AesError::AuthenticationFailed), gcm.decrypt_into(&[1; 12], b"header", &encrypted, &mut output).err());
+        assert!(output.is_empty());
+        let mut slice = [0; 7];
+        assert_eq!(Some(AesError::AuthenticationFailed), gcm.decrypt_into_slice(&[1; 12], b"header", &encrypted, &mut slice).err());
+        assert_eq!([0; 7], slice);
+    }
+
+    #[test]
+    fn test_gcm_invalid_parameters() {
+        assert_eq!(Some(AesError::TagTooShort), Gcm::<16>::new(&[0; 16], 11).err());
+        assert_eq!(Some(AesError::TagTooShort), Gcm::<16>::new(&[0; 16], 4).err());
+        assert_eq!(Some(AesError::InvalidTagLength), Gcm::<16>::new(&[0; 16], 17).err());
+        let gcm = Gcm::<16>::new(&[0; 16], MIN_TAG_LENGTH).unwrap();
+        assert_eq!(Some(AesError::InvalidNonceLength { length: 0, min: 1, max: usize::MAX }), gcm.encrypt(&[], &[], &[]).err());
+    }
+
+}
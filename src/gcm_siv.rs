@@ -0,0 +1,235 @@
+//!
+//! AES-GCM-SIV as specified in RFC 8452. GCM-SIV is an authenticated encryption
+//! mode that is resistant to nonce misuse. The tag is calculated from the 
+//! associated data and the plaintext with POLYVAL and is used as the initial 
+//! counter, so reusing a nonce only reveals whether the same message was 
+//! encrypted twice.
+//! 
+//! A message authentication key and a message encryption key are derived from the
+//! key generating key and the nonce for every message. Only 16 and 32 byte keys
+//! are defined for GCM-SIV.
+//! 
+
+use std::vec::Vec;
+
+use crate::polyval::Polyval;
+use crate::{verify_tag, Aead, Aes, AesError, SecretKey};
+
+///
+/// The maximum length of the plaintext and the associated data, 2^36 bytes.
+/// 
+const MAX_LENGTH: u64 = 1 << 36;
+
+///
+/// GcmSiv is an AES-GCM-SIV cipher with a key generating key.
+/// 
+pub struct GcmSiv<const KEY_BYTES: usize = 16> {
+    cipher: Aes<KEY_BYTES>
+}
+
+impl<const KEY_BYTES: usize> GcmSiv<KEY_BYTES> {
+
+    ///
+    /// Fails the compilation for key lengths other than 16 and 32 bytes.
+    /// 
+    const VALID_KEY: () = assert!(KEY_BYTES == 16 || KEY_BYTES == 32, "GCM-SIV keys must be 16 or 32 bytes");
+
+    ///
+    /// Creates a new GcmSiv cipher.
+    /// 
+    /// key: The key generating key, 16 or 32 bytes.
+    /// 
+    /// result: A new GcmSiv cipher.
+    /// 
+    pub fn new(key: &[u8; KEY_BYTES]) -> Self {
+        let () = Self::VALID_KEY;
+        Self { cipher: Aes::new(key) }
+    }
+
+    ///
+    /// Encrypts and authenticates the plaintext.
+    /// 
+    /// nonce: The 12 byte nonce.
+    /// aad: The associated data, authenticated but not encrypted.
+    /// plaintext: The data to encrypt.
+    /// 
+    /// result: The ciphertext followed by the 16 byte tag or AesError::DataTooLong
+    ///         if the plaintext or the associated data is longer than 2^36 bytes.
+    /// 
+    pub fn encrypt(&self, nonce: &[u8; 12], aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, AesError> {
+        validate_length(aad, plaintext)?;
+        let (authentication_key, encryption_cipher) = self.derive_keys(nonce);
+        let tag = calculate_tag(&authentication_key, &encryption_cipher, nonce, aad, plaintext);
+        let mut encrypted = apply_keystream(&encryption_cipher, &tag, plaintext);
+        encrypted.extend_from_slice(&tag);
+        Ok(encrypted)
+    }
+
+    ///
+    /// Decrypts the ciphertext and verifies the tag.
+    /// 
+    /// nonce: The 12 byte nonce used when encrypting.
+    /// aad: The associated data used when encrypting.
+    /// ciphertext: The ciphertext followed by the 16 byte tag.
+    /// 
+    /// result: The plaintext, AesError::DataTooLong if the ciphertext or the 
+    ///         associated data is too long or AesError::AuthenticationFailed if 
+    ///         the tag does not match.
+    /// 
+    pub fn decrypt(&self, nonce: &[u8; 12], aad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, AesError> {
+        if ciphertext.len() < 16 {
+            return Err(AesError::AuthenticationFailed);
+        }
+        let (ciphertext, tag) = ciphertext.split_at(ciphertext.len() - 16);
+        validate_length(aad, ciphertext)?;
+        let (authentication_key, encryption_cipher) = self.derive_keys(nonce);
+        let tag: [u8; 16] = tag.try_into().unwrap();
+        let plaintext = apply_keystream(&encryption_cipher, &tag, ciphertext);
+        verify_tag(&tag, &calculate_tag(&authentication_key, &encryption_cipher, nonce, aad, &plaintext))?;
+        Ok(plaintext)
+    }
+
+    ///
+    /// Derives the message authentication key and the message encryption key. Each
+    /// 8 bytes of the keys are the start of the encryption of a little endian 
+    /// counter followed by the nonce.
+    /// 
+    fn derive_keys(&self, nonce: &[u8; 12]) -> ([u8; 16], Aes<KEY_BYTES>) {
+        let mut derived = Vec::with_capacity(16 + KEY_BYTES);
+        for counter in 0..(2 + KEY_BYTES / 8) as u32 {
+            let mut block = [0; 16];
+            block[..4].copy_from_slice(&counter.to_le_bytes());
+            block[4..].copy_from_slice(nonce);
+            derived.extend_from_slice(&self.cipher.encrypt_bytes(&block)[..8]);
+        }
+        let authentication_key = derived[..16].try_into().unwrap();
+        (authentication_key, Aes::new(&derived[16..].try_into().unwrap()))
+    }
+
+}
+
+impl<const KEY_BYTES: usize> From<&SecretKey<KEY_BYTES>> for GcmSiv<KEY_BYTES> {
+
+    fn from(key: &SecretKey<KEY_BYTES>) -> Self {
+        GcmSiv::new(key.expose_secret())
+    }
+
+}
+
+///
+/// Validates that the associated data and the plaintext are not too long.
+/// 
+fn validate_length(aad: &[u8], plaintext: &[u8]) -> Result<(), AesError> {
+    if aad.len() as u64 > MAX_LENGTH || plaintext.len() as u64 > MAX_LENGTH {
+        return Err(AesError::DataTooLong);
+    }
+    Ok(())
+}
+
+///
+/// Calculates the tag by encrypting the POLYVAL of the padded associated data, the 
+/// padded plaintext and the bit lengths, xored with the nonce and with the highest
+/// bit cleared.
+/// 
+fn calculate_tag<const KEY_BYTES: usize>(authentication_key: &[u8; 16], cipher: &Aes<KEY_BYTES>, nonce: &[u8; 12], aad: &[u8], plaintext: &[u8]) -> [u8; 16] {
+    let mut polyval = Polyval::new(authentication_key);
+    polyval.update_padded(aad);
+    polyval.update_padded(plaintext);
+    let mut lengths = [0; 16];
+    lengths[..8].copy_from_slice(&(aad.len() as u64 * 8).to_le_bytes());
+    lengths[8..].copy_from_slice(&(plaintext.len() as u64 * 8).to_le_bytes());
+    polyval.update_padded(&lengths);
+    let mut block = polyval.finalize();
+    for (byte, nonce_byte) in block.iter_mut().zip(nonce) {
+        *byte ^= nonce_byte;
+    }
+    block[15] &= 0x7f;
+    cipher.encrypt_bytes(&block).try_into().unwrap()
+}
+
+///
+/// Xors the data with the keystream. The initial counter block is the tag with the
+/// highest bit set, and the first 4 bytes are incremented as a little endian counter.
+/// 
+fn apply_keystream<const KEY_BYTES: usize>(cipher: &Aes<KEY_BYTES>, tag: &[u8; 16], data: &[u8]) -> Vec<u8> {
+    let mut counter = *tag;
+    counter[15] |= 0x80;
+    let mut result = Vec::with_capacity(data.len());
+    for chunk in data.chunks(16) {
+        let keystream = cipher.encrypt_bytes(&counter);
+        result.extend(chunk.iter().zip(keystream).map(|(a, b)| a ^ b));
+        let next = u32::from_le_bytes(counter[..4].try_into().unwrap()).wrapping_add(1);
+        counter[..4].copy_from_slice(&next.to_le_bytes());
+    }
+    result
+}
+
+///
+/// As an Aead the nonce must be 12 bytes, or AesError::InvalidNonceLength is returned.
+/// 
+impl<const KEY_BYTES: usize> Aead for GcmSiv<KEY_BYTES> {
+
+    fn encrypt(&self, nonce: &[u8], aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, AesError> {
+        GcmSiv::encrypt(self, &nonce.try_into().map_err(|_| AesError::InvalidNonceLength { length: nonce.len(), min: 12, max: 12 })?, aad, plaintext)
+    }
+
+    fn decrypt(&self, nonce: &[u8], aad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, AesError> {
+        GcmSiv::decrypt(self, &nonce.try_into().map_err(|_| AesError::InvalidNonceLength { length: nonce.len(), min: 12, max: 12 })?, aad, ciphertext)
+    }
+
+    fn tag_length(&self) -> usize {
+        16
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::test_util::hex;
+
+    fn known_answer<const KEY_BYTES: usize>(key: &str, nonce: &str, aad: &str, plaintext: &str, expected: &str) {
+        let cipher = GcmSiv::<KEY_BYTES>::new(&hex(key).try_into().unwrap());
+        let nonce = hex(nonce).try_into().unwrap();
+        assert_eq!(hex(expected), cipher.encrypt(&nonce, &hex(aad), &hex(plaintext)).unwrap());
+        assert_eq!(hex(plaintext), cipher.decrypt(&nonce, &hex(aad), &hex(expected)).unwrap());
+    }
+
+    #[test]
+    fn test_rfc8452_aes128() {
+        let key = "01000000000000000000000000000000";
+        let nonce = "030000000000000000000000";
+        known_answer::<16>(key, nonce, "", "", "dc20e2d83f25705bb49e439eca56de25");
+        known_answer::<16>(key, nonce, "", "0100000000000000", "b5d839330ac7b786578782fff6013b815b287c22493a364c");
+        known_answer::<16>(key, nonce, "01", "02000000000000000000000000000000 03000000000000000000000000000000",
+            "620048ef3c1e73e57e02bb8562c416a3 19e73e4caac8e96a1ecb2933145a1d71 e6af6a7f87287da059a71684ed3498e1");
+    }
+
+    #[test]
+    fn test_rfc8452_aes256() {
+        let key = "01000000000000000000000000000000 00000000000000000000000000000000";
+        let nonce = "030000000000000000000000";
+        known_answer::<32>(key, nonce, "", "01000000000000000000000000000000", "85a01b63025ba19b7fd3ddfc033b3e76 c9eac6fa700942702e90862383c6c366");
+        known_answer::<32>(key, nonce, "01", "01000000000000000000000000000000 0200000000000000",
+            "df16e904533df155003381fee52b5403 45e6922477d26dc1add0a383a26ad2ff c545c405d2b607c8");
+    }
+
+    #[test]
+    fn test_gcm_siv_text() {
+        known_answer::<16>("ee8e1ed9ff2540ae8f2ba9f50bc2f27c", "752abad3e0afb5f434dc4310", "6578616d706c65", "48656c6c6f20776f726c64",
+            "5d349ead175ef6b1def6fd4fbcdeb7e4793f4a1d7e4faa70100af1");
+    }
+
+    #[test]
+    fn test_gcm_siv_authentication_failed() {
+        let cipher = GcmSiv::<16>::new(&[1; 16]);
+        let mut encrypted = cipher.encrypt(&[3; 12], b"header", b"payload").unwrap();
+        assert_eq!(Some(AesError::AuthenticationFailed), cipher.decrypt(&[3; 12], b"headex", &encrypted).err());
+        assert_eq!(Some(AesError::AuthenticationFailed), cipher.decrypt(&[4; 12], b"header", &encrypted).err());
+        encrypted[3] ^= 0x80;
+        assert_eq!(Some(AesError::AuthenticationFailed), cipher.decrypt(&[3; 12], b"header", &encrypted).err());
+        assert_eq!(Some(AesError::AuthenticationFailed), cipher.decrypt(&[3; 12], b"header", &[0; 15]).err());
+    }
+
+}
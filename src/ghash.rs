@@ -0,0 +1,297 @@
+//!
+//! GHASH, the universal hash of GCM, as specified in NIST SP 800-38D. GHASH is a
+//! multiply and accumulate of 16 byte blocks in GF(2^128) with the hash key H.
+//! The field uses the polynomial x^128 + x^7 + x^2 + x + 1 with the bits of every
+//! byte in reflected order.
+//! 
+//! Ghash can be used to build GMAC, GCM or other constructions with a hash key
+//! from the cipher.
+//! 
+//! The multiplication uses the carry-less multiply instructions when the cpu has
+//! them, PCLMULQDQ on x86_64 and PMULL on aarch64, and a constant time loop over
+//! the bits otherwise.
+//! 
+//! Without the instructions, a Ghash created with Ghash::with_table precomputes 
+//! the multiples of H by every 4 bit polynomial, so a block is multiplied with 32
+//! lookups instead of 128 steps. The lookups depend on the data, so like the 
+//! T-table backend the table is not safe against cache timing attacks, and it is
+//! only used when asked for or when the cipher uses RoundBackend::TTable.
+//! 
+
+use std::boxed::Box;
+
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+use crate::clmul;
+use crate::zeroize::Zeroize;
+use crate::{Aes, RoundBackend};
+
+///
+/// The reductions of the four bits shifted out when multiplying by x^4. Bit b of 
+/// the index is the coefficient of x^(127 - b), which becomes x^(3 - b) * x^128.
+/// 
+const REDUCTIONS: [u128; 16] = reductions();
+
+///
+/// Ghash accumulates 16 byte blocks with the hash key H.
+/// 
+#[derive(Clone)]
+pub struct Ghash {
+    key: u128,
+    table: Option<Box<[u128; 16]>>,
+    accumulator: u128
+}
+
+impl Ghash {
+
+    ///
+    /// Creates a new Ghash.
+    /// 
+    /// key: The 16 byte hash key H.
+    /// 
+    /// result: A new Ghash with the accumulator set to zero.
+    /// 
+    pub fn new(key: &[u8; 16]) -> Self {
+        Self { key: u128::from_be_bytes(*key), table: None, accumulator: 0 }
+    }
+
+    ///
+    /// Creates a new Ghash multiplying with a table of the hash key, which is 
+    /// faster than the constant time loop but not safe against cache timing 
+    /// attacks. The table is only computed if the cpu has no carry-less multiply 
+    /// instructions.
+    /// 
+    /// key: The 16 byte hash key H.
+    /// 
+    /// result: A new Ghash with the accumulator set to zero.
+    /// 
+    pub fn with_table(key: &[u8; 16]) -> Self {
+        let mut ghash = Self::new(key);
+        #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+        if clmul::is_supported() {
+            return ghash;
+        }
+        ghash.table = Some(Box::new(key_table(ghash.key)));
+        ghash
+    }
+
+    ///
+    /// Creates a new Ghash with the hash key of GCM, the encryption of the zero block.
+    /// The table of Ghash::with_table is used if the cipher uses the T-table
+    /// backend, since the lookups of the cipher already depend on the data.
+    /// 
+    /// cipher: The cipher used to derive the hash key.
+    /// 
+    /// result: A new Ghash with the accumulator set to zero.
+    /// 
+    pub fn from_cipher<const KEY_BYTES: usize>(cipher: &Aes<KEY_BYTES>) -> Self {
+        let key = cipher.encrypt_bytes(&[0; 16]).try_into().unwrap();
+        match cipher.backend() {
+            RoundBackend::TTable => Self::with_table(&key),
+            _ => Self::new(&key)
+        }
+    }
+
+    ///
+    /// Adds a block to the accumulator and multiplies it by the hash key.
+    /// 
+    /// block: The 16 byte block.
+    /// 
+    pub fn update_block(&mut self, block: &[u8; 16]) {
+        let value = self.accumulator ^ u128::from_be_bytes(*block);
+        self.accumulator = match &self.table {
+            Some(table) => table_multiply(value, table),
+            None => multiply(value, self.key)
+        };
+    }
+
+    ///
+    /// Adds the data to the hash block by block. A partial last block is padded 
+    /// with zeros, as GCM does for the associated data and the ciphertext.
+    /// 
+    /// data: The data to hash.
+    /// 
+    pub fn update_padded(&mut self, data: &[u8]) {
+        for chunk in data.chunks(16) {
+            let mut block = [0; 16];
+            block[..chunk.len()].copy_from_slice(chunk);
+            self.update_block(&block);
+        }
+    }
+
+    ///
+    /// Adds the GCM length block with the bit lengths of the associated data and
+    /// the ciphertext.
+    /// 
+    /// aad_length: The length of the associated data in bytes.
+    /// ciphertext_length: The length of the ciphertext in bytes.
+    /// 
+    pub fn update_lengths(&mut self, aad_length: u64, ciphertext_length: u64) {
+        let mut block = [0; 16];
+        block[..8].copy_from_slice(&(aad_length * 8).to_be_bytes());
+        block[8..].copy_from_slice(&(ciphertext_length * 8).to_be_bytes());
+        self.update_block(&block);
+    }
+
+    ///
+    /// The hash of all the blocks added.
+    /// 
+    /// result: The 16 byte accumulator.
+    /// 
+    pub fn finalize(&self) -> [u8; 16] {
+        self.accumulator.to_be_bytes()
+    }
+
+}
+
+///
+/// Multiplies x and y in GF(2^128) with reflected bits, with the carry-less 
+/// multiply instructions if the cpu supports them.
+/// 
+fn multiply(x: u128, y: u128) -> u128 {
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    if clmul::is_supported() {
+        // The instructions are only used when the cpu supports them.
+        return unsafe { clmul::multiply(x, y) };
+    }
+    software_multiply(x, y)
+}
+
+///
+/// Multiplies x and y in GF(2^128) with reflected bits, as algorithm 1 in 
+/// NIST SP 800-38D. The bits of x and the reductions select the values with masks
+/// instead of branches, so the time does not depend on the factors.
+/// 
+fn software_multiply(x: u128, y: u128) -> u128 {
+    let mut result = 0;
+    let mut value = y;
+    for bit in (0..128).rev() {
+        result ^= value & ((x >> bit) & 1).wrapping_neg();
+        value = (value >> 1) ^ ((0xe1 << 120) & (value & 1).wrapping_neg());
+    }
+    result
+}
+
+///
+/// Precomputes the products of the hash key and every polynomial of degree below 4.
+/// Bit 3 of the index is the coefficient of x^0 and bit 0 the coefficient of x^3,
+/// as the four bits of a window of the other factor.
+/// 
+fn key_table(key: u128) -> [u128; 16] {
+    let mut table = [0; 16];
+    let mut power = key;
+    for bit in (0..4).rev() {
+        table[1 << bit] = power;
+        power = (power >> 1) ^ ((0xe1 << 120) & (power & 1).wrapping_neg());
+    }
+    for index in 3..16usize {
+        table[index] = table[index & (index - 1)] ^ table[index & index.wrapping_neg()];
+    }
+    table
+}
+
+///
+/// Multiplies x by the hash key of the table, four bits of x at a time starting 
+/// with the highest powers. The result is multiplied by x^4 before each window is
+/// added, reducing the bits shifted out with the reduction table.
+/// 
+fn table_multiply(x: u128, table: &[u128; 16]) -> u128 {
+    let mut result = 0;
+    for window in 0..32 {
+        result = (result >> 4) ^ REDUCTIONS[(result & 0xf) as usize];
+        result ^= table[((x >> (4 * window)) & 0xf) as usize];
+    }
+    result
+}
+
+///
+/// Generates the reduction table.
+/// 
+const fn reductions() -> [u128; 16] {
+    let mut reductions = [0; 16];
+    let mut index = 0;
+    while index < 16 {
+        let mut bit = 0;
+        while bit < 4 {
+            if (index >> bit) & 1 == 1 {
+                reductions[index] ^= (0xe1 << 120) >> (3 - bit);
+            }
+            bit += 1;
+        }
+        index += 1;
+    }
+    reductions
+}
+
+impl Drop for Ghash {
+
+    fn drop(&mut self) {
+        self.key.zeroize();
+        if let Some(table) = &mut self.table {
+            table.zeroize();
+        }
+        self.accumulator.zeroize();
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::test_util::hex;
+    use crate::Aes128;
+
+    #[test]
+    fn test_gcm_test_case_2() {
+        let mut ghash = Ghash::from_cipher(&Aes128::new(&[0; 16]));
+        ghash.update_padded(&hex("0388dace60b6a392f328c2b971b2fe78"));
+        ghash.update_lengths(0, 16);
+        assert_eq!(hex("f38cbb1ad69223dcc3457ae5b6b0f885"), ghash.finalize());
+    }
+
+    #[test]
+    fn test_gcm_test_case_4() {
+        let mut ghash = Ghash::new(&hex("b83b533708bf535d0aa6e52980d53b78").try_into().unwrap());
+        let aad = hex("feedfacedeadbeeffeedfacedeadbeef abaddad2");
+        let ciphertext = hex("42831ec2217774244b7221b784d0d49c e3aa212f2c02a4e035c17e2329aca12e
+                              21d514b25466931c7d8f6a5aac84aa05 1ba30b396a0aac973d58e091");
+        ghash.update_padded(&aad);
+        ghash.update_padded(&ciphertext);
+        ghash.update_lengths(aad.len() as u64, ciphertext.len() as u64);
+        assert_eq!(hex("698e57f70e6ecc7fd9463b7260a9ae5f"), ghash.finalize());
+    }
+
+    #[test]
+    fn test_with_table() {
+        let key: [u8; 16] = hex("b83b533708bf535d0aa6e52980d53b78").try_into().unwrap();
+        let table = key_table(u128::from_be_bytes(key));
+        for value in [1 << 127, 1, 0x1234, u128::MAX, u128::from_be_bytes(hex("66e94bd4ef8a2c3b884cfa59ca342b2e").try_into().unwrap())] {
+            assert_eq!(software_multiply(value, u128::from_be_bytes(key)), table_multiply(value, &table));
+        }
+        let mut ghash = Ghash::with_table(&key);
+        let mut tabled = Ghash { table: Some(Box::new(table)), ..Ghash::new(&key) };
+        for data in [Ghash::new(&key).finalize().as_slice(), &hex("feedfacedeadbeeffeedfacedeadbeefabaddad2"), &[0xa5; 100]] {
+            ghash.update_padded(data);
+            tabled.update_padded(data);
+        }
+        assert_eq!(ghash.finalize(), tabled.finalize());
+        let cipher = Aes128::new(&[0; 16]).with_backend(RoundBackend::TTable);
+        let mut ghash = Ghash::from_cipher(&cipher);
+        ghash.update_padded(&hex("0388dace60b6a392f328c2b971b2fe78"));
+        ghash.update_lengths(0, 16);
+        assert_eq!(hex("f38cbb1ad69223dcc3457ae5b6b0f885"), ghash.finalize());
+    }
+
+    #[test]
+    fn test_multiply() {
+        let one = 1 << 127;
+        let value = u128::from_be_bytes(hex("66e94bd4ef8a2c3b884cfa59ca342b2e").try_into().unwrap());
+        assert_eq!(value, multiply(value, one));
+        assert_eq!(multiply(value, 0x1234), multiply(0x1234, value));
+        assert_eq!(0, multiply(value, 0));
+        let key = u128::from_be_bytes(hex("b83b533708bf535d0aa6e52980d53b78").try_into().unwrap());
+        assert_eq!(software_multiply(value, key), multiply(value, key));
+        assert_eq!(software_multiply(u128::MAX, u128::MAX), multiply(u128::MAX, u128::MAX));
+    }
+
+}
@@ -0,0 +1,136 @@
+//!
+//! GMAC, the authentication only mode of GCM as specified in NIST SP 800-38D. 
+//! GMAC is GCM with the data as associated data and an empty plaintext, so the
+//! mac is GHASH over the data encrypted with the first counter block.
+//! 
+//! The mac can be calculated at once or incrementally with a GmacState. Unlike
+//! Cmac, GMAC needs a nonce, which must never be used twice with the same
+//! key.
+//! 
+
+use crate::gcm::Gcm;
+use crate::ghash::Ghash;
+use crate::mac::BlockBuffer;
+use crate::{AesError, Mac, SecretKey};
+
+///
+/// Gmac is a GMAC with 16 byte macs.
+/// 
+pub struct Gmac<const KEY_BYTES: usize = 16> {
+    gcm: Gcm<KEY_BYTES>
+}
+
+impl<const KEY_BYTES: usize> Gmac<KEY_BYTES> {
+
+    ///
+    /// Creates a new Gmac.
+    /// 
+    /// key: The key of the mac.
+    /// 
+    /// result: A new Gmac.
+    /// 
+    pub fn new(key: &[u8; KEY_BYTES]) -> Self {
+        Self { gcm: Gcm::new(key, 16).unwrap() }
+    }
+
+    ///
+    /// Calculates the mac of the data.
+    /// 
+    /// nonce: The nonce, preferably 12 bytes. It must not be empty.
+    /// data: The data to authenticate.
+    /// 
+    /// result: The 16 byte mac or AesError::InvalidNonceLength if the nonce is empty.
+    /// 
+    pub fn mac(&self, nonce: &[u8], data: &[u8]) -> Result<[u8; 16], AesError> {
+        let mut state = self.start(nonce)?;
+        state.update(data);
+        Ok(state.finalize())
+    }
+
+    ///
+    /// Starts an incremental mac.
+    /// 
+    /// nonce: The nonce, preferably 12 bytes. It must not be empty.
+    /// 
+    /// result: A GmacState without any data or AesError::InvalidNonceLength if the
+    ///         nonce is empty.
+    /// 
+    pub fn start(&self, nonce: &[u8]) -> Result<GmacState, AesError> {
+        let (hash, mask) = self.gcm.start_authentication(nonce)?;
+        Ok(GmacState { hash, mask, buffer: BlockBuffer::new(), length: 0 })
+    }
+
+}
+
+impl<const KEY_BYTES: usize> From<&SecretKey<KEY_BYTES>> for Gmac<KEY_BYTES> {
+
+    fn from(key: &SecretKey<KEY_BYTES>) -> Self {
+        Gmac::new(key.expose_secret())
+    }
+
+}
+
+///
+/// GmacState is an incremental GMAC of one message.
+/// 
+pub struct GmacState {
+    hash: Ghash,
+    mask: [u8; 16],
+    buffer: BlockBuffer,
+    length: u64
+}
+
+impl Mac for GmacState {
+
+    type Output = [u8; 16];
+
+    fn update(&mut self, data: &[u8]) {
+        self.length += data.len() as u64;
+        let hash = &mut self.hash;
+        self.buffer.update(data, |block| hash.update_block(block));
+    }
+
+    fn finalize(mut self) -> Self::Output {
+        self.hash.update_padded(self.buffer.last());
+        self.hash.update_lengths(self.length, 0);
+        let mut mac = self.hash.finalize();
+        mac.iter_mut().zip(self.mask).for_each(|(a, b)| *a ^= b);
+        mac
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::test_util::hex;
+
+    #[test]
+    fn test_gmac_aes128() {
+        let gmac = Gmac::<16>::new(&hex("77be63708971c4e240d1cb79e8d77feb").try_into().unwrap());
+        let mac = gmac.mac(&hex("e0e00f19fed7ba0136a797f3"), &hex("7a43ec1d9c0a5a78a0b16533a6213cab")).unwrap();
+        assert_eq!(hex("209fcc8d3675ed938e9c7166709dd946"), mac);
+    }
+
+    #[test]
+    fn test_gmac_aes256_short_nonce() {
+        let gmac = Gmac::<32>::new(&[0; 32]);
+        assert_eq!(hex("65fcbcbe726d6c306a433aef861eb079"), gmac.mac(&[0; 8], b"routing header").unwrap());
+        assert_eq!(Some(AesError::InvalidNonceLength { length: 0, min: 1, max: usize::MAX }), gmac.mac(&[], b"routing header").err());
+    }
+
+    #[test]
+    fn test_incremental() {
+        let gmac = Gmac::<32>::new(&[0; 32]);
+        let mut state = gmac.start(&[0; 8]).unwrap();
+        state.update(b"routing");
+        state.update(b" header");
+        assert_eq!(hex("65fcbcbe726d6c306a433aef861eb079"), state.finalize());
+        let data: Vec<u8> = (0..100).collect();
+        let mut state = gmac.start(&[1; 12]).unwrap();
+        data.chunks(16).for_each(|chunk| state.update(chunk));
+        assert_eq!(gmac.mac(&[1; 12], &data).unwrap(), state.finalize());
+    }
+
+}
@@ -0,0 +1,76 @@
+//!
+//! Hex encoding and decoding, used for blocks given as hex strings and for the
+//! printable parts of key blocks.
+//! 
+
+use std::string::String;
+use std::vec::Vec;
+
+///
+/// Encodes the bytes as lowercase hex.
+/// 
+/// data: The bytes to encode.
+/// 
+/// result: A string with two hex digits for each byte.
+/// 
+pub(crate) fn encode(data: &[u8]) -> String {
+    encode_with(data, b"0123456789abcdef")
+}
+
+///
+/// Encodes the bytes as uppercase hex.
+/// 
+/// data: The bytes to encode.
+/// 
+/// result: A string with two hex digits for each byte.
+/// 
+#[cfg(all(feature = "encrypt", feature = "decrypt"))]
+pub(crate) fn encode_upper(data: &[u8]) -> String {
+    encode_with(data, b"0123456789ABCDEF")
+}
+
+///
+/// Encodes the bytes with the specified digits.
+/// 
+fn encode_with(data: &[u8], digits: &[u8; 16]) -> String {
+    data.iter().flat_map(|byte| [digits[(byte >> 4) as usize] as char, digits[(byte & 15) as usize] as char]).collect()
+}
+
+///
+/// Decodes uppercase or lowercase hex.
+/// 
+/// data: The hex digits, two for each byte.
+/// 
+/// result: The decoded bytes or None if the number of digits is odd or a 
+///         character is not a hex digit.
+/// 
+pub(crate) fn decode(data: &[u8]) -> Option<Vec<u8>> {
+    if !data.len().is_multiple_of(2) {
+        return None;
+    }
+    let digit = |digit: u8| (digit as char).to_digit(16);
+    data.chunks(2).map(|pair| Some((digit(pair[0])? << 4 | digit(pair[1])?) as u8)).collect()
+}
+
+#[cfg(all(test, feature = "encrypt", feature = "decrypt"))]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        assert_eq!("00112233445566778899aabbccddeeff", encode(&[0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff]));
+        assert_eq!("0AF1", encode_upper(&[0x0a, 0xf1]));
+        assert_eq!("", encode(&[]));
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(Some(vec![0x0a, 0xf1, 0xab]), decode(b"0aF1Ab"));
+        assert_eq!(Some(vec![]), decode(b""));
+        assert_eq!(None, decode(b"0a1"));
+        assert_eq!(None, decode(b"0g"));
+        assert_eq!(None, decode(b"+1"));
+    }
+
+}
@@ -0,0 +1,107 @@
+//!
+//! HKDF with HMAC-SHA256, as specified in RFC 5869. Extract condenses input key
+//! material into a pseudorandom key, and expand derives any number of keys from
+//! it, each bound to a distinct info label.
+//! 
+//! A single master key can be expanded into separate encryption and mac keys, 
+//! as Etm::from_master_key does.
+//! 
+
+use std::vec::Vec;
+
+use crate::hmac::Hmac;
+use crate::{AesError, Mac};
+
+///
+/// The maximum output length of expand, 255 HMAC-SHA256 outputs.
+/// 
+pub const MAX_OUTPUT_LENGTH: usize = 255 * 32;
+
+///
+/// Extracts a pseudorandom key from the input key material.
+/// 
+/// salt: The optional salt, empty for a salt of 32 zeros.
+/// input_key_material: The input key material.
+/// 
+/// result: The 32 byte pseudorandom key.
+/// 
+pub fn extract(salt: &[u8], input_key_material: &[u8]) -> [u8; 32] {
+    let salt: &[u8] = if salt.is_empty() { &[0; 32] } else { salt };
+    Hmac::new(salt).mac(input_key_material)
+}
+
+///
+/// Expands the pseudorandom key into output key material.
+/// 
+/// pseudorandom_key: The pseudorandom key, from extract or another uniform key of
+///                   at least 32 bytes.
+/// info: The label that makes the output distinct for every use.
+/// length: The length of the output.
+/// 
+/// result: The output key material or AesError::InvalidKdfParameters if the 
+///         length is more than MAX_OUTPUT_LENGTH.
+/// 
+pub fn expand(pseudorandom_key: &[u8], info: &[u8], length: usize) -> Result<Vec<u8>, AesError> {
+    if length > MAX_OUTPUT_LENGTH {
+        return Err(AesError::InvalidKdfParameters);
+    }
+    let hmac = Hmac::new(pseudorandom_key);
+    let mut output = Vec::with_capacity(length);
+    let mut previous: Vec<u8> = Vec::new();
+    for counter in 1..=length.div_ceil(32) as u8 {
+        let mut state = hmac.start();
+        state.update(&previous);
+        state.update(info);
+        state.update(&[counter]);
+        previous = state.finalize().to_vec();
+        output.extend_from_slice(&previous);
+    }
+    output.truncate(length);
+    Ok(output)
+}
+
+///
+/// Extracts and expands in one step.
+/// 
+/// salt: The optional salt, empty for a salt of 32 zeros.
+/// input_key_material: The input key material.
+/// info: The label that makes the output distinct for every use.
+/// length: The length of the output.
+/// 
+/// result: The output key material or AesError::InvalidKdfParameters if the 
+///         length is more than MAX_OUTPUT_LENGTH.
+/// 
+pub fn derive(salt: &[u8], input_key_material: &[u8], info: &[u8], length: usize) -> Result<Vec<u8>, AesError> {
+    expand(&extract(salt, input_key_material), info, length)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::test_util::hex;
+
+    #[test]
+    fn test_rfc5869_case_1() {
+        let salt: Vec<u8> = (0..13).collect();
+        let info: Vec<u8> = (0xf0..0xfa).collect();
+        let pseudorandom_key = extract(&salt, &[0x0b; 22]);
+        assert_eq!(hex("077709362c2e32df0ddc3f0dc47bba6390b6c73bb50f9c3122ec844ad7c2b3e5"), pseudorandom_key);
+        let expected = hex("3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf34007208d5b887185865");
+        assert_eq!(expected, expand(&pseudorandom_key, &info, 42).unwrap());
+    }
+
+    #[test]
+    fn test_rfc5869_case_3() {
+        let expected = hex("8da4e775a563c18f715f802a063c5a31b8a11f5c5ee1879ec3454e5f3c738d2d9d201395faa4b61a96c8");
+        assert_eq!(expected, derive(&[], &[0x0b; 22], &[], 42).unwrap());
+    }
+
+    #[test]
+    fn test_expand_length() {
+        assert_eq!(MAX_OUTPUT_LENGTH, expand(&[1; 32], b"info", MAX_OUTPUT_LENGTH).unwrap().len());
+        assert_eq!(Some(AesError::InvalidKdfParameters), expand(&[1; 32], b"info", MAX_OUTPUT_LENGTH + 1).err());
+        assert_ne!(expand(&[1; 32], b"encryption", 32).unwrap(), expand(&[1; 32], b"mac", 32).unwrap());
+    }
+
+}
@@ -0,0 +1,161 @@
+//!
+//! HMAC-SHA256, as specified in RFC 2104 and FIPS 198-1. HMAC is a mac built 
+//! from a hash function, here the SHA-256 of the crate. It is used by the 
+//! encrypt-then-mac composition and the key derivation functions, including
+//! PBKDF2 for scrypt.
+//! 
+
+use std::vec::Vec;
+
+use crate::sha256::{sha256, Sha256};
+use crate::{Mac, SecretKey};
+
+///
+/// Hmac is an HMAC-SHA256 with 32 byte macs.
+/// 
+#[derive(Clone)]
+pub struct Hmac {
+    inner: Sha256,
+    outer: Sha256
+}
+
+impl Hmac {
+
+    ///
+    /// Creates a new Hmac.
+    /// 
+    /// key: The key of the mac, of any length. Keys longer than 64 bytes are 
+    ///      hashed first.
+    /// 
+    /// result: A new Hmac.
+    /// 
+    pub fn new(key: &[u8]) -> Self {
+        let mut block = [0; 64];
+        if key.len() > 64 {
+            block[..32].copy_from_slice(&sha256(key));
+        } else {
+            block[..key.len()].copy_from_slice(key);
+        }
+        let mut inner = Sha256::new();
+        inner.update(&block.map(|byte| byte ^ 0x36));
+        let mut outer = Sha256::new();
+        outer.update(&block.map(|byte| byte ^ 0x5c));
+        Self { inner, outer }
+    }
+
+    ///
+    /// Calculates the mac of the data.
+    /// 
+    /// data: The data to authenticate. May be of any length.
+    /// 
+    /// result: The 32 byte mac.
+    /// 
+    pub fn mac(&self, data: &[u8]) -> [u8; 32] {
+        let mut state = self.start();
+        state.update(data);
+        state.finalize()
+    }
+
+    ///
+    /// Starts an incremental mac.
+    /// 
+    /// result: An HmacState without any data.
+    /// 
+    pub fn start(&self) -> HmacState<'_> {
+        HmacState { hmac: self, inner: self.inner.clone() }
+    }
+
+}
+
+impl<const KEY_BYTES: usize> From<&SecretKey<KEY_BYTES>> for Hmac {
+
+    fn from(key: &SecretKey<KEY_BYTES>) -> Self {
+        Hmac::new(key.expose_secret())
+    }
+
+}
+
+///
+/// HmacState is an incremental HMAC-SHA256.
+/// 
+pub struct HmacState<'a> {
+    hmac: &'a Hmac,
+    inner: Sha256
+}
+
+impl Mac for HmacState<'_> {
+
+    type Output = [u8; 32];
+
+    fn update(&mut self, data: &[u8]) {
+        self.inner.update(data);
+    }
+
+    fn finalize(self) -> Self::Output {
+        let mut outer = self.hmac.outer.clone();
+        outer.update(&self.inner.finalize());
+        outer.finalize()
+    }
+
+}
+
+///
+/// Derives a key from the password with PBKDF2 and HMAC-SHA256, as in RFC 8018.
+/// 
+/// password: The password.
+/// salt: The salt.
+/// iterations: The number of iterations, at least 1.
+/// length: The length of the key.
+/// 
+/// result: The key.
+/// 
+pub(crate) fn pbkdf2_sha256(password: &[u8], salt: &[u8], iterations: u32, length: usize) -> Vec<u8> {
+    let hmac = Hmac::new(password);
+    let mut output = Vec::with_capacity(length);
+    for index in 1..=length.div_ceil(32) as u32 {
+        let mut state = hmac.start();
+        state.update(salt);
+        state.update(&index.to_be_bytes());
+        let mut value = state.finalize();
+        let mut block = value;
+        for _ in 1..iterations {
+            value = hmac.mac(&value);
+            block.iter_mut().zip(value).for_each(|(a, b)| *a ^= b);
+        }
+        output.extend_from_slice(&block);
+    }
+    output.truncate(length);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::test_util::hex;
+
+    #[test]
+    fn test_rfc4231_vectors() {
+        assert_eq!(hex("b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7"), Hmac::new(&[0x0b; 20]).mac(b"Hi There"));
+        assert_eq!(hex("5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"), Hmac::new(b"Jefe").mac(b"what do ya want for nothing?"));
+        let hmac = Hmac::new(&[0xaa; 131]);
+        assert_eq!(hex("60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"), hmac.mac(b"Test Using Larger Than Block-Size Key - Hash Key First"));
+    }
+
+    #[test]
+    fn test_pbkdf2_sha256() {
+        let expected = hex("55ac046e56e3089fec1691c22544b605f94185216dde0465e68b9d57c20dacbc49ca9cccf179b645991664b39d77ef317c71b845b1e30bd509112041d3a19783");
+        assert_eq!(expected, pbkdf2_sha256(b"passwd", b"salt", 1, 64));
+        assert_eq!(hex("4ddcd8f60b98be21830c"), pbkdf2_sha256(b"Password", b"NaCl", 80000, 10));
+    }
+
+    #[test]
+    fn test_incremental() {
+        let hmac = Hmac::new(b"Jefe");
+        let mut state = hmac.start();
+        state.update(b"what do ya ");
+        state.update(b"want for nothing?");
+        assert_eq!(Ok(()), state.verify(&hex("5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843")));
+    }
+
+}
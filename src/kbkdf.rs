@@ -0,0 +1,106 @@
+//!
+//! The key based key derivation function in counter mode, as specified in NIST 
+//! SP 800-108, with CMAC as the pseudorandom function. Every block of output is
+//! the CMAC of a counter followed by the fixed input data:
+//! 
+//! K(i) = CMAC(key, [i] || fixed input), for i = 1, 2, ...
+//! 
+//! derive uses the common layout of the fixed input with a 32 bit counter,
+//! 
+//! label || 0x00 || context || [L]
+//! 
+//! where L is the output length in bits as a 32 bit big endian number. Protocols
+//! with their own layout, such as payment key blocks, use derive_with_fixed_input.
+//! 
+
+use std::vec::Vec;
+
+use crate::cmac::Cmac;
+use crate::{AesError, Mac};
+
+///
+/// Derives key material from the key, the label and the context.
+/// 
+/// key: The key derivation key.
+/// label: The label identifying the purpose of the derived key.
+/// context: The context, for example the identities of the parties.
+/// length: The length of the output in bytes.
+/// 
+/// result: The derived key material or AesError::InvalidKdfParameters if the 
+///         length is zero or its length in bits does not fit in 32 bits.
+/// 
+pub fn derive<const KEY_BYTES: usize>(key: &[u8; KEY_BYTES], label: &[u8], context: &[u8], length: usize) -> Result<Vec<u8>, AesError> {
+    let bits = u32::try_from(length as u64 * 8).map_err(|_| AesError::InvalidKdfParameters)?;
+    let mut fixed_input = Vec::with_capacity(label.len() + context.len() + 5);
+    fixed_input.extend_from_slice(label);
+    fixed_input.push(0);
+    fixed_input.extend_from_slice(context);
+    fixed_input.extend_from_slice(&bits.to_be_bytes());
+    derive_with_fixed_input(key, 4, &fixed_input, length)
+}
+
+///
+/// Derives key material from the key and fixed input data laid out by the caller.
+/// 
+/// key: The key derivation key.
+/// counter_bytes: The length of the big endian counter, 1 to 4 bytes.
+/// fixed_input: The fixed input data following the counter.
+/// length: The length of the output in bytes.
+/// 
+/// result: The derived key material or AesError::InvalidKdfParameters if the 
+///         counter length is not supported, the length is zero or the counter 
+///         would overflow.
+/// 
+pub fn derive_with_fixed_input<const KEY_BYTES: usize>(key: &[u8; KEY_BYTES], counter_bytes: usize, fixed_input: &[u8], length: usize) -> Result<Vec<u8>, AesError> {
+    let blocks = length.div_ceil(16) as u64;
+    if !(1..=4).contains(&counter_bytes) || length == 0 || blocks >= 1 << (8 * counter_bytes) {
+        return Err(AesError::InvalidKdfParameters);
+    }
+    let cmac = Cmac::new(key);
+    let mut output = Vec::with_capacity(blocks as usize * 16);
+    for counter in 1..=blocks as u32 {
+        let mut state = cmac.start();
+        state.update(&counter.to_be_bytes()[4 - counter_bytes..]);
+        state.update(fixed_input);
+        output.extend_from_slice(&state.finalize());
+    }
+    output.truncate(length);
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::test_util::hex;
+
+    fn key<const KEY_BYTES: usize>() -> [u8; KEY_BYTES] {
+        core::array::from_fn(|index| index as u8)
+    }
+
+    #[test]
+    fn test_derive() {
+        assert_eq!(hex("4a63599f94bd3bf38b763386bb5397c690a3a40600f96a691059cc001c163c49"), derive(&key::<16>(), b"label", b"context", 32).unwrap());
+        assert_eq!(hex("ac011b6d6a116f5a6072ee407059ac35"), derive(&key::<16>(), &[], &[], 16).unwrap());
+        assert_eq!(hex("a272f5e961c25705679824e572d62d311bf0ace1a38e822821243f8167298aca114afc3f40fcce4f"),
+                   derive(&key::<32>(), b"encryption", b"session 1", 40).unwrap());
+    }
+
+    #[test]
+    fn test_fixed_input() {
+        let expected = hex("4987c223965fbda26fd82bfb50c636c9");
+        assert_eq!(expected, derive_with_fixed_input(&key::<16>(), 1, &hex("4b0000040080"), 16).unwrap());
+        let fixed_input = hex("6c6162656c00636f6e7465787400000100");
+        assert_eq!(derive(&key::<16>(), b"label", b"context", 32).unwrap(), derive_with_fixed_input(&key::<16>(), 4, &fixed_input, 32).unwrap());
+    }
+
+    #[test]
+    fn test_invalid_parameters() {
+        assert_eq!(Some(AesError::InvalidKdfParameters), derive(&key::<16>(), &[], &[], 0).err());
+        assert_eq!(Some(AesError::InvalidKdfParameters), derive_with_fixed_input(&key::<16>(), 0, &[], 16).err());
+        assert_eq!(Some(AesError::InvalidKdfParameters), derive_with_fixed_input(&key::<16>(), 5, &[], 16).err());
+        assert_eq!(Some(AesError::InvalidKdfParameters), derive_with_fixed_input(&key::<16>(), 1, &[], 255 * 16 + 1).err());
+        assert_eq!(255 * 16, derive_with_fixed_input(&key::<16>(), 1, &[], 255 * 16).unwrap().len());
+    }
+
+}
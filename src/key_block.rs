@@ -0,0 +1,300 @@
+//!
+//! Key blocks in the format of ANSI X9 TR-31 version D, for exchanging keys with
+//! payment HSMs. A key block is printable ASCII:
+//! 
+//! header (16 characters) || encrypted payload (hex) || mac (32 hex characters)
+//! 
+//! The header holds the version D, the length of the key block, the key usage, 
+//! the algorithm, the mode of use, the key version number and the exportability 
+//! of the key. The payload is the key length in bits (2 bytes), the key and random
+//! padding to a multiple of 16 bytes.
+//! 
+//! Two keys are derived from the key block protection key with the SP 800-108 
+//! KDF in kbkdf. The mac is the CMAC of the header and the clear payload with the
+//! authentication key, and the payload is encrypted with AES-CBC under the 
+//! encryption key with the mac as iv. The header is bound to the key by the mac.
+//! 
+//! Optional header blocks are not supported.
+//! 
+
+use std::vec::Vec;
+
+use crate::cmac::Cmac;
+use crate::hex;
+use crate::kbkdf::derive_with_fixed_input;
+use crate::random::fill_random;
+use crate::zeroize::Zeroizing;
+use crate::{cbc, verify_tag, Aes, AesError, Mac, SecretKey};
+
+///
+/// The version of the key block, the AES key derivation binding method.
+/// 
+pub const VERSION: u8 = b'D';
+
+///
+/// The length of the header without optional blocks.
+/// 
+const HEADER_LENGTH: usize = 16;
+
+///
+/// KeyBlockHeader holds the attributes of the key protected by the key block.
+/// All fields are ASCII characters as defined by TR-31.
+/// 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyBlockHeader {
+    ///
+    /// The key usage, for example B0 for a base derivation key, K0 for a key 
+    /// encryption key or P0 for a PIN encryption key.
+    /// 
+    pub usage: [u8; 2],
+    ///
+    /// The algorithm of the key, A for AES.
+    /// 
+    pub algorithm: u8,
+    ///
+    /// The mode of use, for example B for encrypt and decrypt, E for encrypt only
+    /// or N for no restrictions.
+    /// 
+    pub mode_of_use: u8,
+    ///
+    /// The key version number, 00 if not used.
+    /// 
+    pub key_version: [u8; 2],
+    ///
+    /// The exportability, E for exportable, N for not exportable or S for 
+    /// sensitive.
+    /// 
+    pub exportability: u8
+}
+
+impl KeyBlockHeader {
+
+    ///
+    /// Creates a header for an AES key without a key version number.
+    /// 
+    /// usage: The key usage.
+    /// mode_of_use: The mode of use.
+    /// exportability: The exportability.
+    /// 
+    /// result: A new KeyBlockHeader.
+    /// 
+    pub fn new(usage: [u8; 2], mode_of_use: u8, exportability: u8) -> Self {
+        Self { usage, algorithm: b'A', mode_of_use, key_version: *b"00", exportability }
+    }
+
+    ///
+    /// Encodes the header for a key block of the length.
+    /// 
+    fn encode(&self, length: usize) -> Result<Vec<u8>, AesError> {
+        let fields = [&self.usage[..], &[self.algorithm, self.mode_of_use], &self.key_version];
+        if !fields.concat().iter().all(u8::is_ascii_alphanumeric) || !b"ENS".contains(&self.exportability) || length > 9999 {
+            return Err(AesError::InvalidKeyBlock);
+        }
+        let mut header = vec![VERSION];
+        header.extend_from_slice(format!("{:04}", length).as_bytes());
+        header.extend_from_slice(&self.usage);
+        header.extend_from_slice(&[self.algorithm, self.mode_of_use]);
+        header.extend_from_slice(&self.key_version);
+        header.push(self.exportability);
+        header.extend_from_slice(b"0000");
+        Ok(header)
+    }
+
+    ///
+    /// Decodes the header, returning it with the length of the key block.
+    /// 
+    fn decode(header: &[u8]) -> Result<(Self, usize), AesError> {
+        if header[0] != VERSION {
+            return Err(AesError::UnsupportedVersion);
+        }
+        let length = std::str::from_utf8(&header[1..5]).ok().filter(|digits| digits.bytes().all(|digit| digit.is_ascii_digit()))
+            .and_then(|digits| digits.parse().ok()).ok_or(AesError::InvalidKeyBlock)?;
+        if &header[12..16] != b"0000" {
+            return Err(AesError::InvalidKeyBlock);
+        }
+        let decoded = Self {
+            usage: [header[5], header[6]],
+            algorithm: header[7],
+            mode_of_use: header[8],
+            key_version: [header[9], header[10]],
+            exportability: header[11]
+        };
+        Ok((decoded, length))
+    }
+
+}
+
+///
+/// KeyBlock exports and imports keys protected by a key block protection key.
+/// 
+pub struct KeyBlock<const KEY_BYTES: usize = 32> {
+    encryption: Aes<KEY_BYTES>,
+    authentication: Cmac<KEY_BYTES>
+}
+
+impl<const KEY_BYTES: usize> KeyBlock<KEY_BYTES> {
+
+    ///
+    /// Creates a new KeyBlock, deriving the encryption and authentication keys.
+    /// 
+    /// key: The key block protection key.
+    /// 
+    /// result: A new KeyBlock.
+    /// 
+    pub fn new(key: &[u8; KEY_BYTES]) -> Self {
+        let encryption: Zeroizing<[u8; KEY_BYTES]> = Zeroizing(derive_key(key, 0).try_into().unwrap());
+        let authentication: Zeroizing<[u8; KEY_BYTES]> = Zeroizing(derive_key(key, 1).try_into().unwrap());
+        Self { encryption: Aes::new(&encryption), authentication: Cmac::new(&authentication) }
+    }
+
+    ///
+    /// Exports a key in a key block.
+    /// 
+    /// header: The attributes of the key.
+    /// key: The key, 1 to 255 bytes.
+    /// 
+    /// result: The key block, AesError::InvalidKeyBlock if a header field is not
+    ///         valid or the key is too long, AesError::InvalidKeyLength if the key
+    ///         is empty or AesError::RandomUnavailable if no padding could be generated.
+    /// 
+    pub fn export(&self, header: &KeyBlockHeader, key: &[u8]) -> Result<Vec<u8>, AesError> {
+        if key.is_empty() {
+            return Err(AesError::InvalidKeyLength { length: 0, min: 1, max: 255, multiple: 1 });
+        }
+        let bits = u16::try_from(key.len() * 8).map_err(|_| AesError::InvalidKeyBlock)?;
+        let mut payload = Zeroizing(bits.to_be_bytes().to_vec());
+        payload.extend_from_slice(key);
+        let mut padding = vec![0; payload.len().next_multiple_of(16) - payload.len()];
+        fill_random(&mut padding)?;
+        payload.extend_from_slice(&padding);
+        let mut block = header.encode(HEADER_LENGTH + 2 * payload.len() + 32)?;
+        let mut state = self.authentication.start();
+        state.update(&block);
+        state.update(&payload);
+        let mac = state.finalize();
+        let encrypted = cbc::encrypt_unpadded(&self.encryption, &mac, &payload)?;
+        block.extend_from_slice(hex::encode_upper(&encrypted).as_bytes());
+        block.extend_from_slice(hex::encode_upper(&mac).as_bytes());
+        Ok(block)
+    }
+
+    ///
+    /// Imports a key from a key block.
+    /// 
+    /// block: The key block.
+    /// 
+    /// result: The header and the key, AesError::UnsupportedVersion if the version
+    ///         is not D, AesError::InvalidKeyBlock if the key block is malformed 
+    ///         or AesError::AuthenticationFailed if the mac does not match.
+    /// 
+    pub fn import(&self, block: &[u8]) -> Result<(KeyBlockHeader, Vec<u8>), AesError> {
+        if block.len() < HEADER_LENGTH + 64 {
+            return Err(AesError::InvalidKeyBlock);
+        }
+        let (header, length) = KeyBlockHeader::decode(&block[..HEADER_LENGTH])?;
+        if length != block.len() || !(block.len() - HEADER_LENGTH).is_multiple_of(32) {
+            return Err(AesError::InvalidKeyBlock);
+        }
+        let encrypted = hex::decode(&block[HEADER_LENGTH..block.len() - 32]).ok_or(AesError::InvalidKeyBlock)?;
+        let mac: [u8; 16] = hex::decode(&block[block.len() - 32..]).ok_or(AesError::InvalidKeyBlock)?.try_into().unwrap();
+        let payload = Zeroizing(cbc::decrypt_unpadded(&self.encryption, &mac, &encrypted)?);
+        let mut state = self.authentication.start();
+        state.update(&block[..HEADER_LENGTH]);
+        state.update(&payload);
+        verify_tag(&mac, &state.finalize())?;
+        let bits = u16::from_be_bytes([payload[0], payload[1]]) as usize;
+        if bits == 0 || !bits.is_multiple_of(8) || bits / 8 > payload.len() - 2 {
+            return Err(AesError::InvalidKeyBlock);
+        }
+        Ok((header, payload[2..2 + bits / 8].to_vec()))
+    }
+
+}
+
+impl<const KEY_BYTES: usize> From<&SecretKey<KEY_BYTES>> for KeyBlock<KEY_BYTES> {
+
+    fn from(key: &SecretKey<KEY_BYTES>) -> Self {
+        KeyBlock::new(key.expose_secret())
+    }
+
+}
+
+///
+/// Derives the encryption (usage 0) or authentication (usage 1) key from the 
+/// key block protection key.
+/// 
+fn derive_key<const KEY_BYTES: usize>(key: &[u8; KEY_BYTES], usage: u8) -> Vec<u8> {
+    let algorithm = match KEY_BYTES {
+        16 => 2,
+        24 => 3,
+        _ => 4
+    };
+    let bits = (KEY_BYTES as u16 * 8).to_be_bytes();
+    let fixed_input = [0, usage, 0, 0, algorithm, bits[0], bits[1]];
+    derive_with_fixed_input(key, 1, &fixed_input, KEY_BYTES).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::test_util::hex;
+
+    #[test]
+    fn test_derive_keys() {
+        let key: [u8; 16] = hex("000102030405060708090a0b0c0d0e0f").try_into().unwrap();
+        let cmac = Cmac::new(&key);
+        assert_eq!(cmac.mac(&hex("0100000000020080")).to_vec(), derive_key(&key, 0));
+        assert_eq!(cmac.mac(&hex("0100010000020080")).to_vec(), derive_key(&key, 1));
+        let key = [7; 32];
+        let cmac = Cmac::new(&key);
+        assert_eq!([cmac.mac(&hex("0100000000040100")), cmac.mac(&hex("0200000000040100"))].concat(), derive_key(&key, 0));
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let kb = KeyBlock::<32>::new(&[1; 32]);
+        let header = KeyBlockHeader::new(*b"P0", b'E', b'E');
+        for length in [16, 24, 32] {
+            let key = vec![length as u8; length];
+            let block = kb.export(&header, &key).unwrap();
+            assert!(block.is_ascii());
+            assert_eq!(format!("D{:04}P0AE00E0000", block.len()).as_bytes(), &block[..16]);
+            assert_eq!(16 + 2 * (2 + length).next_multiple_of(16) + 32, block.len());
+            assert_eq!((header, key), kb.import(&block).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_tampering() {
+        let kb = KeyBlock::<16>::new(&[1; 16]);
+        let block = kb.export(&KeyBlockHeader::new(*b"K0", b'B', b'E'), &[2; 16]).unwrap();
+        let mut modified = block.clone();
+        modified[11] = b'N';
+        assert_eq!(Err(AesError::AuthenticationFailed), kb.import(&modified));
+        let mut modified = block.clone();
+        modified[20] = if modified[20] == b'0' { b'1' } else { b'0' };
+        assert_eq!(Err(AesError::AuthenticationFailed), kb.import(&modified));
+        assert_eq!(Err(AesError::AuthenticationFailed), KeyBlock::<16>::new(&[3; 16]).import(&block));
+    }
+
+    #[test]
+    fn test_invalid_blocks() {
+        let kb = KeyBlock::<16>::new(&[1; 16]);
+        let block = kb.export(&KeyBlockHeader::new(*b"K0", b'B', b'E'), &[2; 16]).unwrap();
+        let mut modified = block.clone();
+        modified[0] = b'B';
+        assert_eq!(Err(AesError::UnsupportedVersion), kb.import(&modified));
+        let mut modified = block.clone();
+        modified[4] = b'1';
+        assert_eq!(Err(AesError::InvalidKeyBlock), kb.import(&modified));
+        let mut modified = block.clone();
+        modified[30] = b'G';
+        assert_eq!(Err(AesError::InvalidKeyBlock), kb.import(&modified));
+        assert_eq!(Err(AesError::InvalidKeyBlock), kb.import(&block[..block.len() - 1]));
+        assert_eq!(Err(AesError::InvalidKeyBlock), kb.export(&KeyBlockHeader::new(*b"K0", b'B', b'X'), &[2; 16]));
+        assert_eq!(Err(AesError::InvalidKeyBlock), kb.export(&KeyBlockHeader::new(*b"K ", b'B', b'E'), &[2; 16]));
+        assert_eq!(Err(AesError::InvalidKeyLength { length: 0, min: 1, max: 255, multiple: 1 }), kb.export(&KeyBlockHeader::new(*b"K0", b'B', b'E'), &[]));
+    }
+
+}
@@ -0,0 +1,422 @@
+use std::fmt;
+use std::vec::Vec;
+
+use crate::tables;
+use crate::zeroize::{Zeroize, Zeroizing};
+use crate::RoundKeys;
+
+///
+/// KeySchedule is a struct containing the roundkeys derived from a key using
+/// the Rijndael key expansion. Description of the algorithm can be found here:
+/// https://en.wikipedia.org/wiki/AES_key_schedule
+///
+/// The struct is generic over the key length in bytes. Only 16, 24 and 32 byte
+/// keys are valid, giving 11, 13 or 15 roundkeys. Using any other key length
+/// fails to compile.
+///
+/// The roundkeys can be used directly with the encrypt and decrypt functions
+/// of AESBlock and AESData.
+///
+pub struct KeySchedule<const KEY_BYTES: usize = 16> {
+    roundkeys: RoundKeys
+}
+
+///
+/// KeySchedule for AES-128.
+///
+pub type KeySchedule128 = KeySchedule<16>;
+///
+/// KeySchedule for AES-192.
+///
+pub type KeySchedule192 = KeySchedule<24>;
+///
+/// KeySchedule for AES-256.
+///
+pub type KeySchedule256 = KeySchedule<32>;
+
+impl<const KEY_BYTES: usize> KeySchedule<KEY_BYTES> {
+
+    ///
+    /// Number of rounds for the key length. Evaluating this for an invalid
+    /// key length is a compile time error.
+    ///
+    pub const ROUNDS: usize = match KEY_BYTES {
+        16 => 10,
+        24 => 12,
+        32 => 14,
+        _ => panic!("Key must be 16, 24 or 32 bytes")
+    };
+
+    ///
+    /// Creates a new KeySchedule from a 16, 24 or 32 byte key.
+    ///
+    /// key: The key used to generate the roundkeys.
+    ///
+    /// result: A KeySchedule containing 11, 13 or 15 roundkeys.
+    ///
+    pub fn new(key: &[u8; KEY_BYTES]) -> KeySchedule<KEY_BYTES> {
+        let mut words = expand_key(key, 4 * (Self::ROUNDS + 1));
+        let roundkeys = RoundKeys::from_valid(words.chunks(4).map(|roundkey| roundkey.as_flattened().try_into().unwrap()).collect());
+        words.zeroize();
+        KeySchedule {
+            roundkeys
+        }
+    }
+
+    ///
+    /// Returns the roundkeys of the schedule.
+    ///
+    /// result: The roundkeys of the schedule.
+    ///
+    pub fn roundkeys(&self) -> &RoundKeys {
+        &self.roundkeys
+    }
+
+}
+
+///
+/// Round constants used when generating the roundkeys. AES uses at most 10 of these,
+/// while Rijndael with 256 bit blocks and 128 bit keys uses 29.
+///
+const R_CON: [u8; 30] = tables::round_constants();
+
+///
+/// Expands the key into the specified number of 4 byte words using the Rijndael
+/// key expansion.
+///
+/// key: A key of 16, 24 or 32 bytes.
+/// total_words: The number of words to generate. This is the block length in words
+///              multiplied with the number of roundkeys.
+///
+/// result: A vector of words of 4 bytes.
+///
+pub(crate) fn expand_key(key: &[u8], total_words: usize) -> Vec<[u8; 4]> {
+    let words_in_key = key.len() / 4;
+    let mut words: Vec<[u8; 4]> = key.chunks(4).map(|word| word.try_into().unwrap()).collect();
+    for idx in words_in_key..total_words {
+        let word = next_word(&words[idx - words_in_key..], idx);
+        words.push(word);
+    }
+    words
+}
+
+///
+/// Generates the next word in the key expansion.
+///
+/// previous: The previous words, as many as there are words in the key.
+/// idx: The index of the word to generate.
+///
+/// result: The new word of 4 bytes.
+///
+fn next_word(previous: &[[u8; 4]], idx: usize) -> [u8; 4] {
+    let words_in_key = previous.len();
+    let temp = Zeroizing(transform_word(&previous[words_in_key - 1], idx, words_in_key));
+    xor_word(&previous[0], &temp)
+}
+
+///
+/// Xors two words.
+///
+/// a: A word of 4 bytes.
+/// b: A word of 4 bytes.
+///
+/// result: The xored word.
+///
+fn xor_word(a: &[u8; 4], b: &[u8; 4]) -> [u8; 4] {
+    [a[0] ^ b[0], a[1] ^ b[1], a[2] ^ b[2], a[3] ^ b[3]]
+}
+
+///
+/// Transforms the word preceding the word at idx before it is xored with the word
+/// one key length earlier.
+///
+/// word: The word preceding the word at idx.
+/// idx: The index of the word being generated.
+/// words_in_key: The number of words in the key.
+///
+/// result: The transformed word of 4 bytes.
+///
+fn transform_word(word: &[u8; 4], idx: usize, words_in_key: usize) -> [u8; 4] {
+    if idx.is_multiple_of(words_in_key) {
+        let mut temp = sub_word(&rotate(word));
+        temp[0] ^= R_CON[idx / words_in_key - 1];
+        temp
+    } else if words_in_key > 6 && idx % words_in_key == 4 {
+        sub_word(word)
+    } else {
+        *word
+    }
+}
+
+///
+/// The largest number of words in a key, for 32 byte keys.
+///
+const MAX_WORDS_IN_KEY: usize = 8;
+
+///
+/// Copies the words of a key or of the end of the key expansion into an array of
+/// the largest key length.
+///
+/// words: The 4, 6 or 8 words to copy.
+///
+/// result: The words followed by zero words.
+///
+fn copy_words(words: &[[u8; 4]]) -> [[u8; 4]; MAX_WORDS_IN_KEY] {
+    let mut copied = [[0; 4]; MAX_WORDS_IN_KEY];
+    copied[..words.len()].copy_from_slice(words);
+    copied
+}
+
+///
+/// RoundKeyGenerator is an iterator generating the AES roundkeys one at a time. Only
+/// the words of the previous key length are kept in memory, instead of all the
+/// roundkeys as in KeySchedule. The words are kept in an array, so generating the
+/// roundkeys does not allocate.
+///
+pub(crate) struct RoundKeyGenerator {
+    previous: [[u8; 4]; MAX_WORDS_IN_KEY],
+    words_in_key: usize,
+    idx: usize,
+    total_words: usize
+}
+
+impl RoundKeyGenerator {
+
+    ///
+    /// Creates a new generator from a 16, 24 or 32 byte key.
+    ///
+    /// key: The key used to generate the roundkeys.
+    ///
+    /// result: A generator yielding 11, 13 or 15 roundkeys.
+    ///
+    pub(crate) fn new(key: &[u8]) -> RoundKeyGenerator {
+        RoundKeyGenerator {
+            previous: copy_words(key.as_chunks::<4>().0),
+            words_in_key: key.len() / 4,
+            idx: 0,
+            total_words: 4 * (key.len() / 4 + 7)
+        }
+    }
+
+}
+
+impl Iterator for RoundKeyGenerator {
+
+    type Item = Zeroizing<[u8; 16]>;
+
+    fn next(&mut self) -> Option<Zeroizing<[u8; 16]>> {
+        if self.idx >= self.total_words {
+            return None;
+        }
+        let previous = &mut self.previous[..self.words_in_key];
+        let mut roundkey = Zeroizing([0; 16]);
+        for (column, idx) in (self.idx..self.idx + 4).enumerate() {
+            if idx < previous.len() {
+                roundkey[column * 4..column * 4 + 4].copy_from_slice(&previous[idx]);
+            } else {
+                let word = next_word(previous, idx);
+                roundkey[column * 4..column * 4 + 4].copy_from_slice(&word);
+                previous.rotate_left(1);
+                *previous.last_mut().unwrap() = word;
+            }
+        }
+        self.idx += 4;
+        Some(roundkey)
+    }
+
+}
+
+///
+/// ReverseRoundKeyGenerator is an iterator generating the AES roundkeys one at a time
+/// starting with the last roundkey, as needed when decrypting. The key expansion is
+/// run backwards from the last words of the expansion, so only as many words as
+/// there are in the key are kept in memory, in an array as in RoundKeyGenerator.
+///
+#[cfg(feature = "decrypt")]
+pub(crate) struct ReverseRoundKeyGenerator {
+    window: [[u8; 4]; MAX_WORDS_IN_KEY],
+    words_in_key: usize,
+    start: usize,
+    roundkey: usize
+}
+
+#[cfg(feature = "decrypt")]
+impl ReverseRoundKeyGenerator {
+
+    ///
+    /// Creates a new generator from the last words of the key expansion.
+    ///
+    /// last_words: The last 4, 6 or 8 words of the key expansion.
+    ///
+    /// result: A generator yielding 11, 13 or 15 roundkeys in reverse order.
+    ///
+    pub(crate) fn new(last_words: &[[u8; 4]]) -> ReverseRoundKeyGenerator {
+        let rounds = last_words.len() + 6;
+        ReverseRoundKeyGenerator {
+            window: copy_words(last_words),
+            words_in_key: last_words.len(),
+            start: 4 * (rounds + 1) - last_words.len(),
+            roundkey: rounds + 1
+        }
+    }
+
+    ///
+    /// Generates the last words of the key expansion used to create the generator.
+    ///
+    /// key: A key of 16, 24 or 32 bytes.
+    ///
+    /// result: The last 4, 6 or 8 words of the key expansion.
+    ///
+    pub(crate) fn last_words(key: &[u8]) -> Vec<[u8; 4]> {
+        let words_in_key = key.len() / 4;
+        let mut words = expand_key(key, 4 * (words_in_key + 7));
+        let last_words = words[words.len() - words_in_key..].to_vec();
+        words.zeroize();
+        last_words
+    }
+
+}
+
+#[cfg(feature = "decrypt")]
+impl Iterator for ReverseRoundKeyGenerator {
+
+    type Item = Zeroizing<[u8; 16]>;
+
+    fn next(&mut self) -> Option<Zeroizing<[u8; 16]>> {
+        if self.roundkey == 0 {
+            return None;
+        }
+        self.roundkey -= 1;
+        let words_in_key = self.words_in_key;
+        let window = &mut self.window[..words_in_key];
+        while self.start > self.roundkey * 4 {
+            let idx = self.start - 1 + words_in_key;
+            let temp = Zeroizing(transform_word(&window[words_in_key - 2], idx, words_in_key));
+            let word = xor_word(&window[words_in_key - 1], &temp);
+            window.rotate_right(1);
+            window[0] = word;
+            self.start -= 1;
+        }
+        let offset = self.roundkey * 4 - self.start;
+        Some(Zeroizing(window[offset..offset + 4].as_flattened().try_into().unwrap()))
+    }
+
+}
+
+impl<const KEY_BYTES: usize> fmt::Debug for KeySchedule<KEY_BYTES> {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct(&format!("KeySchedule<{}>", KEY_BYTES)).field("roundkeys", &self.roundkeys).finish()
+    }
+
+}
+
+impl Drop for RoundKeyGenerator {
+
+    fn drop(&mut self) {
+        self.previous.zeroize();
+    }
+
+}
+
+#[cfg(feature = "decrypt")]
+impl Drop for ReverseRoundKeyGenerator {
+
+    fn drop(&mut self) {
+        self.window.zeroize();
+    }
+
+}
+
+///
+/// Rotates the bytes in the word.
+///
+/// word: A word of 4 bytes to be rotated.
+///
+/// result: A word with the rotated values.
+///
+fn rotate(word: &[u8; 4]) -> [u8; 4] {
+    [word[1], word[2], word[3], word[0]]
+}
+
+///
+/// Substitutes each byte in the data with the corresponding byte in the s_box.
+/// This is the same substitution as in the sub_bytes function, but it is used
+/// for generating the roundkeys.
+///
+/// word: A word of 4 bytes to be exchanged.
+///
+/// result: A word with the substituted values.
+///
+fn sub_word(word: &[u8; 4]) -> [u8; 4] {
+    word.map(|value| tables::S_BOX[value as usize])
+}
+
+#[cfg(all(test, feature = "encrypt", feature = "decrypt"))]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_rotate() {
+        let result = rotate(&[0x09, 0xcf, 0x4f, 0x3c]);
+        assert_eq!([0xcf, 0x4f, 0x3c, 0x09], result);
+    }
+
+    #[test]
+    fn test_sub_word() {
+        let result = sub_word(&[0xcf, 0x4f, 0x3c, 0x09]);
+        assert_eq!([0x8a, 0x84, 0xeb, 0x01], result);
+    }
+
+    #[test]
+    fn test_key_schedule_fips197() {
+        let key: [u8; 16] = [0x2b, 0x7e, 0x15, 0x16, 0x28, 0xae, 0xd2, 0xa6, 0xab, 0xf7, 0x15, 0x88, 0x09, 0xcf, 0x4f, 0x3c];
+        let schedule = KeySchedule::new(&key);
+        assert_eq!(11, schedule.roundkeys().as_slice().len());
+        assert_eq!(key, schedule.roundkeys().as_slice()[0]);
+        assert_eq!([0xa0, 0xfa, 0xfe, 0x17, 0x88, 0x54, 0x2c, 0xb1, 0x23, 0xa3, 0x39, 0x39, 0x2a, 0x6c, 0x76, 0x05], schedule.roundkeys().as_slice()[1]);
+        assert_eq!([0xd0, 0x14, 0xf9, 0xa8, 0xc9, 0xee, 0x25, 0x89, 0xe1, 0x3f, 0x0c, 0xc8, 0xb6, 0x63, 0x0c, 0xa6], schedule.roundkeys().as_slice()[10]);
+    }
+
+    #[test]
+    fn test_key_schedule_192_fips197() {
+        let key: [u8; 24] = [0x8e, 0x73, 0xb0, 0xf7, 0xda, 0x0e, 0x64, 0x52, 0xc8, 0x10, 0xf3, 0x2b, 0x80, 0x90, 0x79, 0xe5, 0x62, 0xf8, 0xea, 0xd2, 0x52, 0x2c, 0x6b, 0x7b];
+        let schedule = KeySchedule192::new(&key);
+        assert_eq!(13, schedule.roundkeys().as_slice().len());
+        assert_eq!(key[..16], schedule.roundkeys().as_slice()[0]);
+        assert_eq!([0xe9, 0x8b, 0xa0, 0x6f, 0x44, 0x8c, 0x77, 0x3c, 0x8e, 0xcc, 0x72, 0x04, 0x01, 0x00, 0x22, 0x02], schedule.roundkeys().as_slice()[12]);
+    }
+
+    #[test]
+    fn test_key_schedule_256_fips197() {
+        let key: [u8; 32] = [0x60, 0x3d, 0xeb, 0x10, 0x15, 0xca, 0x71, 0xbe, 0x2b, 0x73, 0xae, 0xf0, 0x85, 0x7d, 0x77, 0x81,
+                             0x1f, 0x35, 0x2c, 0x07, 0x3b, 0x61, 0x08, 0xd7, 0x2d, 0x98, 0x10, 0xa3, 0x09, 0x14, 0xdf, 0xf4];
+        let schedule = KeySchedule256::new(&key);
+        assert_eq!(15, schedule.roundkeys().as_slice().len());
+        assert_eq!(key[16..], schedule.roundkeys().as_slice()[1]);
+        assert_eq!([0xfe, 0x48, 0x90, 0xd1, 0xe6, 0x18, 0x8d, 0x0b, 0x04, 0x6d, 0xf3, 0x44, 0x70, 0x6c, 0x63, 0x1e], schedule.roundkeys().as_slice()[14]);
+    }
+
+    #[test]
+    fn test_round_key_generator() {
+        let key_128: Vec<u8> = (0..16).collect();
+        let key_192: Vec<u8> = (0..24).collect();
+        let key_256: Vec<u8> = (0..32).collect();
+        assert_eq!(KeySchedule128::new(key_128.as_slice().try_into().unwrap()).roundkeys().as_slice(), RoundKeyGenerator::new(&key_128).map(|roundkey| *roundkey).collect::<Vec<[u8; 16]>>());
+        assert_eq!(KeySchedule192::new(key_192.as_slice().try_into().unwrap()).roundkeys().as_slice(), RoundKeyGenerator::new(&key_192).map(|roundkey| *roundkey).collect::<Vec<[u8; 16]>>());
+        assert_eq!(KeySchedule256::new(key_256.as_slice().try_into().unwrap()).roundkeys().as_slice(), RoundKeyGenerator::new(&key_256).map(|roundkey| *roundkey).collect::<Vec<[u8; 16]>>());
+    }
+
+    #[test]
+    fn test_reverse_round_key_generator() {
+        for key_bytes in [16, 24, 32] {
+            let key: Vec<u8> = (0..key_bytes as u8).collect();
+            let mut expected: Vec<[u8; 16]> = RoundKeyGenerator::new(&key).map(|roundkey| *roundkey).collect();
+            expected.reverse();
+            let result: Vec<[u8; 16]> = ReverseRoundKeyGenerator::new(&ReverseRoundKeyGenerator::last_words(&key)).map(|roundkey| *roundkey).collect();
+            assert_eq!(expected, result);
+        }
+    }
+
+}
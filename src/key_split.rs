@@ -0,0 +1,107 @@
+//!
+//! Splitting a key into XOR shares, for key ceremonies where the key components
+//! are held by different custodians. A key is split into n random shares that 
+//! xor to the key. All n shares are needed to recombine the key, any n - 1 shares 
+//! reveal nothing about it.
+//! 
+//! There is no threshold, losing one share loses the key.
+//! 
+
+use std::vec::Vec;
+
+use crate::zeroize::Zeroize;
+use crate::{AesError, SecretKey};
+
+///
+/// The largest number of shares a key can be split into.
+/// 
+pub const MAX_SHARES: usize = 255;
+
+///
+/// Splits a key into XOR shares. The first n - 1 shares are random and the last
+/// is the key xored with all of them.
+/// 
+/// key: The key to split.
+/// count: The number of shares, 2 to MAX_SHARES.
+/// 
+/// result: The shares, AesError::InvalidShareCount if count is less than 2 or 
+///         more than MAX_SHARES or AesError::RandomUnavailable if no random shares could be generated.
+/// 
+pub fn split<const KEY_BYTES: usize>(key: &SecretKey<KEY_BYTES>, count: usize) -> Result<Vec<SecretKey<KEY_BYTES>>, AesError> {
+    if !(2..=MAX_SHARES).contains(&count) {
+        return Err(AesError::InvalidShareCount);
+    }
+    let mut shares = Vec::with_capacity(count);
+    let mut last = *key.expose_secret();
+    for _ in 1..count {
+        let share = SecretKey::<KEY_BYTES>::generate()?;
+        last.iter_mut().zip(share.expose_secret()).for_each(|(a, b)| *a ^= b);
+        shares.push(share);
+    }
+    shares.push(SecretKey::new(last));
+    last.zeroize();
+    Ok(shares)
+}
+
+///
+/// Recombines a key from all its XOR shares, in any order.
+/// 
+/// shares: All the shares of the key.
+/// 
+/// result: The key or AesError::InvalidShareCount if there are less than 2 shares.
+///         A missing or wrong share gives a wrong key, not an error.
+/// 
+pub fn combine<const KEY_BYTES: usize>(shares: &[SecretKey<KEY_BYTES>]) -> Result<SecretKey<KEY_BYTES>, AesError> {
+    if shares.len() < 2 {
+        return Err(AesError::InvalidShareCount);
+    }
+    let mut key = [0; KEY_BYTES];
+    for share in shares {
+        key.iter_mut().zip(share.expose_secret()).for_each(|(a, b)| *a ^= b);
+    }
+    let combined = SecretKey::new(key);
+    key.zeroize();
+    Ok(combined)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::test_util::hex;
+
+    #[test]
+    fn test_split_combine() {
+        let key = SecretKey::<32>::generate().unwrap();
+        for count in [2, 3, 5] {
+            let mut shares = split(&key, count).unwrap();
+            assert_eq!(count, shares.len());
+            assert!(shares.iter().all(|share| share.expose_secret() != key.expose_secret()));
+            assert_eq!(key.expose_secret(), combine(&shares).unwrap().expose_secret());
+            shares.reverse();
+            assert_eq!(key.expose_secret(), combine(&shares).unwrap().expose_secret());
+            shares[0] = SecretKey::generate().unwrap();
+            assert_ne!(key.expose_secret(), combine(&shares).unwrap().expose_secret());
+        }
+    }
+
+    #[test]
+    fn test_combine_components() {
+        let shares = [
+            SecretKey::<16>::from_slice(&hex("0123456789abcdeffedcba9876543210")).unwrap(),
+            SecretKey::<16>::from_slice(&hex("ffffffffffffffff0000000000000000")).unwrap()
+        ];
+        assert_eq!(&hex("fedcba9876543210fedcba9876543210")[..], combine(&shares).unwrap().expose_secret());
+    }
+
+    #[test]
+    fn test_invalid_count() {
+        let key = SecretKey::<16>::new([1; 16]);
+        assert_eq!(Some(AesError::InvalidShareCount), split(&key, 1).err());
+        assert_eq!(Some(AesError::InvalidShareCount), split(&key, MAX_SHARES + 1).err());
+        assert_eq!(Some(AesError::InvalidShareCount), split(&key, usize::MAX).err());
+        assert_eq!(MAX_SHARES, split(&key, MAX_SHARES).unwrap().len());
+        assert_eq!(Some(AesError::InvalidShareCount), combine(&[key]).err());
+    }
+
+}
@@ -0,0 +1,304 @@
+//!
+//! AES key wrap, as specified in RFC 3394, and key wrap with padding, as 
+//! specified in RFC 5649. Both are also given in NIST SP 800-38F as KW and KWP.
+//! Key wrap encrypts key material under a key encryption key with an integrity 
+//! check, without a nonce.
+//! 
+//! Key wrap needs key data of at least 16 bytes in multiples of 8 bytes. Key wrap
+//! with padding takes key data of any length, such as HMAC keys, and stores the 
+//! length in the alternative initial value. The wrapped key is 8 bytes longer 
+//! than the (padded) key data.
+//! 
+
+use std::vec::Vec;
+
+use crate::{verify_tag, Aes, AesError, SecretKey};
+
+///
+/// The default initial value of RFC 3394.
+/// 
+const DEFAULT_IV: [u8; 8] = [0xa6; 8];
+
+///
+/// The constant first half of the alternative initial value of RFC 5649.
+/// 
+const PADDED_IV_PREFIX: [u8; 4] = [0xa6, 0x59, 0x59, 0xa6];
+
+///
+/// KeyWrap wraps and unwraps keys with a key encryption key.
+/// 
+pub struct KeyWrap<const KEY_BYTES: usize = 16> {
+    cipher: Aes<KEY_BYTES>
+}
+
+impl<const KEY_BYTES: usize> KeyWrap<KEY_BYTES> {
+
+    ///
+    /// Creates a new KeyWrap.
+    /// 
+    /// key: The key encryption key.
+    /// 
+    /// result: A new KeyWrap.
+    /// 
+    pub fn new(key: &[u8; KEY_BYTES]) -> Self {
+        Self { cipher: Aes::new(key) }
+    }
+
+    ///
+    /// Wraps the key data with RFC 3394 key wrap.
+    /// 
+    /// key_data: The key data, at least 16 bytes in multiples of 8 bytes.
+    /// 
+    /// result: The wrapped key, 8 bytes longer than the key data, or 
+    ///         AesError::InvalidDataLength if the key data has an invalid length.
+    /// 
+    pub fn wrap(&self, key_data: &[u8]) -> Result<Vec<u8>, AesError> {
+        if key_data.len() < 16 || !key_data.len().is_multiple_of(8) {
+            return Err(AesError::InvalidDataLength { length: key_data.len(), min: 16, multiple: 8 });
+        }
+        wrap_blocks(&|block| Ok(self.encrypt(block)), DEFAULT_IV, key_data)
+    }
+
+    ///
+    /// Unwraps a key wrapped with RFC 3394 key wrap.
+    /// 
+    /// wrapped: The wrapped key.
+    /// 
+    /// result: The key data, AesError::InvalidDataLength if the wrapped key has an 
+    ///         invalid length or AesError::AuthenticationFailed if the integrity 
+    ///         check fails.
+    /// 
+    pub fn unwrap(&self, wrapped: &[u8]) -> Result<Vec<u8>, AesError> {
+        if wrapped.len() < 24 || !wrapped.len().is_multiple_of(8) {
+            return Err(AesError::InvalidDataLength { length: wrapped.len(), min: 24, multiple: 8 });
+        }
+        let (iv, key_data) = unwrap_blocks(&|block| Ok(self.decrypt(block)), wrapped)?;
+        verify_tag(&DEFAULT_IV, &iv)?;
+        Ok(key_data)
+    }
+
+    ///
+    /// Wraps the key data with RFC 5649 key wrap with padding.
+    /// 
+    /// key_data: The key data, 1 to 2^32 - 1 bytes.
+    /// 
+    /// result: The wrapped key, the key data padded to a multiple of 8 bytes and 
+    ///         8 bytes longer, AesError::InvalidDataLength if the key data is empty
+    ///         or AesError::DataTooLong if it is 2^32 bytes or longer.
+    /// 
+    pub fn wrap_with_padding(&self, key_data: &[u8]) -> Result<Vec<u8>, AesError> {
+        wrap_with_padding_using(|block| Ok(self.encrypt(block)), key_data)
+    }
+
+    ///
+    /// Unwraps a key wrapped with RFC 5649 key wrap with padding.
+    /// 
+    /// wrapped: The wrapped key.
+    /// 
+    /// result: The key data, AesError::InvalidDataLength if the wrapped key has an 
+    ///         invalid length or AesError::AuthenticationFailed if the integrity 
+    ///         check, the length or the padding is not valid.
+    /// 
+    pub fn unwrap_with_padding(&self, wrapped: &[u8]) -> Result<Vec<u8>, AesError> {
+        unwrap_with_padding_using(|block| Ok(self.decrypt(block)), wrapped)
+    }
+
+    ///
+    /// Encrypts a block with the key encryption key.
+    /// 
+    fn encrypt(&self, block: &[u8; 16]) -> [u8; 16] {
+        self.cipher.encrypt_bytes(block).try_into().unwrap()
+    }
+
+    ///
+    /// Decrypts a block with the key encryption key.
+    /// 
+    fn decrypt(&self, block: &[u8; 16]) -> [u8; 16] {
+        self.cipher.decrypt_bytes(block).try_into().unwrap()
+    }
+
+}
+
+impl<const KEY_BYTES: usize> From<&SecretKey<KEY_BYTES>> for KeyWrap<KEY_BYTES> {
+
+    fn from(key: &SecretKey<KEY_BYTES>) -> Self {
+        KeyWrap::new(key.expose_secret())
+    }
+
+}
+
+///
+/// RFC 5649 key wrap with padding over any block encryption, such as a key held 
+/// by a KeyBackend.
+/// 
+/// encrypt: Encrypts a 16 byte block with the key encryption key.
+/// key_data: The key data, 1 to 2^32 - 1 bytes.
+/// 
+/// result: The wrapped key, AesError::InvalidDataLength if the key data is 
+///         empty, AesError::DataTooLong if it is 2^32 bytes or longer or the error
+///         of encrypt.
+/// 
+pub(crate) fn wrap_with_padding_using<F: Fn(&[u8; 16]) -> Result<[u8; 16], AesError>>(encrypt: F, key_data: &[u8]) -> Result<Vec<u8>, AesError> {
+    let length = u32::try_from(key_data.len()).map_err(|_| AesError::DataTooLong)?;
+    if length == 0 {
+        return Err(AesError::InvalidDataLength { length: 0, min: 1, multiple: 1 });
+    }
+    let mut iv = [0; 8];
+    iv[..4].copy_from_slice(&PADDED_IV_PREFIX);
+    iv[4..].copy_from_slice(&length.to_be_bytes());
+    let mut padded = key_data.to_vec();
+    padded.resize(key_data.len().next_multiple_of(8), 0);
+    if padded.len() == 8 {
+        let mut block = [0; 16];
+        block[..8].copy_from_slice(&iv);
+        block[8..].copy_from_slice(&padded);
+        return Ok(encrypt(&block)?.to_vec());
+    }
+    wrap_blocks(&encrypt, iv, &padded)
+}
+
+///
+/// RFC 5649 key unwrap with padding over any block decryption.
+/// 
+/// decrypt: Decrypts a 16 byte block with the key encryption key.
+/// wrapped: The wrapped key.
+/// 
+/// result: The key data, AesError::InvalidDataLength if the wrapped key has an 
+///         invalid length, AesError::AuthenticationFailed if the integrity check
+///         fails or the error of decrypt.
+/// 
+pub(crate) fn unwrap_with_padding_using<F: Fn(&[u8; 16]) -> Result<[u8; 16], AesError>>(decrypt: F, wrapped: &[u8]) -> Result<Vec<u8>, AesError> {
+    if wrapped.len() < 16 || !wrapped.len().is_multiple_of(8) {
+        return Err(AesError::InvalidDataLength { length: wrapped.len(), min: 16, multiple: 8 });
+    }
+    let (iv, mut key_data) = if wrapped.len() == 16 {
+        let block = decrypt(wrapped.try_into().unwrap())?;
+        (block[..8].try_into().unwrap(), block[8..].to_vec())
+    } else {
+        unwrap_blocks(&decrypt, wrapped)?
+    };
+    verify_tag(&PADDED_IV_PREFIX, &iv[..4])?;
+    let length = u32::from_be_bytes(iv[4..].try_into().unwrap()) as usize;
+    if length > key_data.len() || length + 8 <= key_data.len() {
+        return Err(AesError::AuthenticationFailed);
+    }
+    verify_tag(&[0; 7][..key_data.len() - length], &key_data[length..])?;
+    key_data.truncate(length);
+    Ok(key_data)
+}
+
+///
+/// The wrapping function W, six rounds over the 8 byte blocks of the data.
+/// 
+fn wrap_blocks<F: Fn(&[u8; 16]) -> Result<[u8; 16], AesError>>(encrypt: &F, iv: [u8; 8], data: &[u8]) -> Result<Vec<u8>, AesError> {
+    let mut a = iv;
+    let mut blocks: Vec<[u8; 8]> = data.chunks(8).map(|block| block.try_into().unwrap()).collect();
+    let count = blocks.len() as u64;
+    for round in 0..6 {
+        for (index, block) in blocks.iter_mut().enumerate() {
+            let mut input = [0; 16];
+            input[..8].copy_from_slice(&a);
+            input[8..].copy_from_slice(block);
+            let output = encrypt(&input)?;
+            let step = count * round + index as u64 + 1;
+            a = (u64::from_be_bytes(output[..8].try_into().unwrap()) ^ step).to_be_bytes();
+            block.copy_from_slice(&output[8..]);
+        }
+    }
+    let mut wrapped = a.to_vec();
+    blocks.iter().for_each(|block| wrapped.extend_from_slice(block));
+    Ok(wrapped)
+}
+
+///
+/// The unwrapping function W^-1, returning the initial value and the data.
+/// 
+fn unwrap_blocks<F: Fn(&[u8; 16]) -> Result<[u8; 16], AesError>>(decrypt: &F, wrapped: &[u8]) -> Result<([u8; 8], Vec<u8>), AesError> {
+    let mut a: [u8; 8] = wrapped[..8].try_into().unwrap();
+    let mut blocks: Vec<[u8; 8]> = wrapped[8..].chunks(8).map(|block| block.try_into().unwrap()).collect();
+    let count = blocks.len() as u64;
+    for round in (0..6).rev() {
+        for (index, block) in blocks.iter_mut().enumerate().rev() {
+            let step = count * round + index as u64 + 1;
+            let mut input = [0; 16];
+            input[..8].copy_from_slice(&(u64::from_be_bytes(a) ^ step).to_be_bytes());
+            input[8..].copy_from_slice(block);
+            let output = decrypt(&input)?;
+            a.copy_from_slice(&output[..8]);
+            block.copy_from_slice(&output[8..]);
+        }
+    }
+    Ok((a, blocks.concat()))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::test_util::hex;
+
+    #[test]
+    fn test_rfc3394_vectors() {
+        let kw = KeyWrap::<16>::new(&hex("000102030405060708090a0b0c0d0e0f").try_into().unwrap());
+        let key_data = hex("00112233445566778899aabbccddeeff");
+        let wrapped = hex("1fa68b0a8112b447aef34bd8fb5a7b829d3e862371d2cfe5");
+        assert_eq!(wrapped, kw.wrap(&key_data).unwrap());
+        assert_eq!(key_data, kw.unwrap(&wrapped).unwrap());
+        let kw = KeyWrap::<32>::new(&hex("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f").try_into().unwrap());
+        let key_data = hex("00112233445566778899aabbccddeeff000102030405060708090a0b0c0d0e0f");
+        let wrapped = hex("28c9f404c4b810f4cbccb35cfb87f8263f5786e2d80ed326cbc7f0e71a99f43bfb988b9b7a02dd21");
+        assert_eq!(wrapped, kw.wrap(&key_data).unwrap());
+        assert_eq!(key_data, kw.unwrap(&wrapped).unwrap());
+    }
+
+    #[test]
+    fn test_rfc5649_vectors() {
+        let kw = KeyWrap::<24>::new(&hex("5840df6e29b02af1ab493b705bf16ea1ae8338f4dcc176a8").try_into().unwrap());
+        let key_data = hex("c37b7e6492584340bed12207808941155068f738");
+        let wrapped = hex("138bdeaa9b8fa7fc61f97742e72248ee5ae6ae5360d1ae6a5f54f373fa543b6a");
+        assert_eq!(wrapped, kw.wrap_with_padding(&key_data).unwrap());
+        assert_eq!(key_data, kw.unwrap_with_padding(&wrapped).unwrap());
+        let key_data = hex("466f7250617369");
+        let wrapped = hex("afbeb0f07dfbf5419200f2ccb50bb24f");
+        assert_eq!(wrapped, kw.wrap_with_padding(&key_data).unwrap());
+        assert_eq!(key_data, kw.unwrap_with_padding(&wrapped).unwrap());
+    }
+
+    #[test]
+    fn test_padding_roundtrip() {
+        let kw = KeyWrap::<16>::new(&[7; 16]);
+        for length in [1, 8, 9, 16, 17, 64, 65] {
+            let key_data = vec![length as u8; length];
+            let wrapped = kw.wrap_with_padding(&key_data).unwrap();
+            assert_eq!(length.next_multiple_of(8) + 8, wrapped.len());
+            assert_eq!(key_data, kw.unwrap_with_padding(&wrapped).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_integrity() {
+        let kw = KeyWrap::<16>::new(&[7; 16]);
+        let mut wrapped = kw.wrap(&[1; 32]).unwrap();
+        wrapped[10] ^= 1;
+        assert_eq!(Err(AesError::AuthenticationFailed), kw.unwrap(&wrapped));
+        let wrapped = kw.wrap(&[1; 32]).unwrap();
+        assert_eq!(Err(AesError::AuthenticationFailed), kw.unwrap_with_padding(&wrapped));
+        let mut wrapped = kw.wrap_with_padding(&[1; 5]).unwrap();
+        wrapped[15] ^= 1;
+        assert_eq!(Err(AesError::AuthenticationFailed), kw.unwrap_with_padding(&wrapped));
+        let wrapped = kw.wrap_with_padding(&[1; 20]).unwrap();
+        assert_eq!(Err(AesError::AuthenticationFailed), KeyWrap::<16>::new(&[8; 16]).unwrap_with_padding(&wrapped));
+    }
+
+    #[test]
+    fn test_invalid_lengths() {
+        let kw = KeyWrap::<16>::new(&[7; 16]);
+        assert_eq!(Err(AesError::InvalidDataLength { length: 8, min: 16, multiple: 8 }), kw.wrap(&[0; 8]));
+        assert_eq!(Err(AesError::InvalidDataLength { length: 20, min: 16, multiple: 8 }), kw.wrap(&[0; 20]));
+        assert_eq!(Err(AesError::InvalidDataLength { length: 16, min: 24, multiple: 8 }), kw.unwrap(&[0; 16]));
+        assert_eq!(Err(AesError::InvalidDataLength { length: 0, min: 1, multiple: 1 }), kw.wrap_with_padding(&[]));
+        assert_eq!(Err(AesError::InvalidDataLength { length: 8, min: 16, multiple: 8 }), kw.unwrap_with_padding(&[0; 8]));
+        assert_eq!(Err(AesError::InvalidDataLength { length: 20, min: 16, multiple: 8 }), kw.unwrap_with_padding(&[0; 20]));
+    }
+
+}
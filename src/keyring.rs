@@ -0,0 +1,449 @@
+//!
+//! A password protected keyring holding named AES keys. Every key has a random 
+//! 16 byte key id and the time it was added. The keyring is serialized and 
+//! encrypted with the password based encryption of the password module, so the 
+//! file is self-describing and authenticated.
+//! 
+//! The serialized keyring before encryption is:
+//! 
+//! version (1 byte) || number of keys (4 bytes) || keys
+//! 
+//! where every key is:
+//! 
+//! key id (16 bytes) || created (8 bytes) || name length (2 bytes) || name || key length (1 byte) || key
+//! 
+//! All numbers are big endian and created is in seconds since the unix epoch.
+//! 
+//! A keyring is saved to a new file in the same directory, readable only by the 
+//! owner on unix, which then replaces the old file. A crash while saving leaves
+//! the old keyring in place.
+//! 
+
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::string::String;
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::vec::Vec;
+
+use crate::password::{decrypt_with_password, encrypt_with_password_kdf, Kdf};
+use crate::random::fill_random;
+use crate::secret::Redacted;
+use crate::zeroize::Zeroizing;
+use crate::{hex, AesError};
+
+///
+/// The version of the serialized keyring.
+/// 
+pub const VERSION: u8 = 1;
+
+///
+/// KeyEntry is a named key of the keyring. The key itself is redacted from the 
+/// Debug output and wiped when the entry is dropped.
+/// 
+#[derive(Clone)]
+pub struct KeyEntry {
+    name: String,
+    id: [u8; 16],
+    created: u64,
+    key: Zeroizing<Vec<u8>>
+}
+
+impl KeyEntry {
+
+    ///
+    /// The name of the key.
+    /// 
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    ///
+    /// The random id of the key.
+    /// 
+    pub fn id(&self) -> &[u8; 16] {
+        &self.id
+    }
+
+    ///
+    /// The time the key was added, in seconds since the unix epoch.
+    /// 
+    pub fn created(&self) -> u64 {
+        self.created
+    }
+
+    ///
+    /// Returns the bytes of the key.
+    /// 
+    /// result: The 16, 24 or 32 byte key.
+    /// 
+    pub fn expose_secret(&self) -> &[u8] {
+        &self.key
+    }
+
+}
+
+impl fmt::Debug for KeyEntry {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeyEntry").field("name", &self.name).field("id", &self.id).field("created", &self.created).field("key", &Redacted(self.key.len())).finish()
+    }
+
+}
+
+///
+/// Keyring is a set of named keys that can be stored encrypted with a password.
+/// 
+#[derive(Debug, Clone, Default)]
+pub struct Keyring {
+    entries: Vec<KeyEntry>
+}
+
+impl Keyring {
+
+    ///
+    /// Creates a new empty Keyring.
+    /// 
+    /// result: A Keyring without keys.
+    /// 
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///
+    /// Adds a key with a new random key id.
+    /// 
+    /// name: The unique name of the key, at most 65535 bytes.
+    /// key: The 16, 24 or 32 byte key.
+    /// 
+    /// result: The key id, AesError::DuplicateKey if a key with the name exists,
+    ///         AesError::InvalidKeyLength if the key has an invalid length, 
+    ///         AesError::DataTooLong if the name is too long or 
+    ///         AesError::RandomUnavailable if no key id could be generated.
+    /// 
+    pub fn add(&mut self, name: &str, key: &[u8]) -> Result<[u8; 16], AesError> {
+        if name.len() > u16::MAX as usize {
+            return Err(AesError::DataTooLong);
+        }
+        let mut id = [0; 16];
+        fill_random(&mut id)?;
+        let created = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |duration| duration.as_secs());
+        self.insert(KeyEntry { name: name.into(), id, created, key: Zeroizing(key.to_vec()) })?;
+        Ok(id)
+    }
+
+    ///
+    /// Adds an entry after checking the key length and that the name is unique.
+    /// 
+    /// result: Ok, AesError::InvalidKeyLength if the key has an invalid length or
+    ///         AesError::DuplicateKey if a key with the name exists.
+    /// 
+    fn insert(&mut self, entry: KeyEntry) -> Result<(), AesError> {
+        if ![16, 24, 32].contains(&entry.key.len()) {
+            return Err(AesError::InvalidKeyLength { length: entry.key.len(), min: 16, max: 32, multiple: 8 });
+        }
+        if self.get(&entry.name).is_some() {
+            return Err(AesError::DuplicateKey);
+        }
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    ///
+    /// Finds a key by name.
+    /// 
+    /// name: The name of the key.
+    /// 
+    /// result: The key or None if there is no key with the name.
+    /// 
+    pub fn get(&self, name: &str) -> Option<&KeyEntry> {
+        self.entries.iter().find(|entry| entry.name == name)
+    }
+
+    ///
+    /// Finds a key by key id.
+    /// 
+    /// id: The key id.
+    /// 
+    /// result: The key or None if there is no key with the id.
+    /// 
+    pub fn get_by_id(&self, id: &[u8; 16]) -> Option<&KeyEntry> {
+        self.entries.iter().find(|entry| &entry.id == id)
+    }
+
+    ///
+    /// Lists the keys in the order they were added.
+    /// 
+    /// result: An iterator over the keys.
+    /// 
+    pub fn list(&self) -> impl Iterator<Item = &KeyEntry> {
+        self.entries.iter()
+    }
+
+    ///
+    /// Deletes a key by name.
+    /// 
+    /// name: The name of the key.
+    /// 
+    /// result: Ok or AesError::KeyNotFound if there is no key with the name.
+    /// 
+    pub fn delete(&mut self, name: &str) -> Result<(), AesError> {
+        let index = self.entries.iter().position(|entry| entry.name == name).ok_or(AesError::KeyNotFound)?;
+        self.entries.remove(index);
+        Ok(())
+    }
+
+    ///
+    /// Serializes and encrypts the keyring with a key derived from the password,
+    /// using the default kdf.
+    /// 
+    /// password: The password.
+    /// 
+    /// result: The encrypted keyring or AesError::RandomUnavailable if no salt and
+    ///         nonce could be generated.
+    /// 
+    pub fn to_bytes(&self, password: &[u8]) -> Result<Vec<u8>, AesError> {
+        self.to_bytes_with_kdf(password, &Kdf::default())
+    }
+
+    ///
+    /// Serializes and encrypts the keyring with a key derived from the password,
+    /// using the given kdf.
+    /// 
+    /// password: The password.
+    /// kdf: The kdf and its parameters.
+    /// 
+    /// result: The encrypted keyring or an error of encrypt_with_password_kdf.
+    /// 
+    pub fn to_bytes_with_kdf(&self, password: &[u8], kdf: &Kdf) -> Result<Vec<u8>, AesError> {
+        let mut plaintext = Zeroizing(vec![VERSION]);
+        plaintext.extend_from_slice(&(self.entries.len() as u32).to_be_bytes());
+        for entry in &self.entries {
+            plaintext.extend_from_slice(&entry.id);
+            plaintext.extend_from_slice(&entry.created.to_be_bytes());
+            plaintext.extend_from_slice(&(entry.name.len() as u16).to_be_bytes());
+            plaintext.extend_from_slice(entry.name.as_bytes());
+            plaintext.push(entry.key.len() as u8);
+            plaintext.extend_from_slice(&entry.key);
+        }
+        encrypt_with_password_kdf(password, &plaintext, kdf)
+    }
+
+    ///
+    /// Decrypts and parses a keyring encrypted by to_bytes.
+    /// 
+    /// password: The password.
+    /// data: The encrypted keyring.
+    /// 
+    /// result: The keyring, an error of decrypt_with_password if the password is
+    ///         wrong or the data was modified, AesError::UnsupportedVersion if the
+    ///         version is unknown or a name is not UTF-8, 
+    ///         AesError::InvalidDataLength if the keyring is truncated, 
+    ///         AesError::DataTooLong if there are bytes after the entries, 
+    ///         AesError::InvalidKeyLength if a key has an invalid length or 
+    ///         AesError::DuplicateKey if two keys have the same name.
+    /// 
+    pub fn from_bytes(password: &[u8], data: &[u8]) -> Result<Self, AesError> {
+        let plaintext = Zeroizing(decrypt_with_password(password, data)?);
+        let mut reader = Reader { data: &plaintext, length: plaintext.len() };
+        if reader.take(1)?[0] != VERSION {
+            return Err(AesError::UnsupportedVersion);
+        }
+        let count = u32::from_be_bytes(reader.take(4)?.try_into().unwrap());
+        let mut keyring = Self::new();
+        for _ in 0..count {
+            let id = reader.take(16)?.try_into().unwrap();
+            let created = u64::from_be_bytes(reader.take(8)?.try_into().unwrap());
+            let name_length = u16::from_be_bytes(reader.take(2)?.try_into().unwrap()) as usize;
+            let name = String::from_utf8(reader.take(name_length)?.to_vec()).map_err(|_| AesError::UnsupportedVersion)?;
+            let key_length = reader.take(1)?[0] as usize;
+            let key = Zeroizing(reader.take(key_length)?.to_vec());
+            keyring.insert(KeyEntry { name, id, created, key })?;
+        }
+        if !reader.data.is_empty() {
+            return Err(AesError::DataTooLong);
+        }
+        Ok(keyring)
+    }
+
+    ///
+    /// Encrypts the keyring with the password and writes it to the file. The 
+    /// keyring is written to a temporary file in the same directory, which is 
+    /// renamed over the file once it is complete.
+    /// 
+    /// path: The path of the file.
+    /// password: The password.
+    /// 
+    /// result: Ok, AesError::Io with the kind of the io error if the file could 
+    ///         not be written, AesError::RandomUnavailable if no temporary name 
+    ///         could be generated or an error of to_bytes.
+    /// 
+    pub fn save<P: AsRef<Path>>(&self, path: P, password: &[u8]) -> Result<(), AesError> {
+        let path = path.as_ref();
+        let data = self.to_bytes(password)?;
+        let mut suffix = [0; 8];
+        fill_random(&mut suffix)?;
+        let mut name = path.file_name().ok_or(AesError::Io { kind: std::io::ErrorKind::InvalidInput })?.to_os_string();
+        name.push(format!(".{}.tmp", hex::encode(&suffix)));
+        let temporary = path.with_file_name(name);
+        let result = create_private(&temporary).and_then(|mut file| {
+            file.write_all(&data)?;
+            file.sync_all()
+        }).and_then(|_| fs::rename(&temporary, path));
+        if result.is_err() {
+            let _ = fs::remove_file(&temporary);
+        }
+        Ok(result?)
+    }
+
+    ///
+    /// Reads the file and decrypts the keyring with the password.
+    /// 
+    /// path: The path of the file.
+    /// password: The password.
+    /// 
+    /// result: The keyring, AesError::Io with the kind of the io error if the file
+    ///         could not be read or an error of from_bytes.
+    /// 
+    pub fn open<P: AsRef<Path>>(path: P, password: &[u8]) -> Result<Self, AesError> {
+        Self::from_bytes(password, &fs::read(path)?)
+    }
+
+}
+
+///
+/// Creates a new file that only the owner can read and write on unix. Fails if
+/// the file exists.
+/// 
+fn create_private(path: &Path) -> std::io::Result<File> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path)
+}
+
+///
+/// Reads the fields of a serialized keyring.
+/// 
+struct Reader<'a> {
+    data: &'a [u8],
+    length: usize
+}
+
+impl<'a> Reader<'a> {
+
+    ///
+    /// Takes the next length bytes, or fails with AesError::InvalidDataLength with
+    /// the end of the field as the minimum.
+    /// 
+    fn take(&mut self, length: usize) -> Result<&'a [u8], AesError> {
+        if self.data.len() < length {
+            return Err(AesError::InvalidDataLength { length: self.length, min: self.length - self.data.len() + length, multiple: 1 });
+        }
+        let (field, rest) = self.data.split_at(length);
+        self.data = rest;
+        Ok(field)
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::scrypt::ScryptParams;
+
+    fn kdf() -> Kdf {
+        Kdf::Scrypt(ScryptParams::new(4, 1, 1).unwrap())
+    }
+
+    #[test]
+    fn test_add_get_list_delete() {
+        let mut keyring = Keyring::new();
+        let id = keyring.add("data", &[1; 32]).unwrap();
+        keyring.add("mac", &[2; 16]).unwrap();
+        assert_eq!(Err(AesError::DuplicateKey), keyring.add("data", &[3; 16]));
+        assert_eq!(Err(AesError::InvalidKeyLength { length: 15, min: 16, max: 32, multiple: 8 }), keyring.add("short", &[3; 15]));
+        assert_eq!(&[1; 32], keyring.get("data").unwrap().expose_secret());
+        assert_eq!("data", keyring.get_by_id(&id).unwrap().name());
+        assert!(keyring.get("data").unwrap().created() > 0);
+        assert_eq!(vec!["data", "mac"], keyring.list().map(KeyEntry::name).collect::<Vec<_>>());
+        keyring.delete("data").unwrap();
+        assert_eq!(Err(AesError::KeyNotFound), keyring.delete("data"));
+        assert!(keyring.get_by_id(&id).is_none());
+        assert_eq!(1, keyring.list().count());
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let mut keyring = Keyring::new();
+        keyring.add("data", &[1; 32]).unwrap();
+        keyring.add("nøkkel", &[2; 24]).unwrap();
+        let encrypted = keyring.to_bytes_with_kdf(b"secret", &kdf()).unwrap();
+        let decrypted = Keyring::from_bytes(b"secret", &encrypted).unwrap();
+        for (expected, entry) in keyring.list().zip(decrypted.list()) {
+            assert_eq!((expected.name(), expected.id(), expected.created()), (entry.name(), entry.id(), entry.created()));
+            assert_eq!(expected.expose_secret(), entry.expose_secret());
+        }
+        assert_eq!(2, decrypted.list().count());
+        assert_eq!(Some(AesError::AuthenticationFailed), Keyring::from_bytes(b"wrong", &encrypted).err());
+    }
+
+    #[test]
+    fn test_file() {
+        let path = std::env::temp_dir().join(format!("aes-keyring-{}.bin", std::process::id()));
+        let mut keyring = Keyring::new();
+        keyring.add("data", &[1; 16]).unwrap();
+        std::fs::write(&path, keyring.to_bytes_with_kdf(b"secret", &kdf()).unwrap()).unwrap();
+        let opened = Keyring::open(&path, b"secret").unwrap();
+        assert_eq!(&[1; 16], opened.get("data").unwrap().expose_secret());
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(Some(AesError::Io { kind: std::io::ErrorKind::NotFound }), Keyring::open(&path, b"secret").err());
+    }
+
+    #[test]
+    fn test_save() {
+        let directory = std::env::temp_dir().join(format!("aes-keyring-save-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("keyring.bin");
+        fs::write(&path, b"old").unwrap();
+        let mut keyring = Keyring::new();
+        keyring.add("data", &[1; 16]).unwrap();
+        keyring.save(&path, b"secret").unwrap();
+        assert_eq!(&[1; 16], Keyring::open(&path, b"secret").unwrap().get("data").unwrap().expose_secret());
+        #[cfg(unix)]
+        assert_eq!(0o600, std::os::unix::fs::PermissionsExt::mode(&fs::metadata(&path).unwrap().permissions()) & 0o777);
+        assert_eq!(1, fs::read_dir(&directory).unwrap().count());
+        assert_eq!(Some(AesError::Io { kind: std::io::ErrorKind::NotFound }), keyring.save(directory.join("missing").join("keyring.bin"), b"secret").err());
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_from_bytes_rejects_invalid_entries() {
+        let entry = |name: &str, length: usize| KeyEntry { name: name.into(), id: [0; 16], created: 0, key: Zeroizing(vec![1; length]) };
+        let keyring = Keyring { entries: vec![entry("data", 15)] };
+        let encrypted = keyring.to_bytes_with_kdf(b"secret", &kdf()).unwrap();
+        assert_eq!(Some(AesError::InvalidKeyLength { length: 15, min: 16, max: 32, multiple: 8 }), Keyring::from_bytes(b"secret", &encrypted).err());
+        let keyring = Keyring { entries: vec![entry("data", 16), entry("data", 32)] };
+        let encrypted = keyring.to_bytes_with_kdf(b"secret", &kdf()).unwrap();
+        assert_eq!(Some(AesError::DuplicateKey), Keyring::from_bytes(b"secret", &encrypted).err());
+        let encrypted = encrypt_with_password_kdf(b"secret", &[VERSION, 0, 0, 0, 1, 0], &kdf()).unwrap();
+        assert_eq!(Some(AesError::InvalidDataLength { length: 6, min: 21, multiple: 1 }), Keyring::from_bytes(b"secret", &encrypted).err());
+        let encrypted = encrypt_with_password_kdf(b"secret", &[VERSION, 0, 0, 0, 0, 0], &kdf()).unwrap();
+        assert_eq!(Some(AesError::DataTooLong), Keyring::from_bytes(b"secret", &encrypted).err());
+    }
+
+    #[test]
+    fn test_debug_redacted() {
+        let mut keyring = Keyring::new();
+        let id = keyring.add("data", &[0xab; 16]).unwrap();
+        let debug = format!("{:?}", keyring);
+        assert!(debug.contains("key: [REDACTED; 16]"));
+        let debug = debug.replace(&format!("{:?}", id), "");
+        assert!(!debug.contains("171, 171"));
+        assert!(!debug.contains("abab"));
+    }
+
+}
@@ -1,5 +1,152 @@
+#![cfg_attr(feature = "portable-simd", feature(portable_simd))]
+
+use std::fmt;
+use std::ops::Deref;
+use std::string::String;
 use std::vec::Vec;
 
+use crate::secret::Redacted;
+use crate::zeroize::{Zeroize, Zeroizing};
+
+#[cfg(not(any(feature = "encrypt", feature = "decrypt")))]
+compile_error!("At least one of the features encrypt and decrypt must be enabled");
+
+#[cfg(feature = "encrypt")]
+mod aead;
+#[cfg(feature = "argon2")]
+pub mod argon2;
+#[cfg(target_arch = "aarch64")]
+mod armv8;
+#[cfg(all(feature = "encrypt", feature = "decrypt"))]
+mod backend;
+#[cfg(feature = "base64")]
+mod base64;
+pub mod benchmark;
+mod bitslice;
+#[cfg(feature = "argon2")]
+mod blake2b;
+mod block_iter;
+mod block_mode;
+#[cfg(all(feature = "encrypt", feature = "decrypt"))]
+pub mod builder;
+pub mod cbc;
+#[cfg(feature = "encrypt")]
+pub mod cbc_mac;
+#[cfg(feature = "encrypt")]
+pub mod ccm;
+#[cfg(feature = "encrypt")]
+pub mod cfb;
+mod cipher;
+#[cfg(all(feature = "encrypt", any(target_arch = "x86_64", target_arch = "aarch64")))]
+mod clmul;
+#[cfg(feature = "encrypt")]
+pub mod cmac;
+mod columns;
+#[cfg(feature = "encrypt")]
+pub mod ctr;
+#[cfg(feature = "encrypt")]
+pub mod ctr_drbg;
+#[cfg(feature = "encrypt")]
+pub mod eax;
+pub mod ecb;
+#[cfg(all(feature = "encrypt", feature = "decrypt"))]
+pub mod envelope;
+mod error;
+#[cfg(all(feature = "encrypt", feature = "decrypt"))]
+pub mod essiv;
+#[cfg(all(feature = "encrypt", feature = "decrypt"))]
+pub mod etm;
+#[cfg(feature = "encrypt")]
+pub mod gcm;
+#[cfg(feature = "encrypt")]
+pub mod gcm_siv;
+#[cfg(feature = "encrypt")]
+pub mod ghash;
+#[cfg(feature = "encrypt")]
+pub mod gmac;
+mod hex;
+pub mod hkdf;
+pub mod hmac;
+#[cfg(feature = "encrypt")]
+pub mod kbkdf;
+#[cfg(all(feature = "encrypt", feature = "decrypt"))]
+pub mod key_block;
+mod key_schedule;
+pub mod key_split;
+#[cfg(all(feature = "encrypt", feature = "decrypt"))]
+pub mod key_wrap;
+#[cfg(feature = "encrypt")]
+pub mod keyring;
+mod mac;
+#[cfg(feature = "mlock")]
+pub mod mlock;
+mod nonce;
+#[cfg(feature = "encrypt")]
+pub mod nonce_tracker;
+#[cfg(all(feature = "encrypt", feature = "decrypt"))]
+pub mod ocb;
+pub mod padding;
+#[cfg(feature = "parallel")]
+pub mod parallel;
+#[cfg(feature = "encrypt")]
+pub mod password;
+#[cfg(feature = "encrypt")]
+pub mod pmac;
+#[cfg(feature = "encrypt")]
+mod polyval;
+mod random;
+mod rijndael;
+mod round_keys;
+pub mod scrypt;
+mod secret;
+mod sha256;
+#[cfg(feature = "portable-simd")]
+mod simd;
+#[cfg(feature = "encrypt")]
+pub mod siv;
+#[cfg(feature = "encrypt")]
+pub mod stream;
+mod tables;
+mod ttable;
+#[cfg(target_arch = "x86_64")]
+mod vaes;
+#[cfg(feature = "encrypt")]
+pub mod xaes;
+#[cfg(feature = "encrypt")]
+pub mod xcbc;
+#[cfg(all(feature = "encrypt", feature = "decrypt"))]
+pub mod xts;
+mod zeroize;
+#[cfg(all(target_arch = "riscv64", target_feature = "zkne", target_feature = "zknd"))]
+mod zkn;
+#[cfg(test)]
+mod test_util;
+
+#[cfg(feature = "encrypt")]
+pub use aead::{AadBuilder, Aead, AeadMessage, OpenMessage, SealedMessage};
+#[cfg(all(feature = "encrypt", feature = "decrypt"))]
+pub use backend::{KeyBackend, KeyHandle, SoftwareBackend};
+pub use block_iter::BlockIterator;
+#[cfg(feature = "decrypt")]
+pub use block_iter::DecryptBlocks;
+#[cfg(feature = "encrypt")]
+pub use block_iter::EncryptBlocks;
+pub use block_mode::BlockMode;
+pub use cipher::{Aes, Aes128, Aes192, Aes256, KeyScheduleMode, RoundBackend};
+#[cfg(feature = "decrypt")]
+pub use cipher::decrypt_bytes;
+#[cfg(feature = "encrypt")]
+pub use cipher::encrypt_bytes;
+pub use error::AesError;
+pub use mac::{verify_tag, Mac};
+pub use nonce::Nonce;
+#[cfg(feature = "decrypt")]
+pub use round_keys::InverseRoundKeys;
+pub use round_keys::RoundKeys;
+pub use secret::SecretKey;
+pub use key_schedule::{KeySchedule, KeySchedule128, KeySchedule192, KeySchedule256};
+pub use rijndael::{RijndaelBlock, RijndaelRoundKeys};
+
 ///
 /// DecryptedState is a marker struct used to indicate that the data is decrypted.
 /// 
@@ -8,6 +155,16 @@ pub struct DecryptedState;
 /// EncryptedState is a marker struct used to indicate that the data is encrypted.
 /// 
 pub struct EncryptedState;
+///
+/// FreshNonceState is a marker struct used to indicate that the iv or nonce of an
+/// encryptor has not been used yet.
+/// 
+pub struct FreshNonceState;
+///
+/// UsedNonceState is a marker struct used to indicate that the iv or nonce of an
+/// encryptor has been used and must be replaced before encrypting again.
+/// 
+pub struct UsedNonceState;
 
 ///
 /// AESBlock is a struct that represents a single 16 byte block of data. 
@@ -18,11 +175,19 @@ pub struct EncryptedState;
 /// or EncryptedState. This is to ensure that the data is not encrypted or decrypted twice.
 /// 
 /// The struct contains a grid of 16 bytes. This grid is considered to be a 4x4 grid with
-/// row-major order. This means that the first 4 bytes are the first row, the next 4 bytes
-/// are the second row and so on.
+/// column-major order as defined in FIPS-197. This means that the first 4 bytes are the
+/// first column, the next 4 bytes are the second column and so on. This is the same layout
+/// as used by other AES implementations, so the encrypted bytes are interchangeable.
+/// 
+/// The grid is a fixed array, so a block is always 16 bytes and the rounds are 
+/// done in place without allocating.
+/// 
+/// Blocks are Copy values and are compared in constant time. Since they are 
+/// copied freely they are not wiped when dropped, so long lived secrets should 
+/// be kept in a SecretKey rather than in a block.
 ///  
 pub struct AESBlock<State = DecryptedState> {
-    grid: Vec<u8>,
+    grid: [u8; 16],
     state: std::marker::PhantomData<State>
 }
 
@@ -41,77 +206,78 @@ pub struct AESData<State = DecryptedState> {
 impl AESBlock<DecryptedState> {
 
     ///
-    /// Substitutes each byte in the data with the corresponding byte in the s_box.
+    /// The s-box used by SubBytes. The table is generated at compile time from the
+    /// multiplicative inverses in GF(2^8) and the affine transformation.
     /// 
-    pub const S_BOX: [&'static u8; 256] = [ &0x63,&0x7c,&0x77,&0x7b,&0xf2,&0x6b,&0x6f,&0xc5,&0x30,&0x01,&0x67,&0x2b,&0xfe,&0xd7,&0xab,&0x76,
-                                        &0xca,&0x82,&0xc9,&0x7d,&0xfa,&0x59,&0x47,&0xf0,&0xad,&0xd4,&0xa2,&0xaf,&0x9c,&0xa4,&0x72,&0xc0,
-                                        &0xb7,&0xfd,&0x93,&0x26,&0x36,&0x3f,&0xf7,&0xcc,&0x34,&0xa5,&0xe5,&0xf1,&0x71,&0xd8,&0x31,&0x15,
-                                        &0x04,&0xc7,&0x23,&0xc3,&0x18,&0x96,&0x05,&0x9a,&0x07,&0x12,&0x80,&0xe2,&0xeb,&0x27,&0xb2,&0x75,
-                                        &0x09,&0x83,&0x2c,&0x1a,&0x1b,&0x6e,&0x5a,&0xa0,&0x52,&0x3b,&0xd6,&0xb3,&0x29,&0xe3,&0x2f,&0x84,
-                                        &0x53,&0xd1,&0x00,&0xed,&0x20,&0xfc,&0xb1,&0x5b,&0x6a,&0xcb,&0xbe,&0x39,&0x4a,&0x4c,&0x58,&0xcf,
-                                        &0xd0,&0xef,&0xaa,&0xfb,&0x43,&0x4d,&0x33,&0x85,&0x45,&0xf9,&0x02,&0x7f,&0x50,&0x3c,&0x9f,&0xa8,
-                                        &0x51,&0xa3,&0x40,&0x8f,&0x92,&0x9d,&0x38,&0xf5,&0xbc,&0xb6,&0xda,&0x21,&0x10,&0xff,&0xf3,&0xd2,
-                                        &0xcd,&0x0c,&0x13,&0xec,&0x5f,&0x97,&0x44,&0x17,&0xc4,&0xa7,&0x7e,&0x3d,&0x64,&0x5d,&0x19,&0x73,
-                                        &0x60,&0x81,&0x4f,&0xdc,&0x22,&0x2a,&0x90,&0x88,&0x46,&0xee,&0xb8,&0x14,&0xde,&0x5e,&0x0b,&0xdb,
-                                        &0xe0,&0x32,&0x3a,&0x0a,&0x49,&0x06,&0x24,&0x5c,&0xc2,&0xd3,&0xac,&0x62,&0x91,&0x95,&0xe4,&0x79,
-                                        &0xe7,&0xc8,&0x37,&0x6d,&0x8d,&0xd5,&0x4e,&0xa9,&0x6c,&0x56,&0xf4,&0xea,&0x65,&0x7a,&0xae,&0x08,
-                                        &0xba,&0x78,&0x25,&0x2e,&0x1c,&0xa6,&0xb4,&0xc6,&0xe8,&0xdd,&0x74,&0x1f,&0x4b,&0xbd,&0x8b,&0x8a,
-                                        &0x70,&0x3e,&0xb5,&0x66,&0x48,&0x03,&0xf6,&0x0e,&0x61,&0x35,&0x57,&0xb9,&0x86,&0xc1,&0x1d,&0x9e,
-                                        &0xe1,&0xf8,&0x98,&0x11,&0x69,&0xd9,&0x8e,&0x94,&0x9b,&0x1e,&0x87,&0xe9,&0xce,&0x55,&0x28,&0xdf,
-                                        &0x8c,&0xa1,&0x89,&0x0d,&0xbf,&0xe6,&0x42,&0x68,&0x41,&0x99,&0x2d,&0x0f,&0xb0,&0x54,&0xbb,&0x16];
-
-    pub fn new(data: Vec<u8>) -> AESBlock<DecryptedState> {
-        AESBlock {
-            grid: data,
-            state: std::marker::PhantomData::<DecryptedState>
-        }
-    }
-    
+    pub const S_BOX: [u8; 256] = tables::S_BOX;
+
     ///
-    /// Full encryption of a single 16 byte block.
+    /// Creates a new AESBlock struct with the specified data.
     /// 
-    /// roundkeys: A vector of 11, 13 or 15 roundkeys. Each roundkey is a vector of 16 bytes.
-    /// 
-    /// result: A vector of 16 bytes encrypted.
-    /// 
-    pub fn encrypt(&self, roundkeys: &Vec<Vec<u8>>) -> AESBlock<EncryptedState> {
-        let mut result = self.add_roundkey(&self.grid, &roundkeys[0]);
-        for (idx, _) in roundkeys.iter().skip(1).enumerate() {
-            result = self.sub_bytes(&result);
-            result = self.shift_grid(&result);
-            result = if idx != (roundkeys.len() - 1) {
-                self.mix_columns(&result)
-            } else {
-                result
-            };
-            result = self.add_roundkey(&result, &roundkeys[idx + 1]);
-        }
-        AESBlock {
-            grid: result.clone(),
-            state: std::marker::PhantomData::<EncryptedState>
-        }
+    /// data: The 16 bytes of the block.
+    /// 
+    /// result: A AESBlock struct with the specified data.
+    /// 
+    pub fn new(data: [u8; 16]) -> AESBlock<DecryptedState> {
+        AESBlock::from_grid(data)
     }
 
     ///
-    /// Shifts the grid by the following pattern:
-    /// row 1 not shifted.
-    /// row 2 shifted to the left once
-    /// row 3 shifted to the left twice
-    /// row 4 shifted to the left three times
+    /// Creates a new AESBlock struct with the specified data.
     /// 
-    /// data: A vector of 16 bytes. These are considered to be in 
-    ///       pattern of a 4x4 grid with row-major order.
+    /// data: A slice of exactly 16 bytes.
     /// 
-    /// result: A vector of 16 bytes. These are considered to be in
-    ///         pattern of a 4x4 grid with row-major order.
+    /// result: A AESBlock struct with the specified data or AesError::InvalidBlockLength
+    ///         if the data is not 16 bytes.
     /// 
-    fn shift_grid(&self, data: &[u8]) -> Vec<u8> {
-        let mut result: Vec<u8> = vec![0; data.len()];
-        data.chunks(4).enumerate().for_each(|(idx, row)| {
-            let shifted_row = self.shift_row(row, &idx);
-            result.splice(idx * 4..idx * 4 + 4, shifted_row);
-        });
-        result
+    pub fn try_new(data: &[u8]) -> Result<AESBlock<DecryptedState>, AesError> {
+        Ok(AESBlock::from_grid(Self::validate_length(data)?))
+    }
+    
+    ///
+    /// Full encryption of a single 16 byte block.
+    /// 
+    /// The encryption follows FIPS-197 section 5.1. The first roundkey is added
+    /// to the data, followed by Nr - 1 full rounds and a final round without
+    /// the column mixing.
+    /// 
+    /// The block is consumed and its grid is encrypted in place, so the state 
+    /// changes from decrypted to encrypted without copying the data.
+    /// 
+    /// roundkeys: The validated roundkeys, containing 11, 13 or 15 roundkeys.
+    /// 
+    /// result: A AESBlock with the 16 bytes encrypted.
+    /// 
+    #[cfg(feature = "encrypt")]
+    pub fn encrypt(self, roundkeys: &RoundKeys) -> AESBlock<EncryptedState> {
+        let mut grid = self.grid;
+        self.encrypt_rounds(&mut grid, roundkeys.rounds(), roundkeys.as_slice().iter());
+        AESBlock::from_grid(grid)
+    }
+
+    ///
+    /// Encrypts a grid in place with the roundkeys produced by an iterator, so that 
+    /// the roundkeys can be generated while encrypting and the data is not copied.
+    /// 
+    /// grid: The 16 bytes to encrypt, replaced by the encrypted bytes.
+    /// rounds: The number of rounds.
+    /// roundkeys: An iterator producing rounds + 1 roundkeys of 16 bytes.
+    /// 
+    #[cfg(feature = "encrypt")]
+    pub(crate) fn encrypt_rounds<K: Deref<Target = [u8; 16]>>(&self, grid: &mut [u8; 16], rounds: usize, mut roundkeys: impl Iterator<Item = K>) {
+        let mut state = columns::from_bytes(grid);
+        columns::add_roundkey_bytes(&mut state, &roundkeys.next().expect("Missing roundkey"));
+        for roundkey in roundkeys.by_ref().take(rounds - 1) {
+            columns::sub_bytes(&mut state, &AESBlock::S_BOX);
+            columns::shift_rows(&mut state, 1);
+            columns::mix_columns(&mut state);
+            columns::add_roundkey_bytes(&mut state, &roundkey);
+        }
+        columns::sub_bytes(&mut state, &AESBlock::S_BOX);
+        columns::shift_rows(&mut state, 1);
+        columns::add_roundkey_bytes(&mut state, &roundkeys.next().expect("Missing roundkey"));
+        columns::to_bytes(&state, grid);
+        state.zeroize();
     }
 
     ///
@@ -119,26 +285,13 @@ impl AESBlock<DecryptedState> {
     /// algorithm. Description of the algorithm can be found here:
     /// https://en.wikipedia.org/wiki/Rijndael_MixColumns
     /// 
-    /// data: A vector of 4 bytes for colunm X,
+    /// data: A slice of 4 bytes for colunm X,
     /// 
-    /// result: A vector of 4 bytes for each row.
+    /// result: The 4 mixed bytes of the column.
     ///  
-    fn mix_column(&self, data: &[u8]) -> Vec<u8> {
-        let mut result: Vec<u8> = vec![0;4];
-        let mut a: Vec<u8> = vec![0;4];
-        let mut b: Vec<u8> = vec![0;4];
-        let mut h: u8;
-        for c in 0..4 {
-            a[c] = data[c];
-            h = (data[c] >> 7) & 1; 
-            b[c] = data[c] << 1; 
-            b[c] ^= h * 0x1B; 
-        }
-        result[0] = self.multiply(0x02, a[0]) ^ self.multiply(0x03, a[1]) ^ self.multiply(0x01, a[2]) ^ self.multiply(0x01, a[3]);
-        result[1] = self.multiply(0x01, a[0]) ^ self.multiply(0x02, a[1]) ^ self.multiply(0x03, a[2]) ^ self.multiply(0x01, a[3]);
-        result[2] = self.multiply(0x01, a[0]) ^ self.multiply(0x01, a[1]) ^ self.multiply(0x02, a[2]) ^ self.multiply(0x03, a[3]);
-        result[3] = self.multiply(0x03, a[0]) ^ self.multiply(0x01, a[1]) ^ self.multiply(0x01, a[2]) ^ self.multiply(0x02, a[3]);   
-        result
+    #[cfg(feature = "encrypt")]
+    fn mix_column(&self, data: &[u8]) -> [u8; 4] {
+        columns::mix_column(u32::from_be_bytes(data.try_into().unwrap())).to_be_bytes()
     }
 
     ///
@@ -146,34 +299,27 @@ impl AESBlock<DecryptedState> {
     /// algorithm. Description of the algorithm can be found here:
     /// https://en.wikipedia.org/wiki/Rijndael_MixColumns.
     /// 
-    /// data: A vector of 16 bytes. These are considered to be in
-    ///      pattern of a 4x4 grid with row-major order.
+    /// data: A multiple of 4 bytes in column-major order, mixed in place.
     /// 
-    /// result: A vector of 16 bytes. These are considered to be in
-    ///     pattern of a 4x4 grid with row-major order.
-    /// 
-    fn mix_columns(&self, data: &[u8]) -> Vec<u8> {        
-        let col1: Vec<u8> = self.mix_column(&[data[0], data[4], data[8], data[12]]);
-        let col2: Vec<u8> = self.mix_column(&[data[1], data[5], data[9], data[13]]);
-        let col3: Vec<u8> = self.mix_column(&[data[2], data[6], data[10], data[14]]);
-        let col4: Vec<u8> = self.mix_column(&[data[3], data[7], data[11], data[15]]);
-        vec![col1[0], col2[0], col3[0], col4[0], col1[1], col2[1], col3[1], col4[1], col1[2], col2[2], col3[2], col4[2], col1[3], col2[3], col3[3], col4[3]]
+    #[cfg(feature = "encrypt")]
+    fn mix_columns(&self, data: &mut [u8]) {
+        for column in data.chunks_exact_mut(4) {
+            let mut mixed = self.mix_column(column);
+            column.copy_from_slice(&mixed);
+            mixed.zeroize();
+        }
     }
 
     ///
     /// Substitutes each byte in the data with the corresponding byte in the s_box.
     /// 
-    /// data: A vector of bytes to be exchanged..
-    /// s_box: A vector of bytes containg the substitution values.
-    /// 
-    /// result: A vector of bytes with the substituted values.
+    /// data: The bytes to be exchanged in place.
     /// 
-    fn sub_bytes(&self, data: &[u8]) -> Vec<u8> {
-        let mut result = vec![0; data.len()];
-        for (idx, value) in data.iter().enumerate() {
-            result[idx] = *AESBlock::S_BOX[*value as usize];            
+    #[cfg(feature = "encrypt")]
+    fn sub_bytes(&self, data: &mut [u8]) {
+        for value in data.iter_mut() {
+            *value = AESBlock::S_BOX[*value as usize];
         }
-        result
     }
 
 
@@ -185,62 +331,125 @@ impl AESBlock<DecryptedState> {
 impl AESBlock<EncryptedState> {
 
     ///
-    /// Substitutes each byte in the data with the corresponding byte in the inverse_s_box.
+    /// The inverse s-box used by InvSubBytes, generated at compile time by inverting
+    /// the s-box.
     /// 
-    const INVERSE_S_BOX: [&'static u8; 256] = [ &0x52,&0x09,&0x6a,&0xd5,&0x30,&0x36,&0xa5,&0x38,&0xbf,&0x40,&0xa3,&0x9e,&0x81,&0xf3,&0xd7,&0xfb,
-                                                &0x7c,&0xe3,&0x39,&0x82,&0x9b,&0x2f,&0xff,&0x87,&0x34,&0x8e,&0x43,&0x44,&0xc4,&0xde,&0xe9,&0xcb,
-                                                &0x54,&0x7b,&0x94,&0x32,&0xa6,&0xc2,&0x23,&0x3d,&0xee,&0x4c,&0x95,&0x0b,&0x42,&0xfa,&0xc3,&0x4e,
-                                                &0x08,&0x2e,&0xa1,&0x66,&0x28,&0xd9,&0x24,&0xb2,&0x76,&0x5b,&0xa2,&0x49,&0x6d,&0x8b,&0xd1,&0x25,
-                                                &0x72,&0xf8,&0xf6,&0x64,&0x86,&0x68,&0x98,&0x16,&0xd4,&0xa4,&0x5c,&0xcc,&0x5d,&0x65,&0xb6,&0x92,
-                                                &0x6c,&0x70,&0x48,&0x50,&0xfd,&0xed,&0xb9,&0xda,&0x5e,&0x15,&0x46,&0x57,&0xa7,&0x8d,&0x9d,&0x84,
-                                                &0x90,&0xd8,&0xab,&0x00,&0x8c,&0xbc,&0xd3,&0x0a,&0xf7,&0xe4,&0x58,&0x05,&0xb8,&0xb3,&0x45,&0x06,
-                                                &0xd0,&0x2c,&0x1e,&0x8f,&0xca,&0x3f,&0x0f,&0x02,&0xc1,&0xaf,&0xbd,&0x03,&0x01,&0x13,&0x8a,&0x6b,
-                                                &0x3a,&0x91,&0x11,&0x41,&0x4f,&0x67,&0xdc,&0xea,&0x97,&0xf2,&0xcf,&0xce,&0xf0,&0xb4,&0xe6,&0x73,
-                                                &0x96,&0xac,&0x74,&0x22,&0xe7,&0xad,&0x35,&0x85,&0xe2,&0xf9,&0x37,&0xe8,&0x1c,&0x75,&0xdf,&0x6e,
-                                                &0x47,&0xf1,&0x1a,&0x71,&0x1d,&0x29,&0xc5,&0x89,&0x6f,&0xb7,&0x62,&0x0e,&0xaa,&0x18,&0xbe,&0x1b,
-                                                &0xfc,&0x56,&0x3e,&0x4b,&0xc6,&0xd2,&0x79,&0x20,&0x9a,&0xdb,&0xc0,&0xfe,&0x78,&0xcd,&0x5a,&0xf4,
-                                                &0x1f,&0xdd,&0xa8,&0x33,&0x88,&0x07,&0xc7,&0x31,&0xb1,&0x12,&0x10,&0x59,&0x27,&0x80,&0xec,&0x5f,
-                                                &0x60,&0x51,&0x7f,&0xa9,&0x19,&0xb5,&0x4a,&0x0d,&0x2d,&0xe5,&0x7a,&0x9f,&0x93,&0xc9,&0x9c,&0xef,
-                                                &0xa0,&0xe0,&0x3b,&0x4d,&0xae,&0x2a,&0xf5,&0xb0,&0xc8,&0xeb,&0xbb,&0x3c,&0x83,&0x53,&0x99,&0x61,
-                                                &0x17,&0x2b,&0x04,&0x7e,&0xba,&0x77,&0xd6,&0x26,&0xe1,&0x69,&0x14,&0x63,&0x55,&0x21,&0x0c,&0x7d];
+    #[cfg(feature = "decrypt")]
+    pub const INVERSE_S_BOX: [u8; 256] = tables::INVERSE_S_BOX;
 
     ///
     /// Creates a new AESBlock struct with the specified data.
     /// 
-    /// data: A vector of bytes.
+    /// data: The 16 bytes of the block.
     /// 
     /// result: A AESBlock struct with the specified data.
     ///                                                     
-    pub fn new(data: Vec<u8>) -> AESBlock<EncryptedState> {
-        AESBlock {
-            grid: data,
-            state: std::marker::PhantomData::<EncryptedState>
-        }
+    pub fn new(data: [u8; 16]) -> AESBlock<EncryptedState> {
+        AESBlock::from_grid(data)
+    }
+
+    ///
+    /// Creates a new AESBlock struct with the specified data.
+    /// 
+    /// data: A slice of exactly 16 bytes.
+    /// 
+    /// result: A AESBlock struct with the specified data or AesError::InvalidBlockLength
+    ///         if the data is not 16 bytes.
+    /// 
+    pub fn try_new(data: &[u8]) -> Result<AESBlock<EncryptedState>, AesError> {
+        Ok(AESBlock::from_grid(Self::validate_length(data)?))
     }
 
     ///
     /// Full decryption of a single 16 byte block.
     /// 
-    /// roundkeys: A vector of 11, 13 or 15 roundkeys. Each roundkey is a vector of 16 bytes.
-    /// 
-    /// result: A vector of 16 bytes decrypted.
-    /// 
-    pub fn decrypt(&self, roundkeys: &Vec<Vec<u8>>) -> AESBlock<DecryptedState> {
-        let mut result = self.add_roundkey(&self.grid, &roundkeys[roundkeys.len() - 1]);
-        for (idx, _) in roundkeys.iter().rev().skip(1).enumerate() {
-            result = if idx != (roundkeys.len() - 1) {
-                self.mix_columns(&result)
-            } else {
-                result
-            };            
-            result = self.shift_grid(&result);
-            result = self.sub_bytes(&result);
-            result = self.add_roundkey(&result, &roundkeys[roundkeys.len() - idx - 2]);
-        }        
-        AESBlock {
-            grid: result.clone(),
-            state: std::marker::PhantomData::<DecryptedState>
+    /// The decryption follows the inverse cipher in FIPS-197 section 5.3. The last 
+    /// roundkey is added to the data, followed by Nr - 1 full inverse rounds and 
+    /// a final inverse round without the column mixing.
+    /// 
+    /// The block is consumed and its grid is decrypted in place, so the state 
+    /// changes from encrypted to decrypted without copying the data.
+    /// 
+    /// roundkeys: The validated roundkeys, containing 11, 13 or 15 roundkeys.
+    /// 
+    /// result: A AESBlock with the 16 bytes decrypted.
+    /// 
+    #[cfg(feature = "decrypt")]
+    pub fn decrypt(self, roundkeys: &RoundKeys) -> AESBlock<DecryptedState> {
+        let mut grid = self.grid;
+        self.decrypt_rounds(&mut grid, roundkeys.rounds(), roundkeys.as_slice().iter().rev());
+        AESBlock::from_grid(grid)
+    }
+
+    ///
+    /// Decrypts a grid in place with the roundkeys produced by an iterator, so that 
+    /// the roundkeys can be generated while decrypting and the data is not copied.
+    /// 
+    /// grid: The 16 bytes to decrypt, replaced by the decrypted bytes.
+    /// rounds: The number of rounds.
+    /// roundkeys: An iterator producing rounds + 1 roundkeys of 16 bytes, starting
+    ///            with the last roundkey.
+    /// 
+    #[cfg(feature = "decrypt")]
+    pub(crate) fn decrypt_rounds<K: Deref<Target = [u8; 16]>>(&self, grid: &mut [u8; 16], rounds: usize, mut roundkeys: impl Iterator<Item = K>) {
+        let mut state = columns::from_bytes(grid);
+        columns::add_roundkey_bytes(&mut state, &roundkeys.next().expect("Missing roundkey"));
+        for roundkey in roundkeys.by_ref().take(rounds - 1) {
+            columns::shift_rows(&mut state, 3);
+            columns::sub_bytes(&mut state, &AESBlock::INVERSE_S_BOX);
+            columns::add_roundkey_bytes(&mut state, &roundkey);
+            columns::inverse_mix_columns(&mut state);
+        }
+        columns::shift_rows(&mut state, 3);
+        columns::sub_bytes(&mut state, &AESBlock::INVERSE_S_BOX);
+        columns::add_roundkey_bytes(&mut state, &roundkeys.next().expect("Missing roundkey"));
+        columns::to_bytes(&state, grid);
+        state.zeroize();
+    }
+
+    ///
+    /// Full decryption of a single 16 byte block using the equivalent inverse cipher.
+    /// 
+    /// The decryption follows the equivalent inverse cipher in FIPS-197 section 5.3.5.
+    /// The transformations are applied in the same order as in the encryption, using
+    /// roundkeys where the inverse column mixing is already applied.
+    /// 
+    /// Like decrypt the block is consumed and decrypted in place.
+    /// 
+    /// roundkeys: The inverse roundkeys, containing 11, 13 or 15 roundkeys.
+    /// 
+    /// result: A AESBlock with the 16 bytes decrypted.
+    /// 
+    #[cfg(feature = "decrypt")]
+    pub fn decrypt_equivalent(self, roundkeys: &InverseRoundKeys) -> AESBlock<DecryptedState> {
+        let mut grid = self.grid;
+        self.decrypt_equivalent_rounds(&mut grid, roundkeys);
+        AESBlock::from_grid(grid)
+    }
+
+    ///
+    /// Decrypts a grid in place using the equivalent inverse cipher.
+    /// 
+    /// grid: The 16 bytes to decrypt, replaced by the decrypted bytes.
+    /// roundkeys: The inverse roundkeys, containing 11, 13 or 15 roundkeys.
+    /// 
+    #[cfg(feature = "decrypt")]
+    pub(crate) fn decrypt_equivalent_rounds(&self, grid: &mut [u8; 16], roundkeys: &InverseRoundKeys) {
+        let rounds = roundkeys.rounds();
+        let roundkeys = roundkeys.as_slice();
+        let mut state = columns::from_bytes(grid);
+        columns::add_roundkey_bytes(&mut state, &roundkeys[rounds]);
+        for roundkey in roundkeys[1..rounds].iter().rev() {
+            columns::sub_bytes(&mut state, &AESBlock::INVERSE_S_BOX);
+            columns::shift_rows(&mut state, 3);
+            columns::inverse_mix_columns(&mut state);
+            columns::add_roundkey_bytes(&mut state, roundkey);
         }
+        columns::sub_bytes(&mut state, &AESBlock::INVERSE_S_BOX);
+        columns::shift_rows(&mut state, 3);
+        columns::add_roundkey_bytes(&mut state, &roundkeys[0]);
+        columns::to_bytes(&state, grid);
+        state.zeroize();
     }
 
     ///
@@ -248,28 +457,13 @@ impl AESBlock<EncryptedState> {
     /// algorithm. Description of the algorithm can be found here:
     /// https://en.wikipedia.org/wiki/Rijndael_MixColumns.
     /// 
-    /// data: A vector of 16 bytes. These are considered to be in
-    ///      pattern of a 4x4 grid with row-major order.
-    /// 
-    /// result: A vector of 16 bytes. These are considered to be in
-    ///     pattern of a 4x4 grid with row-major order.
-    /// 
-    fn mix_column(&self, data: &[u8]) -> Vec<u8> {
-        let mut result: Vec<u8> = vec![0;4];
-        let mut a: Vec<u8> = vec![0;4];
-        let mut b: Vec<u8> = vec![0;4];
-        let mut h: u8;
-        for c in 0..4 {
-            a[c] = data[c];
-            h = (data[c] >> 7) & 1; 
-            b[c] = data[c] << 1; 
-            b[c] ^= h * 0x1B; 
-        }
-        result[0] = self.multiply(0x0E, a[0]) ^ self.multiply(0x0B, a[1]) ^ self.multiply(0x0D, a[2]) ^ self.multiply(0x09, a[3]);
-        result[1] = self.multiply(0x09, a[0]) ^ self.multiply(0x0E, a[1]) ^ self.multiply(0x0B, a[2]) ^ self.multiply(0x0D, a[3]);
-        result[2] = self.multiply(0x0D, a[0]) ^ self.multiply(0x09, a[1]) ^ self.multiply(0x0E, a[2]) ^ self.multiply(0x0B, a[3]);
-        result[3] = self.multiply(0x0B, a[0]) ^ self.multiply(0x0D, a[1]) ^ self.multiply(0x09, a[2]) ^ self.multiply(0x0E, a[3]);    
-        result
+    /// data: A slice of 4 bytes for colunm X,
+    /// 
+    /// result: The 4 inverse mixed bytes of the column.
+    /// 
+    #[cfg(feature = "decrypt")]
+    fn mix_column(&self, data: &[u8]) -> [u8; 4] {
+        columns::inverse_mix_column(u32::from_be_bytes(data.try_into().unwrap())).to_be_bytes()
     }
 
 
@@ -278,62 +472,99 @@ impl AESBlock<EncryptedState> {
     /// algorithm. Description of the algorithm can be found here:
     /// https://en.wikipedia.org/wiki/Rijndael_MixColumns.
     /// 
-    /// data: A vector of 16 bytes. These are considered to be in
-    ///      pattern of a 4x4 grid with row-major order.
+    /// data: A multiple of 4 bytes in column-major order, mixed in place.
     /// 
-    /// result: A vector of 16 bytes. These are considered to be in
-    ///     pattern of a 4x4 grid with row-major order.
+    #[cfg(feature = "decrypt")]
+    fn mix_columns(&self, data: &mut [u8]) {
+        for column in data.chunks_exact_mut(4) {
+            let mut mixed = self.mix_column(column);
+            column.copy_from_slice(&mixed);
+            mixed.zeroize();
+        }
+    }
+
+    ///
+    /// Substitutes each byte in the data with the corresponding byte in the inverse_s_box.
+    /// 
+    /// data: The bytes to be exchanged in place.
     /// 
-    fn mix_columns(&self, data: &[u8]) -> Vec<u8> {
-        let col1: Vec<u8> = self.mix_column(&[data[0], data[4], data[8], data[12]]);
-        let col2: Vec<u8> = self.mix_column(&[data[1], data[5], data[9], data[13]]);
-        let col3: Vec<u8> = self.mix_column(&[data[2], data[6], data[10], data[14]]);
-        let col4: Vec<u8> = self.mix_column(&[data[3], data[7], data[11], data[15]]);
-        vec![col1[0], col2[0], col3[0], col4[0], col1[1], col2[1], col3[1], col4[1], col1[2], col2[2], col3[2], col4[2], col1[3], col2[3], col3[3], col4[3]]
+    #[cfg(feature = "decrypt")]
+    fn sub_bytes(&self, data: &mut [u8]) {
+        for value in data.iter_mut() {
+            *value = AESBlock::INVERSE_S_BOX[*value as usize];
+        }
     }
 
+    
+}
+
+impl<State> AESBlock<State> {
+
     ///
-    /// Shifts the grid by the following pattern:
-    /// row 1 not shifted.
-    /// row 2 shifted to the left thrice
-    /// row 3 shifted to the left twice
-    /// row 4 shifted to the left once
+    /// Creates a new AESBlock struct from a grid.
     /// 
-    /// data: A vector of 16 bytes. These are considered to be in 
-    ///       pattern of a 4x4 grid with row-major order.
+    fn from_grid(grid: [u8; 16]) -> AESBlock<State> {
+        AESBlock {
+            grid,
+            state: std::marker::PhantomData::<State>
+        }
+    }
+
+    ///
+    /// Returns the bytes of the block.
     /// 
-    /// result: A vector of 16 bytes. These are considered to be in
-    ///         pattern of a 4x4 grid with row-major order.
+    /// result: The 16 bytes of the block.
     /// 
-    fn shift_grid(&self, data: &[u8]) -> Vec<u8> {
-        let mut result: Vec<u8> = vec![0; data.len()];
-        data.chunks(4).enumerate().for_each(|(idx, row)| {
-            let shifted_row = self.shift_row(row,  &(4 - idx));
-            result.splice(idx * 4..idx * 4 + 4, shifted_row);
-        });
-        result
+    pub fn as_bytes(&self) -> &[u8; 16] {
+        &self.grid
     }
 
     ///
-    /// Substitutes each byte in the data with the corresponding byte in the inverse_s_box.
+    /// Copies the bytes of the block into a vector.
     /// 
-    /// data: A vector of bytes to be exchanged..
-    /// s_box: A vector of bytes containg the substitution values.
+    /// result: A vector of the 16 bytes of the block.
     /// 
-    /// result: A vector of bytes with the substituted values.
+    pub fn to_vec(&self) -> Vec<u8> {
+        self.grid.to_vec()
+    }
+
+    ///
+    /// Consumes the block and returns the bytes. The block is Copy and is not 
+    /// wiped when dropped, so copies of the bytes may remain in memory. Long lived
+    /// secrets should be kept in a SecretKey instead.
     /// 
-    fn sub_bytes(&self, data: &[u8]) -> Vec<u8> {
-        let mut result = vec![0; data.len()];
-        for (idx, value) in data.iter().enumerate() {
-            result[idx] = *AESBlock::INVERSE_S_BOX[*value as usize];            
-        }
-        result
+    /// result: The 16 bytes of the block.
+    /// 
+    pub fn into_inner(self) -> [u8; 16] {
+        self.grid
     }
 
-    
-}
+    ///
+    /// Creates a new AESBlock struct from a hex string, as test vectors and 
+    /// configurations are usually given. Both uppercase and lowercase digits 
+    /// are accepted.
+    /// 
+    /// data: A hex string of 32 digits.
+    /// 
+    /// result: A AESBlock struct with the decoded data, AesError::InvalidHex if the
+    ///         string is not hex or AesError::InvalidBlockLength if the string 
+    ///         is not 16 bytes.
+    /// 
+    pub fn from_hex(data: &str) -> Result<AESBlock<State>, AesError> {
+        let bytes = Zeroizing(hex::decode(data.as_bytes()).ok_or(AesError::InvalidHex)?);
+        Ok(AESBlock::from_grid(Self::validate_length(&bytes)?))
+    }
 
-impl<State> AESBlock<State> {
+    ///
+    /// Validates that the data is exactly one block of 16 bytes.
+    /// 
+    /// data: A slice of bytes.
+    /// 
+    /// result: The 16 bytes or AesError::InvalidBlockLength.
+    /// 
+    fn validate_length(data: &[u8]) -> Result<[u8; 16], AesError> {
+        data.try_into().map_err(|_| AesError::InvalidBlockLength { length: data.len(), min: 16, max: 16, multiple: 16 })
+    }
 
     ///
     /// Multiplies two bytes in the Galois field.
@@ -365,37 +596,139 @@ impl<State> AESBlock<State> {
     ///
     /// Shifts a row of bytes left by the specified amount.
     /// 
-    /// row: A vector of 4 bytes.
+    /// row: The bytes of the row, shifted in place.
     /// shift: The amount to shift the row by.
     /// 
-    /// result: A vector of 4 bytes shifted.
-    /// 
-    /// 
-    fn shift_row(&self, row: &[u8], shift: &usize) -> Vec<u8> {
-        let mut result = vec![0; row.len()];
-        for (idx, value) in row.iter().enumerate() {
-            let new_idx = idx + row.len() - shift;
-            result[(new_idx) % row.len()] = *value;
-        }
-        result
+    fn shift_row(&self, row: &mut [u8], shift: &usize) {
+        row.rotate_left(shift % row.len());
     }
 
     ///
     /// Adds the roundkey to the data.
     /// 
-    /// data: A vector of bytes to be exchanged.
+    /// data: The bytes the roundkey is added to in place.
     /// roundkey: Key to be added to the data.
     /// 
-    /// result: A vector of bytes with the added values.
+    fn add_roundkey(&self, data: &mut [u8], roundkey: &[u8]) {
+        data.iter_mut().zip(roundkey).for_each(|(value, key)| *value ^= key);
+    }
+
+
+}
+
+impl<State> Clone for AESBlock<State> {
+
+    fn clone(&self) -> Self {
+        *self
+    }
+
+}
+
+impl<State> Copy for AESBlock<State> {}
+
+impl<State> PartialEq for AESBlock<State> {
+
+    fn eq(&self, other: &Self) -> bool {
+        verify_tag(&self.grid, &other.grid).is_ok()
+    }
+
+}
+
+impl<State> Eq for AESBlock<State> {}
+
+impl<State> Zeroize for AESBlock<State> {
+
+    fn zeroize(&mut self) {
+        self.grid.zeroize();
+    }
+
+}
+
+impl AESBlock<EncryptedState> {
+
+    ///
+    /// Encodes the encrypted block as lowercase hex.
     /// 
-    fn add_roundkey(&self, data: &[u8], roundkey: &[u8]) -> Vec<u8> {
-        let mut result: Vec<u8> = vec![0; data.len()];
-        for (idx, value) in data.iter().enumerate() {
-            result[idx] = value ^ roundkey[idx];
-        }
-        result
+    /// result: A string of 32 hex digits.
+    /// 
+    pub fn to_hex(&self) -> String {
+        hex::encode(&self.grid)
+    }
+
+    ///
+    /// Encodes the encrypted block as padded base64.
+    /// 
+    /// result: A string of 24 base64 characters.
+    /// 
+    #[cfg(feature = "base64")]
+    pub fn to_base64(&self) -> String {
+        base64::encode(&self.grid)
+    }
+
+    ///
+    /// Creates a new encrypted AESBlock struct from padded base64.
+    /// 
+    /// data: A base64 string of 16 bytes.
+    /// 
+    /// result: A AESBlock struct with the decoded data, AesError::InvalidBase64 if the
+    ///         string is not base64 or AesError::InvalidBlockLength if the string 
+    ///         is not 16 bytes.
+    /// 
+    #[cfg(feature = "base64")]
+    pub fn from_base64(data: &str) -> Result<AESBlock<EncryptedState>, AesError> {
+        let bytes = base64::decode(data.as_bytes()).ok_or(AesError::InvalidBase64)?;
+        Ok(AESBlock::from_grid(Self::validate_length(&bytes)?))
+    }
+
+}
+
+impl fmt::Display for AESBlock<EncryptedState> {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_hex())
+    }
+
+}
+
+impl fmt::Debug for AESBlock<DecryptedState> {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("AESBlock<DecryptedState>").field(&Redacted(self.grid.len())).finish()
+    }
+
+}
+
+impl fmt::Debug for AESBlock<EncryptedState> {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "AESBlock<EncryptedState>({})", self.to_hex())
+    }
+
+}
+
+impl<State> From<[u8; 16]> for AESBlock<State> {
+
+    fn from(data: [u8; 16]) -> Self {
+        AESBlock::from_grid(data)
+    }
+
+}
+
+impl<State> TryFrom<&[u8]> for AESBlock<State> {
+
+    type Error = AesError;
+
+    fn try_from(data: &[u8]) -> Result<Self, AesError> {
+        Ok(AESBlock::from_grid(Self::validate_length(data)?))
     }
 
+}
+
+impl<State> From<AESBlock<State>> for [u8; 16] {
+
+    fn from(block: AESBlock<State>) -> Self {
+        block.into_inner()
+    }
 
 }
 
@@ -418,27 +751,23 @@ impl AESData<DecryptedState> {
     ///
     /// Encrypts the data using AES ithe specified roundkeys.
     /// Data is padded with a character to make it a multiple of 16 bytes. 
-    /// This character is the last character xored with 0x01.
+    /// This character is the last character xored with 0x01, or 0x01 if
+    /// the data is empty.
     /// 
-    /// roundkeys: A vector of 11, 13 or 15 roundkeys. Each roundkey is a vector of 16 bytes.
+    /// roundkeys: The validated roundkeys, containing 11, 13 or 15 roundkeys.
     /// 
     /// result: A vector of bytes encrypted.
     /// 
-    pub fn encrypt(&self, roundkeys: &Vec<Vec<u8>>) -> AESData<EncryptedState> {
-        let padding_char = self.data[self.data.len() - 1] ^ 0x01;
+    #[cfg(feature = "encrypt")]
+    pub fn encrypt(&self, roundkeys: &RoundKeys) -> AESData<EncryptedState> {
+        let padding_char = self.data.last().map_or(0x01, |last| last ^ 0x01);
         let padded_data: Vec<u8> = self.data.iter().chain(vec![padding_char; 32 - (self.data.len() % 16)].iter()).cloned().collect();
         let encrypted_data = padded_data
         .chunks(16)
         .flat_map(|block: &[u8]| {
-            let block = if block.len() < 16 {
-                let mut block: Vec<u8> = block.to_vec();                
-                block.resize(16, padding_char);
-                block
-            } else {
-                block.to_vec()
-            };
-            let aes_block = AESBlock::<DecryptedState>::new(block);
-            aes_block.encrypt(roundkeys).grid
+            let mut aes_block = AESBlock::<DecryptedState>::from_grid([padding_char; 16]);
+            aes_block.grid[..block.len()].copy_from_slice(block);
+            aes_block.encrypt(roundkeys).into_inner()
         }).collect();
         AESData {
             data: encrypted_data,
@@ -458,324 +787,386 @@ impl AESData<EncryptedState> {
 
     ///
     /// Decrypts the data using AES ithe specified roundkeys.
-    /// Any padded characters are removed.
+    /// Any padded characters are removed. The blocks are decrypted using the 
+    /// equivalent inverse cipher.
     /// 
-    /// roundkeys: A vector of 11, 13 or 15 roundkeys. Each roundkey is a vector of 16 bytes.
+    /// roundkeys: The validated roundkeys, containing 11, 13 or 15 roundkeys.
     /// 
-    /// result: A vector of bytes decrypted.
+    /// result: A vector of bytes decrypted or AesError::InvalidDataLength if the
+    ///         data is empty or not a multiple of 16 bytes.
     /// 
-    pub fn decrypt(&self, roundkeys: &Vec<Vec<u8>>) -> AESData<DecryptedState> {
+    #[cfg(feature = "decrypt")]
+    pub fn decrypt(&self, roundkeys: &RoundKeys) -> Result<AESData<DecryptedState>, AesError> {
+        if self.data.is_empty() || !self.data.len().is_multiple_of(16) {
+            return Err(AesError::InvalidDataLength { length: self.data.len(), min: 16, multiple: 16 });
+        }
+        let inverse_roundkeys = InverseRoundKeys::new(roundkeys);
         let decrypted_data: Vec<u8> = self.data
         .chunks(16)
         .flat_map(|block| {
-            let aes_block = AESBlock::<EncryptedState>::new(block.to_vec());
-            aes_block.decrypt(roundkeys).grid
+            let aes_block = AESBlock::<EncryptedState>::from_grid(block.try_into().unwrap());
+            aes_block.decrypt_equivalent(&inverse_roundkeys).into_inner()
         })
         .collect();
         let padded_char: u8 = decrypted_data[decrypted_data.len() - 1];
-        let idx = decrypted_data.iter().rev().position(|&x| x != padded_char).unwrap_or(0);                
-        AESData {
+        let idx = decrypted_data.iter().rev().position(|&x| x != padded_char).unwrap_or(decrypted_data.len());                
+        Ok(AESData {
             data: decrypted_data[..decrypted_data.len() - idx].to_vec(),
             state: std::marker::PhantomData::<DecryptedState>
-        }
+        })
     }
 }
 
 impl<State> AESData<State> {
-    
-    const R_CON: &[u8] = &[0x01, 0x02, 0x04, 0x08, 0x10, 0x20, 0x40, 0x80, 0x1b, 0x36];
 
     ///
     /// Generates the roundkeys from the key.
     /// 
-    /// key: A vector of bytes used to generate the roundkeys.
+    /// key: A vector of 16, 24 or 32 bytes used to generate the roundkeys.
     /// 
-    /// result: A vector of roundkeys. Each roundkey is a vector of 16 bytes.
+    /// result: The roundkeys generated from the key or AesError::InvalidKeyLength
+    ///         if the key is not 16, 24 or 32 bytes.
     /// 
-    pub fn generate_roundkeys(key: &[u8]) -> Vec<Vec<u8>> {
-        let mut roundkeys: Vec<Vec<u8>> = vec![key.to_vec()];
-        let mut roundkey: Vec<u8> = key.to_vec();
-        for idx in 0..10 {
-            roundkey = AESData::<State>::generate_roundkey(&roundkey, idx);
-            roundkeys.push(roundkey.clone());
+    pub fn generate_roundkeys(key: &[u8]) -> Result<RoundKeys, AesError> {
+        match key.len() {
+            16 => Ok(KeySchedule128::new(key.try_into().unwrap()).roundkeys().clone()),
+            24 => Ok(KeySchedule192::new(key.try_into().unwrap()).roundkeys().clone()),
+            32 => Ok(KeySchedule256::new(key.try_into().unwrap()).roundkeys().clone()),
+            length => Err(AesError::InvalidKeyLength { length, min: 16, max: 32, multiple: 8 })
         }
-        roundkeys
     }
 
-    ///
-    /// Generates a roundkey from the previous roundkey.
-    /// 
-    /// roundkey: A vector of bytes to be exchanged.
-    /// iteration: The iteration of the roundkey.
-    /// 
-    /// result: A vector of with new roundkey.
-    /// 
-    fn generate_roundkey(roundkey: &Vec<u8>, iteration: usize) -> Vec<u8> { 
-        let mut result: Vec<u8> = vec![0; roundkey.len()];
-        let mut temp: Vec<u8> = vec![0; 4];
-        temp[..4].copy_from_slice(&roundkey[12..16]);
-        temp = AESData::<State>::rotate(&temp);
-        temp = AESData::<State>::sub_word(&temp);
-        temp[0] ^= AESData::<State>::R_CON[iteration];
-        for i in 0..4 {
-            temp[i] ^= roundkey[i];
-        }
-        result[..4].copy_from_slice(&temp[..4]);
-        for i in 4..16 {
-            result[i] = result[i - 4] ^ roundkey[i];
-        }
-        result
+}
+
+impl fmt::Debug for AESData<DecryptedState> {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("AESData<DecryptedState>").field(&Redacted(self.data.len())).finish()
     }
 
-    ///
-    /// Rotates the bytes in the word.
-    /// 
-    /// word: A vector of bytes to be rotated.
-    /// 
-    /// result: A vector of bytes with the rotated values.
-    /// 
-    fn rotate(word: &[u8]) -> Vec<u8> {
-        let mut result: Vec<u8> = vec![0; word.len()];
-        result[0] = word[1];
-        result[1] = word[2];
-        result[2] = word[3];
-        result[3] = word[0];
-        result
+}
+
+impl fmt::Debug for AESData<EncryptedState> {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "AESData<EncryptedState>({})", hex::encode(&self.data))
     }
 
-    ///
-    /// Substitutes each byte in the data with the corresponding byte in the s_box.
-    /// This is the same substitution as in the sub_bytes function, but it is used
-    /// for generating the roundkeys.
-    /// 
-    /// word: A vector of bytes to be exchanged..
-    /// 
-    /// result: A vector of bytes with the substituted values.
-    /// 
-    fn sub_word(word: &[u8]) -> Vec<u8> {
-        let mut result: Vec<u8> = vec![0; word.len()];
-        for i in 0..4 {            
-            result[i] = *AESBlock::S_BOX[word[i] as usize]
-        }
-        result
+}
+
+impl<State> Drop for AESData<State> {
+
+    fn drop(&mut self) {
+        self.data.zeroize();
     }
 
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "encrypt", feature = "decrypt"))]
 mod tests {
 
     use super::*;
 
+    ///
+    /// Runs a FIPS-197 known answer test through both encrypt and decrypt.
+    ///
+    fn known_answer(key: &[u8], plaintext: &[u8], ciphertext: &[u8]) {
+        let roundkeys: RoundKeys = AESData::<DecryptedState>::generate_roundkeys(key).unwrap();
+        let encrypted = AESBlock::<DecryptedState>::try_new(plaintext).unwrap().encrypt(&roundkeys);
+        assert_eq!(ciphertext, encrypted.grid);
+        let decrypted = AESBlock::<EncryptedState>::try_new(ciphertext).unwrap().decrypt(&roundkeys);
+        assert_eq!(plaintext, decrypted.grid);
+        let inverse_roundkeys = InverseRoundKeys::new(&roundkeys);
+        let decrypted = AESBlock::<EncryptedState>::try_new(ciphertext).unwrap().decrypt_equivalent(&inverse_roundkeys);
+        assert_eq!(plaintext, decrypted.grid);
+    }
+
     #[test]
     fn test_add_roundkey() {
-        let aes_block = AESBlock::<DecryptedState>::new(vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]);
+        let aes_block = AESBlock::<DecryptedState>::try_new(&[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]).unwrap();
         let expected_result: Vec<u8> = vec![0, 3, 6, 11, 8, 4, 5, 2, 15, 0, 1, 6, 3, 15, 13, 11];
         let roundkey: Vec<u8> = vec![0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4];
-        let grid: Vec<u8> = vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15];
-        let result: Vec<u8> = aes_block.add_roundkey(&grid, &roundkey);
+        let grid = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15];
+        let mut result = grid;
+        aes_block.add_roundkey(&mut result, &roundkey);
         assert_eq!(expected_result, result);
     }
 
+    #[test]
+    fn test_try_new() {
+        assert!(AESBlock::<DecryptedState>::try_new(&[0; 16]).is_ok());
+        assert!(AESBlock::<EncryptedState>::try_new(&[0; 16]).is_ok());
+        assert_eq!(Some(AesError::InvalidBlockLength { length: 3, min: 16, max: 16, multiple: 16 }), AESBlock::<DecryptedState>::try_new(&[0; 3]).err());
+        assert_eq!(Some(AesError::InvalidBlockLength { length: 300, min: 16, max: 16, multiple: 16 }), AESBlock::<EncryptedState>::try_new(&[0; 300]).err());
+        assert_eq!(Some(AesError::InvalidBlockLength { length: 0, min: 16, max: 16, multiple: 16 }), AESBlock::<DecryptedState>::try_new(&[]).err());
+    }
+
+    #[test]
+    fn test_conversions() {
+        let data: [u8; 16] = core::array::from_fn(|idx| idx as u8);
+        let block = AESBlock::<DecryptedState>::from(data);
+        assert_eq!(data, block.grid);
+        let block: AESBlock<EncryptedState> = data.as_slice().try_into().unwrap();
+        assert_eq!(data, <[u8; 16]>::from(block));
+        assert_eq!(Some(AesError::InvalidBlockLength { length: 15, min: 16, max: 16, multiple: 16 }), AESBlock::<DecryptedState>::try_from(&data[..15]).err());
+        let aes = Aes128::new(&[0; 16]);
+        let encrypted: [u8; 16] = aes.encrypt_block(&data.into()).into();
+        assert_eq!(data, <[u8; 16]>::from(aes.decrypt_block(&encrypted.into())));
+    }
+
+    #[test]
+    fn test_copy_eq() {
+        let block = AESBlock::<DecryptedState>::new([1; 16]);
+        let copy = block;
+        assert!(block == copy);
+        let mut other = [1; 16];
+        other[15] = 2;
+        assert!(block != AESBlock::<DecryptedState>::new(other));
+        let aes = Aes128::new(&[0; 16]);
+        assert_eq!(aes.encrypt_block(&block), aes.encrypt_block(&copy.clone()));
+    }
+
+    #[test]
+    fn test_accessors() {
+        let aes = Aes128::new(&[0x2b; 16]);
+        let encrypted = aes.encrypt_block(&AESBlock::<DecryptedState>::new([0x32; 16]));
+        let bytes = aes.encrypt_bytes(&[0x32; 16]);
+        assert_eq!(bytes, encrypted.as_bytes());
+        assert_eq!(bytes, encrypted.to_vec());
+        assert_eq!(bytes, encrypted.into_inner());
+        let decrypted = AESBlock::<DecryptedState>::new([7; 16]);
+        assert_eq!(&[7; 16], decrypted.as_bytes());
+        assert_eq!([7; 16], decrypted.into_inner());
+    }
+
+    #[test]
+    fn test_hex() {
+        let block = AESBlock::<DecryptedState>::from_hex("00112233445566778899AABBCCDDEEFF").unwrap();
+        assert_eq!([0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff], block.grid);
+        let aes = Aes128::new(&core::array::from_fn(|idx| idx as u8));
+        let encrypted = aes.encrypt_block(&block);
+        assert_eq!("69c4e0d86a7b0430d8cdb78070b4c55a", encrypted.to_hex());
+        assert_eq!("69c4e0d86a7b0430d8cdb78070b4c55a", encrypted.to_string());
+        assert_eq!(encrypted.grid, AESBlock::<EncryptedState>::from_hex(&encrypted.to_hex()).unwrap().grid);
+        assert_eq!(Some(AesError::InvalidHex), AESBlock::<DecryptedState>::from_hex("00112233445566778899aabbccddeefg").err());
+        assert_eq!(Some(AesError::InvalidHex), AESBlock::<DecryptedState>::from_hex("0").err());
+        assert_eq!(Some(AesError::InvalidBlockLength { length: 2, min: 16, max: 16, multiple: 16 }), AESBlock::<EncryptedState>::from_hex("0011").err());
+    }
+
+    #[test]
+    fn test_debug() {
+        let block = AESBlock::<DecryptedState>::new([0x41; 16]);
+        assert_eq!("AESBlock<DecryptedState>([REDACTED; 16])", format!("{:?}", block));
+        let encrypted = AESBlock::<EncryptedState>::from_hex("69c4e0d86a7b0430d8cdb78070b4c55a").unwrap();
+        assert_eq!("AESBlock<EncryptedState>(69c4e0d86a7b0430d8cdb78070b4c55a)", format!("{:?}", encrypted));
+        assert_eq!("AESData<DecryptedState>([REDACTED; 3])", format!("{:?}", AESData::<DecryptedState>::new(vec![0x41; 3])));
+        let roundkeys = AESData::<DecryptedState>::generate_roundkeys(&[0x41; 16]).unwrap();
+        assert_eq!("RoundKeys { rounds: 10, roundkeys: [REDACTED; 176] }", format!("{:?}", roundkeys));
+        assert!(!format!("{:?}", InverseRoundKeys::new(&roundkeys)).contains("41"));
+        assert_eq!("KeySchedule<32> { roundkeys: RoundKeys { rounds: 14, roundkeys: [REDACTED; 240] } }", format!("{:?}", KeySchedule256::new(&[0x41; 32])));
+    }
+
+    #[cfg(feature = "base64")]
+    #[test]
+    fn test_base64() {
+        let block = AESBlock::<EncryptedState>::from_hex("69c4e0d86a7b0430d8cdb78070b4c55a").unwrap();
+        assert_eq!("acTg2Gp7BDDYzbeAcLTFWg==", block.to_base64());
+        assert_eq!(block.grid, AESBlock::<EncryptedState>::from_base64("acTg2Gp7BDDYzbeAcLTFWg==").unwrap().grid);
+        assert_eq!(Some(AesError::InvalidBase64), AESBlock::<EncryptedState>::from_base64("acTg2Gp7BDDYzbeAcLTFWg=").err());
+        assert_eq!(Some(AesError::InvalidBlockLength { length: 3, min: 16, max: 16, multiple: 16 }), AESBlock::<EncryptedState>::from_base64("acTg").err());
+    }
+
     #[test]
     fn test_shift_row0() {
-        let aes_block: AESBlock = AESBlock::<DecryptedState>::new(vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]);
+        let aes_block: AESBlock = AESBlock::<DecryptedState>::try_new(&[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]).unwrap();
         let expected_result: Vec<u8> = vec![1, 2, 3, 4];
         let row: Vec<u8> = vec![1, 2, 3, 4];
-        let result = aes_block.shift_row(&row, &0);
+        let mut result = row.clone();
+        aes_block.shift_row(&mut result, &0);
         assert_eq!(expected_result, result);
     }
 
     #[test]
     fn test_shift_row1() {
-        let aes_block: AESBlock = AESBlock::<DecryptedState>::new(vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]);
+        let aes_block: AESBlock = AESBlock::<DecryptedState>::try_new(&[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]).unwrap();
         let expected_result = vec![2, 3, 4, 1];
         let row = vec![1, 2, 3, 4];
-        let result = aes_block.shift_row(&row, &1);
+        let mut result = row.clone();
+        aes_block.shift_row(&mut result, &1);
         assert_eq!(expected_result, result);
     }
 
     #[test]
     fn test_shift_row2() {
-        let aes_block: AESBlock = AESBlock::<DecryptedState>::new(vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]);
+        let aes_block: AESBlock = AESBlock::<DecryptedState>::try_new(&[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]).unwrap();
         let expected_result = vec![3, 4, 1, 2];
         let row = vec![1, 2, 3, 4];
-        let result = aes_block.shift_row(&row, &2);
+        let mut result = row.clone();
+        aes_block.shift_row(&mut result, &2);
         assert_eq!(expected_result, result);
     }
 
     #[test]
     fn test_shift_row3() {
-        let aes_block: AESBlock = AESBlock::<DecryptedState>::new(vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]);
+        let aes_block: AESBlock = AESBlock::<DecryptedState>::try_new(&[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]).unwrap();
         let expected_result = vec![4, 1, 2, 3];
         let row = vec![1, 2, 3, 4];
-        let result = aes_block.shift_row(&row, &3);
-        assert_eq!(expected_result, result);
-    }
-
-    #[test]
-    fn test_shift_grid() {
-        let aes_block: AESBlock = AESBlock::<DecryptedState>::new(vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]);
-        let expected_result: Vec<u8> = vec![0, 1, 2, 3, 5, 6, 7, 4, 10, 11, 8, 9, 15, 12, 13, 14];
-        let grid: Vec<u8> = vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15];
-        let result: Vec<u8> = aes_block.shift_grid(&grid);
-        assert_eq!(expected_result, result);
-    }
-
-    #[test]
-    fn test_shift_grid_reverse() {
-        let aes_block: AESBlock<EncryptedState> = AESBlock::<EncryptedState>::new(vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]);
-        let expected_result: Vec<u8> = vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15];
-        let grid: Vec<u8> = vec![0, 1, 2, 3, 5, 6, 7, 4, 10, 11, 8, 9, 15, 12, 13, 14];
-        let result: Vec<u8> = aes_block.shift_grid(&grid);
+        let mut result = row.clone();
+        aes_block.shift_row(&mut result, &3);
         assert_eq!(expected_result, result);
     }
 
     #[test]
     fn test_mix_column() {
-        let aes_block: AESBlock = AESBlock::<DecryptedState>::new(vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]);
+        let aes_block: AESBlock = AESBlock::<DecryptedState>::try_new(&[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]).unwrap();
         let expected_result: Vec<u8> = vec![1, 1, 1, 1];
         let data: &[u8] = &[1, 1, 1, 1];
-        let result: Vec<u8> = aes_block.mix_column(data);
+        let result = aes_block.mix_column(data);
         assert_eq!(expected_result, result);
     }
 
     #[test]
     fn test_inverse_mix_column() {
-        let aes_block: AESBlock<EncryptedState> = AESBlock::<EncryptedState>::new(vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]);
+        let aes_block: AESBlock<EncryptedState> = AESBlock::<EncryptedState>::try_new(&[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]).unwrap();
         let expected_result: Vec<u8> = vec![1, 1, 1, 1];
         let data: &[u8] = &[1, 1, 1, 1];
-        let result: Vec<u8> = aes_block.mix_column(data);
+        let result = aes_block.mix_column(data);
         assert_eq!(expected_result, result);
     }
 
     #[test]
     fn test_mix_column2() {
-        let aes_block: AESBlock = AESBlock::<DecryptedState>::new(vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]);
+        let aes_block: AESBlock = AESBlock::<DecryptedState>::try_new(&[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]).unwrap();
         let expected_result: Vec<u8> = vec![142, 77, 161, 188];
         let data: &[u8] = &[219, 19, 83, 69];        
-        let result: Vec<u8> = aes_block.mix_column(data);
+        let result = aes_block.mix_column(data);
         assert_eq!(expected_result, result);
     }
 
     #[test]
     fn test_inverse_mix_column2() {
-        let aes_block: AESBlock<EncryptedState> = AESBlock::<EncryptedState>::new(vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]);
+        let aes_block: AESBlock<EncryptedState> = AESBlock::<EncryptedState>::try_new(&[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]).unwrap();
         let expected_result: Vec<u8> = vec![219, 19, 83, 69];
         let data: &[u8] = &[142, 77, 161, 188];
-        let result: Vec<u8> = aes_block.mix_column(data);
+        let result = aes_block.mix_column(data);
         assert_eq!(expected_result, result);
     }
 
     #[test]
     fn test_mix_column3() {
-        let aes_block: AESBlock = AESBlock::<DecryptedState>::new(vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]);
+        let aes_block: AESBlock = AESBlock::<DecryptedState>::try_new(&[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]).unwrap();
         let expected_result: Vec<u8> = vec![159, 220, 88, 157];
         let data: &[u8] = &[242, 10, 34, 92];
-        let result: Vec<u8> = aes_block.mix_column(data);
+        let result = aes_block.mix_column(data);
         assert_eq!(expected_result, result);
     }
 
     #[test]
     fn test_inverse_mix_column3() {
-        let aes_block: AESBlock<EncryptedState> = AESBlock::<EncryptedState>::new(vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]);
+        let aes_block: AESBlock<EncryptedState> = AESBlock::<EncryptedState>::try_new(&[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]).unwrap();
         let expected_result: Vec<u8> = vec![242, 10, 34, 92];
         let data: &[u8] = &[159, 220, 88, 157];
-        let result: Vec<u8> = aes_block.mix_column(data);
+        let result = aes_block.mix_column(data);
         assert_eq!(expected_result, result);
     }
 
     #[test]
     fn test_mix_columns() {
-        let aes_block: AESBlock = AESBlock::<DecryptedState>::new(vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]);
+        let aes_block: AESBlock = AESBlock::<DecryptedState>::try_new(&[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]).unwrap();
         let expected_result: Vec<u8> = vec![1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1];
-        let grid: Vec<u8> = vec![1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1];
-        let result: Vec<u8> = aes_block.mix_columns(&grid);
+        let grid = [1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1];
+        let mut result = grid;
+        aes_block.mix_columns(&mut result);
         assert_eq!(expected_result, result);
     }
 
     #[test]
     fn test_inverse_mix_columns() {
-        let aes_block: AESBlock<EncryptedState> = AESBlock::<EncryptedState>::new(vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]);
+        let aes_block: AESBlock<EncryptedState> = AESBlock::<EncryptedState>::try_new(&[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]).unwrap();
         let expected_result: Vec<u8> = vec![1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1];
-        let grid: Vec<u8> = vec![1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1];
-        let result: Vec<u8> = aes_block.mix_columns(&grid);
+        let grid = [1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1];
+        let mut result = grid;
+        aes_block.mix_columns(&mut result);
         assert_eq!(expected_result, result);
     }
 
     #[test]
     fn test_mix_columns2() {
-        let aes_block: AESBlock = AESBlock::<DecryptedState>::new(vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]);
-        let expected_result: Vec<u8> = vec![142, 159, 1, 198, 77, 220, 1, 198, 161, 88, 1, 198, 188, 157, 1, 198];
-        let grid: Vec<u8> = vec![219, 242, 1, 198, 19, 10, 1, 198, 83, 34, 1, 198, 69, 92, 1, 198];
-        let result: Vec<u8> = aes_block.mix_columns(&grid);
+        let aes_block: AESBlock = AESBlock::<DecryptedState>::try_new(&[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]).unwrap();
+        let expected_result: Vec<u8> = vec![142, 77, 161, 188, 159, 220, 88, 157, 1, 1, 1, 1, 198, 198, 198, 198];
+        let grid = [219, 19, 83, 69, 242, 10, 34, 92, 1, 1, 1, 1, 198, 198, 198, 198];
+        let mut result = grid;
+        aes_block.mix_columns(&mut result);
         assert_eq!(expected_result, result);
     }
 
     #[test]
     fn test_inverse_mix_columns2() {
-        let aes_block: AESBlock<EncryptedState> = AESBlock::<EncryptedState>::new(vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]);
-        let expected_result: Vec<u8> = vec![219, 242, 1, 198, 19, 10, 1, 198, 83, 34, 1, 198, 69, 92, 1, 198];
-        let grid: Vec<u8> = vec![142, 159, 1, 198, 77, 220, 1, 198, 161, 88, 1, 198, 188, 157, 1, 198];
-        let result: Vec<u8> = aes_block.mix_columns(&grid);
+        let aes_block: AESBlock<EncryptedState> = AESBlock::<EncryptedState>::try_new(&[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]).unwrap();
+        let expected_result: Vec<u8> = vec![219, 19, 83, 69, 242, 10, 34, 92, 1, 1, 1, 1, 198, 198, 198, 198];
+        let grid = [142, 77, 161, 188, 159, 220, 88, 157, 1, 1, 1, 1, 198, 198, 198, 198];
+        let mut result = grid;
+        aes_block.mix_columns(&mut result);
         assert_eq!(expected_result, result);
     }
 
     #[test]
     fn test_sub_bytes() {
-        let aes_block: AESBlock = AESBlock::<DecryptedState>::new(vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]);
+        let aes_block: AESBlock = AESBlock::<DecryptedState>::try_new(&[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]).unwrap();
         let expected_result: Vec<u8> = vec![185, 137, 124, 180, 125, 103, 124, 180, 237, 147, 124, 180, 110, 74, 124, 180];
-        let grid: Vec<u8> = vec![219, 242, 1, 198, 19, 10, 1, 198, 83, 34, 1, 198, 69, 92, 1, 198];
-        let result: Vec<u8> = aes_block.sub_bytes(&grid);
+        let grid = [219, 242, 1, 198, 19, 10, 1, 198, 83, 34, 1, 198, 69, 92, 1, 198];
+        let mut result = grid;
+        aes_block.sub_bytes(&mut result);
         assert_eq!(expected_result, result);
     }
 
     #[test]
     fn test_sub_bytes_inverse() {
-        let aes_block: AESBlock<EncryptedState> = AESBlock::<EncryptedState>::new(vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]);
+        let aes_block: AESBlock<EncryptedState> = AESBlock::<EncryptedState>::try_new(&[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]).unwrap();
         let expected_result: Vec<u8> = vec![219, 242, 1, 198, 19, 10, 1, 198, 83, 34, 1, 198, 69, 92, 1, 198];
-        let grid: Vec<u8> = vec![185, 137, 124, 180, 125, 103, 124, 180, 237, 147, 124, 180, 110, 74, 124, 180];
-        let result: Vec<u8> = aes_block.sub_bytes(&grid);
+        let grid = [185, 137, 124, 180, 125, 103, 124, 180, 237, 147, 124, 180, 110, 74, 124, 180];
+        let mut result = grid;
+        aes_block.sub_bytes(&mut result);
         assert_eq!(expected_result, result);
     }
 
     #[test]
     fn test_encrypt() {
-        let aes_block: AESBlock = AESBlock::<DecryptedState>::new(vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]);
-        let roundkeys: Vec<Vec<u8>> = vec![
-            vec![0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
-            vec![0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
-            vec![0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
-            vec![0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
-            vec![0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4],
-            vec![0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
-            vec![0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
-            vec![0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
-            vec![0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
-            vec![0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4],
-            vec![0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4]
-            ];
+        let aes_block: AESBlock = AESBlock::<DecryptedState>::try_new(&[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]).unwrap();
+        let roundkeys: RoundKeys = RoundKeys::new(vec![
+            [0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
+            [0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
+            [0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
+            [0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
+            [0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4],
+            [0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
+            [0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
+            [0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
+            [0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
+            [0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4],
+            [0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4]
+            ]).unwrap();
         let result: AESBlock<EncryptedState> = aes_block.encrypt(&roundkeys);
-        let expected_result: Vec<u8> = vec![128, 249, 176, 188, 201, 213, 195, 110, 192, 161, 230, 165, 31, 182, 33, 44];
+        let expected_result: Vec<u8> = vec![59, 189, 55, 169, 149, 132, 179, 227, 233, 195, 229, 236, 75, 212, 91, 98];
         assert_eq!(expected_result, result.grid);
     }
 
     #[test]
     fn test_decrypt() {
-        let aes_block: AESBlock<EncryptedState> = AESBlock::<EncryptedState>::new(vec![128, 249, 176, 188, 201, 213, 195, 110, 192, 161, 230, 165, 31, 182, 33, 44]);
-        let roundkeys: Vec<Vec<u8>> = vec![
-            vec![0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
-            vec![0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
-            vec![0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
-            vec![0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
-            vec![0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4],
-            vec![0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
-            vec![0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
-            vec![0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
-            vec![0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
-            vec![0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4],
-            vec![0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4]
-            ];
+        let aes_block: AESBlock<EncryptedState> = AESBlock::<EncryptedState>::try_new(&[59, 189, 55, 169, 149, 132, 179, 227, 233, 195, 229, 236, 75, 212, 91, 98]).unwrap();
+        let roundkeys: RoundKeys = RoundKeys::new(vec![
+            [0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
+            [0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
+            [0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
+            [0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
+            [0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4],
+            [0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
+            [0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
+            [0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
+            [0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
+            [0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4],
+            [0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4]
+            ]).unwrap();
         let result: AESBlock<DecryptedState> = aes_block.decrypt(&roundkeys);
         let expected_result: Vec<u8> = vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15];
         assert_eq!(expected_result, result.grid);
@@ -785,22 +1176,22 @@ mod tests {
     fn encrypt_decrypt_file() {
         let bytes = &std::fs::read("testdata/testfile.in").unwrap();   
         let expected_result = String::from_utf8_lossy(bytes);     
-        let roundkeys: Vec<Vec<u8>> = vec![
-            vec![0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
-            vec![0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
-            vec![0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
-            vec![0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
-            vec![0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4],
-            vec![0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
-            vec![0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
-            vec![0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
-            vec![0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
-            vec![0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4],
-            vec![0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4] 
-            ];      
+        let roundkeys: RoundKeys = RoundKeys::new(vec![
+            [0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
+            [0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
+            [0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
+            [0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
+            [0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4],
+            [0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
+            [0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
+            [0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
+            [0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
+            [0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4],
+            [0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4] 
+            ]).unwrap();
         let aes_data = AESData::<DecryptedState>::new(bytes.to_vec());
         let encrypted = aes_data.encrypt(&roundkeys);
-        let decrypted = encrypted.decrypt(&roundkeys);
+        let decrypted = encrypted.decrypt(&roundkeys).unwrap();
         let result = String::from_utf8_lossy(&decrypted.data);
         assert_eq!(expected_result, result);
     }
@@ -809,22 +1200,22 @@ mod tests {
     fn encrypt_decrypt_file2() {
         let bytes = &std::fs::read("testdata/large.in").unwrap();   
         let expected_result = String::from_utf8_lossy(bytes);     
-        let roundkeys: Vec<Vec<u8>> = vec![
-            vec![0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
-            vec![1, 3, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
-            vec![2, 4, 4, 8, 12, 1, 3, 5, 7, 9, 11, 113, 15, 2, 3, 4], 
-            vec![3, 5, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
-            vec![4, 6, 4, 8, 12, 1, 3, 5, 7, 9, 11, 123, 15, 2, 3, 4],
-            vec![5, 7, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
-            vec![6, 8, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
-            vec![7, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 123, 15, 2, 3, 4], 
-            vec![8, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
-            vec![9, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 143, 15, 2, 3, 4],
-            vec![0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4] 
-            ];      
+        let roundkeys: RoundKeys = RoundKeys::new(vec![
+            [0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
+            [1, 3, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
+            [2, 4, 4, 8, 12, 1, 3, 5, 7, 9, 11, 113, 15, 2, 3, 4], 
+            [3, 5, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
+            [4, 6, 4, 8, 12, 1, 3, 5, 7, 9, 11, 123, 15, 2, 3, 4],
+            [5, 7, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
+            [6, 8, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
+            [7, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 123, 15, 2, 3, 4], 
+            [8, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
+            [9, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 143, 15, 2, 3, 4],
+            [0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4] 
+            ]).unwrap();
         let aes_data = AESData::<DecryptedState>::new(bytes.to_vec());
         let encrypted = aes_data.encrypt(&roundkeys);
-        let decrypted = encrypted.decrypt(&roundkeys);
+        let decrypted = encrypted.decrypt(&roundkeys).unwrap();
         let result = String::from_utf8_lossy(&decrypted.data);
         assert_eq!(expected_result, result);
     }
@@ -833,22 +1224,22 @@ mod tests {
     fn encrypt_decrypt_file3() {
         let bytes = &std::fs::read("testdata/binary.in").unwrap();   
         let expected_result = String::from_utf8_lossy(bytes);     
-        let roundkeys: Vec<Vec<u8>> = vec![
-            vec![0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
-            vec![1, 3, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
-            vec![2, 4, 4, 8, 12, 1, 3, 5, 7, 9, 11, 113, 15, 2, 3, 4], 
-            vec![3, 5, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
-            vec![4, 6, 4, 8, 12, 1, 3, 5, 7, 9, 11, 123, 15, 2, 3, 4],
-            vec![5, 7, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
-            vec![6, 8, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
-            vec![7, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 123, 15, 2, 3, 4], 
-            vec![8, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
-            vec![9, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 143, 15, 2, 3, 4],
-            vec![0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4] 
-            ];      
+        let roundkeys: RoundKeys = RoundKeys::new(vec![
+            [0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
+            [1, 3, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
+            [2, 4, 4, 8, 12, 1, 3, 5, 7, 9, 11, 113, 15, 2, 3, 4], 
+            [3, 5, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
+            [4, 6, 4, 8, 12, 1, 3, 5, 7, 9, 11, 123, 15, 2, 3, 4],
+            [5, 7, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
+            [6, 8, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
+            [7, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 123, 15, 2, 3, 4], 
+            [8, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4], 
+            [9, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 143, 15, 2, 3, 4],
+            [0, 2, 4, 8, 12, 1, 3, 5, 7, 9, 11, 13, 15, 2, 3, 4] 
+            ]).unwrap();
         let aes_data = AESData::<DecryptedState>::new(bytes.to_vec());
         let encrypted = aes_data.encrypt(&roundkeys);
-        let decrypted = encrypted.decrypt(&roundkeys);
+        let decrypted = encrypted.decrypt(&roundkeys).unwrap();
         let result = String::from_utf8_lossy(&decrypted.data);
         assert_eq!(expected_result, result);
     }
@@ -856,34 +1247,122 @@ mod tests {
     #[test]
     fn generate_roundkeys() {
         let init_key: Vec<u8> = vec![0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
-        let roundkeys = AESData::<EncryptedState>::generate_roundkeys(&init_key);
-        let expected_result: Vec<Vec<u8>> = vec![
-            vec![0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
-            vec![0x62, 0x63, 0x63, 0x63, 0x62, 0x63, 0x63, 0x63, 0x62, 0x63, 0x63, 0x63, 0x62, 0x63, 0x63, 0x63],
-            vec![0x9b, 0x98, 0x98, 0xc9, 0xf9, 0xfb, 0xfb, 0xaa, 0x9b, 0x98, 0x98, 0xc9, 0xf9, 0xfb, 0xfb, 0xaa],
-            vec![0x90, 0x97, 0x34, 0x50, 0x69, 0x6c, 0xcf, 0xfa, 0xf2, 0xf4, 0x57, 0x33, 0x0b, 0x0f, 0xac, 0x99],
-            vec![0xee, 0x06, 0xda, 0x7b, 0x87, 0x6a, 0x15, 0x81, 0x75, 0x9e, 0x42, 0xb2, 0x7e, 0x91, 0xee, 0x2b],
-            vec![0x7f, 0x2e, 0x2b, 0x88, 0xf8, 0x44, 0x3e, 0x09, 0x8d, 0xda, 0x7c, 0xbb, 0xf3, 0x4b, 0x92, 0x90],
-            vec![0xec, 0x61, 0x4b, 0x85, 0x14, 0x25, 0x75, 0x8c, 0x99, 0xff, 0x09, 0x37, 0x6a, 0xb4, 0x9b, 0xa7],
-            vec![0x21, 0x75, 0x17, 0x87, 0x35, 0x50, 0x62, 0x0b, 0xac, 0xaf, 0x6b, 0x3c, 0xc6, 0x1b, 0xf0, 0x9b],
-            vec![0x0e, 0xf9, 0x03, 0x33, 0x3b, 0xa9, 0x61, 0x38, 0x97, 0x06, 0x0a, 0x04, 0x51, 0x1d, 0xfa, 0x9f],
-            vec![0xb1, 0xd4, 0xd8, 0xe2, 0x8a, 0x7d, 0xb9, 0xda, 0x1d, 0x7b, 0xb3, 0xde, 0x4c, 0x66, 0x49, 0x41],
-            vec![0xb4, 0xef, 0x5b, 0xcb, 0x3e, 0x92, 0xe2, 0x11, 0x23, 0xe9, 0x51, 0xcf, 0x6f, 0x8f, 0x18, 0x8e]
+        let roundkeys = AESData::<EncryptedState>::generate_roundkeys(&init_key).unwrap();
+        let expected_result: Vec<[u8; 16]> = vec![
+            [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+            [0x62, 0x63, 0x63, 0x63, 0x62, 0x63, 0x63, 0x63, 0x62, 0x63, 0x63, 0x63, 0x62, 0x63, 0x63, 0x63],
+            [0x9b, 0x98, 0x98, 0xc9, 0xf9, 0xfb, 0xfb, 0xaa, 0x9b, 0x98, 0x98, 0xc9, 0xf9, 0xfb, 0xfb, 0xaa],
+            [0x90, 0x97, 0x34, 0x50, 0x69, 0x6c, 0xcf, 0xfa, 0xf2, 0xf4, 0x57, 0x33, 0x0b, 0x0f, 0xac, 0x99],
+            [0xee, 0x06, 0xda, 0x7b, 0x87, 0x6a, 0x15, 0x81, 0x75, 0x9e, 0x42, 0xb2, 0x7e, 0x91, 0xee, 0x2b],
+            [0x7f, 0x2e, 0x2b, 0x88, 0xf8, 0x44, 0x3e, 0x09, 0x8d, 0xda, 0x7c, 0xbb, 0xf3, 0x4b, 0x92, 0x90],
+            [0xec, 0x61, 0x4b, 0x85, 0x14, 0x25, 0x75, 0x8c, 0x99, 0xff, 0x09, 0x37, 0x6a, 0xb4, 0x9b, 0xa7],
+            [0x21, 0x75, 0x17, 0x87, 0x35, 0x50, 0x62, 0x0b, 0xac, 0xaf, 0x6b, 0x3c, 0xc6, 0x1b, 0xf0, 0x9b],
+            [0x0e, 0xf9, 0x03, 0x33, 0x3b, 0xa9, 0x61, 0x38, 0x97, 0x06, 0x0a, 0x04, 0x51, 0x1d, 0xfa, 0x9f],
+            [0xb1, 0xd4, 0xd8, 0xe2, 0x8a, 0x7d, 0xb9, 0xda, 0x1d, 0x7b, 0xb3, 0xde, 0x4c, 0x66, 0x49, 0x41],
+            [0xb4, 0xef, 0x5b, 0xcb, 0x3e, 0x92, 0xe2, 0x11, 0x23, 0xe9, 0x51, 0xcf, 0x6f, 0x8f, 0x18, 0x8e]
             ];
-        assert_eq!(expected_result, roundkeys);
+        assert_eq!(expected_result, roundkeys.as_slice());
     }
 
     #[test]
     fn full_crypt_decrypt() {
         let data = vec![0x00, 0x00, 0x01, 0x01, 0x03, 0x03, 0x07, 0x07, 0x0f, 0x0f, 0x1f, 0x1f, 0x3f, 0x3f, 0x7f, 0x7f];
         let init_key: Vec<u8> = vec![0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
-        let roundkeys = AESData::<EncryptedState>::generate_roundkeys(&init_key);
+        let roundkeys = AESData::<EncryptedState>::generate_roundkeys(&init_key).unwrap();
         let aes_data = AESData::<DecryptedState>::new(data.clone());
         let encrypted = aes_data.encrypt(&roundkeys);
-        let decrypted = encrypted.decrypt(&roundkeys);
+        let decrypted = encrypted.decrypt(&roundkeys).unwrap();
         assert_eq!(data, decrypted.data);    
     }
 
+    #[test]
+    fn fips197_appendix_b() {
+        known_answer(
+            &[0x2b, 0x7e, 0x15, 0x16, 0x28, 0xae, 0xd2, 0xa6, 0xab, 0xf7, 0x15, 0x88, 0x09, 0xcf, 0x4f, 0x3c],
+            &[0x32, 0x43, 0xf6, 0xa8, 0x88, 0x5a, 0x30, 0x8d, 0x31, 0x31, 0x98, 0xa2, 0xe0, 0x37, 0x07, 0x34],
+            &[0x39, 0x25, 0x84, 0x1d, 0x02, 0xdc, 0x09, 0xfb, 0xdc, 0x11, 0x85, 0x97, 0x19, 0x6a, 0x0b, 0x32]);
+    }
+
+    #[test]
+    fn fips197_appendix_c1() {
+        known_answer(
+            &[0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f],
+            &[0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff],
+            &[0x69, 0xc4, 0xe0, 0xd8, 0x6a, 0x7b, 0x04, 0x30, 0xd8, 0xcd, 0xb7, 0x80, 0x70, 0xb4, 0xc5, 0x5a]);
+    }
+
+    #[test]
+    fn fips197_appendix_c2() {
+        known_answer(
+            &[0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f,
+              0x10, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17],
+            &[0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff],
+            &[0xdd, 0xa9, 0x7c, 0xa4, 0x86, 0x4c, 0xdf, 0xe0, 0x6e, 0xaf, 0x70, 0xa0, 0xec, 0x0d, 0x71, 0x91]);
+    }
+
+    #[test]
+    fn fips197_appendix_c3() {
+        known_answer(
+            &[0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f,
+              0x10, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17, 0x18, 0x19, 0x1a, 0x1b, 0x1c, 0x1d, 0x1e, 0x1f],
+            &[0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff],
+            &[0x8e, 0xa2, 0xb7, 0xca, 0x51, 0x67, 0x45, 0xbf, 0xea, 0xfc, 0x49, 0x90, 0x4b, 0x49, 0x60, 0x89]);
+    }
+
+    #[test]
+    fn sp800_38a_ecb_aes128() {
+        known_answer(
+            &[0x2b, 0x7e, 0x15, 0x16, 0x28, 0xae, 0xd2, 0xa6, 0xab, 0xf7, 0x15, 0x88, 0x09, 0xcf, 0x4f, 0x3c],
+            &[0x6b, 0xc1, 0xbe, 0xe2, 0x2e, 0x40, 0x9f, 0x96, 0xe9, 0x3d, 0x7e, 0x11, 0x73, 0x93, 0x17, 0x2a],
+            &[0x3a, 0xd7, 0x7b, 0xb4, 0x0d, 0x7a, 0x36, 0x60, 0xa8, 0x9e, 0xca, 0xf3, 0x24, 0x66, 0xef, 0x97]);
+    }
+
+    #[test]
+    fn encrypt_decrypt_empty() {
+        let roundkeys = AESData::<EncryptedState>::generate_roundkeys(&[0; 16]).unwrap();
+        let encrypted = AESData::<DecryptedState>::new(vec![]).encrypt(&roundkeys);
+        let decrypted = encrypted.decrypt(&roundkeys).unwrap();
+        assert!(decrypted.data.is_empty());
+    }
+
+    #[test]
+    fn invalid_inputs() {
+        assert_eq!(Some(AesError::InvalidKeyLength { length: 15, min: 16, max: 32, multiple: 8 }), AESData::<EncryptedState>::generate_roundkeys(&[0; 15]).err());
+        let roundkeys = AESData::<EncryptedState>::generate_roundkeys(&[0; 16]).unwrap();
+        assert_eq!(Some(AesError::InvalidDataLength { length: 17, min: 16, multiple: 16 }), AESData::<EncryptedState>::new(vec![0; 17]).decrypt(&roundkeys).err());
+        assert_eq!(Some(AesError::InvalidDataLength { length: 0, min: 16, multiple: 16 }), AESData::<EncryptedState>::new(vec![]).decrypt(&roundkeys).err());
+    }
+
+    #[cfg(feature = "insecure-research")]
+    #[test]
+    fn reduced_round_encrypt_decrypt() {
+        let roundkeys = AESData::<EncryptedState>::generate_roundkeys(&[0x2b; 16]).unwrap();
+        let block = AESBlock::<DecryptedState>::try_new(&[0x32; 16]).unwrap();
+        let full = block.encrypt(&roundkeys);
+        for rounds in 1..=10 {
+            let reduced_roundkeys = roundkeys.with_rounds(rounds).unwrap();
+            let encrypted = block.encrypt(&reduced_roundkeys);
+            assert_eq!(rounds == 10, encrypted.grid == full.grid);
+            let decrypted = encrypted.decrypt(&reduced_roundkeys);
+            assert_eq!(block.grid, decrypted.grid);
+            let decrypted = encrypted.decrypt_equivalent(&InverseRoundKeys::new(&reduced_roundkeys));
+            assert_eq!(block.grid, decrypted.grid);
+        }
+    }
+
+    #[test]
+    fn full_crypt_decrypt_192_256() {
+        let data = vec![0x00, 0x00, 0x01, 0x01, 0x03, 0x03, 0x07, 0x07, 0x0f, 0x0f, 0x1f, 0x1f, 0x3f, 0x3f, 0x7f, 0x7f];
+        for init_key in [vec![0x01; 24], vec![0x02; 32]] {
+            let roundkeys = AESData::<EncryptedState>::generate_roundkeys(&init_key).unwrap();
+            assert_eq!(init_key.len() / 4 + 6, roundkeys.rounds());
+            let aes_data = AESData::<DecryptedState>::new(data.clone());
+            let encrypted = aes_data.encrypt(&roundkeys);
+            let decrypted = encrypted.decrypt(&roundkeys).unwrap();
+            assert_eq!(data, decrypted.data);
+        }
+    }
+
 }
 
 
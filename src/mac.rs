@@ -0,0 +1,155 @@
+#[cfg(feature = "encrypt")]
+use crate::zeroize::Zeroize;
+use crate::AesError;
+
+///
+/// Mac is the incremental interface of the message authentication codes. The 
+/// data is given in chunks of any size with update, and finalize gives the mac
+/// of all the chunks as if they were given at once.
+/// 
+pub trait Mac {
+
+    ///
+    /// The mac, or a result with the mac for macs that can fail.
+    /// 
+    type Output;
+
+    ///
+    /// Adds the next chunk of the data.
+    /// 
+    /// data: The chunk, of any length.
+    /// 
+    fn update(&mut self, data: &[u8]);
+
+    ///
+    /// Calculates the mac of all the data added.
+    /// 
+    /// result: The mac.
+    /// 
+    fn finalize(self) -> Self::Output;
+
+    ///
+    /// Calculates the mac of all the data added and compares it with the expected
+    /// mac in constant time.
+    /// 
+    /// expected: The mac received with the data.
+    /// 
+    /// result: Ok if the macs are equal or AesError::AuthenticationFailed.
+    /// 
+    fn verify(self, expected: &[u8]) -> Result<(), AesError> where Self: Sized, Self::Output: AsRef<[u8]> {
+        verify_tag(expected, self.finalize().as_ref())
+    }
+
+}
+
+///
+/// Compares a received tag or mac with the computed one in constant time. The 
+/// time taken depends only on the lengths, not on where the tags differ.
+/// 
+/// expected: The tag received with the data.
+/// computed: The tag calculated from the data.
+/// 
+/// result: Ok if the tags are equal or AesError::AuthenticationFailed if they 
+///         differ or have different lengths.
+/// 
+pub fn verify_tag(expected: &[u8], computed: &[u8]) -> Result<(), AesError> {
+    if expected.len() != computed.len() {
+        return Err(AesError::AuthenticationFailed);
+    }
+    let difference = expected.iter().zip(computed).fold(0, |difference, (a, b)| difference | (a ^ b));
+    if std::hint::black_box(difference) != 0 {
+        return Err(AesError::AuthenticationFailed);
+    }
+    Ok(())
+}
+
+///
+/// BlockBuffer splits chunks of data into blocks for the macs. The last block 
+/// is always held back, even if it is full, since most macs process the last 
+/// block differently.
+/// 
+#[cfg(feature = "encrypt")]
+pub(crate) struct BlockBuffer {
+    buffer: [u8; 16],
+    length: usize
+}
+
+#[cfg(feature = "encrypt")]
+impl BlockBuffer {
+
+    ///
+    /// Creates a new empty BlockBuffer.
+    /// 
+    /// result: A BlockBuffer without any data.
+    /// 
+    pub(crate) fn new() -> Self {
+        Self { buffer: [0; 16], length: 0 }
+    }
+
+    ///
+    /// Adds the data and processes every block that is followed by more data.
+    /// 
+    /// data: The chunk of data.
+    /// process: Called with every full block that is not the last.
+    /// 
+    pub(crate) fn update(&mut self, mut data: &[u8], mut process: impl FnMut(&[u8; 16])) {
+        while !data.is_empty() {
+            if self.length == 16 {
+                process(&self.buffer);
+                self.length = 0;
+            }
+            let count = data.len().min(16 - self.length);
+            self.buffer[self.length..self.length + count].copy_from_slice(&data[..count]);
+            self.length += count;
+            data = &data[count..];
+        }
+    }
+
+    ///
+    /// The last block held back.
+    /// 
+    /// result: The last 0 to 16 bytes of the data.
+    /// 
+    pub(crate) fn last(&self) -> &[u8] {
+        &self.buffer[..self.length]
+    }
+
+}
+
+#[cfg(feature = "encrypt")]
+impl Drop for BlockBuffer {
+
+    fn drop(&mut self) {
+        self.buffer.zeroize();
+    }
+
+}
+
+#[cfg(all(test, feature = "encrypt", feature = "decrypt"))]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_verify_tag() {
+        assert_eq!(Ok(()), verify_tag(&[1, 2, 3], &[1, 2, 3]));
+        assert_eq!(Ok(()), verify_tag(&[], &[]));
+        assert_eq!(Err(AesError::AuthenticationFailed), verify_tag(&[1, 2, 3], &[1, 2, 4]));
+        assert_eq!(Err(AesError::AuthenticationFailed), verify_tag(&[0, 2, 3], &[1, 2, 3]));
+        assert_eq!(Err(AesError::AuthenticationFailed), verify_tag(&[1, 2], &[1, 2, 3]));
+    }
+
+    #[test]
+    fn test_block_buffer() {
+        let mut buffer = BlockBuffer::new();
+        let mut blocks = Vec::new();
+        buffer.update(&[1; 10], |block| blocks.push(*block));
+        buffer.update(&[2; 22], |block| blocks.push(*block));
+        assert_eq!(1, blocks.len());
+        assert_eq!(&[2; 16], buffer.last());
+        buffer.update(&[3], |block| blocks.push(*block));
+        assert_eq!(vec![[[1; 10].as_slice(), &[2; 6]].concat().try_into().unwrap(), [2; 16]], blocks);
+        assert_eq!(&[3], buffer.last());
+    }
+
+}
@@ -0,0 +1,248 @@
+//!
+//! Locking key material in memory, so it is never written to a swap file. The 
+//! pages holding the key are locked with mlock on unix and VirtualLock on windows.
+//! On other platforms locking fails with AesError::MemoryLockFailed.
+//! 
+//! LockedKey holds a key and LockedAes a cipher with its roundkeys or key in 
+//! locked memory. Both are boxed, so the locked memory does not move, and the 
+//! memory is wiped before it is unlocked.
+//! 
+//! The operating system locks whole pages and does not count the locks, so 
+//! unlocking a buffer also unlocks other buffers sharing its pages. The number of
+//! locked pages is limited, for example by RLIMIT_MEMLOCK on linux. The cipher 
+//! is created before it is locked, so the roundkeys are unlocked for a moment.
+//! 
+
+use std::boxed::Box;
+use std::fmt;
+use std::mem::ManuallyDrop;
+use std::ops::Deref;
+use std::vec::Vec;
+
+use crate::random::fill_random;
+use crate::zeroize::Zeroize;
+use crate::{Aes, AesError, SecretKey};
+
+#[cfg(unix)]
+extern "C" {
+    fn mlock(addr: *const std::ffi::c_void, len: usize) -> std::ffi::c_int;
+    fn munlock(addr: *const std::ffi::c_void, len: usize) -> std::ffi::c_int;
+}
+
+#[cfg(windows)]
+#[link(name = "kernel32")]
+extern "system" {
+    fn VirtualLock(address: *const std::ffi::c_void, size: usize) -> i32;
+    fn VirtualUnlock(address: *const std::ffi::c_void, size: usize) -> i32;
+}
+
+///
+/// Locks the pages of the memory range.
+/// 
+/// result: Ok or AesError::MemoryLockFailed if the operating system refused.
+/// 
+fn lock(address: usize, length: usize) -> Result<(), AesError> {
+    if length == 0 {
+        return Ok(());
+    }
+    // The range is memory owned by the caller, the calls only change the paging of it.
+    #[cfg(unix)]
+    let locked = unsafe { mlock(address as *const std::ffi::c_void, length) == 0 };
+    #[cfg(windows)]
+    let locked = unsafe { VirtualLock(address as *const std::ffi::c_void, length) != 0 };
+    #[cfg(not(any(unix, windows)))]
+    let locked = false;
+    if !locked {
+        return Err(AesError::MemoryLockFailed);
+    }
+    Ok(())
+}
+
+///
+/// Unlocks the pages of the memory range. Failing to unlock is ignored, as the 
+/// memory is already wiped.
+/// 
+fn unlock(address: usize, length: usize) {
+    if length == 0 {
+        return;
+    }
+    // The range was locked by lock, the calls only change the paging of it.
+    #[cfg(unix)]
+    unsafe { munlock(address as *const std::ffi::c_void, length); }
+    #[cfg(windows)]
+    unsafe { VirtualUnlock(address as *const std::ffi::c_void, length); }
+}
+
+///
+/// Locks all the ranges, unlocking the ones already locked if one fails.
+/// 
+fn lock_all(ranges: &[(usize, usize)]) -> Result<(), AesError> {
+    for (index, &(address, length)) in ranges.iter().enumerate() {
+        if let Err(error) = lock(address, length) {
+            ranges[..index].iter().for_each(|&(address, length)| unlock(address, length));
+            return Err(error);
+        }
+    }
+    Ok(())
+}
+
+///
+/// LockedKey is a key in locked memory. Like SecretKey it can not be printed and
+/// is wiped when dropped.
+/// 
+pub struct LockedKey<const KEY_BYTES: usize = 16> {
+    key: Box<[u8; KEY_BYTES]>
+}
+
+impl<const KEY_BYTES: usize> LockedKey<KEY_BYTES> {
+
+    ///
+    /// Copies the key into locked memory.
+    /// 
+    /// key: The key.
+    /// 
+    /// result: A new LockedKey or AesError::MemoryLockFailed if the memory could 
+    ///         not be locked.
+    /// 
+    pub fn new(key: &SecretKey<KEY_BYTES>) -> Result<Self, AesError> {
+        let mut locked = Self::zeroed()?;
+        locked.key.copy_from_slice(key.expose_secret());
+        Ok(locked)
+    }
+
+    ///
+    /// Generates a new random key directly in locked memory.
+    /// 
+    /// result: A new LockedKey, AesError::MemoryLockFailed if the memory could not 
+    ///         be locked or AesError::RandomUnavailable if no key could be generated.
+    /// 
+    pub fn generate() -> Result<Self, AesError> {
+        let mut locked = Self::zeroed()?;
+        fill_random(&mut locked.key[..])?;
+        Ok(locked)
+    }
+
+    ///
+    /// Returns the bytes of the key.
+    /// 
+    /// result: The key.
+    /// 
+    pub fn expose_secret(&self) -> &[u8; KEY_BYTES] {
+        &self.key
+    }
+
+    ///
+    /// Allocates and locks a key of zeros.
+    /// 
+    fn zeroed() -> Result<Self, AesError> {
+        let key = Box::new([0; KEY_BYTES]);
+        lock(key.as_ptr() as usize, KEY_BYTES)?;
+        Ok(Self { key })
+    }
+
+}
+
+impl<const KEY_BYTES: usize> fmt::Debug for LockedKey<KEY_BYTES> {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "LockedKey<{}>([REDACTED])", KEY_BYTES)
+    }
+
+}
+
+impl<const KEY_BYTES: usize> Drop for LockedKey<KEY_BYTES> {
+
+    fn drop(&mut self) {
+        self.key.zeroize();
+        unlock(self.key.as_ptr() as usize, KEY_BYTES);
+    }
+
+}
+
+///
+/// LockedAes is a cipher with the key or roundkeys in locked memory. It 
+/// dereferences to Aes, so it can be passed to all the modes.
+/// 
+pub struct LockedAes<const KEY_BYTES: usize = 16> {
+    cipher: ManuallyDrop<Box<Aes<KEY_BYTES>>>,
+    ranges: Vec<(usize, usize)>
+}
+
+impl<const KEY_BYTES: usize> LockedAes<KEY_BYTES> {
+
+    ///
+    /// Creates the cipher and locks its key or roundkeys.
+    /// 
+    /// cipher: The cipher to lock.
+    /// 
+    /// result: A new LockedAes or AesError::MemoryLockFailed if the memory could 
+    ///         not be locked.
+    /// 
+    pub fn new(cipher: Aes<KEY_BYTES>) -> Result<Self, AesError> {
+        let cipher = Box::new(cipher);
+        let ranges: Vec<(usize, usize)> = cipher.secret_buffers().iter().map(|buffer| (buffer.as_ptr() as usize, buffer.len())).collect();
+        lock_all(&ranges)?;
+        Ok(Self { cipher: ManuallyDrop::new(cipher), ranges })
+    }
+
+    ///
+    /// Creates a cipher with precomputed roundkeys from a locked key.
+    /// 
+    /// key: The key.
+    /// 
+    /// result: A new LockedAes or AesError::MemoryLockFailed if the memory could 
+    ///         not be locked.
+    /// 
+    pub fn from_key(key: &LockedKey<KEY_BYTES>) -> Result<Self, AesError> {
+        Self::new(Aes::new(key.expose_secret()))
+    }
+
+}
+
+impl<const KEY_BYTES: usize> Deref for LockedAes<KEY_BYTES> {
+
+    type Target = Aes<KEY_BYTES>;
+
+    fn deref(&self) -> &Aes<KEY_BYTES> {
+        &self.cipher
+    }
+
+}
+
+impl<const KEY_BYTES: usize> Drop for LockedAes<KEY_BYTES> {
+
+    fn drop(&mut self) {
+        // The cipher wipes its buffers when dropped, before they are unlocked.
+        // It is not used again, the field is only dropped here.
+        unsafe { ManuallyDrop::drop(&mut self.cipher) };
+        self.ranges.iter().for_each(|&(address, length)| unlock(address, length));
+    }
+
+}
+
+#[cfg(all(test, feature = "encrypt", feature = "decrypt"))]
+mod tests {
+
+    use super::*;
+    use crate::test_util::hex;
+    use crate::{ecb, KeyScheduleMode};
+
+    #[test]
+    fn test_locked_key() {
+        let key = LockedKey::new(&SecretKey::new([0x41; 16])).unwrap();
+        assert_eq!(&[0x41; 16], key.expose_secret());
+        assert_eq!("LockedKey<16>([REDACTED])", format!("{:?}", key));
+        let generated = LockedKey::<32>::generate().unwrap();
+        assert_ne!(&[0; 32], generated.expose_secret());
+    }
+
+    #[test]
+    fn test_locked_aes() {
+        let key = LockedKey::new(&SecretKey::<16>::from_slice(&hex("000102030405060708090a0b0c0d0e0f")).unwrap()).unwrap();
+        let cipher = LockedAes::from_key(&key).unwrap();
+        assert_eq!(hex("69c4e0d86a7b0430d8cdb78070b4c55a"), ecb::encrypt(&cipher, &hex("00112233445566778899aabbccddeeff")).unwrap());
+        let cipher = LockedAes::new(Aes::<16>::with_mode(key.expose_secret(), KeyScheduleMode::OnTheFly)).unwrap();
+        assert_eq!(hex("69c4e0d86a7b0430d8cdb78070b4c55a"), ecb::encrypt(&cipher, &hex("00112233445566778899aabbccddeeff")).unwrap());
+    }
+
+}
@@ -0,0 +1,91 @@
+use crate::random::fill_random;
+use crate::AesError;
+
+///
+/// Nonce is a nonce or iv of N bytes, by default the 12 byte nonce of GCM. A 
+/// nonce is not secret, but it must never be reused with the same key, so 
+/// generating it with the randomness of the operating system is the safe default
+/// when no counter is kept.
+/// 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Nonce<const N: usize = 12> {
+    nonce: [u8; N]
+}
+
+impl<const N: usize> Nonce<N> {
+
+    ///
+    /// Creates a new Nonce from the bytes.
+    /// 
+    /// nonce: The nonce.
+    /// 
+    /// result: A new Nonce.
+    /// 
+    pub fn new(nonce: [u8; N]) -> Self {
+        Self { nonce }
+    }
+
+    ///
+    /// Generates a new random nonce with the randomness of the operating system.
+    /// Random 12 byte nonces should not be used for more than 2^32 messages with
+    /// the same key.
+    /// 
+    /// result: A new Nonce or AesError::RandomUnavailable if the operating system 
+    ///         could not provide the bytes.
+    /// 
+    pub fn generate() -> Result<Self, AesError> {
+        let mut nonce = [0; N];
+        fill_random(&mut nonce)?;
+        Ok(Self { nonce })
+    }
+
+    ///
+    /// Returns the bytes of the nonce.
+    /// 
+    /// result: The nonce.
+    /// 
+    pub fn as_bytes(&self) -> &[u8; N] {
+        &self.nonce
+    }
+
+}
+
+impl<const N: usize> From<[u8; N]> for Nonce<N> {
+
+    fn from(nonce: [u8; N]) -> Self {
+        Self::new(nonce)
+    }
+
+}
+
+impl<const N: usize> AsRef<[u8]> for Nonce<N> {
+
+    fn as_ref(&self) -> &[u8] {
+        &self.nonce
+    }
+
+}
+
+#[cfg(all(test, feature = "encrypt", feature = "decrypt"))]
+mod tests {
+
+    use super::*;
+    use crate::gcm::Gcm;
+    use crate::SecretKey;
+
+    #[test]
+    fn test_generate() {
+        assert_ne!(Nonce::<12>::generate().unwrap(), Nonce::<12>::generate().unwrap());
+        assert_eq!(24, Nonce::<24>::generate().unwrap().as_bytes().len());
+    }
+
+    #[test]
+    fn test_with_gcm() {
+        let key = SecretKey::<32>::generate().unwrap();
+        let gcm = Gcm::new(key.expose_secret(), 16).unwrap();
+        let nonce = Nonce::<12>::generate().unwrap();
+        let ciphertext = gcm.encrypt(nonce.as_ref(), &[], b"data").unwrap();
+        assert_eq!(b"data".to_vec(), gcm.decrypt(nonce.as_ref(), &[], &ciphertext).unwrap());
+    }
+
+}
@@ -0,0 +1,260 @@
+//!
+//! Detection of nonce reuse. A NonceTracker wraps an AEAD and records every nonce
+//! used to encrypt, so encrypting twice with the same nonce under the key fails 
+//! with AesError::NonceReused instead of breaking the confidentiality and 
+//! authenticity of the messages. Decryption is passed through without checks.
+//! 
+//! The nonces are recorded in an exact set, which grows with every message, or in
+//! a bloom filter of fixed size. The bloom filter never misses a reused nonce, but
+//! may reject a new nonce with the false positive rate it was sized for.
+//! 
+//! The tracker only knows the nonces it has seen, it does not survive a restart
+//! and does not see other instances using the same key.
+//! 
+
+use std::collections::hash_map::{DefaultHasher, RandomState};
+use std::collections::HashSet;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::sync::{Mutex, PoisonError};
+use std::vec::Vec;
+
+use crate::{Aead, AesError};
+
+///
+/// The recorded nonces.
+/// 
+enum NonceSet {
+    Exact(HashSet<Vec<u8>>),
+    Bloom { bits: Vec<u64>, hashes: u32, seed: u64 }
+}
+
+impl NonceSet {
+
+    ///
+    /// Records the nonce.
+    /// 
+    /// result: false if the nonce was already recorded, or may have been for a 
+    ///         bloom filter.
+    /// 
+    fn insert(&mut self, nonce: &[u8]) -> bool {
+        match self {
+            NonceSet::Exact(nonces) => nonces.insert(nonce.to_vec()),
+            NonceSet::Bloom { bits, hashes, seed } => {
+                let size = bits.len() as u64 * 64;
+                let first = bloom_hash(*seed, 0, nonce);
+                let second = bloom_hash(*seed, 1, nonce) | 1;
+                let mut new = false;
+                for index in 0..*hashes as u64 {
+                    let bit = first.wrapping_add(index.wrapping_mul(second)) % size;
+                    let word = &mut bits[(bit / 64) as usize];
+                    new |= *word & (1 << (bit % 64)) == 0;
+                    *word |= 1 << (bit % 64);
+                }
+                new
+            }
+        }
+    }
+
+}
+
+///
+/// Hashes the nonce for the bloom filter. The seed is random for every filter, so 
+/// the bits set for a nonce cannot be predicted.
+/// 
+/// seed: The seed of the filter.
+/// index: 0 or 1 for the two hashes combined into the bit positions.
+/// nonce: The nonce.
+/// 
+/// result: The hash.
+/// 
+fn bloom_hash(seed: u64, index: u8, nonce: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    (seed, index, nonce).hash(&mut hasher);
+    hasher.finish()
+}
+
+///
+/// NonceTracker is an AEAD that rejects encryption with a nonce already used. It 
+/// can be shared between threads if the AEAD can.
+/// 
+pub struct NonceTracker<A: Aead> {
+    cipher: A,
+    nonces: Mutex<NonceSet>
+}
+
+impl<A: Aead> NonceTracker<A> {
+
+    ///
+    /// Creates a new NonceTracker that records the nonces in an exact set.
+    /// 
+    /// cipher: The AEAD to wrap.
+    /// 
+    /// result: A new NonceTracker without any nonces.
+    /// 
+    pub fn new(cipher: A) -> Self {
+        Self { cipher, nonces: Mutex::new(NonceSet::Exact(HashSet::new())) }
+    }
+
+    ///
+    /// Creates a new NonceTracker that records the nonces in a bloom filter sized
+    /// for the number of messages and false positive rate.
+    /// 
+    /// cipher: The AEAD to wrap.
+    /// capacity: The expected number of messages, at least 1.
+    /// false_positive_rate: The rate of new nonces rejected after capacity 
+    ///                      messages, between 0 and 1.
+    /// 
+    /// result: A new NonceTracker or AesError::InvalidTrackerParameters if the 
+    ///         capacity or the false positive rate is not valid.
+    /// 
+    pub fn with_bloom_filter(cipher: A, capacity: usize, false_positive_rate: f64) -> Result<Self, AesError> {
+        Self::with_bloom_seed(cipher, capacity, false_positive_rate, RandomState::new().hash_one(0u8))
+    }
+
+    ///
+    /// Creates a new NonceTracker with a bloom filter hashing with the seed.
+    /// 
+    fn with_bloom_seed(cipher: A, capacity: usize, false_positive_rate: f64, seed: u64) -> Result<Self, AesError> {
+        if capacity == 0 || !(false_positive_rate > 0.0 && false_positive_rate < 1.0) {
+            return Err(AesError::InvalidTrackerParameters);
+        }
+        let ln2 = std::f64::consts::LN_2;
+        let size = (-(capacity as f64) * false_positive_rate.ln() / (ln2 * ln2)).ceil().max(64.0);
+        let words = (size as usize).div_ceil(64);
+        let hashes = ((words * 64) as f64 / capacity as f64 * ln2).round().max(1.0) as u32;
+        let nonces = NonceSet::Bloom { bits: vec![0; words], hashes, seed };
+        Ok(Self { cipher, nonces: Mutex::new(nonces) })
+    }
+
+    ///
+    /// Returns the wrapped AEAD, forgetting the nonces.
+    /// 
+    /// result: The AEAD.
+    /// 
+    pub fn into_inner(self) -> A {
+        self.cipher
+    }
+
+}
+
+impl<A: Aead> Aead for NonceTracker<A> {
+
+    fn encrypt(&self, nonce: &[u8], aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, AesError> {
+        let mut nonces = self.nonces.lock().unwrap_or_else(PoisonError::into_inner);
+        let ciphertext = self.cipher.encrypt(nonce, aad, plaintext)?;
+        if !nonces.insert(nonce) {
+            return Err(AesError::NonceReused);
+        }
+        Ok(ciphertext)
+    }
+
+    fn decrypt(&self, nonce: &[u8], aad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, AesError> {
+        self.cipher.decrypt(nonce, aad, ciphertext)
+    }
+
+    fn encrypt_into(&self, nonce: &[u8], aad: &[u8], plaintext: &[u8], output: &mut Vec<u8>) -> Result<(), AesError> {
+        let mut nonces = self.nonces.lock().unwrap_or_else(PoisonError::into_inner);
+        self.cipher.encrypt_into(nonce, aad, plaintext, output)?;
+        if !nonces.insert(nonce) {
+            output.clear();
+            return Err(AesError::NonceReused);
+        }
+        Ok(())
+    }
+
+    fn decrypt_into(&self, nonce: &[u8], aad: &[u8], ciphertext: &[u8], output: &mut Vec<u8>) -> Result<(), AesError> {
+        self.cipher.decrypt_into(nonce, aad, ciphertext, output)
+    }
+
+    fn encrypt_detached(&self, nonce: &[u8], aad: &[u8], plaintext: &[u8]) -> Result<(Vec<u8>, Vec<u8>), AesError> {
+        let mut nonces = self.nonces.lock().unwrap_or_else(PoisonError::into_inner);
+        let detached = self.cipher.encrypt_detached(nonce, aad, plaintext)?;
+        if !nonces.insert(nonce) {
+            return Err(AesError::NonceReused);
+        }
+        Ok(detached)
+    }
+
+    fn decrypt_detached(&self, nonce: &[u8], aad: &[u8], ciphertext: &[u8], tag: &[u8]) -> Result<Vec<u8>, AesError> {
+        self.cipher.decrypt_detached(nonce, aad, ciphertext, tag)
+    }
+
+    fn tag_length(&self) -> usize {
+        self.cipher.tag_length()
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::gcm::Gcm;
+    use crate::siv::Siv;
+
+    #[test]
+    fn test_exact() {
+        let tracker = NonceTracker::new(Gcm::<16>::new(&[1; 16], 16).unwrap());
+        let ciphertext = tracker.encrypt(&[2; 12], b"", b"first").unwrap();
+        assert_eq!(Err(AesError::NonceReused), tracker.encrypt(&[2; 12], b"", b"second"));
+        assert!(tracker.encrypt(&[3; 12], b"", b"second").is_ok());
+        assert_eq!(b"first".to_vec(), tracker.decrypt(&[2; 12], b"", &ciphertext).unwrap());
+        assert_eq!(b"first".to_vec(), tracker.decrypt(&[2; 12], b"", &ciphertext).unwrap());
+        let mut builder = tracker.with_nonce(&[3; 12]);
+        builder.update_aad(b"header");
+        assert_eq!(Some(AesError::NonceReused), builder.encrypt(b"third").err());
+        assert_eq!(Err(AesError::InvalidNonceLength { length: 0, min: 1, max: usize::MAX }), tracker.encrypt(&[], b"", b"data"));
+        assert!(tracker.into_inner().encrypt(&[2; 12], b"", b"first").is_ok());
+    }
+
+    #[test]
+    fn test_exact_into() {
+        let tracker = NonceTracker::new(Gcm::<16>::new(&[1; 16], 16).unwrap());
+        let mut output = Vec::new();
+        tracker.encrypt_into(&[2; 12], b"", b"first", &mut output).unwrap();
+        let ciphertext = output.clone();
+        assert_eq!(Err(AesError::NonceReused), tracker.encrypt_into(&[2; 12], b"", b"second", &mut output));
+        assert!(output.is_empty());
+        tracker.decrypt_into(&[2; 12], b"", &ciphertext, &mut output).unwrap();
+        assert_eq!(b"first".to_vec(), output);
+    }
+
+    #[test]
+    fn test_detached() {
+        let siv = Siv::<16>::new(&[1; 16], &[2; 16]);
+        let expected = siv.encrypt_detached(&[3; 16], b"header", b"data").unwrap();
+        let tracker = NonceTracker::new(siv);
+        let (ciphertext, tag) = tracker.encrypt_detached(&[3; 16], b"header", b"data").unwrap();
+        assert_eq!(expected, (ciphertext.clone(), tag.clone()));
+        assert_eq!(tracker.encrypt(&[4; 16], b"header", b"data").unwrap()[..16], tracker.cipher.encrypt_detached(&[4; 16], b"header", b"data").unwrap().1);
+        assert_eq!(Err(AesError::NonceReused), tracker.encrypt_detached(&[3; 16], b"header", b"data"));
+        assert_eq!(b"data".to_vec(), tracker.decrypt_detached(&[3; 16], b"header", &ciphertext, &tag).unwrap());
+    }
+
+    #[test]
+    fn test_bloom_filter() {
+        let tracker = NonceTracker::with_bloom_seed(Gcm::<16>::new(&[1; 16], 16).unwrap(), 1000, 0.001, 7).unwrap();
+        for counter in 0u32..1000 {
+            assert!(tracker.encrypt(&counter.to_be_bytes(), b"", b"data").is_ok());
+        }
+        for counter in 0u32..1000 {
+            assert_eq!(Err(AesError::NonceReused), tracker.encrypt(&counter.to_be_bytes(), b"", b"data"));
+        }
+        let nonces = tracker.nonces.lock().unwrap();
+        let NonceSet::Bloom { bits, hashes, seed } = &*nonces else { panic!("not a bloom filter") };
+        let rejected = (1000u32..101000).filter(|counter| {
+            let mut filter = NonceSet::Bloom { bits: bits.clone(), hashes: *hashes, seed: *seed };
+            !filter.insert(&counter.to_be_bytes())
+        }).count();
+        assert!(rejected < 200, "{} false positives", rejected);
+    }
+
+    #[test]
+    fn test_invalid_bloom_parameters() {
+        let cipher = || Gcm::<16>::new(&[1; 16], 16).unwrap();
+        for (capacity, rate) in [(0, 0.01), (10, 0.0), (10, 1.0), (10, f64::NAN)] {
+            assert_eq!(Some(AesError::InvalidTrackerParameters), NonceTracker::with_bloom_filter(cipher(), capacity, rate).err());
+        }
+    }
+
+}
@@ -0,0 +1,296 @@
+//!
+//! Offset codebook (OCB3) mode, as specified in RFC 7253. OCB is a one pass 
+//! authenticated encryption mode, every block of plaintext is encrypted with a 
+//! single cipher call. Each block is xored with an offset before and after the 
+//! cipher, and the offsets are derived from the nonce and the block index.
+//! 
+//! The nonce is 1 to 15 bytes, RFC 7253 recommends 12 bytes, and must never be
+//! used twice with the same key.
+//! 
+
+use std::vec::Vec;
+
+use crate::cmac::double;
+use crate::{verify_tag, Aead, Aes, AesError, SecretKey};
+
+///
+/// Ocb is an OCB3 cipher with a fixed tag length.
+/// 
+pub struct Ocb<const KEY_BYTES: usize = 16> {
+    cipher: Aes<KEY_BYTES>,
+    tag_length: usize,
+    l_star: [u8; 16],
+    l_dollar: [u8; 16],
+    l: Vec<[u8; 16]>
+}
+
+impl<const KEY_BYTES: usize> Ocb<KEY_BYTES> {
+
+    ///
+    /// Creates a new Ocb cipher.
+    /// 
+    /// key: The key used to encrypt the data.
+    /// tag_length: The length of the tag in bytes, between 1 and 16. RFC 7253 
+    ///             defines 8, 12 and 16.
+    /// 
+    /// result: A new Ocb cipher or AesError::InvalidTagLength if the tag length is
+    ///         not valid.
+    /// 
+    pub fn new(key: &[u8; KEY_BYTES], tag_length: usize) -> Result<Self, AesError> {
+        if !(1..=16).contains(&tag_length) {
+            return Err(AesError::InvalidTagLength);
+        }
+        let cipher = Aes::new(key);
+        let l_star: [u8; 16] = cipher.encrypt_bytes(&[0; 16]).try_into().unwrap();
+        let l_dollar = double(&l_star);
+        let mut l = vec![double(&l_dollar)];
+        for index in 1..64 {
+            l.push(double(&l[index - 1]));
+        }
+        Ok(Self { cipher, tag_length, l_star, l_dollar, l })
+    }
+
+    ///
+    /// Creates a new Ocb cipher from a SecretKey.
+    /// 
+    /// key: The key used to encrypt the data.
+    /// tag_length: The length of the tag in bytes, between 1 and 16.
+    /// 
+    /// result: A new Ocb cipher or the error of new.
+    /// 
+    pub fn from_secret(key: &SecretKey<KEY_BYTES>, tag_length: usize) -> Result<Self, AesError> {
+        Self::new(key.expose_secret(), tag_length)
+    }
+
+    ///
+    /// Encrypts and authenticates the plaintext.
+    /// 
+    /// nonce: The nonce, 1 to 15 bytes.
+    /// aad: The associated data, authenticated but not encrypted.
+    /// plaintext: The data to encrypt.
+    /// 
+    /// result: The ciphertext followed by the tag or AesError::InvalidNonceLength if
+    ///         the nonce has an invalid length.
+    /// 
+    pub fn encrypt(&self, nonce: &[u8], aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, AesError> {
+        let mut offset = self.initial_offset(nonce)?;
+        let mut checksum = [0; 16];
+        let mut encrypted = Vec::with_capacity(plaintext.len() + self.tag_length);
+        let mut blocks = plaintext.chunks_exact(16);
+        for (index, block) in (&mut blocks).enumerate() {
+            offset = xor(&offset, &self.l[(index + 1).trailing_zeros() as usize]);
+            let block: [u8; 16] = block.try_into().unwrap();
+            encrypted.extend(xor(&offset, &self.encrypt_block(&xor(&block, &offset))));
+            checksum = xor(&checksum, &block);
+        }
+        let remainder = blocks.remainder();
+        if !remainder.is_empty() {
+            offset = xor(&offset, &self.l_star);
+            let pad = self.encrypt_block(&offset);
+            encrypted.extend(remainder.iter().zip(pad).map(|(a, b)| a ^ b));
+            checksum = xor(&checksum, &pad_block(remainder));
+        }
+        encrypted.extend_from_slice(&self.tag(&checksum, &offset, aad)[..self.tag_length]);
+        Ok(encrypted)
+    }
+
+    ///
+    /// Decrypts the ciphertext and verifies the tag.
+    /// 
+    /// nonce: The nonce used when encrypting.
+    /// aad: The associated data used when encrypting.
+    /// ciphertext: The ciphertext followed by the tag.
+    /// 
+    /// result: The plaintext, AesError::InvalidNonceLength if the nonce has an 
+    ///         invalid length or AesError::AuthenticationFailed if the tag does not
+    ///         match.
+    /// 
+    pub fn decrypt(&self, nonce: &[u8], aad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, AesError> {
+        let mut offset = self.initial_offset(nonce)?;
+        if ciphertext.len() < self.tag_length {
+            return Err(AesError::AuthenticationFailed);
+        }
+        let (ciphertext, tag) = ciphertext.split_at(ciphertext.len() - self.tag_length);
+        let mut checksum = [0; 16];
+        let mut decrypted = Vec::with_capacity(ciphertext.len());
+        let mut blocks = ciphertext.chunks_exact(16);
+        for (index, block) in (&mut blocks).enumerate() {
+            offset = xor(&offset, &self.l[(index + 1).trailing_zeros() as usize]);
+            let block = xor(&offset, &self.decrypt_block(&xor(&block.try_into().unwrap(), &offset)));
+            decrypted.extend_from_slice(&block);
+            checksum = xor(&checksum, &block);
+        }
+        let remainder = blocks.remainder();
+        if !remainder.is_empty() {
+            offset = xor(&offset, &self.l_star);
+            let pad = self.encrypt_block(&offset);
+            let block: Vec<u8> = remainder.iter().zip(pad).map(|(a, b)| a ^ b).collect();
+            checksum = xor(&checksum, &pad_block(&block));
+            decrypted.extend(block);
+        }
+        verify_tag(tag, &self.tag(&checksum, &offset, aad)[..self.tag_length])?;
+        Ok(decrypted)
+    }
+
+    ///
+    /// Calculates the first offset from the nonce. The top 122 bits of the formatted
+    /// nonce are encrypted and stretched, and the last 6 bits select which 128 bits
+    /// of the stretch are the offset.
+    /// 
+    fn initial_offset(&self, nonce: &[u8]) -> Result<[u8; 16], AesError> {
+        if nonce.is_empty() || nonce.len() > 15 {
+            return Err(AesError::InvalidNonceLength { length: nonce.len(), min: 1, max: 15 });
+        }
+        let mut formatted = [0; 16];
+        formatted[0] = ((self.tag_length * 8 % 128) << 1) as u8;
+        formatted[15 - nonce.len()] |= 1;
+        formatted[16 - nonce.len()..].copy_from_slice(nonce);
+        let bottom = (formatted[15] & 0x3f) as usize;
+        formatted[15] &= 0xc0;
+        let top = self.encrypt_block(&formatted);
+        let mut stretch = top.to_vec();
+        stretch.extend((0..8).map(|index| top[index] ^ top[index + 1]));
+        let (bytes, bits) = (bottom / 8, bottom % 8);
+        Ok(std::array::from_fn(|index| {
+            let high = stretch[index + bytes] << bits;
+            if bits == 0 { high } else { high | stretch[index + bytes + 1] >> (8 - bits) }
+        }))
+    }
+
+    ///
+    /// Calculates the tag from the checksum, the last offset and the hash of the 
+    /// associated data.
+    /// 
+    fn tag(&self, checksum: &[u8; 16], offset: &[u8; 16], aad: &[u8]) -> [u8; 16] {
+        xor(&self.encrypt_block(&xor(&xor(checksum, offset), &self.l_dollar)), &self.hash(aad))
+    }
+
+    ///
+    /// Calculates HASH of the associated data with its own offsets starting at zero.
+    /// 
+    fn hash(&self, aad: &[u8]) -> [u8; 16] {
+        let mut offset = [0; 16];
+        let mut sum = [0; 16];
+        let mut blocks = aad.chunks_exact(16);
+        for (index, block) in (&mut blocks).enumerate() {
+            offset = xor(&offset, &self.l[(index + 1).trailing_zeros() as usize]);
+            sum = xor(&sum, &self.encrypt_block(&xor(&block.try_into().unwrap(), &offset)));
+        }
+        let remainder = blocks.remainder();
+        if !remainder.is_empty() {
+            offset = xor(&offset, &self.l_star);
+            sum = xor(&sum, &self.encrypt_block(&xor(&pad_block(remainder), &offset)));
+        }
+        sum
+    }
+
+    ///
+    /// Encrypts a single block with the cipher.
+    /// 
+    fn encrypt_block(&self, block: &[u8; 16]) -> [u8; 16] {
+        self.cipher.encrypt_bytes(block).try_into().unwrap()
+    }
+
+    ///
+    /// Decrypts a single block with the cipher.
+    /// 
+    fn decrypt_block(&self, block: &[u8; 16]) -> [u8; 16] {
+        self.cipher.decrypt_bytes(block).try_into().unwrap()
+    }
+
+}
+
+///
+/// Pads a partial block with a one bit followed by zeros.
+/// 
+fn pad_block(data: &[u8]) -> [u8; 16] {
+    let mut block = [0; 16];
+    block[..data.len()].copy_from_slice(data);
+    block[data.len()] = 0x80;
+    block
+}
+
+///
+/// Xors two blocks.
+/// 
+fn xor(block: &[u8; 16], other: &[u8; 16]) -> [u8; 16] {
+    std::array::from_fn(|index| block[index] ^ other[index])
+}
+
+impl<const KEY_BYTES: usize> Aead for Ocb<KEY_BYTES> {
+
+    fn encrypt(&self, nonce: &[u8], aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, AesError> {
+        Ocb::encrypt(self, nonce, aad, plaintext)
+    }
+
+    fn decrypt(&self, nonce: &[u8], aad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, AesError> {
+        Ocb::decrypt(self, nonce, aad, ciphertext)
+    }
+
+    fn tag_length(&self) -> usize {
+        self.tag_length
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::test_util::hex;
+
+    fn range(length: u8) -> Vec<u8> {
+        (0..length).collect()
+    }
+
+    fn known_answer<const KEY_BYTES: usize>(ocb: &Ocb<KEY_BYTES>, nonce: &str, aad: &[u8], plaintext: &[u8], expected: &str) {
+        assert_eq!(hex(expected), ocb.encrypt(&hex(nonce), aad, plaintext).unwrap());
+        assert_eq!(plaintext.to_vec(), ocb.decrypt(&hex(nonce), aad, &hex(expected)).unwrap());
+    }
+
+    #[test]
+    fn test_rfc7253_vectors() {
+        let ocb = Ocb::<16>::new(&range(16).try_into().unwrap(), 16).unwrap();
+        known_answer(&ocb, "bbaa99887766554433221100", &[], &[], "785407bfffc8ad9edcc5520ac9111ee6");
+        known_answer(&ocb, "bbaa99887766554433221101", &range(8), &range(8), "6820b3657b6f615a5725bda0d3b4eb3a257c9af1f8f03009");
+        known_answer(&ocb, "bbaa99887766554433221102", &range(8), &[], "81017f8203f081277152fade694a0a00");
+        known_answer(&ocb, "bbaa99887766554433221103", &[], &range(8), "45dd69f8f5aae72414054cd1f35d82760b2cd00d2f99bfa9");
+        known_answer(&ocb, "bbaa99887766554433221104", &range(16), &range(16),
+            "571d535b60b277188be5147170a9a22c 3ad7a4ff3835b8c5701c1ccec8fc3358");
+        known_answer(&ocb, "bbaa9988776655443322110d", &range(40), &range(40),
+            "d5ca91748410c1751ff8a2f618255b68 a0a12e093ff454606e59f9c1d0ddc54b 65e8628e568bad7aed07ba06a4a69483 a7035490c5769e60");
+    }
+
+    #[test]
+    fn test_rfc7253_taglen_96() {
+        let ocb = Ocb::<16>::new(&hex("0f0e0d0c0b0a09080706050403020100").try_into().unwrap(), 12).unwrap();
+        known_answer(&ocb, "bbaa9988776655443322110d", &range(40), &range(40),
+            "1792a4e31e0755fb03e31b22116e6c2d df9efd6e33d536f1a0124b0a55bae884 ed93481529c76b6ad0c515f4d1cdd4fd ac4f02aa");
+    }
+
+    #[test]
+    fn test_ocb_key_and_nonce_lengths() {
+        let ocb = Ocb::<32>::new(&range(32).try_into().unwrap(), 8).unwrap();
+        known_answer(&ocb, "bbaa99887766554433221100aabbcc", &range(5), &range(71),
+            "07c59f37dd1f10881d4aea07ed1363b0 972a805eef6ca38b6b94d9f05cb9226d 75f6cb3b1e1cc7a9495e38fe0f402ec8 968c1e670bb71d0eda854ef5bc9729be
+             32fb339f53d7b04dfffd3be55666a1");
+        let ocb = Ocb::<24>::new(&range(24).try_into().unwrap(), 16).unwrap();
+        known_answer(&ocb, "01", &range(33), &range(100),
+            "9f2b6ec0363bfc559727e39969273738 7ad7d9781e82ac7eee754e35719b7d94 8ab18e2a651af242ddc6492658a5071d 7769420f27480be941d4013d11515aa0
+             6c8742490b4c98cb09bc0811c939ff33 60a8036980d3ddc2272be1c25f5c675f f460355554e7e1eaf51bb7a73325bb5a d39a2c40");
+    }
+
+    #[test]
+    fn test_ocb_invalid() {
+        assert_eq!(Some(AesError::InvalidTagLength), Ocb::<16>::new(&[0; 16], 0).err());
+        assert_eq!(Some(AesError::InvalidTagLength), Ocb::<16>::new(&[0; 16], 17).err());
+        let ocb = Ocb::<16>::new(&[0; 16], 16).unwrap();
+        assert_eq!(Some(AesError::InvalidNonceLength { length: 0, min: 1, max: 15 }), ocb.encrypt(&[], &[], &[]).err());
+        assert_eq!(Some(AesError::InvalidNonceLength { length: 16, min: 1, max: 15 }), ocb.encrypt(&[0; 16], &[], &[]).err());
+        let mut encrypted = ocb.encrypt(&[1; 12], b"header", b"payload").unwrap();
+        assert_eq!(Some(AesError::AuthenticationFailed), ocb.decrypt(&[1; 12], b"headex", &encrypted).err());
+        encrypted[1] ^= 1;
+        assert_eq!(Some(AesError::AuthenticationFailed), ocb.decrypt(&[1; 12], b"header", &encrypted).err());
+    }
+
+}
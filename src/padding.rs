@@ -0,0 +1,325 @@
+//!
+//! PKCS#7 padding, as specified in RFC 5652. The data is padded to a multiple 
+//! of 16 bytes with n bytes of the value n, between 1 and 16, so the padding 
+//! can always be removed again. The padded modes of cbc and ecb use it.
+//! 
+//! unpad checks the last block without branching on the data and returns the 
+//! same error for every kind of invalid padding, so the validation does not 
+//! give away which bytes were wrong. Servers should still authenticate the 
+//! ciphertext before it is decrypted, as a padding oracle remains if the error
+//! itself is visible.
+//! 
+//! ZeroPadding fills the last block with zeros and adds nothing to data that is 
+//! already a multiple of 16 bytes. Trailing zeros of the data cannot be told 
+//! apart from the padding, so frame can prefix the data with its length first 
+//! and unframe recovers the exact data after decryption.
+//! 
+//! Other padding schemes can be used with the cbc and ecb modes by implementing
+//! the Padding trait.
+//! 
+
+use std::vec::Vec;
+
+use crate::AesError;
+
+///
+/// Pads the data with PKCS#7 to a multiple of 16 bytes. A full block of
+/// padding is added when the data is already a multiple of 16 bytes.
+/// 
+/// data: The data to pad. May be of any length.
+/// 
+/// result: The padded data, 1 to 16 bytes longer than the data.
+/// 
+pub fn pad(data: &[u8]) -> Vec<u8> {
+    let padding = 16 - data.len() % 16;
+    let mut padded = Vec::with_capacity(data.len() + padding);
+    padded.extend_from_slice(data);
+    padded.resize(data.len() + padding, padding as u8);
+    padded
+}
+
+///
+/// Removes the PKCS#7 padding from the data.
+/// 
+/// data: The padded data.
+/// 
+/// result: The data without the padding or AesError::InvalidPadding if the data
+///         is empty, the last byte is not between 1 and 16 or the padding bytes 
+///         are not all equal to it.
+/// 
+pub fn unpad(data: &[u8]) -> Result<&[u8], AesError> {
+    let padding = *data.last().ok_or(AesError::InvalidPadding)? as u32;
+    let window = data.len().min(16);
+    let mut invalid = is_zero(padding) | less_than(window as u32, padding);
+    for (index, &byte) in data[data.len() - window..].iter().rev().enumerate() {
+        invalid |= less_than(index as u32, padding) & !is_zero(byte as u32 ^ padding) & 1;
+    }
+    if invalid != 0 {
+        return Err(AesError::InvalidPadding);
+    }
+    Ok(&data[..data.len() - padding as usize])
+}
+
+///
+/// Checks if the value is zero without branching.
+/// 
+/// value: The value to check, less than 2^31.
+/// 
+/// result: 1 if the value is zero, else 0.
+/// 
+fn is_zero(value: u32) -> u32 {
+    (value.wrapping_sub(1) >> 31) & 1
+}
+
+///
+/// Checks if a is less than b without branching.
+/// 
+/// a: The first value, less than 2^31.
+/// b: The second value, less than 2^31.
+/// 
+/// result: 1 if a is less than b, else 0.
+/// 
+fn less_than(a: u32, b: u32) -> u32 {
+    a.wrapping_sub(b) >> 31
+}
+
+///
+/// Padding is a padding scheme for the block modes. The data is split into full
+/// blocks and the last partial block, and only the last partial block is padded.
+/// 
+pub trait Padding {
+
+    ///
+    /// Pads the last partial block of the data.
+    /// 
+    /// last: The last 0 to 15 bytes of the data.
+    /// 
+    /// result: The padded blocks, a multiple of 16 bytes. May be empty if the 
+    ///         scheme adds no padding to empty data.
+    /// 
+    fn pad_block(&self, last: &[u8]) -> Vec<u8>;
+
+    ///
+    /// Finds the length of the data in the last decrypted block.
+    /// 
+    /// last: The last decrypted block.
+    /// 
+    /// result: The number of bytes of the block that are data or 
+    ///         AesError::InvalidPadding if the padding is not valid.
+    /// 
+    fn unpad_block(&self, last: &[u8; 16]) -> Result<usize, AesError>;
+
+}
+
+///
+/// Pkcs7 is PKCS#7 padding as a Padding.
+/// 
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Pkcs7;
+
+impl Padding for Pkcs7 {
+
+    fn pad_block(&self, last: &[u8]) -> Vec<u8> {
+        pad(last)
+    }
+
+    fn unpad_block(&self, last: &[u8; 16]) -> Result<usize, AesError> {
+        Ok(unpad(last)?.len())
+    }
+
+}
+
+///
+/// ZeroPadding pads the last partial block with zeros. Unpadding removes all 
+/// trailing zeros of the last block, including zeros that were part of the data.
+/// 
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ZeroPadding;
+
+impl Padding for ZeroPadding {
+
+    fn pad_block(&self, last: &[u8]) -> Vec<u8> {
+        if last.is_empty() {
+            return Vec::new();
+        }
+        let mut block = last.to_vec();
+        block.resize(16, 0);
+        block
+    }
+
+    fn unpad_block(&self, last: &[u8; 16]) -> Result<usize, AesError> {
+        Ok(last.iter().rposition(|&byte| byte != 0).map_or(0, |index| index + 1))
+    }
+
+}
+
+///
+/// Prefixes the data with its length as an 8 byte big endian number.
+/// 
+/// data: The data to frame.
+/// 
+/// result: The length followed by the data.
+/// 
+pub fn frame(data: &[u8]) -> Vec<u8> {
+    let mut framed = Vec::with_capacity(data.len() + 8);
+    framed.extend_from_slice(&(data.len() as u64).to_be_bytes());
+    framed.extend_from_slice(data);
+    framed
+}
+
+///
+/// Reads the data framed by frame. Any bytes after the data, such as padding, 
+/// are ignored.
+/// 
+/// framed: The length followed by the data and optionally padding.
+/// 
+/// result: The data or AesError::InvalidDataLength with the length of the 
+///         framed data and the 8 bytes of the length plus the encoded length as
+///         the minimum if the framed data is shorter.
+/// 
+pub fn unframe(framed: &[u8]) -> Result<&[u8], AesError> {
+    if framed.len() < 8 {
+        return Err(AesError::InvalidDataLength { length: framed.len(), min: 8, multiple: 1 });
+    }
+    let (length, data) = framed.split_at(8);
+    let length = u64::from_be_bytes(length.try_into().unwrap());
+    if length > data.len() as u64 {
+        return Err(AesError::InvalidDataLength { length: framed.len(), min: usize::try_from(length).unwrap_or(usize::MAX).saturating_add(8), multiple: 1 });
+    }
+    Ok(&data[..length as usize])
+}
+
+///
+/// Pads the data with the padding scheme.
+/// 
+/// padding: The padding scheme.
+/// data: The data to pad. May be of any length.
+/// 
+/// result: The padded data, a multiple of 16 bytes.
+/// 
+#[cfg(feature = "encrypt")]
+pub(crate) fn pad_with<P: Padding>(padding: &P, data: &[u8]) -> Vec<u8> {
+    let full = data.len() / 16 * 16;
+    let mut padded = data[..full].to_vec();
+    padded.extend_from_slice(&padding.pad_block(&data[full..]));
+    padded
+}
+
+///
+/// Removes the padding of the padding scheme from the decrypted data.
+/// 
+/// padding: The padding scheme.
+/// data: The decrypted data, a multiple of 16 bytes. Empty data is only valid
+///       if the scheme pads empty data to nothing.
+/// 
+/// result: The data without the padding or AesError::InvalidPadding if the 
+///         padding is not valid.
+/// 
+#[cfg(feature = "decrypt")]
+pub(crate) fn unpad_with<'a, P: Padding>(padding: &P, data: &'a [u8]) -> Result<&'a [u8], AesError> {
+    if data.is_empty() && padding.pad_block(&[]).is_empty() {
+        return Ok(data);
+    }
+    let start = data.len().checked_sub(16).ok_or(AesError::InvalidPadding)?;
+    let length = padding.unpad_block(&data[start..].try_into().unwrap())?;
+    if length > 16 {
+        return Err(AesError::InvalidPadding);
+    }
+    Ok(&data[..start + length])
+}
+
+#[cfg(all(test, feature = "encrypt", feature = "decrypt"))]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_pad_unpad() {
+        assert_eq!(vec![16; 16], pad(&[]));
+        assert_eq!(vec![1, 2, 3, 13, 13, 13, 13, 13, 13, 13, 13, 13, 13, 13, 13, 13], pad(&[1, 2, 3]));
+        assert_eq!(Ok(&[1, 2, 3][..]), unpad(&pad(&[1, 2, 3])));
+        assert_eq!(32, pad(&[0; 16]).len());
+    }
+
+    #[test]
+    fn test_unpad_invalid() {
+        assert_eq!(Err(AesError::InvalidPadding), unpad(&[]));
+        assert_eq!(Err(AesError::InvalidPadding), unpad(&[17; 16]));
+        assert_eq!(Err(AesError::InvalidPadding), unpad(&[0; 16]));
+        assert_eq!(Err(AesError::InvalidPadding), unpad(&[3, 3]));
+        assert_eq!(Err(AesError::InvalidPadding), unpad(&[1, 2, 3, 4, 4, 3, 4]));
+    }
+
+    ///
+    /// A padding with 0x80 followed by zeros, as in ISO/IEC 7816-4.
+    /// 
+    struct Iso7816;
+
+    impl Padding for Iso7816 {
+
+        fn pad_block(&self, last: &[u8]) -> Vec<u8> {
+            let mut block = last.to_vec();
+            block.push(0x80);
+            block.resize(16, 0);
+            block
+        }
+
+        fn unpad_block(&self, last: &[u8; 16]) -> Result<usize, AesError> {
+            match last.iter().rposition(|&byte| byte != 0) {
+                Some(index) if last[index] == 0x80 => Ok(index),
+                _ => Err(AesError::InvalidPadding)
+            }
+        }
+
+    }
+
+    #[test]
+    fn test_unpad_every_last_byte() {
+        for last in 0..=255u8 {
+            let mut data = [last; 32];
+            data[..16].copy_from_slice(&[0xaa; 16]);
+            let expected = if (1..=16).contains(&last) { Ok(32 - last as usize) } else { Err(AesError::InvalidPadding) };
+            assert_eq!(expected, unpad(&data).map(|unpadded| unpadded.len()));
+            if (2..=16).contains(&last) {
+                data[32 - last as usize] ^= 1;
+                assert_eq!(Err(AesError::InvalidPadding), unpad(&data));
+            }
+        }
+    }
+
+    #[test]
+    fn test_zero_padding() {
+        assert_eq!(Vec::<u8>::new(), pad_with(&ZeroPadding, &[]));
+        assert_eq!(vec![1; 16], pad_with(&ZeroPadding, &[1; 16]));
+        let padded = pad_with(&ZeroPadding, &[1, 2, 3]);
+        assert_eq!([vec![1, 2, 3], vec![0; 13]].concat(), padded);
+        assert_eq!(Ok(&[1, 2, 3][..]), unpad_with(&ZeroPadding, &padded));
+        assert_eq!(Ok(&[][..]), unpad_with(&ZeroPadding, &[]));
+        assert_eq!(Ok(&[1][..]), unpad_with(&ZeroPadding, &pad_with(&ZeroPadding, &[1, 0])));
+    }
+
+    #[test]
+    fn test_frame() {
+        let padded = pad_with(&ZeroPadding, &frame(&[1, 0]));
+        assert_eq!(16, padded.len());
+        assert_eq!(Ok(&[1, 0][..]), unframe(&padded));
+        assert_eq!(Ok(&[][..]), unframe(&frame(&[])));
+        assert_eq!(Err(AesError::InvalidDataLength { length: 7, min: 8, multiple: 1 }), unframe(&[0; 7]));
+        assert_eq!(Err(AesError::InvalidDataLength { length: 9, min: 17, multiple: 1 }), unframe(&[0, 0, 0, 0, 0, 0, 0, 9, 1]));
+        assert_eq!(Err(AesError::InvalidDataLength { length: 8, min: usize::MAX, multiple: 1 }), unframe(&[0xff; 8]));
+    }
+
+    #[test]
+    fn test_pad_with() {
+        let padded = pad_with(&Pkcs7, &[1; 20]);
+        assert_eq!(pad(&[1; 20]), padded);
+        assert_eq!(Ok(&[1; 20][..]), unpad_with(&Pkcs7, &padded));
+        let padded = pad_with(&Iso7816, &[1; 16]);
+        assert_eq!(32, padded.len());
+        assert_eq!(0x80, padded[16]);
+        assert_eq!(Ok(&[1; 16][..]), unpad_with(&Iso7816, &padded));
+        assert_eq!(Err(AesError::InvalidPadding), unpad_with(&Iso7816, &[0; 16]));
+        assert_eq!(Err(AesError::InvalidPadding), unpad_with(&Pkcs7, &[]));
+    }
+
+}
@@ -0,0 +1,70 @@
+//!
+//! Parallel processing of large buffers for the modes where the blocks do not
+//! depend on each other, ctr, ecb and xts. The buffer is split into chunks, and
+//! the chunks are handed out to one scoped thread per cpu until all of them are
+//! processed. The threads only live for the call, so no thread pool is kept.
+//!
+//! The parallel functions of the modes give the same result as the sequential
+//! functions for every chunk size. Larger chunks have less overhead, smaller
+//! chunks spread the work more evenly over the threads.
+//!
+
+use std::num::NonZeroUsize;
+use std::sync::Mutex;
+
+///
+/// The chunk size used when no other size is wanted, 64 KiB.
+///
+pub const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+
+///
+/// Runs a function on every chunk of the data, in parallel on the cpus. The data
+/// is processed on the calling thread when there is only one chunk or one cpu.
+///
+/// data: The data to split, changed in place by the function.
+/// chunk_size: The size of the chunks, rounded down to a multiple of the unit.
+/// unit: The size the chunks must be a multiple of, for example 16 for blocks.
+/// process: The function called with the index and the contents of each chunk.
+///
+pub(crate) fn for_each_chunk(data: &mut [u8], chunk_size: usize, unit: usize, process: impl Fn(usize, &mut [u8]) + Sync) {
+    let chunk_size = (chunk_size / unit).max(1) * unit;
+    let threads = std::thread::available_parallelism().map_or(1, NonZeroUsize::get).min(data.len().div_ceil(chunk_size));
+    if threads <= 1 {
+        data.chunks_mut(chunk_size).enumerate().for_each(|(index, chunk)| process(index, chunk));
+        return;
+    }
+    let chunks = Mutex::new(data.chunks_mut(chunk_size).enumerate());
+    std::thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| {
+                while let Some((index, chunk)) = next_chunk(&chunks) {
+                    process(index, chunk);
+                }
+            });
+        }
+    });
+}
+
+///
+/// Takes the next chunk to process.
+///
+fn next_chunk<T>(chunks: &Mutex<impl Iterator<Item = T>>) -> Option<T> {
+    chunks.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).next()
+}
+
+#[cfg(all(test, feature = "encrypt", feature = "decrypt"))]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_for_each_chunk() {
+        for (length, chunk_size) in [(0, 16), (100, 16), (100, 40), (1000, 1), (1000, 5000)] {
+            let mut data = vec![0u8; length];
+            for_each_chunk(&mut data, chunk_size, 16, |index, chunk| chunk.iter_mut().for_each(|byte| *byte = index as u8));
+            let chunk_size = (chunk_size / 16).max(1) * 16;
+            assert!(data.iter().enumerate().all(|(position, byte)| *byte == (position / chunk_size) as u8));
+        }
+    }
+
+}
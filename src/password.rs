@@ -0,0 +1,479 @@
+//!
+//! Password based encryption in the style of PBES2. A key is derived from the 
+//! password and a random salt with a password KDF and the data is encrypted with
+//! AES-256-GCM under a random nonce.
+//! 
+//! The output starts with a versioned header describing how the key was derived,
+//! so data stays decryptable when the defaults change:
+//! 
+//! version (1 byte) || kdf id (1 byte) || kdf parameters || salt length (1 byte) || salt || nonce (12 bytes) || ciphertext || tag (16 bytes)
+//! 
+//! The kdf id is 1 for scrypt with 9 bytes of parameters and 2 for Argon2id with
+//! 12 bytes of parameters. The header is authenticated as the additional data of
+//! GCM.
+//! 
+//! The header is read before the data can be authenticated, so its cost 
+//! parameters are checked against KdfLimits before the key is derived. Forged 
+//! data can then not make decrypt_with_password use more memory and time than 
+//! the caller allows.
+//! 
+
+use std::vec::Vec;
+
+#[cfg(feature = "argon2")]
+use crate::argon2::{argon2id, Argon2Params};
+use crate::gcm::Gcm;
+use crate::random::fill_random;
+use crate::scrypt::{scrypt, ScryptParams};
+use crate::zeroize::Zeroizing;
+use crate::AesError;
+
+///
+/// The version of the header written by this crate.
+/// 
+pub const VERSION: u8 = 1;
+
+///
+/// The length of the random salt in bytes.
+/// 
+pub const SALT_LENGTH: usize = 16;
+
+///
+/// The length of the random GCM nonce in bytes.
+/// 
+pub const NONCE_LENGTH: usize = 12;
+
+///
+/// The length of the GCM tag in bytes.
+/// 
+pub const TAG_LENGTH: usize = 16;
+
+///
+/// The kdf id of scrypt.
+/// 
+const SCRYPT_ID: u8 = 1;
+
+///
+/// The kdf id of Argon2id.
+/// 
+#[cfg(feature = "argon2")]
+const ARGON2ID_ID: u8 = 2;
+
+///
+/// Kdf is a password key derivation function with its cost parameters.
+/// 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kdf {
+    ///
+    /// scrypt, as specified in RFC 7914.
+    /// 
+    Scrypt(ScryptParams),
+    ///
+    /// Argon2id, as specified in RFC 9106.
+    /// 
+    #[cfg(feature = "argon2")]
+    Argon2id(Argon2Params)
+}
+
+impl Kdf {
+
+    ///
+    /// Derives the 32 byte key from the password and the salt.
+    /// 
+    fn derive(&self, password: &[u8], salt: &[u8]) -> Result<Vec<u8>, AesError> {
+        match self {
+            Kdf::Scrypt(params) => scrypt(password, salt, params, 32),
+            #[cfg(feature = "argon2")]
+            Kdf::Argon2id(params) => argon2id(password, salt, params, 32)
+        }
+    }
+
+    ///
+    /// Writes the kdf id and the encoded parameters.
+    /// 
+    fn encode(&self, output: &mut Vec<u8>) {
+        match self {
+            Kdf::Scrypt(params) => {
+                output.push(SCRYPT_ID);
+                output.extend_from_slice(&params.encode());
+            }
+            #[cfg(feature = "argon2")]
+            Kdf::Argon2id(params) => {
+                output.push(ARGON2ID_ID);
+                output.extend_from_slice(&params.encode());
+            }
+        }
+    }
+
+    ///
+    /// Reads the kdf id and the encoded parameters.
+    /// 
+    /// result: The kdf and the remaining data, AesError::InvalidDataLength if the 
+    ///         data is too short or AesError::InvalidKdfParameters if the kdf is
+    ///         unknown or the parameters are not valid.
+    /// 
+    fn decode(data: &[u8]) -> Result<(Self, &[u8]), AesError> {
+        match split(data, 1)?.0[0] {
+            SCRYPT_ID => {
+                let (encoded, rest) = split(data, 1 + ScryptParams::ENCODED_LENGTH)?;
+                Ok((Kdf::Scrypt(ScryptParams::decode(encoded[1..].try_into().unwrap())?), rest))
+            }
+            #[cfg(feature = "argon2")]
+            ARGON2ID_ID => {
+                let (encoded, rest) = split(data, 1 + Argon2Params::ENCODED_LENGTH)?;
+                Ok((Kdf::Argon2id(Argon2Params::decode(encoded[1..].try_into().unwrap())?), rest))
+            }
+            _ => Err(AesError::InvalidKdfParameters)
+        }
+    }
+
+}
+
+impl Kdf {
+
+    ///
+    /// Checks the cost of the kdf against the limits.
+    /// 
+    /// result: Ok or AesError::InvalidKdfParameters if the memory or the passes 
+    ///         are above the limits.
+    /// 
+    fn check_limits(&self, limits: &KdfLimits) -> Result<(), AesError> {
+        let (memory, passes) = match self {
+            Kdf::Scrypt(params) => (params.memory_bytes().map_or(u64::MAX, |memory| memory as u64), params.parallelism),
+            #[cfg(feature = "argon2")]
+            Kdf::Argon2id(params) => (params.memory_kib as u64 * 1024, params.iterations)
+        };
+        if memory > limits.max_memory || passes > limits.max_passes {
+            return Err(AesError::InvalidKdfParameters);
+        }
+        Ok(())
+    }
+
+}
+
+impl Default for Kdf {
+
+    ///
+    /// Argon2id with its default parameters if the argon2 feature is enabled, 
+    /// otherwise scrypt with its default parameters.
+    /// 
+    fn default() -> Self {
+        #[cfg(feature = "argon2")]
+        return Kdf::Argon2id(Argon2Params::default());
+        #[cfg(not(feature = "argon2"))]
+        return Kdf::Scrypt(ScryptParams::default());
+    }
+
+}
+
+///
+/// KdfLimits is the highest cost of the kdf that decrypt_with_password_limits 
+/// accepts from the header of the data.
+/// 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KdfLimits {
+    ///
+    /// The most memory in bytes the key derivation may use.
+    /// 
+    pub max_memory: u64,
+    ///
+    /// The most passes of the key derivation, the parallelism of scrypt, which 
+    /// runs ROMix once for each block on the calling thread, or the iterations of
+    /// Argon2id.
+    /// 
+    pub max_passes: u32
+}
+
+impl Default for KdfLimits {
+
+    ///
+    /// 1 GiB of memory and 16 passes, well above the default parameters of both kdfs.
+    /// 
+    fn default() -> Self {
+        Self { max_memory: 1 << 30, max_passes: 16 }
+    }
+
+}
+
+///
+/// PasswordHeader is the parsed header of password encrypted data.
+/// 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PasswordHeader {
+    ///
+    /// The version of the header.
+    /// 
+    pub version: u8,
+    ///
+    /// The kdf and the parameters the key was derived with.
+    /// 
+    pub kdf: Kdf,
+    ///
+    /// The salt the key was derived with.
+    /// 
+    pub salt: Vec<u8>
+}
+
+impl PasswordHeader {
+
+    ///
+    /// Parses the header of data encrypted by encrypt_with_password.
+    /// 
+    /// data: The encrypted data.
+    /// 
+    /// result: The header and its length in bytes, AesError::UnsupportedVersion if 
+    ///         the version is unknown, AesError::InvalidKdfParameters if the kdf 
+    ///         is unknown or its parameters are not valid or 
+    ///         AesError::InvalidDataLength if the data is too short.
+    /// 
+    pub fn parse(data: &[u8]) -> Result<(Self, usize), AesError> {
+        let truncated = |rest: &[u8]| {
+            let offset = data.len() - rest.len();
+            move |error| match error {
+                AesError::InvalidDataLength { min, .. } => AesError::InvalidDataLength { length: data.len(), min: offset + min, multiple: 1 },
+                error => error
+            }
+        };
+        let (version, rest) = split(data, 1)?;
+        if version[0] != VERSION {
+            return Err(AesError::UnsupportedVersion);
+        }
+        let (kdf, rest) = Kdf::decode(rest).map_err(truncated(rest))?;
+        let (salt_length, rest) = split(rest, 1).map_err(truncated(rest))?;
+        let (salt, rest) = split(rest, salt_length[0] as usize).map_err(truncated(rest))?;
+        Ok((Self { version: version[0], kdf, salt: salt.to_vec() }, data.len() - rest.len()))
+    }
+
+}
+
+///
+/// Encrypts the plaintext with a key derived from the password, using the default
+/// kdf.
+/// 
+/// password: The password.
+/// plaintext: The data to encrypt.
+/// 
+/// result: The self-describing encrypted data or AesError::RandomUnavailable if 
+///         no salt and nonce could be generated.
+/// 
+pub fn encrypt_with_password(password: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, AesError> {
+    encrypt_with_password_kdf(password, plaintext, &Kdf::default())
+}
+
+///
+/// Encrypts the plaintext with a key derived from the password, using the given
+/// kdf. The kdf and its parameters are stored in the header.
+/// 
+/// password: The password.
+/// plaintext: The data to encrypt.
+/// kdf: The kdf and its parameters.
+/// 
+/// result: The self-describing encrypted data, AesError::InvalidKdfParameters if 
+///         the parameters are not valid or AesError::RandomUnavailable if no salt
+///         and nonce could be generated.
+/// 
+pub fn encrypt_with_password_kdf(password: &[u8], plaintext: &[u8], kdf: &Kdf) -> Result<Vec<u8>, AesError> {
+    let mut salt = [0; SALT_LENGTH];
+    fill_random(&mut salt)?;
+    let mut nonce = [0; NONCE_LENGTH];
+    fill_random(&mut nonce)?;
+    let mut output = vec![VERSION];
+    kdf.encode(&mut output);
+    output.push(SALT_LENGTH as u8);
+    output.extend_from_slice(&salt);
+    let ciphertext = cipher(kdf, password, &salt)?.encrypt(&nonce, &output, plaintext)?;
+    output.extend_from_slice(&nonce);
+    output.extend_from_slice(&ciphertext);
+    Ok(output)
+}
+
+///
+/// Decrypts data encrypted by encrypt_with_password, with the kdf described by 
+/// its header. The cost of the kdf must be within the default KdfLimits.
+/// 
+/// password: The password.
+/// data: The self-describing encrypted data.
+/// 
+/// result: The plaintext, an error of PasswordHeader::parse if the header is not
+///         valid, AesError::InvalidKdfParameters if the cost is above the 
+///         limits, AesError::InvalidDataLength if the data is too short or 
+///         AesError::AuthenticationFailed if the password is wrong or the data
+///         was modified.
+/// 
+pub fn decrypt_with_password(password: &[u8], data: &[u8]) -> Result<Vec<u8>, AesError> {
+    decrypt_with_password_limits(password, data, &KdfLimits::default())
+}
+
+///
+/// Decrypts data encrypted by encrypt_with_password, with the kdf described by 
+/// its header. The cost of the kdf is checked against the limits before the key
+/// is derived.
+/// 
+/// password: The password.
+/// data: The self-describing encrypted data.
+/// limits: The highest cost of the kdf accepted.
+/// 
+/// result: The plaintext, an error of PasswordHeader::parse if the header is not
+///         valid, AesError::InvalidKdfParameters if the cost is above the 
+///         limits, AesError::InvalidDataLength if the data is too short or 
+///         AesError::AuthenticationFailed if the password is wrong or the data
+///         was modified.
+/// 
+pub fn decrypt_with_password_limits(password: &[u8], data: &[u8], limits: &KdfLimits) -> Result<Vec<u8>, AesError> {
+    let (header, header_length) = PasswordHeader::parse(data)?;
+    let (aad, rest) = data.split_at(header_length);
+    if rest.len() < NONCE_LENGTH + TAG_LENGTH {
+        return Err(AesError::InvalidDataLength { length: data.len(), min: header_length + NONCE_LENGTH + TAG_LENGTH, multiple: 1 });
+    }
+    header.kdf.check_limits(limits)?;
+    let (nonce, ciphertext) = rest.split_at(NONCE_LENGTH);
+    cipher(&header.kdf, password, &header.salt)?.decrypt(nonce, aad, ciphertext)
+}
+
+///
+/// Creates the GCM cipher with the key derived from the password and the salt.
+/// 
+fn cipher(kdf: &Kdf, password: &[u8], salt: &[u8]) -> Result<Gcm<32>, AesError> {
+    let key = Zeroizing(kdf.derive(password, salt)?);
+    Gcm::new(key.as_slice().try_into().unwrap(), TAG_LENGTH)
+}
+
+///
+/// Splits the first length bytes off the data, or fails with 
+/// AesError::InvalidDataLength with the length as the minimum.
+/// 
+fn split(data: &[u8], length: usize) -> Result<(&[u8], &[u8]), AesError> {
+    if data.len() < length {
+        return Err(AesError::InvalidDataLength { length: data.len(), min: length, multiple: 1 });
+    }
+    Ok(data.split_at(length))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::test_util::hex;
+
+    fn kdf() -> Kdf {
+        Kdf::Scrypt(ScryptParams::new(4, 1, 1).unwrap())
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        for plaintext in [&b""[..], b"attack at dawn", &[7; 100]] {
+            let encrypted = encrypt_with_password_kdf(b"secret", plaintext, &kdf()).unwrap();
+            assert_eq!(12 + SALT_LENGTH + NONCE_LENGTH + plaintext.len() + TAG_LENGTH, encrypted.len());
+            assert_eq!(plaintext, decrypt_with_password(b"secret", &encrypted).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_header() {
+        let encrypted = encrypt_with_password_kdf(b"secret", b"data", &kdf()).unwrap();
+        let (header, length) = PasswordHeader::parse(&encrypted).unwrap();
+        assert_eq!(VERSION, header.version);
+        assert_eq!(kdf(), header.kdf);
+        assert_eq!(&encrypted[12..28], header.salt);
+        assert_eq!(28, length);
+        assert_eq!(hex("01 01 04 00000001 00000001 10"), encrypted[..12]);
+    }
+
+    #[test]
+    fn test_format() {
+        let encrypted = encrypt_with_password_kdf(b"secret", b"data", &kdf()).unwrap();
+        let mut reencrypted = encrypted[..28].to_vec();
+        let key = scrypt(b"secret", &encrypted[12..28], &ScryptParams::new(4, 1, 1).unwrap(), 32).unwrap();
+        let gcm = Gcm::<32>::new(&key.try_into().unwrap(), 16).unwrap();
+        reencrypted.extend_from_slice(&[9; 12]);
+        reencrypted.extend_from_slice(&gcm.encrypt(&[9; 12], &encrypted[..28], b"other").unwrap());
+        assert_eq!(b"other".to_vec(), decrypt_with_password(b"secret", &reencrypted).unwrap());
+    }
+
+    #[test]
+    fn test_random_salt_and_nonce() {
+        let first = encrypt_with_password_kdf(b"secret", b"data", &kdf()).unwrap();
+        let second = encrypt_with_password_kdf(b"secret", b"data", &kdf()).unwrap();
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn test_wrong_password() {
+        let encrypted = encrypt_with_password_kdf(b"secret", b"data", &kdf()).unwrap();
+        assert_eq!(Err(AesError::AuthenticationFailed), decrypt_with_password(b"Secret", &encrypted));
+    }
+
+    #[test]
+    fn test_modified_header() {
+        let mut encrypted = encrypt_with_password_kdf(b"secret", b"data", &kdf()).unwrap();
+        encrypted[20] ^= 1;
+        assert_eq!(Err(AesError::AuthenticationFailed), decrypt_with_password(b"secret", &encrypted));
+        encrypted[2] = 0;
+        assert_eq!(Err(AesError::InvalidKdfParameters), decrypt_with_password(b"secret", &encrypted));
+        encrypted[1] = 9;
+        assert_eq!(Err(AesError::InvalidKdfParameters), decrypt_with_password(b"secret", &encrypted));
+        encrypted[0] = 2;
+        assert_eq!(Err(AesError::UnsupportedVersion), decrypt_with_password(b"secret", &encrypted));
+        assert_eq!(Err(AesError::InvalidDataLength { length: 0, min: 1, multiple: 1 }), decrypt_with_password(b"secret", &[]));
+    }
+
+    #[test]
+    fn test_truncated() {
+        let encrypted = encrypt_with_password_kdf(b"secret", b"data", &kdf()).unwrap();
+        for (length, min) in [(1, 2), (5, 11), (12, 28), (27, 28), (28, 56), (55, 56)] {
+            assert_eq!(Err(AesError::InvalidDataLength { length, min, multiple: 1 }), decrypt_with_password(b"secret", &encrypted[..length]));
+        }
+    }
+
+    fn forged(kdf_id: u8, params: &[u8]) -> Vec<u8> {
+        [&[VERSION, kdf_id][..], params, &[SALT_LENGTH as u8], &[0; SALT_LENGTH], &[0; NONCE_LENGTH + 1 + TAG_LENGTH]].concat()
+    }
+
+    #[test]
+    fn test_oversized_scrypt() {
+        let encrypted = forged(SCRYPT_ID, &hex("3a 00000008 00000001"));
+        assert_eq!(57, encrypted.len());
+        assert_eq!(Err(AesError::InvalidKdfParameters), decrypt_with_password(b"secret", &encrypted));
+        let encrypted = forged(SCRYPT_ID, &ScryptParams::new(21, 8, 1).unwrap().encode());
+        assert_eq!(Err(AesError::InvalidKdfParameters), decrypt_with_password(b"secret", &encrypted));
+        let encrypted = forged(SCRYPT_ID, &ScryptParams::new(4, 1, 17).unwrap().encode());
+        assert_eq!(Err(AesError::InvalidKdfParameters), decrypt_with_password(b"secret", &encrypted));
+    }
+
+    #[test]
+    fn test_limits() {
+        let encrypted = encrypt_with_password_kdf(b"secret", b"data", &kdf()).unwrap();
+        let limits = KdfLimits { max_memory: 128 * 17 - 1, max_passes: 1 };
+        assert_eq!(Err(AesError::InvalidKdfParameters), decrypt_with_password_limits(b"secret", &encrypted, &limits));
+        let limits = KdfLimits { max_memory: 128 * 17, max_passes: 1 };
+        assert_eq!(b"data".to_vec(), decrypt_with_password_limits(b"secret", &encrypted, &limits).unwrap());
+        let limits = KdfLimits { max_memory: 1 << 40, max_passes: 1 };
+        let encrypted = forged(SCRYPT_ID, &ScryptParams::new(4, 1, 2).unwrap().encode());
+        assert_eq!(Err(AesError::InvalidKdfParameters), decrypt_with_password_limits(b"secret", &encrypted, &limits));
+    }
+
+    #[cfg(feature = "argon2")]
+    #[test]
+    fn test_oversized_argon2id() {
+        let encrypted = forged(ARGON2ID_ID, &hex("ffffffff 00000001 00000001"));
+        assert_eq!(Err(AesError::InvalidKdfParameters), decrypt_with_password(b"secret", &encrypted));
+        let encrypted = forged(ARGON2ID_ID, &Argon2Params::new(crate::argon2::MAX_MEMORY_KIB, 1, 1).unwrap().encode());
+        assert_eq!(Err(AesError::InvalidKdfParameters), decrypt_with_password(b"secret", &encrypted));
+        let encrypted = forged(ARGON2ID_ID, &Argon2Params::new(8, 17, 1).unwrap().encode());
+        assert_eq!(Err(AesError::InvalidKdfParameters), decrypt_with_password(b"secret", &encrypted));
+        let limits = KdfLimits { max_memory: 1 << 20, max_passes: 16 };
+        let encrypted = forged(ARGON2ID_ID, &Argon2Params::new(1025, 1, 1).unwrap().encode());
+        assert_eq!(Err(AesError::InvalidKdfParameters), decrypt_with_password_limits(b"secret", &encrypted, &limits));
+    }
+
+    #[cfg(feature = "argon2")]
+    #[test]
+    fn test_argon2id() {
+        let kdf = Kdf::Argon2id(Argon2Params::new(8, 1, 1).unwrap());
+        let encrypted = encrypt_with_password_kdf(b"secret", b"data", &kdf).unwrap();
+        assert_eq!(ARGON2ID_ID, encrypted[1]);
+        assert_eq!(kdf, PasswordHeader::parse(&encrypted).unwrap().0.kdf);
+        assert_eq!(b"data".to_vec(), decrypt_with_password(b"secret", &encrypted).unwrap());
+    }
+
+}
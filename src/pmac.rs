@@ -0,0 +1,172 @@
+//!
+//! PMAC, the parallelizable message authentication code of Black and Rogaway, 
+//! in the PMAC1 variant. Every full block but the last is xored with an offset 
+//! and encrypted independently, so the blocks can be processed in any order or
+//! in parallel. The sum of the encrypted blocks and the last block is encrypted
+//! to give the mac.
+//! 
+//! The offsets are derived from L = E(0) with the same doubling as Cmac and OCB.
+//! The mac can be calculated at once or incrementally with a PmacState.
+//! 
+
+use std::vec::Vec;
+
+use crate::cmac::double;
+use crate::mac::BlockBuffer;
+use crate::zeroize::Zeroize;
+use crate::{Aes, Mac, SecretKey};
+
+///
+/// Pmac is a PMAC1 with 16 byte macs.
+/// 
+pub struct Pmac<const KEY_BYTES: usize = 16> {
+    cipher: Aes<KEY_BYTES>,
+    l_inverse: [u8; 16],
+    l: Vec<[u8; 16]>
+}
+
+impl<const KEY_BYTES: usize> Pmac<KEY_BYTES> {
+
+    ///
+    /// Creates a new Pmac.
+    /// 
+    /// key: The key of the mac.
+    /// 
+    /// result: A new Pmac with the offsets derived.
+    /// 
+    pub fn new(key: &[u8; KEY_BYTES]) -> Self {
+        let cipher = Aes::new(key);
+        let l_zero: [u8; 16] = cipher.encrypt_bytes(&[0; 16]).try_into().unwrap();
+        let l_inverse = halve(&l_zero);
+        let mut l = vec![l_zero];
+        for index in 1..64 {
+            l.push(double(&l[index - 1]));
+        }
+        Self { cipher, l_inverse, l }
+    }
+
+    ///
+    /// Calculates the mac of the data.
+    /// 
+    /// data: The data to authenticate. May be of any length.
+    /// 
+    /// result: The 16 byte mac.
+    /// 
+    pub fn mac(&self, data: &[u8]) -> [u8; 16] {
+        let mut state = self.start();
+        state.update(data);
+        state.finalize()
+    }
+
+    ///
+    /// Starts an incremental mac.
+    /// 
+    /// result: A PmacState without any data.
+    /// 
+    pub fn start(&self) -> PmacState<'_, KEY_BYTES> {
+        PmacState { pmac: self, offset: [0; 16], sum: [0; 16], index: 0, buffer: BlockBuffer::new() }
+    }
+
+}
+
+impl<const KEY_BYTES: usize> From<&SecretKey<KEY_BYTES>> for Pmac<KEY_BYTES> {
+
+    fn from(key: &SecretKey<KEY_BYTES>) -> Self {
+        Pmac::new(key.expose_secret())
+    }
+
+}
+
+///
+/// PmacState is an incremental PMAC.
+/// 
+pub struct PmacState<'a, const KEY_BYTES: usize> {
+    pmac: &'a Pmac<KEY_BYTES>,
+    offset: [u8; 16],
+    sum: [u8; 16],
+    index: usize,
+    buffer: BlockBuffer
+}
+
+impl<const KEY_BYTES: usize> Mac for PmacState<'_, KEY_BYTES> {
+
+    type Output = [u8; 16];
+
+    fn update(&mut self, data: &[u8]) {
+        let (pmac, offset, sum, index) = (self.pmac, &mut self.offset, &mut self.sum, &mut self.index);
+        self.buffer.update(data, |block| {
+            *index += 1;
+            xor_into(offset, &pmac.l[index.trailing_zeros() as usize]);
+            let mut input = *offset;
+            xor_into(&mut input, block);
+            xor_into(sum, &pmac.cipher.encrypt_bytes(&input));
+        });
+    }
+
+    fn finalize(mut self) -> Self::Output {
+        let last = self.buffer.last();
+        xor_into(&mut self.sum, last);
+        if last.len() == 16 {
+            xor_into(&mut self.sum, &self.pmac.l_inverse);
+        } else {
+            self.sum[last.len()] ^= 0x80;
+        }
+        self.pmac.cipher.encrypt_bytes(&self.sum).try_into().unwrap()
+    }
+
+}
+
+///
+/// Divides the block by x in GF(2^128), the inverse of double.
+/// 
+fn halve(block: &[u8; 16]) -> [u8; 16] {
+    let value = u128::from_be_bytes(*block);
+    ((value >> 1) ^ ((value & 1) * ((1 << 127) | 0x43))).to_be_bytes()
+}
+
+///
+/// Xors the data into the block.
+/// 
+fn xor_into(block: &mut [u8; 16], data: &[u8]) {
+    block.iter_mut().zip(data).for_each(|(a, b)| *a ^= b);
+}
+
+impl<const KEY_BYTES: usize> Drop for Pmac<KEY_BYTES> {
+
+    fn drop(&mut self) {
+        self.l_inverse.zeroize();
+        self.l.zeroize();
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::test_util::hex;
+
+    #[test]
+    fn test_pmac_vectors() {
+        let pmac = Pmac::<16>::new(&(0..16).collect::<Vec<u8>>().try_into().unwrap());
+        let message: Vec<u8> = (0..1000).map(|index| index as u8).collect();
+        assert_eq!(hex("4399572cd6ea5341b8d35876a7098af7"), pmac.mac(&[]));
+        assert_eq!(hex("256ba5193c1b991b4df0c51f388a9e27"), pmac.mac(&message[..3]));
+        assert_eq!(hex("ebbd822fa458daf6dfdad7c27da76338"), pmac.mac(&message[..16]));
+        assert_eq!(hex("0412ca150bbf79058d8c75a58c993f55"), pmac.mac(&message[..20]));
+        assert_eq!(hex("e97ac04e9e5e3399ce5355cd7407bc75"), pmac.mac(&message[..32]));
+        assert_eq!(hex("5cba7d5eb24f7c86ccc54604e53d5512"), pmac.mac(&message[..34]));
+        assert_eq!(hex("01cc3529fcb42950d4327116b06dcba7"), pmac.mac(&message));
+        let mut state = pmac.start();
+        message.chunks(7).for_each(|chunk| state.update(chunk));
+        assert_eq!(hex("01cc3529fcb42950d4327116b06dcba7"), state.finalize());
+    }
+
+    #[test]
+    fn test_halve() {
+        let block = [0x5a; 16];
+        assert_eq!(block, halve(&double(&block)));
+        assert_eq!(block, double(&halve(&block)));
+    }
+
+}
@@ -0,0 +1,95 @@
+//!
+//! POLYVAL as specified in RFC 8452, the universal hash of AES-GCM-SIV. 
+//! Field elements are 16 byte little endian numbers, and the multiplication is
+//! done in GF(2^128) with the polynomial x^128 + x^127 + x^126 + x^121 + 1.
+//! 
+
+use crate::zeroize::Zeroize;
+
+///
+/// Polyval accumulates 16 byte blocks with the hash key H.
+/// 
+pub(crate) struct Polyval {
+    key: u128,
+    accumulator: u128
+}
+
+impl Polyval {
+
+    ///
+    /// Creates a new Polyval with the 16 byte hash key.
+    /// 
+    pub(crate) fn new(key: &[u8; 16]) -> Self {
+        Self { key: u128::from_le_bytes(*key), accumulator: 0 }
+    }
+
+    ///
+    /// Adds the data to the hash, padded with zeros to a multiple of 16 bytes.
+    /// 
+    pub(crate) fn update_padded(&mut self, data: &[u8]) {
+        for chunk in data.chunks(16) {
+            let mut block = [0; 16];
+            block[..chunk.len()].copy_from_slice(chunk);
+            self.accumulator = dot(self.accumulator ^ u128::from_le_bytes(block), self.key);
+        }
+    }
+
+    ///
+    /// The hash of all the blocks.
+    /// 
+    pub(crate) fn finalize(&self) -> [u8; 16] {
+        self.accumulator.to_le_bytes()
+    }
+
+}
+
+///
+/// Multiplies a and b and divides by x^128. The bits of a are added from the
+/// lowest, and the sum is divided by x after every bit.
+/// 
+fn dot(a: u128, b: u128) -> u128 {
+    let mut result = 0;
+    for bit in 0..128 {
+        if (a >> bit) & 1 == 1 {
+            result ^= b;
+        }
+        let reduce = result & 1;
+        result >>= 1;
+        if reduce == 1 {
+            result ^= 0xe1 << 120;
+        }
+    }
+    result
+}
+
+impl Drop for Polyval {
+
+    fn drop(&mut self) {
+        self.key.zeroize();
+        self.accumulator.zeroize();
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::test_util::hex;
+
+    #[test]
+    fn test_rfc8452_polyval() {
+        let mut polyval = Polyval::new(&hex("25629347589242761d31f826ba4b757b").try_into().unwrap());
+        polyval.update_padded(&hex("4f4f95668c83dfb6401762bb2d01a262 d1a24ddd2721d006bbe45f20d3c9f362"));
+        assert_eq!(hex("f7a3b47b846119fae5b7866cf5e5b77e"), polyval.finalize());
+    }
+
+    #[test]
+    fn test_dot_identity() {
+        let one_over_x128 = dot(1, 1);
+        let value = u128::from_le_bytes(hex("4f4f95668c83dfb6401762bb2d01a262").try_into().unwrap());
+        assert_eq!(dot(value, 1), dot(1, value));
+        assert_eq!(dot(dot(value, 1), 1), dot(value, one_over_x128));
+    }
+
+}
@@ -0,0 +1,88 @@
+//!
+//! Randomness from the operating system, used where the crate generates salts,
+//! nonces or keys itself. On unix it is read from /dev/urandom, on windows it 
+//! comes from BCryptGenRandom with the system preferred generator and on wasi 
+//! from random_get. Other targets have no known source, so the crate does not 
+//! compile for them rather than failing when a key is generated.
+//! 
+
+use crate::AesError;
+
+#[cfg(not(any(unix, windows, target_os = "wasi")))]
+compile_error!("No source of random bytes is known for this target");
+
+///
+/// BCRYPT_USE_SYSTEM_PREFERRED_RNG, so no algorithm handle is needed.
+/// 
+#[cfg(windows)]
+const BCRYPT_USE_SYSTEM_PREFERRED_RNG: u32 = 2;
+
+#[cfg(windows)]
+#[link(name = "bcrypt")]
+extern "system" {
+    fn BCryptGenRandom(algorithm: *mut std::ffi::c_void, buffer: *mut u8, length: u32, flags: u32) -> i32;
+}
+
+#[cfg(target_os = "wasi")]
+#[link(wasm_import_module = "wasi_snapshot_preview1")]
+extern "C" {
+    fn random_get(buffer: *mut u8, length: usize) -> u16;
+}
+
+///
+/// Fills the buffer with random bytes from the operating system.
+/// 
+/// buffer: The buffer to fill.
+/// 
+/// result: Ok or AesError::RandomUnavailable if the operating system could not 
+///         provide the bytes.
+/// 
+#[cfg(unix)]
+pub(crate) fn fill_random(buffer: &mut [u8]) -> Result<(), AesError> {
+    use std::io::Read;
+    let mut source = std::fs::File::open("/dev/urandom").map_err(|_| AesError::RandomUnavailable)?;
+    source.read_exact(buffer).map_err(|_| AesError::RandomUnavailable)
+}
+
+///
+/// Fills the buffer with random bytes from BCryptGenRandom, in chunks of at most
+/// u32::MAX bytes.
+/// 
+#[cfg(windows)]
+pub(crate) fn fill_random(buffer: &mut [u8]) -> Result<(), AesError> {
+    for chunk in buffer.chunks_mut(u32::MAX as usize) {
+        // The pointer and the length describe the chunk, which is valid for writes.
+        let status = unsafe { BCryptGenRandom(std::ptr::null_mut(), chunk.as_mut_ptr(), chunk.len() as u32, BCRYPT_USE_SYSTEM_PREFERRED_RNG) };
+        if status != 0 {
+            return Err(AesError::RandomUnavailable);
+        }
+    }
+    Ok(())
+}
+
+///
+/// Fills the buffer with random bytes from the random_get call of wasi.
+/// 
+#[cfg(target_os = "wasi")]
+pub(crate) fn fill_random(buffer: &mut [u8]) -> Result<(), AesError> {
+    // The pointer and the length describe the buffer, which is valid for writes.
+    if unsafe { random_get(buffer.as_mut_ptr(), buffer.len()) } != 0 {
+        return Err(AesError::RandomUnavailable);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_fill_random() {
+        let (mut first, mut second) = ([0u8; 32], [0u8; 32]);
+        fill_random(&mut first).unwrap();
+        fill_random(&mut second).unwrap();
+        assert_ne!(first, second);
+    }
+
+}
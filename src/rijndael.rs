@@ -0,0 +1,340 @@
+use std::fmt;
+use std::vec::Vec;
+
+use crate::key_schedule::expand_key;
+use crate::secret::Redacted;
+use crate::zeroize::Zeroize;
+use crate::{hex, AESBlock, AesError, DecryptedState, EncryptedState};
+
+///
+/// RijndaelRoundKeys is a struct containing the roundkeys for the Rijndael cipher
+/// with a block length of 16, 24 or 32 bytes. AES is Rijndael with the block length
+/// fixed to 16 bytes, while the larger blocks are used by some legacy formats.
+///
+/// The number of rounds is the largest of the key length and the block length in
+/// words added with 6.
+///
+#[derive(Clone)]
+pub struct RijndaelRoundKeys {
+    roundkeys: Vec<Vec<u8>>,
+    block_bytes: usize
+}
+
+impl RijndaelRoundKeys {
+
+    ///
+    /// Creates the roundkeys from the key for the specified block length.
+    ///
+    /// key: A key of 16, 24 or 32 bytes.
+    /// block_bytes: The block length. Must be 16, 24 or 32 bytes.
+    ///
+    /// result: The roundkeys, AesError::InvalidKeyLength if the key length is invalid
+    ///         or AesError::InvalidBlockLength if the block length is invalid.
+    ///
+    pub fn new(key: &[u8], block_bytes: usize) -> Result<RijndaelRoundKeys, AesError> {
+        if ![16, 24, 32].contains(&key.len()) {
+            return Err(AesError::InvalidKeyLength { length: key.len(), min: 16, max: 32, multiple: 8 });
+        }
+        RijndaelBlock::<DecryptedState>::validate_length(block_bytes)?;
+        let rounds = key.len().max(block_bytes) / 4 + 6;
+        let words = expand_key(key, block_bytes / 4 * (rounds + 1));
+        Ok(RijndaelRoundKeys {
+            roundkeys: words.chunks(block_bytes / 4).map(|roundkey| roundkey.as_flattened().to_vec()).collect(),
+            block_bytes
+        })
+    }
+
+    ///
+    /// Returns the number of rounds. This is one less than the number of roundkeys.
+    ///
+    /// result: Between 10 and 14 rounds.
+    ///
+    pub fn rounds(&self) -> usize {
+        self.roundkeys.len() - 1
+    }
+
+    ///
+    /// Returns the block length the roundkeys were created for.
+    ///
+    /// result: 16, 24 or 32 bytes.
+    ///
+    pub fn block_bytes(&self) -> usize {
+        self.block_bytes
+    }
+
+}
+
+///
+/// RijndaelBlock is a struct that represents a single block of 16, 24 or 32 bytes.
+/// Like AESBlock it is generic over the state, so that the data is not encrypted or
+/// decrypted twice.
+///
+/// The grid is considered to be a grid of 4 rows and 4, 6 or 8 columns in column-major
+/// order.
+///
+pub struct RijndaelBlock<State = DecryptedState> {
+    grid: Vec<u8>,
+    state: std::marker::PhantomData<State>
+}
+
+impl RijndaelBlock<DecryptedState> {
+
+    ///
+    /// Creates a new RijndaelBlock struct with the specified data.
+    ///
+    /// data: A slice of 16, 24 or 32 bytes.
+    ///
+    /// result: A RijndaelBlock struct with the specified data or AesError::InvalidBlockLength
+    ///         if the data is not 16, 24 or 32 bytes.
+    ///
+    pub fn try_new(data: &[u8]) -> Result<RijndaelBlock<DecryptedState>, AesError> {
+        Self::validate_length(data.len())?;
+        Ok(RijndaelBlock::from_grid(data.to_vec()))
+    }
+
+    ///
+    /// Full encryption of a single block.
+    ///
+    /// roundkeys: The roundkeys created for the block length.
+    ///
+    /// result: A RijndaelBlock with the data encrypted or AesError::InvalidBlockLength
+    ///         if the roundkeys were created for another block length.
+    ///
+    #[cfg(feature = "encrypt")]
+    pub fn encrypt(&self, roundkeys: &RijndaelRoundKeys) -> Result<RijndaelBlock<EncryptedState>, AesError> {
+        if roundkeys.block_bytes != self.grid.len() {
+            return Err(AesError::InvalidBlockLength { length: self.grid.len(), min: roundkeys.block_bytes, max: roundkeys.block_bytes, multiple: roundkeys.block_bytes });
+        }
+        let block = AESBlock::<DecryptedState>::from_grid([0; 16]);
+        let rounds = roundkeys.rounds();
+        let roundkeys = &roundkeys.roundkeys;
+        let mut result = RijndaelBlock::from_grid(self.grid.clone());
+        let grid = &mut result.grid;
+        block.add_roundkey(grid, &roundkeys[0]);
+        for roundkey in &roundkeys[1..rounds] {
+            block.sub_bytes(grid);
+            self.shift_grid(grid, false);
+            block.mix_columns(grid);
+            block.add_roundkey(grid, roundkey);
+        }
+        block.sub_bytes(grid);
+        self.shift_grid(grid, false);
+        block.add_roundkey(grid, &roundkeys[rounds]);
+        Ok(result)
+    }
+
+}
+
+impl RijndaelBlock<EncryptedState> {
+
+    ///
+    /// Creates a new RijndaelBlock struct with the specified data.
+    ///
+    /// data: A slice of 16, 24 or 32 bytes.
+    ///
+    /// result: A RijndaelBlock struct with the specified data or AesError::InvalidBlockLength
+    ///         if the data is not 16, 24 or 32 bytes.
+    ///
+    pub fn try_new(data: &[u8]) -> Result<RijndaelBlock<EncryptedState>, AesError> {
+        Self::validate_length(data.len())?;
+        Ok(RijndaelBlock::from_grid(data.to_vec()))
+    }
+
+    ///
+    /// Full decryption of a single block.
+    ///
+    /// roundkeys: The roundkeys created for the block length.
+    ///
+    /// result: A RijndaelBlock with the data decrypted or AesError::InvalidBlockLength
+    ///         if the roundkeys were created for another block length.
+    ///
+    #[cfg(feature = "decrypt")]
+    pub fn decrypt(&self, roundkeys: &RijndaelRoundKeys) -> Result<RijndaelBlock<DecryptedState>, AesError> {
+        if roundkeys.block_bytes != self.grid.len() {
+            return Err(AesError::InvalidBlockLength { length: self.grid.len(), min: roundkeys.block_bytes, max: roundkeys.block_bytes, multiple: roundkeys.block_bytes });
+        }
+        let block = AESBlock::<EncryptedState>::from_grid([0; 16]);
+        let rounds = roundkeys.rounds();
+        let roundkeys = &roundkeys.roundkeys;
+        let mut result = RijndaelBlock::from_grid(self.grid.clone());
+        let grid = &mut result.grid;
+        block.add_roundkey(grid, &roundkeys[rounds]);
+        for roundkey in roundkeys[1..rounds].iter().rev() {
+            self.shift_grid(grid, true);
+            block.sub_bytes(grid);
+            block.add_roundkey(grid, roundkey);
+            block.mix_columns(grid);
+        }
+        self.shift_grid(grid, true);
+        block.sub_bytes(grid);
+        block.add_roundkey(grid, &roundkeys[0]);
+        Ok(result)
+    }
+
+}
+
+impl<State> RijndaelBlock<State> {
+
+    ///
+    /// Creates a new RijndaelBlock struct from a grid known to be valid.
+    ///
+    fn from_grid(grid: Vec<u8>) -> RijndaelBlock<State> {
+        RijndaelBlock {
+            grid,
+            state: std::marker::PhantomData::<State>
+        }
+    }
+
+    ///
+    /// Validates that the block length is 16, 24 or 32 bytes.
+    ///
+    fn validate_length(block_bytes: usize) -> Result<(), AesError> {
+        if ![16, 24, 32].contains(&block_bytes) {
+            return Err(AesError::InvalidBlockLength { length: block_bytes, min: 16, max: 32, multiple: 8 });
+        }
+        Ok(())
+    }
+
+    ///
+    /// Shifts the rows of the grid to the left. The offsets of the rows depend
+    /// on the number of columns:
+    /// 4 and 6 columns: rows are shifted 0, 1, 2 and 3 times.
+    /// 8 columns: rows are shifted 0, 1, 3 and 4 times.
+    ///
+    /// data: The 16, 24 or 32 bytes in column-major order, shifted in place.
+    /// inverse: Shifts the rows to the right instead when true.
+    ///
+    fn shift_grid(&self, data: &mut [u8], inverse: bool) {
+        let block = AESBlock::<State>::from_grid([0; 16]);
+        let columns = data.len() / 4;
+        let offsets: [usize; 4] = if columns == 8 { [0, 1, 3, 4] } else { [0, 1, 2, 3] };
+        for (idx, offset) in offsets.iter().enumerate() {
+            let mut row = [0; 8];
+            let row = &mut row[..columns];
+            for (column, value) in row.iter_mut().enumerate() {
+                *value = data[column * 4 + idx];
+            }
+            let shift = if inverse { (columns - offset) % columns } else { *offset };
+            block.shift_row(row, &shift);
+            for (column, value) in row.iter().enumerate() {
+                data[column * 4 + idx] = *value;
+            }
+            row.zeroize();
+        }
+    }
+
+}
+
+impl fmt::Debug for RijndaelRoundKeys {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RijndaelRoundKeys")
+            .field("rounds", &self.rounds())
+            .field("block_bytes", &self.block_bytes)
+            .field("roundkeys", &Redacted(self.roundkeys.len() * self.block_bytes))
+            .finish()
+    }
+
+}
+
+impl fmt::Debug for RijndaelBlock<DecryptedState> {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("RijndaelBlock<DecryptedState>").field(&Redacted(self.grid.len())).finish()
+    }
+
+}
+
+impl fmt::Debug for RijndaelBlock<EncryptedState> {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "RijndaelBlock<EncryptedState>({})", hex::encode(&self.grid))
+    }
+
+}
+
+impl Drop for RijndaelRoundKeys {
+
+    fn drop(&mut self) {
+        self.roundkeys.zeroize();
+    }
+
+}
+
+impl<State> Drop for RijndaelBlock<State> {
+
+    fn drop(&mut self) {
+        self.grid.zeroize();
+    }
+
+}
+
+#[cfg(all(test, feature = "encrypt", feature = "decrypt"))]
+mod tests {
+
+    use super::*;
+
+    fn known_answer(key_bytes: usize, block_bytes: usize, expected: &[u8]) {
+        let key: Vec<u8> = (0..key_bytes as u8).collect();
+        let plaintext: Vec<u8> = (0..block_bytes as u8).collect();
+        let roundkeys = RijndaelRoundKeys::new(&key, block_bytes).unwrap();
+        let encrypted = RijndaelBlock::<DecryptedState>::try_new(&plaintext).unwrap().encrypt(&roundkeys).unwrap();
+        assert_eq!(expected.to_vec(), encrypted.grid);
+        let decrypted = encrypted.decrypt(&roundkeys).unwrap();
+        assert_eq!(plaintext, decrypted.grid);
+    }
+
+    #[test]
+    fn test_rounds() {
+        assert_eq!(10, RijndaelRoundKeys::new(&[0; 16], 16).unwrap().rounds());
+        assert_eq!(12, RijndaelRoundKeys::new(&[0; 16], 24).unwrap().rounds());
+        assert_eq!(14, RijndaelRoundKeys::new(&[0; 16], 32).unwrap().rounds());
+        assert_eq!(12, RijndaelRoundKeys::new(&[0; 24], 16).unwrap().rounds());
+        assert_eq!(14, RijndaelRoundKeys::new(&[0; 32], 24).unwrap().rounds());
+    }
+
+    #[test]
+    fn test_invalid_lengths() {
+        assert_eq!(Some(AesError::InvalidKeyLength { length: 20, min: 16, max: 32, multiple: 8 }), RijndaelRoundKeys::new(&[0; 20], 16).err());
+        assert_eq!(Some(AesError::InvalidBlockLength { length: 20, min: 16, max: 32, multiple: 8 }), RijndaelRoundKeys::new(&[0; 16], 20).err());
+        assert_eq!(Some(AesError::InvalidBlockLength { length: 20, min: 16, max: 32, multiple: 8 }), RijndaelBlock::<DecryptedState>::try_new(&[0; 20]).err());
+        let roundkeys = RijndaelRoundKeys::new(&[0; 16], 24).unwrap();
+        let block = RijndaelBlock::<DecryptedState>::try_new(&[0; 32]).unwrap();
+        assert_eq!(Some(AesError::InvalidBlockLength { length: 32, min: 24, max: 24, multiple: 24 }), block.encrypt(&roundkeys).err());
+    }
+
+    #[test]
+    fn test_shift_grid_256() {
+        let block = RijndaelBlock::<DecryptedState>::try_new(&[0; 32]).unwrap();
+        let data: Vec<u8> = (0..32).collect();
+        let mut result = data.clone();
+        block.shift_grid(&mut result, false);
+        assert_eq!(vec![0, 5, 14, 19, 4, 9, 18, 23, 8, 13, 22, 27, 12, 17, 26, 31,
+                        16, 21, 30, 3, 20, 25, 2, 7, 24, 29, 6, 11, 28, 1, 10, 15], result);
+        block.shift_grid(&mut result, true);
+        assert_eq!(data, result);
+    }
+
+    #[test]
+    fn test_aes_compatible() {
+        known_answer(16, 16, &[0x0a, 0x94, 0x0b, 0xb5, 0x41, 0x6e, 0xf0, 0x45, 0xf1, 0xc3, 0x94, 0x58, 0xc6, 0x53, 0xea, 0x5a]);
+    }
+
+    #[test]
+    fn test_block_192() {
+        known_answer(16, 24, &[0x54, 0x03, 0x06, 0x26, 0xe3, 0x66, 0xbb, 0xa5, 0x82, 0x7f, 0x46, 0xbe, 0x06, 0x0b, 0x53, 0xc7, 0x56, 0x68, 0xfc, 0x25, 0xfb, 0x1a, 0x60, 0x74]);
+        known_answer(24, 24, &[0x7a, 0x5a, 0x73, 0xc8, 0xfb, 0xdb, 0xb2, 0xaa, 0x68, 0x66, 0xcc, 0x95, 0x1b, 0x3e, 0x05, 0x9a, 0x63, 0x1c, 0xfe, 0xfc, 0x09, 0xc4, 0x24, 0xcf]);
+        known_answer(32, 24, &[0xb5, 0xe5, 0xbb, 0x69, 0x8a, 0x33, 0xa8, 0x0e, 0x4d, 0xae, 0xd2, 0x56, 0x76, 0x0f, 0x1a, 0x5f, 0x08, 0xcc, 0x6f, 0x18, 0x1e, 0x67, 0xb5, 0xbc]);
+    }
+
+    #[test]
+    fn test_block_256() {
+        known_answer(16, 32, &[0x21, 0xc8, 0x9c, 0x4a, 0x7a, 0xe3, 0x7f, 0x18, 0x55, 0x97, 0x36, 0x2e, 0x5d, 0x20, 0x48, 0x5f,
+                               0x61, 0x44, 0xaf, 0xed, 0x71, 0xbd, 0x4a, 0x79, 0x86, 0x88, 0x66, 0x2e, 0x6c, 0xde, 0x7d, 0xc4]);
+        known_answer(24, 32, &[0xd4, 0xcc, 0x0b, 0x07, 0x0e, 0xbe, 0xbd, 0x98, 0xff, 0xa1, 0xc2, 0x8e, 0x40, 0xbf, 0xfa, 0x5d,
+                               0xb8, 0xbd, 0xb8, 0xfb, 0x5b, 0xfb, 0x6c, 0xcf, 0x23, 0xaf, 0x2c, 0x16, 0x08, 0x96, 0x7a, 0xcc]);
+        known_answer(32, 32, &[0x62, 0x3d, 0x2b, 0xd4, 0xca, 0x37, 0x96, 0xdc, 0x3d, 0x02, 0xec, 0xf2, 0xf3, 0x7f, 0xb6, 0x37,
+                               0xfd, 0x3d, 0xa5, 0x85, 0x09, 0xce, 0xbb, 0x67, 0xab, 0x92, 0x65, 0xb0, 0x4d, 0xb5, 0x1e, 0x7d]);
+    }
+
+}
@@ -0,0 +1,297 @@
+use std::fmt;
+use std::vec::Vec;
+
+use crate::secret::Redacted;
+use crate::zeroize::Zeroize;
+#[cfg(feature = "decrypt")]
+use crate::{AESBlock, EncryptedState};
+use crate::AesError;
+
+///
+/// RoundKeys is a struct containing a validated set of roundkeys. It is guaranteed
+/// to contain 11, 13 or 15 roundkeys of 16 bytes.
+/// 
+/// RoundKeys is either created from a KeySchedule or from a set of roundkeys
+/// created by other means using the new function.
+/// 
+#[derive(Clone)]
+pub struct RoundKeys {
+    roundkeys: Vec<[u8; 16]>
+}
+
+impl RoundKeys {
+
+    ///
+    /// Creates a new RoundKeys struct from the specified roundkeys.
+    /// 
+    /// roundkeys: A vector of 11, 13 or 15 roundkeys of 16 bytes.
+    /// 
+    /// result: A RoundKeys struct or AesError::InvalidRoundKeys if the number of
+    ///         roundkeys is wrong.
+    /// 
+    pub fn new(roundkeys: Vec<[u8; 16]>) -> Result<RoundKeys, AesError> {
+        if ![11, 13, 15].contains(&roundkeys.len()) {
+            return Err(AesError::InvalidRoundKeys);
+        }
+        Ok(RoundKeys {
+            roundkeys
+        })
+    }
+
+    ///
+    /// Creates a new RoundKeys struct without validation. Only used for roundkeys
+    /// that are valid by construction.
+    /// 
+    pub(crate) fn from_valid(roundkeys: Vec<[u8; 16]>) -> RoundKeys {
+        RoundKeys {
+            roundkeys
+        }
+    }
+
+    ///
+    /// Returns the number of rounds. This is one less than the number of roundkeys.
+    /// 
+    /// result: 10, 12 or 14 rounds.
+    /// 
+    pub fn rounds(&self) -> usize {
+        self.roundkeys.len() - 1
+    }
+
+    ///
+    /// Returns the roundkeys.
+    /// 
+    /// result: A slice of 11, 13 or 15 roundkeys of 16 bytes.
+    /// 
+    pub fn as_slice(&self) -> &[[u8; 16]] {
+        &self.roundkeys
+    }
+
+    ///
+    /// Serializes the roundkeys by concatenating them, so that an expanded key can
+    /// be stored or sent to another process.
+    /// 
+    /// result: A vector of 176, 208 or 240 bytes.
+    /// 
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.roundkeys.as_flattened().to_vec()
+    }
+
+    ///
+    /// Creates the roundkeys from bytes created by to_bytes.
+    /// 
+    /// bytes: A slice of 176, 208 or 240 bytes.
+    /// 
+    /// result: A RoundKeys struct or AesError::InvalidRoundKeys if the number of
+    ///         bytes is wrong.
+    /// 
+    pub fn from_bytes(bytes: &[u8]) -> Result<RoundKeys, AesError> {
+        if !bytes.len().is_multiple_of(16) {
+            return Err(AesError::InvalidRoundKeys);
+        }
+        RoundKeys::new(bytes.chunks(16).map(|roundkey| roundkey.try_into().unwrap()).collect())
+    }
+
+    ///
+    /// Creates roundkeys for a reduced round cipher by keeping only the first
+    /// rounds + 1 roundkeys.
+    /// 
+    /// WARNING: A reduced round cipher is NOT secure. This is only meant for studying
+    /// the cipher, for example differential trails in 4 round AES.
+    /// 
+    /// rounds: The number of rounds, between 1 and the number of rounds of the roundkeys.
+    /// 
+    /// result: The reduced roundkeys or AesError::InvalidRoundKeys if the number of rounds
+    ///         is out of range.
+    /// 
+    #[cfg(feature = "insecure-research")]
+    pub fn with_rounds(&self, rounds: usize) -> Result<RoundKeys, AesError> {
+        if rounds == 0 || rounds > self.rounds() {
+            return Err(AesError::InvalidRoundKeys);
+        }
+        Ok(RoundKeys {
+            roundkeys: self.roundkeys[..=rounds].to_vec()
+        })
+    }
+
+    ///
+    /// Creates a new RoundKeys struct for a reduced round cipher from the specified
+    /// roundkeys.
+    /// 
+    /// WARNING: A reduced round cipher is NOT secure. This is only meant for studying
+    /// the cipher.
+    /// 
+    /// roundkeys: A vector of 2 to 15 roundkeys of 16 bytes.
+    /// 
+    /// result: A RoundKeys struct or AesError::InvalidRoundKeys if the number of
+    ///         roundkeys is wrong.
+    /// 
+    #[cfg(feature = "insecure-research")]
+    pub fn new_reduced(roundkeys: Vec<[u8; 16]>) -> Result<RoundKeys, AesError> {
+        if !(2..=15).contains(&roundkeys.len()) {
+            return Err(AesError::InvalidRoundKeys);
+        }
+        Ok(RoundKeys {
+            roundkeys
+        })
+    }
+
+}
+
+///
+/// InverseRoundKeys is a struct containing the roundkeys used by the equivalent inverse
+/// cipher described in FIPS-197 section 5.3.5. The roundkeys are the same as the
+/// encryption roundkeys except that the inverse column mixing is applied to all but
+/// the first and last roundkey. This lets decryption use the same ordering of the
+/// transformations in each round as encryption.
+/// 
+#[cfg(feature = "decrypt")]
+#[derive(Clone)]
+pub struct InverseRoundKeys {
+    roundkeys: Vec<[u8; 16]>
+}
+
+#[cfg(feature = "decrypt")]
+impl InverseRoundKeys {
+
+    ///
+    /// Creates a new InverseRoundKeys struct from the encryption roundkeys.
+    /// 
+    /// roundkeys: The validated encryption roundkeys.
+    /// 
+    /// result: The roundkeys for the equivalent inverse cipher.
+    /// 
+    pub fn new(roundkeys: &RoundKeys) -> InverseRoundKeys {
+        let rounds = roundkeys.rounds();
+        let block = AESBlock::<EncryptedState>::from_grid([0; 16]);
+        InverseRoundKeys {
+            roundkeys: roundkeys.as_slice().iter().enumerate().map(|(idx, roundkey)| {
+                let mut roundkey = *roundkey;
+                if idx != 0 && idx != rounds {
+                    block.mix_columns(&mut roundkey);
+                }
+                roundkey
+            }).collect()
+        }
+    }
+
+    ///
+    /// Returns the number of rounds. This is one less than the number of roundkeys.
+    /// 
+    /// result: 10, 12 or 14 rounds.
+    /// 
+    pub fn rounds(&self) -> usize {
+        self.roundkeys.len() - 1
+    }
+
+    ///
+    /// Returns the roundkeys.
+    /// 
+    /// result: A slice of 11, 13 or 15 roundkeys of 16 bytes.
+    /// 
+    pub fn as_slice(&self) -> &[[u8; 16]] {
+        &self.roundkeys
+    }
+
+}
+
+impl fmt::Debug for RoundKeys {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RoundKeys")
+            .field("rounds", &self.rounds())
+            .field("roundkeys", &Redacted(self.roundkeys.len() * 16))
+            .finish()
+    }
+
+}
+
+#[cfg(feature = "decrypt")]
+impl fmt::Debug for InverseRoundKeys {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InverseRoundKeys")
+            .field("rounds", &self.rounds())
+            .field("roundkeys", &Redacted(self.roundkeys.len() * 16))
+            .finish()
+    }
+
+}
+
+impl Drop for RoundKeys {
+
+    fn drop(&mut self) {
+        self.roundkeys.zeroize();
+    }
+
+}
+
+#[cfg(feature = "decrypt")]
+impl Drop for InverseRoundKeys {
+
+    fn drop(&mut self) {
+        self.roundkeys.zeroize();
+    }
+
+}
+
+#[cfg(all(test, feature = "encrypt", feature = "decrypt"))]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_valid_roundkeys() {
+        for count in [11, 13, 15] {
+            let roundkeys = RoundKeys::new(vec![[0; 16]; count]).unwrap();
+            assert_eq!(count - 1, roundkeys.rounds());
+            assert_eq!(count, roundkeys.as_slice().len());
+        }
+    }
+
+    #[test]
+    fn test_inverse_roundkeys() {
+        let mut keys = vec![[1; 16]; 11];
+        keys[5] = [142, 77, 161, 188, 159, 220, 88, 157, 1, 1, 1, 1, 198, 198, 198, 198];
+        keys[10] = keys[5];
+        let inverse = InverseRoundKeys::new(&RoundKeys::new(keys.clone()).unwrap());
+        assert_eq!(10, inverse.rounds());
+        assert_eq!(keys[0], inverse.as_slice()[0]);
+        assert_eq!(keys[10], inverse.as_slice()[10]);
+        assert_eq!([219, 19, 83, 69, 242, 10, 34, 92, 1, 1, 1, 1, 198, 198, 198, 198], inverse.as_slice()[5]);
+    }
+
+    #[test]
+    fn test_to_from_bytes() {
+        let roundkeys = RoundKeys::new((0..13).map(|idx| [idx as u8; 16]).collect()).unwrap();
+        let bytes = roundkeys.to_bytes();
+        assert_eq!(208, bytes.len());
+        assert_eq!(roundkeys.as_slice(), RoundKeys::from_bytes(&bytes).unwrap().as_slice());
+        assert_eq!(Some(AesError::InvalidRoundKeys), RoundKeys::from_bytes(&bytes[..207]).err());
+        assert_eq!(Some(AesError::InvalidRoundKeys), RoundKeys::from_bytes(&bytes[..192]).err());
+    }
+
+    #[test]
+    fn test_invalid_roundkey_count() {
+        assert_eq!(Some(AesError::InvalidRoundKeys), RoundKeys::new(vec![[0; 16]; 10]).err());
+        assert_eq!(Some(AesError::InvalidRoundKeys), RoundKeys::new(vec![[0; 16]; 12]).err());
+        assert_eq!(Some(AesError::InvalidRoundKeys), RoundKeys::new(vec![]).err());
+    }
+
+    #[cfg(feature = "insecure-research")]
+    #[test]
+    fn test_with_rounds() {
+        let roundkeys = RoundKeys::new(vec![[3; 16]; 15]).unwrap();
+        assert_eq!(4, roundkeys.with_rounds(4).unwrap().rounds());
+        assert_eq!(1, roundkeys.with_rounds(1).unwrap().rounds());
+        assert_eq!(Some(AesError::InvalidRoundKeys), roundkeys.with_rounds(0).err());
+        assert_eq!(Some(AesError::InvalidRoundKeys), roundkeys.with_rounds(15).err());
+    }
+
+    #[cfg(feature = "insecure-research")]
+    #[test]
+    fn test_new_reduced() {
+        assert_eq!(4, RoundKeys::new_reduced(vec![[0; 16]; 5]).unwrap().rounds());
+        assert_eq!(Some(AesError::InvalidRoundKeys), RoundKeys::new_reduced(vec![[0; 16]; 1]).err());
+    }
+
+}
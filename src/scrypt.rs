@@ -0,0 +1,257 @@
+//!
+//! The scrypt password based key derivation function, as specified in RFC 7914.
+//! scrypt is memory hard through the ROMix function over Salsa20/8, with PBKDF2
+//! and HMAC-SHA256 before and after it.
+//! 
+//! Argon2id is preferred for new data, scrypt is offered for compatibility with
+//! existing encrypted files. ScryptParams can be encoded into a header like 
+//! Argon2Params.
+//! 
+
+use std::vec::Vec;
+
+use crate::hmac::pbkdf2_sha256;
+use crate::zeroize::{Zeroize, Zeroizing};
+use crate::AesError;
+
+///
+/// The largest memory in bytes scrypt may use, 4 GiB. Parameters needing more are
+/// rejected, so a forged header cannot make the derivation allocate without bound.
+/// 
+pub const MAX_MEMORY: u64 = 1 << 32;
+
+///
+/// The cost parameters of scrypt.
+/// 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScryptParams {
+    ///
+    /// The base 2 logarithm of the cost N, 1 to 63, limited further by MAX_MEMORY.
+    /// 
+    pub log_n: u8,
+    ///
+    /// The block size r, at least 1.
+    /// 
+    pub block_size: u32,
+    ///
+    /// The parallelism p, at least 1.
+    /// 
+    pub parallelism: u32
+}
+
+impl ScryptParams {
+
+    ///
+    /// The length of the encoded parameters in bytes.
+    /// 
+    pub const ENCODED_LENGTH: usize = 9;
+
+    ///
+    /// Creates new parameters.
+    /// 
+    /// log_n: The base 2 logarithm of the cost.
+    /// block_size: The block size.
+    /// parallelism: The parallelism.
+    /// 
+    /// result: The parameters or AesError::InvalidKdfParameters if they are not valid.
+    /// 
+    pub fn new(log_n: u8, block_size: u32, parallelism: u32) -> Result<Self, AesError> {
+        let params = Self { log_n, block_size, parallelism };
+        params.validate()?;
+        Ok(params)
+    }
+
+    ///
+    /// Encodes the parameters as log_n in one byte, then the block size and the 
+    /// parallelism, each a 4 byte big endian number.
+    /// 
+    /// result: The 9 byte encoding.
+    /// 
+    pub fn encode(&self) -> [u8; 9] {
+        let mut encoded = [0; 9];
+        encoded[0] = self.log_n;
+        encoded[1..5].copy_from_slice(&self.block_size.to_be_bytes());
+        encoded[5..].copy_from_slice(&self.parallelism.to_be_bytes());
+        encoded
+    }
+
+    ///
+    /// Decodes parameters encoded by encode.
+    /// 
+    /// encoded: The 9 byte encoding.
+    /// 
+    /// result: The parameters or AesError::InvalidKdfParameters if they are not valid.
+    /// 
+    pub fn decode(encoded: &[u8; 9]) -> Result<Self, AesError> {
+        let word = |index: usize| u32::from_be_bytes(encoded[index..index + 4].try_into().unwrap());
+        Self::new(encoded[0], word(1), word(5))
+    }
+
+    ///
+    /// The memory the derivation uses, the 128 r N bytes of the ROMix table and the
+    /// 128 r p bytes of the blocks.
+    /// 
+    /// result: The memory in bytes or None if it does not fit in usize.
+    /// 
+    pub fn memory_bytes(&self) -> Option<usize> {
+        let cost = 1usize.checked_shl(self.log_n as u32)?;
+        let block_bytes = 128usize.checked_mul(usize::try_from(self.block_size).ok()?)?;
+        block_bytes.checked_mul(cost.checked_add(usize::try_from(self.parallelism).ok()?)?)
+    }
+
+    ///
+    /// Checks the limits of RFC 7914, N < 2^(128 r / 8) and r p < 2^30, and that 
+    /// the memory is at most MAX_MEMORY.
+    /// 
+    fn validate(&self) -> Result<(), AesError> {
+        if !(1..64).contains(&self.log_n) || self.block_size == 0 || self.parallelism == 0 {
+            return Err(AesError::InvalidKdfParameters);
+        }
+        if self.log_n as u64 >= 16 * self.block_size as u64 || self.block_size as u64 * self.parallelism as u64 >= 1 << 30 {
+            return Err(AesError::InvalidKdfParameters);
+        }
+        match self.memory_bytes() {
+            Some(memory) if memory as u64 <= MAX_MEMORY => Ok(()),
+            _ => Err(AesError::InvalidKdfParameters)
+        }
+    }
+
+}
+
+impl Default for ScryptParams {
+
+    ///
+    /// The interactive parameters of the scrypt paper, N = 2^15, r = 8 and p = 1.
+    /// 
+    fn default() -> Self {
+        Self { log_n: 15, block_size: 8, parallelism: 1 }
+    }
+
+}
+
+///
+/// Derives a key from the password with scrypt.
+/// 
+/// password: The password.
+/// salt: The salt, unique for every password.
+/// params: The cost parameters.
+/// length: The length of the key.
+/// 
+/// result: The key or AesError::InvalidKdfParameters if the parameters are not 
+///         valid or the memory could not be allocated.
+/// 
+pub fn scrypt(password: &[u8], salt: &[u8], params: &ScryptParams, length: usize) -> Result<Vec<u8>, AesError> {
+    params.validate()?;
+    let block_bytes = 128 * params.block_size as usize;
+    let mut blocks = Zeroizing(pbkdf2_sha256(password, salt, 1, block_bytes * params.parallelism as usize));
+    for block in blocks.chunks_mut(block_bytes) {
+        ro_mix(block, 1 << params.log_n)?;
+    }
+    Ok(pbkdf2_sha256(password, &blocks, 1, length))
+}
+
+///
+/// The ROMix function, mixing the block with a table of N earlier states.
+/// 
+/// result: Ok or AesError::InvalidKdfParameters if the table could not be allocated.
+/// 
+fn ro_mix(block: &mut [u8], cost: usize) -> Result<(), AesError> {
+    let mut x: Vec<u32> = block.chunks(4).map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap())).collect();
+    let length = cost.checked_mul(x.len()).ok_or(AesError::InvalidKdfParameters)?;
+    let mut table = Vec::new();
+    table.try_reserve_exact(length).map_err(|_| AesError::InvalidKdfParameters)?;
+    for _ in 0..cost {
+        table.extend_from_slice(&x);
+        x = block_mix(&x);
+    }
+    for _ in 0..cost {
+        let start = integerify(&x) % cost * x.len();
+        x.iter_mut().zip(&table[start..]).for_each(|(a, b)| *a ^= b);
+        x = block_mix(&x);
+    }
+    for (bytes, word) in block.chunks_mut(4).zip(&x) {
+        bytes.copy_from_slice(&word.to_le_bytes());
+    }
+    x.zeroize();
+    table.zeroize();
+    Ok(())
+}
+
+///
+/// The BlockMix function over Salsa20/8, with the outputs of the even blocks 
+/// first and of the odd blocks after.
+/// 
+fn block_mix(block: &[u32]) -> Vec<u32> {
+    let count = block.len() / 16;
+    let mut x: [u32; 16] = block[block.len() - 16..].try_into().unwrap();
+    let mut output = vec![0; block.len()];
+    for (index, chunk) in block.chunks(16).enumerate() {
+        x.iter_mut().zip(chunk).for_each(|(a, b)| *a ^= b);
+        salsa20_8(&mut x);
+        let position = (index / 2 + (index % 2) * count / 2) * 16;
+        output[position..position + 16].copy_from_slice(&x);
+    }
+    output
+}
+
+///
+/// The first 64 bits of the last 64 byte block as a little endian number.
+/// 
+fn integerify(block: &[u32]) -> usize {
+    let last = block.len() - 16;
+    (block[last] as u64 | (block[last + 1] as u64) << 32) as usize
+}
+
+///
+/// The Salsa20/8 core, 8 rounds of Salsa20 added to the input.
+/// 
+fn salsa20_8(block: &mut [u32; 16]) {
+    let mut x = *block;
+    for _ in 0..4 {
+        for (a, b, c, d) in [(0, 4, 8, 12), (5, 9, 13, 1), (10, 14, 2, 6), (15, 3, 7, 11), (0, 1, 2, 3), (5, 6, 7, 4), (10, 11, 8, 9), (15, 12, 13, 14)] {
+            x[b] ^= x[a].wrapping_add(x[d]).rotate_left(7);
+            x[c] ^= x[b].wrapping_add(x[a]).rotate_left(9);
+            x[d] ^= x[c].wrapping_add(x[b]).rotate_left(13);
+            x[a] ^= x[d].wrapping_add(x[c]).rotate_left(18);
+        }
+    }
+    block.iter_mut().zip(x).for_each(|(a, b)| *a = a.wrapping_add(b));
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::test_util::hex;
+
+    #[test]
+    fn test_rfc7914_vectors() {
+        let expected = hex("77d6576238657b203b19ca42c18a0497f16b4844e3074ae8dfdffa3fede21442fcd0069ded0948f8326a753a0fc81f17e8d3e0fb2e0d3628cf35e20c38d18906");
+        assert_eq!(expected, scrypt(&[], &[], &ScryptParams::new(4, 1, 1).unwrap(), 64).unwrap());
+        let expected = hex("fdbabe1c9d3472007856e7190d01e9fe7c6ad7cbc8237830e77376634b3731622eaf30d92e22a3886ff109279d9830dac727afb94a83ee6d8360cbdfa2cc0640");
+        assert_eq!(expected, scrypt(b"password", b"NaCl", &ScryptParams::new(10, 8, 16).unwrap(), 64).unwrap());
+    }
+
+    #[test]
+    fn test_params() {
+        let params = ScryptParams::default();
+        assert_eq!(Ok(params), ScryptParams::decode(&params.encode()));
+        assert_eq!(Some(AesError::InvalidKdfParameters), ScryptParams::new(0, 8, 1).err());
+        assert_eq!(Some(AesError::InvalidKdfParameters), ScryptParams::new(16, 1, 1).err());
+        assert_eq!(Some(AesError::InvalidKdfParameters), ScryptParams::new(10, 0, 1).err());
+        assert_eq!(Some(AesError::InvalidKdfParameters), ScryptParams::new(10, 1 << 15, 1 << 15).err());
+        assert_eq!(Some(8 * 128 * ((1 << 15) + 1)), params.memory_bytes());
+    }
+
+    #[test]
+    fn test_memory_limit() {
+        assert_eq!(Some(AesError::InvalidKdfParameters), ScryptParams::new(58, 8, 1).err());
+        assert_eq!(Some(AesError::InvalidKdfParameters), ScryptParams::new(63, 8, 1).err());
+        assert_eq!(None, ScryptParams { log_n: 63, block_size: 8, parallelism: 1 }.memory_bytes());
+        assert!(ScryptParams::new(24, 8, 1).is_err());
+        assert!(ScryptParams::new(21, 8, 1).is_ok());
+        let forged = ScryptParams { log_n: 58, block_size: 8, parallelism: 1 };
+        assert_eq!(Err(AesError::InvalidKdfParameters), scrypt(b"password", b"salt", &forged, 32));
+    }
+
+}
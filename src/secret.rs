@@ -0,0 +1,169 @@
+use std::fmt;
+
+use crate::random::fill_random;
+use crate::zeroize::Zeroize;
+use crate::{Aes, AesError};
+
+///
+/// SecretKey holds the bytes of a 16, 24 or 32 byte key. It can not be printed,
+/// Debug and Display only show that the key is redacted, so it is not written to
+/// logs by accident. The bytes are only available through expose_secret, which 
+/// makes every use of the raw key explicit, and they are wiped when the key is 
+/// dropped.
+/// 
+/// The ciphers take the key as a byte array, so it is passed as 
+/// Gcm::new(key.expose_secret(), 16), or with Aes::from(&key).
+/// 
+#[derive(Clone)]
+pub struct SecretKey<const KEY_BYTES: usize = 16> {
+    key: [u8; KEY_BYTES]
+}
+
+impl<const KEY_BYTES: usize> SecretKey<KEY_BYTES> {
+
+    ///
+    /// Creates a new SecretKey. The array is copied, so the caller should wipe it
+    /// if it is kept.
+    /// 
+    /// key: The key.
+    /// 
+    /// result: A new SecretKey.
+    /// 
+    pub fn new(key: [u8; KEY_BYTES]) -> Self {
+        Self { key }
+    }
+
+    ///
+    /// Generates a new random key with the randomness of the operating system.
+    /// 
+    /// result: A new SecretKey or AesError::RandomUnavailable if the operating 
+    ///         system could not provide the bytes.
+    /// 
+    pub fn generate() -> Result<Self, AesError> {
+        let mut key = Self::new([0; KEY_BYTES]);
+        fill_random(&mut key.key)?;
+        Ok(key)
+    }
+
+    ///
+    /// Creates a new SecretKey from a slice, for example key material returned by
+    /// a key derivation function.
+    /// 
+    /// key: The key, exactly KEY_BYTES bytes.
+    /// 
+    /// result: A new SecretKey or AesError::InvalidKeyLength if the slice has 
+    ///         another length.
+    /// 
+    pub fn from_slice(key: &[u8]) -> Result<Self, AesError> {
+        Ok(Self::new(key.try_into().map_err(|_| AesError::InvalidKeyLength { length: key.len(), min: KEY_BYTES, max: KEY_BYTES, multiple: KEY_BYTES })?))
+    }
+
+    ///
+    /// Returns the bytes of the key.
+    /// 
+    /// result: The key.
+    /// 
+    pub fn expose_secret(&self) -> &[u8; KEY_BYTES] {
+        &self.key
+    }
+
+}
+
+impl<const KEY_BYTES: usize> From<[u8; KEY_BYTES]> for SecretKey<KEY_BYTES> {
+
+    fn from(key: [u8; KEY_BYTES]) -> Self {
+        Self::new(key)
+    }
+
+}
+
+impl<const KEY_BYTES: usize> From<&SecretKey<KEY_BYTES>> for Aes<KEY_BYTES> {
+
+    fn from(key: &SecretKey<KEY_BYTES>) -> Self {
+        Aes::new(key.expose_secret())
+    }
+
+}
+
+impl<const KEY_BYTES: usize> fmt::Debug for SecretKey<KEY_BYTES> {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SecretKey<{}>([REDACTED])", KEY_BYTES)
+    }
+
+}
+
+///
+/// Redacted is printed in place of secret bytes by the Debug implementations, so
+/// structs containing keys or blocks only show how many bytes are hidden.
+/// 
+pub(crate) struct Redacted(pub(crate) usize);
+
+impl fmt::Debug for Redacted {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[REDACTED; {}]", self.0)
+    }
+
+}
+
+impl<const KEY_BYTES: usize> fmt::Display for SecretKey<KEY_BYTES> {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[REDACTED]")
+    }
+
+}
+
+impl<const KEY_BYTES: usize> Drop for SecretKey<KEY_BYTES> {
+
+    fn drop(&mut self) {
+        self.key.zeroize();
+    }
+
+}
+
+#[cfg(all(test, feature = "encrypt", feature = "decrypt"))]
+mod tests {
+
+    use super::*;
+    use crate::test_util::hex;
+
+    #[test]
+    fn test_redacted() {
+        let key = SecretKey::new([0x41; 16]);
+        assert_eq!("SecretKey<16>([REDACTED])", format!("{:?}", key));
+        assert_eq!("[REDACTED]", key.to_string());
+        assert!(!format!("{:?} {}", key, key).contains("41"));
+    }
+
+    #[test]
+    fn test_expose_secret() {
+        let bytes = hex("000102030405060708090a0b0c0d0e0f");
+        let key = SecretKey::<16>::from_slice(&bytes).unwrap();
+        assert_eq!(bytes, key.expose_secret());
+        assert_eq!(Some(AesError::InvalidKeyLength { length: 16, min: 32, max: 32, multiple: 32 }), SecretKey::<32>::from_slice(&bytes).err());
+        let aes = Aes::from(&key);
+        assert_eq!(hex("69c4e0d86a7b0430d8cdb78070b4c55a"), aes.encrypt_bytes(&hex("00112233445566778899aabbccddeeff")));
+    }
+
+    #[test]
+    fn test_generate() {
+        let first = SecretKey::<32>::generate().unwrap();
+        let second = SecretKey::<32>::generate().unwrap();
+        assert_ne!(first.expose_secret(), second.expose_secret());
+    }
+
+    #[test]
+    fn test_mode_constructors() {
+        let bytes = [0x2b; 16];
+        let key = SecretKey::new(bytes);
+        let nonce = [0; 12];
+        let gcm = crate::gcm::Gcm::from_secret(&key, 16).unwrap();
+        assert_eq!(crate::gcm::Gcm::new(&bytes, 16).unwrap().encrypt(&nonce, b"aad", b"data"), gcm.encrypt(&nonce, b"aad", b"data"));
+        assert_eq!(crate::cmac::Cmac::new(&bytes).mac(b"data"), crate::cmac::Cmac::from(&key).mac(b"data"));
+        let key_wrap = crate::key_wrap::KeyWrap::from(&key);
+        assert_eq!(crate::key_wrap::KeyWrap::new(&bytes).wrap(&[1; 16]), key_wrap.wrap(&[1; 16]));
+    }
+
+}
@@ -0,0 +1,178 @@
+//!
+//! SHA-256 as specified in FIPS 180-4. Only used internally, for example to derive
+//! the ESSIV key from the data key and in HMAC-SHA256.
+//! 
+
+use crate::zeroize::Zeroize;
+
+///
+/// The first 32 bits of the fractional parts of the cube roots of the first 64 primes.
+/// 
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2
+];
+
+///
+/// The initial hash value, the first 32 bits of the fractional parts of the square
+/// roots of the first 8 primes.
+/// 
+const H: [u32; 8] = [0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19];
+
+///
+/// Calculates the SHA-256 digest of the data.
+/// 
+/// data: The data to hash.
+/// 
+/// result: The 32 byte digest.
+/// 
+pub(crate) fn sha256(data: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(data);
+    hasher.finalize()
+}
+
+///
+/// Sha256 is an incremental SHA-256 that buffers a partial chunk between updates.
+/// 
+#[derive(Clone)]
+pub(crate) struct Sha256 {
+    hash: [u32; 8],
+    buffer: [u8; 64],
+    buffered: usize,
+    length: u64
+}
+
+impl Sha256 {
+
+    ///
+    /// Creates a new Sha256 with the initial hash value.
+    /// 
+    /// result: A Sha256 without any data.
+    /// 
+    pub(crate) fn new() -> Self {
+        Self { hash: H, buffer: [0; 64], buffered: 0, length: 0 }
+    }
+
+    ///
+    /// Adds the data to the hash.
+    /// 
+    /// data: The next chunk of the data, of any length.
+    /// 
+    pub(crate) fn update(&mut self, mut data: &[u8]) {
+        self.length += data.len() as u64;
+        while !data.is_empty() {
+            let count = data.len().min(64 - self.buffered);
+            self.buffer[self.buffered..self.buffered + count].copy_from_slice(&data[..count]);
+            self.buffered += count;
+            data = &data[count..];
+            if self.buffered == 64 {
+                compress(&mut self.hash, &self.buffer);
+                self.buffered = 0;
+            }
+        }
+    }
+
+    ///
+    /// Pads the data and calculates the digest.
+    /// 
+    /// result: The 32 byte digest.
+    /// 
+    pub(crate) fn finalize(mut self) -> [u8; 32] {
+        let length = self.length * 8;
+        self.update(&[0x80]);
+        while self.buffered != 56 {
+            self.update(&[0]);
+        }
+        self.update(&length.to_be_bytes());
+        let mut digest = [0; 32];
+        for (bytes, word) in digest.chunks_mut(4).zip(self.hash) {
+            bytes.copy_from_slice(&word.to_be_bytes());
+        }
+        digest
+    }
+
+}
+
+///
+/// Processes a 64 byte chunk of the padded message.
+/// 
+fn compress(hash: &mut [u32; 8], chunk: &[u8]) {
+    let mut w = [0u32; 64];
+    for (index, bytes) in chunk.chunks(4).enumerate() {
+        w[index] = u32::from_be_bytes(bytes.try_into().unwrap());
+    }
+    for index in 16..64 {
+        let s0 = w[index - 15].rotate_right(7) ^ w[index - 15].rotate_right(18) ^ (w[index - 15] >> 3);
+        let s1 = w[index - 2].rotate_right(17) ^ w[index - 2].rotate_right(19) ^ (w[index - 2] >> 10);
+        w[index] = w[index - 16].wrapping_add(s0).wrapping_add(w[index - 7]).wrapping_add(s1);
+    }
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *hash;
+    for index in 0..64 {
+        let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+        let choice = (e & f) ^ (!e & g);
+        let temp1 = h.wrapping_add(s1).wrapping_add(choice).wrapping_add(K[index]).wrapping_add(w[index]);
+        let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+        let majority = (a & b) ^ (a & c) ^ (b & c);
+        let temp2 = s0.wrapping_add(majority);
+        h = g;
+        g = f;
+        f = e;
+        e = d.wrapping_add(temp1);
+        d = c;
+        c = b;
+        b = a;
+        a = temp1.wrapping_add(temp2);
+    }
+    for (word, value) in hash.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+        *word = word.wrapping_add(value);
+    }
+}
+
+impl Drop for Sha256 {
+
+    fn drop(&mut self) {
+        self.hash.zeroize();
+        self.buffer.zeroize();
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::test_util::hex;
+
+    #[test]
+    fn test_sha256() {
+        assert_eq!(hex("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"), sha256(b""));
+        assert_eq!(hex("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"), sha256(b"abc"));
+        assert_eq!(hex("248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"), sha256(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"));
+    }
+
+    #[test]
+    fn test_sha256_block_boundaries() {
+        assert_eq!(hex("9f4390f8d30c2dd92ec9f095b65e2b9ae9b0a925a5258e241c9f1e910f734318"), sha256(&[0x61; 55]));
+        assert_eq!(hex("b35439a4ac6f0948b6d6f9e3c6af0f5f590ce20f1bde7090ef7970686ec6738a"), sha256(&[0x61; 56]));
+        assert_eq!(hex("ffe054fe7ae0cb6dc65c3af9b61d5209f439851db43d0ba5997337df154668eb"), sha256(&[0x61; 64]));
+    }
+
+    #[test]
+    fn test_sha256_incremental() {
+        let data = [0x61; 200];
+        for split in [0, 1, 55, 63, 64, 65, 128, 200] {
+            let mut hasher = Sha256::new();
+            hasher.update(&data[..split]);
+            hasher.update(&data[split..]);
+            assert_eq!(sha256(&data), hasher.finalize());
+        }
+    }
+
+}
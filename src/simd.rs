@@ -0,0 +1,108 @@
+//!
+//! Portable SIMD words for the bitsliced rounds. Every word of the state is a
+//! vector of four u64, and each element holds four blocks as in the bitsliced
+//! backend, so sixteen blocks are processed in parallel. std::simd compiles the
+//! vectors to the SIMD instructions of the target, for example SSE2 or AVX2 on
+//! x86_64, NEON on aarch64 or simd128 on wasm32, and to plain u64 operations on
+//! targets without them.
+//!
+//! The rounds are the same as in the bitsliced backend, so they are constant
+//! time. std::simd is only available with a nightly compiler.
+//!
+
+use std::simd::Simd;
+
+use crate::bitslice::Word;
+use crate::zeroize::Zeroize;
+
+///
+/// The number of blocks in each element of the vectors.
+///
+const ELEMENT_BLOCKS: usize = 4;
+
+///
+/// The words of the state, four u64 in a vector.
+///
+pub(crate) type Words = Simd<u64, 4>;
+
+impl Zeroize for Words {
+
+    fn zeroize(&mut self) {
+        self.as_mut_array().zeroize();
+    }
+
+}
+
+impl Word for Words {
+
+    const BLOCKS: usize = 4 * ELEMENT_BLOCKS;
+
+    fn splat(bits: u64) -> Self {
+        Simd::splat(bits)
+    }
+
+    fn pack(blocks: &[[u8; 16]]) -> [Self; 8] {
+        let mut state = [Simd::splat(0); 8];
+        for (element, group) in blocks.chunks(ELEMENT_BLOCKS).enumerate() {
+            let mut packed = u64::pack(group);
+            for (word, bits) in state.iter_mut().zip(packed) {
+                word.as_mut_array()[element] = bits;
+            }
+            packed.zeroize();
+        }
+        state
+    }
+
+    fn unpack(state: &[Self; 8], blocks: &mut [[u8; 16]]) {
+        for (element, group) in blocks.chunks_mut(ELEMENT_BLOCKS).enumerate() {
+            let mut packed = state.map(|word| word[element]);
+            u64::unpack(&packed, group);
+            packed.zeroize();
+        }
+    }
+
+}
+
+#[cfg(all(test, feature = "encrypt", feature = "decrypt"))]
+mod tests {
+
+    use super::*;
+    use crate::bitslice;
+    use crate::test_util::hex;
+    use crate::key_schedule::{ReverseRoundKeyGenerator, RoundKeyGenerator};
+    use crate::{InverseRoundKeys, RoundKeys};
+
+    #[test]
+    fn test_pack() {
+        let blocks: Vec<[u8; 16]> = (0..Words::BLOCKS).map(|block| std::array::from_fn(|position| (block * 16 + position) as u8)).collect();
+        let state = Words::pack(&blocks);
+        assert_eq!(u64::pack(&blocks[4..8]), state.map(|word| word[1]));
+        let mut unpacked = vec![[0; 16]; Words::BLOCKS - 3];
+        Words::unpack(&state, &mut unpacked);
+        assert_eq!(blocks[..Words::BLOCKS - 3], unpacked);
+    }
+
+    #[test]
+    fn test_fips197_vectors() {
+        let plaintext: [u8; 16] = hex("00112233445566778899aabbccddeeff").try_into().unwrap();
+        let vectors = [
+            ("000102030405060708090a0b0c0d0e0f", "69c4e0d86a7b0430d8cdb78070b4c55a"),
+            ("000102030405060708090a0b0c0d0e0f1011121314151617", "dda97ca4864cdfe06eaf70a0ec0d7191"),
+            ("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f", "8ea2b7ca516745bfeafc49904b496089")
+        ];
+        for (key, expected) in vectors {
+            let key = hex(key);
+            let roundkeys = RoundKeys::new(RoundKeyGenerator::new(&key).map(|roundkey| *roundkey).collect()).unwrap();
+            let mut blocks = [plaintext; 19];
+            bitslice::encrypt_rounds::<Words, _>(&mut blocks, roundkeys.rounds(), roundkeys.as_slice().iter());
+            assert_eq!([<[u8; 16]>::try_from(hex(expected)).unwrap(); 19], blocks);
+            let mut decrypted = blocks;
+            bitslice::decrypt_equivalent_rounds::<Words>(&mut decrypted, &InverseRoundKeys::new(&roundkeys));
+            assert_eq!([plaintext; 19], decrypted);
+            let mut decrypted = blocks;
+            bitslice::decrypt_rounds::<Words, _>(&mut decrypted, roundkeys.rounds(), ReverseRoundKeyGenerator::new(&ReverseRoundKeyGenerator::last_words(&key)));
+            assert_eq!([plaintext; 19], decrypted);
+        }
+    }
+
+}
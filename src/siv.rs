@@ -0,0 +1,241 @@
+//!
+//! Synthetic initialization vector (SIV) mode, as specified in RFC 5297. SIV is a
+//! deterministic authenticated encryption mode. The S2V construction calculates
+//! a CMAC over every component of the associated data and the plaintext, and the
+//! result is both the tag and the initial counter for counter mode.
+//! 
+//! Without a nonce the same plaintext and associated data always give the same
+//! ciphertext, which is useful for example for encrypted database keys. A nonce
+//! can be given as the last component of the associated data. The ciphertext is
+//! the 16 byte synthetic iv followed by the encrypted plaintext.
+//! 
+
+use std::vec::Vec;
+
+use crate::cmac::{double, Cmac};
+use crate::{ctr, verify_tag, Aead, Aes, AesError, SecretKey};
+
+///
+/// The maximum number of associated data components, so that S2V gets at most 
+/// 127 components with the plaintext.
+/// 
+const MAX_COMPONENTS: usize = 126;
+
+///
+/// Siv is an AES-SIV cipher made from a mac key and an encryption key of the same 
+/// length. AES-SIV-256 in RFC 5297 uses two 16 byte keys.
+/// 
+pub struct Siv<const KEY_BYTES: usize = 16> {
+    mac: Cmac<KEY_BYTES>,
+    cipher: Aes<KEY_BYTES>
+}
+
+impl<const KEY_BYTES: usize> Siv<KEY_BYTES> {
+
+    ///
+    /// Creates a new Siv cipher. The RFC 5297 key is the mac key followed by the
+    /// encryption key.
+    /// 
+    /// mac_key: The key used by S2V.
+    /// encryption_key: The key used by counter mode.
+    /// 
+    /// result: A new Siv cipher.
+    /// 
+    pub fn new(mac_key: &[u8; KEY_BYTES], encryption_key: &[u8; KEY_BYTES]) -> Self {
+        Self { mac: Cmac::new(mac_key), cipher: Aes::new(encryption_key) }
+    }
+
+    ///
+    /// Creates a new Siv cipher from two SecretKeys.
+    /// 
+    /// mac_key: The key used by S2V.
+    /// encryption_key: The key used by counter mode.
+    /// 
+    /// result: A new Siv cipher.
+    /// 
+    pub fn from_secret(mac_key: &SecretKey<KEY_BYTES>, encryption_key: &SecretKey<KEY_BYTES>) -> Self {
+        Self::new(mac_key.expose_secret(), encryption_key.expose_secret())
+    }
+
+    ///
+    /// Encrypts and authenticates the plaintext.
+    /// 
+    /// associated_data: The components of the associated data, at most 126. A 
+    ///                  nonce, if used, is the last component.
+    /// plaintext: The data to encrypt.
+    /// 
+    /// result: The synthetic iv followed by the ciphertext or AesError::DataTooLong
+    ///         if there are more than 126 components of associated data.
+    /// 
+    pub fn encrypt(&self, associated_data: &[&[u8]], plaintext: &[u8]) -> Result<Vec<u8>, AesError> {
+        let iv = self.s2v(associated_data, plaintext)?;
+        let mut encrypted = iv.to_vec();
+        encrypted.extend(ctr::apply_keystream(&self.cipher, &counter_block(&iv), plaintext));
+        Ok(encrypted)
+    }
+
+    ///
+    /// Decrypts the ciphertext and verifies the synthetic iv.
+    /// 
+    /// associated_data: The components of the associated data used when encrypting.
+    /// ciphertext: The synthetic iv followed by the ciphertext.
+    /// 
+    /// result: The plaintext, AesError::DataTooLong if there are more than 126 
+    ///         components of associated data or AesError::AuthenticationFailed if
+    ///         the synthetic iv does not match.
+    /// 
+    pub fn decrypt(&self, associated_data: &[&[u8]], ciphertext: &[u8]) -> Result<Vec<u8>, AesError> {
+        if ciphertext.len() < 16 {
+            return Err(AesError::AuthenticationFailed);
+        }
+        let (iv, ciphertext) = ciphertext.split_at(16);
+        let iv: [u8; 16] = iv.try_into().unwrap();
+        let plaintext = ctr::apply_keystream(&self.cipher, &counter_block(&iv), ciphertext);
+        verify_tag(&iv, &self.s2v(associated_data, &plaintext)?)?;
+        Ok(plaintext)
+    }
+
+    ///
+    /// Calculates S2V of the associated data components and the plaintext.
+    /// 
+    fn s2v(&self, associated_data: &[&[u8]], plaintext: &[u8]) -> Result<[u8; 16], AesError> {
+        if associated_data.len() > MAX_COMPONENTS {
+            return Err(AesError::DataTooLong);
+        }
+        let mut d = self.mac.mac(&[0; 16]);
+        for component in associated_data {
+            d = xor(&double(&d), &self.mac.mac(component));
+        }
+        if plaintext.len() >= 16 {
+            let mut last = plaintext.to_vec();
+            let start = last.len() - 16;
+            for (byte, d_byte) in last[start..].iter_mut().zip(d) {
+                *byte ^= d_byte;
+            }
+            Ok(self.mac.mac(&last))
+        } else {
+            let mut padded = [0; 16];
+            padded[..plaintext.len()].copy_from_slice(plaintext);
+            padded[plaintext.len()] = 0x80;
+            Ok(self.mac.mac(&xor(&double(&d), &padded)))
+        }
+    }
+
+}
+
+///
+/// Creates the initial counter block from the synthetic iv, with the highest bit
+/// of the two 32 bit words cleared.
+/// 
+fn counter_block(iv: &[u8; 16]) -> [u8; 16] {
+    let mut counter = *iv;
+    counter[8] &= 0x7f;
+    counter[12] &= 0x7f;
+    counter
+}
+
+///
+/// Xors two blocks.
+/// 
+fn xor(block: &[u8; 16], other: &[u8; 16]) -> [u8; 16] {
+    std::array::from_fn(|index| block[index] ^ other[index])
+}
+
+///
+/// As an Aead the associated data is a single component followed by the nonce as
+/// the last component. An empty nonce is left out, which gives deterministic 
+/// encryption. The synthetic IV is the tag, so the detached variants return and 
+/// accept it separately from the front of the ciphertext.
+/// 
+impl<const KEY_BYTES: usize> Aead for Siv<KEY_BYTES> {
+
+    fn encrypt(&self, nonce: &[u8], aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, AesError> {
+        Siv::encrypt(self, &components(nonce, aad), plaintext)
+    }
+
+    fn decrypt(&self, nonce: &[u8], aad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, AesError> {
+        Siv::decrypt(self, &components(nonce, aad), ciphertext)
+    }
+
+    fn tag_length(&self) -> usize {
+        16
+    }
+
+    fn encrypt_detached(&self, nonce: &[u8], aad: &[u8], plaintext: &[u8]) -> Result<(Vec<u8>, Vec<u8>), AesError> {
+        let mut tag = Siv::encrypt(self, &components(nonce, aad), plaintext)?;
+        let ciphertext = tag.split_off(16);
+        Ok((ciphertext, tag))
+    }
+
+    fn decrypt_detached(&self, nonce: &[u8], aad: &[u8], ciphertext: &[u8], tag: &[u8]) -> Result<Vec<u8>, AesError> {
+        if tag.len() != 16 {
+            return Err(AesError::InvalidTagLength);
+        }
+        Siv::decrypt(self, &components(nonce, aad), &[tag, ciphertext].concat())
+    }
+
+}
+
+///
+/// The associated data components used by the Aead implementation.
+/// 
+fn components<'a>(nonce: &'a [u8], aad: &'a [u8]) -> Vec<&'a [u8]> {
+    if nonce.is_empty() { vec![aad] } else { vec![aad, nonce] }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::test_util::hex;
+
+    fn siv(key: &str) -> Siv<16> {
+        let key = hex(key);
+        Siv::new(&key[..16].try_into().unwrap(), &key[16..].try_into().unwrap())
+    }
+
+    #[test]
+    fn test_rfc5297_deterministic() {
+        let siv = siv("fffefdfcfbfaf9f8f7f6f5f4f3f2f1f0 f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff");
+        let aad = hex("101112131415161718191a1b1c1d1e1f 2021222324252627");
+        let plaintext = hex("112233445566778899aabbccddee");
+        let expected = hex("85632d07c6e8f37f950acd320a2ecc93 40c02b9690c4dc04daef7f6afe5c");
+        assert_eq!(expected, siv.encrypt(&[&aad], &plaintext).unwrap());
+        assert_eq!(plaintext, siv.decrypt(&[&aad], &expected).unwrap());
+    }
+
+    #[test]
+    fn test_rfc5297_nonce_based() {
+        let siv = siv("7f7e7d7c7b7a79787776757473727170 404142434445464748494a4b4c4d4e4f");
+        let aad1 = hex("00112233445566778899aabbccddeeff deaddadadeaddadaffeeddccbbaa9988 7766554433221100");
+        let aad2 = hex("102030405060708090a0");
+        let nonce = hex("09f911029d74e35bd84156c5635688c0");
+        let plaintext = hex("7468697320697320736f6d6520706c61 696e7465787420746f20656e63727970 74207573696e67205349562d414553");
+        let expected = hex("7bdb6e3b432667eb06f4d14bff2fbd0f cb900f2fddbe404326601965c889bf17 dba77ceb094fa663b7a3f748ba8af829 ea64ad544a272e9c485b62a3fd5c0d");
+        assert_eq!(expected, siv.encrypt(&[&aad1, &aad2, &nonce], &plaintext).unwrap());
+        assert_eq!(plaintext, siv.decrypt(&[&aad1, &aad2, &nonce], &expected).unwrap());
+    }
+
+    #[test]
+    fn test_siv_aes256() {
+        let key: Vec<u8> = (0..64).collect();
+        let siv = Siv::<32>::new(&key[..32].try_into().unwrap(), &key[32..].try_into().unwrap());
+        let expected = hex("90022d563b381d0ba89d04b7fc700ebb 8eeea0af34");
+        assert_eq!(expected, siv.encrypt(&[b"a"], b"short").unwrap());
+        assert_eq!(b"short".to_vec(), siv.decrypt(&[b"a"], &expected).unwrap());
+    }
+
+    #[test]
+    fn test_siv_authentication_failed() {
+        let siv = siv("fffefdfcfbfaf9f8f7f6f5f4f3f2f1f0 f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff");
+        let mut encrypted = siv.encrypt(&[b"header"], b"payload").unwrap();
+        assert_eq!(Some(AesError::AuthenticationFailed), siv.decrypt(&[b"header", b""], &encrypted).err());
+        assert_eq!(Some(AesError::AuthenticationFailed), siv.decrypt(&[], &encrypted).err());
+        encrypted[20] ^= 1;
+        assert_eq!(Some(AesError::AuthenticationFailed), siv.decrypt(&[b"header"], &encrypted).err());
+        assert_eq!(Some(AesError::AuthenticationFailed), siv.decrypt(&[], &[0; 15]).err());
+        let components: Vec<&[u8]> = vec![b"x"; 127];
+        assert_eq!(Some(AesError::DataTooLong), siv.encrypt(&components, b"payload").err());
+    }
+
+}
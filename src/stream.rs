@@ -0,0 +1,234 @@
+//!
+//! The STREAM construction of Hoang, Reyhanitabar, Rogaway and Vizár for 
+//! authenticated encryption of long messages in chunks. Every chunk is sealed 
+//! with an Aead and the nonce prefix || 32 bit big endian counter || last flag,
+//! so reordered, dropped, duplicated or truncated chunks fail authentication.
+//! 
+//! The nonce prefix must be unique for every stream encrypted with the same key,
+//! and be 5 bytes shorter than the nonce of the Aead. For 12 byte nonces the 
+//! prefix is 7 bytes.
+//! 
+
+use std::vec::Vec;
+
+use crate::{Aead, AesError};
+
+///
+/// The value of the last byte of the nonce for all chunks but the last.
+/// 
+const NOT_LAST: u8 = 0;
+
+///
+/// The value of the last byte of the nonce for the last chunk.
+/// 
+const LAST: u8 = 1;
+
+///
+/// Encryptor encrypts a stream chunk by chunk.
+/// 
+pub struct Encryptor<'a, A: Aead> {
+    nonces: Nonces<'a, A>
+}
+
+impl<'a, A: Aead> Encryptor<'a, A> {
+
+    ///
+    /// Creates a new Encryptor.
+    /// 
+    /// aead: The Aead used to seal the chunks.
+    /// nonce_prefix: The nonce prefix, unique for the stream.
+    /// 
+    /// result: A new Encryptor starting at the first chunk.
+    /// 
+    pub fn new(aead: &'a A, nonce_prefix: &[u8]) -> Self {
+        Self { nonces: Nonces::new(aead, nonce_prefix) }
+    }
+
+    ///
+    /// Encrypts the next chunk, which is not the last.
+    /// 
+    /// aad: The associated data of the chunk.
+    /// chunk: The plaintext of the chunk.
+    /// 
+    /// result: The sealed chunk, AesError::CounterOverflow if the stream has 
+    ///         2^32 chunks already or the error from the Aead.
+    /// 
+    pub fn encrypt_next(&mut self, aad: &[u8], chunk: &[u8]) -> Result<Vec<u8>, AesError> {
+        let nonce = self.nonces.next(NOT_LAST)?;
+        self.nonces.aead.encrypt(&nonce, aad, chunk)
+    }
+
+    ///
+    /// Encrypts the last chunk and ends the stream.
+    /// 
+    /// aad: The associated data of the chunk.
+    /// chunk: The plaintext of the chunk, which may be empty.
+    /// 
+    /// result: The sealed chunk, AesError::CounterOverflow if the stream has 
+    ///         2^32 chunks already or the error from the Aead.
+    /// 
+    pub fn encrypt_last(mut self, aad: &[u8], chunk: &[u8]) -> Result<Vec<u8>, AesError> {
+        let nonce = self.nonces.next(LAST)?;
+        self.nonces.aead.encrypt(&nonce, aad, chunk)
+    }
+
+}
+
+///
+/// Decryptor decrypts a stream chunk by chunk. The stream is only complete when
+/// decrypt_last succeeds, a stream ending without it has been truncated.
+/// 
+pub struct Decryptor<'a, A: Aead> {
+    nonces: Nonces<'a, A>
+}
+
+impl<'a, A: Aead> Decryptor<'a, A> {
+
+    ///
+    /// Creates a new Decryptor.
+    /// 
+    /// aead: The Aead used to seal the chunks.
+    /// nonce_prefix: The nonce prefix of the stream.
+    /// 
+    /// result: A new Decryptor starting at the first chunk.
+    /// 
+    pub fn new(aead: &'a A, nonce_prefix: &[u8]) -> Self {
+        Self { nonces: Nonces::new(aead, nonce_prefix) }
+    }
+
+    ///
+    /// Decrypts the next chunk, which is not the last.
+    /// 
+    /// aad: The associated data of the chunk.
+    /// chunk: The sealed chunk.
+    /// 
+    /// result: The plaintext of the chunk, AesError::AuthenticationFailed if the 
+    ///         chunk is modified, out of order or the last chunk, 
+    ///         AesError::CounterOverflow if the stream has 2^32 chunks already or
+    ///         another error from the Aead.
+    /// 
+    pub fn decrypt_next(&mut self, aad: &[u8], chunk: &[u8]) -> Result<Vec<u8>, AesError> {
+        let nonce = self.nonces.next(NOT_LAST)?;
+        self.nonces.aead.decrypt(&nonce, aad, chunk)
+    }
+
+    ///
+    /// Decrypts the last chunk and ends the stream.
+    /// 
+    /// aad: The associated data of the chunk.
+    /// chunk: The sealed chunk.
+    /// 
+    /// result: The plaintext of the chunk, AesError::AuthenticationFailed if the 
+    ///         chunk is modified, out of order or not the last chunk, 
+    ///         AesError::CounterOverflow if the stream has 2^32 chunks already or
+    ///         another error from the Aead.
+    /// 
+    pub fn decrypt_last(mut self, aad: &[u8], chunk: &[u8]) -> Result<Vec<u8>, AesError> {
+        let nonce = self.nonces.next(LAST)?;
+        self.nonces.aead.decrypt(&nonce, aad, chunk)
+    }
+
+}
+
+///
+/// Nonces creates the nonces of the chunks.
+/// 
+struct Nonces<'a, A: Aead> {
+    aead: &'a A,
+    prefix: Vec<u8>,
+    counter: u64
+}
+
+impl<'a, A: Aead> Nonces<'a, A> {
+
+    ///
+    /// Creates new Nonces starting at counter zero.
+    /// 
+    /// aead: The Aead used to seal the chunks.
+    /// prefix: The nonce prefix.
+    /// 
+    /// result: New Nonces.
+    /// 
+    fn new(aead: &'a A, prefix: &[u8]) -> Self {
+        Self { aead, prefix: prefix.to_vec(), counter: 0 }
+    }
+
+    ///
+    /// Creates the nonce of the next chunk and increments the counter.
+    /// 
+    /// flag: The last byte, LAST for the last chunk or NOT_LAST.
+    /// 
+    /// result: The nonce or AesError::CounterOverflow if the counter is used up.
+    /// 
+    fn next(&mut self, flag: u8) -> Result<Vec<u8>, AesError> {
+        let counter = u32::try_from(self.counter).map_err(|_| AesError::CounterOverflow)?;
+        self.counter += 1;
+        let mut nonce = Vec::with_capacity(self.prefix.len() + 5);
+        nonce.extend_from_slice(&self.prefix);
+        nonce.extend_from_slice(&counter.to_be_bytes());
+        nonce.push(flag);
+        Ok(nonce)
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::gcm::Gcm;
+
+    const PREFIX: [u8; 7] = [7; 7];
+
+    fn encrypt_stream(gcm: &Gcm<16>) -> Vec<Vec<u8>> {
+        let mut encryptor = Encryptor::new(gcm, &PREFIX);
+        let mut chunks = vec![encryptor.encrypt_next(b"header", b"first").unwrap()];
+        chunks.push(encryptor.encrypt_next(&[], b"second").unwrap());
+        chunks.push(encryptor.encrypt_last(&[], b"third").unwrap());
+        chunks
+    }
+
+    #[test]
+    fn test_stream_roundtrip() {
+        let gcm = Gcm::<16>::new(&[1; 16], 16).unwrap();
+        let chunks = encrypt_stream(&gcm);
+        let mut decryptor = Decryptor::new(&gcm, &PREFIX);
+        assert_eq!(b"first".to_vec(), decryptor.decrypt_next(b"header", &chunks[0]).unwrap());
+        assert_eq!(b"second".to_vec(), decryptor.decrypt_next(&[], &chunks[1]).unwrap());
+        assert_eq!(b"third".to_vec(), decryptor.decrypt_last(&[], &chunks[2]).unwrap());
+        let mut nonce = PREFIX.to_vec();
+        nonce.extend_from_slice(&[0, 0, 0, 1, NOT_LAST]);
+        assert_eq!(gcm.encrypt(&nonce, &[], b"second").unwrap(), chunks[1]);
+    }
+
+    #[test]
+    fn test_stream_reordered() {
+        let gcm = Gcm::<16>::new(&[1; 16], 16).unwrap();
+        let chunks = encrypt_stream(&gcm);
+        let mut decryptor = Decryptor::new(&gcm, &PREFIX);
+        assert_eq!(Some(AesError::AuthenticationFailed), decryptor.decrypt_next(&[], &chunks[1]).err());
+    }
+
+    #[test]
+    fn test_stream_truncated() {
+        let gcm = Gcm::<16>::new(&[1; 16], 16).unwrap();
+        let chunks = encrypt_stream(&gcm);
+        let mut decryptor = Decryptor::new(&gcm, &PREFIX);
+        decryptor.decrypt_next(b"header", &chunks[0]).unwrap();
+        assert_eq!(Some(AesError::AuthenticationFailed), decryptor.decrypt_last(&[], &chunks[1]).err());
+        let mut decryptor = Decryptor::new(&gcm, &PREFIX);
+        decryptor.decrypt_next(b"header", &chunks[0]).unwrap();
+        decryptor.decrypt_next(&[], &chunks[1]).unwrap();
+        assert_eq!(Some(AesError::AuthenticationFailed), decryptor.decrypt_next(&[], &chunks[2]).err());
+    }
+
+    #[test]
+    fn test_stream_counter_overflow() {
+        let gcm = Gcm::<16>::new(&[1; 16], 16).unwrap();
+        let mut encryptor = Encryptor::new(&gcm, &PREFIX);
+        encryptor.nonces.counter = u32::MAX as u64;
+        assert!(encryptor.encrypt_next(&[], b"chunk").is_ok());
+        assert_eq!(Some(AesError::CounterOverflow), encryptor.encrypt_last(&[], b"chunk").err());
+    }
+
+}
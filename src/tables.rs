@@ -0,0 +1,133 @@
+//!
+//! Generation of the constant tables from the arithmetic of GF(2^8) with the
+//! polynomial x^8 + x^4 + x^3 + x + 1. The functions are const, so the s-boxes,
+//! the round constants and the T-tables are computed by the compiler and stored
+//! as flat arrays, with no work done when the crate is used.
+//!
+
+///
+/// The s-box, the multiplicative inverse of each byte followed by the affine
+/// transformation of FIPS-197 section 5.1.1.
+///
+pub(crate) const S_BOX: [u8; 256] = s_box();
+
+///
+/// The inverse s-box, the permutation undoing the s-box.
+///
+#[cfg(feature = "decrypt")]
+pub(crate) const INVERSE_S_BOX: [u8; 256] = inverse_s_box(&S_BOX);
+
+///
+/// Multiplies two bytes in GF(2^8) with the polynomial x^8 + x^4 + x^3 + x + 1.
+///
+/// a: A byte to be multiplied.
+/// b: A byte to be multiplied.
+///
+/// result: The product of the bytes.
+///
+pub(crate) const fn multiply(mut a: u8, mut b: u8) -> u8 {
+    let mut result = 0;
+    while b != 0 {
+        if b & 1 == 1 {
+            result ^= a;
+        }
+        a = (a << 1) ^ ((a >> 7) * 0x1b);
+        b >>= 1;
+    }
+    result
+}
+
+///
+/// Finds the multiplicative inverse of a byte as a^254, since a^255 is 1 for every
+/// byte except 0. The inverse of 0 is 0.
+///
+const fn inverse(value: u8) -> u8 {
+    let mut result = 1;
+    let mut power = value;
+    let mut exponent = 254;
+    while exponent != 0 {
+        if exponent & 1 == 1 {
+            result = multiply(result, power);
+        }
+        power = multiply(power, power);
+        exponent >>= 1;
+    }
+    result
+}
+
+///
+/// Generates the s-box.
+///
+const fn s_box() -> [u8; 256] {
+    let mut s_box = [0; 256];
+    let mut index = 0;
+    while index < 256 {
+        let value = inverse(index as u8);
+        s_box[index] = value ^ value.rotate_left(1) ^ value.rotate_left(2) ^ value.rotate_left(3) ^ value.rotate_left(4) ^ 0x63;
+        index += 1;
+    }
+    s_box
+}
+
+///
+/// Generates the inverse of a permutation of the bytes.
+///
+#[cfg(feature = "decrypt")]
+const fn inverse_s_box(s_box: &[u8; 256]) -> [u8; 256] {
+    let mut inverse = [0; 256];
+    let mut index = 0;
+    while index < 256 {
+        inverse[s_box[index] as usize] = index as u8;
+        index += 1;
+    }
+    inverse
+}
+
+///
+/// Generates the round constants of the key expansion, the powers of x starting
+/// with x^0.
+///
+/// result: The first N round constants.
+///
+pub(crate) const fn round_constants<const N: usize>() -> [u8; N] {
+    let mut constants = [0; N];
+    let mut value = 1;
+    let mut index = 0;
+    while index < N {
+        constants[index] = value;
+        value = multiply(value, 2);
+        index += 1;
+    }
+    constants
+}
+
+#[cfg(all(test, feature = "encrypt", feature = "decrypt"))]
+mod tests {
+
+    use super::*;
+    use crate::test_util::hex;
+
+    #[test]
+    fn test_s_box() {
+        assert_eq!(hex("637c777bf26b6fc53001672bfed7ab76"), S_BOX[..16]);
+        assert_eq!(hex("8ca1890dbfe6426841992d0fb054bb16"), S_BOX[240..]);
+        assert_eq!(0xed, S_BOX[0x53]);
+        assert_eq!(hex("52096ad53036a538bf40a39e81f3d7fb"), INVERSE_S_BOX[..16]);
+        assert_eq!(hex("172b047eba77d626e169146355210c7d"), INVERSE_S_BOX[240..]);
+        assert!((0..=255).all(|value: u8| INVERSE_S_BOX[S_BOX[value as usize] as usize] == value));
+    }
+
+    #[test]
+    fn test_multiply() {
+        assert_eq!(0xc1, multiply(0x57, 0x83));
+        assert_eq!(0xfe, multiply(0x57, 0x13));
+        assert!((1..=255).all(|value: u8| multiply(value, inverse(value)) == 1));
+        assert_eq!(0, inverse(0));
+    }
+
+    #[test]
+    fn test_round_constants() {
+        assert_eq!(hex("01020408102040801b366cd8ab4d9a2f5ebc63c697356ad4b37dfaefc591"), round_constants::<30>());
+    }
+
+}
@@ -0,0 +1,10 @@
+use std::vec::Vec;
+
+///
+/// Converts a hex string to bytes. Used for the test vectors, which are almost 
+/// always given as hex strings. Whitespace in the string is ignored.
+/// 
+pub(crate) fn hex(data: &str) -> Vec<u8> {
+    let data: String = data.chars().filter(|c| !c.is_whitespace()).collect();
+    (0..data.len()).step_by(2).map(|idx| u8::from_str_radix(&data[idx..idx + 2], 16).unwrap()).collect()
+}
@@ -0,0 +1,235 @@
+//!
+//! T-table implementation of the rounds. The state is kept as four big endian
+//! column words like in the bytewise rounds, and SubBytes, ShiftRows and MixColumns
+//! of a round are combined into four lookups per column in tables of 256 words. This is several times
+//! faster than the bytewise rounds. The tables are generated at compile time
+//! from the s-boxes.
+//!
+//! Four blocks are run through the rounds together, so the lookups of independent
+//! blocks are interleaved and the cpu can do them in parallel. A single block
+//! runs on its own, so its latency is not increased.
+//!
+//! The lookups depend on the data, so the rounds are not safe against cache
+//! timing attacks by an attacker sharing the cpu with the cipher.
+//!
+
+use std::ops::Deref;
+
+use crate::columns::{add_roundkey, from_bytes, to_bytes, Columns};
+#[cfg(feature = "decrypt")]
+use crate::columns::inverse_mix_column;
+use crate::tables::multiply;
+#[cfg(feature = "encrypt")]
+use crate::tables::S_BOX;
+#[cfg(feature = "decrypt")]
+use crate::tables::INVERSE_S_BOX;
+use crate::zeroize::{Zeroize, Zeroizing};
+#[cfg(feature = "decrypt")]
+use crate::InverseRoundKeys;
+
+///
+/// The number of blocks interleaved through the rounds.
+///
+const BLOCKS: usize = 4;
+
+///
+/// The encryption tables, MixColumns of the s-box value in each of the four rows.
+///
+#[cfg(feature = "encrypt")]
+static ENCRYPT_TABLES: [[u32; 256]; 4] = tables(&S_BOX, [0x02, 0x01, 0x01, 0x03]);
+
+///
+/// The decryption tables, InvMixColumns of the inverse s-box value in each of the
+/// four rows.
+///
+#[cfg(feature = "decrypt")]
+static DECRYPT_TABLES: [[u32; 256]; 4] = tables(&INVERSE_S_BOX, [0x0e, 0x09, 0x0d, 0x0b]);
+
+///
+/// Generates the four tables of a direction. The first table holds the first
+/// column of the mixing matrix multiplied by the s-box value, the other tables
+/// are the first rotated one, two and three bytes to the right.
+///
+/// s_box: The s-box of the direction.
+/// column: The first column of the MixColumns or InvMixColumns matrix.
+///
+/// result: The four tables.
+///
+const fn tables(s_box: &[u8; 256], column: [u8; 4]) -> [[u32; 256]; 4] {
+    let mut tables = [[0; 256]; 4];
+    let mut index = 0;
+    while index < 256 {
+        let value = s_box[index];
+        let word = u32::from_be_bytes([multiply(column[0], value), multiply(column[1], value), multiply(column[2], value), multiply(column[3], value)]);
+        tables[0][index] = word;
+        tables[1][index] = word.rotate_right(8);
+        tables[2][index] = word.rotate_right(16);
+        tables[3][index] = word.rotate_right(24);
+        index += 1;
+    }
+    tables
+}
+
+
+///
+/// Encrypts the blocks in place with the roundkeys produced by an iterator.
+///
+/// blocks: The blocks to encrypt, replaced by the encrypted blocks.
+/// rounds: The number of rounds.
+/// roundkeys: An iterator producing rounds + 1 roundkeys of 16 bytes.
+///
+#[cfg(feature = "encrypt")]
+pub(crate) fn encrypt_rounds<K: Deref<Target = [u8; 16]>>(blocks: &mut [[u8; 16]], rounds: usize, roundkeys: impl Iterator<Item = K>) {
+    let roundkeys = copy_roundkeys(roundkeys.map(|roundkey| from_bytes(&roundkey)));
+    run_rounds(&ENCRYPT_TABLES, &S_BOX, 1, blocks, rounds, &roundkeys);
+}
+
+///
+/// Decrypts a grid in place with the roundkeys produced by an iterator in reverse
+/// order. InvMixColumns is applied to the roundkeys of the middle rounds, so the
+/// equivalent inverse cipher can be used.
+///
+/// blocks: The blocks to decrypt, replaced by the decrypted blocks.
+/// rounds: The number of rounds.
+/// roundkeys: An iterator producing rounds + 1 roundkeys of 16 bytes, starting
+///            with the last roundkey.
+///
+#[cfg(feature = "decrypt")]
+pub(crate) fn decrypt_rounds<K: Deref<Target = [u8; 16]>>(blocks: &mut [[u8; 16]], rounds: usize, roundkeys: impl Iterator<Item = K>) {
+    let roundkeys = roundkeys.enumerate().map(|(index, roundkey)| {
+        let mut words = from_bytes(&roundkey);
+        if index != 0 && index != rounds {
+            words = words.map(inverse_mix_column);
+        }
+        words
+    });
+    run_rounds(&DECRYPT_TABLES, &INVERSE_S_BOX, 3, blocks, rounds, &copy_roundkeys(roundkeys));
+}
+
+///
+/// Decrypts the blocks in place using the equivalent inverse cipher.
+///
+/// blocks: The blocks to decrypt, replaced by the decrypted blocks.
+/// roundkeys: The inverse roundkeys, containing 11, 13 or 15 roundkeys.
+///
+#[cfg(feature = "decrypt")]
+pub(crate) fn decrypt_equivalent_rounds(blocks: &mut [[u8; 16]], roundkeys: &InverseRoundKeys) {
+    let rounds = roundkeys.rounds();
+    let roundkeys = copy_roundkeys(roundkeys.as_slice().iter().rev().map(from_bytes));
+    run_rounds(&DECRYPT_TABLES, &INVERSE_S_BOX, 3, blocks, rounds, &roundkeys);
+}
+
+///
+/// Copies the roundkeys as column words into an array, in the order they are used.
+///
+fn copy_roundkeys(roundkeys: impl Iterator<Item = Columns>) -> Zeroizing<[Columns; 15]> {
+    let mut copied = Zeroizing([[0; 4]; 15]);
+    for (slot, roundkey) in copied.iter_mut().zip(roundkeys) {
+        *slot = roundkey;
+    }
+    copied
+}
+
+///
+/// Runs the rounds of either direction on the blocks, four blocks at a time and
+/// the remaining blocks one at a time.
+///
+/// tables: The tables of the direction.
+/// s_box: The s-box of the direction, used in the final round.
+/// shift: The column offset of the second row, 1 for encryption and 3 for decryption.
+/// blocks: The blocks to transform in place.
+/// rounds: The number of rounds.
+/// roundkeys: The rounds + 1 roundkeys as column words.
+///
+fn run_rounds(tables: &[[u32; 256]; 4], s_box: &[u8; 256], shift: usize, blocks: &mut [[u8; 16]], rounds: usize, roundkeys: &[Columns; 15]) {
+    let (groups, remainder) = blocks.as_chunks_mut::<BLOCKS>();
+    for group in groups {
+        run_interleaved(tables, s_box, shift, group, rounds, roundkeys);
+    }
+    for block in remainder {
+        run_interleaved(tables, s_box, shift, std::array::from_mut(block), rounds, roundkeys);
+    }
+}
+
+///
+/// Runs the rounds on N blocks, one round of all the blocks at a time. The first
+/// roundkey is added to the states, followed by rounds - 1 table rounds and a
+/// final round with only the s-box and the row shifting.
+///
+fn run_interleaved<const N: usize>(tables: &[[u32; 256]; 4], s_box: &[u8; 256], shift: usize, blocks: &mut [[u8; 16]; N], rounds: usize, roundkeys: &[Columns; 15]) {
+    let mut states = blocks.map(|grid| from_bytes(&grid));
+    states.iter_mut().for_each(|state| add_roundkey(state, &roundkeys[0]));
+    for roundkey in &roundkeys[1..rounds] {
+        let mut mixed: [Columns; N] = std::array::from_fn(|index| std::array::from_fn(|column| {
+            (0..4).fold(roundkey[column], |word, row| word ^ tables[row][byte(&states[index], column, row, shift)])
+        }));
+        states = mixed;
+        mixed.zeroize();
+    }
+    for (grid, state) in blocks.iter_mut().zip(&states) {
+        let mut last: Columns = std::array::from_fn(|column| {
+            roundkeys[rounds][column] ^ u32::from_be_bytes(std::array::from_fn(|row| s_box[byte(state, column, row, shift)]))
+        });
+        to_bytes(&last, grid);
+        last.zeroize();
+    }
+    states.zeroize();
+}
+
+///
+/// Finds the byte of the state that ends up in the row of the column after the
+/// row shifting.
+///
+fn byte(state: &Columns, column: usize, row: usize, shift: usize) -> usize {
+    (state[(column + row * shift) % 4] >> (24 - 8 * row) & 0xff) as usize
+}
+
+
+#[cfg(all(test, feature = "encrypt", feature = "decrypt"))]
+mod tests {
+
+    use super::*;
+    use crate::test_util::hex;
+    use crate::key_schedule::{ReverseRoundKeyGenerator, RoundKeyGenerator};
+    use crate::{KeySchedule, RoundKeys};
+
+    #[test]
+    fn test_tables() {
+        assert_eq!([0xc66363a5, 0xa5c66363, 0x63a5c663, 0x6363a5c6], ENCRYPT_TABLES.map(|table| table[0]));
+        assert_eq!(0x2c16163a, ENCRYPT_TABLES[0][0xff]);
+        assert_eq!([0x51f4a750, 0x5051f4a7, 0xa75051f4, 0xf4a75051], DECRYPT_TABLES.map(|table| table[0]));
+        assert_eq!(0xd0b85742, DECRYPT_TABLES[0][0xff]);
+    }
+
+    #[test]
+    fn test_fips197_vectors() {
+        let plaintext: [u8; 16] = hex("00112233445566778899aabbccddeeff").try_into().unwrap();
+        let vectors = [
+            ("000102030405060708090a0b0c0d0e0f", "69c4e0d86a7b0430d8cdb78070b4c55a"),
+            ("000102030405060708090a0b0c0d0e0f1011121314151617", "dda97ca4864cdfe06eaf70a0ec0d7191"),
+            ("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f", "8ea2b7ca516745bfeafc49904b496089")
+        ];
+        for (key, expected) in vectors {
+            let key = hex(key);
+            let roundkeys = RoundKeys::new(RoundKeyGenerator::new(&key).map(|roundkey| *roundkey).collect()).unwrap();
+            let mut blocks = [plaintext; 6];
+            encrypt_rounds(&mut blocks, roundkeys.rounds(), roundkeys.as_slice().iter());
+            assert_eq!([<[u8; 16]>::try_from(hex(expected)).unwrap(); 6], blocks);
+            let mut decrypted = blocks;
+            decrypt_equivalent_rounds(&mut decrypted, &InverseRoundKeys::new(&roundkeys));
+            assert_eq!([plaintext; 6], decrypted);
+            let mut decrypted = blocks;
+            decrypt_rounds(&mut decrypted, roundkeys.rounds(), ReverseRoundKeyGenerator::new(&ReverseRoundKeyGenerator::last_words(&key)));
+            assert_eq!([plaintext; 6], decrypted);
+        }
+    }
+
+    #[test]
+    fn test_inverse_mix_column() {
+        assert_eq!(0xdb135345, inverse_mix_column(0x8e4da1bc));
+        let roundkeys = KeySchedule::<16>::new(&[7; 16]).roundkeys().clone();
+        let inverse = InverseRoundKeys::new(&roundkeys);
+        assert_eq!(from_bytes(&inverse.as_slice()[1]), from_bytes(&roundkeys.as_slice()[1]).map(inverse_mix_column));
+    }
+
+}
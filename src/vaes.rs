@@ -0,0 +1,200 @@
+//!
+//! VAES implementation of the rounds, for x86_64 cpus with AVX-512 such as Intel
+//! Ice Lake and AMD Zen 4. The VAES instructions compute a round of four blocks at
+//! a time in a 512 bit register, so the blocks are processed in groups of four and
+//! the remaining blocks with the AES-NI instructions. The instructions take
+//! constant time.
+//!
+//! The instructions are optional, so the support is detected when the backend is
+//! chosen. The functions must only be called when is_supported is true.
+//!
+
+use std::arch::x86_64::{__m128i, __m512i, _mm512_broadcast_i32x4, _mm512_loadu_si512, _mm512_storeu_si512, _mm512_xor_si512, _mm_loadu_si128, _mm_setzero_si128, _mm_storeu_si128, _mm_xor_si128};
+#[cfg(feature = "decrypt")]
+use std::arch::x86_64::{_mm512_aesdec_epi128, _mm512_aesdeclast_epi128, _mm_aesdec_si128, _mm_aesdeclast_si128, _mm_aesimc_si128};
+#[cfg(feature = "encrypt")]
+use std::arch::x86_64::{_mm512_aesenc_epi128, _mm512_aesenclast_epi128, _mm_aesenc_si128, _mm_aesenclast_si128};
+use std::ops::Deref;
+
+use crate::zeroize::Zeroizing;
+#[cfg(feature = "decrypt")]
+use crate::InverseRoundKeys;
+
+///
+/// Whether the cpu supports the VAES, AVX-512 and AES-NI instructions.
+///
+/// result: true if the backend can be used.
+///
+pub(crate) fn is_supported() -> bool {
+    std::arch::is_x86_feature_detected!("vaes") && std::arch::is_x86_feature_detected!("avx512f") && std::arch::is_x86_feature_detected!("aes")
+}
+
+///
+/// Encrypts the blocks in place with the roundkeys produced by an iterator.
+///
+/// blocks: The blocks to encrypt, replaced by the encrypted blocks.
+/// rounds: The number of rounds.
+/// roundkeys: An iterator producing rounds + 1 roundkeys of 16 bytes.
+///
+#[cfg(feature = "encrypt")]
+pub(crate) fn encrypt_rounds<K: Deref<Target = [u8; 16]>>(blocks: &mut [[u8; 16]], rounds: usize, roundkeys: impl Iterator<Item = K>) {
+    debug_assert!(is_supported());
+    let roundkeys = copy_roundkeys(roundkeys);
+    // The backend is only chosen when the cpu supports the instructions.
+    unsafe { encrypt(blocks, rounds, &roundkeys) }
+}
+
+///
+/// Decrypts the blocks in place with the roundkeys produced by an iterator in
+/// reverse order. AESIMC is applied to the roundkeys of the middle rounds, so the
+/// equivalent inverse cipher can be used.
+///
+/// blocks: The blocks to decrypt, replaced by the decrypted blocks.
+/// rounds: The number of rounds.
+/// roundkeys: An iterator producing rounds + 1 roundkeys of 16 bytes, starting
+///            with the last roundkey.
+///
+#[cfg(feature = "decrypt")]
+pub(crate) fn decrypt_rounds<K: Deref<Target = [u8; 16]>>(blocks: &mut [[u8; 16]], rounds: usize, roundkeys: impl Iterator<Item = K>) {
+    debug_assert!(is_supported());
+    let roundkeys = copy_roundkeys(roundkeys);
+    // The backend is only chosen when the cpu supports the instructions.
+    unsafe { decrypt(blocks, rounds, &roundkeys, true) }
+}
+
+///
+/// Decrypts the blocks in place using the equivalent inverse cipher.
+///
+/// blocks: The blocks to decrypt, replaced by the decrypted blocks.
+/// roundkeys: The inverse roundkeys, containing 11, 13 or 15 roundkeys.
+///
+#[cfg(feature = "decrypt")]
+pub(crate) fn decrypt_equivalent_rounds(blocks: &mut [[u8; 16]], roundkeys: &InverseRoundKeys) {
+    debug_assert!(is_supported());
+    let rounds = roundkeys.rounds();
+    let roundkeys = copy_roundkeys(roundkeys.as_slice().iter().rev());
+    // The backend is only chosen when the cpu supports the instructions.
+    unsafe { decrypt(blocks, rounds, &roundkeys, false) }
+}
+
+///
+/// Copies the roundkeys into an array, in the order they are used.
+///
+fn copy_roundkeys<K: Deref<Target = [u8; 16]>>(roundkeys: impl Iterator<Item = K>) -> Zeroizing<[[u8; 16]; 15]> {
+    let mut copied = Zeroizing([[0; 16]; 15]);
+    for (slot, roundkey) in copied.iter_mut().zip(roundkeys) {
+        *slot = *roundkey;
+    }
+    copied
+}
+
+///
+/// Loads the roundkeys into 128 bit registers and broadcasts each of them to the
+/// four lanes of a 512 bit register.
+///
+#[target_feature(enable = "avx512f,aes")]
+unsafe fn load_roundkeys(roundkeys: &[[u8; 16]; 15]) -> ([__m128i; 15], [__m512i; 15]) {
+    let mut keys = [_mm_setzero_si128(); 15];
+    for (key, roundkey) in keys.iter_mut().zip(roundkeys) {
+        *key = _mm_loadu_si128(roundkey.as_ptr().cast());
+    }
+    (keys, keys.map(|key| _mm512_broadcast_i32x4(key)))
+}
+
+///
+/// Encrypts the blocks with AESENC and AESENCLAST, four blocks at a time with
+/// VAES. AESENC adds the roundkey after the mixing, so the first roundkey is added
+/// separately.
+///
+#[cfg(feature = "encrypt")]
+#[target_feature(enable = "avx512f,vaes,aes")]
+unsafe fn encrypt(blocks: &mut [[u8; 16]], rounds: usize, roundkeys: &[[u8; 16]; 15]) {
+    let (keys, wide_keys) = load_roundkeys(roundkeys);
+    let mut groups = blocks.chunks_exact_mut(4);
+    for group in groups.by_ref() {
+        let mut state = _mm512_xor_si512(_mm512_loadu_si512(group.as_ptr().cast()), wide_keys[0]);
+        for key in &wide_keys[1..rounds] {
+            state = _mm512_aesenc_epi128(state, *key);
+        }
+        state = _mm512_aesenclast_epi128(state, wide_keys[rounds]);
+        _mm512_storeu_si512(group.as_mut_ptr().cast(), state);
+    }
+    for block in groups.into_remainder() {
+        let mut state = _mm_xor_si128(_mm_loadu_si128(block.as_ptr().cast()), keys[0]);
+        for key in &keys[1..rounds] {
+            state = _mm_aesenc_si128(state, *key);
+        }
+        state = _mm_aesenclast_si128(state, keys[rounds]);
+        _mm_storeu_si128(block.as_mut_ptr().cast(), state);
+    }
+}
+
+///
+/// Decrypts the blocks with AESDEC and AESDECLAST using the equivalent inverse
+/// cipher, four blocks at a time with VAES. Without mixed roundkeys, AESIMC is
+/// applied to the roundkeys of the middle rounds first.
+///
+#[cfg(feature = "decrypt")]
+#[target_feature(enable = "avx512f,vaes,aes")]
+unsafe fn decrypt(blocks: &mut [[u8; 16]], rounds: usize, roundkeys: &[[u8; 16]; 15], mix_roundkeys: bool) {
+    let (mut keys, mut wide_keys) = load_roundkeys(roundkeys);
+    if mix_roundkeys {
+        for (key, wide_key) in keys[1..rounds].iter_mut().zip(&mut wide_keys[1..rounds]) {
+            *key = _mm_aesimc_si128(*key);
+            *wide_key = _mm512_broadcast_i32x4(*key);
+        }
+    }
+    let mut groups = blocks.chunks_exact_mut(4);
+    for group in groups.by_ref() {
+        let mut state = _mm512_xor_si512(_mm512_loadu_si512(group.as_ptr().cast()), wide_keys[0]);
+        for key in &wide_keys[1..rounds] {
+            state = _mm512_aesdec_epi128(state, *key);
+        }
+        state = _mm512_aesdeclast_epi128(state, wide_keys[rounds]);
+        _mm512_storeu_si512(group.as_mut_ptr().cast(), state);
+    }
+    for block in groups.into_remainder() {
+        let mut state = _mm_xor_si128(_mm_loadu_si128(block.as_ptr().cast()), keys[0]);
+        for key in &keys[1..rounds] {
+            state = _mm_aesdec_si128(state, *key);
+        }
+        state = _mm_aesdeclast_si128(state, keys[rounds]);
+        _mm_storeu_si128(block.as_mut_ptr().cast(), state);
+    }
+}
+
+#[cfg(all(test, feature = "encrypt", feature = "decrypt"))]
+mod tests {
+
+    use super::*;
+    use crate::test_util::hex;
+    use crate::key_schedule::{ReverseRoundKeyGenerator, RoundKeyGenerator};
+    use crate::RoundKeys;
+
+    #[test]
+    fn test_fips197_vectors() {
+        if !is_supported() {
+            return;
+        }
+        let plaintext: [u8; 16] = hex("00112233445566778899aabbccddeeff").try_into().unwrap();
+        let vectors = [
+            ("000102030405060708090a0b0c0d0e0f", "69c4e0d86a7b0430d8cdb78070b4c55a"),
+            ("000102030405060708090a0b0c0d0e0f1011121314151617", "dda97ca4864cdfe06eaf70a0ec0d7191"),
+            ("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f", "8ea2b7ca516745bfeafc49904b496089")
+        ];
+        for (key, expected) in vectors {
+            let key = hex(key);
+            let roundkeys = RoundKeys::new(RoundKeyGenerator::new(&key).map(|roundkey| *roundkey).collect()).unwrap();
+            let mut blocks = [plaintext; 7];
+            encrypt_rounds(&mut blocks, roundkeys.rounds(), roundkeys.as_slice().iter());
+            assert_eq!([<[u8; 16]>::try_from(hex(expected)).unwrap(); 7], blocks);
+            let mut decrypted = blocks;
+            decrypt_equivalent_rounds(&mut decrypted, &InverseRoundKeys::new(&roundkeys));
+            assert_eq!([plaintext; 7], decrypted);
+            let mut decrypted = blocks;
+            decrypt_rounds(&mut decrypted, roundkeys.rounds(), ReverseRoundKeyGenerator::new(&ReverseRoundKeyGenerator::last_words(&key)));
+            assert_eq!([plaintext; 7], decrypted);
+        }
+    }
+
+}
@@ -0,0 +1,144 @@
+//!
+//! XAES-256-GCM, an extended nonce construction as specified by C2SP. A per 
+//! message AES-256 key is derived from the key and the first 12 bytes of the 24
+//! byte nonce with a CMAC based KDF in counter mode (NIST SP 800-108), and the 
+//! message is encrypted with AES-256-GCM and the last 12 bytes of the nonce.
+//! 
+//! The 192 bit nonce is long enough to be chosen at random for practically any
+//! number of messages with the same key.
+//! 
+
+use std::vec::Vec;
+
+use crate::cmac;
+use crate::gcm::Gcm;
+use crate::{Aead, Aes256, AesError, SecretKey};
+
+///
+/// XaesGcm is an XAES-256-GCM cipher with 16 byte tags.
+/// 
+pub struct XaesGcm {
+    cipher: Aes256,
+    subkey: [u8; 16]
+}
+
+impl XaesGcm {
+
+    ///
+    /// Creates a new XaesGcm cipher.
+    /// 
+    /// key: The 32 byte key.
+    /// 
+    /// result: A new XaesGcm cipher.
+    /// 
+    pub fn new(key: &[u8; 32]) -> Self {
+        let cipher = Aes256::new(key);
+        let subkey = cmac::double(&cipher.encrypt_bytes(&[0; 16]).try_into().unwrap());
+        Self { cipher, subkey }
+    }
+
+    ///
+    /// Encrypts and authenticates the plaintext.
+    /// 
+    /// nonce: The 24 byte nonce, which may be random.
+    /// aad: The associated data, authenticated but not encrypted.
+    /// plaintext: The data to encrypt.
+    /// 
+    /// result: The ciphertext followed by the 16 byte tag or the error from GCM.
+    /// 
+    pub fn encrypt(&self, nonce: &[u8; 24], aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, AesError> {
+        self.derive(nonce).encrypt(&nonce[12..], aad, plaintext)
+    }
+
+    ///
+    /// Decrypts the ciphertext and verifies the tag.
+    /// 
+    /// nonce: The 24 byte nonce used when encrypting.
+    /// aad: The associated data used when encrypting.
+    /// ciphertext: The ciphertext followed by the 16 byte tag.
+    /// 
+    /// result: The plaintext, AesError::AuthenticationFailed if the tag does not
+    ///         match or another error from GCM.
+    /// 
+    pub fn decrypt(&self, nonce: &[u8; 24], aad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, AesError> {
+        self.derive(nonce).decrypt(&nonce[12..], aad, ciphertext)
+    }
+
+    ///
+    /// Derives the GCM cipher of the message from the first half of the nonce.
+    /// 
+    /// nonce: The 24 byte nonce.
+    /// 
+    /// result: A Gcm cipher with the derived key.
+    /// 
+    fn derive(&self, nonce: &[u8; 24]) -> Gcm<32> {
+        let mut key = [0; 32];
+        for (index, half) in key.chunks_mut(16).enumerate() {
+            let mut block = [0, index as u8 + 1, b'X', 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+            block[4..].copy_from_slice(&nonce[..12]);
+            block.iter_mut().zip(self.subkey).for_each(|(a, b)| *a ^= b);
+            half.copy_from_slice(&self.cipher.encrypt_bytes(&block));
+        }
+        Gcm::new(&key, 16).unwrap()
+    }
+
+}
+
+impl From<&SecretKey<32>> for XaesGcm {
+
+    fn from(key: &SecretKey<32>) -> Self {
+        XaesGcm::new(key.expose_secret())
+    }
+
+}
+
+///
+/// As an Aead the nonce must be 24 bytes, or AesError::InvalidNonceLength is returned.
+/// 
+impl Aead for XaesGcm {
+
+    fn encrypt(&self, nonce: &[u8], aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, AesError> {
+        XaesGcm::encrypt(self, &nonce.try_into().map_err(|_| AesError::InvalidNonceLength { length: nonce.len(), min: 24, max: 24 })?, aad, plaintext)
+    }
+
+    fn decrypt(&self, nonce: &[u8], aad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, AesError> {
+        XaesGcm::decrypt(self, &nonce.try_into().map_err(|_| AesError::InvalidNonceLength { length: nonce.len(), min: 24, max: 24 })?, aad, ciphertext)
+    }
+
+    fn tag_length(&self) -> usize {
+        16
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::test_util::hex;
+
+    const NONCE: &[u8; 24] = b"ABCDEFGHIJKLMNOPQRSTUVWX";
+
+    #[test]
+    fn test_c2sp_vectors() {
+        let xaes = XaesGcm::new(&[1; 32]);
+        let expected = hex("ce546ef63c9cc60765923609b33a9a1974e96e52daf2fcf7075e2271");
+        assert_eq!(expected, xaes.encrypt(NONCE, &[], b"XAES-256-GCM").unwrap());
+        assert_eq!(b"XAES-256-GCM".to_vec(), xaes.decrypt(NONCE, &[], &expected).unwrap());
+        let xaes = XaesGcm::new(&[3; 32]);
+        let expected = hex("986ec1832593df5443a179437fd083bf3fdb41abd740a21f71eb769d");
+        assert_eq!(expected, xaes.encrypt(NONCE, b"c2sp.org/XAES-256-GCM", b"XAES-256-GCM").unwrap());
+        assert_eq!(b"XAES-256-GCM".to_vec(), xaes.decrypt(NONCE, b"c2sp.org/XAES-256-GCM", &expected).unwrap());
+    }
+
+    #[test]
+    fn test_xaes_authentication_failed() {
+        let xaes = XaesGcm::new(&[1; 32]);
+        let encrypted = xaes.encrypt(NONCE, b"header", b"payload").unwrap();
+        let mut nonce = *NONCE;
+        nonce[0] ^= 1;
+        assert_eq!(Some(AesError::AuthenticationFailed), xaes.decrypt(&nonce, b"header", &encrypted).err());
+        assert_eq!(Some(AesError::InvalidNonceLength { length: 12, min: 24, max: 24 }), Aead::encrypt(&xaes, &[0; 12], &[], &[]).err());
+    }
+
+}
@@ -0,0 +1,180 @@
+//!
+//! AES-XCBC-MAC-96 from RFC 3566 and AES-XCBC-PRF-128 from RFC 4434, as used by
+//! IPsec and IKE. XCBC is a CBC-MAC with a key K1 derived from the key, where 
+//! the last block is xored with the derived key K2 if it is full or padded and 
+//! xored with the derived key K3 otherwise. XCBC is only defined for AES-128.
+//! 
+//! The PRF accepts keys of any length. Shorter keys are padded with zeros and 
+//! longer keys are replaced by their mac under the zero key.
+//! 
+
+use crate::cbc_mac::chain_block;
+use crate::cmac::finalize_block;
+use crate::mac::BlockBuffer;
+use crate::zeroize::Zeroize;
+use crate::{Aes128, Mac};
+
+///
+/// XcbcMac is an AES-XCBC-MAC with the keys K1, K2 and K3 derived.
+/// 
+pub struct XcbcMac {
+    cipher: Aes128,
+    complete_key: [u8; 16],
+    partial_key: [u8; 16]
+}
+
+impl XcbcMac {
+
+    ///
+    /// Creates a new XcbcMac.
+    /// 
+    /// key: The 16 byte key.
+    /// 
+    /// result: A new XcbcMac.
+    /// 
+    pub fn new(key: &[u8; 16]) -> Self {
+        let cipher = Aes128::new(key);
+        let derive = |constant: u8| -> [u8; 16] { cipher.encrypt_bytes(&[constant; 16]).try_into().unwrap() };
+        let (derived, complete_key, partial_key) = (derive(1), derive(2), derive(3));
+        Self { cipher: Aes128::new(&derived), complete_key, partial_key }
+    }
+
+    ///
+    /// Calculates the full mac of the data.
+    /// 
+    /// data: The data to authenticate. May be of any length.
+    /// 
+    /// result: The 16 byte mac.
+    /// 
+    pub fn mac(&self, data: &[u8]) -> [u8; 16] {
+        let mut state = self.start();
+        state.update(data);
+        state.finalize()
+    }
+
+    ///
+    /// Calculates the AES-XCBC-MAC-96 of the data, the mac truncated to 12 bytes.
+    /// 
+    /// data: The data to authenticate. May be of any length.
+    /// 
+    /// result: The 12 byte mac.
+    /// 
+    pub fn mac_96(&self, data: &[u8]) -> [u8; 12] {
+        self.mac(data)[..12].try_into().unwrap()
+    }
+
+    ///
+    /// Starts an incremental mac.
+    /// 
+    /// result: An XcbcState without any data.
+    /// 
+    pub fn start(&self) -> XcbcState<'_> {
+        XcbcState { xcbc: self, state: [0; 16], buffer: BlockBuffer::new() }
+    }
+
+}
+
+///
+/// XcbcState is an incremental AES-XCBC-MAC.
+/// 
+pub struct XcbcState<'a> {
+    xcbc: &'a XcbcMac,
+    state: [u8; 16],
+    buffer: BlockBuffer
+}
+
+impl XcbcState<'_> {
+
+    ///
+    /// Calculates the AES-XCBC-MAC-96 of all the data added.
+    /// 
+    /// result: The 12 byte mac.
+    /// 
+    pub fn finalize_96(self) -> [u8; 12] {
+        self.finalize()[..12].try_into().unwrap()
+    }
+
+}
+
+impl Mac for XcbcState<'_> {
+
+    type Output = [u8; 16];
+
+    fn update(&mut self, data: &[u8]) {
+        let (cipher, state) = (&self.xcbc.cipher, &mut self.state);
+        self.buffer.update(data, |block| chain_block(cipher, state, block));
+    }
+
+    fn finalize(mut self) -> Self::Output {
+        let last = finalize_block(self.buffer.last(), &self.xcbc.complete_key, &self.xcbc.partial_key);
+        chain_block(&self.xcbc.cipher, &mut self.state, &last);
+        self.state
+    }
+
+}
+
+///
+/// Calculates AES-XCBC-PRF-128 of the data.
+/// 
+/// key: The key, of any length.
+/// data: The data.
+/// 
+/// result: The 16 byte output of the prf.
+/// 
+pub fn prf_128(key: &[u8], data: &[u8]) -> [u8; 16] {
+    let key: [u8; 16] = match key.len() {
+        16 => key.try_into().unwrap(),
+        length if length < 16 => {
+            let mut padded = [0; 16];
+            padded[..length].copy_from_slice(key);
+            padded
+        },
+        _ => XcbcMac::new(&[0; 16]).mac(key)
+    };
+    XcbcMac::new(&key).mac(data)
+}
+
+impl Drop for XcbcMac {
+
+    fn drop(&mut self) {
+        self.complete_key.zeroize();
+        self.partial_key.zeroize();
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::test_util::hex;
+
+    #[test]
+    fn test_rfc3566_vectors() {
+        let key: Vec<u8> = (0..16).collect();
+        let message: Vec<u8> = (0..34).collect();
+        let xcbc = XcbcMac::new(&key.try_into().unwrap());
+        assert_eq!(hex("75f0251d528ac01c4573dfd584d79f29"), xcbc.mac(&[]));
+        assert_eq!(hex("5b376580ae2f19afe7219ceef172756f"), xcbc.mac(&message[..3]));
+        assert_eq!(hex("d2a246fa349b68a79998a4394ff7a263"), xcbc.mac(&message[..16]));
+        assert_eq!(hex("47f51b4564966215b8985c63055ed308"), xcbc.mac(&message[..20]));
+        assert_eq!(hex("f54f0ec8d2b9f3d36807734bd5283fd4"), xcbc.mac(&message[..32]));
+        assert_eq!(hex("becbb3bccdb518a30677d5481fb6b4d8"), xcbc.mac(&message));
+        assert_eq!(hex("f0dafee895db30253761103b5d84528f"), xcbc.mac(&[0; 1000]));
+        assert_eq!(hex("5b376580ae2f19afe7219cee"), xcbc.mac_96(&message[..3]));
+        let mut state = xcbc.start();
+        state.update(&message[..5]);
+        state.update(&message[5..]);
+        assert_eq!(hex("becbb3bccdb518a30677d5481fb6b4d8")[..12], state.finalize_96());
+    }
+
+    #[test]
+    fn test_rfc4434_vectors() {
+        let key: Vec<u8> = (0..16).chain([0xed, 0xcb]).collect();
+        let message: Vec<u8> = (0..20).collect();
+        assert_eq!(hex("47f51b4564966215b8985c63055ed308"), prf_128(&key[..16], &message));
+        assert_eq!(hex("0fa087af7d866e7653434e602fdde835"), prf_128(&key[..10], &message));
+        assert_eq!(hex("8cd3c93ae598a9803006ffb67c40e9e4"), prf_128(&key, &message));
+    }
+
+}
@@ -0,0 +1,314 @@
+//!
+//! XTS-AES mode for sector based storage, as specified in IEEE 1619. Every data
+//! unit, for example a disk sector, is encrypted with a tweak derived from its
+//! sector number, so identical sectors give different ciphertexts.
+//!
+//! Two keys are used, one for the data and one for the tweak. A data unit that is
+//! not a multiple of 16 bytes is handled with ciphertext stealing, so the
+//! ciphertext always has the same length as the plaintext.
+//!
+
+use std::vec::Vec;
+
+#[cfg(feature = "parallel")]
+use crate::parallel;
+use crate::{Aes, AesError};
+
+///
+/// Xts is a XTS-AES cipher made from a data key and a tweak key of the same length.
+/// 
+pub struct Xts<const KEY_BYTES: usize = 16> {
+    data_cipher: Aes<KEY_BYTES>,
+    tweak_cipher: Aes<KEY_BYTES>
+}
+
+///
+/// XTS-AES-128 with two 16 byte keys.
+/// 
+pub type Xts128 = Xts<16>;
+///
+/// XTS-AES-256 with two 32 byte keys.
+/// 
+pub type Xts256 = Xts<32>;
+
+impl<const KEY_BYTES: usize> Xts<KEY_BYTES> {
+
+    ///
+    /// Creates a new Xts cipher. The keys should be independent of each other.
+    /// 
+    /// data_key: The key used to encrypt the data.
+    /// tweak_key: The key used to encrypt the tweak.
+    /// 
+    /// result: A new Xts cipher.
+    /// 
+    pub fn new(data_key: &[u8; KEY_BYTES], tweak_key: &[u8; KEY_BYTES]) -> Self {
+        Self {
+            data_cipher: Aes::new(data_key),
+            tweak_cipher: Aes::new(tweak_key)
+        }
+    }
+
+    ///
+    /// Encrypts a data unit.
+    /// 
+    /// sector_number: The sequence number of the data unit, used as the tweak.
+    /// data: The data to encrypt. Must be at least 16 bytes.
+    /// 
+    /// result: The encrypted data or AesError::InvalidDataLength if the data is
+    ///         shorter than 16 bytes.
+    /// 
+    pub fn encrypt_sector(&self, sector_number: u128, data: &[u8]) -> Result<Vec<u8>, AesError> {
+        self.crypt_sector(sector_number, data, false)
+    }
+
+    ///
+    /// Decrypts a data unit.
+    /// 
+    /// sector_number: The sequence number of the data unit used when encrypting.
+    /// data: The data to decrypt. Must be at least 16 bytes.
+    /// 
+    /// result: The decrypted data or AesError::InvalidDataLength if the data is
+    ///         shorter than 16 bytes.
+    /// 
+    pub fn decrypt_sector(&self, sector_number: u128, data: &[u8]) -> Result<Vec<u8>, AesError> {
+        self.crypt_sector(sector_number, data, true)
+    }
+
+    ///
+    /// Encrypts consecutive data units of the same size, with the chunks of the
+    /// data encrypted in parallel. The data unit at offset i * sector_size has the
+    /// sector number first_sector_number + i, and the last data unit may be shorter.
+    /// 
+    /// first_sector_number: The sequence number of the first data unit.
+    /// sector_size: The size of each data unit. Must be at least 16 bytes.
+    /// data: The data units to encrypt. The last data unit must be at least 16 bytes.
+    /// chunk_size: The bytes processed by a thread at a time, rounded down to a 
+    ///             multiple of the sector size, for example parallel::DEFAULT_CHUNK_SIZE.
+    /// 
+    /// result: The encrypted data units or AesError::InvalidDataLength with the 
+    ///         length of the data unit if a data unit is shorter than 16 bytes.
+    /// 
+    #[cfg(feature = "parallel")]
+    pub fn encrypt_sectors_parallel(&self, first_sector_number: u128, sector_size: usize, data: &[u8], chunk_size: usize) -> Result<Vec<u8>, AesError> {
+        self.crypt_sectors_parallel(first_sector_number, sector_size, data, chunk_size, false)
+    }
+
+    ///
+    /// Decrypts consecutive data units of the same size, with the chunks of the
+    /// data decrypted in parallel.
+    /// 
+    /// first_sector_number: The sequence number of the first data unit.
+    /// sector_size: The size of each data unit. Must be at least 16 bytes.
+    /// data: The data units to decrypt. The last data unit must be at least 16 bytes.
+    /// chunk_size: The bytes processed by a thread at a time, rounded down to a 
+    ///             multiple of the sector size, for example parallel::DEFAULT_CHUNK_SIZE.
+    /// 
+    /// result: The decrypted data units or AesError::InvalidDataLength with the 
+    ///         length of the data unit if a data unit is shorter than 16 bytes.
+    /// 
+    #[cfg(feature = "parallel")]
+    pub fn decrypt_sectors_parallel(&self, first_sector_number: u128, sector_size: usize, data: &[u8], chunk_size: usize) -> Result<Vec<u8>, AesError> {
+        self.crypt_sectors_parallel(first_sector_number, sector_size, data, chunk_size, true)
+    }
+
+    ///
+    /// Checks the lengths of the data units and encrypts or decrypts the chunks in
+    /// parallel, each data unit with crypt_sector.
+    /// 
+    #[cfg(feature = "parallel")]
+    fn crypt_sectors_parallel(&self, first_sector_number: u128, sector_size: usize, data: &[u8], chunk_size: usize, decrypt: bool) -> Result<Vec<u8>, AesError> {
+        if sector_size < 16 {
+            return Err(AesError::InvalidDataLength { length: sector_size, min: 16, multiple: 1 });
+        }
+        if !data.len().is_multiple_of(sector_size) && data.len() % sector_size < 16 {
+            return Err(AesError::InvalidDataLength { length: data.len() % sector_size, min: 16, multiple: 1 });
+        }
+        let sectors_per_chunk = (chunk_size / sector_size).max(1);
+        let mut result = data.to_vec();
+        parallel::for_each_chunk(&mut result, chunk_size, sector_size, |index, chunk| {
+            let first_sector = first_sector_number.wrapping_add((index * sectors_per_chunk) as u128);
+            for (offset, sector) in chunk.chunks_mut(sector_size).enumerate() {
+                let processed = self.crypt_sector(first_sector.wrapping_add(offset as u128), sector, decrypt).unwrap();
+                sector.copy_from_slice(&processed);
+            }
+        });
+        Ok(result)
+    }
+
+    ///
+    /// Encrypts or decrypts all full blocks and steals ciphertext for a partial 
+    /// last block. With stealing the last two tweaks are used in swapped order 
+    /// when decrypting. The full blocks are xored with their tweaks and passed to 
+    /// the cipher in one call, so the backends handling several blocks at once are 
+    /// used.
+    /// 
+    fn crypt_sector(&self, sector_number: u128, data: &[u8], decrypt: bool) -> Result<Vec<u8>, AesError> {
+        if data.len() < 16 {
+            return Err(AesError::InvalidDataLength { length: data.len(), min: 16, multiple: 1 });
+        }
+        let partial = data.len() % 16;
+        let full_blocks = data.len() / 16 - usize::from(partial != 0);
+        let sector_tweak = self.tweak_cipher.encrypt_bytes(&sector_number.to_le_bytes());
+        let mut result = Vec::with_capacity(data.len());
+        result.extend_from_slice(&data[..full_blocks * 16]);
+        let tweak = xor_tweaks(&mut result, &sector_tweak);
+        if decrypt {
+            self.data_cipher.decrypt_blocks(&mut result)?;
+        } else {
+            self.data_cipher.encrypt_blocks(&mut result)?;
+        }
+        xor_tweaks(&mut result, &sector_tweak);
+        if partial != 0 {
+            let next_tweak = multiply_alpha(&tweak);
+            let (first_tweak, second_tweak) = if decrypt { (&next_tweak, &tweak) } else { (&tweak, &next_tweak) };
+            let last_full = &data[full_blocks * 16..full_blocks * 16 + 16];
+            let stolen = self.crypt_block(last_full, first_tweak, decrypt);
+            let mut combined = data[full_blocks * 16 + 16..].to_vec();
+            combined.extend_from_slice(&stolen[partial..]);
+            result.extend(self.crypt_block(&combined, second_tweak, decrypt));
+            result.extend_from_slice(&stolen[..partial]);
+        }
+        Ok(result)
+    }
+
+    ///
+    /// Xors the block with the tweak before and after the cipher.
+    /// 
+    fn crypt_block(&self, block: &[u8], tweak: &[u8], decrypt: bool) -> Vec<u8> {
+        let input: Vec<u8> = block.iter().zip(tweak).map(|(a, b)| a ^ b).collect();
+        let output = if decrypt { self.data_cipher.decrypt_bytes(&input) } else { self.data_cipher.encrypt_bytes(&input) };
+        output.iter().zip(tweak).map(|(a, b)| a ^ b).collect()
+    }
+
+}
+
+///
+/// Xors each block with its tweak, starting with the first tweak and multiplying
+/// by alpha for every block.
+/// 
+/// blocks: The whole blocks to xor with the tweaks.
+/// tweak: The tweak of the first block.
+/// 
+/// result: The tweak of the block after the last block.
+/// 
+fn xor_tweaks(blocks: &mut [u8], tweak: &[u8]) -> Vec<u8> {
+    let mut tweak = tweak.to_vec();
+    for block in blocks.chunks_exact_mut(16) {
+        block.iter_mut().zip(&tweak).for_each(|(byte, key)| *byte ^= key);
+        tweak = multiply_alpha(&tweak);
+    }
+    tweak
+}
+
+///
+/// Multiplies the tweak by the primitive element alpha in GF(2^128). The tweak is
+/// a little endian number and the reduction polynomial is x^128 + x^7 + x^2 + x + 1.
+/// 
+fn multiply_alpha(tweak: &[u8]) -> Vec<u8> {
+    let value = u128::from_le_bytes(tweak.try_into().unwrap());
+    let carry = value >> 127;
+    ((value << 1) ^ (carry * 0x87)).to_le_bytes().to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::test_util::hex;
+
+    const KEY_128: &str = "27182818284590452353602874713526 31415926535897932384626433832795";
+    const KEY_256: &str = "2718281828459045235360287471352662497757247093699959574966967627
+                           3141592653589793238462643383279502884197169399375105820974944592";
+
+    fn xts128(keys: &str) -> Xts128 {
+        let keys = hex(keys);
+        Xts128::new(&keys[..16].try_into().unwrap(), &keys[16..].try_into().unwrap())
+    }
+
+    fn xts256(keys: &str) -> Xts256 {
+        let keys = hex(keys);
+        Xts256::new(&keys[..32].try_into().unwrap(), &keys[32..].try_into().unwrap())
+    }
+
+    fn range(length: u8) -> Vec<u8> {
+        (0..length).collect()
+    }
+
+    #[test]
+    fn test_ieee1619_vector_1() {
+        let xts = xts128("00000000000000000000000000000000 00000000000000000000000000000000");
+        let expected = hex("917cf69ebd68b2ec9b9fe9a3eadda692 cd43d2f59598ed858c02c2652fbf922e");
+        assert_eq!(expected, xts.encrypt_sector(0, &[0; 32]).unwrap());
+        assert_eq!(vec![0; 32], xts.decrypt_sector(0, &expected).unwrap());
+    }
+
+    #[test]
+    fn test_xts128() {
+        let xts = xts128(KEY_128);
+        let expected = hex("27a7479befa1d476489f308cd4cfa6e2 a96e4bbe3208ff25287dd3819616e89c");
+        assert_eq!(expected, xts.encrypt_sector(0, &range(32)).unwrap());
+        let expected = hex("b4223d09530ed5a17e11a6031d62c31d a7bade6ec1b03565ff2d0a6ed58683e5
+                            767b6854413740912004455b859342ab 22a1c336569186b3cf76c657aba87f1d");
+        assert_eq!(expected, xts.encrypt_sector(0x123456789a, &range(64)).unwrap());
+        assert_eq!(range(64), xts.decrypt_sector(0x123456789a, &expected).unwrap());
+    }
+
+    #[test]
+    fn test_xts256() {
+        let xts = xts256(KEY_256);
+        let expected = hex("1c3b3a102f770386e4836c99e370cf9b ea00803f5e482357a4ae12d414a3e63b
+                            5d31e276f8fe4a8d66b317f9ac683f44 680a86ac35adfc3345befecb4bb188fd");
+        assert_eq!(expected, xts.encrypt_sector(0xff, &range(64)).unwrap());
+        assert_eq!(range(64), xts.decrypt_sector(0xff, &expected).unwrap());
+    }
+
+    #[test]
+    fn test_xts_ciphertext_stealing() {
+        let xts = xts128(KEY_128);
+        let vectors = [
+            (17, hex("7564bb107d17706d99fe5d9ba276a49e 91")),
+            (45, hex("9143d34ca9bbaf9fd30d2cafe33a7ed4 09c3d65d6a7595bd3f4197e7f7efcd9e 76c41b458bac06d170d7edf7ae"))
+        ];
+        for (length, expected) in vectors {
+            assert_eq!(expected, xts.encrypt_sector(7, &range(length)).unwrap());
+            assert_eq!(range(length), xts.decrypt_sector(7, &expected).unwrap());
+        }
+        let xts = xts256(KEY_256);
+        let expected = hex("1c3b3a102f770386e4836c99e370cf9b d500010cf8fe25a2cf7dce0764caf96c ea00803f5e");
+        assert_eq!(expected, xts.encrypt_sector(0xff, &range(37)).unwrap());
+        assert_eq!(range(37), xts.decrypt_sector(0xff, &expected).unwrap());
+    }
+
+    #[test]
+    fn test_xts_invalid_length() {
+        let xts = xts128(KEY_128);
+        assert_eq!(Some(AesError::InvalidDataLength { length: 15, min: 16, multiple: 1 }), xts.encrypt_sector(0, &[0; 15]).err());
+        assert_eq!(Some(AesError::InvalidDataLength { length: 0, min: 16, multiple: 1 }), xts.decrypt_sector(0, &[]).err());
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_xts_parallel() {
+        let xts = xts128(KEY_128);
+        let data: Vec<u8> = (0..2000).map(|value| value as u8).collect();
+        let expected: Vec<u8> = data.chunks(512).enumerate().flat_map(|(index, sector)| xts.encrypt_sector(5 + index as u128, sector).unwrap()).collect();
+        for chunk_size in [1, 512, 1500, parallel::DEFAULT_CHUNK_SIZE] {
+            assert_eq!(expected, xts.encrypt_sectors_parallel(5, 512, &data, chunk_size).unwrap());
+            assert_eq!(data, xts.decrypt_sectors_parallel(5, 512, &expected, chunk_size).unwrap());
+        }
+        assert_eq!(Some(AesError::InvalidDataLength { length: 8, min: 16, multiple: 1 }), xts.encrypt_sectors_parallel(0, 512, &[0; 520], 512).err());
+        assert_eq!(Some(AesError::InvalidDataLength { length: 8, min: 16, multiple: 1 }), xts.encrypt_sectors_parallel(0, 8, &[0; 16], 512).err());
+        assert!(xts.encrypt_sectors_parallel(0, 512, &[], 512).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_multiply_alpha() {
+        let mut tweak = vec![0; 16];
+        tweak[15] = 0x80;
+        let mut expected = vec![0; 16];
+        expected[0] = 0x87;
+        assert_eq!(expected, multiply_alpha(&tweak));
+        assert_eq!(hex("02000000000000000000000000000000"), multiply_alpha(&hex("01000000000000000000000000000000")));
+    }
+
+}
@@ -0,0 +1,131 @@
+//!
+//! Wiping of secret data. Keys, roundkeys, hash states and intermediate blocks 
+//! are overwritten with zeros when they are dropped, so they do not linger in 
+//! freed memory. The writes are volatile, so the compiler can not remove them
+//! even though the memory is never read again.
+//! 
+
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{compiler_fence, Ordering};
+use std::vec::Vec;
+
+///
+/// Zeroize is implemented by types that can be overwritten with zeros.
+/// 
+pub(crate) trait Zeroize {
+
+    ///
+    /// Overwrites the value with zeros.
+    /// 
+    fn zeroize(&mut self);
+
+}
+
+macro_rules! zeroize_primitive {
+    ($($primitive:ty),*) => {
+        $(
+            impl Zeroize for $primitive {
+                fn zeroize(&mut self) {
+                    // The reference is valid and aligned, so the write is sound.
+                    unsafe { std::ptr::write_volatile(self, 0) };
+                    compiler_fence(Ordering::SeqCst);
+                }
+            }
+        )*
+    };
+}
+
+zeroize_primitive!(u8, u32, u64, u128, usize);
+
+impl<T: Zeroize> Zeroize for [T] {
+
+    fn zeroize(&mut self) {
+        self.iter_mut().for_each(Zeroize::zeroize);
+    }
+
+}
+
+impl<T: Zeroize, const N: usize> Zeroize for [T; N] {
+
+    fn zeroize(&mut self) {
+        self.as_mut_slice().zeroize();
+    }
+
+}
+
+impl<T: Zeroize> Zeroize for Vec<T> {
+
+    ///
+    /// Overwrites the elements with zeros. Spare capacity is not touched, so 
+    /// vectors holding secrets should not be grown after they are filled.
+    /// 
+    fn zeroize(&mut self) {
+        self.as_mut_slice().zeroize();
+    }
+
+}
+
+///
+/// Zeroizing wraps a value, for example a generated roundkey, and overwrites it
+/// with zeros when it is dropped.
+/// 
+pub(crate) struct Zeroizing<T: Zeroize>(pub(crate) T);
+
+impl<T: Zeroize> Deref for Zeroizing<T> {
+
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+
+}
+
+impl<T: Zeroize> DerefMut for Zeroizing<T> {
+
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+
+}
+
+impl<T: Zeroize + AsRef<[u8]>> AsRef<[u8]> for Zeroizing<T> {
+
+    fn as_ref(&self) -> &[u8] {
+        self.0.as_ref()
+    }
+
+}
+
+impl<T: Zeroize + Clone> Clone for Zeroizing<T> {
+
+    fn clone(&self) -> Self {
+        Zeroizing(self.0.clone())
+    }
+
+}
+
+impl<T: Zeroize> Drop for Zeroizing<T> {
+
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_zeroize() {
+        let mut bytes = vec![vec![1u8; 16], vec![2; 16]];
+        bytes.zeroize();
+        assert_eq!(vec![vec![0u8; 16]; 2], bytes);
+        let mut words = [u32::MAX; 8];
+        words.zeroize();
+        assert_eq!([0; 8], words);
+    }
+
+}
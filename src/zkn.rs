@@ -0,0 +1,207 @@
+//!
+//! RISC-V scalar cryptography implementation of the rounds, for riscv64 cpus with
+//! the Zkne and Zknd extensions. A 64 bit instruction computes half of the next
+//! state from both halves of the current state, so a round is two instructions
+//! and two xors with the roundkey. AES64ESM and AES64DSM do a middle round without
+//! AddRoundKey, AES64ES and AES64DS the last round and AES64IM does InvMixColumns.
+//! The instructions take constant time.
+//!
+//! There is no runtime detection of the extensions on riscv64, so the backend is
+//! only compiled when the target enables them, for example with
+//! -C target-feature=+zkne,+zknd.
+//!
+
+use std::arch::asm;
+use std::ops::Deref;
+
+use crate::zeroize::Zeroizing;
+#[cfg(feature = "decrypt")]
+use crate::InverseRoundKeys;
+
+///
+/// Encrypts the blocks in place with the roundkeys produced by an iterator.
+///
+/// blocks: The blocks to encrypt, replaced by the encrypted blocks.
+/// rounds: The number of rounds.
+/// roundkeys: An iterator producing rounds + 1 roundkeys of 16 bytes.
+///
+#[cfg(feature = "encrypt")]
+pub(crate) fn encrypt_rounds<K: Deref<Target = [u8; 16]>>(blocks: &mut [[u8; 16]], rounds: usize, roundkeys: impl Iterator<Item = K>) {
+    let keys = copy_roundkeys(roundkeys);
+    for block in blocks.iter_mut() {
+        let [mut low, mut high] = halves(block, &keys[0]);
+        for key in &keys[1..rounds] {
+            (low, high) = (aes64esm(low, high) ^ key[0], aes64esm(high, low) ^ key[1]);
+        }
+        (low, high) = (aes64es(low, high) ^ keys[rounds][0], aes64es(high, low) ^ keys[rounds][1]);
+        store(block, low, high);
+    }
+}
+
+///
+/// Decrypts the blocks in place with the roundkeys produced by an iterator in
+/// reverse order. AES64IM is applied to the roundkeys of the middle rounds, so the
+/// equivalent inverse cipher can be used.
+///
+/// blocks: The blocks to decrypt, replaced by the decrypted blocks.
+/// rounds: The number of rounds.
+/// roundkeys: An iterator producing rounds + 1 roundkeys of 16 bytes, starting
+///            with the last roundkey.
+///
+#[cfg(feature = "decrypt")]
+pub(crate) fn decrypt_rounds<K: Deref<Target = [u8; 16]>>(blocks: &mut [[u8; 16]], rounds: usize, roundkeys: impl Iterator<Item = K>) {
+    let mut keys = copy_roundkeys(roundkeys);
+    for key in &mut keys[1..rounds] {
+        *key = key.map(aes64im);
+    }
+    decrypt(blocks, rounds, &keys);
+}
+
+///
+/// Decrypts the blocks in place using the equivalent inverse cipher.
+///
+/// blocks: The blocks to decrypt, replaced by the decrypted blocks.
+/// roundkeys: The inverse roundkeys, containing 11, 13 or 15 roundkeys.
+///
+#[cfg(feature = "decrypt")]
+pub(crate) fn decrypt_equivalent_rounds(blocks: &mut [[u8; 16]], roundkeys: &InverseRoundKeys) {
+    decrypt(blocks, roundkeys.rounds(), &copy_roundkeys(roundkeys.as_slice().iter().rev()));
+}
+
+///
+/// Decrypts the blocks with AES64DSM and AES64DS using the equivalent inverse
+/// cipher, with InvMixColumns already applied to the roundkeys of the middle rounds.
+///
+#[cfg(feature = "decrypt")]
+fn decrypt(blocks: &mut [[u8; 16]], rounds: usize, keys: &[[u64; 2]; 15]) {
+    for block in blocks.iter_mut() {
+        let [mut low, mut high] = halves(block, &keys[0]);
+        for key in &keys[1..rounds] {
+            (low, high) = (aes64dsm(low, high) ^ key[0], aes64dsm(high, low) ^ key[1]);
+        }
+        (low, high) = (aes64ds(low, high) ^ keys[rounds][0], aes64ds(high, low) ^ keys[rounds][1]);
+        store(block, low, high);
+    }
+}
+
+///
+/// Copies the roundkeys into an array of little endian halves, in the order they
+/// are used.
+///
+fn copy_roundkeys<K: Deref<Target = [u8; 16]>>(roundkeys: impl Iterator<Item = K>) -> Zeroizing<[[u64; 2]; 15]> {
+    let mut copied = Zeroizing([[0; 2]; 15]);
+    for (slot, roundkey) in copied.iter_mut().zip(roundkeys) {
+        *slot = halves(&roundkey, &[0; 2]);
+    }
+    copied
+}
+
+///
+/// Reads the block as two little endian halves, the first two columns in the low
+/// half, and xors the roundkey into them.
+///
+fn halves(block: &[u8; 16], roundkey: &[u64; 2]) -> [u64; 2] {
+    let low = u64::from_le_bytes(block[..8].try_into().unwrap());
+    let high = u64::from_le_bytes(block[8..].try_into().unwrap());
+    [low ^ roundkey[0], high ^ roundkey[1]]
+}
+
+///
+/// Writes the two halves back into the block.
+///
+fn store(block: &mut [u8; 16], low: u64, high: u64) {
+    block[..8].copy_from_slice(&low.to_le_bytes());
+    block[8..].copy_from_slice(&high.to_le_bytes());
+}
+
+///
+/// ShiftRows, SubBytes and MixColumns of a middle round, giving the half of the
+/// state from low and high.
+///
+#[cfg(feature = "encrypt")]
+fn aes64esm(low: u64, high: u64) -> u64 {
+    let half;
+    // The instruction only reads and writes registers.
+    unsafe { asm!("aes64esm {}, {}, {}", lateout(reg) half, in(reg) low, in(reg) high, options(pure, nomem, nostack)) };
+    half
+}
+
+///
+/// ShiftRows and SubBytes of the last round, giving the half of the state from
+/// low and high.
+///
+#[cfg(feature = "encrypt")]
+fn aes64es(low: u64, high: u64) -> u64 {
+    let half;
+    // The instruction only reads and writes registers.
+    unsafe { asm!("aes64es {}, {}, {}", lateout(reg) half, in(reg) low, in(reg) high, options(pure, nomem, nostack)) };
+    half
+}
+
+///
+/// InvShiftRows, InvSubBytes and InvMixColumns of a middle round, giving the half
+/// of the state from low and high.
+///
+#[cfg(feature = "decrypt")]
+fn aes64dsm(low: u64, high: u64) -> u64 {
+    let half;
+    // The instruction only reads and writes registers.
+    unsafe { asm!("aes64dsm {}, {}, {}", lateout(reg) half, in(reg) low, in(reg) high, options(pure, nomem, nostack)) };
+    half
+}
+
+///
+/// InvShiftRows and InvSubBytes of the last round, giving the half of the state
+/// from low and high.
+///
+#[cfg(feature = "decrypt")]
+fn aes64ds(low: u64, high: u64) -> u64 {
+    let half;
+    // The instruction only reads and writes registers.
+    unsafe { asm!("aes64ds {}, {}, {}", lateout(reg) half, in(reg) low, in(reg) high, options(pure, nomem, nostack)) };
+    half
+}
+
+///
+/// InvMixColumns of the two columns in the half.
+///
+#[cfg(feature = "decrypt")]
+fn aes64im(half: u64) -> u64 {
+    let mixed;
+    // The instruction only reads and writes registers.
+    unsafe { asm!("aes64im {}, {}", lateout(reg) mixed, in(reg) half, options(pure, nomem, nostack)) };
+    mixed
+}
+
+#[cfg(all(test, feature = "encrypt", feature = "decrypt"))]
+mod tests {
+
+    use super::*;
+    use crate::test_util::hex;
+    use crate::key_schedule::{ReverseRoundKeyGenerator, RoundKeyGenerator};
+    use crate::RoundKeys;
+
+    #[test]
+    fn test_fips197_vectors() {
+        let plaintext: [u8; 16] = hex("00112233445566778899aabbccddeeff").try_into().unwrap();
+        let vectors = [
+            ("000102030405060708090a0b0c0d0e0f", "69c4e0d86a7b0430d8cdb78070b4c55a"),
+            ("000102030405060708090a0b0c0d0e0f1011121314151617", "dda97ca4864cdfe06eaf70a0ec0d7191"),
+            ("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f", "8ea2b7ca516745bfeafc49904b496089")
+        ];
+        for (key, expected) in vectors {
+            let key = hex(key);
+            let roundkeys = RoundKeys::new(RoundKeyGenerator::new(&key).map(|roundkey| *roundkey).collect()).unwrap();
+            let mut blocks = [plaintext; 3];
+            encrypt_rounds(&mut blocks, roundkeys.rounds(), roundkeys.as_slice().iter());
+            assert_eq!([<[u8; 16]>::try_from(hex(expected)).unwrap(); 3], blocks);
+            let mut decrypted = blocks;
+            decrypt_equivalent_rounds(&mut decrypted, &InverseRoundKeys::new(&roundkeys));
+            assert_eq!([plaintext; 3], decrypted);
+            let mut decrypted = blocks;
+            decrypt_rounds(&mut decrypted, roundkeys.rounds(), ReverseRoundKeyGenerator::new(&ReverseRoundKeyGenerator::last_words(&key)));
+            assert_eq!([plaintext; 3], decrypted);
+        }
+    }
+
+}